@@ -127,6 +127,17 @@ enum class ParseFlowSetting {
   UNAMBIGUOUS,
 };
 
+/// The goal symbol of a parsed source.
+enum class SourceType : uint8_t {
+  /// A script, which cannot contain import and export declarations.
+  Script,
+  /// A module, which is always strict.
+  Module,
+  /// A script or a module, depending on whether it contains import or export
+  /// declarations.
+  Unambiguous,
+};
+
 /// An enum to track the "source visibility" of functions. This notion is coined
 /// to implement "directives" such as 'hide source' and 'sensitive' defined by
 /// https://github.com/tc39/proposal-function-implementation-hiding, as well as
@@ -194,6 +205,23 @@ class Context {
   /// Whether we are running in script mode. Default to strict.
   bool strictMode_{false};
 
+  /// Whether the Annex B web compatibility extensions are enabled.
+  bool annexB_{true};
+
+  /// Whether the Annex B HTML-like comments are recognized. They are only
+  /// recognized when parsing the Script goal, never in modules.
+  bool htmlLikeComments_{false};
+
+  /// Whether the parser reports every error recovery action as a remark.
+  bool recordRecovery_{false};
+
+  /// Whether the parser resumes at the next statement after a syntax error,
+  /// producing a partial AST.
+  bool errorRecovery_{false};
+
+  /// Maximum nesting depth of the parser, 0 for its built-in limit.
+  unsigned maxParserDepth_{0};
+
   /// Is 'eval()' is enabled.
   bool enableEval_{true};
 
@@ -228,6 +256,9 @@ class Context {
   /// Whether to parse Flow type syntax.
   ParseFlowSetting parseFlow_{ParseFlowSetting::NONE};
 
+  /// The goal symbol of the parsed sources.
+  SourceType sourceType_{SourceType::Unambiguous};
+
   /// Whether to parse TypeScript syntax.
   bool parseTS_{false};
 
@@ -354,6 +385,44 @@ class Context {
     return strictMode_;
   }
 
+  void setAnnexB(bool annexB) {
+    annexB_ = annexB;
+  }
+  bool isAnnexB() const {
+    return annexB_;
+  }
+
+  void setHTMLLikeComments(bool htmlLikeComments) {
+    htmlLikeComments_ = htmlLikeComments;
+  }
+  bool isHTMLLikeComments() const {
+    return htmlLikeComments_;
+  }
+
+  void setRecordRecovery(bool recordRecovery) {
+    recordRecovery_ = recordRecovery;
+  }
+  bool isRecordRecovery() const {
+    return recordRecovery_;
+  }
+
+  void setErrorRecovery(bool errorRecovery) {
+    errorRecovery_ = errorRecovery;
+  }
+  bool isErrorRecovery() const {
+    return errorRecovery_;
+  }
+
+  /// Set the maximum nesting depth of the parser, past which it reports an
+  /// error instead of recursing further. 0 means the built-in limit, which
+  /// also caps larger values, since it is sized for the native stack.
+  void setMaxParserDepth(unsigned maxParserDepth) {
+    maxParserDepth_ = maxParserDepth;
+  }
+  unsigned getMaxParserDepth() const {
+    return maxParserDepth_;
+  }
+
   bool getEnableEval() const {
     return enableEval_;
   }
@@ -407,6 +476,13 @@ class Context {
     return parseFlow_ == ParseFlowSetting::ALL;
   }
 
+  void setSourceType(SourceType sourceType) {
+    sourceType_ = sourceType;
+  }
+  SourceType getSourceType() const {
+    return sourceType_;
+  }
+
   void setParseFlowComponentSyntax(bool parseFlowComponentSyntax) {
     parseFlowComponentSyntax_ = parseFlowComponentSyntax;
   }
@@ -404,6 +404,18 @@ class JSLexer {
 
   bool strictMode_;
 
+  /// Whether the Annex B web compatibility extensions are enabled, allowing
+  /// legacy octal literals and escapes in non-strict code.
+  bool annexB_{true};
+
+  /// Whether the Annex B HTML-like comments `<!--` and `-->` are recognized.
+  /// They are only valid in the Script goal, so this must not be set when
+  /// lexing a module.
+  bool htmlLikeComments_{false};
+
+  /// Whether to report every character skipped in error recovery as a remark.
+  bool recordRecovery_{false};
+
   /// Whether to store the comments instead of skipping them.
   bool storeComments_{false};
 
@@ -520,6 +532,30 @@ class JSLexer {
     strictMode_ = strictMode;
   }
 
+  bool isAnnexB() const {
+    return annexB_;
+  }
+
+  void setAnnexB(bool annexB) {
+    annexB_ = annexB;
+  }
+
+  bool isHTMLLikeComments() const {
+    return htmlLikeComments_;
+  }
+
+  void setHTMLLikeComments(bool htmlLikeComments) {
+    htmlLikeComments_ = htmlLikeComments;
+  }
+
+  bool isRecordRecovery() const {
+    return recordRecovery_;
+  }
+
+  void setRecordRecovery(bool recordRecovery) {
+    recordRecovery_ = recordRecovery;
+  }
+
   void setStoreComments(bool storeComments) {
     storeComments_ = storeComments;
   }
@@ -1009,6 +1045,17 @@ class JSLexer {
     return error({startLoc, SMLoc::getFromPointer(curCharPtr_)}, msg);
   }
 
+  /// Report the characters from \p startLoc to the current character as
+  /// skipped in error recovery, if recovery recording is enabled.
+  void recordSkipped(SMLoc startLoc, const llvh::Twine &what) {
+    if (LLVM_UNLIKELY(recordRecovery_))
+      sm_.message(
+          SourceErrorManager::DK_Remark,
+          {startLoc, SMLoc::getFromPointer(curCharPtr_)},
+          "recovery: skipped " + what,
+          Subsystem::Lexer);
+  }
+
   /// Report an error using the current token's location.
   bool error(const llvh::Twine &msg) {
     return error(token_.getSourceRange(), msg);
@@ -69,6 +69,7 @@ class SourceErrorManager {
   enum DiagKind {
     DK_Error = llvh::SourceMgr::DK_Error,
     DK_Warning = llvh::SourceMgr::DK_Warning,
+    DK_Remark = llvh::SourceMgr::DK_Remark,
     DK_Note = llvh::SourceMgr::DK_Note,
   };
 
@@ -657,6 +657,18 @@ void SemanticValidator::visit(ClassPrivatePropertyNode *node) {
   visitESTreeChildren(*this, node);
 }
 
+void SemanticValidator::visit(ClassAccessorPropertyNode *node) {
+  if (compile_)
+    sm_.error(node->getSourceRange(), "accessor fields are not supported");
+  visitESTreeChildren(*this, node);
+}
+
+void SemanticValidator::visit(StaticBlockNode *node) {
+  if (compile_)
+    sm_.error(node->getSourceRange(), "class static blocks are not supported");
+  visitESTreeChildren(*this, node);
+}
+
 void SemanticValidator::visit(ImportDeclarationNode *importDecl) {
   // Like variable declarations, imported names must be hoisted.
   if (!astContext_.getTransformCJSModules()) {
@@ -207,6 +207,8 @@ class SemanticValidator {
   void visit(ClassDeclarationNode *node);
   void visit(PrivateNameNode *node);
   void visit(ClassPrivatePropertyNode *node);
+  void visit(ClassAccessorPropertyNode *node);
+  void visit(StaticBlockNode *node);
 
   void visit(ImportDeclarationNode *importDecl);
   void visit(ImportDefaultSpecifierNode *importDecl);
@@ -712,6 +712,39 @@ class GenJS {
         node->_value,
         node);
   }
+  void visit(ClassAccessorPropertyNode *node) {
+    if (node->_static) {
+      OS_ << "static ";
+    }
+    OS_ << "accessor ";
+    if (node->_computed) {
+      OS_ << '[';
+    }
+    visitESTreeNode(*this, node->_key, node);
+    if (node->_computed) {
+      OS_ << ']';
+    }
+    if (node->_value) {
+      OS_ << " = ";
+      visitESTreeNode(*this, node->_value, node);
+    }
+    OS_ << ';';
+  }
+  void visit(StaticBlockNode *node) {
+    if (node->_body.empty()) {
+      OS_ << "static {}";
+      return;
+    }
+    OS_ << "static {";
+    incIndent();
+    newline();
+
+    visitStmtList(node->_body, node);
+
+    decIndent();
+    newline();
+    OS_ << '}';
+  }
   void visitClassProperty(
       bool isStatic,
       bool isComputed,
@@ -231,11 +231,33 @@ const Token *JSLexer::advance(GrammarContext grammarContext) {
       PUNC_L3_3('!', TokenKind::exclaim, '=', TokenKind::exclaimequal, '=', TokenKind::exclaimequalequal);
 
       // + ++ +=
-      // - -- -=
       // & && &=
       // | || |=
       PUNC_L2_3('+', TokenKind::plus,  '+', TokenKind::plusplus,   '=', TokenKind::plusequal);
-      PUNC_L2_3('-', TokenKind::minus, '-', TokenKind::minusminus, '=', TokenKind::minusequal);
+      // clang-format on
+
+      // - -- -= and, in scripts, the HTML-like comment --> at the start of a
+      // line.
+      case '-':
+        if (LLVM_UNLIKELY(
+                htmlLikeComments_ && curCharPtr_[1] == '-' && curCharPtr_[2] == '>' &&
+                (newLineBeforeCurrentToken_ || curCharPtr_ == bufferStart_))) {
+          scanLineComment(curCharPtr_);
+          continue;
+        }
+        token_.setStart(curCharPtr_);
+        if (curCharPtr_[1] == '-') {
+          token_.setPunctuator(TokenKind::minusminus);
+          curCharPtr_ += 2;
+        } else if (curCharPtr_[1] == '=') {
+          token_.setPunctuator(TokenKind::minusequal);
+          curCharPtr_ += 2;
+        } else {
+          token_.setPunctuator(TokenKind::minus);
+          curCharPtr_ += 1;
+        }
+        break;
+      // clang-format off
 
       case '&':
         token_.setStart(curCharPtr_);
@@ -436,6 +458,14 @@ const Token *JSLexer::advance(GrammarContext grammarContext) {
 
       // <  <= << <<=
       case '<':
+        // In scripts, <!-- starts an HTML-like comment ending at the end of
+        // the line.
+        if (LLVM_UNLIKELY(
+                htmlLikeComments_ && curCharPtr_[1] == '!' && curCharPtr_[2] == '-' &&
+                curCharPtr_[3] == '-')) {
+          scanLineComment(curCharPtr_);
+          continue;
+        }
         token_.setStart(curCharPtr_);
         if (HERMES_PARSE_FLOW &&
             LLVM_UNLIKELY(grammarContext == JSLexer::GrammarContext::Type)) {
@@ -538,6 +568,7 @@ const Token *JSLexer::advance(GrammarContext grammarContext) {
         } else {
           curCharPtr_ += 1;
           errorRange(token_.getStartLoc(), "unrecognized character '@'");
+          recordSkipped(token_.getStartLoc(), "character '@'");
           continue;
         }
         break;
@@ -551,6 +582,7 @@ const Token *JSLexer::advance(GrammarContext grammarContext) {
               token_.getStartLoc(),
               "Unicode escape \\u" + Twine::utohexstr(cp) +
                   " is not a valid identifier start");
+          recordSkipped(token_.getStartLoc(), "Unicode escape");
           continue;
         } else {
           appendUnicodeToStorage(cp);
@@ -590,6 +622,7 @@ const Token *JSLexer::advance(GrammarContext grammarContext) {
             errorRange(
                 token_.getStartLoc(),
                 "unrecognized Unicode character \\u" + Twine::utohexstr(ch));
+          recordSkipped(token_.getStartLoc(), "character");
           continue;
         }
 
@@ -1116,10 +1149,11 @@ void JSLexer::consumeIdentifierParts() {
 unsigned char JSLexer::consumeOctal(unsigned maxLen) {
   assert(*curCharPtr_ >= '0' && *curCharPtr_ <= '7');
 
-  if (strictMode_) {
+  if (strictMode_ || !annexB_) {
     if (!error(
             SMLoc::getFromPointer(curCharPtr_ - 1),
-            "octals not allowed in strict mode")) {
+            strictMode_ ? "octals not allowed in strict mode"
+                        : "octals not allowed without Annex B")) {
       return 0;
     }
   }
@@ -1235,7 +1269,9 @@ llvh::Optional<uint32_t> JSLexer::consumeBracedCodePoint(bool errorOnFail) {
 llvh::StringRef JSLexer::lineCommentHelper(const char *start) {
   assert(
       (start[0] == '/' && start[1] == '/') ||
-      (start[0] == '#' && start[1] == '!'));
+      (start[0] == '#' && start[1] == '!') ||
+      (start[0] == '<' && start[1] == '!') ||
+      (start[0] == '-' && start[1] == '-'));
   const char *lineCommentEnd;
   const char *cur = start + 2;
 
@@ -1289,8 +1325,8 @@ void JSLexer::scanLineComment(const char *start) {
 
   if (storeComments_) {
     commentStorage_.emplace_back(
-        start[0] == '/' ? StoredComment::Kind::Line
-                        : StoredComment::Kind::Hashbang,
+        start[0] == '#' ? StoredComment::Kind::Hashbang
+                        : StoredComment::Kind::Line,
         SMRange{
             SMLoc::getFromPointer(comment.begin()),
             SMLoc::getFromPointer(comment.end())});
@@ -1536,7 +1572,7 @@ end:
     val = ival;
   } else if (real || radix == 10) {
     if (legacyOctal) {
-      if (strictMode_ || grammarContext == GrammarContext::Type) {
+      if (strictMode_ || !annexB_ || grammarContext == GrammarContext::Type) {
         if (!errorRange(
                 token_.getStartLoc(),
                 "Decimals with leading zeros are not allowed in strict mode")) {
@@ -1599,7 +1635,7 @@ end:
     }
   } else {
     if (legacyOctal &&
-        (strictMode_ || grammarContext == GrammarContext::Type) &&
+        (strictMode_ || !annexB_ || grammarContext == GrammarContext::Type) &&
         curCharPtr_ - start > 1) {
       if (!errorRange(
               token_.getStartLoc(),
@@ -32,6 +32,14 @@ JSParserImpl::JSParserImpl(
           &context.getStringTable(),
           context.isStrictMode()),
       pass_(FullParse) {
+  lexer_.setAnnexB(context.isAnnexB());
+  lexer_.setHTMLLikeComments(
+      context.isHTMLLikeComments() &&
+      context.getSourceType() == SourceType::Script);
+  lexer_.setRecordRecovery(context.isRecordRecovery());
+  if (context.getMaxParserDepth() != 0 &&
+      context.getMaxParserDepth() < MAX_RECURSION_DEPTH)
+    maxRecursionDepth_ = context.getMaxParserDepth();
   initializeIdentifiers();
 }
 
@@ -46,6 +54,14 @@ JSParserImpl::JSParserImpl(Context &context, uint32_t bufferId, ParserPass pass)
           context.isStrictMode()),
       pass_(pass) {
   preParsed_ = context.getPreParsedBufferInfo(bufferId);
+  lexer_.setAnnexB(context.isAnnexB());
+  lexer_.setHTMLLikeComments(
+      context.isHTMLLikeComments() &&
+      context.getSourceType() == SourceType::Script);
+  lexer_.setRecordRecovery(context.isRecordRecovery());
+  if (context.getMaxParserDepth() != 0 &&
+      context.getMaxParserDepth() < MAX_RECURSION_DEPTH)
+    maxRecursionDepth_ = context.getMaxParserDepth();
   initializeIdentifiers();
 }
 
@@ -81,6 +97,7 @@ void JSParserImpl::initializeIdentifiers() {
   asyncIdent_ = lexer_.getIdentifier("async");
   awaitIdent_ = lexer_.getIdentifier("await");
   assertIdent_ = lexer_.getIdentifier("assert");
+  accessorIdent_ = lexer_.getIdentifier("accessor");
 
 #if HERMES_PARSE_FLOW
 
@@ -156,7 +173,8 @@ Optional<ESTree::ProgramNode *> JSParserImpl::parse() {
   auto res = parseProgram();
   if (!res)
     return None;
-  if (lexer_.getSourceMgr().getErrorCount() != 0)
+  if (lexer_.getSourceMgr().getErrorCount() != 0 &&
+      !context_.isErrorRecovery())
     return None;
   return res.getValue();
 }
@@ -343,12 +361,33 @@ bool JSParserImpl::recursionDepthExceeded() {
 Optional<ESTree::ProgramNode *> JSParserImpl::parseProgram() {
   SMLoc startLoc = tok_->getStartLoc();
   SaveStrictModeAndSeenDirectives saveStrictModeAndSeenDirectives{this};
+  // Module : ModuleBody[+Await], so 'await' is an operator at the top level
+  // of a module. An unambiguous source is parsed like a script here.
+  llvh::SaveAndRestore<bool> saveParamAwait(
+      paramAwait_, context_.getSourceType() == SourceType::Module);
   ESTree::NodeList stmtList;
 
   if (!parseStatementList(
           Param{}, TokenKind::eof, true, AllowImportExport::Yes, stmtList))
     return None;
 
+  if (context_.getSourceType() == SourceType::Script) {
+    for (auto &stmt : stmtList) {
+      if (isa<ESTree::ImportDeclarationNode>(&stmt)) {
+        error(
+            stmt.getSourceRange(),
+            "import declaration is only allowed in a module");
+      } else if (
+          isa<ESTree::ExportNamedDeclarationNode>(&stmt) ||
+          isa<ESTree::ExportDefaultDeclarationNode>(&stmt) ||
+          isa<ESTree::ExportAllDeclarationNode>(&stmt)) {
+        error(
+            stmt.getSourceRange(),
+            "export declaration is only allowed in a module");
+      }
+    }
+  }
+
   SMLoc endLoc = startLoc;
   if (!stmtList.empty()) {
     endLoc = stmtList.back().getEndLoc();
@@ -910,14 +949,48 @@ Optional<bool> JSParserImpl::parseStatementList(
   }
 
   while (!check(TokenKind::eof) && !checkN(until, otherUntil...)) {
+    SMLoc stmtStartLoc = tok_->getStartLoc();
     if (!parseStatementListItem(param, allowImportExport, stmtList)) {
-      return None;
+      if (!context_.isErrorRecovery())
+        return None;
+      skipToNextStatement(stmtStartLoc, until, otherUntil...);
     }
   }
 
   return true;
 }
 
+template <typename... Tail>
+void JSParserImpl::skipToNextStatement(
+    SMLoc startLoc,
+    TokenKind until,
+    Tail... otherUntil) {
+  SMLoc endLoc = getPrevTokenEndLoc();
+  // Always make progress, even if the error was at the first token.
+  if (tok_->getStartLoc() == startLoc && !check(TokenKind::eof))
+    endLoc = advance().End;
+
+  unsigned depth = 0;
+  while (!check(TokenKind::eof)) {
+    if (depth == 0 && !sm_.isErrorLimitReached()) {
+      if (checkN(until, otherUntil...))
+        break;
+      if (lexer_.isNewLineBeforeCurrentToken() && checkStatementKeyword())
+        break;
+    }
+    bool isSemi = check(TokenKind::semi);
+    if (check(TokenKind::l_brace))
+      ++depth;
+    else if (check(TokenKind::r_brace) && depth != 0)
+      --depth;
+    endLoc = advance().End;
+    if (isSemi && depth == 0 && !sm_.isErrorLimitReached())
+      break;
+  }
+
+  recordRecovery({startLoc, endLoc}, "skipped statement");
+}
+
 Optional<ESTree::BlockStatementNode *> JSParserImpl::parseBlock(
     Param param,
     JSLexer::GrammarContext grammarContext,
@@ -2142,9 +2215,12 @@ Optional<ESTree::SwitchStatementNode *> JSParserImpl::parseSwitchStatement(
             TokenKind::r_brace))
       return None;
 
-    if (!ignoreClause) {
-      auto clauseEndLoc =
-          stmtList.empty() ? colonLoc : stmtList.back().getEndLoc();
+    auto clauseEndLoc =
+        stmtList.empty() ? colonLoc : stmtList.back().getEndLoc();
+    if (ignoreClause) {
+      recordRecovery(
+          {clauseStartLoc, clauseEndLoc}, "dropped 'default' clause");
+    } else {
       clauseList.push_back(*setLocation(
           clauseStartLoc,
           clauseEndLoc,
@@ -4686,6 +4762,17 @@ Optional<ESTree::ClassBodyNode *> JSParserImpl::parseClassBody(SMLoc startLoc) {
         break;
 
       case TokenKind::rw_static:
+        if (!isStatic && !readonly && !declare && !accessibility) {
+          auto optNext = lexer_.lookahead1(llvh::None);
+          if (optNext.hasValue() && *optNext == TokenKind::l_brace) {
+            // ClassStaticBlock
+            auto optBlock = parseClassStaticBlock();
+            if (!optBlock)
+              return None;
+            body.push_back(**optBlock);
+            break;
+          }
+        }
         if (context_.getParseTS() && (readonly || isStatic)) {
           // Don't advance() when `readonly` or `static` is already seen,
           // so the current one can be regarded as an identifier.
@@ -4718,13 +4805,19 @@ Optional<ESTree::ClassBodyNode *> JSParserImpl::parseClassBody(SMLoc startLoc) {
               constructor = method;
             }
           }
-        } else if (auto *prop = dyn_cast<ESTree::ClassPropertyNode>(*optElem)) {
-          if (auto *propId = dyn_cast<ESTree::IdentifierNode>(prop->_key)) {
+        } else if (
+            isa<ESTree::ClassPropertyNode>(*optElem) ||
+            isa<ESTree::ClassAccessorPropertyNode>(*optElem)) {
+          auto *key = isa<ESTree::ClassPropertyNode>(*optElem)
+              ? cast<ESTree::ClassPropertyNode>(*optElem)->_key
+              : cast<ESTree::ClassAccessorPropertyNode>(*optElem)->_key;
+          auto *prop = *optElem;
+          if (auto *propId = dyn_cast<ESTree::IdentifierNode>(key)) {
             if (propId->_name == constructorIdent_) {
               error(prop->getSourceRange(), "invalid class property name");
             }
           } else if (
-              auto *propStr = dyn_cast<ESTree::StringLiteralNode>(prop->_key)) {
+              auto *propStr = dyn_cast<ESTree::StringLiteralNode>(key)) {
             if (propStr->_value == constructorIdent_) {
               error(prop->getSourceRange(), "invalid class property name");
             }
@@ -4750,6 +4843,42 @@ Optional<ESTree::ClassBodyNode *> JSParserImpl::parseClassBody(SMLoc startLoc) {
       new (context_) ESTree::ClassBodyNode(std::move(body)));
 }
 
+Optional<ESTree::StaticBlockNode *> JSParserImpl::parseClassStaticBlock() {
+  assert(check(TokenKind::rw_static));
+  SMLoc startLoc = advance().Start;
+
+  // ClassStaticBlock : static { ClassStaticBlockBody }
+  //                           ^
+  if (!eat(
+          TokenKind::l_brace,
+          JSLexer::AllowRegExp,
+          "in static block",
+          "start of static block",
+          startLoc))
+    return None;
+
+  // ClassStaticBlockStatementList : StatementList[~Yield, +Await, ~Return]
+  // 'await' expressions are rejected by semantic validation.
+  llvh::SaveAndRestore<bool> saveParamYield(paramYield_, false);
+  llvh::SaveAndRestore<bool> saveParamAwait(paramAwait_, true);
+  ESTree::NodeList body;
+  if (!parseStatementList(
+          Param{}, TokenKind::r_brace, false, AllowImportExport::No, body))
+    return None;
+
+  SMLoc endLoc = tok_->getEndLoc();
+  if (!eat(
+          TokenKind::r_brace,
+          JSLexer::AllowRegExp,
+          "at end of static block",
+          "start of static block",
+          startLoc))
+    return None;
+
+  return setLocation(
+      startLoc, endLoc, new (context_) ESTree::StaticBlockNode(std::move(body)));
+}
+
 Optional<ESTree::Node *> JSParserImpl::parseClassElement(
     bool isStatic,
     SMRange startRange,
@@ -4761,6 +4890,8 @@ Optional<ESTree::Node *> JSParserImpl::parseClassElement(
 
   bool optional = false;
   bool isPrivate = false;
+  // Whether this is an auto-accessor field, declared with 'accessor'.
+  bool isAccessor = false;
 
   enum class SpecialKind {
     None,
@@ -4864,6 +4995,25 @@ Optional<ESTree::Node *> JSParserImpl::parseClassElement(
     }
   } else if (checkAndEat(TokenKind::star)) {
     special = SpecialKind::Generator;
+  } else if (check(accessorIdent_)) {
+    SMRange range = advance();
+    if (!checkN(
+            TokenKind::less,
+            TokenKind::l_paren,
+            TokenKind::r_brace,
+            TokenKind::equal,
+            TokenKind::colon,
+            TokenKind::semi) &&
+        !lexer_.isNewLineBeforeCurrentToken()) {
+      // accessor [no LineTerminator here] ClassElementName
+      isAccessor = true;
+    } else {
+      prop = setLocation(
+          range,
+          range,
+          new (context_) ESTree::IdentifierNode(accessorIdent_, nullptr, false));
+      doParsePropertyName = false;
+    }
   } else if (isStatic && staticIsPropertyName()) {
     // This is the name of the property/method.
     // We've already parsed 'static', but it must be used as the
@@ -4961,6 +5111,21 @@ Optional<ESTree::Node *> JSParserImpl::parseClassElement(
           startRange.Start);
       return None;
     }
+    if (isAccessor) {
+      if (declare || typeAnnotation || optional || variance || accessibility ||
+          readonly) {
+        error(startRange, "'accessor' field may not have modifiers or types");
+      }
+      if (isPrivate) {
+        prop = setLocation(
+            prop, prop, new (context_) ESTree::PrivateNameNode(prop));
+      }
+      return setLocation(
+          startRange,
+          getPrevTokenEndLoc(),
+          new (context_) ESTree::ClassAccessorPropertyNode(
+              prop, value, computed, isStatic));
+    }
     if (isPrivate) {
       if (accessibility) {
         error(
@@ -5011,6 +5176,9 @@ Optional<ESTree::Node *> JSParserImpl::parseClassElement(
   if (declare) {
     error(startRange, "Invalid 'declare' in class method");
   }
+  if (isAccessor) {
+    error(startRange, "'accessor' is only valid on fields");
+  }
 
   SMLoc funcExprStartLoc = tok_->getStartLoc();
 
@@ -6082,24 +6250,31 @@ Optional<ESTree::StringLiteralNode *> JSParserImpl::parseFromClause() {
   return source;
 }
 
-bool JSParserImpl::parseAssertClause(ESTree::NodeList &attributes) {
-  assert(check(assertIdent_));
+bool JSParserImpl::checkImportAttributes() {
+  // The legacy 'assert' keyword can't be preceded by a newline.
+  return check(TokenKind::rw_with) ||
+      (check(assertIdent_) && !lexer_.isNewLineBeforeCurrentToken());
+}
+
+bool JSParserImpl::parseImportAttributes(ESTree::NodeList &attributes) {
+  assert(checkImportAttributes());
   SMLoc start = advance().Start;
 
-  // assert { }
-  // assert { AssertEntries ,[opt] }
-  //        ^
+  // with { }
+  // with { WithEntries ,[opt] }
+  //      ^
 
   if (!eat(
           TokenKind::l_brace,
           JSLexer::GrammarContext::AllowRegExp,
-          "in import assertion",
-          "start of assertion",
+          "in import attributes",
+          "start of import attributes",
           start))
     return false;
 
   while (!check(TokenKind::r_brace)) {
-    // AssertionKey : StringLiteral
+    // AttributeKey : IdentifierName
+    // AttributeKey : StringLiteral
     // ^
     ESTree::Node *key = nullptr;
     if (check(TokenKind::string_literal)) {
@@ -6111,8 +6286,8 @@ bool JSParserImpl::parseAssertClause(ESTree::NodeList &attributes) {
     } else {
       if (!need(
               TokenKind::identifier,
-              "in import assertion",
-              "start of assertion",
+              "in import attributes",
+              "start of import attributes",
               start))
         return false;
 
@@ -6127,18 +6302,18 @@ bool JSParserImpl::parseAssertClause(ESTree::NodeList &attributes) {
     if (!eat(
             TokenKind::colon,
             JSLexer::GrammarContext::AllowRegExp,
-            "in import assertion",
-            "start of assertion",
+            "in import attributes",
+            "start of import attributes",
             start))
       return false;
 
-    // AssertionKey : StringLiteral
+    // AttributeKey : StringLiteral
     //                ^
 
     if (!need(
             TokenKind::string_literal,
-            "in import assertion",
-            "start of assertion",
+            "in import attributes",
+            "start of import attributes",
             start))
       return false;
 
@@ -6154,14 +6329,14 @@ bool JSParserImpl::parseAssertClause(ESTree::NodeList &attributes) {
       break;
   }
 
-  // assert { AssertEntries ,[opt] }
-  //                               ^
+  // with { WithEntries ,[opt] }
+  //                           ^
 
   if (!eat(
           TokenKind::r_brace,
           JSLexer::GrammarContext::AllowRegExp,
-          "in import assertion",
-          "start of assertion",
+          "in import attributes",
+          "start of import attributes",
           start))
     return false;
 
@@ -6186,8 +6361,8 @@ JSParserImpl::parseImportDeclaration() {
     advance();
 
     ESTree::NodeList attributes{};
-    if (check(assertIdent_) && !lexer_.isNewLineBeforeCurrentToken()) {
-      if (!parseAssertClause(attributes))
+    if (checkImportAttributes()) {
+      if (!parseImportAttributes(attributes))
         return None;
     }
 
@@ -6214,8 +6389,8 @@ JSParserImpl::parseImportDeclaration() {
   }
 
   ESTree::NodeList attributes{};
-  if (check(assertIdent_) && !lexer_.isNewLineBeforeCurrentToken()) {
-    if (!parseAssertClause(attributes))
+  if (checkImportAttributes()) {
+    if (!parseImportAttributes(attributes))
       return None;
   }
 
@@ -6371,6 +6546,9 @@ bool JSParserImpl::parseNamedImports(ESTree::NodeList &specifiers) {
           "Duplicate entry in import declaration list");
       sm_.note(
           insertRes.first->second->getSourceRange(), "first usage of name");
+      recordRecovery(
+          optSpecifier.getValue()->getSourceRange(),
+          "dropped import specifier");
     }
 
     if (!checkAndEat(TokenKind::comma)) {
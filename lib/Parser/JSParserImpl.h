@@ -211,6 +211,10 @@ class JSParserImpl {
 #endif
       ;
 
+  /// The maximum depth of parser recursion for this parser, at most
+  /// MAX_RECURSION_DEPTH (see Context::setMaxParserDepth).
+  unsigned maxRecursionDepth_{MAX_RECURSION_DEPTH};
+
   /// Set when the parser sees the 'use static builtin' directive in any scope.
   bool useStaticBuiltin_{false};
 
@@ -276,6 +280,7 @@ class JSParserImpl {
   UniqueString *asyncIdent_;
   UniqueString *awaitIdent_;
   UniqueString *assertIdent_;
+  UniqueString *accessorIdent_;
 
 #if HERMES_PARSE_FLOW
 
@@ -462,6 +467,17 @@ class JSParserImpl {
     error(tok_->getSourceRange(), msg);
   }
 
+  /// Report an error recovery action (e.g. a node dropped from the AST) as a
+  /// remark, if recovery recording is enabled.
+  void recordRecovery(SMRange range, const llvh::Twine &action) {
+    if (LLVM_UNLIKELY(context_.isRecordRecovery()))
+      sm_.message(
+          SourceErrorManager::DK_Remark,
+          range,
+          "recovery: " + action,
+          Subsystem::Parser);
+  }
+
   /// Emit an error at the specified source location and range. If the maximum
   /// number of errors has been reached, return false and move the scanning
   /// pointer to EOF.
@@ -628,7 +644,7 @@ class JSParserImpl {
   /// NOTE: This is intended to stay inline to avoid a function call unless the
   /// depth was actually exceeded.
   inline bool recursionDepthCheck() {
-    if (LLVM_LIKELY(recursionDepth_ < MAX_RECURSION_DEPTH)) {
+    if (LLVM_LIKELY(recursionDepth_ < maxRecursionDepth_)) {
       return false;
     }
     return recursionDepthExceeded();
@@ -694,6 +710,37 @@ class JSParserImpl {
       AllowImportExport allowImportExport,
       ESTree::NodeList &stmtList);
 
+  /// Recover from a syntax error in the statement starting at \p startLoc, by
+  /// skipping tokens up to the start of the next statement in the enclosing
+  /// statement list: after a ';', before a keyword starting a statement on a
+  /// new line, or before one of the tokens ending the list. Braces are skipped
+  /// in pairs. If the error limit has been reached, skip to EOF instead.
+  template <typename... Tail>
+  void skipToNextStatement(SMLoc startLoc, TokenKind until, Tail... otherUntil);
+
+  /// \return true if the current token is a keyword which can only start a
+  /// statement.
+  bool checkStatementKeyword() const {
+    return checkN(
+        TokenKind::rw_var,
+        TokenKind::rw_const,
+        TokenKind::rw_function,
+        TokenKind::rw_class,
+        TokenKind::rw_if,
+        TokenKind::rw_for,
+        TokenKind::rw_while,
+        TokenKind::rw_do,
+        TokenKind::rw_return,
+        TokenKind::rw_switch,
+        TokenKind::rw_try,
+        TokenKind::rw_throw,
+        TokenKind::rw_break,
+        TokenKind::rw_continue,
+        TokenKind::rw_import,
+        TokenKind::rw_export,
+        letIdent_);
+  }
+
   /// Parse a statement block.
   /// \param param [Yield, Return]
   /// \param grammarContext context to be used when consuming the closing brace.
@@ -943,6 +990,9 @@ class JSParserImpl {
 
   Optional<ESTree::ClassBodyNode *> parseClassBody(SMLoc startLoc);
 
+  /// Parse a class static initialization block, starting at 'static'.
+  Optional<ESTree::StaticBlockNode *> parseClassStaticBlock();
+
   Optional<ESTree::Node *> parseClassElement(
       bool isStatic,
       SMRange startRange,
@@ -1030,7 +1080,13 @@ class JSParserImpl {
   /// Parse a FromClause and return the string literal representing the source.
   Optional<ESTree::StringLiteralNode *> parseFromClause();
 
-  bool parseAssertClause(ESTree::NodeList &attributes);
+  /// \return true if the current token starts import attributes, either
+  /// 'with' or the legacy 'assert'.
+  bool checkImportAttributes();
+
+  /// Parse the import attributes following the source of an import.
+  /// \return false on error.
+  bool parseImportAttributes(ESTree::NodeList &attributes);
 
   Optional<ESTree::ImportDeclarationNode *> parseImportDeclaration();
 
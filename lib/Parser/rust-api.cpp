@@ -431,6 +431,24 @@ struct ParserFlags {
   ParserDialect dialect = ParserDialect::JavaScript;
   /// Store doc-comment block at the top of the file.
   bool storeDocBlock = false;
+  /// Enable the Annex B web compatibility extensions: legacy octal literals
+  /// and escapes in non-strict code.
+  bool annexB = true;
+  /// Recognize the Annex B HTML-like comments `<!--` and `-->`. Only applies
+  /// when the source type is Script.
+  bool htmlLikeComments = false;
+  /// Report every error recovery action of the parser as a remark.
+  bool recordRecovery = false;
+  /// Resume parsing at the next statement after a syntax error, keeping the
+  /// partial AST.
+  bool errorRecovery = false;
+  /// Stop after this many errors. 0 means no limit.
+  unsigned errorLimit = 0;
+  /// Report an error past this nesting depth. 0 means the built-in limit,
+  /// which also caps larger values.
+  unsigned maxDepth = 0;
+  /// Whether the source is a script or a module.
+  SourceType sourceType = SourceType::Unambiguous;
 };
 
 enum class DiagKind : uint32_t {
@@ -492,6 +510,9 @@ struct DiagMessage {
   SMLoc loc{};
   /// Source coordinate.
   Coord coord{};
+  /// End of the first highlighted range, clipped to the line of the location,
+  /// or the location itself if there is no range.
+  Coord endCoord{};
   /// What kind of message.
   DiagKind diagKind = DiagKind::Error;
   /// Error message.
@@ -504,6 +525,10 @@ struct DiagMessage {
   DiagMessage(const llvh::SMDiagnostic &diag)
       : loc(diag.getLoc()),
         coord(diag.getLineNo(), diag.getColumnNo()),
+        endCoord(
+            diag.getLineNo(),
+            diag.getRanges().empty() ? diag.getColumnNo()
+                                     : diag.getRanges().front().second),
         diagKind(toDiagKind(diag.getKind())),
         message(diag.getMessage()),
         lineContents(diag.getLineContents()) {}
@@ -578,7 +603,15 @@ extern "C" ParserContext *
 hermes_parser_parse(ParserFlags flags, const char *source, size_t len) {
   std::unique_ptr<ParserContext> parserCtx(new ParserContext());
 
-  parserCtx->context_.setStrictMode(flags.strictMode);
+  parserCtx->context_.setStrictMode(
+      flags.strictMode || flags.sourceType == SourceType::Module);
+  parserCtx->context_.setSourceType(flags.sourceType);
+  parserCtx->context_.setAnnexB(flags.annexB);
+  parserCtx->context_.setHTMLLikeComments(flags.htmlLikeComments);
+  parserCtx->context_.setRecordRecovery(flags.recordRecovery);
+  parserCtx->context_.setErrorRecovery(flags.errorRecovery);
+  parserCtx->context_.getSourceErrorManager().setErrorLimit(flags.errorLimit);
+  parserCtx->context_.setMaxParserDepth(flags.maxDepth);
   parserCtx->context_.setParseJSX(flags.enableJSX);
 
   if (len == 0 || source[len - 1] != 0) {
@@ -631,6 +664,9 @@ hermes_parser_parse(ParserFlags flags, const char *source, size_t len) {
     } else {
       parserCtx->ast_ = *ast;
     }
+  } else if (ast && flags.errorRecovery) {
+    // Keep the partial AST.
+    parserCtx->ast_ = *ast;
   }
   return parserCtx.release();
 }
@@ -730,3 +766,303 @@ extern "C" DataRef hermes_get_node_name(ESTree::Node *n) {
 extern "C" DataRef hermes_parser_get_doc_block(ParserContext *parserCtx) {
   return toDataRef(parserCtx->docBlock_);
 }
+
+namespace {
+
+/// The category of a token or comment returned to Rust.
+enum class TokenCategory : uint32_t {
+  Eof,
+  Identifier,
+  PrivateIdentifier,
+  Keyword,
+  Punctuator,
+  Numeric,
+  BigInt,
+  String,
+  RegExp,
+  NoSubstitutionTemplate,
+  TemplateHead,
+  TemplateMiddle,
+  TemplateTail,
+  LineComment,
+  BlockComment,
+  Hashbang,
+};
+
+/// A temporary struct describing a token or a comment, returned to Rust.
+struct LexedToken {
+  TokenCategory category = TokenCategory::Eof;
+  /// Source range. The end is exclusive.
+  SMRange range{};
+  /// Value of a numeric literal.
+  double numeric = 0;
+  /// Whether \c value is set. It isn't for punctuators, comments and template
+  /// literals containing an invalid escape.
+  bool hasValue = false;
+  /// Name of an identifier or keyword, cooked value of a string or template
+  /// literal, digits of a bigint or body of a regexp.
+  llvh::StringRef value{};
+  /// Flags of a regexp.
+  llvh::StringRef flags{};
+};
+
+/// This object contains the entire lexer state.
+struct LexerContext {
+  /// Provides the context of the lexer and collects its messages.
+  ParserContext parserCtx_{};
+  std::unique_ptr<parser::JSLexer> lexer_{};
+  /// Number of comments stored by the lexer which have been returned.
+  size_t numComments_ = 0;
+  /// Whether the current token of the lexer has been returned.
+  bool tokenReturned_ = true;
+  /// Whether the eof token has been returned.
+  bool eofReturned_ = false;
+  /// Whether a "/" after the current token starts a regexp, rather than being
+  /// a division. The lexer needs the parser to know that, so this is a guess
+  /// based on the current token.
+  bool allowRegExp_ = true;
+  /// For each open brace, 1 if it is the end of a template head or middle,
+  /// so the matching "}" must be rescanned as the rest of the template.
+  std::vector<uint8_t> braces_{};
+  /// The values of allowRegExp_ and braces_ before the current token, from
+  /// which lexing can resume at the end of the previous token.
+  bool allowRegExpBefore_ = true;
+  std::vector<uint8_t> bracesBefore_{};
+
+  /// Scan the next token, taking the braces of template literals into account.
+  void advance() {
+    auto &lexer = *lexer_;
+    allowRegExpBefore_ = allowRegExp_;
+    bracesBefore_ = braces_;
+    const parser::Token *tok = lexer.advance(
+        allowRegExp_ ? parser::JSLexer::AllowRegExp
+                     : parser::JSLexer::AllowDiv);
+    if (tok->getKind() == parser::TokenKind::l_brace) {
+      braces_.push_back(0);
+    } else if (
+        tok->getKind() == parser::TokenKind::r_brace && !braces_.empty()) {
+      bool inTemplate = braces_.back();
+      braces_.pop_back();
+      if (inTemplate)
+        tok = lexer.rescanRBraceInTemplateLiteral();
+    }
+    if (tok->getKind() == parser::TokenKind::template_head ||
+        tok->getKind() == parser::TokenKind::template_middle) {
+      braces_.push_back(1);
+    }
+    allowRegExp_ = !endsExpression(tok->getKind());
+    tokenReturned_ = false;
+  }
+
+  /// \return whether a token of kind \p kind can end an expression, in which
+  /// case a "/" after it is a division.
+  static bool endsExpression(parser::TokenKind kind) {
+    switch (kind) {
+      case parser::TokenKind::identifier:
+      case parser::TokenKind::private_identifier:
+      case parser::TokenKind::numeric_literal:
+      case parser::TokenKind::bigint_literal:
+      case parser::TokenKind::string_literal:
+      case parser::TokenKind::regexp_literal:
+      case parser::TokenKind::no_substitution_template:
+      case parser::TokenKind::template_tail:
+      case parser::TokenKind::rw_this:
+      case parser::TokenKind::rw_super:
+      case parser::TokenKind::rw_true:
+      case parser::TokenKind::rw_false:
+      case parser::TokenKind::rw_null:
+      case parser::TokenKind::r_paren:
+      case parser::TokenKind::r_square:
+      case parser::TokenKind::plusplus:
+      case parser::TokenKind::minusminus:
+        return true;
+      // A "}" more often ends a statement than an object literal.
+      default:
+        return false;
+    }
+  }
+};
+
+void setComment(const parser::StoredComment &comment, LexedToken *res) {
+  switch (comment.getKind()) {
+    case parser::StoredComment::Kind::Line:
+      res->category = TokenCategory::LineComment;
+      break;
+    case parser::StoredComment::Kind::Block:
+      res->category = TokenCategory::BlockComment;
+      break;
+    case parser::StoredComment::Kind::Hashbang:
+      res->category = TokenCategory::Hashbang;
+      break;
+  }
+  res->range = comment.getSourceRange();
+}
+
+void setToken(const parser::Token *tok, LexedToken *res) {
+  res->range = tok->getSourceRange();
+  res->hasValue = true;
+  switch (tok->getKind()) {
+    case parser::TokenKind::eof:
+      res->category = TokenCategory::Eof;
+      res->hasValue = false;
+      break;
+    case parser::TokenKind::identifier:
+      res->category = TokenCategory::Identifier;
+      res->value = tok->getIdentifier()->str();
+      break;
+    case parser::TokenKind::private_identifier:
+      res->category = TokenCategory::PrivateIdentifier;
+      res->value = tok->getPrivateIdentifier()->str();
+      break;
+    case parser::TokenKind::numeric_literal:
+      res->category = TokenCategory::Numeric;
+      res->numeric = tok->getNumericLiteral();
+      res->hasValue = false;
+      break;
+    case parser::TokenKind::bigint_literal:
+      res->category = TokenCategory::BigInt;
+      res->value = tok->getBigIntLiteral()->str();
+      break;
+    case parser::TokenKind::string_literal:
+      res->category = TokenCategory::String;
+      res->value = tok->getStringLiteral()->str();
+      break;
+    case parser::TokenKind::regexp_literal:
+      res->category = TokenCategory::RegExp;
+      res->value = tok->getRegExpLiteral()->getBody()->str();
+      res->flags = tok->getRegExpLiteral()->getFlags()->str();
+      break;
+    case parser::TokenKind::no_substitution_template:
+    case parser::TokenKind::template_head:
+    case parser::TokenKind::template_middle:
+    case parser::TokenKind::template_tail:
+      switch (tok->getKind()) {
+        case parser::TokenKind::no_substitution_template:
+          res->category = TokenCategory::NoSubstitutionTemplate;
+          break;
+        case parser::TokenKind::template_head:
+          res->category = TokenCategory::TemplateHead;
+          break;
+        case parser::TokenKind::template_middle:
+          res->category = TokenCategory::TemplateMiddle;
+          break;
+        default:
+          res->category = TokenCategory::TemplateTail;
+          break;
+      }
+      if (tok->getTemplateLiteralContainsNotEscapes())
+        res->hasValue = false;
+      else
+        res->value = tok->getTemplateValue()->str();
+      break;
+    default:
+      if (tok->isResWord()) {
+        res->category = TokenCategory::Keyword;
+        res->value = tok->getResWordIdentifier()->str();
+      } else {
+        res->category = TokenCategory::Punctuator;
+        res->hasValue = false;
+      }
+      break;
+  }
+}
+
+} // namespace
+
+/// Create a lexer for the zero terminated input. source[len-1] must be \0.
+/// Only the strictMode, annexB, htmlLikeComments and sourceType flags are
+/// used.
+extern "C" LexerContext *
+hermes_lexer_new(ParserFlags flags, const char *source, size_t len) {
+  std::unique_ptr<LexerContext> lexerCtx(new LexerContext());
+  auto &parserCtx = lexerCtx->parserCtx_;
+
+  parserCtx.context_.setStrictMode(flags.strictMode);
+  parserCtx.context_.setAnnexB(flags.annexB);
+
+  if (len == 0 || source[len - 1] != 0) {
+    parserCtx.addError("Input is not zero terminated");
+    return lexerCtx.release();
+  }
+
+  parserCtx.setInputBuffer(llvh::StringRef(source, len));
+  lexerCtx->lexer_ = std::make_unique<parser::JSLexer>(
+      parserCtx.getBufferId(),
+      parserCtx.context_.getSourceErrorManager(),
+      parserCtx.context_.getAllocator(),
+      &parserCtx.context_.getStringTable(),
+      flags.strictMode);
+  lexerCtx->lexer_->setAnnexB(flags.annexB);
+  lexerCtx->lexer_->setHTMLLikeComments(
+      flags.htmlLikeComments && flags.sourceType == SourceType::Script);
+  lexerCtx->lexer_->setStoreComments(true);
+  return lexerCtx.release();
+}
+
+extern "C" void hermes_lexer_free(LexerContext *lexerCtx) {
+  delete lexerCtx;
+}
+
+/// Store the next token or comment in \p res, in source order.
+/// \return false after the eof token has been returned.
+extern "C" bool hermes_lexer_next(LexerContext *lexerCtx, LexedToken *res) {
+  if (!lexerCtx->lexer_ || lexerCtx->eofReturned_)
+    return false;
+  auto &lexer = *lexerCtx->lexer_;
+  if (lexerCtx->tokenReturned_)
+    lexerCtx->advance();
+
+  *res = LexedToken{};
+  // The comments before the current token were stored when it was scanned.
+  auto comments = lexer.getStoredComments();
+  if (lexerCtx->numComments_ < comments.size()) {
+    setComment(comments[lexerCtx->numComments_++], res);
+    return true;
+  }
+  setToken(lexer.getCurToken(), res);
+  lexerCtx->tokenReturned_ = true;
+  lexerCtx->eofReturned_ = res->category == TokenCategory::Eof;
+  return true;
+}
+
+/// \return whether a "/" starts a regexp in the state before the last token or
+/// comment returned by hermes_lexer_next().
+extern "C" bool hermes_lexer_get_allow_regexp(const LexerContext *lexerCtx) {
+  return lexerCtx->allowRegExpBefore_;
+}
+
+/// \return the open braces in the state before the last token or comment
+/// returned by hermes_lexer_next(): for each of them, 1 if it is in a template
+/// literal, 0 otherwise.
+extern "C" DataRef hermes_lexer_get_braces(const LexerContext *lexerCtx) {
+  return toDataRef(lexerCtx->bracesBefore_);
+}
+
+/// Resume lexing at byte \p offset of the source, which must be the end of a
+/// token or comment, in the state saved before the token or comment following
+/// it by hermes_lexer_get_allow_regexp() and hermes_lexer_get_braces().
+extern "C" void hermes_lexer_seek(
+    LexerContext *lexerCtx,
+    size_t offset,
+    bool allowRegExp,
+    const uint8_t *braces,
+    size_t numBraces) {
+  if (!lexerCtx->lexer_)
+    return;
+  auto &lexer = *lexerCtx->lexer_;
+  lexer.seek(SMLoc::getFromPointer(lexer.getBufferStart() + offset));
+  lexerCtx->numComments_ = lexer.getStoredComments().size();
+  lexerCtx->tokenReturned_ = true;
+  lexerCtx->eofReturned_ = false;
+  lexerCtx->allowRegExp_ = allowRegExp;
+  lexerCtx->braces_.assign(braces, braces + numBraces);
+}
+
+/// \return the index of the first error or -1 if no errors.
+extern "C" ssize_t hermes_lexer_get_first_error(const LexerContext *lexerCtx) {
+  return hermes_parser_get_first_error(&lexerCtx->parserCtx_);
+}
+
+extern "C" DataRef hermes_lexer_get_messages(const LexerContext *lexerCtx) {
+  return hermes_parser_get_messages(&lexerCtx->parserCtx_);
+}
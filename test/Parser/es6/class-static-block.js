@@ -0,0 +1,62 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %hermesc -dump-ast -pretty-json %s | %FileCheck --match-full-lines %s
+
+// CHECK-LABEL: {
+// CHECK-NEXT:   "type": "Program",
+// CHECK-NEXT:   "body": [
+
+class A {
+  static {
+    x;
+  }
+  static accessor y = 1;
+}
+// CHECK-NEXT:     {
+// CHECK-NEXT:       "type": "ClassDeclaration",
+// CHECK-NEXT:       "id": {
+// CHECK-NEXT:         "type": "Identifier",
+// CHECK-NEXT:         "name": "A"
+// CHECK-NEXT:       },
+// CHECK-NEXT:       "superClass": null,
+// CHECK-NEXT:       "body": {
+// CHECK-NEXT:         "type": "ClassBody",
+// CHECK-NEXT:         "body": [
+// CHECK-NEXT:           {
+// CHECK-NEXT:             "type": "StaticBlock",
+// CHECK-NEXT:             "body": [
+// CHECK-NEXT:               {
+// CHECK-NEXT:                 "type": "ExpressionStatement",
+// CHECK-NEXT:                 "expression": {
+// CHECK-NEXT:                   "type": "Identifier",
+// CHECK-NEXT:                   "name": "x"
+// CHECK-NEXT:                 },
+// CHECK-NEXT:                 "directive": null
+// CHECK-NEXT:               }
+// CHECK-NEXT:             ]
+// CHECK-NEXT:           },
+// CHECK-NEXT:           {
+// CHECK-NEXT:             "type": "ClassAccessorProperty",
+// CHECK-NEXT:             "key": {
+// CHECK-NEXT:               "type": "Identifier",
+// CHECK-NEXT:               "name": "y"
+// CHECK-NEXT:             },
+// CHECK-NEXT:             "value": {
+// CHECK-NEXT:               "type": "NumericLiteral",
+// CHECK-NEXT:               "value": 1,
+// CHECK-NEXT:               "raw": "1"
+// CHECK-NEXT:             },
+// CHECK-NEXT:             "computed": false,
+// CHECK-NEXT:             "static": true
+// CHECK-NEXT:           }
+// CHECK-NEXT:         ]
+// CHECK-NEXT:       }
+// CHECK-NEXT:     }
+
+// CHECK-NEXT:   ]
+// CHECK-NEXT: }
@@ -0,0 +1,72 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %hermesc -dump-ast -pretty-json %s | %FileCheck --match-full-lines %s
+
+// CHECK-LABEL: {
+// CHECK-NEXT:   "type": "Program",
+// CHECK-NEXT:   "body": [
+
+import 'foo.json' with {type: 'json'};
+// CHECK-NEXT:     {
+// CHECK-NEXT:       "type": "ImportDeclaration",
+// CHECK-NEXT:       "specifiers": [],
+// CHECK-NEXT:       "source": {
+// CHECK-NEXT:         "type": "StringLiteral",
+// CHECK-NEXT:         "value": "foo.json"
+// CHECK-NEXT:       },
+// CHECK-NEXT:       "assertions": [
+// CHECK-NEXT:         {
+// CHECK-NEXT:           "type": "ImportAttribute",
+// CHECK-NEXT:           "key": {
+// CHECK-NEXT:             "type": "Identifier",
+// CHECK-NEXT:             "name": "type"
+// CHECK-NEXT:           },
+// CHECK-NEXT:           "value": {
+// CHECK-NEXT:             "type": "StringLiteral",
+// CHECK-NEXT:             "value": "json"
+// CHECK-NEXT:           }
+// CHECK-NEXT:         }
+// CHECK-NEXT:       ],
+// CHECK-NEXT:       "importKind": "value"
+// CHECK-NEXT:     },
+
+import styles from 'foo.css'
+with {'type': 'css'};
+// CHECK-NEXT:     {
+// CHECK-NEXT:       "type": "ImportDeclaration",
+// CHECK-NEXT:       "specifiers": [
+// CHECK-NEXT:         {
+// CHECK-NEXT:           "type": "ImportDefaultSpecifier",
+// CHECK-NEXT:           "local": {
+// CHECK-NEXT:             "type": "Identifier",
+// CHECK-NEXT:             "name": "styles"
+// CHECK-NEXT:           }
+// CHECK-NEXT:         }
+// CHECK-NEXT:       ],
+// CHECK-NEXT:       "source": {
+// CHECK-NEXT:         "type": "StringLiteral",
+// CHECK-NEXT:         "value": "foo.css"
+// CHECK-NEXT:       },
+// CHECK-NEXT:       "assertions": [
+// CHECK-NEXT:         {
+// CHECK-NEXT:           "type": "ImportAttribute",
+// CHECK-NEXT:           "key": {
+// CHECK-NEXT:             "type": "StringLiteral",
+// CHECK-NEXT:             "value": "type"
+// CHECK-NEXT:           },
+// CHECK-NEXT:           "value": {
+// CHECK-NEXT:             "type": "StringLiteral",
+// CHECK-NEXT:             "value": "css"
+// CHECK-NEXT:           }
+// CHECK-NEXT:         }
+// CHECK-NEXT:       ],
+// CHECK-NEXT:       "importKind": "value"
+// CHECK-NEXT:     }
+
+// CHECK-NEXT:   ]
+// CHECK-NEXT: }
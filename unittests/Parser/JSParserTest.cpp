@@ -298,4 +298,21 @@ TEST(JSParserTest, TestStoredTokens) {
 #endif
 }
 
+TEST(JSParserTest, MaxParserDepth) {
+  Context context;
+  DiagContext diag(context);
+  std::string nested = std::string(20, '[') + std::string(20, ']');
+  {
+    JSParser parser(context, nested);
+    ASSERT_TRUE(parser.parse().hasValue());
+  }
+
+  context.setMaxParserDepth(10);
+  {
+    JSParser parser(context, nested);
+    ASSERT_FALSE(parser.parse().hasValue());
+    ASSERT_EQ(1, diag.getErrCountClear());
+  }
+}
+
 }; // anonymous namespace
@@ -24,7 +24,6 @@ use command_line::CommandLine;
 use command_line::Opt;
 use command_line::OptDesc;
 use juno::ast;
-use juno::ast::node_cast;
 use juno::ast::NodeRc;
 use juno::hparser;
 use juno::hparser::ParserDialect;
@@ -91,8 +90,8 @@ impl<'gc> ast::Visitor<'gc> for FindEscapes {
         use ast::*;
 
         match node {
-            Node::FunctionExpression(FunctionExpression { body, .. })
-            | Node::FunctionDeclaration(FunctionDeclaration { body, .. }) => {
+            Node::FunctionExpression(_) | Node::FunctionDeclaration(_) => {
+                let body = node.function_like_body();
                 let scope_id = self.sem.node_scope(NodeRc::from_node(lock, body)).unwrap();
                 self.cur_fun = self.sem.scope(scope_id).parent_function;
                 node.visit_children(lock, self);
@@ -719,7 +718,8 @@ impl Compiler<'_> {
     ) -> ValueId {
         use ast::*;
         match node {
-            Node::FunctionExpression(FunctionExpression { params, body, .. }) => {
+            Node::FunctionExpression(func) => {
+                let FunctionExpression { params, body, .. } = &**func;
                 self.gen_function_exp(params, body, scope, lock)
             }
             Node::ObjectExpression(ObjectExpression { properties, .. }) => {
@@ -1165,12 +1165,13 @@ impl Compiler<'_> {
                     self.gen_store(lref, init, scope);
                 }
             }
-            Node::FunctionDeclaration(FunctionDeclaration {
-                id: ident_opt,
-                params,
-                body,
-                ..
-            }) => {
+            Node::FunctionDeclaration(func) => {
+                let FunctionDeclaration {
+                    id: ident_opt,
+                    params,
+                    body,
+                    ..
+                } = &**func;
                 // Evaluate the function as a value.
                 let fn_id = self.gen_function_exp(params, body, scope, lock);
                 if let Some(ident) = ident_opt {
@@ -1196,13 +1197,14 @@ impl Compiler<'_> {
             Node::WhileStatement(WhileStatement { test, body, .. }) => {
                 self.gen_loop(Some(test), None, body, scope, lock);
             }
-            Node::ForStatement(ForStatement {
-                init,
-                test,
-                update,
-                body,
-                ..
-            }) => {
+            Node::ForStatement(for_stmt) => {
+                let ForStatement {
+                    init,
+                    test,
+                    update,
+                    body,
+                    ..
+                } = &**for_stmt;
                 out!(self, "{{");
                 let inner_scope = self.init_scope(node, scope, lock);
                 if let Some(init) = init {
@@ -1301,6 +1303,13 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
             enable_jsx: false,
             dialect: ParserDialect::JavaScript,
             store_doc_block: false,
+            annex_b: ctx.annex_b(),
+            html_like_comments: false,
+            record_recovery: false,
+            error_recovery: false,
+            error_limit: 0,
+            max_depth: 0,
+            source_type: hparser::SourceType::Module,
         },
         &buf,
     );
@@ -1313,17 +1322,7 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
     let (ast, sem) = {
         let resolver = resolve_dependency::DefaultResolver::new(ctx.sm());
         let lock = ast::GCLock::new(&mut ctx);
-        let program = &node_cast!(ast::Node::Program, parsed.to_ast(&lock, file_id).unwrap());
-        let module = ast::builder::Module::build_template(
-            &lock,
-            ast::template::Module {
-                metadata: ast::TemplateMetadata {
-                    phantom: Default::default(),
-                    range: program.metadata.range,
-                },
-                body: program.body,
-            },
-        );
+        let module = parsed.to_ast(&lock, file_id).unwrap();
         (
             NodeRc::from_node(&lock, module),
             sema::resolve_module(&lock, module, SourceId(0), &resolver),
@@ -93,6 +93,8 @@ pub enum NodeKind {
     ClassProperty,
     ClassPrivateProperty,
     MethodDefinition,
+    ClassAccessorProperty,
+    StaticBlock,
     ImportDeclaration,
     ImportSpecifier,
     ImportDefaultSpecifier,
@@ -500,6 +502,13 @@ extern "C" {
     pub fn hermes_get_MethodDefinition_kind(node: NodePtr) -> NodeLabel;
     pub fn hermes_get_MethodDefinition_computed(node: NodePtr) -> bool;
     pub fn hermes_get_MethodDefinition_static(node: NodePtr) -> bool;
+    // ClassAccessorProperty
+    pub fn hermes_get_ClassAccessorProperty_key(node: NodePtr) -> NodePtr;
+    pub fn hermes_get_ClassAccessorProperty_value(node: NodePtr) -> NodePtrOpt;
+    pub fn hermes_get_ClassAccessorProperty_computed(node: NodePtr) -> bool;
+    pub fn hermes_get_ClassAccessorProperty_static(node: NodePtr) -> bool;
+    // StaticBlock
+    pub fn hermes_get_StaticBlock_body(node: NodePtr) -> NodeListRef;
     // ImportDeclaration
     pub fn hermes_get_ImportDeclaration_specifiers(node: NodePtr) -> NodeListRef;
     pub fn hermes_get_ImportDeclaration_source(node: NodePtr) -> NodePtr;
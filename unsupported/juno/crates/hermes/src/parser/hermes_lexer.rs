@@ -0,0 +1,189 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::mem::MaybeUninit;
+use std::os::raw::c_char;
+
+use juno_support::NullTerminatedBuf;
+
+use super::hermes_parser::DataRef;
+use super::hermes_parser::DiagMessage;
+use super::hermes_parser::ParserFlags;
+use super::node::SMLoc;
+use super::node::SMRange;
+use super::node::StringRef;
+
+/// The category of a token or comment.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenCategory {
+    Eof,
+    Identifier,
+    PrivateIdentifier,
+    Keyword,
+    Punctuator,
+    Numeric,
+    BigInt,
+    String,
+    RegExp,
+    NoSubstitutionTemplate,
+    TemplateHead,
+    TemplateMiddle,
+    TemplateTail,
+    LineComment,
+    BlockComment,
+    Hashbang,
+}
+
+/// A token or a comment produced by the lexer.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LexedToken {
+    pub category: TokenCategory,
+    /// Source range. The end is exclusive.
+    pub range: SMRange,
+    /// Value of a numeric literal.
+    pub numeric: f64,
+    /// Whether `value` is set. It isn't for punctuators, comments and template literals
+    /// containing an invalid escape.
+    pub has_value: bool,
+    /// Name of an identifier or keyword, cooked value of a string or template literal,
+    /// digits of a bigint or body of a regexp, encoded in UTF-8 with surrogates.
+    pub value: StringRef,
+    /// Flags of a regexp.
+    pub flags: StringRef,
+}
+
+/// The state of the lexer between two tokens, from which lexing can resume.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LexerState {
+    /// Whether a `/` starts a regexp rather than a division.
+    pub allow_regexp: bool,
+    /// For each open brace, whether it is in a template literal, i.e. whether it is
+    /// the `${` of a template head or middle.
+    pub braces: Vec<bool>,
+}
+
+impl Default for LexerState {
+    /// The state at the start of the source.
+    fn default() -> Self {
+        LexerState {
+            allow_regexp: true,
+            braces: vec![],
+        }
+    }
+}
+
+#[repr(C)]
+struct LexerContext {
+    _unused: i32,
+}
+
+extern "C" {
+    /// Note: source[len-1] must be 0.
+    fn hermes_lexer_new(flags: ParserFlags, source: *const c_char, len: usize)
+        -> *mut LexerContext;
+    fn hermes_lexer_free(lexer_ctx: *mut LexerContext);
+    fn hermes_lexer_next(lexer_ctx: *mut LexerContext, res: *mut LexedToken) -> bool;
+    fn hermes_lexer_get_allow_regexp(lexer_ctx: *const LexerContext) -> bool;
+    fn hermes_lexer_get_braces<'a>(lexer_ctx: *const LexerContext) -> DataRef<'a, u8>;
+    fn hermes_lexer_seek(
+        lexer_ctx: *mut LexerContext,
+        offset: usize,
+        allow_regexp: bool,
+        braces: *const u8,
+        num_braces: usize,
+    );
+    fn hermes_lexer_get_first_error(lexer_ctx: *const LexerContext) -> isize;
+    fn hermes_lexer_get_messages<'a>(lexer_ctx: *const LexerContext) -> DataRef<'a, DiagMessage>;
+}
+
+/// The Hermes lexer, scanning the tokens of a source without parsing it.
+/// Without the parser, whether a `/` starts a regexp or is a division is decided
+/// from the previous token, and JSX and type annotations are lexed as JavaScript.
+pub struct HermesLexer<'a> {
+    /// A pointer to the opaque C++ lexer object. It should never be null.
+    lexer_ctx: *mut LexerContext,
+    source: &'a NullTerminatedBuf,
+}
+
+impl Drop for HermesLexer<'_> {
+    fn drop(&mut self) {
+        unsafe { hermes_lexer_free(self.lexer_ctx) }
+    }
+}
+
+impl HermesLexer<'_> {
+    /// Only the `strict_mode`, `annex_b`, `html_like_comments` and `source_type` flags are used.
+    pub fn new(flags: ParserFlags, source: &NullTerminatedBuf) -> HermesLexer<'_> {
+        HermesLexer {
+            lexer_ctx: unsafe { hermes_lexer_new(flags, source.as_c_char_ptr(), source.len()) },
+            source,
+        }
+    }
+
+    /// Return the next token or comment, in source order. The last token has the category
+    /// `Eof`, and is followed by `None`.
+    pub fn next_token(&mut self) -> Option<LexedToken> {
+        let mut res = MaybeUninit::<LexedToken>::uninit();
+        if unsafe { hermes_lexer_next(self.lexer_ctx, res.as_mut_ptr()) } {
+            Some(unsafe { res.assume_init() })
+        } else {
+            None
+        }
+    }
+
+    /// Return the state before the last token or comment returned by `next_token`.
+    /// Lexing can resume from it at the end of the previous token or comment.
+    pub fn state(&self) -> LexerState {
+        let braces = unsafe { hermes_lexer_get_braces(self.lexer_ctx) };
+        LexerState {
+            allow_regexp: unsafe { hermes_lexer_get_allow_regexp(self.lexer_ctx) },
+            braces: braces.as_slice().iter().map(|&b| b != 0).collect(),
+        }
+    }
+
+    /// Resume lexing at the byte `offset`, which must be the end of a token or comment
+    /// (or 0), in the `state` saved before the next token or comment.
+    pub fn seek(&mut self, offset: usize, state: &LexerState) {
+        let braces = state.braces.iter().map(|&b| b as u8).collect::<Vec<_>>();
+        unsafe {
+            hermes_lexer_seek(
+                self.lexer_ctx,
+                offset,
+                state.allow_regexp,
+                braces.as_ptr(),
+                braces.len(),
+            )
+        }
+    }
+
+    /// Translate a source location into a byte offset in the source.
+    pub fn offset(&self, loc: SMLoc) -> usize {
+        loc.as_ptr() as usize - self.source.as_bytes().as_ptr() as usize
+    }
+
+    /// Return the index of the first error (there could be warnings before it).
+    pub fn first_error_index(&self) -> Option<usize> {
+        let index = unsafe { hermes_lexer_get_first_error(self.lexer_ctx) };
+        if index < 0 {
+            None
+        } else {
+            Some(index as usize)
+        }
+    }
+
+    /// Return true if there was at least one error so far.
+    pub fn has_errors(&self) -> bool {
+        self.first_error_index().is_some()
+    }
+
+    /// Return a slice containing all messages so far.
+    pub fn messages(&self) -> &[DiagMessage] {
+        unsafe { hermes_lexer_get_messages(self.lexer_ctx).as_slice() }
+    }
+}
@@ -43,6 +43,19 @@ pub enum ParserDialect {
     TypeScript,
 }
 
+/// The goal symbol of a parsed source.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceType {
+    /// A script, which cannot contain import and export declarations.
+    Script,
+    /// A module, which is always strict and allows `await` at the top level.
+    Module,
+    /// A script or a module, depending on whether it contains import or export declarations.
+    /// `await` at the top level is parsed as in a script.
+    Unambiguous,
+}
+
 /// Flags controlling the behavior of the parser.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -55,6 +68,24 @@ pub struct ParserFlags {
     pub dialect: ParserDialect,
     /// Store doc-comment block at the top of the file.
     pub store_doc_block: bool,
+    /// Enable the Annex B web compatibility extensions: legacy octal literals
+    /// and escapes in non-strict code.
+    pub annex_b: bool,
+    /// Recognize the Annex B HTML-like comments `<!--` and `-->`. Only applies when
+    /// `source_type` is [`SourceType::Script`], since they are never valid in modules.
+    pub html_like_comments: bool,
+    /// Report every error recovery action of the parser (a character skipped, a node
+    /// dropped) as a remark with the affected range.
+    pub record_recovery: bool,
+    /// Resume parsing at the next statement after a syntax error, keeping the partial AST.
+    pub error_recovery: bool,
+    /// Stop after this many errors. 0 means no limit.
+    pub error_limit: u32,
+    /// Report an error instead of recursing past this nesting depth, e.g. on deeply nested
+    /// parentheses. 0 means the built-in limit, which also caps larger values.
+    pub max_depth: u32,
+    /// Whether the source is a script or a module.
+    pub source_type: SourceType,
 }
 
 impl Default for ParserFlags {
@@ -64,6 +95,13 @@ impl Default for ParserFlags {
             enable_jsx: false,
             dialect: ParserDialect::JavaScript,
             store_doc_block: false,
+            annex_b: true,
+            html_like_comments: false,
+            record_recovery: false,
+            error_recovery: false,
+            error_limit: 0,
+            max_depth: 0,
+            source_type: SourceType::Unambiguous,
         }
     }
 }
@@ -162,6 +200,9 @@ pub struct DiagMessage {
     pub loc: SMLoc,
     /// Source coordinate.
     pub coord: Coord,
+    /// End of the first highlighted range, clipped to the line of `coord`, or `coord`
+    /// itself if there is no range.
+    pub end_coord: Coord,
     /// What kind of message.
     pub diag_kind: DiagKind,
     /// Error message.
@@ -278,7 +319,8 @@ impl HermesParser<'_> {
         }
     }
 
-    /// Return true if there was at least one parser error. That implies that there is no AST.
+    /// Return true if there was at least one parser error. That implies that there is no AST,
+    /// unless `ParserFlags::error_recovery` was set.
     pub fn has_errors(&self) -> bool {
         self.first_error_index().is_some()
     }
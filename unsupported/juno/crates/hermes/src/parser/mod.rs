@@ -6,9 +6,11 @@
  */
 
 mod generated_ffi;
+mod hermes_lexer;
 mod hermes_parser;
 mod node;
 
 pub use generated_ffi::*;
+pub use hermes_lexer::*;
 pub use hermes_parser::*;
 pub use node::*;
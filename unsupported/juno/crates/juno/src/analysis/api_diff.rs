@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Differences between the interfaces of two versions of a module (see [`super::exports`]),
+//! so that library authors can catch accidental breaking changes.
+//!
+//! A change is breaking if importers of the old version may fail with the new one:
+//! an export or `export *` was removed, an export changed to a different kind of thing,
+//! or a function requires more arguments.
+//! Changes between `const`, `let` and `var`, and between a function declaration and a
+//! variable initialized with a function, aren't breaking, since importers can't tell.
+
+use std::io;
+use std::io::Write;
+
+use super::exports::Export;
+use super::exports::ExportedKind;
+use super::exports::ModuleInterface;
+
+/// A difference between two versions of a module.
+#[derive(Debug, Clone)]
+pub enum ApiChange<'a> {
+    /// A name is exported by the new version only.
+    Added(&'a Export),
+
+    /// A name is exported by the old version only.
+    Removed(&'a Export),
+
+    /// The kind of an export, or the module it is re-exported from, changed.
+    Changed { old: &'a Export, new: &'a Export },
+
+    /// The arity of an exported function changed.
+    ArityChanged { old: &'a Export, new: &'a Export },
+
+    /// All the names of a module are re-exported by the new version only.
+    StarExportAdded(&'a str),
+
+    /// All the names of a module are re-exported by the old version only.
+    StarExportRemoved(&'a str),
+}
+
+impl ApiChange<'_> {
+    /// Whether the change may break the importers of the module.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ApiChange::Added(_) | ApiChange::StarExportAdded(_) => false,
+            ApiChange::Removed(_) | ApiChange::StarExportRemoved(_) => true,
+            ApiChange::Changed { old, new } => !compatible(old, new),
+            ApiChange::ArityChanged { old, new } => new.arity > old.arity,
+        }
+    }
+
+    /// The export of the version where the change is located, if any.
+    fn export(&self) -> Option<&Export> {
+        match self {
+            ApiChange::Added(export) | ApiChange::Removed(export) => Some(export),
+            ApiChange::Changed { new, .. } | ApiChange::ArityChanged { new, .. } => Some(new),
+            ApiChange::StarExportAdded(_) | ApiChange::StarExportRemoved(_) => None,
+        }
+    }
+}
+
+/// Whether importers can't tell `old` from `new`, though their kinds are different.
+fn compatible(old: &Export, new: &Export) -> bool {
+    use ExportedKind::*;
+    let is_variable = |kind| matches!(kind, Const | Let | Var);
+    let is_function = |export: &Export| export.arity.is_some();
+    match (old.kind, new.kind) {
+        (old_kind, new_kind) if old_kind == new_kind => true,
+        (old_kind, new_kind) if is_variable(old_kind) && is_variable(new_kind) => true,
+        (Function, new_kind) => is_variable(new_kind) && is_function(new),
+        (old_kind, Function) => is_variable(old_kind) && is_function(old),
+        _ => false,
+    }
+}
+
+/// Compute the changes from `old` to `new`: the removed and changed exports in the order of
+/// `old`, then the added exports in the order of `new`, then the changes of `export *`.
+pub fn diff<'a>(old: &'a ModuleInterface, new: &'a ModuleInterface) -> Vec<ApiChange<'a>> {
+    let mut changes = vec![];
+    for old_export in &old.exports {
+        match new.export(&old_export.name) {
+            None => changes.push(ApiChange::Removed(old_export)),
+            Some(new_export) => {
+                if old_export.kind != new_export.kind || old_export.source != new_export.source {
+                    changes.push(ApiChange::Changed {
+                        old: old_export,
+                        new: new_export,
+                    });
+                }
+                if old_export.arity.is_some()
+                    && new_export.arity.is_some()
+                    && old_export.arity != new_export.arity
+                {
+                    changes.push(ApiChange::ArityChanged {
+                        old: old_export,
+                        new: new_export,
+                    });
+                }
+            }
+        }
+    }
+    for new_export in &new.exports {
+        if old.export(&new_export.name).is_none() {
+            changes.push(ApiChange::Added(new_export));
+        }
+    }
+    for source in &old.star_exports {
+        if !new.star_exports.contains(source) {
+            changes.push(ApiChange::StarExportRemoved(source));
+        }
+    }
+    for source in &new.star_exports {
+        if !old.star_exports.contains(source) {
+            changes.push(ApiChange::StarExportAdded(source));
+        }
+    }
+    changes
+}
+
+/// Describe `export`, like `function f` or `re-export g from './g'`.
+fn describe(export: &Export) -> String {
+    match &export.source {
+        Some(source) => format!("{} {} from '{}'", export.kind.as_str(), export.name, source),
+        None => format!("{} {}", export.kind.as_str(), export.name),
+    }
+}
+
+/// Write a human readable report with one line per change, prefixed by the location of the
+/// change in the old version, named `old_name`, or the new one, named `new_name`.
+/// Breaking changes are marked as such.
+pub fn write_report(
+    old_name: &str,
+    new_name: &str,
+    changes: &[ApiChange],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for change in changes {
+        let name = match change {
+            ApiChange::Removed(_) | ApiChange::StarExportRemoved(_) => old_name,
+            _ => new_name,
+        };
+        match change.export() {
+            Some(export) => write!(out, "{}:{}: ", name, export.range.start.line)?,
+            None => write!(out, "{}: ", name)?,
+        }
+        if change.is_breaking() {
+            write!(out, "breaking: ")?;
+        }
+        match change {
+            ApiChange::Added(export) => writeln!(out, "added {}", describe(export))?,
+            ApiChange::Removed(export) => writeln!(out, "removed {}", describe(export))?,
+            ApiChange::Changed { old, new } => {
+                writeln!(out, "changed {} to {}", describe(old), describe(new))?
+            }
+            ApiChange::ArityChanged { old, new } => writeln!(
+                out,
+                "changed arity of {} from {} to {}",
+                new.name,
+                old.arity.unwrap_or(0),
+                new.arity.unwrap_or(0)
+            )?,
+            ApiChange::StarExportAdded(source) => {
+                writeln!(out, "added export * from '{}'", source)?
+            }
+            ApiChange::StarExportRemoved(source) => {
+                writeln!(out, "removed export * from '{}'", source)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::SourceId;
+    use crate::ast::SourceLoc;
+    use crate::ast::SourceRange;
+
+    fn export(name: &str, kind: ExportedKind, arity: Option<usize>) -> Export {
+        Export {
+            name: name.to_string(),
+            kind,
+            source: None,
+            arity,
+            range: SourceRange::from_loc(SourceId(0), SourceLoc { line: 1, col: 1 }),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        use ExportedKind::*;
+        let old = ModuleInterface {
+            exports: vec![
+                export("f", Function, Some(1)),
+                export("g", Function, Some(2)),
+                export("C", Class, None),
+                export("x", Const, None),
+                export("removed", Let, None),
+            ],
+            star_exports: vec!["./a".to_string()],
+            side_effects: false,
+        };
+        let new = ModuleInterface {
+            exports: vec![
+                export("f", Function, Some(2)),
+                export("g", Const, Some(1)),
+                export("C", Function, Some(0)),
+                export("x", Let, None),
+                export("added", Var, None),
+            ],
+            star_exports: vec!["./b".to_string()],
+            side_effects: false,
+        };
+        let changes = diff(&old, &new);
+        let summary = changes
+            .iter()
+            .map(|change| {
+                let kind = match change {
+                    ApiChange::Added(_) => "added",
+                    ApiChange::Removed(_) => "removed",
+                    ApiChange::Changed { .. } => "changed",
+                    ApiChange::ArityChanged { .. } => "arity",
+                    ApiChange::StarExportAdded(_) => "star added",
+                    ApiChange::StarExportRemoved(_) => "star removed",
+                };
+                (kind, change.is_breaking())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                // f requires one more argument.
+                ("arity", true),
+                // g became a variable with a function which requires fewer arguments.
+                ("changed", false),
+                ("arity", false),
+                // C isn't a class anymore.
+                ("changed", true),
+                ("changed", false),
+                ("removed", true),
+                ("added", false),
+                ("star removed", true),
+                ("star added", false),
+            ]
+        );
+
+        let mut out = vec![];
+        write_report("old.js", "new.js", &changes, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.starts_with("new.js:1: breaking: changed arity of f from 1 to 2\n"));
+        assert!(report.contains("old.js:1: breaking: removed let removed\n"));
+        assert!(report.ends_with("new.js: added export * from './b'\n"));
+    }
+}
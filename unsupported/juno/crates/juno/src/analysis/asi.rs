@@ -0,0 +1,479 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Detection of code which relies on automatic semicolon insertion (ASI) in a way
+//! that likely differs from what the author intended.
+//!
+//! Two kinds of hazards are reported:
+//! - A `return` without a value followed by a line break and an expression statement.
+//!   ASI ends the `return` at the line break, so the expression is never evaluated.
+//! - A line starting with `(`, `[`, `` ` ``, `+` or `-` which continues the expression
+//!   on the previous line, as a call, a computed member access, a tagged template or
+//!   a binary operation. No semicolon is inserted there, so the two lines form a single
+//!   statement even if they look like two.
+//!   Lines starting with `+` or `-` are often intentional continuations of long
+//!   expressions, so they are only reported when the binary expression forms a whole
+//!   expression statement.
+//!
+//! The AST doesn't retain tokens, so the source text is used to find where the lines break.
+//! Nodes without a valid location are skipped.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use juno_support::source_manager::line_starts;
+
+use crate::ast::*;
+
+/// The token whose position is hazardous.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsiHazardKind {
+    /// The next line after a `return` without a value.
+    Return,
+
+    /// `(` continuing the previous line as a call.
+    Call,
+
+    /// `[` continuing the previous line as a computed member access.
+    ComputedMember,
+
+    /// `` ` `` continuing the previous line as a tagged template.
+    TaggedTemplate,
+
+    /// `+` continuing the previous line as an addition.
+    Plus,
+
+    /// `-` continuing the previous line as a subtraction.
+    Minus,
+}
+
+impl AsiHazardKind {
+    /// The token starting the line, for the kinds which continue the previous line.
+    fn token(self) -> &'static str {
+        match self {
+            AsiHazardKind::Return => "return",
+            AsiHazardKind::Call => "(",
+            AsiHazardKind::ComputedMember => "[",
+            AsiHazardKind::TaggedTemplate => "`",
+            AsiHazardKind::Plus => "+",
+            AsiHazardKind::Minus => "-",
+        }
+    }
+}
+
+/// A line break whose effect on the code is likely different from what it looks like.
+#[derive(Debug, Clone)]
+pub struct AsiHazard {
+    pub kind: AsiHazardKind,
+
+    /// Location of the first token of the line: the statement following the `return`,
+    /// or the token continuing the previous line.
+    pub range: SourceRange,
+
+    /// Location of the last token of the previous line: the `return`, or the end of the
+    /// expression which is continued.
+    pub prev: SourceRange,
+}
+
+impl AsiHazard {
+    /// A suggestion for fixing the hazard, phrased relative to [`AsiHazard::prev`].
+    pub fn suggestion(&self) -> String {
+        match self.kind {
+            AsiHazardKind::Return => "to return the value, start it on the same line as \
+                 `return`; otherwise add a `;` after `return`"
+                .to_string(),
+            kind => format!(
+                "to start a new statement, add a `;` here; otherwise move the `{}` \
+                 to the end of this line",
+                kind.token()
+            ),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self.kind {
+            AsiHazardKind::Return => {
+                "statement is not returned, `return` ends at the line break".to_string()
+            }
+            AsiHazardKind::Call => "`(` continues the previous line as a call".to_string(),
+            AsiHazardKind::ComputedMember => {
+                "`[` continues the previous line as a member access".to_string()
+            }
+            AsiHazardKind::TaggedTemplate => {
+                "`` ` `` continues the previous line as a tagged template".to_string()
+            }
+            kind => format!(
+                "`{}` continues the previous line as a binary operation",
+                kind.token()
+            ),
+        }
+    }
+}
+
+/// Find every ASI hazard in the AST rooted at `root`, in the order they appear in the source.
+pub fn find_hazards<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<AsiHazard> {
+    let mut finder = HazardFinder {
+        text: SourceText::default(),
+        returns: vec![],
+        statement_starts: HashSet::new(),
+        result: vec![],
+    };
+    root.visit(gc, &mut finder, None);
+
+    for ret in std::mem::take(&mut finder.returns) {
+        let range = *ret.range();
+        let end = match finder.text.offset(gc, range.file, range.end) {
+            Some(end) => end,
+            None => continue,
+        };
+        let buf = gc.sm().source_buffer(range.file).as_bytes();
+        if buf[end] == b';' {
+            continue;
+        }
+        let (next, newline) = skip_trivia(buf, end + 1);
+        if !newline || next >= buf.len() {
+            continue;
+        }
+        let loc = finder.text.loc(range.file, next);
+        if finder
+            .statement_starts
+            .contains(&(range.file, loc.line, loc.col))
+        {
+            finder.result.push(AsiHazard {
+                kind: AsiHazardKind::Return,
+                range: SourceRange::from_loc(range.file, loc),
+                prev: range,
+            });
+        }
+    }
+
+    let mut result = finder.result;
+    result.sort_by_key(|h| (h.range.file.0, h.range.start.line, h.range.start.col));
+    result
+}
+
+/// Report a warning for every ASI hazard in the AST rooted at `root`, with a note
+/// suggesting a fix.
+/// Returns the number of warnings.
+pub fn lint<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> usize {
+    let hazards = find_hazards(gc, root);
+    for h in &hazards {
+        gc.sm().warning(h.range, h.message());
+        gc.sm().note(h.prev, h.suggestion());
+    }
+    hazards.len()
+}
+
+/// Line starts of the source files, computed when first needed for each file.
+#[derive(Default)]
+struct SourceText {
+    line_starts: HashMap<SourceId, Vec<usize>>,
+}
+
+impl SourceText {
+    /// The byte offset of `loc` in `file`, or `None` if it is invalid or outside the file.
+    fn offset(&mut self, gc: &GCLock, file: SourceId, loc: SourceLoc) -> Option<usize> {
+        if !file.is_valid() || file.0 as usize >= gc.sm().num_sources() || loc.line == 0 {
+            return None;
+        }
+        let buf = gc.sm().source_buffer(file).as_bytes();
+        let starts = self
+            .line_starts
+            .entry(file)
+            .or_insert_with(|| line_starts(buf));
+        let offset = starts.get(loc.line as usize - 1)? + loc.col as usize - 1;
+        if offset < buf.len() {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// The location of the byte `offset` in `file`.
+    /// [`SourceText::offset`] must have been called with `file` before.
+    fn loc(&self, file: SourceId, offset: usize) -> SourceLoc {
+        let starts = &self.line_starts[&file];
+        let line = starts.partition_point(|&start| start <= offset);
+        SourceLoc {
+            line: line as u32,
+            col: (offset - starts[line - 1] + 1) as u32,
+        }
+    }
+}
+
+/// Skip the whitespace, line terminators and comments starting at `offset` in `buf`.
+/// Return the offset of the next token and whether a line terminator was skipped.
+fn skip_trivia(buf: &[u8], mut offset: usize) -> (usize, bool) {
+    let mut newline = false;
+    while offset < buf.len() {
+        match buf[offset..] {
+            [b'\n' | b'\r', ..] => {
+                newline = true;
+                offset += 1;
+            }
+            [0xE2, 0x80, 0xA8 | 0xA9, ..] => {
+                newline = true;
+                offset += 3;
+            }
+            [b' ' | b'\t' | 0x0B | 0x0C, ..] => offset += 1,
+            [0xC2, 0xA0, ..] => offset += 2,
+            [0xEF, 0xBB, 0xBF, ..] => offset += 3,
+            [b'/', b'/', ..] => {
+                while offset < buf.len() && !matches!(buf[offset], b'\n' | b'\r') {
+                    offset += 1;
+                }
+            }
+            [b'/', b'*', ..] => {
+                offset += 2;
+                while offset < buf.len() && !buf[offset..].starts_with(b"*/") {
+                    // A multi-line comment containing a line terminator counts as one.
+                    if matches!(buf[offset], b'\n' | b'\r')
+                        || buf[offset..].starts_with(&[0xE2, 0x80, 0xA8])
+                        || buf[offset..].starts_with(&[0xE2, 0x80, 0xA9])
+                    {
+                        newline = true;
+                    }
+                    offset += 1;
+                }
+                offset += 2;
+            }
+            _ => break,
+        }
+    }
+    (offset, newline)
+}
+
+/// Collects the hazards which continue the previous line, the `return` statements
+/// without a value, and the locations where expression statements start.
+struct HazardFinder<'gc> {
+    text: SourceText,
+    returns: Vec<&'gc Node<'gc>>,
+    statement_starts: HashSet<(SourceId, u32, u32)>,
+    result: Vec<AsiHazard>,
+}
+
+impl<'gc> HazardFinder<'gc> {
+    /// If the token following `prev`, skipping closing parentheses, is `kind`'s token
+    /// and starts a line, record a hazard.
+    fn check_continuation(&mut self, gc: &'gc GCLock, prev: &'gc Node<'gc>, kind: AsiHazardKind) {
+        let file = prev.range().file;
+        let end = prev.range().end;
+        let mut offset = match self.text.offset(gc, file, end) {
+            Some(offset) => offset + utf8_len(gc.sm().source_buffer(file).as_bytes()[offset]),
+            None => return,
+        };
+        let buf = gc.sm().source_buffer(file).as_bytes();
+        loop {
+            let (next, newline) = skip_trivia(buf, offset);
+            match buf.get(next) {
+                // The parentheses of a parenthesized expression aren't in its range.
+                Some(b')') => offset = next + 1,
+                Some(_) if newline && buf[next..].starts_with(kind.token().as_bytes()) => {
+                    self.result.push(AsiHazard {
+                        kind,
+                        range: SourceRange::from_loc(file, self.text.loc(file, next)),
+                        prev: SourceRange::from_loc(file, end),
+                    });
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+impl<'gc> Visitor<'gc> for HazardFinder<'gc> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        match node {
+            Node::ReturnStatement(ReturnStatement { argument: None, .. }) => {
+                self.returns.push(node);
+            }
+            Node::ExpressionStatement(_) => {
+                let range = node.range();
+                self.statement_starts
+                    .insert((range.file, range.start.line, range.start.col));
+            }
+            Node::CallExpression(CallExpression { callee, .. }) => {
+                self.check_continuation(gc, callee, AsiHazardKind::Call);
+            }
+            Node::MemberExpression(MemberExpression {
+                object,
+                computed: true,
+                ..
+            }) => {
+                self.check_continuation(gc, object, AsiHazardKind::ComputedMember);
+            }
+            Node::TaggedTemplateExpression(TaggedTemplateExpression { tag, .. }) => {
+                self.check_continuation(gc, tag, AsiHazardKind::TaggedTemplate);
+            }
+            Node::BinaryExpression(BinaryExpression { left, operator, .. })
+                if matches!(
+                    path,
+                    Some(Path {
+                        parent: Node::ExpressionStatement(_),
+                        ..
+                    })
+                ) =>
+            {
+                match operator {
+                    BinaryExpressionOperator::Plus => {
+                        self.check_continuation(gc, left, AsiHazardKind::Plus)
+                    }
+                    BinaryExpressionOperator::Minus => {
+                        self.check_continuation(gc, left, AsiHazardKind::Minus)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// Length of the UTF-8 sequence starting with `byte`.
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno_support::NullTerminatedBuf;
+
+    use super::*;
+
+    fn range<'gc>(file: SourceId, start: (u32, u32), end: (u32, u32)) -> TemplateMetadata<'gc> {
+        TemplateMetadata {
+            range: SourceRange {
+                file,
+                start: SourceLoc {
+                    line: start.0,
+                    col: start.1,
+                },
+                end: SourceLoc {
+                    line: end.0,
+                    col: end.1,
+                },
+            },
+            ..Default::default()
+        }
+    }
+
+    fn ident<'gc>(gc: &'gc GCLock, name: &str, file: SourceId, loc: (u32, u32)) -> &'gc Node<'gc> {
+        builder::Identifier::build_template(
+            gc,
+            template::Identifier {
+                metadata: range(file, loc, loc),
+                name: gc.atom(name),
+                type_annotation: None,
+                optional: false,
+            },
+        )
+    }
+
+    fn expr_stmt<'gc>(
+        gc: &'gc GCLock,
+        expression: &'gc Node<'gc>,
+        file: SourceId,
+        start: (u32, u32),
+        end: (u32, u32),
+    ) -> &'gc Node<'gc> {
+        builder::ExpressionStatement::build_template(
+            gc,
+            template::ExpressionStatement {
+                metadata: range(file, start, end),
+                expression,
+                directive: None,
+            },
+        )
+    }
+
+    fn ret<'gc>(
+        gc: &'gc GCLock,
+        file: SourceId,
+        start: (u32, u32),
+        end: (u32, u32),
+    ) -> &'gc Node<'gc> {
+        builder::ReturnStatement::build_template(
+            gc,
+            template::ReturnStatement {
+                metadata: range(file, start, end),
+                argument: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_hazards() {
+        let mut ctx = Context::new();
+        let file = ctx.sm_mut().add_source(
+            "<input>",
+            NullTerminatedBuf::from_str_copy("{return\nx; return; y}\nf // call\n(y)\n"),
+        );
+        let gc = GCLock::new(&mut ctx);
+
+        // {return
+        // x; return; y}
+        let block = builder::BlockStatement::build_template(
+            &gc,
+            template::BlockStatement {
+                metadata: range(file, (1, 1), (2, 13)),
+                body: NodeList::from_iter(
+                    &gc,
+                    [
+                        ret(&gc, file, (1, 2), (1, 7)),
+                        expr_stmt(&gc, ident(&gc, "x", file, (2, 1)), file, (2, 1), (2, 2)),
+                        ret(&gc, file, (2, 4), (2, 10)),
+                        expr_stmt(&gc, ident(&gc, "y", file, (2, 12)), file, (2, 12), (2, 12)),
+                    ],
+                ),
+            },
+        );
+        // f // call
+        // (y)
+        let call = builder::CallExpression::build_template(
+            &gc,
+            template::CallExpression {
+                metadata: range(file, (3, 1), (4, 3)),
+                callee: ident(&gc, "f", file, (3, 1)),
+                type_arguments: None,
+                arguments: NodeList::from_iter(&gc, [ident(&gc, "y", file, (4, 2))]),
+            },
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: range(file, (1, 1), (4, 3)),
+                body: NodeList::from_iter(&gc, [block, expr_stmt(&gc, call, file, (3, 1), (4, 3))]),
+            },
+        );
+
+        let hazards = find_hazards(&gc, program)
+            .into_iter()
+            .map(|h| {
+                (
+                    h.kind,
+                    h.range.start.line,
+                    h.range.start.col,
+                    h.prev.start.line,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hazards,
+            [
+                (AsiHazardKind::Return, 2, 1, 1),
+                (AsiHazardKind::Call, 4, 1, 3)
+            ]
+        );
+    }
+}
@@ -0,0 +1,527 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Completion analysis: the ways in which executing a statement can end.
+//!
+//! Following the completion records of the spec, a statement completes either normally,
+//! continuing with the next statement, or abruptly with `return`, `throw`, `break` or
+//! `continue`. The analysis is syntactic and conservative: every completion which may
+//! happen is reported, and completions which can't happen may be reported too.
+//! In particular, any expression other than a literal or a function is assumed to throw.
+//!
+//! `try` statements follow the full semantics of `finally`:
+//! when the `finally` block completes normally, the completion of the `try` or `catch`
+//! block is retained, otherwise the completion of the `finally` block replaces it,
+//! e.g. a `return` in `finally` overrides a `return` or `throw` in the `try` block.
+//! Control flow graphs and data-flow analyses must model these paths,
+//! otherwise liveness and dead code elimination are unsound around `finally`.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+
+/// The ways in which executing a statement can end.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// Execution can continue with the next statement.
+    pub normal: bool,
+
+    /// Can end with `return`.
+    pub returns: bool,
+
+    /// Can end by throwing an exception.
+    pub throws: bool,
+
+    /// Can end with an unlabeled `break`.
+    pub breaks: bool,
+
+    /// Can end with an unlabeled `continue`.
+    pub continues: bool,
+
+    /// Labels of the `break` statements which can end it.
+    pub break_labels: HashSet<NodeLabel>,
+
+    /// Labels of the `continue` statements which can end it.
+    pub continue_labels: HashSet<NodeLabel>,
+}
+
+impl Completions {
+    fn normal() -> Self {
+        Completions {
+            normal: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether it can end in any abrupt way.
+    pub fn is_abrupt(&self) -> bool {
+        self.returns
+            || self.throws
+            || self.breaks
+            || self.continues
+            || !self.break_labels.is_empty()
+            || !self.continue_labels.is_empty()
+    }
+
+    /// Add every completion of `other`.
+    fn merge(&mut self, other: Completions) {
+        self.normal |= other.normal;
+        self.merge_abrupt(other);
+    }
+
+    /// Add the abrupt completions of `other`.
+    fn merge_abrupt(&mut self, other: Completions) {
+        self.returns |= other.returns;
+        self.throws |= other.throws;
+        self.breaks |= other.breaks;
+        self.continues |= other.continues;
+        self.break_labels.extend(other.break_labels);
+        self.continue_labels.extend(other.continue_labels);
+    }
+
+    /// Turn the `break` statements exiting a statement labeled with `labels`
+    /// into normal completions.
+    fn consume_breaks(&mut self, labels: &[NodeLabel]) {
+        for label in labels {
+            if self.break_labels.remove(label) {
+                self.normal = true;
+            }
+        }
+    }
+}
+
+/// Compute the completions of the statement `stmt`.
+pub fn statement<'gc>(gc: &'gc GCLock, stmt: &'gc Node<'gc>) -> Completions {
+    labeled_statement(gc, stmt, &[])
+}
+
+/// Compute the completions of the statements in `list`, executed in sequence.
+pub fn list<'gc>(gc: &'gc GCLock, list: &NodeList<'gc>) -> Completions {
+    let mut result = Completions::normal();
+    for stmt in list.iter() {
+        let completions = statement(gc, stmt);
+        let normal = completions.normal;
+        result.merge_abrupt(completions);
+        if !normal {
+            // The following statements are unreachable.
+            result.normal = false;
+            break;
+        }
+    }
+    result
+}
+
+/// Compute the completions of `stmt`, which is labeled with `labels`.
+fn labeled_statement<'gc>(
+    gc: &'gc GCLock,
+    stmt: &'gc Node<'gc>,
+    labels: &[NodeLabel],
+) -> Completions {
+    let mut result = match stmt {
+        Node::LabeledStatement(LabeledStatement { label, body, .. }) => {
+            let mut labels = labels.to_vec();
+            labels.push(node_cast!(Node::Identifier, label).name);
+            return labeled_statement(gc, body, &labels);
+        }
+        _ if stmt.is_loop_statement() => return loop_statement(gc, stmt, labels),
+
+        Node::EmptyStatement(_)
+        | Node::FunctionDeclaration(_)
+        | Node::ImportDeclaration(_)
+        | Node::ExportAllDeclaration(_) => Completions::normal(),
+        Node::ExpressionStatement(ExpressionStatement { expression, .. }) => evaluate(expression),
+        Node::VariableDeclaration(VariableDeclaration { declarations, .. }) => {
+            let mut result = Completions::normal();
+            result.throws = declarations.iter().any(|decl| {
+                matches!(
+                    decl,
+                    Node::VariableDeclarator(VariableDeclarator {
+                        init: Some(init),
+                        ..
+                    }) if may_throw(init)
+                )
+            });
+            result
+        }
+        Node::ReturnStatement(ReturnStatement { argument, .. }) => Completions {
+            returns: true,
+            throws: matches!(argument, Some(arg) if may_throw(arg)),
+            ..Default::default()
+        },
+        Node::ThrowStatement(_) => Completions {
+            throws: true,
+            ..Default::default()
+        },
+        Node::BreakStatement(BreakStatement { label, .. }) => {
+            let mut result = Completions::default();
+            match label {
+                Some(label) => {
+                    result
+                        .break_labels
+                        .insert(node_cast!(Node::Identifier, label).name);
+                }
+                None => result.breaks = true,
+            }
+            result
+        }
+        Node::ContinueStatement(ContinueStatement { label, .. }) => {
+            let mut result = Completions::default();
+            match label {
+                Some(label) => {
+                    result
+                        .continue_labels
+                        .insert(node_cast!(Node::Identifier, label).name);
+                }
+                None => result.continues = true,
+            }
+            result
+        }
+        Node::BlockStatement(BlockStatement { body, .. }) => list(gc, body),
+        Node::IfStatement(IfStatement {
+            test,
+            consequent,
+            alternate,
+            ..
+        }) => {
+            let mut result = statement(gc, consequent);
+            match alternate {
+                Some(alternate) => result.merge(statement(gc, alternate)),
+                None => result.normal = true,
+            }
+            result.throws |= may_throw(test);
+            result
+        }
+        Node::WithStatement(WithStatement { object, body, .. }) => {
+            let mut result = statement(gc, body);
+            result.throws |= may_throw(object);
+            result
+        }
+        Node::SwitchStatement(switch) => switch_statement(gc, switch),
+        Node::TryStatement(TryStatement {
+            block,
+            handler,
+            finalizer,
+            ..
+        }) => {
+            let mut result = statement(gc, block);
+            if let Some(Node::CatchClause(CatchClause { body, .. })) = handler {
+                // The handler runs only if the block throws, and catches the exception.
+                if result.throws {
+                    result.throws = false;
+                    result.merge(statement(gc, body));
+                }
+            }
+            if let Some(finalizer) = finalizer {
+                let finally = statement(gc, finalizer);
+                if finally.normal {
+                    // The completion of the block or handler is retained.
+                    result.merge_abrupt(finally);
+                } else {
+                    // The completion of the finalizer overrides it.
+                    result = finally;
+                }
+            }
+            result
+        }
+        // Class declarations may throw when evaluating `extends` or computed keys,
+        // and other statements are assumed to be able to throw.
+        _ => Completions {
+            normal: true,
+            throws: true,
+            ..Default::default()
+        },
+    };
+    result.consume_breaks(labels);
+    result
+}
+
+/// Compute the completions of the loop statement `stmt`, which is labeled with `labels`.
+fn loop_statement<'gc>(gc: &'gc GCLock, stmt: &'gc Node<'gc>, labels: &[NodeLabel]) -> Completions {
+    let (body, test_always_true, runs_once, throws) = match stmt {
+        Node::WhileStatement(WhileStatement { test, body, .. }) => {
+            (body, is_truthy_literal(test), false, may_throw(test))
+        }
+        Node::DoWhileStatement(DoWhileStatement { test, body, .. }) => {
+            (body, is_truthy_literal(test), true, may_throw(test))
+        }
+        Node::ForStatement(for_stmt) => {
+            let ForStatement {
+                init,
+                test,
+                update,
+                body,
+                ..
+            } = &**for_stmt;
+            (
+                body,
+                // A missing test is always true.
+                !matches!(test, Some(test) if !is_truthy_literal(test)),
+                false,
+                init.is_some()
+                    || matches!(test, Some(test) if may_throw(test))
+                    || matches!(update, Some(update) if may_throw(update)),
+            )
+        }
+        Node::ForInStatement(ForInStatement { body, .. }) => (body, false, false, true),
+        Node::ForOfStatement(for_of) => (&for_of.body, false, false, true),
+        _ => unreachable!("Not a loop: {:?}", stmt.variant()),
+    };
+
+    let mut body = statement(gc, body);
+    // `continue` statements targeting this loop go to the next iteration.
+    for label in labels {
+        body.continues |= body.continue_labels.remove(label);
+    }
+    let next_iteration = body.normal || body.continues;
+    let exits = if runs_once {
+        next_iteration && !test_always_true
+    } else {
+        !test_always_true
+    };
+
+    let mut result = Completions {
+        normal: exits || body.breaks,
+        returns: body.returns,
+        throws: body.throws || throws,
+        break_labels: body.break_labels,
+        continue_labels: body.continue_labels,
+        ..Default::default()
+    };
+    result.consume_breaks(labels);
+    result
+}
+
+/// Compute the completions of the switch statement `switch`.
+fn switch_statement<'gc>(gc: &'gc GCLock, switch: &'gc SwitchStatement<'gc>) -> Completions {
+    let mut result = Completions::default();
+    let mut has_default = false;
+    let mut last_normal = true;
+    result.throws = may_throw(switch.discriminant);
+    // Any case can be entered, so every case contributes its abrupt completions.
+    for case in switch.cases.iter() {
+        let case = node_cast!(Node::SwitchCase, case);
+        match case.test {
+            Some(test) => result.throws |= may_throw(test),
+            None => has_default = true,
+        }
+        let body = list(gc, &case.consequent);
+        last_normal = body.normal;
+        result.merge_abrupt(body);
+    }
+    // The switch completes normally if no case matches, if a `break` exits it,
+    // or by falling off the end of the last case.
+    result.normal = !has_default || result.breaks || last_normal;
+    result.breaks = false;
+    result
+}
+
+/// Completions of evaluating the expression `expr`.
+fn evaluate(expr: &Node) -> Completions {
+    Completions {
+        normal: true,
+        throws: may_throw(expr),
+        ..Default::default()
+    }
+}
+
+/// Whether evaluating the expression `expr` may throw.
+fn may_throw(expr: &Node) -> bool {
+    !matches!(
+        expr,
+        Node::NullLiteral(_)
+            | Node::BooleanLiteral(_)
+            | Node::NumericLiteral(_)
+            | Node::StringLiteral(_)
+            | Node::RegExpLiteral(_)
+            | Node::BigIntLiteral(_)
+            | Node::ThisExpression(_)
+            | Node::FunctionExpression(_)
+            | Node::ArrowFunctionExpression(_)
+    )
+}
+
+/// Whether `node` is a literal which is always truthy.
+fn is_truthy_literal(node: &Node) -> bool {
+    match node {
+        Node::BooleanLiteral(BooleanLiteral { value, .. }) => *value,
+        Node::NumericLiteral(NumericLiteral { value, .. }) => *value != 0.0 && !value.is_nan(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_stmt<'gc>(
+        gc: &'gc GCLock,
+        block: &'gc Node<'gc>,
+        handler: Option<&'gc Node<'gc>>,
+        finalizer: Option<&'gc Node<'gc>>,
+    ) -> &'gc Node<'gc> {
+        builder::TryStatement::build_template(
+            gc,
+            template::TryStatement {
+                metadata: Default::default(),
+                block,
+                handler: handler.map(|body| {
+                    builder::CatchClause::build_template(
+                        gc,
+                        template::CatchClause {
+                            metadata: Default::default(),
+                            param: None,
+                            body,
+                        },
+                    )
+                }),
+                finalizer,
+            },
+        )
+    }
+
+    fn return_stmt<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+        builder::ReturnStatement::build_template(
+            gc,
+            template::ReturnStatement {
+                metadata: Default::default(),
+                argument: None,
+            },
+        )
+    }
+
+    fn throw_stmt<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+        builder::ThrowStatement::build_template(
+            gc,
+            template::ThrowStatement {
+                metadata: Default::default(),
+                argument: builders::null(gc),
+            },
+        )
+    }
+
+    fn break_stmt<'gc>(gc: &'gc GCLock, label: Option<&str>) -> &'gc Node<'gc> {
+        builder::BreakStatement::build_template(
+            gc,
+            template::BreakStatement {
+                metadata: Default::default(),
+                label: label.map(|label| builders::ident(gc, label)),
+            },
+        )
+    }
+
+    #[test]
+    fn test_try_finally() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+
+        // try { return; } finally {}
+        let c = statement(
+            &gc,
+            try_stmt(
+                &gc,
+                builders::block(&gc, [return_stmt(&gc)]),
+                None,
+                Some(builders::block(&gc, [])),
+            ),
+        );
+        assert!(!c.normal && c.returns && !c.throws);
+
+        // try { throw null; } catch { } -- the exception is caught.
+        let c = statement(
+            &gc,
+            try_stmt(
+                &gc,
+                builders::block(&gc, [throw_stmt(&gc)]),
+                Some(builders::block(&gc, [])),
+                None,
+            ),
+        );
+        assert!(c.normal && !c.throws);
+
+        // try { throw null; } finally { return; } -- the return overrides the throw.
+        let c = statement(
+            &gc,
+            try_stmt(
+                &gc,
+                builders::block(&gc, [throw_stmt(&gc)]),
+                None,
+                Some(builders::block(&gc, [return_stmt(&gc)])),
+            ),
+        );
+        assert!(!c.normal && c.returns && !c.throws);
+
+        // try { return; } catch { throw null; } finally { } -- the catch is unreachable.
+        let c = statement(
+            &gc,
+            try_stmt(
+                &gc,
+                builders::block(&gc, [return_stmt(&gc)]),
+                Some(builders::block(&gc, [throw_stmt(&gc)])),
+                Some(builders::block(&gc, [])),
+            ),
+        );
+        assert!(!c.normal && c.returns && !c.throws);
+
+        // try { x(); } catch { throw null; } -- rethrows.
+        let call = builders::expr_stmt(&gc, builders::call(&gc, builders::ident(&gc, "x"), []));
+        let c = statement(
+            &gc,
+            try_stmt(
+                &gc,
+                builders::block(&gc, [call]),
+                Some(builders::block(&gc, [throw_stmt(&gc)])),
+                None,
+            ),
+        );
+        assert!(c.normal && c.throws);
+    }
+
+    #[test]
+    fn test_loops_and_labels() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let while_true = |body| {
+            builder::WhileStatement::build_template(
+                &gc,
+                template::WhileStatement {
+                    metadata: Default::default(),
+                    body,
+                    test: builders::boolean(&gc, true),
+                },
+            )
+        };
+
+        // while (true) {}
+        let c = statement(&gc, while_true(builders::block(&gc, [])));
+        assert!(!c.normal && !c.is_abrupt());
+
+        // while (true) { break; }
+        let c = statement(&gc, while_true(break_stmt(&gc, None)));
+        assert!(c.normal && !c.is_abrupt());
+
+        // a: while (true) { while (true) { break a; } }
+        let labeled = builder::LabeledStatement::build_template(
+            &gc,
+            template::LabeledStatement {
+                metadata: Default::default(),
+                label: builders::ident(&gc, "a"),
+                body: while_true(while_true(break_stmt(&gc, Some("a")))),
+            },
+        );
+        let c = statement(&gc, labeled);
+        assert!(c.normal && !c.is_abrupt());
+
+        // A `break` to an outer label is propagated.
+        let c = statement(&gc, while_true(break_stmt(&gc, Some("a"))));
+        assert!(!c.normal && c.break_labels.len() == 1);
+
+        // { break; x(); } -- the call is unreachable.
+        let call = builders::expr_stmt(&gc, builders::call(&gc, builders::ident(&gc, "x"), []));
+        let c = statement(&gc, builders::block(&gc, [break_stmt(&gc, None), call]));
+        assert!(!c.normal && c.breaks && !c.throws);
+    }
+}
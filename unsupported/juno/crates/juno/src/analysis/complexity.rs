@@ -0,0 +1,303 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Cyclomatic and cognitive complexity of functions.
+//!
+//! Cyclomatic complexity is one plus the number of decision points in the function.
+//! Cognitive complexity follows the rules popularized by SonarSource:
+//! control flow structures add one plus their nesting level,
+//! `else`, labeled jumps and each sequence of like logical operators add one.
+//!
+//! Every function is measured separately: the code of nested functions
+//! doesn't count towards the enclosing function.
+
+use std::io;
+use std::io::Write;
+
+use crate::ast::*;
+
+/// Complexity of a single function.
+#[derive(Debug, Clone)]
+pub struct FunctionComplexity {
+    /// Name of the function, if it has one.
+    pub name: Option<String>,
+
+    /// Location of the function.
+    pub range: SourceRange,
+
+    pub cyclomatic: u32,
+    pub cognitive: u32,
+}
+
+/// Compute the complexity of every function in the AST rooted at `root`,
+/// in the order the functions appear in the source.
+pub fn analyze<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<FunctionComplexity> {
+    let mut analyzer = Analyzer {
+        results: vec![],
+        stack: vec![],
+    };
+    root.visit(gc, &mut analyzer, None);
+    analyzer.results
+}
+
+/// Write a human readable report with one line per function.
+pub fn write_report(
+    sm: &SourceManager,
+    results: &[FunctionComplexity],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for f in results {
+        writeln!(
+            out,
+            "{}:{}:{}: {}: cyclomatic {}, cognitive {}",
+            sm.source_name(f.range.file),
+            f.range.start.line,
+            f.range.start.col,
+            f.name.as_deref().unwrap_or("<anonymous>"),
+            f.cyclomatic,
+            f.cognitive
+        )?;
+    }
+    Ok(())
+}
+
+/// State of the function currently being analyzed.
+struct Frame {
+    /// Index of the function in `results`.
+    index: usize,
+
+    /// Number of nesting structures enclosing the current node.
+    nesting: u32,
+}
+
+struct Analyzer {
+    results: Vec<FunctionComplexity>,
+
+    /// Functions enclosing the current node, innermost last.
+    stack: Vec<Frame>,
+}
+
+impl Analyzer {
+    fn add(&mut self, cyclomatic: u32, cognitive: u32) {
+        if let Some(frame) = self.stack.last() {
+            let result = &mut self.results[frame.index];
+            result.cyclomatic += cyclomatic;
+            result.cognitive += cognitive;
+        }
+    }
+
+    /// Add a control flow structure which increases the nesting of its children.
+    fn add_structure(&mut self, cyclomatic: u32) {
+        let nesting = self.stack.last().map_or(0, |frame| frame.nesting);
+        self.add(cyclomatic, 1 + nesting);
+    }
+
+    fn visit_function<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        let name = node
+            .function_like_id()
+            .map(|id| gc.str(node_cast!(Node::Identifier, id).name).to_string());
+        self.stack.push(Frame {
+            index: self.results.len(),
+            nesting: 0,
+        });
+        self.results.push(FunctionComplexity {
+            name,
+            range: *node.range(),
+            cyclomatic: 1,
+            cognitive: 0,
+        });
+        node.visit_children(gc, self);
+        self.stack.pop();
+    }
+}
+
+/// Whether `node` is a child of `path` which is nested inside a control flow structure.
+fn is_nested(node: &Node, path: Path) -> bool {
+    match (path.parent, path.field) {
+        (Node::IfStatement(_), NodeField::consequent) => true,
+        // `else if` doesn't increase nesting.
+        (Node::IfStatement(_), NodeField::alternate) => !matches!(node, Node::IfStatement(_)),
+        (Node::ConditionalExpression(_), NodeField::consequent | NodeField::alternate) => true,
+        (Node::SwitchStatement(_), NodeField::cases) => true,
+        (Node::CatchClause(_), NodeField::body) => true,
+        (parent, NodeField::body) => parent.is_loop_statement(),
+        _ => false,
+    }
+}
+
+impl<'gc> Visitor<'gc> for Analyzer {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        if node.is_function_like() {
+            self.visit_function(gc, node);
+            return;
+        }
+
+        let nested = matches!(path, Some(path) if is_nested(node, path));
+        if nested {
+            if let Some(frame) = self.stack.last_mut() {
+                frame.nesting += 1;
+            }
+        }
+
+        match node {
+            Node::IfStatement(IfStatement { alternate, .. }) => {
+                let is_else_if = matches!(
+                    path,
+                    Some(Path {
+                        parent: Node::IfStatement(_),
+                        field: NodeField::alternate,
+                    })
+                );
+                if is_else_if {
+                    self.add(1, 1);
+                } else {
+                    self.add_structure(1);
+                }
+                if matches!(alternate, Some(alt) if !matches!(alt, Node::IfStatement(_))) {
+                    // Plain `else`.
+                    self.add(0, 1);
+                }
+            }
+            Node::ConditionalExpression(_) | Node::CatchClause(_) => self.add_structure(1),
+            Node::SwitchStatement(_) => self.add_structure(0),
+            Node::SwitchCase(SwitchCase { test: Some(_), .. }) => self.add(1, 0),
+            _ if node.is_loop_statement() => self.add_structure(1),
+            Node::LogicalExpression(LogicalExpression { operator, .. }) => {
+                // Only the first of a sequence of like operators counts for cognitive complexity.
+                let continues_sequence = matches!(
+                    path,
+                    Some(Path {
+                        parent: Node::LogicalExpression(LogicalExpression {
+                            operator: parent_op,
+                            ..
+                        }),
+                        ..
+                    }) if parent_op == operator
+                );
+                self.add(1, if continues_sequence { 0 } else { 1 });
+            }
+            Node::AssignmentExpression(AssignmentExpression {
+                operator:
+                    AssignmentExpressionOperator::LogicalAndAssign
+                    | AssignmentExpressionOperator::LogicalOrAssign
+                    | AssignmentExpressionOperator::NullishCoalesceAssign,
+                ..
+            }) => self.add(1, 0),
+            Node::BreakStatement(BreakStatement { label: Some(_), .. })
+            | Node::ContinueStatement(ContinueStatement { label: Some(_), .. }) => self.add(0, 1),
+            _ => {}
+        }
+
+        node.visit_children(gc, self);
+
+        if nested {
+            if let Some(frame) = self.stack.last_mut() {
+                frame.nesting -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_stmt<'gc>(
+        gc: &'gc GCLock,
+        test: &'gc Node<'gc>,
+        consequent: &'gc Node<'gc>,
+        alternate: Option<&'gc Node<'gc>>,
+    ) -> &'gc Node<'gc> {
+        builder::IfStatement::build_template(
+            gc,
+            template::IfStatement {
+                metadata: Default::default(),
+                test,
+                consequent,
+                alternate,
+            },
+        )
+    }
+
+    fn function<'gc>(gc: &'gc GCLock, name: &str, body: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::FunctionDeclaration::build_template(
+            gc,
+            template::FunctionDeclaration {
+                metadata: Default::default(),
+                id: Some(builders::ident(gc, name)),
+                params: NodeList::new(gc),
+                body,
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                generator: false,
+                is_async: false,
+            },
+        )
+    }
+
+    fn logical<'gc>(
+        gc: &'gc GCLock,
+        operator: LogicalExpressionOperator,
+        left: &'gc Node<'gc>,
+        right: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builder::LogicalExpression::build_template(
+            gc,
+            template::LogicalExpression {
+                metadata: Default::default(),
+                left,
+                right,
+                operator,
+            },
+        )
+    }
+
+    #[test]
+    fn test_complexity() {
+        use LogicalExpressionOperator::*;
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let a = builders::ident(&gc, "a");
+        let b = builders::ident(&gc, "b");
+        let empty = builders::block(&gc, []);
+
+        // function f() {
+        //   if (a) {           // +1 cyclomatic, +1 cognitive
+        //     if (a && b && a) // +1 +2 cyclomatic, +2 (nesting) +1 (&& sequence) cognitive
+        //       {}
+        //   } else if (b) {}   // +1 cyclomatic, +1 cognitive
+        //   else {}            // +1 cognitive
+        // }
+        let and = logical(&gc, And, logical(&gc, And, a, b), a);
+        let inner = if_stmt(&gc, and, empty, None);
+        let else_if = if_stmt(&gc, b, empty, Some(empty));
+        let outer = if_stmt(&gc, a, builders::block(&gc, [inner]), Some(else_if));
+        // function g() { (a || b) ?? a }, nested in f.
+        let nested = function(
+            &gc,
+            "g",
+            builders::block(
+                &gc,
+                [builders::expr_stmt(
+                    &gc,
+                    logical(&gc, NullishCoalesce, logical(&gc, Or, a, b), a),
+                )],
+            ),
+        );
+        let f = function(&gc, "f", builders::block(&gc, [outer, nested]));
+
+        let results = analyze(&gc, f);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name.as_deref(), Some("f"));
+        assert_eq!(results[0].cyclomatic, 6);
+        assert_eq!(results[0].cognitive, 6);
+        assert_eq!(results[1].name.as_deref(), Some("g"));
+        assert_eq!(results[1].cyclomatic, 3);
+        assert_eq!(results[1].cognitive, 2);
+    }
+}
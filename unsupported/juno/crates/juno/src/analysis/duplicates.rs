@@ -0,0 +1,479 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Duplicate keys in object literals and duplicate members in class bodies.
+//!
+//! Two kinds of duplicates are early errors in the spec:
+//! - an object literal with two `__proto__: value` properties (ES2022 13.2.5.1),
+//! - a class body declaring a private name twice, unless once as a getter and once as a
+//!   setter with the same placement, i.e. both static or both not static (ES2022 15.7.1).
+//!
+//! The others are legal, but likely mistakes since the later definition silently replaces
+//! the earlier one: two properties or members with the same key, or an accessor conflicting
+//! with a data property or another accessor of the same kind.
+//!
+//! Only keys known statically are compared: identifiers, string literals and numeric
+//! literals, which are compared by their string value (`1` and `"1"` are the same key).
+
+use juno_support::convert::number_to_string;
+
+use crate::ast::*;
+
+/// Kind of duplicate found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Two `__proto__: value` properties in an object literal. Early error.
+    Proto,
+
+    /// A private name declared twice in a class body. Early error.
+    PrivateName,
+
+    /// Two data properties, or two methods or fields, with the same key.
+    Key,
+
+    /// An accessor with the same key as a data property or another accessor of the same
+    /// kind.
+    Accessor,
+}
+
+impl DuplicateKind {
+    /// Whether the duplicate is an early error, as opposed to a lint warning.
+    pub fn is_early_error(self) -> bool {
+        matches!(self, DuplicateKind::Proto | DuplicateKind::PrivateName)
+    }
+}
+
+/// A property or member whose key duplicates an earlier one.
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub kind: DuplicateKind,
+
+    /// The key, with a `#` prefix for private names.
+    pub name: String,
+
+    /// Location of the key of the duplicate.
+    pub range: SourceRange,
+
+    /// Location of the key of the earlier property or member.
+    pub first: SourceRange,
+}
+
+impl Duplicate {
+    /// Message describing the duplicate.
+    pub fn message(&self) -> String {
+        match self.kind {
+            DuplicateKind::Proto => "duplicate __proto__ property".to_string(),
+            DuplicateKind::PrivateName => format!("duplicate private name '{}'", self.name),
+            DuplicateKind::Key => format!("duplicate key '{}'", self.name),
+            DuplicateKind::Accessor => format!("conflicting definitions of '{}'", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemberKind {
+    Data,
+    Getter,
+    Setter,
+}
+
+/// A property or class member with a static key.
+struct Member<'gc> {
+    kind: MemberKind,
+    name: String,
+    private: bool,
+    is_static: bool,
+    /// Whether this is a `__proto__: value` property.
+    proto: bool,
+    key: &'gc Node<'gc>,
+}
+
+/// Find the duplicates among the properties of `node` if it is an `ObjectExpression`,
+/// or its members if it is a `ClassBody`, in the order they appear in the source.
+/// Returns nothing for other nodes.
+pub fn find_member_duplicates<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> Vec<Duplicate> {
+    let members = match node {
+        Node::ObjectExpression(ObjectExpression { properties, .. }) => properties
+            .iter()
+            .filter_map(|prop| object_member(gc, prop))
+            .collect::<Vec<_>>(),
+        Node::ClassBody(ClassBody { body, .. }) => body
+            .iter()
+            .filter_map(|member| class_member(gc, member))
+            .collect::<Vec<_>>(),
+        _ => return vec![],
+    };
+    let mut result = vec![];
+    for (i, member) in members.iter().enumerate() {
+        let earlier = members[..i]
+            .iter()
+            .filter(|prev| prev.private == member.private && prev.name == member.name);
+        let conflict = if member.private {
+            // A getter and a setter may share a name, but nothing else.
+            let earlier = earlier.collect::<Vec<_>>();
+            match earlier.as_slice() {
+                [] => None,
+                [prev]
+                    if prev.is_static == member.is_static
+                        && matches!(
+                            (prev.kind, member.kind),
+                            (MemberKind::Getter, MemberKind::Setter)
+                                | (MemberKind::Setter, MemberKind::Getter)
+                        ) =>
+                {
+                    None
+                }
+                [prev, ..] => Some((DuplicateKind::PrivateName, *prev)),
+            }
+        } else if member.proto {
+            earlier
+                .clone()
+                .find(|prev| prev.proto)
+                .map(|prev| (DuplicateKind::Proto, prev))
+                .or_else(|| public_conflict(member, earlier))
+        } else {
+            public_conflict(member, earlier)
+        };
+        if let Some((kind, prev)) = conflict {
+            result.push(Duplicate {
+                kind,
+                name: member.name.clone(),
+                range: *member.key.range(),
+                first: *prev.key.range(),
+            });
+        }
+    }
+    result
+}
+
+/// The first of the `earlier` public members with the same key which conflicts with
+/// `member`.
+fn public_conflict<'m, 'gc>(
+    member: &Member<'gc>,
+    earlier: impl Iterator<Item = &'m Member<'gc>>,
+) -> Option<(DuplicateKind, &'m Member<'gc>)>
+where
+    'gc: 'm,
+{
+    earlier
+        .filter(|prev| prev.is_static == member.is_static)
+        .find_map(|prev| match (prev.kind, member.kind) {
+            (MemberKind::Data, MemberKind::Data) => Some((DuplicateKind::Key, prev)),
+            (MemberKind::Getter, MemberKind::Setter) | (MemberKind::Setter, MemberKind::Getter) => {
+                None
+            }
+            _ => Some((DuplicateKind::Accessor, prev)),
+        })
+}
+
+/// Find the duplicates in every object literal and class body in the AST rooted at `root`.
+pub fn find_duplicates<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<Duplicate> {
+    iter_preorder(gc, root)
+        .flat_map(|(node, _, _)| find_member_duplicates(gc, node))
+        .collect()
+}
+
+/// Report a warning for every duplicate in the AST rooted at `root` which is not an early
+/// error. Early errors are reported by semantic analysis.
+/// Synthesized object literals and class bodies are skipped, since their keys weren't
+/// written by the user.
+/// Returns the number of warnings.
+pub fn lint<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> usize {
+    let duplicates = iter_preorder(gc, root)
+        .filter(|(node, _, _)| !node.is_synthesized())
+        .flat_map(|(node, _, _)| find_member_duplicates(gc, node));
+    let mut count = 0;
+    for d in duplicates {
+        if !d.kind.is_early_error() {
+            gc.sm().warning(d.range, d.message());
+            gc.sm().note(d.first, "first definition is here");
+            count += 1;
+        }
+    }
+    count
+}
+
+fn object_member<'gc>(gc: &'gc GCLock, prop: &'gc Node<'gc>) -> Option<Member<'gc>> {
+    let Property {
+        key,
+        kind,
+        computed,
+        method,
+        shorthand,
+        ..
+    } = match prop {
+        Node::Property(prop) => prop,
+        _ => return None,
+    };
+    if *computed {
+        return None;
+    }
+    let name = static_key(gc, key)?;
+    let kind = match kind {
+        PropertyKind::Init => MemberKind::Data,
+        PropertyKind::Get => MemberKind::Getter,
+        PropertyKind::Set => MemberKind::Setter,
+    };
+    Some(Member {
+        proto: kind == MemberKind::Data && !method && !shorthand && name == "__proto__",
+        kind,
+        name,
+        private: false,
+        is_static: false,
+        key,
+    })
+}
+
+fn class_member<'gc>(gc: &'gc GCLock, member: &'gc Node<'gc>) -> Option<Member<'gc>> {
+    let (key, kind, is_static) = match member {
+        Node::MethodDefinition(MethodDefinition {
+            key,
+            kind,
+            computed: false,
+            is_static,
+            ..
+        }) => match kind {
+            MethodDefinitionKind::Method => (key, MemberKind::Data, is_static),
+            MethodDefinitionKind::Get => (key, MemberKind::Getter, is_static),
+            MethodDefinitionKind::Set => (key, MemberKind::Setter, is_static),
+            MethodDefinitionKind::Constructor => return None,
+        },
+        Node::ClassProperty(prop) if !prop.computed => {
+            (&prop.key, MemberKind::Data, &prop.is_static)
+        }
+        Node::ClassPrivateProperty(prop) => (&prop.key, MemberKind::Data, &prop.is_static),
+        Node::ClassAccessorProperty(ClassAccessorProperty {
+            key,
+            computed: false,
+            is_static,
+            ..
+        }) => (key, MemberKind::Data, is_static),
+        _ => return None,
+    };
+    let (name, private) = match key {
+        Node::PrivateName(PrivateName { id, .. }) => {
+            let name = node_cast!(Node::Identifier, id).name;
+            (format!("#{}", gc.str(name)), true)
+        }
+        _ => (static_key(gc, key)?, false),
+    };
+    Some(Member {
+        kind,
+        name,
+        private,
+        is_static: *is_static,
+        proto: false,
+        key,
+    })
+}
+
+/// The string value of the non-computed property key `key`, if it is known statically.
+fn static_key<'gc>(gc: &'gc GCLock, key: &'gc Node<'gc>) -> Option<String> {
+    match key {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        Node::StringLiteral(StringLiteral { value, .. }) => {
+            Some(String::from_utf16_lossy(gc.str_u16(*value)))
+        }
+        Node::NumericLiteral(NumericLiteral { value, .. }) => Some(number_to_string(*value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builders::ident(gc, name)
+    }
+
+    fn prop<'gc>(gc: &'gc GCLock, name: &str, kind: PropertyKind) -> &'gc Node<'gc> {
+        builder::Property::build_template(
+            gc,
+            template::Property {
+                metadata: Default::default(),
+                key: key(gc, name),
+                value: builders::number(gc, 1.0),
+                kind,
+                computed: false,
+                method: false,
+                shorthand: false,
+            },
+        )
+    }
+
+    fn object<'gc>(gc: &'gc GCLock, props: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::ObjectExpression::build_template(
+            gc,
+            template::ObjectExpression {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(gc, props),
+            },
+        )
+    }
+
+    fn method<'gc>(
+        gc: &'gc GCLock,
+        key: &'gc Node<'gc>,
+        kind: MethodDefinitionKind,
+        is_static: bool,
+    ) -> &'gc Node<'gc> {
+        let body = builder::BlockStatement::build_template(
+            gc,
+            template::BlockStatement {
+                metadata: Default::default(),
+                body: NodeList::new(gc),
+            },
+        );
+        builder::MethodDefinition::build_template(
+            gc,
+            template::MethodDefinition {
+                metadata: Default::default(),
+                key,
+                value: builder::FunctionExpression::build_template(
+                    gc,
+                    template::FunctionExpression {
+                        metadata: Default::default(),
+                        id: None,
+                        params: NodeList::new(gc),
+                        body,
+                        type_parameters: None,
+                        return_type: None,
+                        predicate: None,
+                        generator: false,
+                        is_async: false,
+                    },
+                ),
+                kind,
+                computed: false,
+                is_static,
+            },
+        )
+    }
+
+    fn private<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builder::PrivateName::build_template(
+            gc,
+            template::PrivateName {
+                metadata: Default::default(),
+                id: key(gc, name),
+            },
+        )
+    }
+
+    fn class_body<'gc>(gc: &'gc GCLock, members: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::ClassBody::build_template(
+            gc,
+            template::ClassBody {
+                metadata: Default::default(),
+                body: NodeList::from_iter(gc, members),
+            },
+        )
+    }
+
+    fn kinds(duplicates: Vec<Duplicate>) -> Vec<(DuplicateKind, String)> {
+        duplicates.into_iter().map(|d| (d.kind, d.name)).collect()
+    }
+
+    #[test]
+    fn test_object() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        // ({__proto__: 1, "__proto__": 1, a: 1, get a() {}, get b() {}, set b(v) {}, 1: 1, "1": 1})
+        let number_key = builder::Property::build_template(
+            &gc,
+            template::Property {
+                metadata: Default::default(),
+                key: builders::number(&gc, 1.0),
+                value: builders::number(&gc, 1.0),
+                kind: PropertyKind::Init,
+                computed: false,
+                method: false,
+                shorthand: false,
+            },
+        );
+        let string_key = builder::Property::build_template(
+            &gc,
+            template::Property {
+                metadata: Default::default(),
+                key: builders::string(&gc, "1"),
+                value: builders::number(&gc, 1.0),
+                kind: PropertyKind::Init,
+                computed: false,
+                method: false,
+                shorthand: false,
+            },
+        );
+        let proto_string = builder::Property::build_template(
+            &gc,
+            template::Property {
+                metadata: Default::default(),
+                key: builders::string(&gc, "__proto__"),
+                value: builders::number(&gc, 1.0),
+                kind: PropertyKind::Init,
+                computed: false,
+                method: false,
+                shorthand: false,
+            },
+        );
+        let obj = object(
+            &gc,
+            vec![
+                prop(&gc, "__proto__", PropertyKind::Init),
+                proto_string,
+                prop(&gc, "a", PropertyKind::Init),
+                prop(&gc, "a", PropertyKind::Get),
+                prop(&gc, "b", PropertyKind::Get),
+                prop(&gc, "b", PropertyKind::Set),
+                number_key,
+                string_key,
+            ],
+        );
+        assert_eq!(
+            kinds(find_duplicates(&gc, obj)),
+            [
+                (DuplicateKind::Proto, "__proto__".to_string()),
+                (DuplicateKind::Accessor, "a".to_string()),
+                (DuplicateKind::Key, "1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        // class {
+        //   get #a() {} set #a(v) {}
+        //   get #b() {} static set #b(v) {}
+        //   #c() {} get #c() {}
+        //   m() {} static m() {} m() {}
+        // }
+        let body = class_body(
+            &gc,
+            vec![
+                method(&gc, private(&gc, "a"), MethodDefinitionKind::Get, false),
+                method(&gc, private(&gc, "a"), MethodDefinitionKind::Set, false),
+                method(&gc, private(&gc, "b"), MethodDefinitionKind::Get, false),
+                method(&gc, private(&gc, "b"), MethodDefinitionKind::Set, true),
+                method(&gc, private(&gc, "c"), MethodDefinitionKind::Method, false),
+                method(&gc, private(&gc, "c"), MethodDefinitionKind::Get, false),
+                method(&gc, key(&gc, "m"), MethodDefinitionKind::Method, false),
+                method(&gc, key(&gc, "m"), MethodDefinitionKind::Method, true),
+                method(&gc, key(&gc, "m"), MethodDefinitionKind::Method, false),
+            ],
+        );
+        assert_eq!(
+            kinds(find_duplicates(&gc, body)),
+            [
+                (DuplicateKind::PrivateName, "#b".to_string()),
+                (DuplicateKind::PrivateName, "#c".to_string()),
+                (DuplicateKind::Key, "m".to_string()),
+            ]
+        );
+    }
+}
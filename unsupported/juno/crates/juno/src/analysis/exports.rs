@@ -0,0 +1,707 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Interface of an ES module: the names it exports, what they are, and whether evaluating
+//! the module has side effects.
+//!
+//! Only the syntax of the module is considered: the kind of an exported name is the kind of
+//! its top-level declaration, and names imported from other modules and exported again are
+//! re-exports, whose kind is in the other module.
+//!
+//! The top-level code has side effects unless every top-level statement is a declaration,
+//! an import or export, or a variable declaration whose initializers are pure expressions.
+//! Pure expressions are literals, identifiers, functions, classes without static
+//! initialization code, and operators, object and array literals and templates made of pure
+//! expressions. Implicit conversions, like `valueOf` calls by `+`, aren't considered, since
+//! bundlers make the same assumption when they drop unused code.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use juno_support::json::JSONEmitter;
+
+use crate::ast::*;
+
+/// What an exported name refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportedKind {
+    Function,
+    Class,
+    Const,
+    Let,
+    Var,
+    Enum,
+    /// A Flow or TypeScript type.
+    Type,
+    /// A name imported from another module.
+    ReExport,
+    /// All the exports of another module, as an object: `export * as ns from`.
+    Namespace,
+    /// Any other value, like the result of the expression of `export default`,
+    /// or a name which isn't declared in the module.
+    Value,
+}
+
+impl ExportedKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportedKind::Function => "function",
+            ExportedKind::Class => "class",
+            ExportedKind::Const => "const",
+            ExportedKind::Let => "let",
+            ExportedKind::Var => "var",
+            ExportedKind::Enum => "enum",
+            ExportedKind::Type => "type",
+            ExportedKind::ReExport => "re-export",
+            ExportedKind::Namespace => "namespace",
+            ExportedKind::Value => "value",
+        }
+    }
+}
+
+/// A name exported by a module.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// The exported name, `default` for the default export.
+    pub name: String,
+
+    pub kind: ExportedKind,
+
+    /// The module the name is re-exported from, for re-exports and namespaces.
+    pub source: Option<String>,
+
+    /// For functions, the number of parameters before the first one with a default value
+    /// or the rest parameter, i.e. the `length` of the function.
+    pub arity: Option<usize>,
+
+    /// Location of the export.
+    pub range: SourceRange,
+}
+
+/// Interface of a module.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleInterface {
+    /// The exported names, in the order they appear in the source.
+    pub exports: Vec<Export>,
+
+    /// Modules all of whose names are re-exported by `export * from`.
+    pub star_exports: Vec<String>,
+
+    /// Whether evaluating the top-level code of the module may have side effects.
+    pub side_effects: bool,
+}
+
+impl ModuleInterface {
+    /// The export named `name`, if any.
+    pub fn export(&self, name: &str) -> Option<&Export> {
+        self.exports.iter().find(|export| export.name == name)
+    }
+
+    /// Emit the interface as a JSON object:
+    /// ```json
+    /// {
+    ///   "exports": [{"name": "f", "kind": "function", "arity": 2, "line": 1}, ...],
+    ///   "starExports": ["./other"],
+    ///   "sideEffects": false
+    /// }
+    /// ```
+    /// Re-exports and namespaces also have the `source` they are exported from.
+    pub fn emit_json<W: Write>(&self, emitter: &mut JSONEmitter<W>) {
+        emitter.open_dict();
+        emitter.emit_key("exports");
+        emitter.open_array();
+        for export in &self.exports {
+            emitter.open_dict();
+            emitter.emit_key("name");
+            emitter.emit_string(&export.name);
+            emitter.emit_key("kind");
+            emitter.emit_string(export.kind.as_str());
+            if let Some(source) = &export.source {
+                emitter.emit_key("source");
+                emitter.emit_string(source);
+            }
+            if let Some(arity) = export.arity {
+                emitter.emit_key("arity");
+                emitter.emit_number(arity as f64);
+            }
+            emitter.emit_key("line");
+            emitter.emit_number(export.range.start.line as f64);
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.emit_key("starExports");
+        emitter.open_array();
+        for source in &self.star_exports {
+            emitter.emit_string(source);
+        }
+        emitter.close_array();
+        emitter.emit_key("sideEffects");
+        emitter.emit_bool(self.side_effects);
+        emitter.close_dict();
+    }
+}
+
+/// A top-level binding of the module.
+#[derive(Debug, Clone)]
+struct Local {
+    kind: ExportedKind,
+
+    /// The module it is imported from, for imports.
+    source: Option<String>,
+
+    /// The arity of functions.
+    arity: Option<usize>,
+}
+
+/// Compute the interface of the module rooted at `root`.
+/// A `Program`, which is a script, has no exports.
+pub fn analyze<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> ModuleInterface {
+    let body = match root {
+        Node::Module(Module { body, .. }) | Node::Program(Program { body, .. }) => *body,
+        _ => return ModuleInterface::default(),
+    };
+
+    // Exports may precede the declarations of the names they export.
+    let mut locals = HashMap::<String, Local>::new();
+    for stmt in body.iter() {
+        if let Node::ImportDeclaration(import) = stmt {
+            let ImportDeclaration {
+                specifiers,
+                source,
+                import_kind,
+                ..
+            } = &**import;
+            let source = string_value(gc, source);
+            for specifier in specifiers.iter() {
+                let (local, kind) = match specifier {
+                    Node::ImportSpecifier(ImportSpecifier {
+                        local, import_kind, ..
+                    }) => (local, *import_kind),
+                    Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local, .. })
+                    | Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local, .. }) => {
+                        (local, *import_kind)
+                    }
+                    _ => continue,
+                };
+                locals.insert(
+                    ident_name(gc, local),
+                    Local {
+                        kind: if kind == ImportKind::Value {
+                            ExportedKind::ReExport
+                        } else {
+                            ExportedKind::Type
+                        },
+                        source: Some(source.clone()),
+                        arity: None,
+                    },
+                );
+            }
+            continue;
+        }
+        let decl = match stmt {
+            Node::ExportNamedDeclaration(export) => export.declaration.unwrap_or(stmt),
+            _ => stmt,
+        };
+        for (name, kind, arity) in declared_names(gc, decl) {
+            locals.insert(
+                name,
+                Local {
+                    kind,
+                    source: None,
+                    arity,
+                },
+            );
+        }
+    }
+
+    let mut interface = ModuleInterface::default();
+    let local_export = |name: &str, exported: String, range: SourceRange| match locals.get(name) {
+        Some(local) => Export {
+            name: exported,
+            kind: local.kind,
+            source: local.source.clone(),
+            arity: local.arity,
+            range,
+        },
+        None => Export {
+            name: exported,
+            kind: ExportedKind::Value,
+            source: None,
+            arity: None,
+            range,
+        },
+    };
+    for stmt in body.iter() {
+        if !is_pure_statement(stmt) {
+            interface.side_effects = true;
+        }
+        match stmt {
+            Node::ExportNamedDeclaration(export_decl) => match export_decl.declaration {
+                Some(decl) => {
+                    for (name, kind, arity) in declared_names(gc, decl) {
+                        interface.exports.push(Export {
+                            name,
+                            kind,
+                            source: None,
+                            arity,
+                            range: *decl.range(),
+                        });
+                    }
+                }
+                None => {
+                    let ExportNamedDeclaration {
+                        specifiers,
+                        source,
+                        export_kind,
+                        ..
+                    } = &**export_decl;
+                    let source = source.map(|source| string_value(gc, source));
+                    for specifier in specifiers.iter() {
+                        let range = *specifier.range();
+                        let export = match (specifier, &source) {
+                            (
+                                Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                                    exported,
+                                    ..
+                                }),
+                                _,
+                            ) => Export {
+                                name: ident_name(gc, exported),
+                                kind: ExportedKind::Namespace,
+                                source: source.clone(),
+                                arity: None,
+                                range,
+                            },
+                            (Node::ExportSpecifier(ExportSpecifier { exported, .. }), Some(_)) => {
+                                Export {
+                                    name: ident_name(gc, exported),
+                                    kind: ExportedKind::ReExport,
+                                    source: source.clone(),
+                                    arity: None,
+                                    range,
+                                }
+                            }
+                            (
+                                Node::ExportSpecifier(ExportSpecifier {
+                                    exported, local, ..
+                                }),
+                                None,
+                            ) => local_export(
+                                &ident_name(gc, local),
+                                ident_name(gc, exported),
+                                range,
+                            ),
+                            _ => continue,
+                        };
+                        interface.exports.push(match export_kind {
+                            ExportKind::Value => export,
+                            ExportKind::Type => Export {
+                                kind: ExportedKind::Type,
+                                ..export
+                            },
+                        });
+                    }
+                }
+            },
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+                let range = *stmt.range();
+                interface.exports.push(match declaration {
+                    Node::Identifier(Identifier { name, .. }) => {
+                        local_export(gc.str(*name), "default".to_string(), range)
+                    }
+                    _ => Export {
+                        name: "default".to_string(),
+                        kind: match declaration {
+                            _ if declaration.is_function_like() => ExportedKind::Function,
+                            Node::ClassDeclaration(_) | Node::ClassExpression(_) => {
+                                ExportedKind::Class
+                            }
+                            _ => ExportedKind::Value,
+                        },
+                        source: None,
+                        arity: if declaration.is_function_like() {
+                            Some(arity(declaration))
+                        } else {
+                            None
+                        },
+                        range,
+                    },
+                });
+            }
+            Node::ExportAllDeclaration(ExportAllDeclaration { source, .. }) => {
+                interface.star_exports.push(string_value(gc, source));
+            }
+            _ => {}
+        }
+    }
+    interface
+}
+
+fn ident_name(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => gc.str(*name).to_string(),
+        _ => string_value(gc, node),
+    }
+}
+
+fn string_value(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::StringLiteral(StringLiteral { value, .. }) => {
+            String::from_utf16_lossy(gc.str_u16(*value))
+        }
+        _ => String::new(),
+    }
+}
+
+/// The `length` of the function-like node `func`.
+fn arity(func: &Node) -> usize {
+    func.function_like_params()
+        .iter()
+        .take_while(|param| !matches!(param, Node::AssignmentPattern(_) | Node::RestElement(_)))
+        .count()
+}
+
+/// The names declared by the declaration `decl`, their kinds, and the arity of functions.
+pub(crate) fn declared_names<'gc>(
+    gc: &'gc GCLock,
+    decl: &'gc Node<'gc>,
+) -> Vec<(String, ExportedKind, Option<usize>)> {
+    let named = |id: Option<&Node>, kind| {
+        id.map(|id| (ident_name(gc, id), kind, None))
+            .into_iter()
+            .collect()
+    };
+    match decl {
+        Node::FunctionDeclaration(func) => func
+            .id
+            .map(|id| {
+                (
+                    ident_name(gc, id),
+                    ExportedKind::Function,
+                    Some(arity(decl)),
+                )
+            })
+            .into_iter()
+            .collect(),
+        Node::ClassDeclaration(class) => named(class.id, ExportedKind::Class),
+        Node::VariableDeclaration(VariableDeclaration {
+            kind, declarations, ..
+        }) => {
+            let kind = match kind {
+                VariableDeclarationKind::Var => ExportedKind::Var,
+                VariableDeclarationKind::Let => ExportedKind::Let,
+                VariableDeclarationKind::Const => ExportedKind::Const,
+            };
+            let mut names = vec![];
+            for declarator in declarations.iter() {
+                match declarator {
+                    // `const f = () => {}` is exported like a function, but keeps its kind.
+                    Node::VariableDeclarator(VariableDeclarator {
+                        id: id @ Node::Identifier(_),
+                        init: Some(init),
+                        ..
+                    }) if init.is_function_like() => {
+                        names.push((ident_name(gc, id), kind, Some(arity(init))));
+                    }
+                    Node::VariableDeclarator(VariableDeclarator { id, .. }) => {
+                        for name in bound_names(id) {
+                            names.push((ident_name(gc, name), kind, None));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            names
+        }
+        Node::EnumDeclaration(EnumDeclaration { id, .. }) => named(Some(id), ExportedKind::Enum),
+        Node::TSEnumDeclaration(TSEnumDeclaration { id, .. }) => {
+            named(Some(id), ExportedKind::Enum)
+        }
+        Node::TypeAlias(TypeAlias { id, .. })
+        | Node::TSTypeAliasDeclaration(TSTypeAliasDeclaration { id, .. }) => {
+            named(Some(id), ExportedKind::Type)
+        }
+        Node::OpaqueType(opaque) => named(Some(opaque.id), ExportedKind::Type),
+        Node::InterfaceDeclaration(interface) => named(Some(interface.id), ExportedKind::Type),
+        Node::TSInterfaceDeclaration(interface) => named(Some(interface.id), ExportedKind::Type),
+        _ => vec![],
+    }
+}
+
+/// Whether evaluating the top-level statement `stmt` can't have side effects.
+fn is_pure_statement(stmt: &Node) -> bool {
+    match stmt {
+        Node::FunctionDeclaration(_)
+        | Node::EmptyStatement(_)
+        | Node::Directive(_)
+        | Node::ImportDeclaration(_)
+        | Node::ExportAllDeclaration(_)
+        | Node::TSTypeAliasDeclaration(_)
+        | Node::TSInterfaceDeclaration(_) => true,
+        Node::ExpressionStatement(ExpressionStatement {
+            directive: Some(_), ..
+        }) => true,
+        Node::ExportNamedDeclaration(export) => export
+            .declaration
+            .iter()
+            .all(|decl| is_pure_statement(decl)),
+        Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+            match declaration {
+                Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => {
+                    is_pure_statement(declaration)
+                }
+                _ => is_pure_expression(declaration),
+            }
+        }
+        Node::ClassDeclaration(class) => {
+            is_pure_class(class.super_class, class.decorators, class.body)
+        }
+        Node::VariableDeclaration(VariableDeclaration { declarations, .. }) => {
+            declarations.iter().all(|declarator| match declarator {
+                // Destructuring may call getters and iterators.
+                Node::VariableDeclarator(VariableDeclarator {
+                    id: Node::Identifier(_),
+                    init,
+                    ..
+                }) => init.iter().all(|init| is_pure_expression(init)),
+                _ => false,
+            })
+        }
+        _ => stmt.variant().is_subtype_of(NodeVariant::FlowDeclaration),
+    }
+}
+
+/// Whether defining a class has no side effects: it has no decorators, and the expressions
+/// evaluated when defining it, its superclass, computed keys and static initializers, are pure.
+fn is_pure_class(super_class: Option<&Node>, decorators: NodeList, body: &Node) -> bool {
+    if !decorators.is_empty() || !super_class.iter().all(|class| is_pure_expression(class)) {
+        return false;
+    }
+    let members = match body {
+        Node::ClassBody(ClassBody { body, .. }) => *body,
+        _ => return false,
+    };
+    members.iter().all(|member| match member {
+        Node::MethodDefinition(MethodDefinition { key, computed, .. }) => {
+            !*computed || is_pure_expression(key)
+        }
+        Node::ClassProperty(prop) => {
+            (!prop.computed || is_pure_expression(prop.key))
+                && (!prop.is_static || prop.value.iter().all(|value| is_pure_expression(value)))
+        }
+        Node::ClassAccessorProperty(ClassAccessorProperty {
+            key,
+            value,
+            computed,
+            is_static,
+            ..
+        }) => {
+            (!*computed || is_pure_expression(key))
+                && (!*is_static || value.iter().all(|value| is_pure_expression(value)))
+        }
+        Node::ClassPrivateProperty(prop) => {
+            !prop.is_static || prop.value.iter().all(|value| is_pure_expression(value))
+        }
+        Node::StaticBlock(StaticBlock { body, .. }) => body.is_empty(),
+        _ => false,
+    })
+}
+
+/// Whether evaluating the expression `expr` can't have side effects.
+fn is_pure_expression(expr: &Node) -> bool {
+    match expr {
+        Node::Identifier(_) | Node::FunctionExpression(_) | Node::ArrowFunctionExpression(_) => {
+            true
+        }
+        _ if expr.variant().is_subtype_of(NodeVariant::Literal) => true,
+        Node::ClassExpression(class) => {
+            is_pure_class(class.super_class, class.decorators, class.body)
+        }
+        Node::TemplateLiteral(TemplateLiteral { expressions, .. })
+        | Node::SequenceExpression(SequenceExpression { expressions, .. }) => {
+            expressions.iter().all(is_pure_expression)
+        }
+        Node::ArrayExpression(ArrayExpression { elements, .. }) => elements
+            .iter()
+            .all(|element| matches!(element, Node::Empty(_)) || is_pure_expression(element)),
+        Node::ObjectExpression(ObjectExpression { properties, .. }) => {
+            properties.iter().all(|property| match property {
+                Node::Property(Property {
+                    key,
+                    value,
+                    computed,
+                    ..
+                }) => (!*computed || is_pure_expression(key)) && is_pure_expression(value),
+                _ => false,
+            })
+        }
+        Node::UnaryExpression(UnaryExpression {
+            operator, argument, ..
+        }) => *operator != UnaryExpressionOperator::Delete && is_pure_expression(argument),
+        Node::BinaryExpression(BinaryExpression { left, right, .. })
+        | Node::LogicalExpression(LogicalExpression { left, right, .. }) => {
+            is_pure_expression(left) && is_pure_expression(right)
+        }
+        Node::ConditionalExpression(ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+            ..
+        }) => {
+            is_pure_expression(test)
+                && is_pure_expression(consequent)
+                && is_pure_expression(alternate)
+        }
+        Node::TypeCastExpression(TypeCastExpression { expression, .. })
+        | Node::TSAsExpression(TSAsExpression { expression, .. }) => is_pure_expression(expression),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var_decl<'gc>(
+        gc: &'gc GCLock,
+        kind: VariableDeclarationKind,
+        name: &str,
+        init: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builder::VariableDeclaration::build_template(
+            gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind,
+                declarations: NodeList::from_iter(
+                    gc,
+                    [builder::VariableDeclarator::build_template(
+                        gc,
+                        template::VariableDeclarator {
+                            metadata: Default::default(),
+                            init: Some(init),
+                            id: builders::ident(gc, name),
+                        },
+                    )],
+                ),
+            },
+        )
+    }
+
+    fn export_decl<'gc>(gc: &'gc GCLock, decl: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::ExportNamedDeclaration::build_template(
+            gc,
+            template::ExportNamedDeclaration {
+                metadata: Default::default(),
+                declaration: Some(decl),
+                specifiers: NodeList::new(gc),
+                source: None,
+                export_kind: ExportKind::Value,
+            },
+        )
+    }
+
+    fn export_specifier<'gc>(gc: &'gc GCLock, local: &str, exported: &str) -> &'gc Node<'gc> {
+        builder::ExportNamedDeclaration::build_template(
+            gc,
+            template::ExportNamedDeclaration {
+                metadata: Default::default(),
+                declaration: None,
+                specifiers: NodeList::from_iter(
+                    gc,
+                    [builder::ExportSpecifier::build_template(
+                        gc,
+                        template::ExportSpecifier {
+                            metadata: Default::default(),
+                            exported: builders::ident(gc, exported),
+                            local: builders::ident(gc, local),
+                        },
+                    )],
+                ),
+                source: None,
+                export_kind: ExportKind::Value,
+            },
+        )
+    }
+
+    fn module<'gc>(gc: &'gc GCLock, body: &[&'gc Node<'gc>]) -> &'gc Node<'gc> {
+        builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: Default::default(),
+                body: NodeList::from_iter(gc, body.iter().copied()),
+            },
+        )
+    }
+
+    #[test]
+    fn test_exports() {
+        use VariableDeclarationKind::*;
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+
+        // export const a = 1;
+        // export { b as c };
+        // let b = [a, 'x'];
+        // export * from './d';
+        let all = builder::ExportAllDeclaration::build_template(
+            &gc,
+            template::ExportAllDeclaration {
+                metadata: Default::default(),
+                source: builders::string(&gc, "./d"),
+                export_kind: ExportKind::Value,
+            },
+        );
+        let array = builder::ArrayExpression::build_template(
+            &gc,
+            template::ArrayExpression {
+                metadata: Default::default(),
+                elements: NodeList::from_iter(
+                    &gc,
+                    [builders::ident(&gc, "a"), builders::string(&gc, "x")],
+                ),
+                trailing_comma: false,
+            },
+        );
+        let pure = module(
+            &gc,
+            &[
+                export_decl(&gc, var_decl(&gc, Const, "a", builders::number(&gc, 1.0))),
+                export_specifier(&gc, "b", "c"),
+                var_decl(&gc, Let, "b", array),
+                all,
+            ],
+        );
+        let interface = analyze(&gc, pure);
+        let exports = interface
+            .exports
+            .iter()
+            .map(|export| (export.name.as_str(), export.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            exports,
+            [("a", ExportedKind::Const), ("c", ExportedKind::Let)]
+        );
+        assert_eq!(interface.star_exports, ["./d"]);
+        assert!(!interface.side_effects);
+
+        // export { f };
+        // var x = f();
+        let call = builders::call(&gc, builders::ident(&gc, "f"), []);
+        let impure = module(
+            &gc,
+            &[
+                export_specifier(&gc, "f", "f"),
+                var_decl(&gc, Var, "x", call),
+            ],
+        );
+        let interface = analyze(&gc, impure);
+        assert_eq!(interface.exports.len(), 1);
+        assert_eq!(interface.exports[0].kind, ExportedKind::Value);
+        assert!(interface.side_effects);
+    }
+}
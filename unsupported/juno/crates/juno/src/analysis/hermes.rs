@@ -0,0 +1,420 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Patterns which are slow or produce large bytecode in Hermes.
+//!
+//! These are heuristics based on how Hermes compiles and runs code, not measurements:
+//! - `with` statements and direct `eval` prevent resolving variables at compile time, so the
+//!   variables of the enclosing functions are looked up by name at runtime.
+//! - Object literals with many properties and `delete` of properties switch objects to
+//!   dictionary mode, where property accesses can't be cached.
+//! - Accessing properties with computed keys in loops sees many different keys, so the
+//!   property caches are megamorphic.
+//! - Most instructions encode registers in one byte and jumps in one byte, with larger
+//!   encodings for the rest, so functions with many locals or much code get larger and slower.
+//!
+//! The bytecode size of a function is estimated from the number of operations in its body,
+//! excluding nested functions, which are compiled separately, and Flow types, which are
+//! erased.
+
+use crate::ast::*;
+
+/// Number of properties above which objects are in dictionary mode.
+pub const LARGE_OBJECT_PROPERTIES: usize = 64;
+
+/// Number of locals above which register operands need the long encodings.
+pub const MANY_LOCALS: usize = 256;
+
+/// Estimated bytecode size, in bytes, above which a function is considered too large.
+pub const LARGE_FUNCTION_BYTES: usize = 64 * 1024;
+
+/// Estimated average size of an instruction, in bytes.
+const AVERAGE_INSTRUCTION_BYTES: usize = 4;
+
+/// Estimated effect of a pattern on performance.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Impact {
+    Low,
+    Medium,
+    High,
+}
+
+impl Impact {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Impact::Low => "low",
+            Impact::Medium => "medium",
+            Impact::High => "high",
+        }
+    }
+}
+
+/// A pattern which is slow or large in Hermes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternKind {
+    /// A `with` statement.
+    With,
+    /// A direct call to `eval`.
+    DirectEval,
+    /// An object literal with more than `LARGE_OBJECT_PROPERTIES` properties.
+    LargeObjectLiteral { properties: usize },
+    /// A `delete` of a property.
+    Delete,
+    /// A computed property access with a non-literal key in a loop.
+    DynamicKeyInLoop,
+    /// A function with more than `MANY_LOCALS` parameters and variables.
+    ManyLocals { locals: usize },
+    /// A function whose estimated bytecode exceeds `LARGE_FUNCTION_BYTES`.
+    LargeFunction { bytes: usize },
+}
+
+/// An occurrence of a pattern.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub kind: PatternKind,
+
+    /// Location of the pattern, or of the function for `ManyLocals` and `LargeFunction`.
+    pub range: SourceRange,
+
+    /// Name of the enclosing function, if it has one.
+    pub function: Option<String>,
+}
+
+impl Finding {
+    /// Estimated effect of the pattern on performance.
+    pub fn impact(&self) -> Impact {
+        match self.kind {
+            PatternKind::With | PatternKind::DirectEval => Impact::High,
+            PatternKind::LargeObjectLiteral { .. }
+            | PatternKind::Delete
+            | PatternKind::LargeFunction { .. } => Impact::Medium,
+            PatternKind::DynamicKeyInLoop | PatternKind::ManyLocals { .. } => Impact::Low,
+        }
+    }
+
+    /// Message describing the pattern and its impact.
+    pub fn message(&self) -> String {
+        let msg = match self.kind {
+            PatternKind::With => "'with' prevents resolving variables at compile time".to_string(),
+            PatternKind::DirectEval => {
+                "direct 'eval' prevents resolving variables at compile time".to_string()
+            }
+            PatternKind::LargeObjectLiteral { properties } => format!(
+                "object literal with {} properties is in dictionary mode",
+                properties
+            ),
+            PatternKind::Delete => "'delete' switches the object to dictionary mode".to_string(),
+            PatternKind::DynamicKeyInLoop => {
+                "property access with a computed key in a loop is megamorphic".to_string()
+            }
+            PatternKind::ManyLocals { locals } => format!(
+                "function has {} locals, registers need the long encodings",
+                locals
+            ),
+            PatternKind::LargeFunction { bytes } => {
+                format!("function has an estimated {} KiB of bytecode", bytes / 1024)
+            }
+        };
+        format!("{} ({} impact)", msg, self.impact().as_str())
+    }
+}
+
+/// Find every slow or large pattern in the AST rooted at `root`, in source order.
+pub fn analyze<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<Finding> {
+    let mut analyzer = Analyzer {
+        findings: vec![],
+        stack: vec![Frame::new(None, *root.range())],
+    };
+    root.visit(gc, &mut analyzer, None);
+    let frame = analyzer.stack.pop().unwrap();
+    analyzer.finish(frame);
+    let mut findings = analyzer.findings;
+    findings.sort_by_key(|f| (f.range.start.line, f.range.start.col));
+    findings
+}
+
+/// Report a warning for every slow or large pattern in the AST rooted at `root`.
+/// Returns the number of warnings.
+pub fn lint<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> usize {
+    let findings = analyze(gc, root);
+    for f in &findings {
+        gc.sm().warning(f.range, f.message());
+    }
+    findings.len()
+}
+
+/// Estimated bytecode size of the function `node`, or of the global code if `node` is the
+/// `Program`, excluding nested functions.
+pub fn estimated_bytes<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> usize {
+    let mut analyzer = Analyzer {
+        findings: vec![],
+        stack: vec![Frame::new(None, *node.range())],
+    };
+    if node.is_function_like() {
+        node.visit_children(gc, &mut analyzer);
+    } else {
+        node.visit(gc, &mut analyzer, None);
+    }
+    analyzer.stack[0].bytes
+}
+
+/// State of the function currently being analyzed.
+struct Frame {
+    name: Option<String>,
+    range: SourceRange,
+    bytes: usize,
+    locals: usize,
+
+    /// Number of loops enclosing the current node.
+    loops: u32,
+}
+
+impl Frame {
+    fn new(name: Option<String>, range: SourceRange) -> Frame {
+        Frame {
+            name,
+            range,
+            bytes: 0,
+            locals: 0,
+            loops: 0,
+        }
+    }
+}
+
+struct Analyzer {
+    findings: Vec<Finding>,
+
+    /// Functions enclosing the current node, innermost last.
+    stack: Vec<Frame>,
+}
+
+impl Analyzer {
+    fn frame(&mut self) -> &mut Frame {
+        self.stack.last_mut().unwrap()
+    }
+
+    fn push(&mut self, kind: PatternKind, range: SourceRange) {
+        let function = self.frame().name.clone();
+        self.findings.push(Finding {
+            kind,
+            range,
+            function,
+        });
+    }
+
+    /// Report the patterns concerning the whole function `frame`.
+    fn finish(&mut self, frame: Frame) {
+        if frame.locals > MANY_LOCALS {
+            self.findings.push(Finding {
+                kind: PatternKind::ManyLocals {
+                    locals: frame.locals,
+                },
+                range: frame.range,
+                function: frame.name.clone(),
+            });
+        }
+        if frame.bytes > LARGE_FUNCTION_BYTES {
+            self.findings.push(Finding {
+                kind: PatternKind::LargeFunction { bytes: frame.bytes },
+                range: frame.range,
+                function: frame.name,
+            });
+        }
+    }
+
+    fn visit_function<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) {
+        let name = match (node.function_like_id(), path) {
+            (Some(id), _) => Some(id),
+            (
+                None,
+                Some(Path {
+                    parent: Node::VariableDeclarator(VariableDeclarator { id, .. }),
+                    field: NodeField::init,
+                }),
+            ) => Some(*id),
+            _ => None,
+        }
+        .and_then(|id| match id {
+            Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+            _ => None,
+        });
+
+        // Creating the closure, and the binding of a declaration in the enclosing function.
+        let outer = self.frame();
+        outer.bytes += AVERAGE_INSTRUCTION_BYTES;
+        if let Node::FunctionDeclaration(_) = node {
+            outer.locals += 1;
+        }
+
+        let mut frame = Frame::new(name, *node.range());
+        frame.locals = node.function_like_params().len();
+        self.stack.push(frame);
+        node.visit_children(gc, self);
+        let frame = self.stack.pop().unwrap();
+        self.finish(frame);
+    }
+}
+
+impl<'gc> Visitor<'gc> for Analyzer {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        let variant = node.variant();
+        if variant.is_subtype_of(NodeVariant::Flow)
+            || variant.is_subtype_of(NodeVariant::FlowDeclaration)
+        {
+            return;
+        }
+        if node.is_function_like() {
+            self.visit_function(gc, node, path);
+            return;
+        }
+
+        match node {
+            // Operands, or only structure.
+            Node::Identifier(_)
+            | Node::Program(_)
+            | Node::BlockStatement(_)
+            | Node::ExpressionStatement(_)
+            | Node::VariableDeclaration(_)
+            | Node::EmptyStatement(_) => {}
+            _ => self.frame().bytes += AVERAGE_INSTRUCTION_BYTES,
+        }
+
+        match node {
+            Node::WithStatement(_) => self.push(PatternKind::With, *node.range()),
+            Node::CallExpression(CallExpression {
+                callee: Node::Identifier(Identifier { name, .. }),
+                ..
+            }) if gc.str(*name) == "eval" => self.push(PatternKind::DirectEval, *node.range()),
+            Node::ObjectExpression(ObjectExpression { properties, .. })
+                if properties.len() > LARGE_OBJECT_PROPERTIES =>
+            {
+                self.push(
+                    PatternKind::LargeObjectLiteral {
+                        properties: properties.len(),
+                    },
+                    *node.range(),
+                )
+            }
+            Node::UnaryExpression(UnaryExpression {
+                operator: UnaryExpressionOperator::Delete,
+                argument: Node::MemberExpression(_) | Node::OptionalMemberExpression(_),
+                ..
+            }) => self.push(PatternKind::Delete, *node.range()),
+            Node::MemberExpression(MemberExpression {
+                property,
+                computed: true,
+                ..
+            })
+            | Node::OptionalMemberExpression(OptionalMemberExpression {
+                property,
+                computed: true,
+                ..
+            }) if self.frame().loops != 0 && !property.is_literal() => {
+                self.push(PatternKind::DynamicKeyInLoop, *node.range())
+            }
+            Node::VariableDeclarator(_) | Node::ClassDeclaration(_) => self.frame().locals += 1,
+            _ => {}
+        }
+
+        let is_loop = node.is_loop_statement();
+        if is_loop {
+            self.frame().loops += 1;
+        }
+        node.visit_children(gc, self);
+        if is_loop {
+            self.frame().loops -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn computed<'gc>(
+        gc: &'gc GCLock,
+        object: &'gc Node<'gc>,
+        property: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builder::MemberExpression::build_template(
+            gc,
+            template::MemberExpression {
+                metadata: Default::default(),
+                object,
+                property,
+                computed: true,
+            },
+        )
+    }
+
+    #[test]
+    fn test_patterns() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let o = builders::ident(&gc, "o");
+        let k = builders::ident(&gc, "k");
+
+        // while (k) { o[k]; o[0]; delete o.p; eval(k); }
+        let body = builders::block(
+            &gc,
+            [
+                builders::expr_stmt(&gc, computed(&gc, o, k)),
+                builders::expr_stmt(&gc, computed(&gc, o, builders::number(&gc, 0.0))),
+                builders::expr_stmt(
+                    &gc,
+                    builder::UnaryExpression::build_template(
+                        &gc,
+                        template::UnaryExpression {
+                            metadata: Default::default(),
+                            operator: UnaryExpressionOperator::Delete,
+                            argument: builders::member(&gc, o, "p"),
+                            prefix: true,
+                        },
+                    ),
+                ),
+                builders::expr_stmt(&gc, builders::call(&gc, builders::ident(&gc, "eval"), [k])),
+            ],
+        );
+        let loop_ = builder::WhileStatement::build_template(
+            &gc,
+            template::WhileStatement {
+                metadata: Default::default(),
+                test: k,
+                body,
+            },
+        );
+        // o[k]; outside of the loop.
+        let outside = builders::expr_stmt(&gc, computed(&gc, o, k));
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [loop_, outside]),
+            },
+        );
+
+        let kinds = analyze(&gc, program)
+            .into_iter()
+            .map(|f| f.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                PatternKind::DynamicKeyInLoop,
+                PatternKind::Delete,
+                PatternKind::DirectEval,
+            ]
+        );
+        // The loop, 4 member expressions, `delete`, a literal and a call.
+        assert_eq!(estimated_bytes(&gc, program), 8 * AVERAGE_INSTRUCTION_BYTES);
+    }
+}
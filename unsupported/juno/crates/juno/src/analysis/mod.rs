@@ -0,0 +1,21 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! # Analyses
+//!
+//! Read-only analyses of the AST which produce reports about the code,
+//! as opposed to passes, which transform it.
+
+pub mod api_diff;
+pub mod asi;
+pub mod completion;
+pub mod complexity;
+pub mod duplicates;
+pub mod exports;
+pub mod hermes;
+pub mod react;
+pub mod switch;
@@ -0,0 +1,646 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! React components and hooks, and violations of the Rules of Hooks.
+//!
+//! Following the conventions of `eslint-plugin-react-hooks`, a hook is a function whose name
+//! is `use` followed by an uppercase letter or a digit, and a component is a function whose
+//! name starts with an uppercase letter, or the callback of `memo` or `forwardRef`.
+//! The name of an anonymous function is the variable, property or method it is assigned to.
+//! Components are only reported as such if they return JSX or call hooks.
+//!
+//! Hooks must be called in the same order on every render, so they may only be called at the
+//! top level of components and hooks: not in conditionals, loops, callbacks, class methods,
+//! other functions or at the top level of the module. A hook called after a `return`
+//! statement of the function, outside of conditionals and loops, is called conditionally,
+//! since the `return` may have been executed.
+
+use crate::ast::*;
+
+/// What kind of React function a function is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReactFunctionKind {
+    Component,
+    Hook,
+}
+
+/// A component or a hook.
+#[derive(Debug, Clone)]
+pub struct ReactFunction {
+    /// Name of the function, if it has one. Callbacks of `memo` and `forwardRef` don't.
+    pub name: Option<String>,
+
+    pub kind: ReactFunctionKind,
+
+    /// Location of the function.
+    pub range: SourceRange,
+
+    /// Number of hooks called directly by the function.
+    pub hook_calls: usize,
+
+    /// Whether the function returns JSX.
+    pub returns_jsx: bool,
+}
+
+/// A way of breaking the Rules of Hooks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// Called in a branch of a conditional statement or expression.
+    Conditional,
+    /// Called in a loop.
+    Loop,
+    /// Called after a `return` statement.
+    AfterEarlyReturn,
+    /// Called in an anonymous function nested in a component or a hook.
+    Callback,
+    /// Called in a named function which is neither a component nor a hook.
+    NotComponentOrHook,
+    /// Called in a method of a class.
+    Class,
+    /// Called at the top level of the module.
+    TopLevel,
+}
+
+/// A hook call breaking the Rules of Hooks.
+#[derive(Debug, Clone)]
+pub struct HookViolation {
+    /// Name of the hook, like `useState` or `React.useState`.
+    pub hook: String,
+
+    pub kind: ViolationKind,
+
+    /// Name of the function calling the hook, if it has one.
+    pub function: Option<String>,
+
+    /// Location of the call.
+    pub range: SourceRange,
+
+    /// Location of the first `return` statement, for `AfterEarlyReturn`.
+    pub early_return: Option<SourceRange>,
+}
+
+impl HookViolation {
+    /// Human readable description of the violation.
+    pub fn message(&self) -> String {
+        let hook = &self.hook;
+        match self.kind {
+            ViolationKind::Conditional => format!(
+                "React Hook `{}` is called conditionally, hooks must be called in the same \
+                order on every render",
+                hook
+            ),
+            ViolationKind::Loop => format!(
+                "React Hook `{}` is called in a loop, and may be called more than once per \
+                render",
+                hook
+            ),
+            ViolationKind::AfterEarlyReturn => format!(
+                "React Hook `{}` is called conditionally, after an early return",
+                hook
+            ),
+            ViolationKind::Callback => {
+                format!("React Hook `{}` cannot be called inside a callback", hook)
+            }
+            ViolationKind::NotComponentOrHook => format!(
+                "React Hook `{}` is called in function `{}`, which is neither a component nor \
+                a hook",
+                hook,
+                self.function.as_deref().unwrap_or("<anonymous>")
+            ),
+            ViolationKind::Class => {
+                format!("React Hook `{}` cannot be called in a class", hook)
+            }
+            ViolationKind::TopLevel => {
+                format!("React Hook `{}` cannot be called at the top level", hook)
+            }
+        }
+    }
+}
+
+/// The components, hooks and violations of the Rules of Hooks of a module.
+#[derive(Debug, Clone, Default)]
+pub struct ReactReport {
+    /// The components and hooks, in the order they appear in the source.
+    pub functions: Vec<ReactFunction>,
+
+    /// The violations, in the order they appear in the source.
+    pub violations: Vec<HookViolation>,
+}
+
+/// Find the components, hooks and violations of the Rules of Hooks in the AST rooted at
+/// `root`.
+pub fn analyze<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> ReactReport {
+    let mut analyzer = Analyzer {
+        report: ReactReport::default(),
+        stack: vec![],
+    };
+    root.visit(gc, &mut analyzer, None);
+    analyzer.report
+}
+
+/// Report a warning for every violation of the Rules of Hooks in the AST rooted at `root`.
+/// Returns the number of warnings.
+pub fn lint<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> usize {
+    let violations = analyze(gc, root).violations;
+    for v in &violations {
+        gc.sm().warning(v.range, v.message());
+        if let Some(ret) = v.early_return {
+            gc.sm().note(ret, "early return is here");
+        }
+    }
+    violations.len()
+}
+
+/// Whether `name` is the name of a hook.
+pub fn is_hook_name(name: &str) -> bool {
+    match name.strip_prefix("use") {
+        Some(rest) => rest
+            .chars()
+            .next()
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// Whether `name` is the name of a component.
+pub fn is_component_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+fn ident_name<'gc>(gc: &'gc GCLock, node: &Node) -> Option<&'gc str> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name)),
+        _ => None,
+    }
+}
+
+/// The name of the hook called by `callee`, like `useState` or `React.useState`, if it is a
+/// hook. Hooks can be properties of namespaces, whose names start with an uppercase letter.
+fn hook_name(gc: &GCLock, callee: &Node) -> Option<String> {
+    match callee {
+        Node::Identifier(_) => ident_name(gc, callee)
+            .filter(|name| is_hook_name(name))
+            .map(str::to_string),
+        Node::MemberExpression(MemberExpression {
+            object,
+            property,
+            computed: false,
+            ..
+        }) => {
+            let object = ident_name(gc, object).filter(|name| is_component_name(name))?;
+            let property = ident_name(gc, property).filter(|name| is_hook_name(name))?;
+            Some(format!("{}.{}", object, property))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `callee` is `memo` or `forwardRef`, whose callbacks are components.
+fn is_component_wrapper(gc: &GCLock, callee: &Node) -> bool {
+    let name = match callee {
+        Node::Identifier(_) => ident_name(gc, callee),
+        Node::MemberExpression(MemberExpression {
+            property,
+            computed: false,
+            ..
+        }) => ident_name(gc, property),
+        _ => None,
+    };
+    matches!(name, Some("memo" | "forwardRef"))
+}
+
+/// Whether `node` is JSX.
+fn is_jsx(node: &Node) -> bool {
+    matches!(node, Node::JSXElement(_) | Node::JSXFragment(_))
+}
+
+/// What a function is, for the hooks it calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    /// A component or a hook, at `index` in `functions`.
+    React(usize),
+    /// A function which may be a component, if it returns JSX or calls hooks.
+    MaybeComponent,
+    /// A method or a property initializer of a class.
+    Class,
+    /// An anonymous function.
+    Callback,
+    /// A named function which is neither a component nor a hook.
+    Other,
+}
+
+/// State of the function currently being analyzed.
+struct Frame {
+    kind: FrameKind,
+    name: Option<String>,
+    range: SourceRange,
+
+    /// Number of conditional branches enclosing the current node.
+    conditionals: u32,
+
+    /// Number of loops enclosing the current node.
+    loops: u32,
+
+    /// The first `return` statement visited, which precedes the current node.
+    first_return: Option<SourceRange>,
+
+    hook_calls: usize,
+    returns_jsx: bool,
+}
+
+/// How a child of a node is executed.
+enum Branch {
+    Conditional,
+    Loop,
+}
+
+/// How `path` executes its child: conditionally, repeatedly, or once.
+fn branch(path: Path) -> Option<Branch> {
+    match (path.parent, path.field) {
+        (Node::IfStatement(_), NodeField::consequent | NodeField::alternate)
+        | (Node::ConditionalExpression(_), NodeField::consequent | NodeField::alternate)
+        | (Node::LogicalExpression(_), NodeField::right)
+        | (Node::SwitchStatement(_), NodeField::cases)
+        | (Node::TryStatement(_), NodeField::handler) => Some(Branch::Conditional),
+        (
+            Node::AssignmentExpression(AssignmentExpression {
+                operator:
+                    AssignmentExpressionOperator::LogicalAndAssign
+                    | AssignmentExpressionOperator::LogicalOrAssign
+                    | AssignmentExpressionOperator::NullishCoalesceAssign,
+                ..
+            }),
+            NodeField::right,
+        ) => Some(Branch::Conditional),
+        // The initializer of a `for` loop and the iterated object are evaluated once.
+        (Node::ForStatement(_), NodeField::init)
+        | (Node::ForInStatement(_) | Node::ForOfStatement(_), NodeField::right) => None,
+        (parent, _) if parent.is_loop_statement() => Some(Branch::Loop),
+        _ => None,
+    }
+}
+
+struct Analyzer {
+    report: ReactReport,
+
+    /// Functions enclosing the current node, innermost last.
+    stack: Vec<Frame>,
+}
+
+impl Analyzer {
+    /// The name of the function `node`, from its id or what it is assigned to.
+    fn function_name<'gc>(
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> Option<String> {
+        if let Some(id) = node.function_like_id() {
+            return ident_name(gc, id).map(str::to_string);
+        }
+        let target = match path? {
+            Path {
+                parent: Node::VariableDeclarator(VariableDeclarator { id, .. }),
+                field: NodeField::init,
+            } => id,
+            Path {
+                parent: Node::AssignmentExpression(AssignmentExpression { left, .. }),
+                field: NodeField::right,
+            } => match left {
+                Node::MemberExpression(MemberExpression {
+                    property,
+                    computed: false,
+                    ..
+                }) => property,
+                _ => left,
+            },
+            Path {
+                parent:
+                    Node::Property(Property {
+                        key,
+                        computed: false,
+                        ..
+                    })
+                    | Node::MethodDefinition(MethodDefinition {
+                        key,
+                        computed: false,
+                        ..
+                    }),
+                field: NodeField::value,
+            } => key,
+            _ => return None,
+        };
+        ident_name(gc, target).map(str::to_string)
+    }
+
+    fn visit_function<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) {
+        let name = Self::function_name(gc, node, path);
+        let kind = match (&name, path) {
+            (Some(name), _) if is_hook_name(name) => {
+                self.report.functions.push(ReactFunction {
+                    name: Some(name.clone()),
+                    kind: ReactFunctionKind::Hook,
+                    range: *node.range(),
+                    hook_calls: 0,
+                    returns_jsx: false,
+                });
+                FrameKind::React(self.report.functions.len() - 1)
+            }
+            (
+                _,
+                Some(Path {
+                    parent: Node::MethodDefinition(_) | Node::ClassProperty(_),
+                    ..
+                }),
+            ) => FrameKind::Class,
+            (Some(name), _) if is_component_name(name) => FrameKind::MaybeComponent,
+            (
+                _,
+                Some(Path {
+                    parent: Node::CallExpression(CallExpression { callee, .. }),
+                    field: NodeField::arguments,
+                }),
+            ) if is_component_wrapper(gc, callee) => FrameKind::MaybeComponent,
+            (Some(_), _) => FrameKind::Other,
+            (None, _) => FrameKind::Callback,
+        };
+        self.stack.push(Frame {
+            kind,
+            name,
+            range: *node.range(),
+            conditionals: 0,
+            loops: 0,
+            first_return: None,
+            hook_calls: 0,
+            returns_jsx: matches!(
+                node,
+                Node::ArrowFunctionExpression(arrow) if arrow.expression && is_jsx(arrow.body)
+            ),
+        });
+        node.visit_children(gc, self);
+        let frame = self.stack.pop().unwrap();
+        match frame.kind {
+            FrameKind::React(index) => {
+                let function = &mut self.report.functions[index];
+                function.hook_calls = frame.hook_calls;
+                function.returns_jsx = frame.returns_jsx;
+            }
+            FrameKind::MaybeComponent if frame.returns_jsx || frame.hook_calls != 0 => {
+                // Nested functions were visited first, keep the order of the source.
+                let index = self
+                    .report
+                    .functions
+                    .iter()
+                    .position(|f| {
+                        (f.range.start.line, f.range.start.col)
+                            > (frame.range.start.line, frame.range.start.col)
+                    })
+                    .unwrap_or(self.report.functions.len());
+                self.report.functions.insert(
+                    index,
+                    ReactFunction {
+                        name: frame.name,
+                        kind: ReactFunctionKind::Component,
+                        range: frame.range,
+                        hook_calls: frame.hook_calls,
+                        returns_jsx: frame.returns_jsx,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_hook_call(&mut self, hook: String, range: SourceRange) {
+        let (kind, function, early_return) = match self.stack.last_mut() {
+            None => (Some(ViolationKind::TopLevel), None, None),
+            Some(frame) => {
+                frame.hook_calls += 1;
+                let kind = match frame.kind {
+                    FrameKind::Class => Some(ViolationKind::Class),
+                    FrameKind::Callback => Some(ViolationKind::Callback),
+                    FrameKind::Other => Some(ViolationKind::NotComponentOrHook),
+                    FrameKind::React(_) | FrameKind::MaybeComponent => {
+                        if frame.loops != 0 {
+                            Some(ViolationKind::Loop)
+                        } else if frame.conditionals != 0 {
+                            Some(ViolationKind::Conditional)
+                        } else if frame.first_return.is_some() {
+                            Some(ViolationKind::AfterEarlyReturn)
+                        } else {
+                            None
+                        }
+                    }
+                };
+                let early_return = match kind {
+                    Some(ViolationKind::AfterEarlyReturn) => frame.first_return,
+                    _ => None,
+                };
+                (kind, frame.name.clone(), early_return)
+            }
+        };
+        if let Some(kind) = kind {
+            self.report.violations.push(HookViolation {
+                hook,
+                kind,
+                function,
+                range,
+                early_return,
+            });
+        }
+    }
+}
+
+impl<'gc> Visitor<'gc> for Analyzer {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        if node.is_function_like() {
+            self.visit_function(gc, node, path);
+            return;
+        }
+
+        let branch = path.and_then(branch);
+        if let Some(frame) = self.stack.last_mut() {
+            match branch {
+                Some(Branch::Conditional) => frame.conditionals += 1,
+                Some(Branch::Loop) => frame.loops += 1,
+                None => {}
+            }
+        }
+
+        // The arguments are evaluated before the hook is called.
+        node.visit_children(gc, self);
+        match node {
+            Node::CallExpression(CallExpression { callee, .. }) => {
+                if let Some(hook) = hook_name(gc, callee) {
+                    self.visit_hook_call(hook, *node.range());
+                }
+            }
+            Node::ReturnStatement(ReturnStatement { argument, .. }) => {
+                if let Some(frame) = self.stack.last_mut() {
+                    if frame.first_return.is_none() {
+                        frame.first_return = Some(*node.range());
+                    }
+                    if argument.iter().any(|arg| is_jsx(arg)) {
+                        frame.returns_jsx = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(frame) = self.stack.last_mut() {
+            match branch {
+                Some(Branch::Conditional) => frame.conditionals -= 1,
+                Some(Branch::Loop) => frame.loops -= 1,
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn function<'gc>(gc: &'gc GCLock, name: &str, body: &[&'gc Node<'gc>]) -> &'gc Node<'gc> {
+        builder::FunctionDeclaration::build_template(
+            gc,
+            template::FunctionDeclaration {
+                metadata: Default::default(),
+                id: Some(builders::ident(gc, name)),
+                params: NodeList::new(gc),
+                body: builders::block(gc, body.iter().copied()),
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                generator: false,
+                is_async: false,
+            },
+        )
+    }
+
+    fn if_stmt<'gc>(
+        gc: &'gc GCLock,
+        test: &'gc Node<'gc>,
+        consequent: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builder::IfStatement::build_template(
+            gc,
+            template::IfStatement {
+                metadata: Default::default(),
+                test,
+                consequent,
+                alternate: None,
+            },
+        )
+    }
+
+    fn ret<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+        builder::ReturnStatement::build_template(
+            gc,
+            template::ReturnStatement {
+                metadata: Default::default(),
+                argument: Some(builders::null(gc)),
+            },
+        )
+    }
+
+    fn hook<'gc>(gc: &'gc GCLock, callee: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builders::expr_stmt(gc, builders::call(gc, callee, []))
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let a = builders::ident(&gc, "a");
+        let use_state = builders::ident(&gc, "useState");
+        let use_effect = builders::ident(&gc, "useEffect");
+
+        // function Good() { React.useState(); return null; }
+        let good = function(
+            &gc,
+            "Good",
+            &[
+                hook(
+                    &gc,
+                    builders::member(&gc, builders::ident(&gc, "React"), "useState"),
+                ),
+                ret(&gc),
+            ],
+        );
+        // function Comp() { if (a) { useState(); } return null; }
+        let comp = function(
+            &gc,
+            "Comp",
+            &[
+                if_stmt(&gc, a, builders::block(&gc, [hook(&gc, use_state)])),
+                ret(&gc),
+            ],
+        );
+        // function useFoo() { if (a) return null; useEffect(); }
+        let use_foo = function(
+            &gc,
+            "useFoo",
+            &[if_stmt(&gc, a, ret(&gc)), hook(&gc, use_effect)],
+        );
+        // function helper() { useState(); }
+        let helper = function(&gc, "helper", &[hook(&gc, use_state)]);
+        // function Plain() { return null; }
+        let plain = function(&gc, "Plain", &[ret(&gc)]);
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(
+                    &gc,
+                    [good, comp, use_foo, helper, plain, hook(&gc, use_state)],
+                ),
+            },
+        );
+
+        let report = analyze(&gc, program);
+        let functions = report
+            .functions
+            .iter()
+            .map(|f| (f.name.as_deref().unwrap(), f.kind, f.hook_calls))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            [
+                ("Good", ReactFunctionKind::Component, 1),
+                ("Comp", ReactFunctionKind::Component, 1),
+                ("useFoo", ReactFunctionKind::Hook, 1),
+            ]
+        );
+        let violations = report
+            .violations
+            .iter()
+            .map(|v| (v.hook.as_str(), v.kind, v.function.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            [
+                ("useState", ViolationKind::Conditional, Some("Comp")),
+                ("useEffect", ViolationKind::AfterEarlyReturn, Some("useFoo")),
+                (
+                    "useState",
+                    ViolationKind::NotComponentOrHook,
+                    Some("helper")
+                ),
+                ("useState", ViolationKind::TopLevel, None),
+            ]
+        );
+        assert!(report.violations[1].early_return.is_some());
+    }
+}
@@ -0,0 +1,304 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Fall-through and duplicate case analysis of `switch` statements.
+//!
+//! A case falls through when the end of its body is reachable, so execution continues
+//! with the body of the next case. Cases with an empty body, as in `case 1: case 2: ...`,
+//! share the body of the next case and are considered intentional.
+//! Whether the end of a body is reachable is determined by the [`completion`] analysis:
+//! it is assumed reachable unless the body always ends with a jump, like `break`, `return`
+//! or `throw`, or with a statement which never completes, like `while (true) {}`.
+//!
+//! Once comments are retained in the AST, a `// falls through` comment should also
+//! mark the fall-through as intentional.
+
+use super::completion;
+use crate::ast::*;
+
+/// Whether the fall-through is intended by the author.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallThroughKind {
+    /// The case has an empty body and shares the body of the next case.
+    Grouped,
+
+    /// The case has a body whose end is reachable, likely missing a `break`.
+    MissingBreak,
+}
+
+/// A case which falls through into the next case.
+#[derive(Debug, Clone)]
+pub struct FallThrough {
+    pub kind: FallThroughKind,
+
+    /// Location of the case which falls through.
+    pub range: SourceRange,
+
+    /// Location of the case it falls through into.
+    pub next: SourceRange,
+}
+
+/// A case whose test duplicates the test of an earlier case, so it can never match.
+#[derive(Debug, Clone)]
+pub struct DuplicateCase {
+    /// Location of the duplicate case.
+    pub range: SourceRange,
+
+    /// Location of the first case with the same test.
+    pub first: SourceRange,
+}
+
+/// Whether the body of the case `case` can continue into the next case.
+/// This is the condition for an edge from the end of the body of `case` to the start
+/// of the body of the next case in a control flow graph.
+pub fn case_falls_through<'gc>(gc: &'gc GCLock, case: &'gc Node<'gc>) -> bool {
+    completion::list(gc, &node_cast!(Node::SwitchCase, case).consequent).normal
+}
+
+/// Find every case which falls through in the AST rooted at `root`,
+/// in the order they appear in the source.
+pub fn find_fall_throughs<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<FallThrough> {
+    fall_throughs(gc, &find_switches(gc, root, false))
+}
+
+/// Find every case which falls through in `switches`.
+fn fall_throughs<'gc>(gc: &'gc GCLock, switches: &[&'gc Node<'gc>]) -> Vec<FallThrough> {
+    let mut result = vec![];
+    for &switch in switches {
+        let cases = node_cast!(Node::SwitchStatement, switch).cases;
+        for (case, next) in cases.iter().zip(cases.iter().skip(1)) {
+            let consequent = &node_cast!(Node::SwitchCase, case).consequent;
+            let kind = if consequent.is_empty() {
+                FallThroughKind::Grouped
+            } else if completion::list(gc, consequent).normal {
+                FallThroughKind::MissingBreak
+            } else {
+                continue;
+            };
+            result.push(FallThrough {
+                kind,
+                range: *case.range(),
+                next: *next.range(),
+            });
+        }
+    }
+    result
+}
+
+/// Find every case in the AST rooted at `root` whose test is a literal
+/// equal to the test of an earlier case of the same `switch`.
+pub fn find_duplicate_cases<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<DuplicateCase> {
+    duplicate_cases(gc, &find_switches(gc, root, false))
+}
+
+/// Find every duplicate case in `switches`.
+fn duplicate_cases<'gc>(gc: &'gc GCLock, switches: &[&'gc Node<'gc>]) -> Vec<DuplicateCase> {
+    let mut result = vec![];
+    for &switch in switches {
+        let cases = node_cast!(Node::SwitchStatement, switch).cases;
+        for (i, case) in cases.iter().enumerate() {
+            let test = match node_cast!(Node::SwitchCase, case).test {
+                Some(test) => test,
+                None => continue,
+            };
+            let first = cases.iter().take(i).find(|prev| {
+                let prev_test = node_cast!(Node::SwitchCase, prev).test;
+                matches!(prev_test, Some(prev_test) if same_literal(gc, prev_test, test))
+            });
+            if let Some(first) = first {
+                result.push(DuplicateCase {
+                    range: *case.range(),
+                    first: *first.range(),
+                });
+            }
+        }
+    }
+    result
+}
+
+/// Report a warning for every case which is likely missing a `break` and every
+/// duplicate case in the AST rooted at `root`.
+/// Synthesized `switch` statements are skipped, since their cases weren't written by the user.
+/// Returns the number of warnings.
+pub fn lint<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> usize {
+    let switches = find_switches(gc, root, true);
+    let mut count = 0;
+    for f in fall_throughs(gc, &switches) {
+        if f.kind == FallThroughKind::MissingBreak {
+            gc.sm()
+                .warning(f.range, "case falls through into the next case");
+            gc.sm().note(f.next, "next case is here");
+            count += 1;
+        }
+    }
+    for d in duplicate_cases(gc, &switches) {
+        gc.sm().warning(d.range, "duplicate case can never match");
+        gc.sm().note(d.first, "first case is here");
+        count += 1;
+    }
+    count
+}
+
+/// Whether `a` and `b` are literals with the same value.
+fn same_literal<'gc>(gc: &'gc GCLock, a: &'gc Node<'gc>, b: &'gc Node<'gc>) -> bool {
+    match (a, b) {
+        (Node::NullLiteral(_), Node::NullLiteral(_)) => true,
+        (
+            Node::BooleanLiteral(BooleanLiteral { value: a, .. }),
+            Node::BooleanLiteral(BooleanLiteral { value: b, .. }),
+        ) => a == b,
+        // `switch` compares with `===`, so NaN never matches and 0 matches -0.
+        (
+            Node::NumericLiteral(NumericLiteral { value: a, .. }),
+            Node::NumericLiteral(NumericLiteral { value: b, .. }),
+        ) => a == b,
+        (
+            Node::StringLiteral(StringLiteral { value: a, .. }),
+            Node::StringLiteral(StringLiteral { value: b, .. }),
+        ) => gc.str_u16(*a) == gc.str_u16(*b),
+        _ => false,
+    }
+}
+
+/// Find every `switch` statement in the AST rooted at `root`, in source order,
+/// except the synthesized ones if `skip_synthesized` is set.
+fn find_switches<'gc>(
+    gc: &'gc GCLock,
+    root: &'gc Node<'gc>,
+    skip_synthesized: bool,
+) -> Vec<&'gc Node<'gc>> {
+    let mut finder = SwitchFinder {
+        switches: vec![],
+        skip_synthesized,
+    };
+    root.visit(gc, &mut finder, None);
+    finder.switches
+}
+
+/// Collects every `switch` statement, in source order.
+struct SwitchFinder<'gc> {
+    switches: Vec<&'gc Node<'gc>>,
+    skip_synthesized: bool,
+}
+
+impl<'gc> Visitor<'gc> for SwitchFinder<'gc> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if let Node::SwitchStatement(_) = node {
+            if !(self.skip_synthesized && node.is_synthesized()) {
+                self.switches.push(node);
+            }
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case<'gc>(
+        gc: &'gc GCLock,
+        test: Option<&'gc Node<'gc>>,
+        consequent: &[&'gc Node<'gc>],
+    ) -> &'gc Node<'gc> {
+        builder::SwitchCase::build_template(
+            gc,
+            template::SwitchCase {
+                metadata: Default::default(),
+                test,
+                consequent: NodeList::from_iter(gc, consequent.iter().copied()),
+            },
+        )
+    }
+
+    fn break_stmt<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+        builder::BreakStatement::build_template(
+            gc,
+            template::BreakStatement {
+                metadata: Default::default(),
+                label: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_fall_through() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let x = builders::ident(&gc, "x");
+        let call = builders::expr_stmt(&gc, builders::call(&gc, x, []));
+        let forever = builder::WhileStatement::build_template(
+            &gc,
+            template::WhileStatement {
+                metadata: Default::default(),
+                body: call,
+                test: builders::boolean(&gc, true),
+            },
+        );
+        let if_break = builder::IfStatement::build_template(
+            &gc,
+            template::IfStatement {
+                metadata: Default::default(),
+                test: x,
+                consequent: break_stmt(&gc),
+                alternate: None,
+            },
+        );
+
+        // switch (x) {
+        //   case 1:               // Grouped.
+        //   case 2: x();          // MissingBreak.
+        //   case 3: if (x) break; // MissingBreak.
+        //   case 'a': break;
+        //   case 2: while (true) x();
+        //   default: x();
+        // }
+        let switch = builder::SwitchStatement::build_template(
+            &gc,
+            template::SwitchStatement {
+                metadata: Default::default(),
+                discriminant: x,
+                cases: NodeList::from_iter(
+                    &gc,
+                    [
+                        case(&gc, Some(builders::number(&gc, 1.0)), &[]),
+                        case(&gc, Some(builders::number(&gc, 2.0)), &[call]),
+                        case(&gc, Some(builders::number(&gc, 3.0)), &[if_break]),
+                        case(&gc, Some(builders::string(&gc, "a")), &[break_stmt(&gc)]),
+                        case(&gc, Some(builders::number(&gc, 2.0)), &[forever]),
+                        case(&gc, None, &[call]),
+                    ],
+                ),
+            },
+        );
+
+        let kinds = find_fall_throughs(&gc, switch)
+            .into_iter()
+            .map(|f| f.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                FallThroughKind::Grouped,
+                FallThroughKind::MissingBreak,
+                FallThroughKind::MissingBreak
+            ]
+        );
+        assert_eq!(find_duplicate_cases(&gc, switch).len(), 1);
+        // Built without ranges, so synthesized.
+        assert_eq!(lint(&gc, switch), 0);
+
+        let cases = node_cast!(Node::SwitchStatement, switch).cases;
+        assert!(case_falls_through(&gc, cases.head().unwrap()));
+        assert!(!case_falls_through(&gc, cases.iter().nth(3).unwrap()));
+
+        // The switch has a default and no `break` which exits it,
+        // but the last case completes normally.
+        assert!(completion::statement(&gc, switch).normal);
+        assert!(!completion::statement(&gc, forever).normal);
+    }
+}
@@ -0,0 +1,491 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Grammar coverage of a corpus of sources, used to find the syntax which a set of tests
+//! doesn't exercise.
+//!
+//! Every source is parsed, and the report lists how many times each node kind was produced
+//! and each [`Production`] was exercised, followed by the ones which never were.
+//! Productions cover the syntax which doesn't have a node kind of its own, like optional
+//! catch bindings or async arrow functions.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::ast;
+use crate::ast::find_top_level_await;
+use crate::ast::GCLock;
+use crate::ast::Node;
+use crate::ast::NodeVariant;
+use crate::ast::Visitor;
+use crate::hparser;
+
+/// Syntax which is recognized from the fields of a node or from its parent, rather than
+/// from its kind alone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Production {
+    /// `catch {}`
+    OptionalCatchBinding,
+    /// `for await (x of y)`
+    ForAwaitOf,
+    /// `async function f() {}`
+    AsyncFunction,
+    /// `function* f() {}`
+    Generator,
+    /// `async function* f() {}`
+    AsyncGenerator,
+    /// `async () => {}`
+    AsyncArrow,
+    /// `() => x`
+    ExpressionBodyArrow,
+    /// `a?.b`, `a?.[b]` and `a?.()`
+    OptionalChain,
+    /// `a ?? b`
+    NullishCoalescing,
+    /// `a ||= b`, `a &&= b` and `a ??= b`
+    LogicalAssignment,
+    /// `a ** b` and `a **= b`
+    Exponentiation,
+    /// `{...a} = b`
+    ObjectRest,
+    /// `({...a})`
+    ObjectSpread,
+    /// `({[a]: b})`
+    ComputedProperty,
+    /// `({a})`
+    ShorthandProperty,
+    /// `({a() {}})`
+    MethodProperty,
+    /// `({get a() {}})` and `class { set a(v) {} }`
+    Accessor,
+    /// `class { static a() {} }`
+    StaticMember,
+    /// `#a in b`
+    PrivateIn,
+    /// `new.target`
+    NewTarget,
+    /// `import.meta`
+    ImportMeta,
+    /// `continue label`
+    LabeledContinue,
+    /// `'use strict'`
+    UseStrict,
+    /// `import a from 'b' with {type: 'json'}`
+    ImportAttributes,
+    /// `` `a${b}` ``
+    TemplateSubstitution,
+    /// `await` at the top level of a module.
+    TopLevelAwait,
+}
+
+impl Production {
+    /// Every production, in the order in which they are reported.
+    pub const ALL: &'static [Production] = &[
+        Production::OptionalCatchBinding,
+        Production::ForAwaitOf,
+        Production::AsyncFunction,
+        Production::Generator,
+        Production::AsyncGenerator,
+        Production::AsyncArrow,
+        Production::ExpressionBodyArrow,
+        Production::OptionalChain,
+        Production::NullishCoalescing,
+        Production::LogicalAssignment,
+        Production::Exponentiation,
+        Production::ObjectRest,
+        Production::ObjectSpread,
+        Production::ComputedProperty,
+        Production::ShorthandProperty,
+        Production::MethodProperty,
+        Production::Accessor,
+        Production::StaticMember,
+        Production::PrivateIn,
+        Production::NewTarget,
+        Production::ImportMeta,
+        Production::LabeledContinue,
+        Production::UseStrict,
+        Production::ImportAttributes,
+        Production::TemplateSubstitution,
+        Production::TopLevelAwait,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Production::OptionalCatchBinding => "optional-catch-binding",
+            Production::ForAwaitOf => "for-await-of",
+            Production::AsyncFunction => "async-function",
+            Production::Generator => "generator",
+            Production::AsyncGenerator => "async-generator",
+            Production::AsyncArrow => "async-arrow",
+            Production::ExpressionBodyArrow => "expression-body-arrow",
+            Production::OptionalChain => "optional-chain",
+            Production::NullishCoalescing => "nullish-coalescing",
+            Production::LogicalAssignment => "logical-assignment",
+            Production::Exponentiation => "exponentiation",
+            Production::ObjectRest => "object-rest",
+            Production::ObjectSpread => "object-spread",
+            Production::ComputedProperty => "computed-property",
+            Production::ShorthandProperty => "shorthand-property",
+            Production::MethodProperty => "method-property",
+            Production::Accessor => "accessor",
+            Production::StaticMember => "static-member",
+            Production::PrivateIn => "private-in",
+            Production::NewTarget => "new-target",
+            Production::ImportMeta => "import-meta",
+            Production::LabeledContinue => "labeled-continue",
+            Production::UseStrict => "use-strict",
+            Production::ImportAttributes => "import-attributes",
+            Production::TemplateSubstitution => "template-substitution",
+            Production::TopLevelAwait => "top-level-await",
+        }
+    }
+}
+
+/// Node kinds and productions exercised by a corpus of sources.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Number of sources which were parsed successfully.
+    pub files: usize,
+    /// Path and error of every source which couldn't be parsed.
+    pub failures: Vec<(PathBuf, String)>,
+    pub node_kinds: HashMap<NodeVariant, usize>,
+    pub productions: HashMap<Production, usize>,
+}
+
+impl Coverage {
+    /// Record the node kinds and productions exercised by the AST `root`.
+    pub fn add_ast<'gc>(&mut self, gc: &'gc GCLock, root: &'gc Node<'gc>) {
+        self.files += 1;
+        root.visit(gc, &mut CoverageCollector { coverage: self }, None);
+        if let Node::Module(_) = root {
+            if find_top_level_await(gc, root).is_some() {
+                self.add_production(Production::TopLevelAwait);
+            }
+        }
+    }
+
+    /// Parse `source` with `flags` and record what its AST exercises.
+    /// Returns the parser error if it couldn't be parsed.
+    pub fn add_source(
+        &mut self,
+        flags: hparser::ParserFlags,
+        source: &str,
+    ) -> Result<(), hparser::ParseError> {
+        let mut ctx = ast::Context::new();
+        let ast = hparser::parse_with_flags(flags, source, &mut ctx)?;
+        let gc = GCLock::new(&mut ctx);
+        self.add_ast(&gc, ast.node(&gc));
+        Ok(())
+    }
+
+    /// Add every source under `path`, which may be a single file or a directory.
+    /// Files with a `.mjs` extension are parsed as modules, other `.js`, `.cjs` and `.jsx`
+    /// files with `flags`. Sources which fail to parse are recorded in `failures`.
+    pub fn add_path(&mut self, flags: hparser::ParserFlags, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                self.add_path(flags, &entry)?;
+            }
+            return Ok(());
+        }
+        let flags = match path.extension().and_then(OsStr::to_str) {
+            Some("mjs") => hparser::ParserFlags {
+                source_type: hparser::SourceType::Module,
+                ..flags
+            },
+            Some("js" | "cjs" | "jsx") => flags,
+            _ => return Ok(()),
+        };
+        let source = fs::read_to_string(path)?;
+        if let Err(e) = self.add_source(flags, &source) {
+            self.failures.push((path.to_path_buf(), e.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Number of nodes of kind `variant` produced so far.
+    pub fn kind_count(&self, variant: NodeVariant) -> usize {
+        self.node_kinds.get(&variant).copied().unwrap_or(0)
+    }
+
+    /// Number of times `production` was exercised so far.
+    pub fn production_count(&self, production: Production) -> usize {
+        self.productions.get(&production).copied().unwrap_or(0)
+    }
+
+    /// The node kinds which were never produced, in definition order.
+    pub fn missing_kinds(&self) -> Vec<NodeVariant> {
+        NodeVariant::KINDS
+            .iter()
+            .copied()
+            .filter(|&kind| self.kind_count(kind) == 0)
+            .collect()
+    }
+
+    /// The productions which were never exercised.
+    pub fn missing_productions(&self) -> Vec<Production> {
+        Production::ALL
+            .iter()
+            .copied()
+            .filter(|&production| self.production_count(production) == 0)
+            .collect()
+    }
+
+    fn add_production(&mut self, production: Production) {
+        *self.productions.entry(production).or_insert(0) += 1;
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, msg) in &self.failures {
+            writeln!(f, "FAIL {}: {}", path.display(), msg)?;
+        }
+
+        let kinds = NodeVariant::KINDS
+            .iter()
+            .filter(|&&kind| self.kind_count(kind) != 0)
+            .collect::<Vec<_>>();
+        writeln!(
+            f,
+            "Node kinds: {} of {}",
+            kinds.len(),
+            NodeVariant::KINDS.len()
+        )?;
+        for &&kind in &kinds {
+            writeln!(
+                f,
+                "  {:<40} {:>8}",
+                format!("{:?}", kind),
+                self.kind_count(kind)
+            )?;
+        }
+
+        let productions = Production::ALL
+            .iter()
+            .filter(|&&production| self.production_count(production) != 0)
+            .collect::<Vec<_>>();
+        writeln!(
+            f,
+            "Productions: {} of {}",
+            productions.len(),
+            Production::ALL.len()
+        )?;
+        for &&production in &productions {
+            writeln!(
+                f,
+                "  {:<40} {:>8}",
+                production.as_str(),
+                self.production_count(production)
+            )?;
+        }
+
+        writeln!(f, "Missing node kinds:")?;
+        for kind in self.missing_kinds() {
+            writeln!(f, "  {:?}", kind)?;
+        }
+        writeln!(f, "Missing productions:")?;
+        for production in self.missing_productions() {
+            writeln!(f, "  {}", production.as_str())?;
+        }
+        writeln!(
+            f,
+            "{} files parsed, {} failed",
+            self.files,
+            self.failures.len()
+        )
+    }
+}
+
+struct CoverageCollector<'a> {
+    coverage: &'a mut Coverage,
+}
+
+impl<'gc> Visitor<'gc> for CoverageCollector<'_> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<ast::Path<'gc>>) {
+        *self.coverage.node_kinds.entry(node.variant()).or_insert(0) += 1;
+        for production in productions(gc, node, path.map(|p| p.parent)) {
+            self.coverage.add_production(production);
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// The productions exercised by `node` itself, whose parent is `parent`.
+fn productions<'gc>(
+    gc: &'gc GCLock,
+    node: &'gc Node<'gc>,
+    parent: Option<&'gc Node<'gc>>,
+) -> Vec<Production> {
+    use ast::AssignmentExpressionOperator as AssignOp;
+
+    let mut result = vec![];
+    match node {
+        Node::CatchClause(ast::CatchClause { param: None, .. }) => {
+            result.push(Production::OptionalCatchBinding)
+        }
+        Node::ForOfStatement(for_of) if for_of.is_await => result.push(Production::ForAwaitOf),
+        Node::FunctionDeclaration(func) => {
+            result.extend(function_production(func.is_async, func.generator))
+        }
+        Node::FunctionExpression(func) => {
+            result.extend(function_production(func.is_async, func.generator))
+        }
+        Node::ArrowFunctionExpression(arrow) => {
+            if arrow.is_async {
+                result.push(Production::AsyncArrow);
+            }
+            if arrow.expression {
+                result.push(Production::ExpressionBodyArrow);
+            }
+        }
+        Node::OptionalMemberExpression(ast::OptionalMemberExpression {
+            optional: true, ..
+        }) => result.push(Production::OptionalChain),
+        Node::OptionalCallExpression(call) if call.optional => {
+            result.push(Production::OptionalChain)
+        }
+        Node::LogicalExpression(ast::LogicalExpression {
+            operator: ast::LogicalExpressionOperator::NullishCoalesce,
+            ..
+        }) => result.push(Production::NullishCoalescing),
+        Node::AssignmentExpression(ast::AssignmentExpression { operator, .. }) => match operator {
+            AssignOp::LogicalOrAssign
+            | AssignOp::LogicalAndAssign
+            | AssignOp::NullishCoalesceAssign => result.push(Production::LogicalAssignment),
+            AssignOp::ExpAssign => result.push(Production::Exponentiation),
+            _ => {}
+        },
+        Node::BinaryExpression(ast::BinaryExpression {
+            operator: ast::BinaryExpressionOperator::Exp,
+            ..
+        }) => result.push(Production::Exponentiation),
+        Node::BinaryExpression(ast::BinaryExpression {
+            operator: ast::BinaryExpressionOperator::In,
+            left: Node::PrivateName(_),
+            ..
+        }) => result.push(Production::PrivateIn),
+        Node::RestElement(_) if matches!(parent, Some(Node::ObjectPattern(_))) => {
+            result.push(Production::ObjectRest)
+        }
+        Node::SpreadElement(_) if matches!(parent, Some(Node::ObjectExpression(_))) => {
+            result.push(Production::ObjectSpread)
+        }
+        Node::Property(ast::Property {
+            kind,
+            computed,
+            method,
+            shorthand,
+            ..
+        }) => {
+            if *computed {
+                result.push(Production::ComputedProperty);
+            }
+            if *shorthand {
+                result.push(Production::ShorthandProperty);
+            }
+            if *method {
+                result.push(Production::MethodProperty);
+            }
+            if *kind != ast::PropertyKind::Init {
+                result.push(Production::Accessor);
+            }
+        }
+        Node::MethodDefinition(ast::MethodDefinition {
+            kind, is_static, ..
+        }) => {
+            if matches!(
+                kind,
+                ast::MethodDefinitionKind::Get | ast::MethodDefinitionKind::Set
+            ) {
+                result.push(Production::Accessor);
+            }
+            if *is_static {
+                result.push(Production::StaticMember);
+            }
+        }
+        Node::ClassProperty(prop) if prop.is_static => result.push(Production::StaticMember),
+        Node::ClassPrivateProperty(prop) if prop.is_static => result.push(Production::StaticMember),
+        Node::ClassAccessorProperty(ast::ClassAccessorProperty {
+            is_static: true, ..
+        }) => result.push(Production::StaticMember),
+        Node::MetaProperty(ast::MetaProperty {
+            meta: Node::Identifier(meta),
+            property: Node::Identifier(property),
+            ..
+        }) => match (gc.str(meta.name), gc.str(property.name)) {
+            ("new", "target") => result.push(Production::NewTarget),
+            ("import", "meta") => result.push(Production::ImportMeta),
+            _ => {}
+        },
+        Node::ContinueStatement(ast::ContinueStatement { label: Some(_), .. }) => {
+            result.push(Production::LabeledContinue)
+        }
+        Node::ExpressionStatement(ast::ExpressionStatement {
+            directive: Some(directive),
+            ..
+        }) if gc.str_u16(*directive) == "use strict".encode_utf16().collect::<Vec<_>>() => {
+            result.push(Production::UseStrict)
+        }
+        Node::ImportDeclaration(import) if matches!(import.assertions, Some(assertions) if !assertions.is_empty()) => {
+            result.push(Production::ImportAttributes)
+        }
+        Node::TemplateLiteral(ast::TemplateLiteral { expressions, .. })
+            if !expressions.is_empty() =>
+        {
+            result.push(Production::TemplateSubstitution)
+        }
+        _ => {}
+    }
+    result
+}
+
+/// The production of a function which is `async` and/or a generator, if any.
+fn function_production(is_async: bool, generator: bool) -> Option<Production> {
+    match (is_async, generator) {
+        (true, false) => Some(Production::AsyncFunction),
+        (false, true) => Some(Production::Generator),
+        (true, true) => Some(Production::AsyncGenerator),
+        (false, false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing() {
+        let coverage = Coverage::default();
+        assert_eq!(coverage.missing_kinds().len(), NodeVariant::KINDS.len());
+        assert_eq!(coverage.missing_productions(), Production::ALL);
+
+        let mut coverage = Coverage::default();
+        coverage.node_kinds.insert(NodeVariant::Program, 2);
+        coverage.add_production(Production::OptionalCatchBinding);
+        assert_eq!(coverage.kind_count(NodeVariant::Program), 2);
+        assert!(!coverage.missing_kinds().contains(&NodeVariant::Program));
+        assert!(coverage.missing_kinds().contains(&NodeVariant::Module));
+        assert_eq!(
+            coverage.production_count(Production::OptionalCatchBinding),
+            1
+        );
+        assert_eq!(
+            coverage.missing_productions().len(),
+            Production::ALL.len() - 1
+        );
+    }
+}
@@ -0,0 +1,452 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! API documentation of modules, generated from their exports (see
+//! [`crate::analysis::exports`]) and the JSDoc comments of the exported declarations
+//! (see [`crate::jsdoc`]), as Markdown by [`write_markdown`] or as JSON by [`write_json`].
+//!
+//! The comment of a name exported by `export { name }` or `export default name` is the
+//! comment of its declaration. The description of the module is the first JSDoc comment
+//! with a `@file`, `@fileoverview` or `@module` tag, and such comments don't document
+//! declarations.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use juno_support::json::JSONEmitter;
+use juno_support::json::Pretty;
+
+use crate::analysis::exports;
+use crate::analysis::exports::Export;
+use crate::analysis::exports::ExportedKind;
+use crate::ast::*;
+use crate::jsdoc;
+use crate::jsdoc::JSDoc;
+
+/// Tags of the comment describing a whole module.
+const MODULE_TAGS: &[&str] = &["file", "fileoverview", "module"];
+
+/// Whether `doc` describes a whole module rather than a declaration.
+fn is_module_doc(doc: &JSDoc) -> bool {
+    doc.tags
+        .iter()
+        .any(|tag| MODULE_TAGS.contains(&tag.name.as_str()))
+}
+
+/// The description of the module in `doc`, which is usually the text of the tag, as in
+/// `@fileoverview Utilities.`, but may precede it. The text of `@module` is a name.
+fn module_description(doc: &JSDoc) -> String {
+    let mut parts = vec![doc.description.as_str()];
+    for tag in &doc.tags {
+        if tag.name == "file" || tag.name == "fileoverview" {
+            parts.push(&tag.text);
+        }
+    }
+    parts.retain(|part| !part.is_empty());
+    parts.join("\n\n")
+}
+
+/// Documentation of an exported name.
+#[derive(Debug, Clone)]
+pub struct ItemDoc {
+    pub export: Export,
+
+    /// The JSDoc comment of the declaration, if any.
+    pub doc: Option<JSDoc>,
+}
+
+/// Documentation of a module.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleDoc {
+    /// Name of the module, usually its path.
+    pub name: String,
+
+    /// Description of the module, empty if there is none.
+    pub description: String,
+
+    /// The exported names, in the order they appear in the source.
+    pub items: Vec<ItemDoc>,
+
+    /// Modules all of whose names are re-exported by `export * from`.
+    pub star_exports: Vec<String>,
+}
+
+/// Document the module `root`, named `name`.
+pub fn document<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>, name: &str) -> ModuleDoc {
+    let file = root.range().file;
+    let comments = jsdoc::jsdoc_comments(gc.sm().source_buffer(file));
+    let description = comments
+        .iter()
+        .map(|(_, doc)| doc)
+        .find(|doc| is_module_doc(doc))
+        .map_or_else(String::new, module_description);
+    let docs = jsdoc::attach_comments(gc, root, file, comments);
+    let doc_of = |node: &'gc Node<'gc>| docs.get(node).filter(|doc| !is_module_doc(doc));
+
+    let body = match root {
+        Node::Module(Module { body, .. }) | Node::Program(Program { body, .. }) => body,
+        _ => {
+            return ModuleDoc {
+                name: name.to_string(),
+                description,
+                ..Default::default()
+            };
+        }
+    };
+
+    // The comments of the top-level declarations, by declared name.
+    let mut local_docs: HashMap<String, &JSDoc> = HashMap::new();
+    for stmt in body.iter() {
+        let decl = match stmt {
+            Node::ExportNamedDeclaration(export) => export.declaration.unwrap_or(stmt),
+            _ => stmt,
+        };
+        if let Some(doc) = doc_of(decl) {
+            for (name, _, _) in exports::declared_names(gc, decl) {
+                local_docs.insert(name, doc);
+            }
+        }
+    }
+
+    // The comments of the exports, by the start of their range.
+    let key = |node: &Node| {
+        let start = node.range().start;
+        (start.line, start.col)
+    };
+    let mut export_docs: HashMap<(u32, u32), &JSDoc> = HashMap::new();
+    for stmt in body.iter() {
+        match stmt {
+            Node::ExportNamedDeclaration(export) => match (export.declaration, export.source) {
+                (Some(decl), _) => {
+                    if let Some(doc) = doc_of(decl) {
+                        export_docs.insert(key(decl), doc);
+                    }
+                }
+                (None, None) => {
+                    for specifier in export.specifiers.iter() {
+                        if let Node::ExportSpecifier(ExportSpecifier {
+                            local: Node::Identifier(Identifier { name, .. }),
+                            ..
+                        }) = specifier
+                        {
+                            if let Some(doc) =
+                                doc_of(specifier).or_else(|| local_docs.get(gc.str(*name)).copied())
+                            {
+                                export_docs.insert(key(specifier), doc);
+                            }
+                        }
+                    }
+                }
+                (None, Some(_)) => {}
+            },
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+                let local = match declaration {
+                    Node::Identifier(Identifier { name, .. }) => {
+                        local_docs.get(gc.str(*name)).copied()
+                    }
+                    _ => None,
+                };
+                if let Some(doc) = doc_of(stmt).or(local) {
+                    export_docs.insert(key(stmt), doc);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let interface = exports::analyze(gc, root);
+    let items = interface
+        .exports
+        .into_iter()
+        .map(|export| {
+            let start = export.range.start;
+            ItemDoc {
+                doc: export_docs
+                    .get(&(start.line, start.col))
+                    .map(|&doc| doc.clone()),
+                export,
+            }
+        })
+        .collect();
+    ModuleDoc {
+        name: name.to_string(),
+        description,
+        items,
+        star_exports: interface.star_exports,
+    }
+}
+
+/// Write `modules` as a JSON document:
+/// ```json
+/// {
+///   "modules": [{
+///     "name": "a.js",
+///     "description": "...",
+///     "exports": [{
+///       "name": "f", "kind": "function", "arity": 1, "line": 3,
+///       "description": "...", "deprecated": "...",
+///       "params": [{"name": "a", "type": "number", "optional": false, "description": "..."}],
+///       "returns": {"type": "string", "description": "..."},
+///       "tags": [{"name": "see", "text": "g"}]
+///     }],
+///     "starExports": ["./b"]
+///   }]
+/// }
+/// ```
+/// Keys without a value, like the `type` of an untyped parameter, are omitted.
+pub fn write_json<W: Write>(modules: &[ModuleDoc], out: W, pretty: Pretty) -> io::Result<()> {
+    let mut emitter = JSONEmitter::new(out, pretty);
+    let emit_opt = |emitter: &mut JSONEmitter<W>, key: &str, value: Option<&str>| {
+        if let Some(value) = value {
+            emitter.emit_key(key);
+            emitter.emit_string(value);
+        }
+    };
+    emitter.open_dict();
+    emitter.emit_key("modules");
+    emitter.open_array();
+    for module in modules {
+        emitter.open_dict();
+        emitter.emit_key("name");
+        emitter.emit_string(&module.name);
+        emitter.emit_key("description");
+        emitter.emit_string(&module.description);
+        emitter.emit_key("exports");
+        emitter.open_array();
+        for item in &module.items {
+            let export = &item.export;
+            emitter.open_dict();
+            emitter.emit_key("name");
+            emitter.emit_string(&export.name);
+            emitter.emit_key("kind");
+            emitter.emit_string(export.kind.as_str());
+            emit_opt(&mut emitter, "source", export.source.as_deref());
+            if let Some(arity) = export.arity {
+                emitter.emit_key("arity");
+                emitter.emit_number(arity as f64);
+            }
+            emitter.emit_key("line");
+            emitter.emit_number(export.range.start.line as f64);
+            if let Some(doc) = &item.doc {
+                emitter.emit_key("description");
+                emitter.emit_string(&doc.description);
+                emit_opt(&mut emitter, "deprecated", doc.deprecated.as_deref());
+                emit_opt(&mut emitter, "type", doc.type_expr.as_deref());
+                emitter.emit_key("params");
+                emitter.open_array();
+                for param in &doc.params {
+                    emitter.open_dict();
+                    emitter.emit_key("name");
+                    emitter.emit_string(&param.name);
+                    emit_opt(&mut emitter, "type", param.type_expr.as_deref());
+                    emitter.emit_key("optional");
+                    emitter.emit_bool(param.optional);
+                    emit_opt(&mut emitter, "default", param.default.as_deref());
+                    emitter.emit_key("description");
+                    emitter.emit_string(&param.description);
+                    emitter.close_dict();
+                }
+                emitter.close_array();
+                if let Some(returns) = &doc.returns {
+                    emitter.emit_key("returns");
+                    emitter.open_dict();
+                    emit_opt(&mut emitter, "type", returns.type_expr.as_deref());
+                    emitter.emit_key("description");
+                    emitter.emit_string(&returns.description);
+                    emitter.close_dict();
+                }
+                emitter.emit_key("tags");
+                emitter.open_array();
+                for tag in &doc.tags {
+                    emitter.open_dict();
+                    emitter.emit_key("name");
+                    emitter.emit_string(&tag.name);
+                    emitter.emit_key("text");
+                    emitter.emit_string(&tag.text);
+                    emitter.close_dict();
+                }
+                emitter.close_array();
+            }
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.emit_key("starExports");
+        emitter.open_array();
+        for source in &module.star_exports {
+            emitter.emit_string(source);
+        }
+        emitter.close_array();
+        emitter.close_dict();
+    }
+    emitter.close_array();
+    emitter.close_dict();
+    emitter.end()
+}
+
+/// `text` on a single line, for a cell of a Markdown table.
+fn table_cell(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+}
+
+/// Write `modules` as Markdown, with a section per module and a subsection per export.
+pub fn write_markdown(modules: &[ModuleDoc], out: &mut dyn Write) -> io::Result<()> {
+    for (i, module) in modules.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "# {}", module.name)?;
+        if !module.description.is_empty() {
+            writeln!(out, "\n{}", module.description)?;
+        }
+        for item in &module.items {
+            write_item_markdown(item, out)?;
+        }
+        if !module.star_exports.is_empty() {
+            writeln!(out, "\nAlso exports everything from:\n")?;
+            for source in &module.star_exports {
+                writeln!(out, "- `{}`", source)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_item_markdown(item: &ItemDoc, out: &mut dyn Write) -> io::Result<()> {
+    let export = &item.export;
+    writeln!(out, "\n## `{}`\n", export.name)?;
+    match (&export.source, export.kind) {
+        (Some(source), ExportedKind::Namespace) => {
+            writeln!(out, "*namespace*, all the exports of `{}`.", source)?
+        }
+        (Some(source), _) => writeln!(out, "*{}* from `{}`.", export.kind.as_str(), source)?,
+        (None, _) => writeln!(out, "*{}*", export.kind.as_str())?,
+    }
+    let doc = match &item.doc {
+        Some(doc) => doc,
+        None => return Ok(()),
+    };
+    if let Some(deprecated) = &doc.deprecated {
+        if deprecated.is_empty() {
+            writeln!(out, "\n**Deprecated.**")?;
+        } else {
+            writeln!(out, "\n**Deprecated:** {}", deprecated)?;
+        }
+    }
+    if !doc.description.is_empty() {
+        writeln!(out, "\n{}", doc.description)?;
+    }
+    if let Some(type_expr) = &doc.type_expr {
+        writeln!(out, "\n**Type:** `{}`", type_expr)?;
+    }
+    if !doc.params.is_empty() {
+        writeln!(out, "\n| Parameter | Type | Description |")?;
+        writeln!(out, "| --- | --- | --- |")?;
+        for param in &doc.params {
+            let name = match (&param.default, param.optional) {
+                (Some(default), _) => format!("[{}={}]", param.name, default),
+                (None, true) => format!("[{}]", param.name),
+                (None, false) => param.name.clone(),
+            };
+            writeln!(
+                out,
+                "| `{}` | {} | {} |",
+                table_cell(&name),
+                param
+                    .type_expr
+                    .as_deref()
+                    .map_or_else(String::new, |t| format!("`{}`", table_cell(t))),
+                table_cell(&param.description)
+            )?;
+        }
+    }
+    if let Some(returns) = &doc.returns {
+        write!(out, "\n**Returns**")?;
+        if let Some(type_expr) = &returns.type_expr {
+            write!(out, " `{}`", type_expr)?;
+        }
+        if returns.description.is_empty() {
+            writeln!(out)?;
+        } else {
+            writeln!(out, ": {}", returns.description)?;
+        }
+    }
+    for tag in &doc.tags {
+        writeln!(out, "\n**@{}** {}", tag.name, tag.text)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsdoc::ParamTag;
+    use crate::jsdoc::ReturnsTag;
+
+    #[test]
+    fn test_write() {
+        let export = |name: &str, kind, arity| Export {
+            name: name.to_string(),
+            kind,
+            source: None,
+            arity,
+            range: SourceRange::from_loc(SourceId(0), SourceLoc { line: 2, col: 1 }),
+        };
+        let modules = [ModuleDoc {
+            name: "a.js".to_string(),
+            description: "Arithmetic.".to_string(),
+            items: vec![
+                ItemDoc {
+                    export: export("add", ExportedKind::Function, Some(2)),
+                    doc: Some(JSDoc {
+                        description: "Adds numbers.".to_string(),
+                        params: vec![ParamTag {
+                            name: "a".to_string(),
+                            type_expr: Some("number|string".to_string()),
+                            description: "The first\nnumber.".to_string(),
+                            ..Default::default()
+                        }],
+                        returns: Some(ReturnsTag {
+                            type_expr: Some("number".to_string()),
+                            description: String::new(),
+                        }),
+                        deprecated: Some(String::new()),
+                        ..Default::default()
+                    }),
+                },
+                ItemDoc {
+                    export: export("x", ExportedKind::Const, None),
+                    doc: None,
+                },
+            ],
+            star_exports: vec!["./b".to_string()],
+        }];
+
+        let mut out = vec![];
+        write_markdown(&modules, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# a.js\n\nArithmetic.\n\n\
+            ## `add`\n\n*function*\n\n**Deprecated.**\n\nAdds numbers.\n\n\
+            | Parameter | Type | Description |\n| --- | --- | --- |\n\
+            | `a` | `number\\|string` | The first number. |\n\n\
+            **Returns** `number`\n\n\
+            ## `x`\n\n*const*\n\n\
+            Also exports everything from:\n\n- `./b`\n"
+        );
+
+        let mut out = vec![];
+        write_json(&modules, &mut out, Pretty::No).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.starts_with(r#"{"modules":[{"name":"a.js","description":"Arithmetic.","#));
+        assert!(json.contains(r#""params":[{"name":"a","type":"number|string","optional":false,"#));
+        assert!(json.contains(r#"{"name":"x","kind":"const","line":2}"#));
+    }
+}
@@ -0,0 +1,739 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Constant evaluation of a side-effect-free subset of JS.
+//!
+//! Supports literals, arithmetic and string operators, array and object literals,
+//! template literals, conditionals and user supplied defines.
+//! Anything else (calls, assignments, getters, etc.) is not constant and makes the evaluation
+//! return `None`. This does not need the Hermes VM, so it can be used by passes and tests.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use juno_support::convert::number_to_string;
+
+use crate::ast::*;
+
+/// A JS value produced by constant evaluation.
+/// Arrays and objects are immutable, since the evaluated subset cannot modify them.
+#[derive(Debug, Clone)]
+pub enum JsValue {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(Rc<[u16]>),
+    Array(Rc<Vec<JsValue>>),
+    /// Properties in insertion order, keys are unique.
+    Object(Rc<Vec<(Rc<[u16]>, JsValue)>>),
+}
+
+impl JsValue {
+    /// Create a string value from a Rust string.
+    pub fn string(s: &str) -> JsValue {
+        JsValue::String(s.encode_utf16().collect::<Vec<_>>().into())
+    }
+
+    /// Return the result of the `typeof` operator.
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            JsValue::Undefined => "undefined",
+            JsValue::Null | JsValue::Array(_) | JsValue::Object(_) => "object",
+            JsValue::Boolean(_) => "boolean",
+            JsValue::Number(_) => "number",
+            JsValue::String(_) => "string",
+        }
+    }
+
+    fn is_primitive(&self) -> bool {
+        !matches!(self, JsValue::Array(_) | JsValue::Object(_))
+    }
+
+    /// ES2022 7.1.2 ToBoolean.
+    pub fn to_boolean(&self) -> bool {
+        match self {
+            JsValue::Undefined | JsValue::Null => false,
+            JsValue::Boolean(b) => *b,
+            JsValue::Number(n) => !(*n == 0.0 || n.is_nan()),
+            JsValue::String(s) => !s.is_empty(),
+            JsValue::Array(_) | JsValue::Object(_) => true,
+        }
+    }
+
+    /// ES2022 7.1.4 ToNumber.
+    pub fn to_number(&self) -> f64 {
+        match self {
+            JsValue::Undefined => f64::NAN,
+            JsValue::Null => 0.0,
+            JsValue::Boolean(b) => {
+                if *b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            JsValue::Number(n) => *n,
+            JsValue::String(s) => string_to_number(s),
+            JsValue::Array(_) | JsValue::Object(_) => string_to_number(&self.to_js_string()),
+        }
+    }
+
+    /// ES2022 7.1.17 ToString.
+    /// Objects use the default `toString`, since no prototypes can be modified.
+    pub fn to_js_string(&self) -> Rc<[u16]> {
+        match self {
+            JsValue::String(s) => s.clone(),
+            JsValue::Array(elements) => {
+                let mut result = Vec::new();
+                for (i, elem) in elements.iter().enumerate() {
+                    if i != 0 {
+                        result.push(',' as u16);
+                    }
+                    if !matches!(elem, JsValue::Undefined | JsValue::Null) {
+                        result.extend_from_slice(&elem.to_js_string());
+                    }
+                }
+                result.into()
+            }
+            _ => JsValue::string(&self.to_string()).to_js_string(),
+        }
+    }
+
+    /// ES2022 7.2.15 IsStrictlyEqual.
+    pub fn strict_equals(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Undefined, JsValue::Undefined) | (JsValue::Null, JsValue::Null) => true,
+            (JsValue::Boolean(a), JsValue::Boolean(b)) => a == b,
+            (JsValue::Number(a), JsValue::Number(b)) => a == b,
+            (JsValue::String(a), JsValue::String(b)) => a == b,
+            (JsValue::Array(a), JsValue::Array(b)) => Rc::ptr_eq(a, b),
+            (JsValue::Object(a), JsValue::Object(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// ES2022 7.2.14 IsLooselyEqual.
+    pub fn loose_equals(&self, other: &JsValue) -> bool {
+        match (self, other) {
+            (JsValue::Undefined | JsValue::Null, JsValue::Undefined | JsValue::Null) => true,
+            (JsValue::Undefined | JsValue::Null, _) | (_, JsValue::Undefined | JsValue::Null) => {
+                false
+            }
+            _ if self.type_of() == other.type_of() => self.strict_equals(other),
+            _ if !self.is_primitive() || !other.is_primitive() => self
+                .to_primitive_value()
+                .loose_equals(&other.to_primitive_value()),
+            _ => self.to_number() == other.to_number(),
+        }
+    }
+
+    /// ES2022 7.1.1 ToPrimitive.
+    /// Objects always convert to strings in the evaluated subset.
+    fn to_primitive_value(&self) -> JsValue {
+        if self.is_primitive() {
+            self.clone()
+        } else {
+            JsValue::String(self.to_js_string())
+        }
+    }
+
+    /// Look up the own or well-known property `key` of the value.
+    /// Returns `None` if the lookup can't be resolved statically.
+    fn get_property(&self, key: &JsValue) -> Option<JsValue> {
+        let key = key.to_js_string();
+        let is_length = *key == *JsValue::string("length").to_js_string();
+        match self {
+            JsValue::String(s) => {
+                if is_length {
+                    Some(JsValue::Number(s.len() as f64))
+                } else {
+                    array_index(&key).map(|i| match s.get(i) {
+                        Some(c) => JsValue::String(vec![*c].into()),
+                        None => JsValue::Undefined,
+                    })
+                }
+            }
+            JsValue::Array(elements) => {
+                if is_length {
+                    Some(JsValue::Number(elements.len() as f64))
+                } else {
+                    array_index(&key)
+                        .map(|i| elements.get(i).cloned().unwrap_or(JsValue::Undefined))
+                }
+            }
+            // Missing properties could still be found in the prototype chain.
+            JsValue::Object(props) => props
+                .iter()
+                .find(|(k, _)| **k == *key)
+                .map(|(_, v)| v.clone()),
+            _ => None,
+        }
+    }
+
+    /// Create an AST node that evaluates to this value.
+    /// Returns `None` for values that have no literal representation, such as `NaN`.
+    pub fn to_node<'gc>(&self, gc: &'gc GCLock, range: SourceRange) -> Option<&'gc Node<'gc>> {
+        Some(match self {
+            JsValue::Undefined => builder::UnaryExpression::build_template(
+                gc,
+                template::UnaryExpression {
+                    metadata: range.into(),
+                    operator: UnaryExpressionOperator::Void,
+                    argument: JsValue::Number(0.0).to_node(gc, range)?,
+                    prefix: true,
+                },
+            ),
+            JsValue::Null => builder::NullLiteral::build_template(
+                gc,
+                template::NullLiteral {
+                    metadata: range.into(),
+                },
+            ),
+            JsValue::Boolean(value) => builder::BooleanLiteral::build_template(
+                gc,
+                template::BooleanLiteral {
+                    metadata: range.into(),
+                    value: *value,
+                },
+            ),
+            JsValue::Number(value) => {
+                if !value.is_finite() {
+                    return None;
+                }
+                if value.is_sign_negative() {
+                    builder::UnaryExpression::build_template(
+                        gc,
+                        template::UnaryExpression {
+                            metadata: range.into(),
+                            operator: UnaryExpressionOperator::Minus,
+                            argument: JsValue::Number(-value).to_node(gc, range)?,
+                            prefix: true,
+                        },
+                    )
+                } else {
+                    builder::NumericLiteral::build_template(
+                        gc,
+                        template::NumericLiteral {
+                            metadata: range.into(),
+                            value: *value,
+                        },
+                    )
+                }
+            }
+            JsValue::String(s) => builder::StringLiteral::build_template(
+                gc,
+                template::StringLiteral {
+                    metadata: range.into(),
+                    value: gc.atom_u16(s.to_vec()),
+                },
+            ),
+            JsValue::Array(elements) => {
+                let mut nodes = Vec::with_capacity(elements.len());
+                for elem in elements.iter() {
+                    nodes.push(elem.to_node(gc, range)?);
+                }
+                builder::ArrayExpression::build_template(
+                    gc,
+                    template::ArrayExpression {
+                        metadata: range.into(),
+                        elements: NodeList::from_iter(gc, nodes),
+                        trailing_comma: false,
+                    },
+                )
+            }
+            JsValue::Object(props) => {
+                let mut nodes = Vec::with_capacity(props.len());
+                for (key, value) in props.iter() {
+                    nodes.push(builder::Property::build_template(
+                        gc,
+                        template::Property {
+                            metadata: range.into(),
+                            key: JsValue::String(key.clone()).to_node(gc, range)?,
+                            value: value.to_node(gc, range)?,
+                            kind: PropertyKind::Init,
+                            computed: false,
+                            method: false,
+                            shorthand: false,
+                        },
+                    ));
+                }
+                builder::ObjectExpression::build_template(
+                    gc,
+                    template::ObjectExpression {
+                        metadata: range.into(),
+                        properties: NodeList::from_iter(gc, nodes),
+                    },
+                )
+            }
+        })
+    }
+}
+
+impl fmt::Display for JsValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsValue::Undefined => write!(f, "undefined"),
+            JsValue::Null => write!(f, "null"),
+            JsValue::Boolean(b) => write!(f, "{}", b),
+            JsValue::Number(n) => write!(f, "{}", number_to_string(*n)),
+            JsValue::String(s) => write!(f, "{}", String::from_utf16_lossy(s)),
+            JsValue::Array(_) => write!(f, "{}", String::from_utf16_lossy(&self.to_js_string())),
+            JsValue::Object(_) => write!(f, "[object Object]"),
+        }
+    }
+}
+
+/// Return the array index represented by the canonical numeric string `key`.
+fn array_index(key: &[u16]) -> Option<usize> {
+    let s = String::from_utf16(key).ok()?;
+    let index = s.parse::<u32>().ok()?;
+    if index.to_string() == s && index != u32::MAX {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+/// ES2022 7.1.4.1.1 StringToNumber.
+fn string_to_number(s: &[u16]) -> f64 {
+    let s = String::from_utf16_lossy(s);
+    let s = s.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+    if s.is_empty() {
+        return 0.0;
+    }
+    let radix = |prefix: &[&str], radix| {
+        prefix.iter().find_map(|p| s.strip_prefix(p)).map(|digits| {
+            match u64::from_str_radix(digits, radix) {
+                Ok(v) if !digits.starts_with('+') => v as f64,
+                _ => f64::NAN,
+            }
+        })
+    };
+    if let Some(n) = radix(&["0x", "0X"], 16)
+        .or_else(|| radix(&["0o", "0O"], 8))
+        .or_else(|| radix(&["0b", "0B"], 2))
+    {
+        return n;
+    }
+    let (sign, unsigned) = match s.as_bytes()[0] {
+        b'-' => (-1.0, &s[1..]),
+        b'+' => (1.0, &s[1..]),
+        _ => (1.0, s),
+    };
+    if unsigned == "Infinity" {
+        return sign * f64::INFINITY;
+    }
+    // Rust accepts some spellings that JS doesn't, so only allow the JS characters.
+    if unsigned.is_empty()
+        || !unsigned
+            .bytes()
+            .all(|c| c.is_ascii_digit() || matches!(c, b'.' | b'e' | b'E' | b'+' | b'-'))
+    {
+        return f64::NAN;
+    }
+    match unsigned.parse::<f64>() {
+        Ok(n) => sign * n,
+        Err(_) => f64::NAN,
+    }
+}
+
+/// ES2022 7.1.6 ToInt32.
+fn to_int32(n: f64) -> i32 {
+    to_uint32(n) as i32
+}
+
+/// ES2022 7.1.7 ToUint32.
+fn to_uint32(n: f64) -> u32 {
+    if !n.is_finite() {
+        return 0;
+    }
+    n.trunc().rem_euclid(4294967296.0) as u32
+}
+
+/// Evaluates constant expressions.
+#[derive(Debug, Default)]
+pub struct ConstEvaluator {
+    /// Values to substitute for global names and dotted member chains (e.g. `process.env.X`).
+    defines: HashMap<String, JsValue>,
+}
+
+impl ConstEvaluator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Substitute `value` for the identifier or dotted member chain `name`.
+    /// The evaluator doesn't know about scopes: use [`ConstEvaluator::eval_in`] to skip
+    /// the places where `name` is shadowed.
+    pub fn define<S: Into<String>>(&mut self, name: S, value: JsValue) {
+        self.defines.insert(name.into(), value);
+    }
+
+    /// Evaluate `node`, returning `None` if it isn't a constant expression.
+    /// Every identifier is assumed to refer to a global, see [`ConstEvaluator::eval_in`].
+    pub fn eval<'gc>(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> Option<JsValue> {
+        self.eval_in(gc, node, &|_| true)
+    }
+
+    /// Evaluate `node`, returning `None` if it isn't a constant expression.
+    /// `is_global` is called with the identifiers which would be evaluated as `undefined`,
+    /// `NaN`, `Infinity` or a define, and must return whether they refer to the global,
+    /// so that local bindings shadowing those names aren't evaluated.
+    pub fn eval_in<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        is_global: &dyn Fn(&'gc Node<'gc>) -> bool,
+    ) -> Option<JsValue> {
+        if let Some((name, root)) = dotted_name(gc, node) {
+            if let Some(value) = self.defines.get(&name) {
+                if is_global(root) {
+                    return Some(value.clone());
+                }
+            }
+        }
+
+        match node {
+            Node::NullLiteral(_) => Some(JsValue::Null),
+            Node::BooleanLiteral(BooleanLiteral { value, .. }) => Some(JsValue::Boolean(*value)),
+            Node::NumericLiteral(NumericLiteral { value, .. }) => Some(JsValue::Number(*value)),
+            Node::StringLiteral(StringLiteral { value, .. }) => {
+                Some(JsValue::String(gc.str_u16(*value).into()))
+            }
+            Node::Identifier(Identifier { name, .. }) => {
+                let value = match gc.str(*name) {
+                    "undefined" => JsValue::Undefined,
+                    "NaN" => JsValue::Number(f64::NAN),
+                    "Infinity" => JsValue::Number(f64::INFINITY),
+                    _ => return None,
+                };
+                is_global(node).then_some(value)
+            }
+            Node::UnaryExpression(UnaryExpression {
+                operator, argument, ..
+            }) => {
+                let arg = self.eval_in(gc, argument, is_global)?;
+                Some(match operator {
+                    UnaryExpressionOperator::Void => JsValue::Undefined,
+                    UnaryExpressionOperator::Typeof => JsValue::string(arg.type_of()),
+                    UnaryExpressionOperator::Plus => JsValue::Number(arg.to_number()),
+                    UnaryExpressionOperator::Minus => JsValue::Number(-arg.to_number()),
+                    UnaryExpressionOperator::BitNot => {
+                        JsValue::Number(!to_int32(arg.to_number()) as f64)
+                    }
+                    UnaryExpressionOperator::Not => JsValue::Boolean(!arg.to_boolean()),
+                    UnaryExpressionOperator::Delete => return None,
+                })
+            }
+            Node::BinaryExpression(BinaryExpression {
+                left,
+                right,
+                operator,
+                ..
+            }) => {
+                let left = self.eval_in(gc, left, is_global)?;
+                let right = self.eval_in(gc, right, is_global)?;
+                eval_binary(*operator, &left, &right)
+            }
+            Node::LogicalExpression(LogicalExpression {
+                left,
+                right,
+                operator,
+                ..
+            }) => {
+                // Only the branch which is taken needs to be constant.
+                let left = self.eval_in(gc, left, is_global)?;
+                let take_left = match operator {
+                    LogicalExpressionOperator::And => !left.to_boolean(),
+                    LogicalExpressionOperator::Or => left.to_boolean(),
+                    LogicalExpressionOperator::NullishCoalesce => {
+                        !matches!(left, JsValue::Undefined | JsValue::Null)
+                    }
+                };
+                if take_left {
+                    Some(left)
+                } else {
+                    self.eval_in(gc, right, is_global)
+                }
+            }
+            Node::ConditionalExpression(ConditionalExpression {
+                test,
+                consequent,
+                alternate,
+                ..
+            }) => {
+                if self.eval_in(gc, test, is_global)?.to_boolean() {
+                    self.eval_in(gc, consequent, is_global)
+                } else {
+                    self.eval_in(gc, alternate, is_global)
+                }
+            }
+            Node::SequenceExpression(SequenceExpression { expressions, .. }) => {
+                let mut result = JsValue::Undefined;
+                for expr in expressions.iter() {
+                    result = self.eval_in(gc, expr, is_global)?;
+                }
+                Some(result)
+            }
+            Node::TemplateLiteral(TemplateLiteral {
+                quasis,
+                expressions,
+                ..
+            }) => {
+                let mut result = Vec::new();
+                let mut exprs = expressions.iter();
+                for quasi in quasis.iter() {
+                    let cooked = match quasi {
+                        Node::TemplateElement(TemplateElement {
+                            cooked: Some(cooked),
+                            ..
+                        }) => *cooked,
+                        _ => return None,
+                    };
+                    result.extend_from_slice(gc.str_u16(cooked));
+                    if let Some(expr) = exprs.next() {
+                        result
+                            .extend_from_slice(&self.eval_in(gc, expr, is_global)?.to_js_string());
+                    }
+                }
+                Some(JsValue::String(result.into()))
+            }
+            Node::ArrayExpression(ArrayExpression { elements, .. }) => {
+                let mut result = Vec::new();
+                for elem in elements.iter() {
+                    match elem {
+                        Node::Empty(_) => result.push(JsValue::Undefined),
+                        Node::SpreadElement(SpreadElement { argument, .. }) => {
+                            match self.eval_in(gc, argument, is_global)? {
+                                JsValue::Array(a) => result.extend(a.iter().cloned()),
+                                JsValue::String(s) => result.extend(
+                                    String::from_utf16_lossy(&s)
+                                        .chars()
+                                        .map(|c| JsValue::string(c.encode_utf8(&mut [0; 4]))),
+                                ),
+                                _ => return None,
+                            }
+                        }
+                        _ => result.push(self.eval_in(gc, elem, is_global)?),
+                    }
+                }
+                Some(JsValue::Array(Rc::new(result)))
+            }
+            Node::ObjectExpression(ObjectExpression { properties, .. }) => {
+                let mut result: Vec<(Rc<[u16]>, JsValue)> = Vec::new();
+                let mut set = |key: Rc<[u16]>, value: JsValue| {
+                    match result.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, v)) => *v = value,
+                        None => result.push((key, value)),
+                    };
+                };
+                for prop in properties.iter() {
+                    match prop {
+                        Node::Property(Property {
+                            key,
+                            value,
+                            kind: PropertyKind::Init,
+                            computed,
+                            method: false,
+                            ..
+                        }) => {
+                            let key = match (key, computed) {
+                                (Node::Identifier(Identifier { name, .. }), false) => {
+                                    JsValue::string(gc.str(*name)).to_js_string()
+                                }
+                                _ => self.eval_in(gc, key, is_global)?.to_js_string(),
+                            };
+                            set(key, self.eval_in(gc, value, is_global)?);
+                        }
+                        Node::SpreadElement(SpreadElement { argument, .. }) => {
+                            match self.eval_in(gc, argument, is_global)? {
+                                JsValue::Object(props) => {
+                                    for (k, v) in props.iter() {
+                                        set(k.clone(), v.clone());
+                                    }
+                                }
+                                JsValue::Undefined | JsValue::Null => {}
+                                _ => return None,
+                            }
+                        }
+                        _ => return None,
+                    }
+                }
+                Some(JsValue::Object(Rc::new(result)))
+            }
+            Node::MemberExpression(MemberExpression {
+                object,
+                property,
+                computed,
+                ..
+            }) => {
+                let object = self.eval_in(gc, object, is_global)?;
+                let key = match (property, computed) {
+                    (Node::Identifier(Identifier { name, .. }), false) => {
+                        JsValue::string(gc.str(*name))
+                    }
+                    _ => self.eval_in(gc, property, is_global)?,
+                };
+                object.get_property(&key)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Return `a.b.c` and the identifier `a`
+/// if `node` is a chain of non-computed member expressions on an identifier.
+fn dotted_name<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> Option<(String, &'gc Node<'gc>)> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => Some((gc.str(*name).to_string(), node)),
+        Node::MemberExpression(MemberExpression {
+            object,
+            property: Node::Identifier(Identifier { name, .. }),
+            computed: false,
+            ..
+        }) => {
+            let (mut result, root) = dotted_name(gc, object)?;
+            result.push('.');
+            result.push_str(gc.str(*name));
+            Some((result, root))
+        }
+        _ => None,
+    }
+}
+
+fn eval_binary(op: BinaryExpressionOperator, left: &JsValue, right: &JsValue) -> Option<JsValue> {
+    use BinaryExpressionOperator::*;
+    let num = |f: fn(f64, f64) -> f64| JsValue::Number(f(left.to_number(), right.to_number()));
+    let int = |f: fn(i32, u32) -> i32| {
+        JsValue::Number(f(to_int32(left.to_number()), to_uint32(right.to_number())) as f64)
+    };
+    Some(match op {
+        StrictEquals => JsValue::Boolean(left.strict_equals(right)),
+        StrictNotEquals => JsValue::Boolean(!left.strict_equals(right)),
+        LooseEquals => JsValue::Boolean(left.loose_equals(right)),
+        LooseNotEquals => JsValue::Boolean(!left.loose_equals(right)),
+        Less | LessEquals | Greater | GreaterEquals => {
+            let (a, b) = (left.to_primitive_value(), right.to_primitive_value());
+            let ord = match (&a, &b) {
+                (JsValue::String(a), JsValue::String(b)) => Some(a.cmp(b)),
+                _ => a.to_number().partial_cmp(&b.to_number()),
+            };
+            JsValue::Boolean(match ord {
+                None => false,
+                Some(ord) => match op {
+                    Less => ord.is_lt(),
+                    LessEquals => ord.is_le(),
+                    Greater => ord.is_gt(),
+                    _ => ord.is_ge(),
+                },
+            })
+        }
+        Plus => {
+            let (a, b) = (left.to_primitive_value(), right.to_primitive_value());
+            if matches!(a, JsValue::String(_)) || matches!(b, JsValue::String(_)) {
+                let mut s = a.to_js_string().to_vec();
+                s.extend_from_slice(&b.to_js_string());
+                JsValue::String(s.into())
+            } else {
+                JsValue::Number(a.to_number() + b.to_number())
+            }
+        }
+        Minus => num(|a, b| a - b),
+        Mult => num(|a, b| a * b),
+        Div => num(|a, b| a / b),
+        Mod => num(|a, b| a % b),
+        Exp => num(|a, b| {
+            // `1 ** NaN` and `1 ** Infinity` are NaN in JS.
+            if a.abs() == 1.0 && !b.is_finite() {
+                f64::NAN
+            } else {
+                a.powf(b)
+            }
+        }),
+        LShift => int(|a, b| a.wrapping_shl(b & 31)),
+        RShift => int(|a, b| a >> (b & 31)),
+        RShift3 => JsValue::Number(
+            (to_uint32(left.to_number()) >> (to_uint32(right.to_number()) & 31)) as f64,
+        ),
+        BitOr => int(|a, b| a | b as i32),
+        BitXor => int(|a, b| a ^ b as i32),
+        BitAnd => int(|a, b| a & b as i32),
+        In | Instanceof => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(
+            string_to_number(&JsValue::string(" 12 ").to_js_string()),
+            12.0
+        );
+        assert_eq!(
+            string_to_number(&JsValue::string("0x1f").to_js_string()),
+            31.0
+        );
+        assert_eq!(
+            string_to_number(&JsValue::string("-Infinity").to_js_string()),
+            f64::NEG_INFINITY
+        );
+        assert!(string_to_number(&JsValue::string("inf").to_js_string()).is_nan());
+        assert!(string_to_number(&JsValue::string("1x").to_js_string()).is_nan());
+        assert_eq!(to_int32(4294967295.0), -1);
+        assert_eq!(to_uint32(-1.0), 4294967295);
+
+        let arr = JsValue::Array(Rc::new(vec![
+            JsValue::Number(1.0),
+            JsValue::Null,
+            JsValue::string("a"),
+        ]));
+        assert_eq!(arr.to_string(), "1,,a");
+        assert!(arr.to_boolean());
+        assert!(!JsValue::Number(f64::NAN).to_boolean());
+    }
+
+    #[test]
+    fn test_binary() {
+        let eval = |op, a, b| eval_binary(op, &a, &b).unwrap().to_string();
+        use BinaryExpressionOperator::*;
+        assert_eq!(eval(Plus, JsValue::string("a"), JsValue::Number(1.0)), "a1");
+        assert_eq!(
+            eval(Plus, JsValue::Boolean(true), JsValue::Number(1.0)),
+            "2"
+        );
+        assert_eq!(
+            eval(RShift3, JsValue::Number(-1.0), JsValue::Number(0.0)),
+            "4294967295"
+        );
+        assert_eq!(
+            eval(LShift, JsValue::Number(1.0), JsValue::Number(31.0)),
+            "-2147483648"
+        );
+        assert_eq!(eval(LooseEquals, JsValue::Null, JsValue::Undefined), "true");
+        assert_eq!(
+            eval(LooseEquals, JsValue::string("1"), JsValue::Number(1.0)),
+            "true"
+        );
+        assert_eq!(
+            eval(StrictEquals, JsValue::string("1"), JsValue::Number(1.0)),
+            "false"
+        );
+        assert_eq!(
+            eval(Less, JsValue::string("a"), JsValue::string("b")),
+            "true"
+        );
+        assert_eq!(
+            eval(Exp, JsValue::Number(1.0), JsValue::Number(f64::NAN)),
+            "NaN"
+        );
+    }
+}
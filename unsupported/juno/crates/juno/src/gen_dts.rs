@@ -0,0 +1,1437 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Generate TypeScript declarations (`.d.ts`) from Flow-annotated sources, so that libraries
+//! written in Flow can ship types to TypeScript users without a separate tool.
+//!
+//! Every top-level declaration of the module is declared, with its Flow types converted to
+//! the equivalent TypeScript types, and the imports and exports are kept. Other statements
+//! are dropped. Values without annotations are `any`, except literals and functions, whose
+//! types follow from their syntax.
+//!
+//! Flow utility types are converted to their TypeScript counterparts where there is one,
+//! e.g. `$ReadOnly<T>` to `Readonly<T>` and `$Keys<T>` to `keyof T`. Types without a
+//! counterpart, like components, become `any`, and opaque types become their supertype.
+//!
+//! Unannotated parameters, return values, variables and properties get the types of their
+//! JSDoc comments (see [`crate::jsdoc`]), so that plain JS with JSDoc types is declared too.
+
+use std::io;
+use std::io::Write;
+
+use crate::ast::*;
+use crate::jsdoc;
+use crate::jsdoc::JSDoc;
+use crate::jsdoc::JSDocs;
+
+/// Generate the declarations of the module `root` to `out`.
+pub fn generate(out: &mut dyn Write, ctx: &mut Context, root: &NodeRc) -> io::Result<()> {
+    let gc = GCLock::new(ctx);
+    let root = root.node(&gc);
+    let mut gen = GenDts {
+        out: String::new(),
+        docs: jsdoc::attach(&gc, root, root.range().file),
+    };
+    gen.gen_root(&gc, root);
+    out.write_all(gen.out.as_bytes())
+}
+
+/// Precedence of a TypeScript type, to decide where it needs parentheses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// Function and conditional types.
+    Function,
+    Union,
+    Intersection,
+    /// `keyof` and `infer`.
+    Prefix,
+    /// Arrays, indexed accesses and everything else.
+    Primary,
+}
+
+/// A TypeScript type and its precedence.
+struct Type(String, Prec);
+
+fn primary<S: Into<String>>(ts: S) -> Type {
+    Type(ts.into(), Prec::Primary)
+}
+
+/// The TypeScript type of the Flow type `node`.
+fn ty(gc: &GCLock, node: &Node) -> String {
+    convert(gc, node).0
+}
+
+/// The TypeScript type of the Flow type `node`, parenthesized unless its precedence is at
+/// least `min`.
+fn ty_at(gc: &GCLock, node: &Node, min: Prec) -> String {
+    let Type(ts, prec) = convert(gc, node);
+    if prec < min {
+        format!("({})", ts)
+    } else {
+        ts
+    }
+}
+
+/// The TypeScript type of the optional annotation `node`, `any` if there is none.
+fn annotation(gc: &GCLock, node: Option<&Node>) -> String {
+    node.map_or_else(|| "any".to_string(), |node| ty(gc, node))
+}
+
+fn join<I: Iterator<Item = String>>(items: I, sep: &str) -> String {
+    items.collect::<Vec<_>>().join(sep)
+}
+
+/// Convert the Flow type `node` to TypeScript.
+fn convert(gc: &GCLock, node: &Node) -> Type {
+    match node {
+        Node::TypeAnnotation(TypeAnnotation {
+            type_annotation, ..
+        }) => convert(gc, type_annotation),
+        Node::AnyTypeAnnotation(_) | Node::ExistsTypeAnnotation(_) => primary("any"),
+        Node::MixedTypeAnnotation(_) => primary("unknown"),
+        Node::EmptyTypeAnnotation(_) => primary("never"),
+        Node::VoidTypeAnnotation(_) => primary("void"),
+        Node::NullLiteralTypeAnnotation(_) => primary("null"),
+        Node::NumberTypeAnnotation(_) => primary("number"),
+        Node::StringTypeAnnotation(_) => primary("string"),
+        Node::BooleanTypeAnnotation(_) => primary("boolean"),
+        Node::BigIntTypeAnnotation(_) => primary("bigint"),
+        Node::SymbolTypeAnnotation(_) => primary("symbol"),
+        Node::StringLiteralTypeAnnotation(StringLiteralTypeAnnotation { raw, .. }) => {
+            primary(String::from_utf16_lossy(gc.str_u16(*raw)))
+        }
+        Node::NumberLiteralTypeAnnotation(NumberLiteralTypeAnnotation { raw, .. })
+        | Node::BigIntLiteralTypeAnnotation(BigIntLiteralTypeAnnotation { raw, .. }) => {
+            primary(gc.str(*raw))
+        }
+        Node::BooleanLiteralTypeAnnotation(BooleanLiteralTypeAnnotation { value, .. }) => {
+            primary(if *value { "true" } else { "false" })
+        }
+        Node::NullableTypeAnnotation(NullableTypeAnnotation {
+            type_annotation, ..
+        }) => Type(
+            format!(
+                "{} | null | undefined",
+                ty_at(gc, type_annotation, Prec::Intersection)
+            ),
+            Prec::Union,
+        ),
+        Node::ArrayTypeAnnotation(ArrayTypeAnnotation { element_type, .. }) => {
+            primary(format!("{}[]", ty_at(gc, element_type, Prec::Primary)))
+        }
+        Node::UnionTypeAnnotation(UnionTypeAnnotation { types, .. }) => Type(
+            join(
+                types.iter().map(|t| ty_at(gc, t, Prec::Intersection)),
+                " | ",
+            ),
+            Prec::Union,
+        ),
+        Node::IntersectionTypeAnnotation(IntersectionTypeAnnotation { types, .. }) => Type(
+            join(types.iter().map(|t| ty_at(gc, t, Prec::Prefix)), " & "),
+            Prec::Intersection,
+        ),
+        Node::FunctionTypeAnnotation(_) => Type(function_type(gc, node, true), Prec::Function),
+        Node::ObjectTypeAnnotation(_) => object_type(gc, node),
+        Node::InterfaceTypeAnnotation(InterfaceTypeAnnotation { extends, body, .. }) => {
+            let mut types = extends
+                .iter()
+                .map(|extends| interface_extends(gc, extends))
+                .collect::<Vec<_>>();
+            if let Some(body) = body {
+                types.push(ty_at(gc, body, Prec::Prefix));
+            }
+            match types.len() {
+                0 => primary("{}"),
+                1 => primary(types.pop().unwrap()),
+                _ => Type(types.join(" & "), Prec::Intersection),
+            }
+        }
+        Node::GenericTypeAnnotation(GenericTypeAnnotation {
+            id,
+            type_parameters,
+            ..
+        }) => generic(gc, id, *type_parameters),
+        Node::TypeofTypeAnnotation(TypeofTypeAnnotation { argument, .. }) => {
+            let name = match argument {
+                Node::GenericTypeAnnotation(GenericTypeAnnotation { id, .. }) => {
+                    qualified_name(gc, id)
+                }
+                _ => qualified_name(gc, argument),
+            };
+            match name {
+                Some(name) => primary(format!("typeof {}", name)),
+                None => primary("any"),
+            }
+        }
+        Node::KeyofTypeAnnotation(KeyofTypeAnnotation { argument, .. }) => Type(
+            format!("keyof {}", ty_at(gc, argument, Prec::Prefix)),
+            Prec::Prefix,
+        ),
+        Node::IndexedAccessType(IndexedAccessType {
+            object_type,
+            index_type,
+            ..
+        }) => primary(format!(
+            "{}[{}]",
+            ty_at(gc, object_type, Prec::Primary),
+            ty(gc, index_type)
+        )),
+        Node::OptionalIndexedAccessType(OptionalIndexedAccessType {
+            object_type,
+            index_type,
+            ..
+        }) => primary(format!(
+            "NonNullable<{}>[{}]",
+            ty(gc, object_type),
+            ty(gc, index_type)
+        )),
+        Node::TupleTypeAnnotation(TupleTypeAnnotation { types, .. }) => primary(format!(
+            "[{}]",
+            join(types.iter().map(|t| tuple_element(gc, t)), ", ")
+        )),
+        Node::ConditionalTypeAnnotation(cond) => {
+            let ConditionalTypeAnnotation {
+                check_type,
+                extends_type,
+                true_type,
+                false_type,
+                ..
+            } = &**cond;
+            Type(
+                format!(
+                    "{} extends {} ? {} : {}",
+                    ty_at(gc, check_type, Prec::Union),
+                    ty_at(gc, extends_type, Prec::Union),
+                    ty(gc, true_type),
+                    ty(gc, false_type)
+                ),
+                Prec::Function,
+            )
+        }
+        Node::InferTypeAnnotation(InferTypeAnnotation { type_parameter, .. }) => Type(
+            format!("infer {}", type_param(gc, type_parameter)),
+            Prec::Prefix,
+        ),
+        Node::TypePredicate(TypePredicate {
+            parameter_name,
+            type_annotation,
+            asserts,
+            ..
+        }) => {
+            let mut ts = String::new();
+            if *asserts {
+                ts.push_str("asserts ");
+            }
+            ts.push_str(&ident(gc, parameter_name));
+            if let Some(t) = type_annotation {
+                ts.push_str(" is ");
+                ts.push_str(&ty(gc, t));
+            }
+            Type(ts, Prec::Function)
+        }
+        _ => primary("any"),
+    }
+}
+
+/// Flow utility types which are TypeScript utility types under another name.
+const RENAMED_UTILITIES: &[(&str, &str)] = &[
+    ("$ReadOnly", "Readonly"),
+    ("$ReadOnlyArray", "ReadonlyArray"),
+    ("$ReadOnlyMap", "ReadonlyMap"),
+    ("$ReadOnlySet", "ReadonlySet"),
+    ("$Shape", "Partial"),
+    ("$NonMaybeType", "NonNullable"),
+];
+
+/// Convert the generic type `id<type_parameters>`, which may be a Flow utility type.
+fn generic(gc: &GCLock, id: &Node, type_parameters: Option<&Node>) -> Type {
+    let name = match qualified_name(gc, id) {
+        Some(name) => name,
+        None => return primary("any"),
+    };
+    let args = match type_parameters {
+        Some(Node::TypeParameterInstantiation(TypeParameterInstantiation { params, .. })) => {
+            params.iter().collect::<Vec<_>>()
+        }
+        _ => vec![],
+    };
+    match (name.as_str(), args.as_slice()) {
+        ("$Exact", [arg]) => convert(gc, arg),
+        ("$Keys", [arg]) => Type(
+            format!("keyof {}", ty_at(gc, arg, Prec::Prefix)),
+            Prec::Prefix,
+        ),
+        ("$Values", [arg]) => {
+            let arg = ty_at(gc, arg, Prec::Primary);
+            primary(format!("{}[keyof {}]", arg, arg))
+        }
+        ("Class", [arg]) => Type(
+            format!("new (...args: any[]) => {}", ty(gc, arg)),
+            Prec::Function,
+        ),
+        ("$FlowFixMe" | "$FlowIssue" | "$FlowExpectedError", _) => primary("any"),
+        _ => {
+            let name = RENAMED_UTILITIES
+                .iter()
+                .find(|(flow, _)| *flow == name)
+                .map_or(name.as_str(), |(_, ts)| ts);
+            primary(format!("{}{}", name, type_args(gc, type_parameters)))
+        }
+    }
+}
+
+/// The name of the identifier `node`.
+fn ident(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => gc.str(*name).to_string(),
+        _ => "_".to_string(),
+    }
+}
+
+/// The dotted name of `node`, an identifier or a qualified identifier.
+fn qualified_name(gc: &GCLock, node: &Node) -> Option<String> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        Node::QualifiedTypeIdentifier(QualifiedTypeIdentifier {
+            qualification, id, ..
+        })
+        | Node::QualifiedTypeofIdentifier(QualifiedTypeofIdentifier {
+            qualification, id, ..
+        }) => Some(format!(
+            "{}.{}",
+            qualified_name(gc, qualification)?,
+            ident(gc, id)
+        )),
+        _ => None,
+    }
+}
+
+/// The dotted name of the expression `node`, an identifier or a chain of member accesses,
+/// as in `extends React.Component`.
+fn expression_name(gc: &GCLock, node: &Node) -> Option<String> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        Node::MemberExpression(MemberExpression {
+            object,
+            property,
+            computed: false,
+            ..
+        }) => Some(format!(
+            "{}.{}",
+            expression_name(gc, object)?,
+            ident(gc, property)
+        )),
+        _ => None,
+    }
+}
+
+/// A JS string literal for `value`.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{2028}' || c == '\u{2029}' => {
+                quoted.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A JS string literal for the value of the string literal `node`.
+fn string_literal(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::StringLiteral(StringLiteral { value, .. }) => {
+            quote(&String::from_utf16_lossy(gc.str_u16(*value)))
+        }
+        _ => quote(""),
+    }
+}
+
+/// `<A, B>` for the type arguments `node`, if any.
+fn type_args(gc: &GCLock, node: Option<&Node>) -> String {
+    match node {
+        Some(Node::TypeParameterInstantiation(TypeParameterInstantiation { params, .. })) => {
+            format!("<{}>", join(params.iter().map(|p| ty(gc, p)), ", "))
+        }
+        _ => String::new(),
+    }
+}
+
+/// `<T extends A = B>` for the type parameters `node`, if any.
+fn type_params(gc: &GCLock, node: Option<&Node>) -> String {
+    match node {
+        Some(Node::TypeParameterDeclaration(TypeParameterDeclaration { params, .. })) => {
+            format!("<{}>", join(params.iter().map(|p| type_param(gc, p)), ", "))
+        }
+        _ => String::new(),
+    }
+}
+
+fn type_param(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::TypeParameter(param) => {
+            let TypeParameter {
+                name,
+                bound,
+                default,
+                ..
+            } = &**param;
+            let mut ts = gc.str(*name).to_string();
+            if let Some(bound) = bound {
+                ts.push_str(" extends ");
+                ts.push_str(&ty(gc, bound));
+            }
+            if let Some(default) = default {
+                ts.push_str(" = ");
+                ts.push_str(&ty(gc, default));
+            }
+            ts
+        }
+        _ => "_".to_string(),
+    }
+}
+
+/// `A<B>` for a node of an `extends` clause of an interface or a declared class.
+fn interface_extends(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::InterfaceExtends(InterfaceExtends {
+            id,
+            type_parameters,
+            ..
+        })
+        | Node::ClassImplements(ClassImplements {
+            id,
+            type_parameters,
+            ..
+        }) => match qualified_name(gc, id) {
+            Some(name) => format!("{}{}", name, type_args(gc, *type_parameters)),
+            None => "any".to_string(),
+        },
+        _ => "any".to_string(),
+    }
+}
+
+fn tuple_element(gc: &GCLock, node: &Node) -> String {
+    match node {
+        Node::TupleTypeLabeledElement(elem) => {
+            let TupleTypeLabeledElement {
+                label,
+                element_type,
+                optional,
+                ..
+            } = &**elem;
+            format!(
+                "{}{}: {}",
+                ident(gc, label),
+                if *optional { "?" } else { "" },
+                ty(gc, element_type)
+            )
+        }
+        Node::TupleTypeSpreadElement(TupleTypeSpreadElement {
+            label: Some(label),
+            type_annotation,
+            ..
+        }) => format!("...{}: {}", ident(gc, label), ty(gc, type_annotation)),
+        Node::TupleTypeSpreadElement(TupleTypeSpreadElement {
+            label: None,
+            type_annotation,
+            ..
+        }) => format!("...{}", ty(gc, type_annotation)),
+        _ => ty(gc, node),
+    }
+}
+
+/// `name?: T` for the parameter `node` of a function type, which is the `index`th one.
+fn function_type_param(gc: &GCLock, node: &Node, index: usize) -> String {
+    match node {
+        Node::FunctionTypeParam(FunctionTypeParam {
+            name,
+            type_annotation,
+            optional,
+            ..
+        }) => format!(
+            "{}{}: {}",
+            name.map_or_else(|| format!("arg{}", index), |name| ident(gc, name)),
+            if *optional { "?" } else { "" },
+            ty(gc, type_annotation)
+        ),
+        _ => format!("arg{}: any", index),
+    }
+}
+
+/// The signature of the function type `node`, `<T>(a: A) => R` if `arrow`,
+/// or `<T>(a: A): R` for methods.
+fn function_type(gc: &GCLock, node: &Node, arrow: bool) -> String {
+    let (params, this, return_type, rest, type_parameters) = match node {
+        Node::FunctionTypeAnnotation(func_type) => {
+            let FunctionTypeAnnotation {
+                params,
+                this,
+                return_type,
+                rest,
+                type_parameters,
+                ..
+            } = &**func_type;
+            (params, this, return_type, rest, type_parameters)
+        }
+        _ => return "any".to_string(),
+    };
+    let mut ts_params = vec![];
+    if let Some(Node::FunctionTypeParam(FunctionTypeParam {
+        type_annotation, ..
+    })) = this
+    {
+        ts_params.push(format!("this: {}", ty(gc, type_annotation)));
+    }
+    for (index, param) in params.iter().enumerate() {
+        ts_params.push(function_type_param(gc, param, index));
+    }
+    if let Some(rest) = rest {
+        ts_params.push(format!(
+            "...{}",
+            function_type_param(gc, rest, ts_params.len())
+        ));
+    }
+    format!(
+        "{}({}){}{}",
+        type_params(gc, *type_parameters),
+        ts_params.join(", "),
+        if arrow { " => " } else { ": " },
+        ty(gc, return_type)
+    )
+}
+
+/// The members of the object type `node`, and the types it spreads.
+fn object_members(gc: &GCLock, node: &Node) -> (Vec<String>, Vec<String>) {
+    let mut members = vec![];
+    let mut spreads = vec![];
+    let (properties, indexers, call_properties) = match node {
+        Node::ObjectTypeAnnotation(obj_type) => {
+            let ObjectTypeAnnotation {
+                properties,
+                indexers,
+                call_properties,
+                ..
+            } = &**obj_type;
+            (properties, indexers, call_properties)
+        }
+        _ => return (members, spreads),
+    };
+    for property in properties.iter() {
+        match property {
+            Node::ObjectTypeProperty(_) => members.extend(object_property(gc, property, "")),
+            Node::ObjectTypeSpreadProperty(ObjectTypeSpreadProperty { argument, .. }) => {
+                spreads.push(ty_at(gc, argument, Prec::Prefix))
+            }
+            _ => {}
+        }
+    }
+    for indexer in indexers.iter() {
+        if let Node::ObjectTypeIndexer(indexer) = indexer {
+            let ObjectTypeIndexer {
+                id,
+                key,
+                value,
+                variance,
+                ..
+            } = &**indexer;
+            members.push(format!(
+                "{}[{}: {}]: {}",
+                if is_covariant(gc, *variance) {
+                    "readonly "
+                } else {
+                    ""
+                },
+                id.map_or_else(|| "key".to_string(), |id| ident(gc, id)),
+                ty(gc, key),
+                ty(gc, value)
+            ));
+        }
+    }
+    for call in call_properties.iter() {
+        if let Node::ObjectTypeCallProperty(ObjectTypeCallProperty { value, .. }) = call {
+            members.push(function_type(gc, value, false));
+        }
+    }
+    (members, spreads)
+}
+
+/// Convert the object type `node`, whose spreads become an intersection.
+fn object_type(gc: &GCLock, node: &Node) -> Type {
+    let (members, mut spreads) = object_members(gc, node);
+    if members.is_empty() && spreads.len() == 1 {
+        return Type(spreads.pop().unwrap(), Prec::Prefix);
+    }
+    let body = if members.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", members.join("; "))
+    };
+    if spreads.is_empty() {
+        primary(body)
+    } else {
+        spreads.push(body);
+        Type(spreads.join(" & "), Prec::Intersection)
+    }
+}
+
+/// Whether the variance `node` is `+`, which makes a property read-only.
+fn is_covariant(gc: &GCLock, node: Option<&Node>) -> bool {
+    matches!(node, Some(Node::Variance(Variance { kind, .. })) if gc.str(*kind) == "plus")
+}
+
+/// The key of an object type property or a class member, quoted if it isn't an identifier.
+fn property_key(gc: &GCLock, node: &Node) -> Option<String> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        Node::StringLiteral(_) => Some(string_literal(gc, node)),
+        Node::NumericLiteral(NumericLiteral { value, .. }) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The member for the object type property `node`, prefixed by `prefix` like `static `.
+/// Getters are declared as properties, and setters aren't declared, since accessors aren't
+/// allowed in object types by every TypeScript version.
+fn object_property(gc: &GCLock, node: &Node, prefix: &str) -> Option<String> {
+    let (key, value, method, optional, variance, kind) = match node {
+        Node::ObjectTypeProperty(prop) => {
+            let ObjectTypeProperty {
+                key,
+                value,
+                method,
+                optional,
+                variance,
+                kind,
+                ..
+            } = &**prop;
+            (key, value, *method, *optional, *variance, gc.str(*kind))
+        }
+        _ => return None,
+    };
+    let key = property_key(gc, key)?;
+    match (kind, value) {
+        ("set", _) => None,
+        ("get", Node::FunctionTypeAnnotation(func_type)) => Some(format!(
+            "{}readonly {}: {}",
+            prefix,
+            key,
+            ty(gc, func_type.return_type)
+        )),
+        (_, Node::FunctionTypeAnnotation(_)) if method => Some(format!(
+            "{}{}{}",
+            prefix,
+            key,
+            function_type(gc, value, false)
+        )),
+        _ => Some(format!(
+            "{}{}{}{}: {}",
+            prefix,
+            if is_covariant(gc, variance) {
+                "readonly "
+            } else {
+                ""
+            },
+            key,
+            if optional { "?" } else { "" },
+            ty(gc, value)
+        )),
+    }
+}
+
+/// The type annotation of the parameter or binding `node`, if any.
+fn pattern_annotation<'gc>(node: &Node<'gc>) -> Option<&'gc Node<'gc>> {
+    match node {
+        Node::Identifier(Identifier {
+            type_annotation, ..
+        })
+        | Node::ObjectPattern(ObjectPattern {
+            type_annotation, ..
+        })
+        | Node::ArrayPattern(ArrayPattern {
+            type_annotation, ..
+        }) => *type_annotation,
+        _ => None,
+    }
+}
+
+/// `name?: T` for the parameter `node` of a function, which is the `index`th one,
+/// and which may be documented by the JSDoc comment `doc` of the function.
+fn param(gc: &GCLock, node: &Node, index: usize, doc: Option<&JSDoc>) -> String {
+    let name = |node: &Node| match node {
+        Node::Identifier(Identifier { name, .. }) => gc.str(*name).to_string(),
+        _ => format!("arg{}", index),
+    };
+    let doc_param = |node: &Node| doc.and_then(|doc| doc.param(&name(node)));
+    let doc_type = |node: &Node| {
+        doc_param(node)
+            .and_then(|param| param.type_expr.as_deref())
+            .map(jsdoc_type)
+    };
+    match node {
+        Node::Identifier(Identifier {
+            type_annotation: Some(t),
+            optional,
+            ..
+        }) => format!(
+            "{}{}: {}",
+            name(node),
+            if *optional { "?" } else { "" },
+            ty(gc, t)
+        ),
+        Node::Identifier(Identifier {
+            type_annotation: None,
+            optional,
+            ..
+        }) => format!(
+            "{}{}: {}",
+            name(node),
+            if *optional || doc_param(node).iter().any(|param| param.optional) {
+                "?"
+            } else {
+                ""
+            },
+            doc_type(node).unwrap_or_else(|| "any".to_string())
+        ),
+        Node::AssignmentPattern(AssignmentPattern { left, right, .. }) => format!(
+            "{}?: {}",
+            name(left),
+            match pattern_annotation(left) {
+                Some(t) => ty(gc, t),
+                None => doc_type(left).unwrap_or_else(|| infer(gc, right, None)),
+            }
+        ),
+        Node::RestElement(RestElement { argument, .. }) => format!(
+            "...{}: {}",
+            name(argument),
+            match pattern_annotation(argument) {
+                Some(t) => ty(gc, t),
+                None => doc_type(argument).unwrap_or_else(|| "any[]".to_string()),
+            }
+        ),
+        _ => format!(
+            "{}: {}",
+            name(node),
+            annotation(gc, pattern_annotation(node))
+        ),
+    }
+}
+
+fn params(gc: &GCLock, params: &NodeList, doc: Option<&JSDoc>) -> String {
+    join(
+        params
+            .iter()
+            .enumerate()
+            .map(|(index, node)| param(gc, node, index, doc)),
+        ", ",
+    )
+}
+
+/// The signature of the function-like node `func`, documented by `doc`,
+/// `<T>(a: A) => R` if `arrow`, or `<T>(a: A): R` for declarations and methods.
+fn signature(gc: &GCLock, func: &Node, arrow: bool, doc: Option<&JSDoc>) -> String {
+    let (type_parameters, return_type, is_async) = match func {
+        Node::FunctionDeclaration(func) => (func.type_parameters, func.return_type, func.is_async),
+        Node::FunctionExpression(func) => (func.type_parameters, func.return_type, func.is_async),
+        Node::ArrowFunctionExpression(arrow) => {
+            (arrow.type_parameters, arrow.return_type, arrow.is_async)
+        }
+        _ => return "any".to_string(),
+    };
+    let doc_return_type = doc
+        .and_then(|doc| doc.returns.as_ref())
+        .and_then(|returns| returns.type_expr.as_deref());
+    let return_type = match (return_type, doc_return_type) {
+        (Some(t), _) => ty(gc, t),
+        (None, Some(t)) => jsdoc_type(t),
+        (None, None) if is_async => "Promise<any>".to_string(),
+        (None, None) => "any".to_string(),
+    };
+    format!(
+        "{}({}){}{}",
+        type_params(gc, type_parameters),
+        params(gc, &func.function_like_params(), doc),
+        if arrow { " => " } else { ": " },
+        return_type
+    )
+}
+
+/// The type of the value of the unannotated expression `node`, `any` if it isn't obvious.
+/// `doc` is the JSDoc comment of the declaration, which documents functions.
+fn infer(gc: &GCLock, node: &Node, doc: Option<&JSDoc>) -> String {
+    match node {
+        Node::NumericLiteral(_) => "number".to_string(),
+        Node::StringLiteral(_) | Node::TemplateLiteral(_) => "string".to_string(),
+        Node::BooleanLiteral(_) => "boolean".to_string(),
+        Node::BigIntLiteral(_) => "bigint".to_string(),
+        Node::RegExpLiteral(_) => "RegExp".to_string(),
+        Node::UnaryExpression(UnaryExpression {
+            operator: UnaryExpressionOperator::Minus,
+            argument: Node::NumericLiteral(_),
+            ..
+        }) => "number".to_string(),
+        Node::TypeCastExpression(TypeCastExpression {
+            type_annotation, ..
+        }) => ty(gc, type_annotation),
+        _ if node.is_function_like() => signature(gc, node, true, doc),
+        _ => "any".to_string(),
+    }
+}
+
+/// Convert the JSDoc type `text` to TypeScript. The syntax of the Closure Compiler, like
+/// `?number` or `Array.<string>`, is converted, and anything else is assumed to be
+/// TypeScript already.
+fn jsdoc_type(text: &str) -> String {
+    let text = text.trim();
+    // `T=` is optional, which the parameter declares.
+    let text = text.strip_suffix('=').unwrap_or(text);
+    if let Some(element) = text.strip_prefix("...") {
+        return format!("Array<{}>", jsdoc_type(element));
+    }
+    if let Some(nullable) = text.strip_prefix('?').filter(|t| !t.is_empty()) {
+        return format!("{} | null", jsdoc_type(nullable));
+    }
+    if let Some(non_null) = text.strip_prefix('!') {
+        return jsdoc_type(non_null);
+    }
+    match text {
+        "" | "*" | "?" => "any".to_string(),
+        _ if text.starts_with("function(") => "Function".to_string(),
+        _ => text.replace(".<", "<"),
+    }
+}
+
+/// `declare ` for declarations which need it after `prefix`: `export default` can't be
+/// followed by `declare`.
+fn declare(prefix: &str) -> &'static str {
+    if prefix.starts_with("export default") {
+        ""
+    } else {
+        "declare "
+    }
+}
+
+struct GenDts<'gc> {
+    out: String,
+    docs: JSDocs<'gc>,
+}
+
+impl<'gc> GenDts<'gc> {
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        self.out.push_str(line.as_ref());
+        self.out.push('\n');
+    }
+
+    fn gen_root(&mut self, gc: &'gc GCLock, root: &'gc Node<'gc>) {
+        let body = match root {
+            Node::Module(Module { body, .. }) | Node::Program(Program { body, .. }) => body,
+            _ => return,
+        };
+        for stmt in body.iter() {
+            self.gen_statement(gc, stmt);
+        }
+    }
+
+    fn gen_statement(&mut self, gc: &'gc GCLock, stmt: &'gc Node<'gc>) {
+        match stmt {
+            Node::ImportDeclaration(_) => self.gen_import(gc, stmt),
+            Node::ExportNamedDeclaration(export) => match export.declaration {
+                Some(decl) => self.gen_declaration(gc, decl, "export "),
+                None => self.gen_export_specifiers(
+                    gc,
+                    &export.specifiers,
+                    export.source,
+                    export.export_kind,
+                ),
+            },
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+                self.gen_export_default(gc, declaration)
+            }
+            Node::DeclareExportDeclaration(export) => match export.declaration {
+                Some(decl) if !export.default => self.gen_declaration(gc, decl, "export "),
+                Some(decl) => {
+                    if decl.variant().is_subtype_of(NodeVariant::FlowType) {
+                        self.line(format!("declare const _default: {};", ty(gc, decl)));
+                        self.line("export default _default;");
+                    } else {
+                        self.gen_declaration(gc, decl, "export default ");
+                    }
+                }
+                None => self.gen_export_specifiers(
+                    gc,
+                    &export.specifiers,
+                    export.source,
+                    ExportKind::Value,
+                ),
+            },
+            Node::ExportAllDeclaration(ExportAllDeclaration { source, .. })
+            | Node::DeclareExportAllDeclaration(DeclareExportAllDeclaration { source, .. }) => {
+                self.line(format!("export * from {};", string_literal(gc, source)))
+            }
+            _ => self.gen_declaration(gc, stmt, ""),
+        }
+    }
+
+    fn gen_import(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        let (specifiers, source, import_kind) = match node {
+            Node::ImportDeclaration(import) => {
+                let ImportDeclaration {
+                    specifiers,
+                    source,
+                    import_kind,
+                    ..
+                } = &**import;
+                (specifiers, string_literal(gc, source), *import_kind)
+            }
+            _ => return,
+        };
+        // `typeof` imports of values are imported as values, whose types are available.
+        let import = if import_kind == ImportKind::Type {
+            "import type"
+        } else {
+            "import"
+        };
+        let mut named = vec![];
+        for specifier in specifiers.iter() {
+            match specifier {
+                Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local, .. }) => {
+                    self.line(format!("{} {} from {};", import, ident(gc, local), source))
+                }
+                Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local, .. }) => self
+                    .line(format!(
+                        "{} * as {} from {};",
+                        import,
+                        ident(gc, local),
+                        source
+                    )),
+                Node::ImportSpecifier(ImportSpecifier {
+                    imported,
+                    local,
+                    import_kind: specifier_kind,
+                    ..
+                }) => {
+                    let (imported, local) = (ident(gc, imported), ident(gc, local));
+                    let mut name = String::new();
+                    if import_kind != ImportKind::Type && *specifier_kind == ImportKind::Type {
+                        name.push_str("type ");
+                    }
+                    name.push_str(&imported);
+                    if local != imported {
+                        name.push_str(" as ");
+                        name.push_str(&local);
+                    }
+                    named.push(name);
+                }
+                _ => {}
+            }
+        }
+        if !named.is_empty() {
+            self.line(format!(
+                "{} {{ {} }} from {};",
+                import,
+                named.join(", "),
+                source
+            ));
+        }
+    }
+
+    fn gen_export_specifiers(
+        &mut self,
+        gc: &'gc GCLock,
+        specifiers: &NodeList,
+        source: Option<&Node>,
+        export_kind: ExportKind,
+    ) {
+        let from = source.map_or_else(String::new, |source| {
+            format!(" from {}", string_literal(gc, source))
+        });
+        let mut names = vec![];
+        for specifier in specifiers.iter() {
+            match specifier {
+                Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier { exported, .. }) => {
+                    self.line(format!("export * as {}{};", ident(gc, exported), from))
+                }
+                Node::ExportSpecifier(ExportSpecifier {
+                    exported, local, ..
+                }) => {
+                    let (exported, local) = (ident(gc, exported), ident(gc, local));
+                    names.push(if exported == local {
+                        local
+                    } else {
+                        format!("{} as {}", local, exported)
+                    });
+                }
+                _ => {}
+            }
+        }
+        if !names.is_empty() || specifiers.is_empty() {
+            self.line(format!(
+                "export {}{{ {} }}{};",
+                if export_kind == ExportKind::Type {
+                    "type "
+                } else {
+                    ""
+                },
+                names.join(", "),
+                from
+            ));
+        }
+    }
+
+    fn gen_export_default(&mut self, gc: &'gc GCLock, decl: &'gc Node<'gc>) {
+        match decl {
+            Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => {
+                self.gen_declaration(gc, decl, "export default ")
+            }
+            Node::Identifier(Identifier { name, .. }) => {
+                self.line(format!("export default {};", gc.str(*name)))
+            }
+            _ => {
+                self.line(format!(
+                    "declare const _default: {};",
+                    infer(gc, decl, self.docs.get(decl))
+                ));
+                self.line("export default _default;");
+            }
+        }
+    }
+
+    /// Declare the declaration `decl`, prefixed by `prefix`, which is empty or an `export`.
+    /// Other statements are dropped.
+    fn gen_declaration(&mut self, gc: &'gc GCLock, decl: &'gc Node<'gc>, prefix: &str) {
+        let declare = declare(prefix);
+        match decl {
+            Node::FunctionDeclaration(func) => {
+                let FunctionDeclaration { id, .. } = &**func;
+                self.line(format!(
+                    "{}{}function{}{};",
+                    prefix,
+                    declare,
+                    id.map_or_else(String::new, |id| format!(" {}", ident(gc, id))),
+                    signature(gc, decl, false, self.docs.get(decl))
+                ))
+            }
+            Node::ClassDeclaration(_) => self.gen_class(gc, decl, prefix),
+            Node::VariableDeclaration(VariableDeclaration {
+                kind, declarations, ..
+            }) => {
+                // Cloned, since `self` is borrowed to generate the lines.
+                let doc = self.docs.get(decl).cloned();
+                let doc = doc.as_ref();
+                let doc_type = doc.and_then(|doc| doc.type_expr.as_deref());
+                for declarator in declarations.iter() {
+                    let (id, init) = match declarator {
+                        Node::VariableDeclarator(VariableDeclarator { id, init, .. }) => (id, init),
+                        _ => continue,
+                    };
+                    let bound = bound_names(id);
+                    for name in &bound {
+                        // The JSDoc comment documents a declaration of a single identifier.
+                        let simple = id.ptr_eq(name);
+                        let doc_type = doc_type.filter(|_| simple && declarations.len() == 1);
+                        let ts = match (pattern_annotation(name), doc_type, init) {
+                            (Some(t), _, _) => ty(gc, t),
+                            (None, Some(t), _) => jsdoc_type(t),
+                            (None, None, Some(init)) if simple => infer(gc, init, doc),
+                            _ => "any".to_string(),
+                        };
+                        self.line(format!(
+                            "{}{}{} {}: {};",
+                            prefix,
+                            declare,
+                            kind.as_str(),
+                            ident(gc, name),
+                            ts
+                        ));
+                    }
+                }
+            }
+            Node::TypeAlias(TypeAlias {
+                id,
+                type_parameters,
+                right,
+                ..
+            })
+            | Node::DeclareTypeAlias(DeclareTypeAlias {
+                id,
+                type_parameters,
+                right,
+                ..
+            }) => self.line(format!(
+                "{}type {}{} = {};",
+                prefix,
+                ident(gc, id),
+                type_params(gc, *type_parameters),
+                ty(gc, right)
+            )),
+            Node::OpaqueType(opaque) => self.gen_opaque_type(
+                gc,
+                prefix,
+                opaque.id,
+                opaque.type_parameters,
+                opaque.supertype,
+            ),
+            Node::DeclareOpaqueType(opaque) => self.gen_opaque_type(
+                gc,
+                prefix,
+                opaque.id,
+                opaque.type_parameters,
+                opaque.supertype,
+            ),
+            Node::InterfaceDeclaration(interface) => self.gen_interface(
+                gc,
+                prefix,
+                interface.id,
+                interface.type_parameters,
+                interface.extends,
+                interface.body,
+            ),
+            Node::DeclareInterface(interface) => {
+                self.gen_interface(
+                    gc,
+                    prefix,
+                    interface.id,
+                    interface.type_parameters,
+                    interface.extends,
+                    interface.body,
+                );
+            }
+            Node::EnumDeclaration(EnumDeclaration { id, body, .. })
+            | Node::DeclareEnum(DeclareEnum { id, body, .. }) => {
+                self.gen_enum(gc, &ident(gc, id), body, prefix)
+            }
+            Node::DeclareFunction(DeclareFunction { id, .. }) => {
+                let function = pattern_annotation(id).map(|t| match t {
+                    Node::TypeAnnotation(TypeAnnotation {
+                        type_annotation, ..
+                    }) => *type_annotation,
+                    _ => t,
+                });
+                self.line(format!(
+                    "{}{}function {}{};",
+                    prefix,
+                    declare,
+                    ident(gc, id),
+                    match function {
+                        Some(function) => function_type(gc, function, false),
+                        None => "(...args: any[]): any".to_string(),
+                    }
+                ));
+            }
+            Node::DeclareVariable(DeclareVariable { id, kind, .. }) => self.line(format!(
+                "{}{}{} {}: {};",
+                prefix,
+                declare,
+                kind.as_str(),
+                ident(gc, id),
+                annotation(gc, pattern_annotation(id))
+            )),
+            Node::DeclareClass(class) => {
+                let DeclareClass {
+                    id,
+                    type_parameters,
+                    extends,
+                    implements,
+                    body,
+                    ..
+                } = &**class;
+                let mut header = format!(
+                    "{}{}class {}{}",
+                    prefix,
+                    declare,
+                    ident(gc, id),
+                    type_params(gc, *type_parameters)
+                );
+                if let Some(extends) = extends.iter().next() {
+                    header.push_str(" extends ");
+                    header.push_str(&interface_extends(gc, extends));
+                }
+                if !implements.is_empty() {
+                    header.push_str(" implements ");
+                    header.push_str(&join(
+                        implements.iter().map(|i| interface_extends(gc, i)),
+                        ", ",
+                    ));
+                }
+                let mut members = vec![];
+                if let Node::ObjectTypeAnnotation(obj_type) = body {
+                    let ObjectTypeAnnotation { properties, .. } = &**obj_type;
+                    for property in properties.iter() {
+                        let prefix = match property {
+                            Node::ObjectTypeProperty(prop) if prop.is_static => "static ",
+                            _ => "",
+                        };
+                        members.extend(object_property(gc, property, prefix));
+                    }
+                }
+                self.gen_body(header, members);
+            }
+            _ => {}
+        }
+    }
+
+    /// Declare the opaque type `id`, as an alias of its supertype.
+    fn gen_opaque_type(
+        &mut self,
+        gc: &'gc GCLock,
+        prefix: &str,
+        id: &'gc Node<'gc>,
+        type_parameters: Option<&'gc Node<'gc>>,
+        supertype: Option<&'gc Node<'gc>>,
+    ) {
+        self.line(format!(
+            "{}type {}{} = {};",
+            prefix,
+            ident(gc, id),
+            type_params(gc, type_parameters),
+            supertype.map_or_else(|| "unknown".to_string(), |t| ty(gc, t))
+        ))
+    }
+
+    /// Declare the interface `id`, whose body is the object type `body`.
+    fn gen_interface(
+        &mut self,
+        gc: &'gc GCLock,
+        prefix: &str,
+        id: &'gc Node<'gc>,
+        type_parameters: Option<&'gc Node<'gc>>,
+        extends: NodeList<'gc>,
+        body: &'gc Node<'gc>,
+    ) {
+        let mut header = format!(
+            "{}interface {}{}",
+            prefix,
+            ident(gc, id),
+            type_params(gc, type_parameters)
+        );
+        if !extends.is_empty() {
+            header.push_str(" extends ");
+            header.push_str(&join(
+                extends.iter().map(|e| interface_extends(gc, e)),
+                ", ",
+            ));
+        }
+        let (members, _) = object_members(gc, body);
+        self.gen_body(header, members);
+    }
+
+    /// Declare a block like a class or an interface, whose first line is `header`.
+    fn gen_body(&mut self, header: String, members: Vec<String>) {
+        if members.is_empty() {
+            self.line(format!("{} {{}}", header));
+            return;
+        }
+        self.line(format!("{} {{", header));
+        for member in members {
+            self.line(format!("  {};", member));
+        }
+        self.line("}");
+    }
+
+    fn gen_class(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, prefix: &str) {
+        let (id, type_parameters, super_class, super_type_parameters, implements, body) = match node
+        {
+            Node::ClassDeclaration(class) => {
+                let ClassDeclaration {
+                    id,
+                    type_parameters,
+                    super_class,
+                    super_type_parameters,
+                    implements,
+                    body,
+                    ..
+                } = &**class;
+                (
+                    id,
+                    type_parameters,
+                    super_class,
+                    super_type_parameters,
+                    implements,
+                    body,
+                )
+            }
+            _ => return,
+        };
+        let mut header = format!("{}{}class", prefix, declare(prefix));
+        if let Some(id) = id {
+            header.push(' ');
+            header.push_str(&ident(gc, id));
+        }
+        header.push_str(&type_params(gc, *type_parameters));
+        if let Some(name) = super_class.and_then(|class| expression_name(gc, class)) {
+            header.push_str(" extends ");
+            header.push_str(&name);
+            header.push_str(&type_args(gc, *super_type_parameters));
+        }
+        if !implements.is_empty() {
+            header.push_str(" implements ");
+            header.push_str(&join(
+                implements.iter().map(|i| interface_extends(gc, i)),
+                ", ",
+            ));
+        }
+        let members = match body {
+            Node::ClassBody(ClassBody { body, .. }) => body
+                .iter()
+                .filter_map(|member| class_member(gc, member, self.docs.get(member)))
+                .collect(),
+            _ => vec![],
+        };
+        self.gen_body(header, members);
+    }
+
+    fn gen_enum(&mut self, gc: &'gc GCLock, name: &str, body: &'gc Node<'gc>, prefix: &str) {
+        let mut members = vec![];
+        match body {
+            Node::EnumStringBody(EnumStringBody { members: list, .. })
+            | Node::EnumNumberBody(EnumNumberBody { members: list, .. }) => {
+                for member in list.iter() {
+                    members.push(match member {
+                        Node::EnumDefaultedMember(EnumDefaultedMember { id, .. }) => {
+                            let id = ident(gc, id);
+                            format!("{} = {}", id, quote(&id))
+                        }
+                        Node::EnumStringMember(EnumStringMember { id, init, .. }) => {
+                            format!("{} = {}", ident(gc, id), string_literal(gc, init))
+                        }
+                        Node::EnumNumberMember(EnumNumberMember {
+                            id,
+                            init: Node::NumericLiteral(NumericLiteral { value, .. }),
+                            ..
+                        }) => format!("{} = {}", ident(gc, id), value),
+                        _ => continue,
+                    });
+                }
+                self.line(format!("{}{}enum {} {{", prefix, declare(prefix), name));
+                for member in members {
+                    self.line(format!("  {},", member));
+                }
+                self.line("}");
+            }
+            // TypeScript enums can only have string and number values.
+            Node::EnumBooleanBody(EnumBooleanBody { members: list, .. })
+            | Node::EnumSymbolBody(EnumSymbolBody { members: list, .. }) => {
+                for member in list.iter() {
+                    members.push(match member {
+                        Node::EnumBooleanMember(EnumBooleanMember {
+                            id,
+                            init: Node::BooleanLiteral(BooleanLiteral { value, .. }),
+                            ..
+                        }) => format!("readonly {}: {}", ident(gc, id), value),
+                        Node::EnumDefaultedMember(EnumDefaultedMember { id, .. }) => {
+                            format!("readonly {}: symbol", ident(gc, id))
+                        }
+                        _ => continue,
+                    });
+                }
+                self.line(format!(
+                    "{}{}const {}: {{ {} }};",
+                    prefix,
+                    declare(prefix),
+                    name,
+                    members.join("; ")
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The key of the class member `key`, `[Symbol.iterator]` if it is `computed`.
+/// Returns `None` for private names and keys which can't be declared.
+fn class_key(gc: &GCLock, key: &Node, computed: bool) -> Option<String> {
+    if computed {
+        expression_name(gc, key).map(|name| format!("[{}]", name))
+    } else {
+        property_key(gc, key)
+    }
+}
+
+/// The declaration of the class member `node`, documented by `doc`, if it is public.
+fn class_member(gc: &GCLock, node: &Node, doc: Option<&JSDoc>) -> Option<String> {
+    match node {
+        Node::MethodDefinition(MethodDefinition {
+            key,
+            value,
+            kind,
+            computed,
+            is_static,
+            ..
+        }) => {
+            let key = class_key(gc, key, *computed)?;
+            let is_static = if *is_static { "static " } else { "" };
+            Some(match kind {
+                MethodDefinitionKind::Constructor => {
+                    format!(
+                        "constructor({})",
+                        params(gc, &value.function_like_params(), doc)
+                    )
+                }
+                MethodDefinitionKind::Get => {
+                    format!(
+                        "{}get {}{}",
+                        is_static,
+                        key,
+                        signature(gc, value, false, doc)
+                    )
+                }
+                MethodDefinitionKind::Set => format!(
+                    "{}set {}({})",
+                    is_static,
+                    key,
+                    params(gc, &value.function_like_params(), doc)
+                ),
+                MethodDefinitionKind::Method => {
+                    format!("{}{}{}", is_static, key, signature(gc, value, false, doc))
+                }
+            })
+        }
+        Node::ClassProperty(prop) => {
+            let ClassProperty {
+                key,
+                value,
+                computed,
+                is_static,
+                optional,
+                variance,
+                type_annotation,
+                ..
+            } = &**prop;
+            Some(format!(
+                "{}{}{}{}: {}",
+                if *is_static { "static " } else { "" },
+                if is_covariant(gc, *variance) {
+                    "readonly "
+                } else {
+                    ""
+                },
+                class_key(gc, key, *computed)?,
+                if *optional { "?" } else { "" },
+                match (
+                    type_annotation,
+                    doc.and_then(|doc| doc.type_expr.as_deref()),
+                    value
+                ) {
+                    (Some(t), _, _) => ty(gc, t),
+                    (None, Some(t), _) => jsdoc_type(t),
+                    (None, None, Some(value)) => infer(gc, value, doc),
+                    (None, None, None) => "any".to_string(),
+                }
+            ))
+        }
+        _ => None,
+    }
+}
@@ -12,15 +12,24 @@ use std::io::Write;
 use std::rc::Rc;
 
 use juno_support::convert;
+use juno_support::ident;
 use juno_support::source_manager::SourceLoc;
 use sourcemap::RawToken;
 use sourcemap::SourceMap;
 use sourcemap::SourceMapBuilder;
+use thiserror::Error;
 
 use crate::ast::*;
+use crate::hparser;
+use crate::precedence;
+use crate::precedence::Assoc;
+use crate::precedence::ChildPos;
 use crate::sema::DeclKind;
 use crate::sema::Resolution;
 use crate::sema::SemContext;
+use crate::sourcemap::add_sources_content;
+use crate::sourcemap::compose_registered;
+use crate::sourcemap::data_url;
 
 /// Options for JS generation.
 pub struct Opt<'s> {
@@ -38,6 +47,35 @@ pub struct Opt<'s> {
 
     /// Delimiter to use for string literals.
     pub quote: QuoteChar,
+
+    /// How the generated JS refers to its source map.
+    pub source_map: SourceMapKind,
+
+    /// URL of the external source map, referenced by the `sourceMappingURL` comment
+    /// when `source_map` is `File`.
+    pub source_map_url: Option<String>,
+
+    /// Whether to embed the contents of the sources in the source map.
+    pub sources_content: bool,
+
+    /// Whether to make the output safe to embed in an HTML `<script>` element:
+    /// `</script` and `<!--` are escaped in literals, templates and the doc block,
+    /// line and paragraph separators are escaped in templates, and operators are spaced
+    /// so they don't form `<!--` or `-->`.
+    pub inline_script_safe: bool,
+
+    /// Whether to also escape the non-ASCII characters in templates, regular expressions
+    /// and JSX text, making the output ASCII apart from the doc block.
+    /// Identifiers and string literals are always escaped.
+    pub ascii_only: bool,
+
+    /// Whether to save bytes beyond removing whitespace: numbers are written in their
+    /// shortest form, consecutive declarations of the same kind are merged,
+    /// and the semicolon before a `}` or the end of the program is omitted.
+    pub compress: bool,
+
+    /// Whether to write `true` and `false` as `!0` and `!1`.
+    pub compress_booleans: bool,
 }
 
 impl Default for Opt<'_> {
@@ -48,6 +86,13 @@ impl Default for Opt<'_> {
             force_async_arrow_space: true,
             doc_block: None,
             quote: QuoteChar::Single,
+            source_map: SourceMapKind::None,
+            source_map_url: None,
+            sources_content: false,
+            inline_script_safe: false,
+            ascii_only: false,
+            compress: false,
+            compress_booleans: false,
         }
     }
 }
@@ -67,6 +112,20 @@ pub enum Pretty {
     Yes,
 }
 
+/// How the generated JS refers to its source map.
+/// The source map is returned by [`generate`] in every case, for the caller to save.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SourceMapKind {
+    /// No `sourceMappingURL` comment.
+    None,
+    /// Embed the source map in the `sourceMappingURL` comment as a data URL.
+    Inline,
+    /// Refer to the external source map at `Opt::source_map_url`.
+    File,
+    /// Embed the source map, which is also saved externally by the caller.
+    Both,
+}
+
 /// Delimiter to use for string literals.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum QuoteChar {
@@ -86,7 +145,9 @@ impl QuoteChar {
 }
 
 /// Generate JS for `root` and print it to `out`.
-/// FIXME: This currently only returns an empty SourceMap.
+/// Returns the source map of the generated JS, composed with any input source maps
+/// registered in the `SourceManager`.
+/// A `sourceMappingURL` comment is appended as specified by `opt.source_map`.
 pub fn generate(
     out: &mut dyn Write,
     ctx: &mut Context,
@@ -97,84 +158,87 @@ pub fn generate(
     GenJS::gen_root(out, &gc, root.node(&gc), opt)
 }
 
-/// Associativity direction.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Assoc {
-    /// Left to right associativity.
-    Ltr,
-
-    /// Right to left associativity.
-    Rtl,
+/// Error returned by [`emit_verified`].
+/// The variants caused by a bug in JS generation contain the generated JS.
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("generated JS doesn't parse: {error}")]
+    Reparse {
+        error: hparser::ParseError,
+        output: String,
+    },
+    #[error("generated JS parses to a different AST, starting at line {line} of its text dump")]
+    Mismatch { line: usize, output: String },
+    #[error("the {0} option changes the AST of the generated JS and can't be verified")]
+    UnsupportedOption(&'static str),
 }
 
-mod precedence {
-    use crate::ast::BinaryExpressionOperator;
-    use crate::ast::LogicalExpressionOperator;
-
-    pub type Precedence = u32;
-
-    pub const ALWAYS_PAREN: Precedence = 0;
-    pub const SEQ: Precedence = 1;
-    pub const ARROW: Precedence = 2;
-    pub const YIELD: Precedence = 3;
-    pub const ASSIGN: Precedence = 4;
-    pub const COND: Precedence = 5;
-    pub const BIN_START: Precedence = 6;
-    pub const UNARY: Precedence = 26;
-    pub const POST_UPDATE: Precedence = 27;
-    pub const TAGGED_TEMPLATE: Precedence = 28;
-    pub const NEW_NO_ARGS: Precedence = 29;
-    pub const MEMBER: Precedence = 30;
-    pub const PRIMARY: Precedence = 31;
-    pub const TOP: Precedence = 32;
-
-    pub const UNION_TYPE: Precedence = 1;
-    pub const INTERSECTION_TYPE: Precedence = 2;
-
-    pub fn get_binary_precedence(op: BinaryExpressionOperator) -> Precedence {
-        use BinaryExpressionOperator::*;
-        (match op {
-            Exp => 12,
-            Mult => 11,
-            Mod => 11,
-            Div => 11,
-            Plus => 10,
-            Minus => 10,
-            LShift => 9,
-            RShift => 9,
-            RShift3 => 9,
-            Less => 8,
-            Greater => 8,
-            LessEquals => 8,
-            GreaterEquals => 8,
-            LooseEquals => 7,
-            LooseNotEquals => 7,
-            StrictEquals => 7,
-            StrictNotEquals => 7,
-            BitAnd => 6,
-            BitXor => 5,
-            BitOr => 4,
-            In => 8,
-            Instanceof => 8,
-        }) + BIN_START
+/// Like [`generate`], but parse the generated JS with `flags` and check that the result
+/// is structurally equal to `root` before printing it to `out`.
+/// This catches bugs in the handling of precedence and ASI when correctness matters more
+/// than speed. The trees are compared with [`dump_text`], ignoring source ranges.
+/// The raw text of templates, regular expressions and JSX is not compared if `opt` escapes
+/// it with `ascii_only` or `inline_script_safe`.
+/// `compress` and `compress_booleans` are rejected, since the JS they generate parses
+/// to a different AST, e.g. with merged declarations or `!0` instead of `true`.
+pub fn emit_verified(
+    out: &mut dyn Write,
+    ctx: &mut Context,
+    root: &NodeRc,
+    opt: Opt,
+    flags: hparser::ParserFlags,
+) -> Result<SourceMap, VerifyError> {
+    if opt.compress {
+        return Err(VerifyError::UnsupportedOption("compress"));
     }
-
-    pub fn get_logical_precedence(op: LogicalExpressionOperator) -> Precedence {
-        use LogicalExpressionOperator::*;
-        (match op {
-            And => 3,
-            Or => 2,
-            NullishCoalesce => 1,
-        }) + BIN_START
+    if opt.compress_booleans {
+        return Err(VerifyError::UnsupportedOption("compress_booleans"));
+    }
+    let escapes_raw = opt.ascii_only || opt.inline_script_safe;
+    let mut buf = vec![];
+    let sourcemap = generate(&mut buf, ctx, root, opt)?;
+    let output = String::from_utf8(buf).expect("generated JS must be UTF-8");
+    let expected = {
+        let gc = GCLock::new(ctx);
+        dump_text(&gc, root.node(&gc))
+    };
+
+    // Parse in a separate context, to leave the nodes and sources of `ctx` untouched.
+    let mut reparse_ctx = Context::new();
+    let actual = match hparser::parse_with_flags(flags, &output, &mut reparse_ctx) {
+        Ok(reparsed) => {
+            let gc = GCLock::new(&mut reparse_ctx);
+            dump_text(&gc, reparsed.node(&gc))
+        }
+        Err(error) => return Err(VerifyError::Reparse { error, output }),
+    };
+
+    // Lines of the text dumps to compare, with their 1-based line numbers.
+    let comparable = |text: &str| -> Vec<(usize, String)> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                let field = line.trim_start();
+                !(escapes_raw && (field.starts_with("raw: ") || field.starts_with("pattern: ")))
+            })
+            .map(|(i, line)| (i + 1, line.to_string()))
+            .collect()
+    };
+    let (expected, actual) = (comparable(&expected), comparable(&actual));
+    if let Some(i) = (0..expected.len().max(actual.len()))
+        .find(|&i| expected.get(i).map(|l| &l.1) != actual.get(i).map(|l| &l.1))
+    {
+        let (line, _) = expected.get(i).or_else(|| actual.get(i)).unwrap();
+        return Err(VerifyError::Mismatch {
+            line: *line,
+            output,
+        });
     }
-}
 
-/// Child position for the purpose of determining whether the child needs parens.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum ChildPos {
-    Left,
-    Anywhere,
-    Right,
+    out.write_all(output.as_bytes())?;
+    Ok(sourcemap)
 }
 
 /// Whether parens are needed around something.
@@ -202,6 +266,14 @@ enum ForceSpace {
     Yes,
 }
 
+/// Whether a list of statements is followed by the end of a block or of the program,
+/// so the semicolon after the last statement may be omitted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EndsBlock {
+    No,
+    Yes,
+}
+
 /// Whether to force the statements to be emitted inside a new block `{ }`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ForceBlock {
@@ -290,11 +362,18 @@ impl GenJS<'_, '_> {
 
         if let Some(doc_block) = gen_js.opt.doc_block.clone() {
             let mut buf = [0u8; 4];
-            for c in doc_block.chars() {
+            for (i, c) in doc_block.char_indices() {
                 if c == '\n' {
                     gen_js.force_newline_without_indent();
                 } else {
                     gen_js.write_char(c, &mut buf);
+                    // Comments can't contain escapes, so break up the sequence instead.
+                    if c == '<'
+                        && gen_js.opt.inline_script_safe
+                        && is_html_unsafe_after_lt(doc_block[i + 1..].chars())
+                    {
+                        out!(gen_js, "\\");
+                    }
                 }
             }
         }
@@ -303,13 +382,26 @@ impl GenJS<'_, '_> {
         gen_js.force_newline();
 
         gen_js.flush_cur_token();
-        match gen_js.error {
-            None => gen_js
-                .out
-                .flush()
-                .and(Ok(gen_js.sourcemap.into_sourcemap())),
-            Some(err) => Err(err),
+        if let Some(err) = gen_js.error {
+            return Err(err);
+        }
+
+        // Chain with the input source maps, so the result maps to the true originals.
+        let generated = gen_js.sourcemap.into_sourcemap();
+        let mut sourcemap = compose_registered(ctx.sm(), &generated).unwrap_or(generated);
+        if gen_js.opt.sources_content {
+            add_sources_content(ctx.sm(), &mut sourcemap);
+        }
+        let url = match gen_js.opt.source_map {
+            SourceMapKind::None => None,
+            SourceMapKind::Inline | SourceMapKind::Both => Some(data_url(&sourcemap)),
+            SourceMapKind::File => gen_js.opt.source_map_url.clone(),
+        };
+        if let Some(url) = url {
+            writeln!(gen_js.out, "//# sourceMappingURL={}", url)?;
         }
+        gen_js.out.flush()?;
+        Ok(sourcemap)
     }
 
     /// Write to the `out` writer if we haven't seen any errors.
@@ -365,34 +457,71 @@ impl GenJS<'_, '_> {
             Node::Metadata(_) => {}
 
             Node::Program(Program { metadata: _, body }) => {
-                self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body), EndsBlock::Yes);
             }
             Node::Module(Module { metadata: _, body }) => {
-                self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body), EndsBlock::Yes);
             }
 
-            Node::FunctionExpression(FunctionExpression {
-                metadata: _,
-                id,
-                params,
-                body,
-                type_parameters,
-                return_type,
-                predicate,
-                generator,
-                is_async,
-            })
-            | Node::FunctionDeclaration(FunctionDeclaration {
-                metadata: _,
-                id,
-                params,
-                body,
-                type_parameters,
-                return_type,
-                predicate,
-                generator,
-                is_async,
-            }) => {
+            Node::FunctionExpression(_) | Node::FunctionDeclaration(_) => {
+                let (
+                    id,
+                    params,
+                    body,
+                    type_parameters,
+                    return_type,
+                    predicate,
+                    generator,
+                    is_async,
+                ) = match node {
+                    Node::FunctionExpression(func) => {
+                        let FunctionExpression {
+                            metadata: _,
+                            id,
+                            params,
+                            body,
+                            type_parameters,
+                            return_type,
+                            predicate,
+                            generator,
+                            is_async,
+                        } = &**func;
+                        (
+                            id,
+                            params,
+                            body,
+                            type_parameters,
+                            return_type,
+                            predicate,
+                            generator,
+                            is_async,
+                        )
+                    }
+                    Node::FunctionDeclaration(func) => {
+                        let FunctionDeclaration {
+                            metadata: _,
+                            id,
+                            params,
+                            body,
+                            type_parameters,
+                            return_type,
+                            predicate,
+                            generator,
+                            is_async,
+                        } = &**func;
+                        (
+                            id,
+                            params,
+                            body,
+                            type_parameters,
+                            return_type,
+                            predicate,
+                            generator,
+                            is_async,
+                        )
+                    }
+                    _ => unreachable!(),
+                };
                 if *is_async {
                     out_token!(self, node, "async function");
                 } else {
@@ -415,22 +544,23 @@ impl GenJS<'_, '_> {
                     *type_parameters,
                     *return_type,
                     *predicate,
-                    *body,
+                    body,
                     node,
                 );
             }
 
-            Node::ArrowFunctionExpression(ArrowFunctionExpression {
-                metadata: _,
-                id: _,
-                params,
-                body,
-                type_parameters,
-                return_type,
-                predicate,
-                expression,
-                is_async,
-            }) => {
+            Node::ArrowFunctionExpression(arrow) => {
+                let ArrowFunctionExpression {
+                    metadata: _,
+                    id: _,
+                    params,
+                    body,
+                    type_parameters,
+                    return_type,
+                    predicate,
+                    expression,
+                    is_async,
+                } = &**arrow;
                 let mut need_sep = false;
                 if *is_async {
                     out!(self, "async");
@@ -577,13 +707,14 @@ impl GenJS<'_, '_> {
                     Path::new(node, NodeField::body),
                 );
             }
-            Node::ForOfStatement(ForOfStatement {
-                metadata: _,
-                left,
-                right,
-                body,
-                is_await,
-            }) => {
+            Node::ForOfStatement(for_of) => {
+                let ForOfStatement {
+                    metadata: _,
+                    left,
+                    right,
+                    body,
+                    is_await,
+                } = &**for_of;
                 out!(self, "for{}(", if *is_await { " await" } else { "" });
                 left.visit(ctx, self, Some(Path::new(node, NodeField::left)));
                 out!(self, " of ");
@@ -591,18 +722,19 @@ impl GenJS<'_, '_> {
                 out!(self, ")");
                 self.visit_stmt_or_block(
                     ctx,
-                    *body,
+                    body,
                     ForceBlock::No,
                     Path::new(node, NodeField::body),
                 );
             }
-            Node::ForStatement(ForStatement {
-                metadata: _,
-                init,
-                test,
-                update,
-                body,
-            }) => {
+            Node::ForStatement(for_stmt) => {
+                let ForStatement {
+                    metadata: _,
+                    init,
+                    test,
+                    update,
+                    body,
+                } = &**for_stmt;
                 out!(self, "for(");
                 self.print_child(ctx, *init, Path::new(node, NodeField::init), ChildPos::Left);
                 out!(self, ";");
@@ -618,7 +750,7 @@ impl GenJS<'_, '_> {
                 out!(self, ")");
                 self.visit_stmt_or_block(
                     ctx,
-                    *body,
+                    body,
                     ForceBlock::No,
                     Path::new(node, NodeField::body),
                 );
@@ -636,7 +768,12 @@ impl GenJS<'_, '_> {
                     out!(self, "{{");
                     self.inc_indent();
                     self.newline();
-                    self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                    self.visit_stmt_list(
+                        ctx,
+                        body,
+                        Path::new(node, NodeField::body),
+                        EndsBlock::Yes,
+                    );
                     self.dec_indent();
                     self.newline();
                     out!(self, "}}");
@@ -730,7 +867,12 @@ impl GenJS<'_, '_> {
                 if !consequent.is_empty() {
                     self.inc_indent();
                     self.newline();
-                    self.visit_stmt_list(ctx, consequent, Path::new(node, NodeField::consequent));
+                    self.visit_stmt_list(
+                        ctx,
+                        consequent,
+                        Path::new(node, NodeField::consequent),
+                        EndsBlock::No,
+                    );
                     self.dec_indent();
                 }
             }
@@ -826,7 +968,11 @@ impl GenJS<'_, '_> {
             }
 
             Node::BooleanLiteral(BooleanLiteral { metadata: _, value }) => {
-                out_token!(self, node, "{}", if *value { "true" } else { "false" });
+                if self.opt.compress_booleans && !is_exponentiation_base(path, node) {
+                    out_token!(self, node, "{}", if *value { "!0" } else { "!1" });
+                } else {
+                    out_token!(self, node, "{}", if *value { "true" } else { "false" });
+                }
             }
             Node::NullLiteral(_) => {
                 out_token!(self, node, "null");
@@ -837,7 +983,7 @@ impl GenJS<'_, '_> {
                 out!(self, "{}", self.opt.quote.as_char());
             }
             Node::NumericLiteral(NumericLiteral { metadata: _, value }) => {
-                out_token!(self, node, "{}", convert::number_to_string(*value));
+                out_token!(self, node, "{}", self.number_to_string(*value));
             }
             Node::BigIntLiteral(BigIntLiteral {
                 metadata: _,
@@ -854,7 +1000,7 @@ impl GenJS<'_, '_> {
                 out_token!(self, node, "/");
                 // Parser doesn't handle escapes when lexing RegExp,
                 // so we don't need to do any manual escaping here.
-                self.write_utf8(ctx.str(*pattern));
+                self.write_escaped_raw(ctx.str(*pattern));
                 out!(self, "/");
                 self.write_utf8(ctx.str(*flags));
             }
@@ -1046,13 +1192,14 @@ impl GenJS<'_, '_> {
                 }
                 out!(self, ")");
             }
-            Node::OptionalCallExpression(OptionalCallExpression {
-                metadata: _,
-                callee,
-                type_arguments,
-                arguments,
-                optional,
-            }) => {
+            Node::OptionalCallExpression(call) => {
+                let OptionalCallExpression {
+                    metadata: _,
+                    callee,
+                    type_arguments,
+                    arguments,
+                    optional,
+                } = &**call;
                 self.print_child(
                     ctx,
                     Some(*callee),
@@ -1167,9 +1314,14 @@ impl GenJS<'_, '_> {
                 match object {
                     Node::NumericLiteral(NumericLiteral { value, .. }) => {
                         // Account for possible `50..toString()`.
-                        let string = convert::number_to_string(*value);
-                        // If there is an `e` or a decimal point, no need for an extra `.`.
-                        let suffix = string.find::<&[char]>(&['E', 'e', '.']).map_or(".", |_| "");
+                        let string = self.number_to_string(*value);
+                        // If there is an `e`, a decimal point or a hex prefix,
+                        // no need for an extra `.`.
+                        let suffix = if string.bytes().all(|c| c.is_ascii_digit()) {
+                            "."
+                        } else {
+                            ""
+                        };
                         out_token!(self, node, "{}{}", string, suffix);
                     }
                     _ => {
@@ -1231,20 +1383,24 @@ impl GenJS<'_, '_> {
                 right,
                 operator,
             }) => {
-                let ident = operator.as_str().chars().next().unwrap().is_alphabetic();
+                let op = operator.as_str();
+                let ident = op.chars().next().unwrap().is_alphabetic();
+                // Avoid forming `a-->b` or `a<!--b`, which look like HTML comments.
+                let space_before = ident || (self.opt.inline_script_safe && op.starts_with('>'));
+                let space_after = ident || (self.opt.inline_script_safe && op.ends_with('<'));
                 self.print_child(
                     ctx,
                     Some(*left),
                     Path::new(node, NodeField::left),
                     ChildPos::Left,
                 );
-                self.space(if ident {
+                self.space(if space_before {
                     ForceSpace::Yes
                 } else {
                     ForceSpace::No
                 });
-                out!(self, "{}", operator.as_str());
-                self.space(if ident {
+                out!(self, "{}", op);
+                self.space(if space_after {
                     ForceSpace::Yes
                 } else {
                     ForceSpace::No
@@ -1303,7 +1459,8 @@ impl GenJS<'_, '_> {
                 optional,
             }) => {
                 self.add_segment(node);
-                self.write_utf8(ctx.str(*name).as_ref());
+                // Like string literals, identifiers are emitted as ASCII.
+                out!(self, "{}", ident::escape_identifier_name(ctx.str(*name)));
                 self.annotate_identifier(ctx, node);
                 if *optional {
                     out!(self, "?");
@@ -1356,15 +1513,10 @@ impl GenJS<'_, '_> {
             Node::VariableDeclaration(VariableDeclaration {
                 metadata: _,
                 kind,
-                declarations,
+                declarations: _,
             }) => {
                 out_token!(self, node, "{} ", kind.as_str());
-                for (i, decl) in declarations.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    decl.visit(ctx, self, Some(Path::new(node, NodeField::declarations)));
-                }
+                self.visit_declarators(ctx, node);
             }
             Node::VariableDeclarator(VariableDeclarator {
                 metadata: _,
@@ -1400,13 +1552,11 @@ impl GenJS<'_, '_> {
                         cooked: _,
                     }) = quasi
                     {
-                        let mut buf = [0u8; 4];
-                        for char in ctx.str(*raw).chars() {
-                            if char == '\n' {
+                        for (i, line) in ctx.str(*raw).split('\n').enumerate() {
+                            if i > 0 {
                                 self.force_newline_without_indent();
-                                continue;
                             }
-                            self.write_char(char, &mut buf);
+                            self.write_escaped_raw(line);
                         }
                         if let Some(expr) = it_expr.next() {
                             out!(self, "${{");
@@ -1454,12 +1604,13 @@ impl GenJS<'_, '_> {
                     need_sep = true;
                 } else if *method {
                     match value {
-                        Node::FunctionExpression(FunctionExpression {
-                            metadata: _,
-                            generator,
-                            is_async,
-                            ..
-                        }) => {
+                        Node::FunctionExpression(func) => {
+                            let FunctionExpression {
+                                metadata: _,
+                                generator,
+                                is_async,
+                                ..
+                            } = &**func;
                             if *is_async {
                                 out!(self, "async");
                                 need_sep = true;
@@ -1495,26 +1646,27 @@ impl GenJS<'_, '_> {
                 }
                 if *kind != PropertyKind::Init || *method {
                     match value {
-                        Node::FunctionExpression(FunctionExpression {
-                            metadata: _,
-                            // Name is handled by the property key.
-                            id: _,
-                            params,
-                            body,
-                            return_type,
-                            predicate,
-                            type_parameters,
-                            // Handled above.
-                            generator: _,
-                            is_async: _,
-                        }) => {
+                        Node::FunctionExpression(func) => {
+                            let FunctionExpression {
+                                metadata: _,
+                                // Name is handled by the property key.
+                                id: _,
+                                params,
+                                body,
+                                return_type,
+                                predicate,
+                                type_parameters,
+                                // Handled above.
+                                generator: _,
+                                is_async: _,
+                            } = &**func;
                             self.visit_func_params_body(
                                 ctx,
                                 params,
                                 *type_parameters,
                                 *return_type,
                                 *predicate,
-                                *body,
+                                body,
                                 *value,
                             );
                         }
@@ -1550,26 +1702,60 @@ impl GenJS<'_, '_> {
                 );
             }
 
-            Node::ClassExpression(ClassExpression {
-                metadata: _,
-                id,
-                type_parameters,
-                super_class,
-                super_type_parameters,
-                implements,
-                decorators,
-                body,
-            })
-            | Node::ClassDeclaration(ClassDeclaration {
-                metadata: _,
-                id,
-                type_parameters,
-                super_class,
-                super_type_parameters,
-                implements,
-                decorators,
-                body,
-            }) => {
+            Node::ClassExpression(_) | Node::ClassDeclaration(_) => {
+                let (
+                    id,
+                    type_parameters,
+                    super_class,
+                    super_type_parameters,
+                    implements,
+                    decorators,
+                    body,
+                ) = match node {
+                    Node::ClassExpression(class) => {
+                        let ClassExpression {
+                            metadata: _,
+                            id,
+                            type_parameters,
+                            super_class,
+                            super_type_parameters,
+                            implements,
+                            decorators,
+                            body,
+                        } = &**class;
+                        (
+                            id,
+                            type_parameters,
+                            super_class,
+                            super_type_parameters,
+                            implements,
+                            decorators,
+                            body,
+                        )
+                    }
+                    Node::ClassDeclaration(class) => {
+                        let ClassDeclaration {
+                            metadata: _,
+                            id,
+                            type_parameters,
+                            super_class,
+                            super_type_parameters,
+                            implements,
+                            decorators,
+                            body,
+                        } = &**class;
+                        (
+                            id,
+                            type_parameters,
+                            super_class,
+                            super_type_parameters,
+                            implements,
+                            decorators,
+                            body,
+                        )
+                    }
+                    _ => unreachable!(),
+                };
                 if !decorators.is_empty() {
                     for decorator in decorators.iter() {
                         decorator.visit(ctx, self, Some(Path::new(node, NodeField::decorators)));
@@ -1631,18 +1817,19 @@ impl GenJS<'_, '_> {
                     self.newline();
                 }
             }
-            Node::ClassProperty(ClassProperty {
-                metadata: _,
-                key,
-                value,
-                computed,
-                is_static,
-                declare,
-                optional,
-                variance,
-                type_annotation,
-                ts_modifiers: None,
-            }) => {
+            Node::ClassProperty(prop) if prop.ts_modifiers.is_none() => {
+                let ClassProperty {
+                    metadata: _,
+                    key,
+                    value,
+                    computed,
+                    is_static,
+                    declare,
+                    optional,
+                    variance,
+                    type_annotation,
+                    ts_modifiers: _,
+                } = &**prop;
                 if *declare {
                     out!(self, "declare ");
                 }
@@ -1679,17 +1866,18 @@ impl GenJS<'_, '_> {
                 }
                 out!(self, ";");
             }
-            Node::ClassPrivateProperty(ClassPrivateProperty {
-                metadata: _,
-                key,
-                value,
-                is_static,
-                declare,
-                optional,
-                variance,
-                type_annotation,
-                ts_modifiers: None,
-            }) => {
+            Node::ClassPrivateProperty(prop) if prop.ts_modifiers.is_none() => {
+                let ClassPrivateProperty {
+                    metadata: _,
+                    key,
+                    value,
+                    is_static,
+                    declare,
+                    optional,
+                    variance,
+                    type_annotation,
+                    ts_modifiers: _,
+                } = &**prop;
                 if let Some(variance) = variance {
                     variance.visit(ctx, self, Some(Path::new(node, NodeField::variance)));
                 }
@@ -1721,6 +1909,52 @@ impl GenJS<'_, '_> {
                 }
                 out!(self, ";");
             }
+            Node::ClassAccessorProperty(ClassAccessorProperty {
+                metadata: _,
+                key,
+                value,
+                computed,
+                is_static,
+            }) => {
+                if *is_static {
+                    out!(self, "static ");
+                }
+                out!(self, "accessor ");
+                if *computed {
+                    out!(self, "[");
+                }
+                key.visit(ctx, self, Some(Path::new(node, NodeField::key)));
+                if *computed {
+                    out!(self, "]");
+                }
+                if let Some(value) = value {
+                    self.space(ForceSpace::No);
+                    out!(self, "=");
+                    self.space(ForceSpace::No);
+                    value.visit(ctx, self, Some(Path::new(node, NodeField::value)));
+                }
+                out!(self, ";");
+            }
+            Node::StaticBlock(StaticBlock { metadata: _, body }) => {
+                out!(self, "static");
+                self.space(ForceSpace::No);
+                if body.is_empty() {
+                    out!(self, "{{}}");
+                } else {
+                    out!(self, "{{");
+                    self.inc_indent();
+                    self.newline();
+                    self.visit_stmt_list(
+                        ctx,
+                        body,
+                        Path::new(node, NodeField::body),
+                        EndsBlock::Yes,
+                    );
+                    self.dec_indent();
+                    self.newline();
+                    out!(self, "}}");
+                }
+            }
             Node::MethodDefinition(MethodDefinition {
                 metadata: _,
                 key,
@@ -1731,25 +1965,28 @@ impl GenJS<'_, '_> {
             }) => {
                 let (is_async, generator, params, body, return_type, predicate, type_parameters) =
                     match value {
-                        Node::FunctionExpression(FunctionExpression {
-                            metadata: _,
-                            id: _,
-                            generator,
-                            is_async,
-                            params,
-                            body,
-                            return_type,
-                            predicate,
-                            type_parameters,
-                        }) => (
-                            *is_async,
-                            *generator,
-                            params,
-                            body,
-                            return_type,
-                            predicate,
-                            type_parameters,
-                        ),
+                        Node::FunctionExpression(func) => {
+                            let FunctionExpression {
+                                metadata: _,
+                                id: _,
+                                generator,
+                                is_async,
+                                params,
+                                body,
+                                return_type,
+                                predicate,
+                                type_parameters,
+                            } = &**func;
+                            (
+                                *is_async,
+                                *generator,
+                                params,
+                                body,
+                                return_type,
+                                predicate,
+                                type_parameters,
+                            )
+                        }
                         _ => {
                             unreachable!("Invalid method value");
                         }
@@ -1788,18 +2025,19 @@ impl GenJS<'_, '_> {
                     *type_parameters,
                     *return_type,
                     *predicate,
-                    *body,
+                    body,
                     node,
                 );
             }
 
-            Node::ImportDeclaration(ImportDeclaration {
-                metadata: _,
-                specifiers,
-                source,
-                assertions,
-                import_kind,
-            }) => {
+            Node::ImportDeclaration(import) => {
+                let ImportDeclaration {
+                    metadata: _,
+                    specifiers,
+                    source,
+                    assertions,
+                    import_kind,
+                } = &**import;
                 out_token!(self, node, "import ");
                 if *import_kind != ImportKind::Value {
                     out!(self, "{} ", import_kind.as_str());
@@ -1829,7 +2067,7 @@ impl GenJS<'_, '_> {
                 source.visit(ctx, self, Some(Path::new(node, NodeField::source)));
                 if let Some(assertions) = assertions {
                     if !assertions.is_empty() {
-                        out!(self, " assert {{");
+                        out!(self, " with {{");
                         for (i, attribute) in assertions.iter().enumerate() {
                             if i > 0 {
                                 self.comma();
@@ -1875,13 +2113,14 @@ impl GenJS<'_, '_> {
                 value.visit(ctx, self, Some(Path::new(node, NodeField::value)));
             }
 
-            Node::ExportNamedDeclaration(ExportNamedDeclaration {
-                metadata: _,
-                declaration,
-                specifiers,
-                source,
-                export_kind,
-            }) => {
+            Node::ExportNamedDeclaration(export) => {
+                let ExportNamedDeclaration {
+                    metadata: _,
+                    declaration,
+                    specifiers,
+                    source,
+                    export_kind,
+                } = &**export;
                 out_token!(self, node, "export ");
                 if let Some(declaration) = declaration {
                     declaration.visit(ctx, self, Some(Path::new(node, NodeField::declaration)));
@@ -1889,14 +2128,23 @@ impl GenJS<'_, '_> {
                     if *export_kind != ExportKind::Value {
                         out!(self, "{} ", export_kind.as_str());
                     }
-                    out!(self, "{{");
+                    // `export * as ns from 'source'` has no braces.
+                    let braces = !matches!(
+                        specifiers.iter().next(),
+                        Some(Node::ExportNamespaceSpecifier(_))
+                    );
+                    if braces {
+                        out!(self, "{{");
+                    }
                     for (i, spec) in specifiers.iter().enumerate() {
                         if i > 0 {
                             self.comma();
                         }
                         spec.visit(ctx, self, Some(Path::new(node, NodeField::specifiers)));
                     }
-                    out!(self, "}}");
+                    if braces {
+                        out!(self, "}}");
+                    }
                     if let Some(source) = source {
                         out!(self, " from ");
                         source.visit(ctx, self, Some(Path::new(node, NodeField::source)));
@@ -2035,13 +2283,14 @@ impl GenJS<'_, '_> {
                 expression.visit(ctx, self, Some(Path::new(node, NodeField::expression)));
                 out!(self, "}}");
             }
-            Node::JSXOpeningElement(JSXOpeningElement {
-                metadata: _,
-                name,
-                attributes,
-                self_closing,
-                type_arguments,
-            }) => {
+            Node::JSXOpeningElement(opening) => {
+                let JSXOpeningElement {
+                    metadata: _,
+                    name,
+                    attributes,
+                    self_closing,
+                    type_arguments,
+                } = &**opening;
                 out!(self, "<");
                 name.visit(ctx, self, Some(Path::new(node, NodeField::name)));
                 if let Some(type_arguments) = type_arguments {
@@ -2090,28 +2339,14 @@ impl GenJS<'_, '_> {
                 value: _,
                 raw,
             }) => {
-                let mut buf = [0u8; 4];
-                for char in ctx.str(*raw).chars() {
-                    if char == '\n' {
-                        self.force_newline_without_indent();
-                        continue;
-                    }
-                    self.write_char(char, &mut buf);
-                }
+                self.write_jsx_raw(ctx.str(*raw));
             }
             Node::JSXText(JSXText {
                 metadata: _,
                 value: _,
                 raw,
             }) => {
-                let mut buf = [0u8; 4];
-                for char in ctx.str(*raw).chars() {
-                    if char == '\n' {
-                        self.force_newline_without_indent();
-                        continue;
-                    }
-                    self.write_char(char, &mut buf);
-                }
+                self.write_jsx_raw(ctx.str(*raw));
             }
             Node::JSXElement(JSXElement {
                 metadata: _,
@@ -2220,14 +2455,15 @@ impl GenJS<'_, '_> {
             Node::VoidTypeAnnotation(_) => {
                 out_token!(self, node, "void");
             }
-            Node::FunctionTypeAnnotation(FunctionTypeAnnotation {
-                metadata: _,
-                params,
-                this,
-                return_type,
-                rest,
-                type_parameters,
-            }) => {
+            Node::FunctionTypeAnnotation(func_type) => {
+                let FunctionTypeAnnotation {
+                    metadata: _,
+                    params,
+                    this,
+                    return_type,
+                    rest,
+                    type_parameters,
+                } = &**func_type;
                 if let Some(type_parameters) = type_parameters {
                     type_parameters.visit(
                         ctx,
@@ -2460,13 +2696,14 @@ impl GenJS<'_, '_> {
                 }
                 right.visit(ctx, self, Some(Path::new(node, NodeField::right)));
             }
-            Node::OpaqueType(OpaqueType {
-                metadata: _,
-                id,
-                type_parameters,
-                impltype,
-                supertype,
-            }) => {
+            Node::OpaqueType(opaque) => {
+                let OpaqueType {
+                    metadata: _,
+                    id,
+                    type_parameters,
+                    impltype,
+                    supertype,
+                } = &**opaque;
                 out_token!(self, node, "opaque type ");
                 id.visit(ctx, self, Some(Path::new(node, NodeField::id)));
                 if let Some(type_parameters) = type_parameters {
@@ -2488,41 +2725,50 @@ impl GenJS<'_, '_> {
                 }
                 impltype.visit(ctx, self, Some(Path::new(node, NodeField::impltype)));
             }
-            Node::InterfaceDeclaration(InterfaceDeclaration {
-                metadata: _,
-                id,
-                type_parameters,
-                extends,
-                body,
-            })
-            | Node::DeclareInterface(DeclareInterface {
-                metadata: _,
-                id,
-                type_parameters,
-                extends,
-                body,
-            }) => {
+            Node::InterfaceDeclaration(interface) => {
+                let InterfaceDeclaration {
+                    metadata: _,
+                    id,
+                    type_parameters,
+                    extends,
+                    body,
+                } = &**interface;
                 self.visit_interface(
                     ctx,
-                    if matches!(node, Node::InterfaceDeclaration(_)) {
-                        "interface"
-                    } else {
-                        "declare interface"
-                    },
-                    *id,
+                    "interface",
+                    id,
                     *type_parameters,
                     extends,
-                    *body,
+                    body,
                     node,
                 );
             }
-            Node::DeclareOpaqueType(DeclareOpaqueType {
-                metadata: _,
-                id,
-                type_parameters,
-                impltype,
-                supertype,
-            }) => {
+            Node::DeclareInterface(interface) => {
+                let DeclareInterface {
+                    metadata: _,
+                    id,
+                    type_parameters,
+                    extends,
+                    body,
+                } = &**interface;
+                self.visit_interface(
+                    ctx,
+                    "declare interface",
+                    id,
+                    *type_parameters,
+                    extends,
+                    body,
+                    node,
+                );
+            }
+            Node::DeclareOpaqueType(opaque) => {
+                let DeclareOpaqueType {
+                    metadata: _,
+                    id,
+                    type_parameters,
+                    impltype,
+                    supertype,
+                } = &**opaque;
                 if matches!(path,
                     Some(path) if !matches!(path.parent, Node::DeclareExportDeclaration(_)))
                     || path.is_none()
@@ -2552,15 +2798,16 @@ impl GenJS<'_, '_> {
                     impltype.visit(ctx, self, Some(Path::new(node, NodeField::impltype)));
                 }
             }
-            Node::DeclareClass(DeclareClass {
-                metadata: _,
-                id,
-                type_parameters,
-                extends,
-                implements,
-                mixins,
-                body,
-            }) => {
+            Node::DeclareClass(class) => {
+                let DeclareClass {
+                    metadata: _,
+                    id,
+                    type_parameters,
+                    extends,
+                    implements,
+                    mixins,
+                    body,
+                } = &**class;
                 match path {
                     Some(path) if !matches!(path.parent, Node::DeclareExportDeclaration(_)) => {
                         out!(self, "declare ");
@@ -2636,16 +2883,16 @@ impl GenJS<'_, '_> {
                         match type_annotation {
                             Some(Node::TypeAnnotation(TypeAnnotation {
                                 metadata: _,
-                                type_annotation:
-                                    Node::FunctionTypeAnnotation(FunctionTypeAnnotation {
-                                        metadata: _,
-                                        params,
-                                        this,
-                                        return_type,
-                                        rest,
-                                        type_parameters,
-                                    }),
+                                type_annotation: Node::FunctionTypeAnnotation(func_type),
                             })) => {
+                                let FunctionTypeAnnotation {
+                                    metadata: _,
+                                    params,
+                                    this,
+                                    return_type,
+                                    rest,
+                                    type_parameters,
+                                } = &**func_type;
                                 self.visit_func_type_params(
                                     ctx,
                                     params,
@@ -2689,13 +2936,14 @@ impl GenJS<'_, '_> {
                 }
                 id.visit(ctx, self, Some(Path::new(node, NodeField::id)));
             }
-            Node::DeclareExportDeclaration(DeclareExportDeclaration {
-                metadata: _,
-                declaration,
-                specifiers,
-                source,
-                default,
-            }) => {
+            Node::DeclareExportDeclaration(export) => {
+                let DeclareExportDeclaration {
+                    metadata: _,
+                    declaration,
+                    specifiers,
+                    source,
+                    default,
+                } = &**export;
                 out_token!(self, node, "declare export ");
                 if *default {
                     out!(self, "default ");
@@ -2770,15 +3018,16 @@ impl GenJS<'_, '_> {
             }) => {
                 type_annotation.visit(ctx, self, Some(Path::new(node, NodeField::type_annotation)));
             }
-            Node::ObjectTypeAnnotation(ObjectTypeAnnotation {
-                metadata: _,
-                properties,
-                indexers,
-                call_properties,
-                internal_slots,
-                inexact,
-                exact,
-            }) => {
+            Node::ObjectTypeAnnotation(obj_type) => {
+                let ObjectTypeAnnotation {
+                    metadata: _,
+                    properties,
+                    indexers,
+                    call_properties,
+                    internal_slots,
+                    inexact,
+                    exact,
+                } = &**obj_type;
                 out!(self, "{}", if *exact { "{|" } else { "{" });
                 self.inc_indent();
                 self.newline();
@@ -2829,17 +3078,18 @@ impl GenJS<'_, '_> {
                 self.newline();
                 out!(self, "{}", if *exact { "|}" } else { "}" });
             }
-            Node::ObjectTypeProperty(ObjectTypeProperty {
-                metadata: _,
-                key,
-                value,
-                method,
-                optional,
-                is_static,
-                proto,
-                variance,
-                ..
-            }) => {
+            Node::ObjectTypeProperty(prop) => {
+                let ObjectTypeProperty {
+                    metadata: _,
+                    key,
+                    value,
+                    method,
+                    optional,
+                    is_static,
+                    proto,
+                    variance,
+                    ..
+                } = &**prop;
                 if let Some(variance) = variance {
                     variance.visit(ctx, self, Some(Path::new(node, NodeField::variance)));
                 }
@@ -2855,14 +3105,15 @@ impl GenJS<'_, '_> {
                 }
                 if *method {
                     match value {
-                        Node::FunctionTypeAnnotation(FunctionTypeAnnotation {
-                            metadata: _,
-                            params,
-                            this,
-                            return_type,
-                            rest,
-                            type_parameters,
-                        }) => {
+                        Node::FunctionTypeAnnotation(func_type) => {
+                            let FunctionTypeAnnotation {
+                                metadata: _,
+                                params,
+                                this,
+                                return_type,
+                                rest,
+                                type_parameters,
+                            } = &**func_type;
                             self.visit_func_type_params(
                                 ctx,
                                 params,
@@ -2915,14 +3166,15 @@ impl GenJS<'_, '_> {
                 }
                 if *method {
                     match value {
-                        Node::FunctionTypeAnnotation(FunctionTypeAnnotation {
-                            metadata: _,
-                            params,
-                            this,
-                            return_type,
-                            rest,
-                            type_parameters,
-                        }) => {
+                        Node::FunctionTypeAnnotation(func_type) => {
+                            let FunctionTypeAnnotation {
+                                metadata: _,
+                                params,
+                                this,
+                                return_type,
+                                rest,
+                                type_parameters,
+                            } = &**func_type;
                             self.visit_func_type_params(
                                 ctx,
                                 params,
@@ -2958,14 +3210,15 @@ impl GenJS<'_, '_> {
                     out!(self, "static ");
                 }
                 match value {
-                    Node::FunctionTypeAnnotation(FunctionTypeAnnotation {
-                        metadata: _,
-                        params,
-                        this,
-                        return_type,
-                        rest,
-                        type_parameters,
-                    }) => {
+                    Node::FunctionTypeAnnotation(func_type) => {
+                        let FunctionTypeAnnotation {
+                            metadata: _,
+                            params,
+                            this,
+                            return_type,
+                            rest,
+                            type_parameters,
+                        } = &**func_type;
                         self.visit_func_type_params(
                             ctx,
                             params,
@@ -2983,14 +3236,15 @@ impl GenJS<'_, '_> {
                     }
                 }
             }
-            Node::ObjectTypeIndexer(ObjectTypeIndexer {
-                metadata: _,
-                id,
-                key,
-                value,
-                is_static,
-                variance,
-            }) => {
+            Node::ObjectTypeIndexer(indexer) => {
+                let ObjectTypeIndexer {
+                    metadata: _,
+                    id,
+                    key,
+                    value,
+                    is_static,
+                    variance,
+                } = &**indexer;
                 if *is_static {
                     out!(self, "static ");
                 }
@@ -3039,14 +3293,15 @@ impl GenJS<'_, '_> {
                 }
                 out!(self, ">");
             }
-            Node::TypeParameter(TypeParameter {
-                metadata: _,
-                name,
-                bound,
-                variance,
-                default,
-                uses_extends_bound: _,
-            }) => {
+            Node::TypeParameter(param) => {
+                let TypeParameter {
+                    metadata: _,
+                    name,
+                    bound,
+                    variance,
+                    default,
+                    uses_extends_bound: _,
+                } = &**param;
                 if let Some(variance) = variance {
                     variance.visit(ctx, self, Some(Path::new(node, NodeField::variance)));
                 }
@@ -3303,7 +3558,17 @@ impl GenJS<'_, '_> {
         esc: char,
     ) {
         let str = ctx.str_u16(value);
-        for &c in str {
+        for (i, &c) in str.iter().enumerate() {
+            if c == '<' as u16
+                && self.opt.inline_script_safe
+                && is_html_unsafe_after_lt(
+                    char::decode_utf16(str[i + 1..].iter().copied())
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+                )
+            {
+                out!(self, "\\u003c");
+                continue;
+            }
             if c <= u8::MAX as u16 {
                 match char::from(c as u8) {
                     '\\' => {
@@ -3349,6 +3614,62 @@ impl GenJS<'_, '_> {
         }
     }
 
+    /// Write a line of the body of a template element or regular expression,
+    /// which is already escaped.
+    /// If `inline_script_safe`, escape the `<` of `</script` and `<!--` and any line or
+    /// paragraph separators. If `ascii_only`, escape non-ASCII characters.
+    fn write_escaped_raw(&mut self, s: &str) {
+        if !self.opt.inline_script_safe && (!self.opt.ascii_only || s.is_ascii()) {
+            self.write_utf8(s);
+            return;
+        }
+        let mut buf = [0u8; 4];
+        // Whether the previous character was an unescaped backslash.
+        let mut escaped = false;
+        for (i, c) in s.char_indices() {
+            let escape = match c {
+                '<' if self.opt.inline_script_safe
+                    && is_html_unsafe_after_lt(s[i + 1..].chars()) =>
+                {
+                    true
+                }
+                // An escaped line terminator is a line continuation in a template,
+                // which can't be written as an escape sequence.
+                '\u{2028}' | '\u{2029}' if escaped => false,
+                '\u{2028}' | '\u{2029}' if self.opt.inline_script_safe => true,
+                _ => !c.is_ascii() && self.opt.ascii_only,
+            };
+            if escape {
+                // Surrogate pairs are valid in templates and all regular expressions.
+                for (j, unit) in c.encode_utf16(&mut [0u16; 2]).iter().enumerate() {
+                    // A preceding backslash starts the first escape sequence.
+                    if j > 0 || !escaped {
+                        out!(self, "\\");
+                    }
+                    out!(self, "u{:04x}", unit);
+                }
+            } else {
+                self.write_char(c, &mut buf);
+            }
+            escaped = c == '\\' && !escaped;
+        }
+    }
+
+    /// Write the raw text of JSX text or a JSX attribute string,
+    /// as HTML character references if `ascii_only` and the character isn't ASCII.
+    fn write_jsx_raw(&mut self, raw: &str) {
+        let mut buf = [0u8; 4];
+        for char in raw.chars() {
+            if char == '\n' {
+                self.force_newline_without_indent();
+            } else if self.opt.ascii_only && !char.is_ascii() {
+                out!(self, "&#x{:x};", char as u32);
+            } else {
+                self.write_char(char, &mut buf);
+            }
+        }
+    }
+
     fn visit_props<'gc>(&mut self, ctx: &'gc GCLock, props: &'gc NodeList<'gc>, path: Path<'gc>) {
         out!(self, "{{");
         for (i, prop) in props.iter().enumerate() {
@@ -3538,7 +3859,7 @@ impl GenJS<'_, '_> {
             out!(self, "{{");
             self.inc_indent();
             self.newline();
-            self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+            self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body), EndsBlock::Yes);
             self.dec_indent();
             self.newline();
             out!(self, "}}");
@@ -3564,12 +3885,55 @@ impl GenJS<'_, '_> {
         }
     }
 
-    fn visit_stmt_list<'gc>(&mut self, ctx: &'gc GCLock, list: &NodeList<'gc>, path: Path<'gc>) {
-        for (i, stmt) in list.iter().enumerate() {
+    fn visit_stmt_list<'gc>(
+        &mut self,
+        ctx: &'gc GCLock,
+        list: &NodeList<'gc>,
+        path: Path<'gc>,
+        ends_block: EndsBlock,
+    ) {
+        let stmts = list.iter().collect::<Vec<_>>();
+        for (i, &stmt) in stmts.iter().enumerate() {
+            let next = stmts.get(i + 1).copied();
+            if i > 0 && self.opt.compress && can_merge_declarations(stmts[i - 1], stmt) {
+                // Continue the declaration of the previous statement.
+                self.comma();
+                self.visit_declarators(ctx, stmt);
+            } else {
+                if i > 0 {
+                    self.newline();
+                }
+                stmt.visit(ctx, self, Some(path));
+            }
+            let omit_semi = self.opt.compress
+                && match next {
+                    Some(next) => can_merge_declarations(stmt, next),
+                    // The `;` is all there is of an empty statement, even a labeled one.
+                    None => ends_block == EndsBlock::Yes && !is_empty_stmt(stmt),
+                };
+            if !omit_semi && !stmt_skip_semi(ctx, Some(stmt)) {
+                out!(self, ";");
+            }
+        }
+    }
+
+    /// Visit the declarators of the `VariableDeclaration` `node`, separated by commas.
+    fn visit_declarators<'gc>(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>) {
+        let declarations = node_cast!(Node::VariableDeclaration, node).declarations;
+        for (i, decl) in declarations.iter().enumerate() {
             if i > 0 {
-                self.newline();
+                self.comma();
             }
-            self.visit_stmt_in_block(ctx, stmt, path);
+            decl.visit(ctx, self, Some(Path::new(node, NodeField::declarations)));
+        }
+    }
+
+    /// Format `value`, in its shortest form if `compress`.
+    fn number_to_string(&self, value: f64) -> String {
+        if self.opt.compress {
+            shortest_number_string(value)
+        } else {
+            convert::number_to_string(value)
         }
     }
 
@@ -3587,95 +3951,17 @@ impl GenJS<'_, '_> {
 
     /// Return the precedence and associativity of `node`.
     fn get_precedence(&self, node: &Node<'_>) -> (precedence::Precedence, Assoc) {
-        // Precedence order taken from
-        // https://github.com/facebook/flow/blob/master/src/parser_utils/output/js_layout_generator.ml
-        use precedence::*;
-        match &node {
-            Node::Identifier(_)
-            | Node::NullLiteral(_)
-            | Node::BooleanLiteral(_)
-            | Node::StringLiteral(_)
-            | Node::NumericLiteral(_)
-            | Node::RegExpLiteral(_)
-            | Node::ThisExpression(_)
-            | Node::Super(_)
-            | Node::ArrayExpression(_)
-            | Node::ObjectExpression(_)
-            | Node::ObjectPattern(_)
-            | Node::FunctionExpression(_)
-            | Node::ClassExpression(_)
-            | Node::TemplateLiteral(_)
-            | Node::JSXElement(_)
-            | Node::JSXFragment(_)
-            | Node::TypeCastExpression(_) => (PRIMARY, Assoc::Ltr),
-            Node::MemberExpression(_)
-            | Node::OptionalMemberExpression(_)
-            | Node::MetaProperty(_)
-            | Node::CallExpression(_)
-            | Node::OptionalCallExpression(_) => (MEMBER, Assoc::Ltr),
-            Node::NewExpression(NewExpression {
-                metadata: _,
-                arguments,
-                ..
-            }) => {
-                // `new foo()` has higher precedence than `new foo`. In pretty mode we
-                // always append the `()`, but otherwise we must check the number of args.
-                if self.opt.pretty == Pretty::Yes || !arguments.is_empty() {
-                    (MEMBER, Assoc::Ltr)
-                } else {
-                    (NEW_NO_ARGS, Assoc::Ltr)
-                }
-            }
-            Node::TaggedTemplateExpression(_) | Node::ImportExpression(_) => {
-                (TAGGED_TEMPLATE, Assoc::Ltr)
+        match node {
+            // In pretty mode we always append the `()` to `new`,
+            // giving it the precedence of a member expression.
+            Node::NewExpression(_) if self.opt.pretty == Pretty::Yes => {
+                (precedence::MEMBER, Assoc::Ltr)
             }
-            Node::UpdateExpression(UpdateExpression {
-                metadata: _,
-                prefix,
-                ..
-            }) => {
-                if *prefix {
-                    (POST_UPDATE, Assoc::Ltr)
-                } else {
-                    (UNARY, Assoc::Rtl)
-                }
+            // `!0` and `!1` are unary expressions.
+            Node::BooleanLiteral(_) if self.opt.compress_booleans => {
+                (precedence::UNARY, Assoc::Rtl)
             }
-            Node::UnaryExpression(_) => (UNARY, Assoc::Rtl),
-            Node::BinaryExpression(BinaryExpression {
-                metadata: _,
-                operator,
-                ..
-            }) => (get_binary_precedence(*operator), Assoc::Ltr),
-            Node::LogicalExpression(LogicalExpression {
-                metadata: _,
-                operator,
-                ..
-            }) => (get_logical_precedence(*operator), Assoc::Ltr),
-            Node::ConditionalExpression(_) => (COND, Assoc::Rtl),
-            Node::AssignmentExpression(_) => (ASSIGN, Assoc::Rtl),
-            Node::ArrowFunctionExpression(_) => (ARROW, Assoc::Ltr),
-            Node::YieldExpression(_) => (YIELD, Assoc::Ltr),
-            Node::SequenceExpression(_) => (SEQ, Assoc::Rtl),
-
-            Node::ExistsTypeAnnotation(_)
-            | Node::EmptyTypeAnnotation(_)
-            | Node::StringTypeAnnotation(_)
-            | Node::BigIntTypeAnnotation(_)
-            | Node::NumberTypeAnnotation(_)
-            | Node::StringLiteralTypeAnnotation(_)
-            | Node::NumberLiteralTypeAnnotation(_)
-            | Node::BooleanTypeAnnotation(_)
-            | Node::BooleanLiteralTypeAnnotation(_)
-            | Node::NullLiteralTypeAnnotation(_)
-            | Node::SymbolTypeAnnotation(_)
-            | Node::AnyTypeAnnotation(_)
-            | Node::MixedTypeAnnotation(_)
-            | Node::VoidTypeAnnotation(_) => (PRIMARY, Assoc::Ltr),
-            Node::NullableTypeAnnotation(_) => (UNARY, Assoc::Ltr),
-            Node::UnionTypeAnnotation(_) => (UNION_TYPE, Assoc::Ltr),
-            Node::IntersectionTypeAnnotation(_) => (INTERSECTION_TYPE, Assoc::Ltr),
-
-            _ => (ALWAYS_PAREN, Assoc::Ltr),
+            _ => precedence::get_precedence(node),
         }
     }
 
@@ -3688,7 +3974,6 @@ impl GenJS<'_, '_> {
         child: &'gc Node<'gc>,
         child_pos: ChildPos,
     ) -> NeedParens {
-        #[allow(clippy::if_same_then_else)]
         if matches!(path.parent, Node::ArrowFunctionExpression(_)) {
             // (x) => ({x: 10}) needs parens to avoid confusing it with a block and a
             // labelled statement.
@@ -3699,31 +3984,6 @@ impl GenJS<'_, '_> {
             {
                 return NeedParens::Yes;
             }
-        } else if matches!(path.parent, Node::ForStatement(_)) {
-            // for((a in b);..;..) needs parens to avoid confusing it with for(a in b).
-            return NeedParens::from(match &child {
-                Node::BinaryExpression(BinaryExpression {
-                    metadata: _,
-                    operator,
-                    ..
-                }) => *operator == BinaryExpressionOperator::In,
-                _ => false,
-            });
-        } else if matches!(path.parent, Node::NewExpression(_)) {
-            // `new(fn())` needs parens to avoid confusing it with `new fn()`.
-            // Need to check the entire subtree to ensure there isn't a call anywhere in it,
-            // because if there is, it would take precedence and terminate the `new` early.
-            // As an example, see the difference between
-            // `new(foo().bar)` (which gets `bar` on `foo()`)
-            // and
-            // `new foo().bar` (which gets `bar` on `new foo()`)
-            if child_pos == ChildPos::Left && contains_call(ctx, child) {
-                return NeedParens::Yes;
-            }
-            // It's illegal to place parens around spread arguments.
-            if matches!(child, Node::SpreadElement(_)) {
-                return NeedParens::No;
-            }
         } else if matches!(path.parent, Node::ExpressionStatement(_)) {
             // Expression statement like (function () {} + 1) needs parens.
             return NeedParens::from(self.root_starts_with(ctx, child, |kind| -> bool {
@@ -3755,69 +4015,15 @@ impl GenJS<'_, '_> {
             } else {
                 NeedParens::Space
             };
-        } else if matches!(
-            path.parent,
-            Node::MemberExpression(_) | Node::CallExpression(_)
-        ) && matches!(
-            child,
-            Node::OptionalMemberExpression(_) | Node::OptionalCallExpression(_)
-        ) && child_pos == ChildPos::Left
-        {
-            // When optional chains are terminated by non-optional member/calls,
-            // we need the left hand side to be pathhesized.
-            // Avoids confusing `(a?.b).c` with `a?.b.c`.
-            return NeedParens::Yes;
-        } else if (check_and_or(path.parent) && check_nullish(child))
-            || (check_nullish(path.parent) && check_and_or(child))
-        {
-            // Nullish coalescing always requires parens when mixed with any
-            // other logical operations.
-            return NeedParens::Yes;
-        } else if matches!(
-            path.parent,
-            Node::CallExpression(_) | Node::OptionalCallExpression(_)
-        ) && matches!(child, Node::SpreadElement(_))
-        {
-            // It's illegal to place parens around spread arguments.
-            return NeedParens::No;
-        } else if matches!(path.parent, Node::AssignmentExpression(_))
-            && matches!(child, Node::ObjectPattern(_) | Node::ArrayPattern(_))
-            && child_pos == ChildPos::Left
-        {
-            // Avoid parentheses for destructuring patterns.
-            return NeedParens::No;
         }
 
-        let (child_prec, _child_assoc) = self.get_precedence(child);
-        if child_prec == precedence::ALWAYS_PAREN {
-            return NeedParens::Yes;
-        }
-
-        let (path_prec, path_assoc) = self.get_precedence(path.parent);
-
-        if child_prec < path_prec {
-            // Child is definitely a danger.
-            return NeedParens::Yes;
-        }
-        if child_prec > path_prec {
-            // Definitely cool.
-            return NeedParens::No;
-        }
-        // Equal precedence, so associativity (rtl/ltr) is what matters.
-        if child_pos == ChildPos::Anywhere {
-            // Child could be anywhere, so always paren.
-            return NeedParens::Yes;
-        }
-        if child_prec == precedence::TOP {
-            // Both precedences are safe.
-            return NeedParens::No;
-        }
-        // Check if child is on the dangerous side.
-        NeedParens::from(if path_assoc == Assoc::Rtl {
-            child_pos == ChildPos::Left
-        } else {
-            child_pos == ChildPos::Right
-        })
+        NeedParens::from(precedence::needs_parens_with(
+            ctx,
+            path.parent,
+            child,
+            child_pos,
+            |node| self.get_precedence(node),
+        ))
     }
 
     fn root_starts_with<'gc, F: Fn(&'gc Node<'gc>) -> bool>(
@@ -3856,11 +4062,12 @@ impl GenJS<'_, '_> {
             }) => {
                 self.expr_starts_with(ctx, *callee, Some(Path::new(expr, NodeField::callee)), pred)
             }
-            Node::OptionalCallExpression(OptionalCallExpression {
-                metadata: _,
-                callee,
-                ..
-            }) => {
+            Node::OptionalCallExpression(call) => {
+                let OptionalCallExpression {
+                    metadata: _,
+                    callee,
+                    ..
+                } = &**call;
                 self.expr_starts_with(ctx, *callee, Some(Path::new(expr, NodeField::callee)), pred)
             }
             Node::BinaryExpression(BinaryExpression {
@@ -3932,7 +4139,7 @@ impl GenJS<'_, '_> {
             dst_col: self.position.col.wrapping_sub(1),
             src_line: node.range().start.line.wrapping_sub(1),
             src_col: node.range().start.col.wrapping_sub(1),
-            src_id: 0,
+            src_id: node.range().file.0,
             name_id: !0,
         });
         self.flush_cur_token();
@@ -3998,7 +4205,7 @@ impl GenJS<'_, '_> {
 
 impl<'gc> Visitor<'gc> for GenJS<'_, '_> {
     fn call(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
-        self.gen_node(ctx, node, path);
+        grow_stack(|| self.gen_node(ctx, node, path));
     }
 }
 
@@ -4045,6 +4252,75 @@ fn is_binary_op(node: &Node, op: BinaryExpressionOperator) -> bool {
     }
 }
 
+/// Whether `after`, the characters following a `<`, start with `/script` (in any case)
+/// or `!--`, which would end or confuse an HTML `<script>` element containing the `<`.
+fn is_html_unsafe_after_lt(after: impl Iterator<Item = char>) -> bool {
+    let next = after
+        .take(7)
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    next.starts_with("/script") || next.starts_with("!--")
+}
+
+/// Whether `node` is the left operand of `**`, which can't be a unary expression.
+fn is_exponentiation_base<'gc>(path: Option<Path<'gc>>, node: &'gc Node<'gc>) -> bool {
+    match path {
+        Some(Path {
+            parent:
+                Node::BinaryExpression(BinaryExpression {
+                    operator: BinaryExpressionOperator::Exp,
+                    left,
+                    ..
+                }),
+            ..
+        }) => std::ptr::eq(*left, node),
+        _ => false,
+    }
+}
+
+/// Whether `stmt` and `next` are declarations of the same kind,
+/// which can be merged into a single declaration.
+fn can_merge_declarations(stmt: &Node, next: &Node) -> bool {
+    match (stmt, next) {
+        (
+            Node::VariableDeclaration(VariableDeclaration { kind: kind1, .. }),
+            Node::VariableDeclaration(VariableDeclaration { kind: kind2, .. }),
+        ) => kind1 == kind2,
+        _ => false,
+    }
+}
+
+/// Return the shortest JS literal for `value`, choosing between the decimal, exponential
+/// and hexadecimal forms, e.g. `1e3` for `1000` and `.5` for `0.5`.
+fn shortest_number_string(value: f64) -> String {
+    if value < 0.0 {
+        return format!("-{}", shortest_number_string(-value));
+    }
+    // Also handles NaN and Infinity.
+    let decimal = convert::number_to_string(value).replace("e+", "e");
+    let mut candidates = vec![];
+    if let Some(frac) = decimal.strip_prefix("0.") {
+        // `0.0012` can be written `.0012` or `12e-4`.
+        candidates.push(format!(".{}", frac));
+        let digits = frac.trim_start_matches('0');
+        candidates.push(format!("{}e-{}", digits, frac.len()));
+    } else if decimal.bytes().all(|c| c.is_ascii_digit()) {
+        // `1000` can be written `1e3`.
+        let digits = decimal.trim_end_matches('0');
+        let zeros = decimal.len() - digits.len();
+        if zeros > 0 {
+            candidates.push(format!("{}e{}", digits, zeros));
+        }
+        if value < (1u64 << 53) as f64 {
+            candidates.push(format!("0x{:x}", value as u64));
+        }
+    }
+    candidates.into_iter().fold(
+        decimal,
+        |best, c| if c.len() < best.len() { c } else { best },
+    )
+}
+
 fn is_if_without_else(node: &Node) -> bool {
     match node {
         Node::IfStatement(IfStatement {
@@ -4067,26 +4343,13 @@ fn check_minus(node: &Node) -> bool {
         || is_negative_number(node)
 }
 
-fn check_and_or(node: &Node) -> bool {
-    matches!(
-        node,
-        Node::LogicalExpression(LogicalExpression {
-            metadata: _,
-            operator: LogicalExpressionOperator::And | LogicalExpressionOperator::Or,
-            ..
-        })
-    )
-}
-
-fn check_nullish(node: &Node) -> bool {
-    matches!(
-        node,
-        Node::LogicalExpression(LogicalExpression {
-            metadata: _,
-            operator: LogicalExpressionOperator::NullishCoalesce,
-            ..
-        })
-    )
+/// Whether `node` is an `EmptyStatement`, possibly labeled.
+fn is_empty_stmt(node: &Node) -> bool {
+    match node {
+        Node::EmptyStatement(_) => true,
+        Node::LabeledStatement(LabeledStatement { body, .. }) => is_empty_stmt(body),
+        _ => false,
+    }
 }
 
 /// Whether to skip the semicolon at the end of `node`.
@@ -4114,11 +4377,9 @@ fn stmt_skip_semi<'gc>(ctx: &'gc GCLock, node: Option<&'gc Node<'gc>>) -> bool {
             | Node::DeclareInterface(_)
             | Node::DeclareClass(_)
             | Node::DeclareModule(_) => true,
-            Node::DeclareExportDeclaration(DeclareExportDeclaration {
-                declaration,
-                source: None,
-                ..
-            }) => stmt_skip_semi(ctx, *declaration),
+            Node::DeclareExportDeclaration(export) if export.source.is_none() => {
+                stmt_skip_semi(ctx, export.declaration)
+            }
             Node::SwitchStatement(_) => true,
             Node::LabeledStatement(LabeledStatement { body, .. }) => {
                 stmt_skip_semi(ctx, Some(*body))
@@ -4135,39 +4396,10 @@ fn stmt_skip_semi<'gc>(ctx: &'gc GCLock, node: Option<&'gc Node<'gc>>) -> bool {
                 metadata: _,
                 declaration,
             }) => stmt_skip_semi(ctx, Some(*declaration)),
-            Node::ExportNamedDeclaration(ExportNamedDeclaration {
-                metadata: _,
-                declaration,
-                ..
-            }) => stmt_skip_semi(ctx, *declaration),
+            Node::ExportNamedDeclaration(export) => stmt_skip_semi(ctx, export.declaration),
             Node::EnumDeclaration(_) => true,
             _ => false,
         },
         None => false,
     }
 }
-
-/// Return true if `node` contains a `CallExpression`.
-fn contains_call<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> bool {
-    struct CallFinder {
-        found: bool,
-    }
-    impl<'gc> Visitor<'gc> for CallFinder {
-        fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
-            match node {
-                Node::CallExpression(_)
-                | Node::OptionalCallExpression(OptionalCallExpression {
-                    optional: false, ..
-                }) => {
-                    self.found = true;
-                }
-                _ => {
-                    node.visit_children(gc, self);
-                }
-            };
-        }
-    }
-    let mut finder = CallFinder { found: false };
-    node.visit(gc, &mut finder, None);
-    finder.found
-}
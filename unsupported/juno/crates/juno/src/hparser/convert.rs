@@ -7,6 +7,7 @@
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::rc::Rc;
 use std::str::FromStr;
 
 use hermes::parser::DataRef;
@@ -24,6 +25,7 @@ use hermes::utf::is_utf8_continuation;
 use hermes::utf::utf8_with_surrogates_to_string;
 use hermes::utf::utf8_with_surrogates_to_utf16;
 use juno_support::atom_table;
+use juno_support::NullTerminatedBuf;
 
 use super::generated_cvt::cvt_node_ptr;
 use crate::ast;
@@ -65,6 +67,10 @@ pub struct Converter<'parser> {
     /// ast::Identifier. This allows us to avoid repeated conversion of the same
     /// NodeLabel.
     atom_tab_u16: HashMap<NodeString, atom_table::AtomU16>,
+
+    /// The buffer which the names of identifiers point into, if
+    /// [`ast::Context::source_atoms`] is enabled.
+    source: Option<Rc<NullTerminatedBuf>>,
 }
 
 /// Adjust the source location backwards making sure it doesn't point to \r or
@@ -109,9 +115,16 @@ impl<'parser> Converter<'parser> {
             line_cache: Default::default(),
             atom_tab: Default::default(),
             atom_tab_u16: Default::default(),
+            source: None,
         }
     }
 
+    /// Make the names of identifiers point into `source`, the buffer which was parsed,
+    /// when they appear verbatim in it.
+    pub fn set_source(&mut self, source: Rc<NullTerminatedBuf>) {
+        self.source = Some(source);
+    }
+
     /// Convert a SMLoc to ast::SourceLoc using the line_cache. Best cache
     /// utilization is achieved if locations are always increasing. In this way
     /// the next location will almost always be in the current line or the next
@@ -166,6 +179,36 @@ impl<'parser> Converter<'parser> {
             .or_insert_with(move || ctx.atom(utf8_with_surrogates_to_string(u.as_slice()).unwrap()))
     }
 
+    /// Convert the name of an identifier starting at `start`.
+    /// With a source buffer, see [`Converter::set_source`], the name points into it if it
+    /// appears there verbatim, i.e. without escapes.
+    pub fn cvt_source_label(
+        &mut self,
+        ctx: &ast::GCLock<'_, '_>,
+        start: SMLoc,
+        u: NodeLabel,
+    ) -> ast::NodeLabel {
+        if let Some(atom) = self.atom_tab.get(&u) {
+            return *atom;
+        }
+        if let Some(buf) = &self.source {
+            let name = u.as_slice();
+            let bytes = buf.as_bytes();
+            let offset = (start.as_ptr() as usize).wrapping_sub(bytes.as_ptr() as usize);
+            if offset < bytes.len() && bytes[offset..].starts_with(name) {
+                if let Some(atom) = ctx
+                    .ctx()
+                    .atom_table()
+                    .atom_in_buffer(buf, offset..offset + name.len())
+                {
+                    self.atom_tab.insert(u, atom);
+                    return atom;
+                }
+            }
+        }
+        self.cvt_label(ctx, u)
+    }
+
     pub fn cvt_label_opt(
         &mut self,
         ctx: &ast::GCLock<'_, '_>,
@@ -630,7 +630,7 @@ pub unsafe fn cvt_node_ptr<'parser, 'gc>(
           ast::builder::DirectiveLiteral::build_template(gc, template)
         }
         NodeKind::Identifier => {
-          let name = cvt.cvt_label(gc, hermes_get_Identifier_name(n));
+          let name = cvt.cvt_source_label(gc, nr.source_range.start, hermes_get_Identifier_name(n));
           let type_annotation = cvt_node_ptr_opt(cvt, gc, hermes_get_Identifier_typeAnnotation(n));
           let optional = hermes_get_Identifier_optional(n);
           let mut template = ast::template::Identifier {
@@ -876,6 +876,30 @@ pub unsafe fn cvt_node_ptr<'parser, 'gc>(
           template.metadata.range.end = if nr.source_range.is_empty() { template.metadata.range.start } else { cvt.cvt_smloc(nr.source_range.end.pred()) };
           ast::builder::MethodDefinition::build_template(gc, template)
         }
+        NodeKind::ClassAccessorProperty => {
+          let key = cvt_node_ptr(cvt, gc, hermes_get_ClassAccessorProperty_key(n));
+          let value = cvt_node_ptr_opt(cvt, gc, hermes_get_ClassAccessorProperty_value(n));
+          let computed = hermes_get_ClassAccessorProperty_computed(n);
+          let is_static = hermes_get_ClassAccessorProperty_static(n);
+          let mut template = ast::template::ClassAccessorProperty {
+              metadata: ast::TemplateMetadata {range, ..Default::default()},
+                  key,
+                  value,
+                  computed,
+                  is_static,
+          };
+          template.metadata.range.end = if nr.source_range.is_empty() { template.metadata.range.start } else { cvt.cvt_smloc(nr.source_range.end.pred()) };
+          ast::builder::ClassAccessorProperty::build_template(gc, template)
+        }
+        NodeKind::StaticBlock => {
+          let body = cvt_node_list(cvt, gc, hermes_get_StaticBlock_body(n));
+          let mut template = ast::template::StaticBlock {
+              metadata: ast::TemplateMetadata {range, ..Default::default()},
+                  body,
+          };
+          template.metadata.range.end = if nr.source_range.is_empty() { template.metadata.range.start } else { cvt.cvt_smloc(nr.source_range.end.pred()) };
+          ast::builder::StaticBlock::build_template(gc, template)
+        }
         NodeKind::ImportDeclaration => {
           let specifiers = cvt_node_list(cvt, gc, hermes_get_ImportDeclaration_specifiers(n));
           let source = cvt_node_ptr(cvt, gc, hermes_get_ImportDeclaration_source(n));
@@ -1031,7 +1055,7 @@ pub unsafe fn cvt_node_ptr<'parser, 'gc>(
           ast::builder::AssignmentPattern::build_template(gc, template)
         }
         NodeKind::JSXIdentifier => {
-          let name = cvt.cvt_label(gc, hermes_get_JSXIdentifier_name(n));
+          let name = cvt.cvt_source_label(gc, nr.source_range.start, hermes_get_JSXIdentifier_name(n));
           let mut template = ast::template::JSXIdentifier {
               metadata: ast::TemplateMetadata {range, ..Default::default()},
                   name,
@@ -0,0 +1,654 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The tokens of a source, scanned by the Hermes lexer, for tools like syntax highlighters
+//! and formatters which need the tokens rather than an AST.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use hermes::parser::HermesLexer;
+use hermes::parser::LexedToken;
+use hermes::parser::LexerState;
+use hermes::parser::ParserFlags;
+use hermes::parser::TokenCategory;
+use hermes::utf::utf8_with_surrogates_to_string_lossy;
+use hermes::utf::utf8_with_surrogates_to_utf16;
+use juno_support::source_manager::line_starts;
+use juno_support::NullTerminatedBuf;
+
+use super::ParseError;
+use crate::ast;
+
+/// The kind of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier,
+    /// `#name`.
+    PrivateIdentifier,
+    /// A reserved word. Contextual keywords, like `async` or `of`, are identifiers.
+    Keyword,
+    Punctuator,
+    Number,
+    BigInt,
+    String,
+    RegExp,
+    /// A template literal without substitutions, `` `...` ``.
+    NoSubstitutionTemplate,
+    /// The start of a template literal, `` `...${ ``.
+    TemplateHead,
+    /// The part of a template literal between substitutions, `}...${`.
+    TemplateMiddle,
+    /// The end of a template literal, `` }...` ``.
+    TemplateTail,
+    /// `//...`, excluding the line terminator.
+    LineComment,
+    /// `/*...*/`.
+    BlockComment,
+    /// `#!...` at the start of the source.
+    Hashbang,
+    /// A run of whitespace other than line terminators.
+    Whitespace,
+    /// A single line terminator, with `\r\n` counting as one.
+    LineTerminator,
+}
+
+impl TokenKind {
+    /// Whether the token is a comment or whitespace, only produced when requested by
+    /// [`TriviaOpt`].
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::LineComment
+                | TokenKind::BlockComment
+                | TokenKind::Hashbang
+                | TokenKind::Whitespace
+                | TokenKind::LineTerminator
+        )
+    }
+}
+
+/// The value of a token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValue {
+    /// Punctuators, trivia, and template literals containing an invalid escape,
+    /// which have no cooked value.
+    None,
+    /// The name of an identifier, a private identifier or a keyword, with escapes decoded.
+    Name(String),
+    Number(f64),
+    /// The digits of a bigint, without the `n` suffix.
+    BigInt(String),
+    /// The cooked value of a string or a part of a template literal.
+    String(Vec<u16>),
+    RegExp {
+        pattern: String,
+        flags: String,
+    },
+}
+
+/// A token of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte offsets of the token in the source.
+    pub range: Range<usize>,
+    pub value: TokenValue,
+}
+
+/// Which trivia to produce along with the tokens. By default, there are none.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TriviaOpt {
+    /// Produce comments, including the hashbang.
+    pub comments: bool,
+    /// Produce whitespace and line terminators.
+    pub whitespace: bool,
+}
+
+/// An iterator over the tokens of a source, in source order. The end of the file isn't
+/// a token.
+///
+/// The lexer doesn't know what the parser expects, so a `/` is considered to start
+/// a regexp unless it follows a token which can end an expression, like an identifier,
+/// a literal, `)` or `]`. JSX and type annotations are lexed as JavaScript.
+/// Invalid input is reported as errors and skipped, and lexing continues.
+pub struct Lexer<'a> {
+    lexer: HermesLexer<'a>,
+    source: &'a NullTerminatedBuf,
+    trivia: TriviaOpt,
+    /// Offset of the end of the last token or comment returned by `lexer`.
+    pos: usize,
+    /// Tokens which have been scanned but not returned yet.
+    pending: VecDeque<Token>,
+    done: bool,
+}
+
+impl<'a> Lexer<'a> {
+    /// Only the `strict_mode`, `annex_b`, `html_like_comments` and `source_type` flags are used.
+    pub fn new(flags: ParserFlags, source: &'a NullTerminatedBuf, trivia: TriviaOpt) -> Self {
+        Lexer {
+            lexer: HermesLexer::new(flags, source),
+            source,
+            trivia,
+            pos: 0,
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Return true if there has been at least one error so far.
+    pub fn has_errors(&self) -> bool {
+        self.lexer.has_errors()
+    }
+
+    /// Return the first error so far, if any.
+    pub fn first_error(&self) -> Option<ParseError> {
+        self.lexer.first_error_index().map(|index| {
+            let msg = &self.lexer.messages()[index];
+            ParseError {
+                loc: ast::SourceLoc {
+                    line: msg.coord.line,
+                    col: msg.coord.offset + 1,
+                },
+                msg: utf8_with_surrogates_to_string_lossy(msg.message.as_slice()),
+            }
+        })
+    }
+
+    /// Queue the whitespace and line terminators in `range`, which is between two
+    /// tokens or comments.
+    fn push_whitespace(&mut self, range: Range<usize>) {
+        let buf = self.source.as_bytes();
+        let mut start = range.start;
+        let mut i = range.start;
+        while i < range.end {
+            let len = match line_terminator_len(&buf[i..range.end]) {
+                Some(len) => len,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            if start < i {
+                self.push(TokenKind::Whitespace, start..i, TokenValue::None);
+            }
+            self.push(TokenKind::LineTerminator, i..i + len, TokenValue::None);
+            i += len;
+            start = i;
+        }
+        if start < range.end {
+            self.push(TokenKind::Whitespace, start..range.end, TokenValue::None);
+        }
+    }
+
+    /// Resume lexing at `offset`, which must be the end of a token or comment (or 0),
+    /// in `state`.
+    fn seek(&mut self, offset: usize, state: &LexerState) {
+        self.lexer.seek(offset, state);
+        self.pos = offset;
+        self.pending.clear();
+        self.done = false;
+    }
+
+    fn push(&mut self, kind: TokenKind, range: Range<usize>, value: TokenValue) {
+        self.pending.push_back(Token { kind, range, value });
+    }
+
+    /// Scan the next token or comment and queue it with the preceding trivia,
+    /// as requested.
+    fn scan_item(&mut self) {
+        let tok = match self.lexer.next_token() {
+            Some(tok) => tok,
+            None => {
+                self.done = true;
+                return;
+            }
+        };
+        let range = self.lexer.offset(tok.range.start)..self.lexer.offset(tok.range.end);
+        if self.trivia.whitespace && self.pos < range.start {
+            self.push_whitespace(self.pos..range.start);
+        }
+        self.pos = range.end;
+        let kind = match tok.category {
+            TokenCategory::Eof => {
+                self.done = true;
+                return;
+            }
+            TokenCategory::LineComment | TokenCategory::BlockComment | TokenCategory::Hashbang
+                if !self.trivia.comments =>
+            {
+                return;
+            }
+            TokenCategory::Identifier => TokenKind::Identifier,
+            TokenCategory::PrivateIdentifier => TokenKind::PrivateIdentifier,
+            TokenCategory::Keyword => TokenKind::Keyword,
+            TokenCategory::Punctuator => TokenKind::Punctuator,
+            TokenCategory::Numeric => TokenKind::Number,
+            TokenCategory::BigInt => TokenKind::BigInt,
+            TokenCategory::String => TokenKind::String,
+            TokenCategory::RegExp => TokenKind::RegExp,
+            TokenCategory::NoSubstitutionTemplate => TokenKind::NoSubstitutionTemplate,
+            TokenCategory::TemplateHead => TokenKind::TemplateHead,
+            TokenCategory::TemplateMiddle => TokenKind::TemplateMiddle,
+            TokenCategory::TemplateTail => TokenKind::TemplateTail,
+            TokenCategory::LineComment => TokenKind::LineComment,
+            TokenCategory::BlockComment => TokenKind::BlockComment,
+            TokenCategory::Hashbang => TokenKind::Hashbang,
+        };
+        let value = token_value(kind, &tok);
+        self.push(kind, range, value);
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.pending.is_empty() && !self.done {
+            self.scan_item();
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Length of the line terminator at the start of `buf`, if any.
+fn line_terminator_len(buf: &[u8]) -> Option<usize> {
+    match buf {
+        [b'\r', b'\n', ..] => Some(2),
+        [b'\n', ..] | [b'\r', ..] => Some(1),
+        [0xE2, 0x80, 0xA8 | 0xA9, ..] => Some(3),
+        _ => None,
+    }
+}
+
+fn token_value(kind: TokenKind, tok: &LexedToken) -> TokenValue {
+    let string = |s: &hermes::parser::StringRef| utf8_with_surrogates_to_string_lossy(s.as_slice());
+    match kind {
+        TokenKind::Number => TokenValue::Number(tok.numeric),
+        _ if !tok.has_value => TokenValue::None,
+        TokenKind::Identifier | TokenKind::PrivateIdentifier | TokenKind::Keyword => {
+            TokenValue::Name(string(&tok.value))
+        }
+        TokenKind::BigInt => TokenValue::BigInt(string(&tok.value)),
+        TokenKind::RegExp => TokenValue::RegExp {
+            pattern: string(&tok.value),
+            flags: string(&tok.flags),
+        },
+        TokenKind::String
+        | TokenKind::NoSubstitutionTemplate
+        | TokenKind::TemplateHead
+        | TokenKind::TemplateMiddle
+        | TokenKind::TemplateTail => TokenValue::String(
+            // Lone surrogates are encoded in the value, which is otherwise valid UTF-8.
+            utf8_with_surrogates_to_utf16(tok.value.as_slice()).unwrap_or_default(),
+        ),
+        _ => TokenValue::None,
+    }
+}
+
+/// Return the tokens of `source`, without trivia, or the first error.
+/// This is a simple function that is intended to be used mostly for testing.
+pub fn tokenize(flags: ParserFlags, source: &str) -> Result<Vec<Token>, ParseError> {
+    let buf = NullTerminatedBuf::from_str_check(source);
+    let mut lexer = Lexer::new(flags, &buf, Default::default());
+    let tokens = lexer.by_ref().collect::<Vec<_>>();
+    match lexer.first_error() {
+        Some(e) => Err(e),
+        None => Ok(tokens),
+    }
+}
+
+/// Where lexing can resume to produce the tokens from the start of a line.
+#[derive(Debug, Clone)]
+struct Resume {
+    /// End of the last token or comment ending before the start of the line, or 0.
+    offset: usize,
+    /// State of the lexer at `offset`.
+    state: LexerState,
+    /// Index of the first token after `offset`.
+    token: usize,
+}
+
+#[derive(Debug)]
+struct Line {
+    /// Byte offset of the start of the line.
+    start: usize,
+    resume: Resume,
+}
+
+/// The lines after an edit, whose tokens can be reused if lexing reaches one of them
+/// in the same state as before the edit.
+struct Reuse {
+    /// Index of the first of these lines.
+    first_line: usize,
+    /// Where lexing resumed for each line before the edit.
+    resumes: Vec<Resume>,
+    /// The tokens before the edit, starting at index `base`.
+    tokens: Vec<Token>,
+    base: usize,
+    /// End of the edited range, before the edit.
+    old_end: usize,
+    /// Change of the length of the source.
+    delta: isize,
+}
+
+/// The tokens of a source which is being edited, as in a syntax highlighter, updated
+/// after each edit by lexing again only the lines affected by it.
+///
+/// The state of the lexer is recorded at the start of every line. After an edit, lexing
+/// resumes from the state of the line before it, and stops at the first line after it
+/// which starts in the same state as before the edit: the rest of the tokens are the same,
+/// moved by the change of length of the source.
+pub struct LineTokens {
+    flags: ParserFlags,
+    trivia: TriviaOpt,
+    tokens: Vec<Token>,
+    lines: Vec<Line>,
+}
+
+impl LineTokens {
+    /// Lex the whole `source`. Only the `strict_mode`, `annex_b`, `html_like_comments` and
+    /// `source_type` flags are used.
+    pub fn new(flags: ParserFlags, source: &NullTerminatedBuf, trivia: TriviaOpt) -> Self {
+        let text = &source.as_bytes()[..source.len() - 1];
+        let mut result = LineTokens {
+            flags,
+            trivia,
+            tokens: vec![],
+            lines: line_starts(text)
+                .into_iter()
+                .map(|start| Line {
+                    start,
+                    resume: Resume {
+                        offset: 0,
+                        state: Default::default(),
+                        token: 0,
+                    },
+                })
+                .collect(),
+        };
+        result.lex(source, 0, None);
+        result
+    }
+
+    /// All the tokens, in source order.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn num_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The tokens starting on the 0-based `line`. A token spanning several lines,
+    /// like a block comment, is only included in the line where it starts.
+    pub fn line(&self, line: usize) -> &[Token] {
+        let start = self.lines[line].start;
+        let end = self.lines.get(line + 1).map_or(usize::MAX, |l| l.start);
+        let first = self.tokens.partition_point(|t| t.range.start < start);
+        let last = self.tokens.partition_point(|t| t.range.start < end);
+        &self.tokens[first..last]
+    }
+
+    /// Update the tokens after the bytes in `old` have been replaced with `new_len` bytes,
+    /// resulting in `source`.
+    /// Returns the 0-based range of lines of `source` whose tokens may have changed.
+    pub fn edit(
+        &mut self,
+        source: &NullTerminatedBuf,
+        old: Range<usize>,
+        new_len: usize,
+    ) -> Range<usize> {
+        let text = &source.as_bytes()[..source.len() - 1];
+        let new_end = old.start + new_len;
+        // Start from the line before the one containing the edit, since a line terminator
+        // inserted at its start could join the line terminator before it.
+        let first = self.lines.partition_point(|l| l.start < old.start).max(1) - 1;
+        let after = self.lines.partition_point(|l| l.start <= old.end);
+        let survivors = self.lines.split_off(after);
+        self.lines.truncate(first + 1);
+
+        // The lines starting in the edited text.
+        let mut i = self.lines[first].start;
+        while i < new_end {
+            match line_terminator_len(&text[i..]) {
+                Some(len) => {
+                    i += len;
+                    if i <= new_end {
+                        self.lines.push(Line {
+                            start: i,
+                            resume: self.lines[first].resume.clone(),
+                        });
+                    }
+                }
+                None => i += 1,
+            }
+        }
+
+        let delta = new_len as isize - old.len() as isize;
+        let first_line = self.lines.len();
+        let resumes = survivors.iter().map(|l| l.resume.clone()).collect();
+        self.lines.extend(survivors.into_iter().map(|l| Line {
+            start: shift(l.start, delta),
+            resume: l.resume,
+        }));
+        let base = self.lines[first].resume.token;
+        let tokens = self.tokens.split_off(base);
+        let end = self.lex(
+            source,
+            first,
+            Some(Reuse {
+                first_line,
+                resumes,
+                tokens,
+                base,
+                old_end: old.end,
+                delta,
+            }),
+        );
+        let offset = self.lines[first].resume.offset;
+        let start = self.lines.partition_point(|l| l.start <= offset) - 1;
+        start..end
+    }
+
+    /// Lex from where line `from` resumes, recording where the following lines resume,
+    /// until the end of the source or until a line can `reuse` the tokens from before
+    /// an edit. Returns the index of that line, or the number of lines.
+    fn lex(&mut self, source: &NullTerminatedBuf, from: usize, mut reuse: Option<Reuse>) -> usize {
+        let resume = self.lines[from].resume.clone();
+        self.tokens.truncate(resume.token);
+        let mut lexer = Lexer::new(self.flags, source, self.trivia);
+        lexer.seek(resume.offset, &resume.state);
+        let mut line = from + 1;
+        while !lexer.done {
+            let offset = lexer.pos;
+            lexer.scan_item();
+            // The lines starting before the end of the token or comment which was scanned
+            // resume before it.
+            while line < self.lines.len() && (self.lines[line].start < lexer.pos || lexer.done) {
+                let resume = Resume {
+                    offset,
+                    state: lexer.lexer.state(),
+                    token: self.tokens.len(),
+                };
+                if let Some(reuse) = &mut reuse {
+                    if line >= reuse.first_line && self.try_reuse(line, &resume, reuse) {
+                        return line;
+                    }
+                }
+                self.lines[line].resume = resume;
+                line += 1;
+            }
+            self.tokens.extend(lexer.pending.drain(..));
+        }
+        self.lines.len()
+    }
+
+    /// If `line` resumes in the same state as before the edit, reuse the tokens and
+    /// the resume points after it and return true.
+    fn try_reuse(&mut self, line: usize, resume: &Resume, reuse: &mut Reuse) -> bool {
+        let old = &reuse.resumes[line - reuse.first_line];
+        // The tokens after `old` must start after the edit to be reused.
+        if old.offset < reuse.old_end
+            || shift(old.offset, reuse.delta) != resume.offset
+            || old.state != resume.state
+        {
+            return false;
+        }
+        let old_token = old.token;
+        let delta = reuse.delta;
+        self.tokens
+            .extend(reuse.tokens.drain(old_token - reuse.base..).map(|mut t| {
+                t.range = shift(t.range.start, delta)..shift(t.range.end, delta);
+                t
+            }));
+        for (l, old) in self.lines[line..]
+            .iter_mut()
+            .zip(&reuse.resumes[line - reuse.first_line..])
+        {
+            l.resume = Resume {
+                offset: shift(old.offset, delta),
+                state: old.state.clone(),
+                token: old.token - old_token + resume.token,
+            };
+        }
+        true
+    }
+}
+
+/// Move `offset` by `delta` bytes.
+fn shift(offset: usize, delta: isize) -> usize {
+    (offset as isize + delta) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(tokens: &[Token]) -> Vec<TokenKind> {
+        tokens.iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+        let tokens = tokenize(Default::default(), "x = a / b / `t${ {} }u` + /re/g;").unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                Identifier,
+                Punctuator,
+                Identifier,
+                Punctuator,
+                Identifier,
+                Punctuator,
+                TemplateHead,
+                Punctuator,
+                Punctuator,
+                TemplateTail,
+                Punctuator,
+                RegExp,
+                Punctuator
+            ]
+        );
+        assert_eq!(tokens[0].range, 0..1);
+        assert_eq!(tokens[2].value, TokenValue::Name("a".into()));
+        assert_eq!(
+            tokens[11].value,
+            TokenValue::RegExp {
+                pattern: "re".into(),
+                flags: "g".into()
+            }
+        );
+
+        assert!(tokenize(Default::default(), "'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_trivia() {
+        use TokenKind::*;
+        let source = "#!/usr/bin/env node\n/* a */ return 1 // b\r\n";
+        let buf = NullTerminatedBuf::from_str_check(source);
+        let tokens = Lexer::new(
+            Default::default(),
+            &buf,
+            TriviaOpt {
+                comments: true,
+                whitespace: true,
+            },
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            kinds(&tokens),
+            [
+                Hashbang,
+                LineTerminator,
+                BlockComment,
+                Whitespace,
+                Keyword,
+                Whitespace,
+                Number,
+                Whitespace,
+                LineComment,
+                LineTerminator
+            ]
+        );
+        // The trivia and the tokens cover the whole source.
+        let text = tokens
+            .iter()
+            .map(|t| &source[t.range.clone()])
+            .collect::<Vec<_>>()
+            .concat();
+        assert_eq!(text, source);
+        assert_eq!(tokens[6].value, TokenValue::Number(1.0));
+    }
+
+    #[test]
+    fn test_line_tokens() {
+        let trivia = TriviaOpt {
+            comments: true,
+            whitespace: true,
+        };
+        let mut source = "a = 1;\nb = `x${\n c}`;\nd = /re/;\n".to_string();
+        let mut lines = LineTokens::new(
+            Default::default(),
+            &NullTerminatedBuf::from_str_check(&source),
+            trivia,
+        );
+        assert_eq!(lines.num_lines(), 5);
+        assert_eq!(
+            kinds(lines.line(2)),
+            [
+                TokenKind::Whitespace,
+                TokenKind::Identifier,
+                TokenKind::TemplateTail,
+                TokenKind::Punctuator,
+                TokenKind::LineTerminator
+            ]
+        );
+
+        // Replace `range` with `text` and check that the tokens are the same as when lexing
+        // the new source from scratch.
+        let mut edit = |range: Range<usize>, text: &str| {
+            source.replace_range(range.clone(), text);
+            let buf = NullTerminatedBuf::from_str_check(&source);
+            let changed = lines.edit(&buf, range, text.len());
+            assert_eq!(
+                lines.tokens(),
+                LineTokens::new(Default::default(), &buf, trivia).tokens()
+            );
+            changed
+        };
+        assert_eq!(edit(4..5, "\"2\""), 0..1);
+        // Comment out the rest of the source, and the end of the template.
+        edit(9..9, "/*");
+        edit(25..25, "*/");
+        // Join the first two lines.
+        edit(8..9, " ");
+        assert_eq!(lines.num_lines(), 4);
+    }
+}
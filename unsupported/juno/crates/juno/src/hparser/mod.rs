@@ -7,37 +7,46 @@
 
 mod convert;
 mod generated_cvt;
+pub mod lexer;
 
 use std::fmt::Formatter;
 
 use convert::Converter;
 use generated_cvt::cvt_node_ptr;
+use hermes::parser::DiagKind;
 use hermes::parser::HermesParser;
 pub use hermes::parser::MagicCommentKind;
 use hermes::parser::NodePtr;
 pub use hermes::parser::ParserDialect;
 pub use hermes::parser::ParserFlags;
+pub use hermes::parser::SourceType;
 use hermes::utf::utf8_with_surrogates_to_string_lossy;
 use juno_support::source_manager::SourceId;
 use juno_support::NullTerminatedBuf;
 use thiserror::Error;
 
 use crate::ast;
+use crate::ast::node_cast;
 
 pub struct ParsedJS<'a> {
     parser: HermesParser<'a>,
+    source_type: SourceType,
 }
 
 impl<'parser> ParsedJS<'parser> {
     /// Parse the source and store an internal representation of the AST and/or a list of diagnostic
-    /// messages. If at least one of the messages is an error, there is no AST.
+    /// messages. If at least one of the messages is an error, there is no AST, unless
+    /// [`ParserFlags::error_recovery`] is set: then the AST is partial, missing the statements
+    /// which contained errors.
     pub fn parse(flags: ParserFlags, source: &NullTerminatedBuf) -> ParsedJS {
         ParsedJS {
             parser: HermesParser::parse(flags, source),
+            source_type: flags.source_type,
         }
     }
 
-    /// Return true if there is at least one parser error (implying there is no AST).
+    /// Return true if there is at least one parser error (implying there is no AST without
+    /// error recovery).
     pub fn has_errors(&self) -> bool {
         self.parser.has_errors()
     }
@@ -68,28 +77,109 @@ impl<'parser> ParsedJS<'parser> {
         })
     }
 
-    /// Create and return an external representation of the AST, or None if there were parse errors.
+    /// Return all the errors, in the order they were reported.
+    pub fn errors(&self) -> Vec<ParseError> {
+        self.parser
+            .messages()
+            .iter()
+            .filter(|msg| matches!(msg.diag_kind, DiagKind::Error))
+            .map(|msg| ParseError {
+                loc: ast::SourceLoc {
+                    line: msg.coord.line,
+                    col: msg.coord.offset + 1,
+                },
+                msg: utf8_with_surrogates_to_string_lossy(msg.message.as_slice()),
+            })
+            .collect()
+    }
+
+    /// Return the error recovery actions of the parser, in the order they were taken.
+    /// They are only recorded with [`ParserFlags::record_recovery`].
+    pub fn recoveries(&self) -> Vec<Recovery> {
+        self.parser
+            .messages()
+            .iter()
+            .filter(|msg| matches!(msg.diag_kind, DiagKind::Remark))
+            .map(|msg| {
+                let start = ast::SourceLoc {
+                    line: msg.coord.line,
+                    col: msg.coord.offset + 1,
+                };
+                Recovery {
+                    start,
+                    end: if msg.end_coord.offset > msg.coord.offset {
+                        ast::SourceLoc {
+                            line: start.line,
+                            col: msg.end_coord.offset,
+                        }
+                    } else {
+                        start
+                    },
+                    msg: utf8_with_surrogates_to_string_lossy(msg.message.as_slice()),
+                }
+            })
+            .collect()
+    }
+
+    /// Create and return an external representation of the AST, or None if there were parse errors
+    /// without error recovery, or the cancellation token of the context has been cancelled.
+    /// The root is a `Module` if the source is a module, either explicitly or because it
+    /// contains import or export declarations, otherwise a `Program`.
     pub fn to_ast<'gc, 'ast: 'gc>(
         &'parser self,
         ctx: &'gc ast::GCLock<'ast, '_>,
         file_id: SourceId,
     ) -> Option<&'gc ast::Node<'gc>> {
+        if ctx.ctx().is_cancelled() {
+            return None;
+        }
         let mut cvt: Converter<'parser> = Converter::new(&self.parser, file_id);
+        if ctx.ctx().source_atoms() && (file_id.0 as usize) < ctx.sm().num_sources() {
+            cvt.set_source(ctx.sm().source_buffer_rc(file_id));
+        }
 
         match self.parser.root() {
             None => None,
             Some(node) => {
+                // The context may already contain errors from other sources.
+                let num_errors = ctx.sm().num_errors();
                 let ast = convert_ast(&mut cvt, ctx, node);
-                if ctx.sm().num_errors() > 0 {
+                if ctx.sm().num_errors() > num_errors {
                     None
                 } else {
-                    Some(ast)
+                    Some(self.apply_source_type(ctx, ast))
                 }
             }
         }
     }
 }
 
+impl ParsedJS<'_> {
+    /// Turn the converted `program` into a `Module` if the source is a module.
+    fn apply_source_type<'gc>(
+        &self,
+        ctx: &'gc ast::GCLock,
+        program: &'gc ast::Node<'gc>,
+    ) -> &'gc ast::Node<'gc> {
+        let ast::Program { metadata, body } = node_cast!(ast::Node::Program, program);
+        let is_module = match self.source_type {
+            SourceType::Script => false,
+            SourceType::Module => true,
+            SourceType::Unambiguous => body.iter().any(ast::is_module_declaration),
+        };
+        if !is_module {
+            return program;
+        }
+        ast::builder::Module::build_template(
+            ctx,
+            ast::template::Module {
+                metadata: metadata.into(),
+                body: *body,
+            },
+        )
+    }
+}
+
 fn convert_ast<'parser, 'gc, 'ast: 'gc>(
     cvt: &mut Converter<'parser>,
     ctx: &'gc ast::GCLock<'ast, '_>,
@@ -98,6 +188,18 @@ fn convert_ast<'parser, 'gc, 'ast: 'gc>(
     unsafe { cvt_node_ptr(cvt, ctx, n) }
 }
 
+/// An error recovery action of the parser, like a character skipped or a node dropped
+/// from the AST.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    /// Start of the affected range.
+    pub start: ast::SourceLoc,
+    /// End of the affected range, inclusive. It is clipped to the line of `start`.
+    pub end: ast::SourceLoc,
+    /// Description of the action.
+    pub msg: String,
+}
+
 /// The first error encountered when parsing.
 #[derive(Debug, Error)]
 pub struct ParseError {
@@ -130,6 +232,10 @@ pub fn parse_with_flags(
     } else {
         match parsed.first_error() {
             Some((loc, msg)) => Err(ParseError { loc, msg }),
+            None if gc.ctx().is_cancelled() => Err(ParseError {
+                loc: ast::SourceLoc::invalid(),
+                msg: "cancelled".into(),
+            }),
             None => Err(ParseError {
                 loc: ast::SourceLoc::invalid(),
                 msg: "invalid AST produced".into(),
@@ -138,6 +244,32 @@ pub fn parse_with_flags(
     }
 }
 
+/// Parse the source, resuming at the next statement after each syntax error, up to
+/// `flags.error_limit` errors. It automatically imports the source string into the source
+/// manager. Return the partial AST, if there is one, and all the errors.
+pub fn parse_with_recovery(
+    flags: ParserFlags,
+    source: &str,
+    ctx: &mut ast::Context,
+) -> (Option<ast::NodeRc>, Vec<ParseError>) {
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_check(source));
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let parsed = ParsedJS::parse(
+        ParserFlags {
+            error_recovery: true,
+            ..flags
+        },
+        &buf,
+    );
+    let gc = ast::GCLock::new(ctx);
+    let ast = parsed
+        .to_ast(&gc, file_id)
+        .map(|ast| ast::NodeRc::from_node(&gc, ast));
+    (ast, parsed.errors())
+}
+
 /// This is a simple function that is intended to be used mostly for testing.
 /// It automatically imports the source string into the source manager.
 /// When there are errors, it returns only the first error.
@@ -145,6 +277,87 @@ pub fn parse(ctx: &mut ast::Context, source: &str) -> Result<ast::NodeRc, ParseE
     parse_with_flags(Default::default(), source, ctx)
 }
 
+/// Goal symbol of a snippet parsed by [`parse_snippet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetGoal {
+    /// A single expression.
+    Expression,
+
+    /// A single statement or declaration.
+    Statement,
+}
+
+/// Parse `source`, which must consist of exactly one `goal`, into an existing context,
+/// e.g. to build replacement nodes from strings instead of builders.
+/// The source is registered with the source manager, so the nodes have valid ranges.
+pub fn parse_snippet(
+    ctx: &mut ast::Context,
+    flags: ParserFlags,
+    goal: SnippetGoal,
+    source: &str,
+) -> Result<ast::NodeRc, ParseError> {
+    // The parser only accepts programs, so expressions are wrapped in parentheses.
+    // This avoids parsing `{}` as a block or `function f() {}` as a declaration.
+    // The closing parenthesis is on its own line, in case the source ends with a comment.
+    let text = match goal {
+        SnippetGoal::Expression => format!("({}\n)", source),
+        SnippetGoal::Statement => source.to_string(),
+    };
+    let program = parse_with_flags(flags, &text, ctx).map_err(|mut e| {
+        // Report the location of the error relative to `source`.
+        if goal == SnippetGoal::Expression && e.loc.line == 1 && e.loc.col > 1 {
+            e.loc.col -= 1;
+        }
+        e
+    })?;
+
+    let gc = ast::GCLock::new(ctx);
+    let body = match program.node(&gc) {
+        ast::Node::Program(ast::Program { body, .. })
+        | ast::Node::Module(ast::Module { body, .. }) => body,
+        n => unreachable!("unexpected root {}", n.name()),
+    };
+    let stmt = match body.head() {
+        Some(stmt) if body.len() == 1 => stmt,
+        _ => return Err(snippet_error(goal)),
+    };
+    let node = match goal {
+        SnippetGoal::Statement => stmt,
+        SnippetGoal::Expression => {
+            let expr = node_cast!(ast::Node::ExpressionStatement, stmt).expression;
+            // Parentheses aren't in the AST, so an expression which starts with the opening
+            // parenthesis spans several expressions, e.g. `a), (b`.
+            if expr.range().start.col == 1 {
+                return Err(snippet_error(goal));
+            }
+            expr
+        }
+    };
+    Ok(ast::NodeRc::from_node(&gc, node))
+}
+
+/// Parse `source` as a single expression into an existing context.
+/// See [`parse_snippet`].
+pub fn parse_expression(ctx: &mut ast::Context, source: &str) -> Result<ast::NodeRc, ParseError> {
+    parse_snippet(ctx, Default::default(), SnippetGoal::Expression, source)
+}
+
+/// Parse `source` as a single statement into an existing context.
+/// See [`parse_snippet`].
+pub fn parse_statement(ctx: &mut ast::Context, source: &str) -> Result<ast::NodeRc, ParseError> {
+    parse_snippet(ctx, Default::default(), SnippetGoal::Statement, source)
+}
+
+fn snippet_error(goal: SnippetGoal) -> ParseError {
+    ParseError {
+        loc: ast::SourceLoc { line: 1, col: 1 },
+        msg: match goal {
+            SnippetGoal::Expression => "expected a single expression".into(),
+            SnippetGoal::Statement => "expected a single statement".into(),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,4 +367,79 @@ mod tests {
         let mut ctx = ast::Context::new();
         parse(&mut ctx, "function foo(p1) { var x = (10 + p1); }").expect("Parse failed");
     }
+
+    #[test]
+    fn test_snippet() {
+        let mut ctx = ast::Context::new();
+        let expr = parse_expression(&mut ctx, "{a: 1} // comment").unwrap();
+        let stmt = parse_statement(&mut ctx, "function f() {}").unwrap();
+        {
+            let gc = ast::GCLock::new(&mut ctx);
+            assert!(matches!(expr.node(&gc), ast::Node::ObjectExpression(_)));
+            assert!(matches!(stmt.node(&gc), ast::Node::FunctionDeclaration(_)));
+        }
+        let expr = parse_expression(&mut ctx, "function () {}").unwrap();
+        {
+            let gc = ast::GCLock::new(&mut ctx);
+            assert!(matches!(expr.node(&gc), ast::Node::FunctionExpression(_)));
+        }
+
+        assert!(parse_expression(&mut ctx, "a), (b").is_err());
+        assert!(parse_expression(&mut ctx, "a); (b").is_err());
+        assert!(parse_statement(&mut ctx, "a; b").is_err());
+        assert!(parse_expression(&mut ctx, "a +").is_err());
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let src = "var x+ = 10;\nvar y = 1;\nfunction f() { foo(; bar(); }\nbaz();\n";
+        let mut ctx = ast::Context::new();
+        assert!(parse(&mut ctx, src).is_err());
+
+        let (ast, errors) = parse_with_recovery(Default::default(), src, &mut ctx);
+        assert_eq!(
+            errors.iter().map(|e| e.loc.line).collect::<Vec<_>>(),
+            [1, 3]
+        );
+        let ast = ast.unwrap();
+        let gc = ast::GCLock::new(&mut ctx);
+        let program = node_cast!(ast::Node::Program, ast.node(&gc));
+        // `var y`, `function f` and `baz()`.
+        assert_eq!(program.body.len(), 3);
+        let f = node_cast!(
+            ast::Node::FunctionDeclaration,
+            program.body.iter().nth(1).unwrap()
+        );
+        let body = node_cast!(ast::Node::BlockStatement, f.body);
+        // Only `foo(;` is skipped, parsing resumes at `bar()`.
+        assert_eq!(body.body.len(), 1);
+    }
+
+    #[test]
+    fn test_recoveries() {
+        let src = "a @;\nswitch (x) { default: f(); default: g(); }\n";
+        let buf = NullTerminatedBuf::from_str_copy(src);
+        let loc = |line, col| ast::SourceLoc { line, col };
+
+        let parsed = ParsedJS::parse(Default::default(), &buf);
+        assert!(parsed.has_errors());
+        assert!(parsed.recoveries().is_empty());
+
+        let flags = ParserFlags {
+            record_recovery: true,
+            ..Default::default()
+        };
+        let parsed = ParsedJS::parse(flags, &buf);
+        let recoveries = parsed.recoveries();
+        assert_eq!(
+            recoveries
+                .iter()
+                .map(|r| (r.start, r.end, r.msg.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (loc(1, 3), loc(1, 3), "recovery: skipped character '@'"),
+                (loc(2, 28), loc(2, 40), "recovery: dropped 'default' clause"),
+            ]
+        );
+    }
 }
@@ -0,0 +1,97 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! `juno-ignore` comments, which protect the code following them from transforms.
+//!
+//! A comment whose whole text is `juno-ignore`, like `/* juno-ignore */` or
+//! `// juno-ignore`, freezes the outermost node starting after it, e.g. the next statement,
+//! so that later passes leave it as it is (see [`GCLock::freeze`]).
+
+use juno_support::source_manager::line_starts;
+use juno_support::NullTerminatedBuf;
+
+use crate::ast::iter_preorder;
+use crate::ast::GCLock;
+use crate::ast::Node;
+use crate::ast::SourceId;
+use crate::ast::SourceLoc;
+use crate::hparser::lexer::Lexer;
+use crate::hparser::lexer::TokenKind;
+use crate::hparser::lexer::TriviaOpt;
+
+/// Text of the comments which freeze the following node.
+pub const IGNORE_COMMENT: &str = "juno-ignore";
+
+/// Return the location right after each `juno-ignore` comment in `source`, in source order.
+pub fn ignore_comments(source: &NullTerminatedBuf) -> Vec<SourceLoc> {
+    let text = &source.as_bytes()[..source.len() - 1];
+    let starts = line_starts(text);
+    let trivia = TriviaOpt {
+        comments: true,
+        whitespace: false,
+    };
+    Lexer::new(Default::default(), source, trivia)
+        .filter(|token| {
+            let content = match token.kind {
+                TokenKind::LineComment => &text[token.range.start + 2..token.range.end],
+                TokenKind::BlockComment => &text[token.range.start + 2..token.range.end - 2],
+                _ => return false,
+            };
+            std::str::from_utf8(content).map(str::trim) == Ok(IGNORE_COMMENT)
+        })
+        .map(|token| {
+            let line = starts.partition_point(|&start| start <= token.range.end) - 1;
+            SourceLoc {
+                line: line as u32 + 1,
+                col: (token.range.end - starts[line]) as u32 + 1,
+            }
+        })
+        .collect()
+}
+
+/// Freeze the outermost node of `file` starting at or after each of `locs`, which must be
+/// sorted. The `Program` or `Module` is never frozen, even though it starts at its first
+/// token, after any leading comments. Returns the number of nodes which were frozen.
+pub fn freeze_after<'gc>(
+    gc: &'gc GCLock,
+    root: &'gc Node<'gc>,
+    file: SourceId,
+    locs: &[SourceLoc],
+) -> usize {
+    let key = |loc: SourceLoc| (loc.line, loc.col);
+    let mut remaining = locs.iter().copied().peekable();
+    let mut count = 0;
+    for (node, _, _) in iter_preorder(gc, root) {
+        let range = node.range();
+        if range.file != file || matches!(node, Node::Program(_) | Node::Module(_)) {
+            continue;
+        }
+        let mut found = false;
+        while let Some(&loc) = remaining.peek() {
+            if key(loc) > key(range.start) {
+                break;
+            }
+            remaining.next();
+            found = true;
+        }
+        if found {
+            gc.freeze(node);
+            count += 1;
+        }
+        if remaining.peek().is_none() {
+            break;
+        }
+    }
+    count
+}
+
+/// Freeze the nodes following the `juno-ignore` comments of `file`, whose AST is `root`.
+/// Returns the number of nodes which were frozen.
+pub fn freeze_ignored<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>, file: SourceId) -> usize {
+    let locs = ignore_comments(gc.sm().source_buffer(file));
+    freeze_after(gc, root, file, &locs)
+}
@@ -0,0 +1,405 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! JSDoc comments, for documentation generators and for lint rules which need the types of
+//! plain JS.
+//!
+//! A JSDoc comment is a block comment starting with `/**`. It documents the outermost node
+//! starting at the first token after it, like a declaration, a class member or a property,
+//! and an exported declaration is documented by the comment preceding `export`.
+//! Its text is parsed by [`parse`] into a description and tags, and the common tags,
+//! `@param`, `@returns`, `@type` and `@deprecated`, are parsed further.
+//! Types are kept as written, without the braces.
+
+use std::collections::HashMap;
+
+use juno_support::source_manager::line_starts;
+use juno_support::NullTerminatedBuf;
+
+use crate::ast::iter_preorder;
+use crate::ast::ExportDefaultDeclaration;
+use crate::ast::GCLock;
+use crate::ast::Node;
+use crate::ast::NodePtr;
+use crate::ast::SourceId;
+use crate::ast::SourceLoc;
+use crate::hparser::lexer::Lexer;
+use crate::hparser::lexer::TokenKind;
+use crate::hparser::lexer::TriviaOpt;
+
+/// A parsed JSDoc comment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JSDoc {
+    /// The text before the first tag.
+    pub description: String,
+
+    /// The `@param` tags, also spelled `@arg` and `@argument`, in order.
+    pub params: Vec<ParamTag>,
+
+    /// The `@returns` tag, also spelled `@return`.
+    pub returns: Option<ReturnsTag>,
+
+    /// The type of a `@type` tag.
+    pub type_expr: Option<String>,
+
+    /// The message of a `@deprecated` tag, empty if there is none.
+    pub deprecated: Option<String>,
+
+    /// The other tags, in order.
+    pub tags: Vec<Tag>,
+}
+
+/// A `@param {type} name description` tag.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamTag {
+    /// The name, which is dotted for the properties of a parameter, like `options.verbose`.
+    pub name: String,
+
+    pub type_expr: Option<String>,
+
+    /// Whether the name is in brackets, `[name]`, or the type ends with `=`.
+    pub optional: bool,
+
+    /// The default value of `[name=value]`.
+    pub default: Option<String>,
+
+    pub description: String,
+}
+
+/// A `@returns {type} description` tag.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReturnsTag {
+    pub type_expr: Option<String>,
+    pub description: String,
+}
+
+/// Any other tag, `@name text`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub text: String,
+}
+
+impl JSDoc {
+    /// The `@param` tag of the parameter `name`, if any.
+    pub fn param(&self, name: &str) -> Option<&ParamTag> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    /// The tags named `name`, among the tags which aren't parsed further.
+    pub fn tags_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Tag> + 'a {
+        self.tags.iter().filter(move |tag| tag.name == name)
+    }
+}
+
+/// Whether `comment`, the whole text of a block comment, is a JSDoc comment.
+pub fn is_jsdoc(comment: &str) -> bool {
+    // `/**/` is an empty comment, and `/***` usually starts a banner.
+    comment.starts_with("/**") && !comment.starts_with("/***") && comment != "/**/"
+}
+
+/// Parse `comment`, the whole text of a JSDoc comment, including `/**` and `*/`.
+pub fn parse(comment: &str) -> JSDoc {
+    let content = comment.strip_prefix("/**").unwrap_or(comment);
+    let content = content.strip_suffix("*/").unwrap_or(content);
+
+    // The lines without their leading `*`, grouped by the tag they belong to.
+    let mut description = String::new();
+    let mut tags: Vec<(String, String)> = vec![];
+    for line in content.lines() {
+        let line = line.trim_start();
+        let line = match line.strip_prefix('*') {
+            Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+            None => line,
+        };
+        if let Some(tag) = line.strip_prefix('@') {
+            let end = tag
+                .find(|c: char| c.is_whitespace() || c == '{')
+                .unwrap_or(tag.len());
+            tags.push((tag[..end].to_string(), tag[end..].trim().to_string()));
+            continue;
+        }
+        // Continuation lines of tags are unindented, but the description may contain code.
+        let (text, line) = match tags.last_mut() {
+            Some((_, text)) => (text, line.trim()),
+            None => (&mut description, line.trim_end()),
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+
+    let mut doc = JSDoc {
+        description: description.trim().to_string(),
+        ..Default::default()
+    };
+    for (name, text) in tags {
+        let text = text.trim();
+        match name.as_str() {
+            "param" | "arg" | "argument" => doc.params.push(parse_param(text)),
+            "returns" | "return" => {
+                let (type_expr, rest) = split_type(text);
+                doc.returns = Some(ReturnsTag {
+                    type_expr,
+                    description: rest.to_string(),
+                });
+            }
+            "type" => doc.type_expr = split_type(text).0,
+            "deprecated" => doc.deprecated = Some(text.to_string()),
+            _ => doc.tags.push(Tag {
+                name,
+                text: text.to_string(),
+            }),
+        }
+    }
+    doc
+}
+
+/// Split the `{type}` at the start of `text`, if any, from the rest of the text.
+/// Braces may nest, as in `{{a: number}}`.
+fn split_type(text: &str) -> (Option<String>, &str) {
+    if !text.starts_with('{') {
+        return (None, text);
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (
+                        Some(text[1..i].trim().to_string()),
+                        text[i + 1..].trim_start(),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    // Unterminated, so it isn't a type.
+    (None, text)
+}
+
+/// Parse the text of a `@param` tag: `{type} name - description`, where the name may be
+/// `[name]` or `[name=default]`, and the type and the dash are optional.
+fn parse_param(text: &str) -> ParamTag {
+    let (type_expr, rest) = split_type(text);
+    let mut param = ParamTag::default();
+    let rest = if let Some(bracketed) = rest.strip_prefix('[') {
+        let end = bracketed.find(']').unwrap_or(bracketed.len());
+        let (name, default) = match bracketed[..end].split_once('=') {
+            Some((name, default)) => (name, Some(default.trim().to_string())),
+            None => (&bracketed[..end], None),
+        };
+        param.name = name.trim().to_string();
+        param.default = default;
+        param.optional = true;
+        bracketed.get(end + 1..).unwrap_or("")
+    } else {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        param.name = rest[..end].to_string();
+        &rest[end..]
+    };
+    let rest = rest.trim_start();
+    param.description = rest
+        .strip_prefix('-')
+        .map_or(rest, str::trim_start)
+        .to_string();
+    param.type_expr = type_expr.map(|ty| match ty.strip_suffix('=') {
+        Some(ty) => {
+            param.optional = true;
+            ty.to_string()
+        }
+        None => ty,
+    });
+    param
+}
+
+/// Return the JSDoc comments of `source`, each with the location of the first token after
+/// it, in source order. Consecutive comments have the same location.
+pub fn jsdoc_comments(source: &NullTerminatedBuf) -> Vec<(SourceLoc, JSDoc)> {
+    let text = &source.as_bytes()[..source.len() - 1];
+    let starts = line_starts(text);
+    let trivia = TriviaOpt {
+        comments: true,
+        whitespace: false,
+    };
+    let mut result = vec![];
+    let mut pending = vec![];
+    for token in Lexer::new(Default::default(), source, trivia) {
+        match token.kind {
+            TokenKind::BlockComment => {
+                let comment = String::from_utf8_lossy(&text[token.range.clone()]);
+                if is_jsdoc(&comment) {
+                    pending.push(parse(&comment));
+                }
+            }
+            TokenKind::LineComment | TokenKind::Hashbang => {}
+            _ => {
+                if !pending.is_empty() {
+                    let line = starts.partition_point(|&start| start <= token.range.start) - 1;
+                    let loc = SourceLoc {
+                        line: line as u32 + 1,
+                        col: (token.range.start - starts[line]) as u32 + 1,
+                    };
+                    result.extend(pending.drain(..).map(|doc| (loc, doc)));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// The JSDoc comments of a file, attached to the nodes they document.
+#[derive(Debug, Default)]
+pub struct JSDocs<'gc> {
+    docs: HashMap<NodePtr<'gc>, JSDoc>,
+}
+
+impl<'gc> JSDocs<'gc> {
+    /// The JSDoc comment documenting `node`, if any.
+    pub fn get(&self, node: &'gc Node<'gc>) -> Option<&JSDoc> {
+        self.docs.get(&NodePtr::from_node(node))
+    }
+
+    /// Number of documented nodes.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+}
+
+/// Attach the JSDoc comments of `file`, whose AST is `root`, to the nodes they document.
+pub fn attach<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>, file: SourceId) -> JSDocs<'gc> {
+    let comments = jsdoc_comments(gc.sm().source_buffer(file));
+    attach_comments(gc, root, file, comments)
+}
+
+/// Attach `comments`, as returned by [`jsdoc_comments`] for `file`, to the outermost node
+/// starting at their location, and to the declaration of an export node. Of consecutive
+/// comments, the last one documents the node.
+pub fn attach_comments<'gc>(
+    gc: &'gc GCLock,
+    root: &'gc Node<'gc>,
+    file: SourceId,
+    comments: Vec<(SourceLoc, JSDoc)>,
+) -> JSDocs<'gc> {
+    let key = |loc: SourceLoc| (loc.line, loc.col);
+    let mut docs = JSDocs::default();
+    let mut remaining = comments.into_iter().peekable();
+    for (node, _, _) in iter_preorder(gc, root) {
+        let range = node.range();
+        if range.file != file {
+            continue;
+        }
+        // Skip the comments which aren't followed by the start of a node, e.g. `/** */ )`.
+        while let Some((loc, _)) = remaining.peek() {
+            if key(*loc) >= key(range.start) {
+                break;
+            }
+            remaining.next();
+        }
+        match remaining.peek() {
+            Some((loc, _)) if key(*loc) == key(range.start) => {}
+            Some(_) => continue,
+            None => break,
+        }
+        let (loc, mut doc) = remaining.next().unwrap();
+        while let Some((next_loc, _)) = remaining.peek() {
+            if key(*next_loc) != key(loc) {
+                break;
+            }
+            doc = remaining.next().unwrap().1;
+        }
+        match node {
+            Node::ExportNamedDeclaration(export) => {
+                if let Some(decl) = export.declaration {
+                    docs.docs.insert(NodePtr::from_node(decl), doc.clone());
+                }
+            }
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration {
+                declaration: decl, ..
+            }) => {
+                docs.docs.insert(NodePtr::from_node(decl), doc.clone());
+            }
+            _ => {}
+        }
+        docs.docs.insert(NodePtr::from_node(node), doc);
+    }
+    docs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let doc = parse(
+            "/**
+              * Adds numbers.
+              * Really.
+              *
+              * @param {number} a The first.
+              * @param {{x: number}=} b - The second,
+              *   on two lines.
+              * @param [c=1]
+              * @returns {number} The sum.
+              * @deprecated Use `plus`.
+              * @see plus
+              */",
+        );
+        assert_eq!(doc.description, "Adds numbers.\nReally.");
+        assert_eq!(
+            doc.params,
+            [
+                ParamTag {
+                    name: "a".to_string(),
+                    type_expr: Some("number".to_string()),
+                    optional: false,
+                    default: None,
+                    description: "The first.".to_string(),
+                },
+                ParamTag {
+                    name: "b".to_string(),
+                    type_expr: Some("{x: number}".to_string()),
+                    optional: true,
+                    default: None,
+                    description: "The second,\non two lines.".to_string(),
+                },
+                ParamTag {
+                    name: "c".to_string(),
+                    type_expr: None,
+                    optional: true,
+                    default: Some("1".to_string()),
+                    description: String::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            doc.returns,
+            Some(ReturnsTag {
+                type_expr: Some("number".to_string()),
+                description: "The sum.".to_string(),
+            })
+        );
+        assert_eq!(doc.deprecated.as_deref(), Some("Use `plus`."));
+        assert_eq!(doc.tags_named("see").next().unwrap().text, "plus");
+
+        let doc = parse("/** @type {Array<string>} */");
+        assert_eq!(doc.description, "");
+        assert_eq!(doc.type_expr.as_deref(), Some("Array<string>"));
+        assert!(is_jsdoc("/** @type {T} */"));
+        assert!(!is_jsdoc("/* a */"));
+        assert!(!is_jsdoc("/**/"));
+    }
+}
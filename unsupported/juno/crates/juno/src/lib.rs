@@ -7,8 +7,20 @@
 
 pub use juno_ast as ast;
 
+pub mod analysis;
+pub mod coverage;
+pub mod docgen;
+pub mod eval;
+pub mod gen_dts;
 pub mod gen_js;
 pub mod hparser;
+pub mod ignore;
+pub mod jsdoc;
+pub mod module_graph;
+pub mod precedence;
+pub mod reduce;
 pub mod resolve_dependency;
 pub mod sema;
 pub mod sourcemap;
+pub mod stats;
+pub mod test262;
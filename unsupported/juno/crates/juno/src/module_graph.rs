@@ -0,0 +1,228 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The graph of ES modules linked by their import declarations, used to decide in
+//! which order bundled modules are evaluated.
+//!
+//! Modules are evaluated after the modules they import, in depth-first post-order.
+//! A module with a top-level `await` is evaluated asynchronously, and so is every module
+//! importing it, directly or transitively: such a module only starts once its async
+//! imports have finished, after all the modules which don't wait for anything.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use juno_support::source_manager::SourceId;
+
+use crate::ast::find_top_level_await;
+use crate::ast::GCLock;
+use crate::ast::Node;
+use crate::sema::SemContext;
+
+/// A module in the [`ModuleGraph`].
+#[derive(Debug, Clone, Default)]
+pub struct GraphModule {
+    /// Modules imported by this module, in source order, without duplicates.
+    pub imports: Vec<SourceId>,
+    /// Whether the module contains a top-level `await`.
+    pub has_top_level_await: bool,
+}
+
+/// A module in the result of [`ModuleGraph::evaluation_order`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EvaluationStep {
+    pub module: SourceId,
+    /// Whether the module starts after some of its imports have finished asynchronously.
+    pub deferred: bool,
+}
+
+/// Modules and the modules they import.
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    modules: HashMap<SourceId, GraphModule>,
+}
+
+impl ModuleGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add the module `id`, replacing any previous module with the same ID.
+    pub fn add_module(&mut self, id: SourceId, module: GraphModule) {
+        self.modules.insert(id, module);
+    }
+
+    /// Add the module `id`, whose AST is `root`, using the imports resolved by `sem`.
+    /// `require` and `import()` calls are ignored, since they don't affect the evaluation order.
+    pub fn add_resolved<'gc>(
+        &mut self,
+        lock: &'gc GCLock,
+        id: SourceId,
+        root: &'gc Node<'gc>,
+        sem: &SemContext,
+    ) {
+        let mut imports = sem
+            .all_requires()
+            .iter()
+            .filter_map(|(node, &target)| match node.node(lock) {
+                import @ Node::ImportDeclaration(_) => {
+                    let start = import.range().start;
+                    Some(((start.line, start.col), target))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        imports.sort_by_key(|&(loc, _)| loc);
+        let mut seen = HashSet::new();
+        self.add_module(
+            id,
+            GraphModule {
+                imports: imports
+                    .into_iter()
+                    .map(|(_, target)| target)
+                    .filter(|&target| seen.insert(target))
+                    .collect(),
+                has_top_level_await: find_top_level_await(lock, root).is_some(),
+            },
+        );
+    }
+
+    /// Return the module `id`, if it was added.
+    pub fn module(&self, id: SourceId) -> Option<&GraphModule> {
+        self.modules.get(&id)
+    }
+
+    /// Return the IDs of the modules which are evaluated asynchronously: the ones with a
+    /// top-level `await` and the ones importing them, directly or transitively.
+    pub fn async_modules(&self) -> HashSet<SourceId> {
+        let mut importers = HashMap::<SourceId, Vec<SourceId>>::new();
+        for (&id, module) in &self.modules {
+            for &import in &module.imports {
+                importers.entry(import).or_default().push(id);
+            }
+        }
+        let mut worklist = self
+            .modules
+            .iter()
+            .filter(|(_, module)| module.has_top_level_await)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        let mut result = worklist.iter().copied().collect::<HashSet<_>>();
+        while let Some(id) = worklist.pop() {
+            for &importer in importers.get(&id).into_iter().flatten() {
+                if result.insert(importer) {
+                    worklist.push(importer);
+                }
+            }
+        }
+        result
+    }
+
+    /// Return the modules reachable from `entry` in the order in which they start
+    /// evaluating, assuming that async modules finish in the order in which they started.
+    /// Imports of modules which weren't added are ignored.
+    pub fn evaluation_order(&self, entry: SourceId) -> Vec<EvaluationStep> {
+        let async_modules = self.async_modules();
+        let mut postorder = vec![];
+        let mut visited = HashSet::new();
+        self.visit_postorder(entry, &mut visited, &mut postorder);
+
+        let waits = |id: SourceId| {
+            self.modules[&id]
+                .imports
+                .iter()
+                .any(|import| async_modules.contains(import))
+        };
+        let (deferred, immediate): (Vec<_>, Vec<_>) =
+            postorder.into_iter().partition(|&id| waits(id));
+        immediate
+            .into_iter()
+            .map(|module| EvaluationStep {
+                module,
+                deferred: false,
+            })
+            .chain(deferred.into_iter().map(|module| EvaluationStep {
+                module,
+                deferred: true,
+            }))
+            .collect()
+    }
+
+    /// Append to `postorder` the modules reachable from `id` which haven't been visited yet,
+    /// each one after the modules it imports.
+    fn visit_postorder(
+        &self,
+        id: SourceId,
+        visited: &mut HashSet<SourceId>,
+        postorder: &mut Vec<SourceId>,
+    ) {
+        let module = match self.modules.get(&id) {
+            Some(module) => module,
+            None => return,
+        };
+        if !visited.insert(id) {
+            return;
+        }
+        for &import in &module.imports {
+            self.visit_postorder(import, visited, postorder);
+        }
+        postorder.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(imports: &[u32], has_top_level_await: bool) -> GraphModule {
+        GraphModule {
+            imports: imports.iter().map(|&i| SourceId(i)).collect(),
+            has_top_level_await,
+        }
+    }
+
+    fn order(graph: &ModuleGraph, entry: u32) -> Vec<(u32, bool)> {
+        graph
+            .evaluation_order(SourceId(entry))
+            .iter()
+            .map(|step| (step.module.0, step.deferred))
+            .collect()
+    }
+
+    #[test]
+    fn test_sync_order() {
+        let mut graph = ModuleGraph::new();
+        graph.add_module(SourceId(0), module(&[1, 2], false));
+        graph.add_module(SourceId(1), module(&[2, 3], false));
+        graph.add_module(SourceId(2), module(&[0], false));
+        graph.add_module(SourceId(3), module(&[4], false));
+        assert!(graph.async_modules().is_empty());
+        // The cycle through 0 is cut, and 4 was never added.
+        assert_eq!(
+            order(&graph, 0),
+            vec![(2, false), (3, false), (1, false), (0, false)]
+        );
+    }
+
+    #[test]
+    fn test_top_level_await() {
+        let mut graph = ModuleGraph::new();
+        graph.add_module(SourceId(0), module(&[1, 3], false));
+        graph.add_module(SourceId(1), module(&[2], false));
+        graph.add_module(SourceId(2), module(&[], true));
+        graph.add_module(SourceId(3), module(&[], false));
+        assert_eq!(
+            graph.async_modules(),
+            [0, 1, 2].iter().map(|&i| SourceId(i)).collect()
+        );
+        // 3 doesn't wait for 2, unlike 1 and 0.
+        assert_eq!(
+            order(&graph, 0),
+            vec![(2, false), (3, false), (1, true), (0, true)]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Operator precedence and associativity of JS expressions,
+//! used to decide where parentheses are required when emitting or rewriting expressions.
+
+use crate::ast::*;
+
+pub type Precedence = u32;
+
+pub const ALWAYS_PAREN: Precedence = 0;
+pub const SEQ: Precedence = 1;
+pub const ARROW: Precedence = 2;
+pub const YIELD: Precedence = 3;
+pub const ASSIGN: Precedence = 4;
+pub const COND: Precedence = 5;
+pub const BIN_START: Precedence = 6;
+pub const UNARY: Precedence = 26;
+pub const POST_UPDATE: Precedence = 27;
+pub const TAGGED_TEMPLATE: Precedence = 28;
+pub const NEW_NO_ARGS: Precedence = 29;
+pub const MEMBER: Precedence = 30;
+pub const PRIMARY: Precedence = 31;
+pub const TOP: Precedence = 32;
+
+pub const UNION_TYPE: Precedence = 1;
+pub const INTERSECTION_TYPE: Precedence = 2;
+
+/// Associativity direction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Assoc {
+    /// Left to right associativity.
+    Ltr,
+
+    /// Right to left associativity.
+    Rtl,
+}
+
+/// Child position for the purpose of determining whether the child needs parens.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChildPos {
+    Left,
+    Anywhere,
+    Right,
+}
+
+pub fn get_binary_precedence(op: BinaryExpressionOperator) -> Precedence {
+    use BinaryExpressionOperator::*;
+    (match op {
+        Exp => 12,
+        Mult => 11,
+        Mod => 11,
+        Div => 11,
+        Plus => 10,
+        Minus => 10,
+        LShift => 9,
+        RShift => 9,
+        RShift3 => 9,
+        Less => 8,
+        Greater => 8,
+        LessEquals => 8,
+        GreaterEquals => 8,
+        LooseEquals => 7,
+        LooseNotEquals => 7,
+        StrictEquals => 7,
+        StrictNotEquals => 7,
+        BitAnd => 6,
+        BitXor => 5,
+        BitOr => 4,
+        In => 8,
+        Instanceof => 8,
+    }) + BIN_START
+}
+
+pub fn get_binary_assoc(op: BinaryExpressionOperator) -> Assoc {
+    match op {
+        BinaryExpressionOperator::Exp => Assoc::Rtl,
+        _ => Assoc::Ltr,
+    }
+}
+
+pub fn get_logical_precedence(op: LogicalExpressionOperator) -> Precedence {
+    use LogicalExpressionOperator::*;
+    (match op {
+        And => 3,
+        Or => 2,
+        NullishCoalesce => 1,
+    }) + BIN_START
+}
+
+/// All unary operators share the same precedence.
+pub fn get_unary_precedence(_op: UnaryExpressionOperator) -> Precedence {
+    UNARY
+}
+
+/// All assignment operators share the same precedence.
+pub fn get_assignment_precedence(_op: AssignmentExpressionOperator) -> Precedence {
+    ASSIGN
+}
+
+/// Return the precedence and associativity of `node`.
+/// `new` without arguments is assumed to be emitted without `()`,
+/// which is the conservative choice.
+pub fn get_precedence(node: &Node<'_>) -> (Precedence, Assoc) {
+    // Precedence order taken from
+    // https://github.com/facebook/flow/blob/master/src/parser_utils/output/js_layout_generator.ml
+    match &node {
+        Node::Identifier(_)
+        | Node::NullLiteral(_)
+        | Node::BooleanLiteral(_)
+        | Node::StringLiteral(_)
+        | Node::NumericLiteral(_)
+        | Node::RegExpLiteral(_)
+        | Node::ThisExpression(_)
+        | Node::Super(_)
+        | Node::ArrayExpression(_)
+        | Node::ObjectExpression(_)
+        | Node::ObjectPattern(_)
+        | Node::FunctionExpression(_)
+        | Node::ClassExpression(_)
+        | Node::TemplateLiteral(_)
+        | Node::JSXElement(_)
+        | Node::JSXFragment(_)
+        | Node::TypeCastExpression(_) => (PRIMARY, Assoc::Ltr),
+        Node::MemberExpression(_)
+        | Node::OptionalMemberExpression(_)
+        | Node::MetaProperty(_)
+        | Node::CallExpression(_)
+        | Node::OptionalCallExpression(_) => (MEMBER, Assoc::Ltr),
+        Node::NewExpression(NewExpression {
+            metadata: _,
+            arguments,
+            ..
+        }) => {
+            // `new foo()` has higher precedence than `new foo`.
+            if !arguments.is_empty() {
+                (MEMBER, Assoc::Ltr)
+            } else {
+                (NEW_NO_ARGS, Assoc::Ltr)
+            }
+        }
+        Node::TaggedTemplateExpression(_) | Node::ImportExpression(_) => {
+            (TAGGED_TEMPLATE, Assoc::Ltr)
+        }
+        Node::UpdateExpression(UpdateExpression {
+            metadata: _,
+            prefix,
+            ..
+        }) => {
+            if *prefix {
+                (POST_UPDATE, Assoc::Ltr)
+            } else {
+                (UNARY, Assoc::Rtl)
+            }
+        }
+        Node::UnaryExpression(UnaryExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (get_unary_precedence(*operator), Assoc::Rtl),
+        Node::BinaryExpression(BinaryExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (
+            get_binary_precedence(*operator),
+            get_binary_assoc(*operator),
+        ),
+        Node::LogicalExpression(LogicalExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (get_logical_precedence(*operator), Assoc::Ltr),
+        Node::ConditionalExpression(_) => (COND, Assoc::Rtl),
+        Node::AssignmentExpression(AssignmentExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (get_assignment_precedence(*operator), Assoc::Rtl),
+        Node::ArrowFunctionExpression(_) => (ARROW, Assoc::Ltr),
+        Node::YieldExpression(_) => (YIELD, Assoc::Ltr),
+        Node::SequenceExpression(_) => (SEQ, Assoc::Rtl),
+
+        Node::ExistsTypeAnnotation(_)
+        | Node::EmptyTypeAnnotation(_)
+        | Node::StringTypeAnnotation(_)
+        | Node::BigIntTypeAnnotation(_)
+        | Node::NumberTypeAnnotation(_)
+        | Node::StringLiteralTypeAnnotation(_)
+        | Node::NumberLiteralTypeAnnotation(_)
+        | Node::BooleanTypeAnnotation(_)
+        | Node::BooleanLiteralTypeAnnotation(_)
+        | Node::NullLiteralTypeAnnotation(_)
+        | Node::SymbolTypeAnnotation(_)
+        | Node::AnyTypeAnnotation(_)
+        | Node::MixedTypeAnnotation(_)
+        | Node::VoidTypeAnnotation(_) => (PRIMARY, Assoc::Ltr),
+        Node::NullableTypeAnnotation(_) => (UNARY, Assoc::Ltr),
+        Node::UnionTypeAnnotation(_) => (UNION_TYPE, Assoc::Ltr),
+        Node::IntersectionTypeAnnotation(_) => (INTERSECTION_TYPE, Assoc::Ltr),
+
+        _ => (ALWAYS_PAREN, Assoc::Ltr),
+    }
+}
+
+/// Return whether parentheses are needed around `child`,
+/// which is situated at `child_pos` in relation to `parent`.
+///
+/// Only considers the parent and the child themselves, so it doesn't detect
+/// expressions which would be misparsed due to what they start with when they begin
+/// a statement or an arrow function body (e.g. `({}).x;`).
+pub fn needs_parens<'gc>(
+    gc: &'gc GCLock,
+    parent: &'gc Node<'gc>,
+    child: &'gc Node<'gc>,
+    child_pos: ChildPos,
+) -> bool {
+    needs_parens_with(gc, parent, child, child_pos, get_precedence)
+}
+
+/// Implementation of [`needs_parens`] which allows the caller to override the precedence
+/// of nodes, for code generators which emit some nodes differently.
+pub(crate) fn needs_parens_with<'gc, F: Fn(&Node<'_>) -> (Precedence, Assoc)>(
+    gc: &'gc GCLock,
+    parent: &'gc Node<'gc>,
+    child: &'gc Node<'gc>,
+    child_pos: ChildPos,
+    get_precedence: F,
+) -> bool {
+    #[allow(clippy::if_same_then_else)]
+    if matches!(parent, Node::ForStatement(_)) {
+        // for((a in b);..;..) needs parens to avoid confusing it with for(a in b).
+        return match &child {
+            Node::BinaryExpression(BinaryExpression {
+                metadata: _,
+                operator,
+                ..
+            }) => *operator == BinaryExpressionOperator::In,
+            _ => false,
+        };
+    } else if matches!(parent, Node::NewExpression(_)) {
+        // `new(fn())` needs parens to avoid confusing it with `new fn()`.
+        // Need to check the entire subtree to ensure there isn't a call anywhere in it,
+        // because if there is, it would take precedence and terminate the `new` early.
+        // As an example, see the difference between
+        // `new(foo().bar)` (which gets `bar` on `foo()`)
+        // and
+        // `new foo().bar` (which gets `bar` on `new foo()`)
+        if child_pos == ChildPos::Left && contains_call(gc, child) {
+            return true;
+        }
+        // It's illegal to place parens around spread arguments.
+        if matches!(child, Node::SpreadElement(_)) {
+            return false;
+        }
+    } else if matches!(parent, Node::MemberExpression(_) | Node::CallExpression(_))
+        && matches!(
+            child,
+            Node::OptionalMemberExpression(_) | Node::OptionalCallExpression(_)
+        )
+        && child_pos == ChildPos::Left
+    {
+        // When optional chains are terminated by non-optional member/calls,
+        // we need the left hand side to be parenthesized.
+        // Avoids confusing `(a?.b).c` with `a?.b.c`.
+        return true;
+    } else if (check_and_or(parent) && check_nullish(child))
+        || (check_nullish(parent) && check_and_or(child))
+    {
+        // Nullish coalescing always requires parens when mixed with any
+        // other logical operations.
+        return true;
+    } else if matches!(
+        parent,
+        Node::CallExpression(_) | Node::OptionalCallExpression(_)
+    ) && matches!(child, Node::SpreadElement(_))
+    {
+        // It's illegal to place parens around spread arguments.
+        return false;
+    } else if matches!(parent, Node::AssignmentExpression(_))
+        && matches!(child, Node::ObjectPattern(_) | Node::ArrayPattern(_))
+        && child_pos == ChildPos::Left
+    {
+        // Avoid parentheses for destructuring patterns.
+        return false;
+    }
+
+    let (child_prec, _child_assoc) = get_precedence(child);
+    if child_prec == ALWAYS_PAREN {
+        return true;
+    }
+
+    let (parent_prec, parent_assoc) = get_precedence(parent);
+
+    if child_prec < parent_prec {
+        // Child is definitely a danger.
+        return true;
+    }
+    if child_prec > parent_prec {
+        // Definitely cool.
+        return false;
+    }
+    // Equal precedence, so associativity (rtl/ltr) is what matters.
+    if child_pos == ChildPos::Anywhere {
+        // Child could be anywhere, so always paren.
+        return true;
+    }
+    if child_prec == TOP {
+        // Both precedences are safe.
+        return false;
+    }
+    // Check if child is on the dangerous side.
+    if parent_assoc == Assoc::Rtl {
+        child_pos == ChildPos::Left
+    } else {
+        child_pos == ChildPos::Right
+    }
+}
+
+fn check_and_or(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::LogicalExpression(LogicalExpression {
+            metadata: _,
+            operator: LogicalExpressionOperator::And | LogicalExpressionOperator::Or,
+            ..
+        })
+    )
+}
+
+fn check_nullish(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::LogicalExpression(LogicalExpression {
+            metadata: _,
+            operator: LogicalExpressionOperator::NullishCoalesce,
+            ..
+        })
+    )
+}
+
+/// Return true if `node` contains a `CallExpression`.
+fn contains_call<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> bool {
+    struct CallFinder {
+        found: bool,
+    }
+    impl<'gc> Visitor<'gc> for CallFinder {
+        fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            match node {
+                Node::CallExpression(_) => {
+                    self.found = true;
+                }
+                Node::OptionalCallExpression(call) if !call.optional => {
+                    self.found = true;
+                }
+                _ => {
+                    node.visit_children(gc, self);
+                }
+            };
+        }
+    }
+    let mut finder = CallFinder { found: false };
+    node.visit(gc, &mut finder, None);
+    finder.found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builder::Identifier::build_template(
+            gc,
+            template::Identifier {
+                metadata: Default::default(),
+                name: gc.atom(name),
+                type_annotation: None,
+                optional: false,
+            },
+        )
+    }
+
+    fn binary<'gc>(
+        gc: &'gc GCLock,
+        operator: BinaryExpressionOperator,
+        left: &'gc Node<'gc>,
+        right: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builder::BinaryExpression::build_template(
+            gc,
+            template::BinaryExpression {
+                metadata: Default::default(),
+                left,
+                right,
+                operator,
+            },
+        )
+    }
+
+    #[test]
+    fn test_needs_parens() {
+        use BinaryExpressionOperator::*;
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+        let a = ident(&gc, "a");
+        let b = ident(&gc, "b");
+
+        // (a + b) * c
+        let sum = binary(&gc, Plus, a, b);
+        let prod = binary(&gc, Mult, sum, a);
+        assert!(needs_parens(&gc, prod, sum, ChildPos::Left));
+        assert!(!needs_parens(&gc, prod, a, ChildPos::Right));
+
+        // a - (a - b), but (a - b) - a
+        let diff = binary(&gc, Minus, a, b);
+        let outer = binary(&gc, Minus, diff, diff);
+        assert!(!needs_parens(&gc, outer, diff, ChildPos::Left));
+        assert!(needs_parens(&gc, outer, diff, ChildPos::Right));
+
+        // (a ** b) ** a, but a ** (a ** b)
+        let exp = binary(&gc, Exp, a, b);
+        let outer = binary(&gc, Exp, exp, exp);
+        assert!(needs_parens(&gc, outer, exp, ChildPos::Left));
+        assert!(!needs_parens(&gc, outer, exp, ChildPos::Right));
+    }
+}
@@ -0,0 +1,319 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Test case reduction: shrink an input which triggers a bug, while it still triggers it.
+//!
+//! The reduction is a form of delta debugging on the AST rather than on lines of text,
+//! so every candidate is syntactically valid. Two kinds of edits are tried:
+//! - removing elements of lists, like statements or arguments, starting with large chunks
+//!   and halving their size when none of them can be removed;
+//! - replacing an expression by one of its child expressions, or a statement by one of
+//!   its child statements, e.g. `if (x) { f(); }` by `{ f(); }` and then by `f();`.
+//!
+//! Candidates which fail AST validation are skipped. The others are passed to a test,
+//! which decides whether they are still "interesting", e.g. still make a pass panic.
+//! The first interesting candidate replaces the tree, and the edits are enumerated again,
+//! until no edit is interesting.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use crate::ast::*;
+use crate::gen_js;
+
+/// An edit of the tree, identifying nodes by their index in preorder.
+#[derive(Debug, Clone)]
+enum Edit {
+    /// Remove the nodes, which are consecutive elements of a list.
+    Remove(Vec<usize>),
+
+    /// Replace the `target` node by its descendant `child`.
+    Replace { target: usize, child: usize },
+}
+
+/// Statistics of a reduction.
+#[derive(Debug, Clone, Default)]
+pub struct ReduceStats {
+    /// Number of candidates passed to the test.
+    pub tests: usize,
+
+    /// Number of edits which were kept.
+    pub edits: usize,
+}
+
+/// Reduce the tree rooted at `root` while `test` returns true on it, and return the
+/// reduced tree. `test` is assumed to return true on `root`.
+pub fn reduce(
+    ctx: &mut Context,
+    root: NodeRc,
+    test: &mut dyn FnMut(&mut Context, &NodeRc) -> bool,
+    stats: &mut ReduceStats,
+) -> NodeRc {
+    let mut root = root;
+    'restart: loop {
+        for edit in edits(ctx, &root) {
+            let candidate = apply(ctx, &root, &edit);
+            if validate_tree_pure(ctx, &candidate).is_ok() {
+                stats.tests += 1;
+                if test(ctx, &candidate) {
+                    stats.edits += 1;
+                    root = candidate;
+                    ctx.gc();
+                    continue 'restart;
+                }
+            }
+            drop(candidate);
+            ctx.gc();
+        }
+        return root;
+    }
+}
+
+/// List the edits of the tree rooted at `root`, the ones removing the most first.
+fn edits(ctx: &mut Context, root: &NodeRc) -> Vec<Edit> {
+    let gc = GCLock::new(ctx);
+    let nodes = iter_preorder(&gc, root.node(&gc))
+        .map(|(node, _, _)| node)
+        .collect::<Vec<_>>();
+    let indices = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, &node)| (NodePtr::from_node(node), i))
+        .collect::<HashMap<_, _>>();
+    let index = &|node| indices[&NodePtr::from_node(node)];
+
+    // Removals, by decreasing size of chunks.
+    let lists = nodes
+        .iter()
+        .flat_map(|&node| {
+            node.fields()
+                .iter()
+                .filter_map(move |info| match node.field(info.field) {
+                    Some(FieldValue::NodeList(list)) if !list.is_empty() => {
+                        Some(list.iter().map(index).collect::<Vec<_>>())
+                    }
+                    _ => None,
+                })
+        })
+        .collect::<Vec<_>>();
+    let mut result = vec![];
+    let mut size = lists.iter().map(Vec::len).max().unwrap_or(0);
+    while size > 0 {
+        for list in &lists {
+            if size <= list.len() {
+                result.extend(list.chunks(size).map(|chunk| Edit::Remove(chunk.to_vec())));
+            }
+        }
+        size /= 2;
+    }
+
+    // Replacements by a child of the same category.
+    for (target, node) in nodes.iter().enumerate() {
+        let category = match category(node) {
+            Some(category) => category,
+            None => continue,
+        };
+        for info in node.fields() {
+            let children = match node.field(info.field) {
+                Some(FieldValue::Node(child)) => vec![child],
+                Some(FieldValue::NodeList(list)) => list.iter().collect(),
+                _ => continue,
+            };
+            for child in children {
+                if child.variant().is_subtype_of(category) {
+                    result.push(Edit::Replace {
+                        target,
+                        child: index(child),
+                    });
+                }
+            }
+        }
+    }
+    result
+}
+
+/// The category of nodes `node` can be replaced with.
+fn category(node: &Node) -> Option<NodeVariant> {
+    [NodeVariant::Expression, NodeVariant::Statement]
+        .iter()
+        .copied()
+        .find(|&category| node.variant().is_subtype_of(category))
+}
+
+/// Return the root of a copy of the tree rooted at `root` with `edit` applied.
+fn apply(ctx: &mut Context, root: &NodeRc, edit: &Edit) -> NodeRc {
+    let gc = GCLock::new(ctx);
+    let root = root.node(&gc);
+    let nodes = iter_preorder(&gc, root)
+        .map(|(node, _, _)| node)
+        .collect::<Vec<_>>();
+    let mut editor = Editor {
+        edit,
+        nodes: &nodes,
+        next: 0,
+    };
+    match VisitorMut::call(&mut editor, &gc, root, None) {
+        TransformResult::Changed(new_root) => NodeRc::from_node(&gc, new_root),
+        _ => NodeRc::from_node(&gc, root),
+    }
+}
+
+/// Applies an edit, tracking the preorder index of the visited nodes.
+struct Editor<'e, 'gc> {
+    edit: &'e Edit,
+    nodes: &'e [&'gc Node<'gc>],
+    /// Index of the next node in preorder.
+    next: usize,
+}
+
+impl<'gc> Editor<'_, 'gc> {
+    /// Skip the subtree rooted at the current node.
+    fn skip(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        self.next += iter_preorder(gc, node).count();
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Editor<'_, 'gc> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let index = self.next;
+        match self.edit {
+            Edit::Remove(removed) if removed.contains(&index) => {
+                self.skip(gc, node);
+                TransformResult::Removed
+            }
+            &Edit::Replace { target, child } if target == index => {
+                self.skip(gc, node);
+                TransformResult::Changed(self.nodes[child])
+            }
+            _ => {
+                self.next += 1;
+                node.visit_children_mut(gc, self)
+            }
+        }
+    }
+}
+
+/// A test running a shell command on the JS generated from each candidate,
+/// which is interesting when the command succeeds.
+pub struct CommandTest {
+    /// Shell command, to which the path of the candidate is appended.
+    command: String,
+
+    /// File the candidates are written to.
+    path: PathBuf,
+}
+
+impl CommandTest {
+    /// Run `command` on candidates written to `path`.
+    pub fn new(command: String, path: PathBuf) -> Self {
+        Self { command, path }
+    }
+
+    /// Write the JS generated from `root` to the file and run the command on it.
+    /// Returns whether the command succeeded.
+    pub fn run(&self, ctx: &mut Context, root: &NodeRc) -> io::Result<bool> {
+        let mut out = vec![];
+        gen_js::generate(&mut out, ctx, root, gen_js::Opt::new())?;
+        std::fs::write(&self.path, out)?;
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$0\"", self.command))
+            .arg(&self.path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        Ok(status.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce() {
+        let mut ctx = Context::new();
+        // f(1); if (g) { h(2, bug(3)); } k();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let call = |name| {
+                builders::expr_stmt(&gc, builders::call(&gc, builders::ident(&gc, name), []))
+            };
+            let bug = builders::call(
+                &gc,
+                builders::ident(&gc, "bug"),
+                [builders::number(&gc, 3.0)],
+            );
+            let h = builders::call(
+                &gc,
+                builders::ident(&gc, "h"),
+                [builders::number(&gc, 2.0), bug],
+            );
+            let block = builder::BlockStatement::build_template(
+                &gc,
+                template::BlockStatement {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, [builders::expr_stmt(&gc, h)]),
+                },
+            );
+            let if_stmt = builder::IfStatement::build_template(
+                &gc,
+                template::IfStatement {
+                    metadata: Default::default(),
+                    test: builders::ident(&gc, "g"),
+                    consequent: block,
+                    alternate: None,
+                },
+            );
+            let program = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, [call("f"), if_stmt, call("k")]),
+                },
+            );
+            NodeRc::from_node(&gc, program)
+        };
+
+        // Interesting while there is a call to `bug`.
+        let mut test = |ctx: &mut Context, root: &NodeRc| {
+            let gc = GCLock::new(ctx);
+            iter_preorder(&gc, root.node(&gc)).any(|(node, _, _)| match node {
+                Node::CallExpression(CallExpression { callee, .. }) => {
+                    matches!(callee, Node::Identifier(id) if gc.str(id.name) == "bug")
+                }
+                _ => false,
+            })
+        };
+        let mut stats = ReduceStats::default();
+        let root = reduce(&mut ctx, root, &mut test, &mut stats);
+        assert!(stats.edits > 0);
+
+        // The argument is a list element, so it's removed too.
+        let gc = GCLock::new(&mut ctx);
+        let names = iter_preorder(&gc, root.node(&gc))
+            .map(|(node, _, _)| node.name())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "Program",
+                "ExpressionStatement",
+                "CallExpression",
+                "Identifier"
+            ]
+        );
+    }
+}
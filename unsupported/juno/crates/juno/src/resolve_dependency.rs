@@ -13,12 +13,98 @@ use std::path::PathBuf;
 use juno_support::source_manager::SourceId;
 use juno_support::source_manager::SourceManager;
 
+use crate::ast;
+use crate::ast::node_cast;
 use crate::ast::GCLock;
+use crate::ast::Node;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DependencyKind {
     Require,
     Import,
+    /// A call to `import()` with a string literal.
+    DynamicImport,
+}
+
+/// An attribute of an import, such as `type: "json"` in
+/// `import data from "./data.json" with { type: "json" }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportAttribute {
+    pub key: String,
+    pub value: String,
+}
+
+/// Return the attributes of the import declaration or `import()` call `node`.
+/// The attributes of `import()` are the `with` (or legacy `assert`) property of its options,
+/// and only the ones with a string literal value are returned.
+pub fn import_attributes<'gc>(lock: &'gc GCLock, node: &'gc Node<'gc>) -> Vec<ImportAttribute> {
+    let string = |node: &Node| match node {
+        Node::Identifier(ast::Identifier { name, .. }) => Some(lock.str(*name).to_string()),
+        Node::StringLiteral(ast::StringLiteral { value, .. }) => {
+            Some(String::from_utf16_lossy(lock.str_u16(*value)))
+        }
+        _ => None,
+    };
+    let attribute = |key: &'gc Node<'gc>, value: &'gc Node<'gc>| {
+        Some(ImportAttribute {
+            key: string(key)?,
+            value: match value {
+                Node::StringLiteral(_) => string(value)?,
+                _ => return None,
+            },
+        })
+    };
+    match node {
+        Node::ImportDeclaration(import) => import
+            .assertions
+            .iter()
+            .flat_map(|assertions| assertions.iter())
+            .filter_map(|attr| {
+                let attr = node_cast!(Node::ImportAttribute, attr);
+                attribute(attr.key, attr.value)
+            })
+            .collect(),
+        Node::ImportExpression(ast::ImportExpression {
+            attributes: Some(Node::ObjectExpression(options)),
+            ..
+        }) => {
+            let with = options.properties.iter().find_map(|prop| match prop {
+                Node::Property(ast::Property {
+                    key,
+                    value: value @ Node::ObjectExpression(_),
+                    computed: false,
+                    ..
+                }) if matches!(string(key).as_deref(), Some("with" | "assert")) => Some(*value),
+                _ => None,
+            });
+            match with {
+                Some(Node::ObjectExpression(with)) => with
+                    .properties
+                    .iter()
+                    .filter_map(|prop| match prop {
+                        Node::Property(ast::Property {
+                            key,
+                            value,
+                            computed: false,
+                            ..
+                        }) => attribute(key, value),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Return the value of the `type` attribute in `attributes`, which is the type of a non-JS
+/// module, e.g. `json` or `css`.
+pub fn module_type(attributes: &[ImportAttribute]) -> Option<&str> {
+    attributes
+        .iter()
+        .find(|attr| attr.key == "type")
+        .map(|attr| attr.value.as_str())
 }
 
 /// Function used for resolving dependencies.
@@ -32,6 +118,19 @@ pub trait DependencyResolver {
         path: &str,
         kind: DependencyKind,
     ) -> Option<SourceId>;
+
+    /// Resolve an import with `attributes`, e.g. to find a JSON or CSS module.
+    /// By default the attributes are ignored.
+    fn resolve_import(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        kind: DependencyKind,
+        _attributes: &[ImportAttribute],
+    ) -> Option<SourceId> {
+        self.resolve_dependency(lock, file, path, kind)
+    }
 }
 
 #[derive(Debug, Default)]
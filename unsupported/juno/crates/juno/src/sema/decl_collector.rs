@@ -112,8 +112,8 @@ impl<'gc> DeclCollector<'gc> {
 
     fn run_impl(&mut self, lock: &'gc GCLock, root: &'gc Node<'gc>) {
         match root {
-            Node::FunctionDeclaration(ast::FunctionDeclaration { body, .. })
-            | Node::FunctionExpression(ast::FunctionExpression { body, .. }) => {
+            Node::FunctionDeclaration(_) | Node::FunctionExpression(_) => {
+                let body = root.function_like_body();
                 self.new_scope();
                 // Visit the children of the body, since we don't want to associate a
                 // scope with it.
@@ -121,7 +121,8 @@ impl<'gc> DeclCollector<'gc> {
                 body.visit_children(lock, self);
                 self.close_scope(root);
             }
-            Node::ArrowFunctionExpression(ast::ArrowFunctionExpression { body, .. }) => {
+            Node::ArrowFunctionExpression(arrow) => {
+                let ast::ArrowFunctionExpression { body, .. } = &**arrow;
                 self.new_scope();
                 // If there is a BlockStatement, don't visit it, just visit its children.
                 if matches!(*body, Node::BlockStatement { .. }) {
@@ -167,8 +168,11 @@ impl<'gc> Visitor<'gc> for DeclCollector<'gc> {
                 }
             }
 
-            // Don't descend into function expressions.
-            Node::FunctionExpression { .. } | Node::ArrowFunctionExpression { .. } => {}
+            // Don't descend into function expressions and static blocks, which have their
+            // own `var` scope.
+            Node::FunctionExpression { .. }
+            | Node::ArrowFunctionExpression { .. }
+            | Node::StaticBlock { .. } => {}
 
             // Associate a scope with scoped statements.
             // SwitchStatement needs a scope because it doesn't have a BlockStatement child.
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Simulation of the hoisting of declarations to the top of a function or program,
+//! without running the full resolver.
+
+use std::collections::HashSet;
+
+use juno_support::atom_table::Atom;
+
+use crate::ast;
+use crate::ast::GCLock;
+use crate::ast::Node;
+use crate::ast::Path;
+use crate::ast::VariableDeclarationKind;
+use crate::ast::Visitor;
+
+/// How a declaration is hoisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoistedKind {
+    /// A `var`, initialized to `undefined` when the scope is entered.
+    Var,
+    /// A function declaration at the top level of the scope,
+    /// initialized to the function when the scope is entered.
+    Function,
+    /// A function declaration in a block, which also declares a `var` in the
+    /// scope (ES2022 B.3.3). The `var` is `undefined` until the declaration is evaluated.
+    AnnexBFunction,
+}
+
+/// A declaration hoisted to the top of a function or program.
+#[derive(Debug, Clone, Copy)]
+pub struct HoistedDecl<'gc> {
+    /// The declared `Identifier`.
+    pub ident: &'gc Node<'gc>,
+    /// The `VariableDeclaration` or `FunctionDeclaration` containing `ident`.
+    pub decl: &'gc Node<'gc>,
+    pub kind: HoistedKind,
+}
+
+/// Return the declarations hoisted to the top of `scope`, which is a function-like node,
+/// a `Program` or a `Module`, in source order.
+/// Redeclarations of the same name are all returned; when the scope is entered,
+/// each name is bound once, to the last top-level function declaration if there is one.
+/// Declarations in nested functions are not included.
+///
+/// If `annex_b` is true, function declarations in blocks are hoisted as in non-strict code:
+/// those which wouldn't conflict with a parameter or with an enclosing lexical declaration
+/// if they were replaced by a `var` are returned as [`HoistedKind::AnnexBFunction`].
+pub fn hoisted_declarations<'gc>(
+    lock: &'gc GCLock,
+    scope: &'gc Node<'gc>,
+    annex_b: bool,
+) -> Vec<HoistedDecl<'gc>> {
+    let mut hoister = Hoister {
+        annex_b,
+        params: HashSet::new(),
+        scopes: vec![],
+        result: vec![],
+    };
+    match scope {
+        Node::Program(ast::Program { body, .. }) | Node::Module(ast::Module { body, .. }) => {
+            hoister.visit_statements(lock, body.iter());
+        }
+        _ if scope.is_function_like() => {
+            for param in scope.function_like_params() {
+                for ident in ast::bound_names(param) {
+                    hoister.params.insert(ident_name(ident));
+                }
+            }
+            // The body of an arrow function may be an expression, without declarations.
+            if let Node::BlockStatement(ast::BlockStatement { body, .. }) =
+                scope.function_like_body()
+            {
+                hoister.visit_statements(lock, body.iter());
+            }
+        }
+        _ => panic!(
+            "Expected a function, Program or Module, found {:?}",
+            scope.variant()
+        ),
+    }
+    hoister.result
+}
+
+/// The names declared lexically in a block scope.
+#[derive(Default)]
+struct LexicalScope {
+    /// Names of `let`, `const` and `class` declarations.
+    lexical: HashSet<Atom>,
+    /// Names of function declarations.
+    functions: HashSet<Atom>,
+}
+
+struct Hoister<'gc> {
+    annex_b: bool,
+    /// Names of the parameters of the function.
+    params: HashSet<Atom>,
+    /// The enclosing scopes, starting with the top level of the function or program.
+    scopes: Vec<LexicalScope>,
+    result: Vec<HoistedDecl<'gc>>,
+}
+
+impl<'gc> Hoister<'gc> {
+    /// Visit a list of statements forming a block scope.
+    fn visit_statements<I>(&mut self, lock: &'gc GCLock, stmts: I)
+    where
+        I: IntoIterator<Item = &'gc Node<'gc>>,
+    {
+        let stmts = stmts.into_iter().collect::<Vec<_>>();
+        self.scopes.push(lexical_scope(stmts.iter().copied()));
+        for stmt in stmts {
+            stmt.visit(lock, self, None);
+        }
+        self.scopes.pop();
+    }
+
+    /// Whether the function declaration `name` in the innermost scope can be hoisted
+    /// by Annex B, i.e. replacing it with a `var` wouldn't be an early error.
+    fn can_hoist_from_block(&self, name: Atom) -> bool {
+        if self.params.contains(&name) {
+            return false;
+        }
+        let (innermost, enclosing) = self.scopes.split_last().unwrap();
+        if innermost.lexical.contains(&name) {
+            return false;
+        }
+        // Function declarations are lexical in blocks, but not at the top level.
+        let (top, blocks) = enclosing.split_first().unwrap();
+        !top.lexical.contains(&name)
+            && !blocks
+                .iter()
+                .any(|s| s.lexical.contains(&name) || s.functions.contains(&name))
+    }
+}
+
+impl<'gc> Visitor<'gc> for Hoister<'gc> {
+    fn call(&mut self, lock: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        match node {
+            Node::VariableDeclaration(ast::VariableDeclaration {
+                kind: VariableDeclarationKind::Var,
+                declarations,
+                ..
+            }) => {
+                for declarator in declarations.iter() {
+                    if let Node::VariableDeclarator(ast::VariableDeclarator { id, .. }) = declarator
+                    {
+                        for ident in ast::bound_names(id) {
+                            self.result.push(HoistedDecl {
+                                ident,
+                                decl: node,
+                                kind: HoistedKind::Var,
+                            });
+                        }
+                    }
+                }
+                node.visit_children(lock, self);
+            }
+            Node::FunctionDeclaration(func) => {
+                let Some(id) = func.id else {
+                    return;
+                };
+                if self.scopes.len() == 1 {
+                    self.result.push(HoistedDecl {
+                        ident: id,
+                        decl: node,
+                        kind: HoistedKind::Function,
+                    });
+                } else if self.annex_b && self.can_hoist_from_block(ident_name(id)) {
+                    self.result.push(HoistedDecl {
+                        ident: id,
+                        decl: node,
+                        kind: HoistedKind::AnnexBFunction,
+                    });
+                }
+            }
+            Node::BlockStatement(ast::BlockStatement { body, .. }) => {
+                self.visit_statements(lock, body.iter());
+            }
+            Node::SwitchStatement(ast::SwitchStatement {
+                discriminant,
+                cases,
+                ..
+            }) => {
+                discriminant.visit(lock, self, None);
+                let stmts = cases.iter().flat_map(|case| match case {
+                    Node::SwitchCase(ast::SwitchCase { consequent, .. }) => consequent.iter(),
+                    _ => unreachable!("Invalid switch case: {:?}", case.variant()),
+                });
+                self.visit_statements(lock, stmts);
+            }
+            // Loops whose head declares lexical bindings form a scope containing their body.
+            Node::ForStatement(_) | Node::ForInStatement(_) | Node::ForOfStatement(_)
+                if loop_head(node).and_then(lexical_declaration).is_some() =>
+            {
+                self.scopes.push(lexical_scope(loop_head(node).into_iter()));
+                node.visit_children(lock, self);
+                self.scopes.pop();
+            }
+            // Don't descend into nested functions.
+            _ if node.is_function_like() => {}
+            _ => node.visit_children(lock, self),
+        }
+    }
+}
+
+/// If `stmt` is a lexical declaration, possibly exported, return it.
+fn lexical_declaration<'gc>(stmt: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+    match stmt {
+        Node::VariableDeclaration(ast::VariableDeclaration { kind, .. }) => {
+            if *kind == VariableDeclarationKind::Var {
+                None
+            } else {
+                Some(stmt)
+            }
+        }
+        Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => Some(stmt),
+        Node::ExportNamedDeclaration(export) => export.declaration.and_then(lexical_declaration),
+        Node::ExportDefaultDeclaration(ast::ExportDefaultDeclaration { declaration, .. }) => {
+            lexical_declaration(declaration)
+        }
+        _ => None,
+    }
+}
+
+/// If `node` is a loop, return its head, which may declare bindings scoped to the loop.
+fn loop_head<'gc>(node: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+    match node {
+        Node::ForStatement(for_stmt) => for_stmt.init,
+        Node::ForInStatement(ast::ForInStatement { left, .. }) => Some(left),
+        Node::ForOfStatement(for_of) => Some(for_of.left),
+        _ => None,
+    }
+}
+
+/// Collect the names declared lexically by `stmts`.
+fn lexical_scope<'gc>(stmts: impl Iterator<Item = &'gc Node<'gc>>) -> LexicalScope {
+    let mut scope = LexicalScope::default();
+    for stmt in stmts {
+        match lexical_declaration(stmt) {
+            Some(Node::FunctionDeclaration(func)) => {
+                if let Some(id) = func.id {
+                    scope.functions.insert(ident_name(id));
+                }
+            }
+            Some(Node::ClassDeclaration(class)) => {
+                if let Some(id) = class.id {
+                    scope.lexical.insert(ident_name(id));
+                }
+            }
+            Some(Node::VariableDeclaration(ast::VariableDeclaration { declarations, .. })) => {
+                for declarator in declarations.iter() {
+                    if let Node::VariableDeclarator(ast::VariableDeclarator { id, .. }) = declarator
+                    {
+                        for ident in ast::bound_names(id) {
+                            scope.lexical.insert(ident_name(ident));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    scope
+}
+
+fn ident_name(ident: &Node) -> Atom {
+    match ident {
+        Node::Identifier(ast::Identifier { name, .. }) => *name,
+        _ => unreachable!("Expected Identifier, found {:?}", ident.variant()),
+    }
+}
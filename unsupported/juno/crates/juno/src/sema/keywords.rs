@@ -10,8 +10,11 @@ use juno_support::atom_table::AtomTable;
 
 pub(super) struct Keywords {
     pub ident_arguments: Atom,
+    pub ident_constructor: Atom,
     pub ident_eval: Atom,
+    pub ident_import: Atom,
     pub ident_let: Atom,
+    pub ident_meta: Atom,
     pub ident_new: Atom,
     pub ident_target: Atom,
     pub ident_require: Atom,
@@ -21,8 +24,11 @@ impl Keywords {
     pub fn new(tab: &AtomTable) -> Self {
         Keywords {
             ident_arguments: tab.atom("arguments"),
+            ident_constructor: tab.atom("constructor"),
             ident_eval: tab.atom("eval"),
+            ident_import: tab.atom("import"),
             ident_let: tab.atom("let"),
+            ident_meta: tab.atom("meta"),
             ident_new: tab.atom("new"),
             ident_target: tab.atom("target"),
             ident_require: tab.atom("require"),
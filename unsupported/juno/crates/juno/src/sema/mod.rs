@@ -13,11 +13,15 @@
 //! names disallowed in strict mode, etc).
 
 mod decl_collector;
+mod hoisting;
 mod keywords;
 mod known_globals;
 mod resolver;
 mod sem_context;
 
+pub use hoisting::hoisted_declarations;
+pub use hoisting::HoistedDecl;
+pub use hoisting::HoistedKind;
 pub use resolver::resolve_module;
 pub use resolver::resolve_program;
 pub use sem_context::*;
@@ -16,6 +16,8 @@ use juno_support::ScopedHashMap;
 use smallvec::SmallVec;
 
 use super::sem_context::*;
+use crate::analysis::duplicates;
+use crate::analysis::duplicates::DuplicateKind;
 use crate::ast;
 use crate::ast::builder;
 use crate::ast::node_cast;
@@ -28,12 +30,14 @@ use crate::ast::NodeField;
 use crate::ast::NodeList;
 use crate::ast::NodePtr;
 use crate::ast::NodeRc;
+use crate::ast::NodeString;
 use crate::ast::NodeVariant;
 use crate::ast::Path;
 use crate::ast::TemplateMetadata;
 use crate::ast::UnaryExpressionOperator;
 use crate::ast::VariableDeclarationKind;
 use crate::ast::Visitor;
+use crate::resolve_dependency::import_attributes;
 use crate::resolve_dependency::DependencyKind;
 use crate::resolve_dependency::DependencyResolver;
 use crate::sema::decl_collector::DeclCollector;
@@ -54,6 +58,8 @@ struct Label<'gc> {
     id: LabelId,
     /// The declaring node.
     label: &'gc Identifier<'gc>,
+    /// The LabeledStatement declaring the label, exited by `break`.
+    labeled_statement: &'gc Node<'gc>,
     /// Statement targeted by the label.
     /// Either a loop or LabeledStatement.
     target_statement: &'gc Node<'gc>,
@@ -85,13 +91,18 @@ struct Resolver<'gc, 'mode> {
     sem: SemContext,
     func_stack: Vec<FunctionContext<'gc>>,
     current_scope: Option<LexicalScopeId>,
-    /// The most nested active loop statement.
-    current_loop: Option<LabelId>,
-    /// The most nested active loop or switch statement.
-    current_loop_or_switch: Option<LabelId>,
+    /// The most nested active loop statement in the current function.
+    current_loop: Option<&'gc Node<'gc>>,
+    /// The most nested active loop or switch statement in the current function.
+    current_loop_or_switch: Option<&'gc Node<'gc>>,
     binding_table: ScopedHashMap<Atom, Binding<'gc>>,
+    /// The private names declared by the enclosing class bodies, with one scope per class.
+    private_name_table: ScopedHashMap<Atom, PrivateNameId>,
     /// True for a short time we are validating a formal parameter list.
     validating_formal_params: bool,
+    /// True where `this` is undefined: at the top level of a module, outside of non-arrow
+    /// functions and class fields.
+    this_undefined: bool,
     /// The depth of the global scope in ['binding_table'].
     /// It is None until we have actually entered the global scope.
     global_binding_scope_depth: Option<usize>,
@@ -154,7 +165,9 @@ impl<'gc, 'mode> Resolver<'gc, 'mode> {
             current_loop: None,
             current_loop_or_switch: None,
             binding_table: Default::default(),
+            private_name_table: Default::default(),
             validating_formal_params: false,
+            this_undefined: false,
             global_binding_scope_depth: None,
             file_id,
             mode,
@@ -203,6 +216,127 @@ impl<'gc> Resolver<'gc, '_> {
         self.decl_scope(decl).parent_function == self.function_context().func_id
     }
 
+    /// Report an error if the `await` expression or `for await` loop `node` is neither
+    /// in an async function nor at the top level of a module.
+    fn check_await(&self, lock: &'gc GCLock, node: &'gc Node<'gc>) {
+        let allowed = match self.function_context().node {
+            Node::Module(_) => true,
+            Node::FunctionExpression(func) => func.is_async,
+            Node::ArrowFunctionExpression(arrow) => arrow.is_async,
+            Node::FunctionDeclaration(func) => func.is_async,
+            _ => false,
+        };
+        if !allowed {
+            lock.sm().error(
+                *node.range(),
+                "'await' is only valid in async functions and at the top level of modules",
+            );
+        }
+    }
+
+    /// Return `true` if the nearest enclosing non-arrow function is a class static block.
+    fn in_static_block(&self) -> bool {
+        matches!(
+            self.func_stack
+                .iter()
+                .rev()
+                .find(|fc| !matches!(fc.node, Node::ArrowFunctionExpression(_))),
+            Some(fc) if matches!(fc.node, Node::StaticBlock(_))
+        )
+    }
+
+    /// Declare the private names of the members of `class_body` in the current private name
+    /// scope.
+    fn declare_private_names(&mut self, lock: &'gc GCLock, class_body: &'gc Node<'gc>) {
+        let body = &node_cast!(Node::ClassBody, class_body).body;
+        let mut declared = HashMap::<Atom, PrivateNameId>::new();
+        for member in body.iter() {
+            let (key, kind, is_static) = match member {
+                Node::ClassPrivateProperty(prop) => {
+                    (prop.key, PrivateNameKind::Field, prop.is_static)
+                }
+                Node::ClassAccessorProperty(ast::ClassAccessorProperty {
+                    key: key @ Node::PrivateName(_),
+                    is_static,
+                    ..
+                }) => (*key, PrivateNameKind::GetterSetter, *is_static),
+                Node::MethodDefinition(ast::MethodDefinition {
+                    key: key @ Node::PrivateName(_),
+                    kind,
+                    is_static,
+                    ..
+                }) => {
+                    let kind = match kind {
+                        ast::MethodDefinitionKind::Get => PrivateNameKind::Getter,
+                        ast::MethodDefinitionKind::Set => PrivateNameKind::Setter,
+                        _ => PrivateNameKind::Method,
+                    };
+                    (*key, kind, *is_static)
+                }
+                _ => continue,
+            };
+            // The parser doesn't wrap the keys of private fields in a PrivateName.
+            let ident = match key {
+                Node::PrivateName(ast::PrivateName {
+                    id: Node::Identifier(ident),
+                    ..
+                })
+                | Node::Identifier(ident) => ident,
+                _ => continue,
+            };
+            if ident.name == self.kw.ident_constructor {
+                lock.sm()
+                    .error(*key.range(), "'#constructor' is not a valid private name");
+            }
+            // Duplicates have already been reported, so the only name declared twice which
+            // needs attention is a getter and setter pair.
+            let id = match declared.get(&ident.name) {
+                Some(&id) => {
+                    let prev = self.sem.private_name_mut(id);
+                    if matches!(
+                        (prev.kind, kind),
+                        (PrivateNameKind::Getter, PrivateNameKind::Setter)
+                            | (PrivateNameKind::Setter, PrivateNameKind::Getter)
+                    ) {
+                        prev.kind = PrivateNameKind::GetterSetter;
+                    }
+                    id
+                }
+                None => {
+                    let id = self.sem.new_private_name(
+                        ident.name,
+                        kind,
+                        is_static,
+                        NodeRc::from_node(lock, class_body),
+                    );
+                    declared.insert(ident.name, id);
+                    self.private_name_table.insert(ident.name, id);
+                    id
+                }
+            };
+            self.sem
+                .set_private_name_ref(NodeRc::from_node(lock, key), id);
+        }
+    }
+
+    /// Resolve the reference to a private name `node` to the innermost class body declaring
+    /// it, or report an error.
+    fn resolve_private_name(&mut self, lock: &'gc GCLock, node: &'gc Node<'gc>) {
+        let ident = node_cast!(Node::Identifier, node_cast!(Node::PrivateName, node).id);
+        match self.private_name_table.get(&ident.name) {
+            Some(&id) => self
+                .sem
+                .set_private_name_ref(NodeRc::from_node(lock, node), id),
+            None => lock.sm().error(
+                *node.range(),
+                format!(
+                    "private name '#{}' is not declared in an enclosing class",
+                    lock.str(ident.name)
+                ),
+            ),
+        }
+    }
+
     /// Return `true` if the `callee` is the `require` function.
     fn is_require(&mut self, lock: &'gc GCLock, node: &'gc ast::CallExpression<'gc>) -> bool {
         debug_assert!(
@@ -221,6 +355,36 @@ impl<'gc> Resolver<'gc, '_> {
         false
     }
 
+    /// Resolve the import declaration or `import()` call `node` from `source` and record it
+    /// as a dependency.
+    fn resolve_import(
+        &mut self,
+        lock: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        source: NodeString,
+        kind: DependencyKind,
+    ) {
+        if let ResolverMode::Module {
+            dependency_resolver,
+        } = self.mode
+        {
+            let target = String::from_utf16_lossy(lock.str_u16(source));
+            let attributes = import_attributes(lock, node);
+            match dependency_resolver.resolve_import(lock, self.file_id, &target, kind, &attributes)
+            {
+                Some(file_id) => {
+                    self.sem.add_require(NodeRc::from_node(lock, node), file_id);
+                }
+                None => {
+                    lock.sm().warning(
+                        *node.range(),
+                        format!("Unable to resolve import for {}", target),
+                    );
+                }
+            }
+        }
+    }
+
     /// Create a new function, push a new function context, execute the callback
     /// and pop the function context.
     fn in_new_function<R, F: FnOnce(&mut Self) -> R>(
@@ -256,9 +420,20 @@ impl<'gc> Resolver<'gc, '_> {
         // Save and reset validating_formal_params.
         let save_vfp = self.validating_formal_params;
         self.validating_formal_params = false;
+        // `break` and `continue` can't jump out of the function.
+        let save_loop = self.current_loop.take();
+        let save_loop_or_switch = self.current_loop_or_switch.take();
+        // Arrow functions don't bind `this`.
+        let save_this_undefined = self.this_undefined;
+        if !matches!(root, Node::ArrowFunctionExpression(_)) {
+            self.this_undefined = matches!(root, Node::Module(_));
+        }
 
         let res = f(self);
 
+        self.this_undefined = save_this_undefined;
+        self.current_loop_or_switch = save_loop_or_switch;
+        self.current_loop = save_loop;
         self.validating_formal_params = save_vfp;
         self.func_stack.pop();
 
@@ -306,6 +481,14 @@ impl<'gc> Resolver<'gc, '_> {
         res
     }
 
+    /// Record that the `break` or `continue` statement `node` jumps to `target`.
+    fn set_jump_target(&mut self, lock: &'gc GCLock, node: &'gc Node<'gc>, target: &'gc Node<'gc>) {
+        self.sem.set_jump_target(
+            NodeRc::from_node(lock, node),
+            NodeRc::from_node(lock, target),
+        );
+    }
+
     /// Allocate a new label in this scope, updating internal state, and call `f`.
     /// Restore the original label and return the result of `f`.
     /// Reports an error if the label is already defined.
@@ -326,7 +509,7 @@ impl<'gc> Resolver<'gc, '_> {
         match statement {
             Node::LabeledStatement(_) => {}
             Node::SwitchStatement(_) => {
-                self.current_loop_or_switch = Some(new_label);
+                self.current_loop_or_switch = Some(statement);
             }
             Node::ForStatement(_)
             | Node::ForInStatement(_)
@@ -334,8 +517,8 @@ impl<'gc> Resolver<'gc, '_> {
             | Node::WhileStatement(_)
             | Node::DoWhileStatement(_) => {
                 // Must be a loop.
-                self.current_loop = Some(new_label);
-                self.current_loop_or_switch = Some(new_label);
+                self.current_loop = Some(statement);
+                self.current_loop_or_switch = Some(statement);
             }
             _ => {
                 unreachable!("Invalid label statement: {:?}", statement.variant());
@@ -377,6 +560,7 @@ impl<'gc> Resolver<'gc, '_> {
                     entry.insert(Label {
                         id: new_label,
                         label: node_cast!(Node::Identifier, identifier),
+                        labeled_statement: statement,
                         target_statement,
                     });
                     inserted = Some(key);
@@ -469,13 +653,8 @@ impl<'gc> Resolver<'gc, '_> {
                 // Create the module scope as a function.
                 pself.in_new_function(lock, node, |pself| {
                     pself.in_new_scope(lock, node, |pself| {
-                        // Search for "use strict".
-                        if find_use_strict(lock, &node_cast!(Node::Module, node).body).is_some() {
-                            pself
-                                .sem
-                                .function_mut(pself.function_context().func_id)
-                                .strict = true;
-                        }
+                        // Module code is always strict.
+                        *pself.function_strict_mode_mut() = true;
 
                         pself.process_collected_declarations(lock, node);
                         node.visit_children(lock, pself);
@@ -633,7 +812,7 @@ impl<'gc> Resolver<'gc, '_> {
                 }
 
                 if let Node::BlockStatement(_) = body {
-                    if !pself.function_strict_mode() {
+                    if !pself.function_strict_mode() && lock.ctx().annex_b() {
                         pself.promote_scoped_func_decls(lock, node);
                     }
                 }
@@ -667,6 +846,7 @@ impl<'gc> Resolver<'gc, '_> {
                         );
                     } else if !(matches!(node, Node::ForInStatement(_))
                         && !pself.function_strict_mode()
+                        && lock.ctx().annex_b()
                         && vd.kind == ast::VariableDeclarationKind::Var)
                     {
                         lock.sm().error(
@@ -738,7 +918,13 @@ impl<'gc> Resolver<'gc, '_> {
                 computed: false,
                 property: child,
                 ..
-            }) if child.ptr_eq(node) => return,
+            })
+                if child.ptr_eq(node) =>
+            {
+                return
+            }
+            // class { #identifier }
+            Node::ClassPrivateProperty(prop) if prop.key.ptr_eq(node) => return,
             Node::MetaProperty(_)
             | Node::BreakStatement(_)
             | Node::ContinueStatement(_)
@@ -812,6 +998,13 @@ impl<'gc> Resolver<'gc, '_> {
 
         // Is this "arguments" in a function?
         if ident.name == self.kw.ident_arguments && !self.function_context().func_id.is_global() {
+            if self.in_static_block() {
+                lock.sm().error(
+                    ident.metadata.range,
+                    "'arguments' is not allowed in class static blocks",
+                );
+                return;
+            }
             if decl.is_none() || !self.decl_in_cur_function(decl.unwrap()) {
                 let args_decl = self
                     .sem
@@ -1081,21 +1274,44 @@ impl<'gc> Resolver<'gc, '_> {
             }
             Node::ObjectPattern(ast::ObjectPattern { properties, .. }) => {
                 for property in properties.iter() {
-                    self.validate_assignment_target(lock, property)
+                    match property {
+                        // ES2022 13.15.5.1: the target of a rest property can't be a pattern.
+                        Node::RestElement(ast::RestElement { argument, .. }) => self
+                            .validate_simple_assignment_target(
+                                lock,
+                                argument,
+                                "invalid rest property target",
+                            ),
+                        _ => self.validate_assignment_target(lock, property),
+                    }
                 }
             }
             Node::RestElement(ast::RestElement { argument, .. }) => {
                 self.validate_assignment_target(lock, argument)
             }
-            _ => {
-                if !self.is_lvalue(node) {
-                    lock.sm()
-                        .error(*node.range(), "invalid assignment left-hand side");
-                }
-            }
+            _ => self.validate_simple_assignment_target(
+                lock,
+                node,
+                "invalid assignment left-hand side",
+            ),
         };
     }
 
+    /// Ensure that `node` is a simple assignment target: an identifier other than `eval`
+    /// and `arguments` in strict mode, or a member expression. Report `msg` otherwise.
+    fn validate_simple_assignment_target(&self, lock: &GCLock, node: &Node, msg: &str) {
+        if self.is_lvalue(node) {
+            return;
+        }
+        match node {
+            Node::Identifier(id) => lock.sm().error(
+                *node.range(),
+                format!("cannot assign to '{}' in strict mode", lock.str(id.name)),
+            ),
+            _ => lock.sm().error(*node.range(), msg),
+        }
+    }
+
     /// Extract the list of declared identifiers in a declaration node and return
     /// the declaration kind of the node. Function declarations are always returned
     /// as DeclKind::ScopedFunction, so they can be distinguished.
@@ -1188,45 +1404,13 @@ impl<'gc> Resolver<'gc, '_> {
         }
         let node = node_opt.unwrap();
 
-        match node {
-            Node::Identifier(_) => {
-                idents.push(node);
-            }
-            Node::Empty(_) => {}
-            Node::AssignmentPattern(n) => {
-                Self::extract_declared_idents_from_id(lock, Some(n.left), idents);
-            }
-            Node::ArrayPattern(n) => {
-                for elem in n.elements {
-                    Self::extract_declared_idents_from_id(lock, Some(elem), idents);
-                }
-            }
-            Node::RestElement(n) => {
-                Self::extract_declared_idents_from_id(lock, Some(n.argument), idents);
-            }
-            Node::ObjectPattern(n) => {
-                for prop in n.properties {
-                    match prop {
-                        Node::Property(nn) => {
-                            Self::extract_declared_idents_from_id(lock, Some(nn.value), idents);
-                        }
-                        Node::RestElement(nn) => {
-                            Self::extract_declared_idents_from_id(lock, Some(nn.argument), idents);
-                        }
-                        bn => lock.sm().error(
-                            *bn.range(),
-                            format!("unexpected ObjectPattern property {}", bn.name()),
-                        ),
-                    }
-                }
-            }
-            _ => {
-                lock.sm().error(
-                    *node.range(),
-                    format!("invalid destructuring target {}", node.name()),
-                );
-            }
-        }
+        ast::visit_bound_names(node, &mut |name| match name {
+            Ok(ast::BoundName { ident, .. }) => idents.push(ident),
+            Err(bad) => lock.sm().error(
+                *bad.range(),
+                format!("invalid destructuring target {}", bad.name()),
+            ),
+        });
     }
 
     /// Mark the current scope and every one of its ancestor scopes as users of local `eval()`.
@@ -1280,6 +1464,9 @@ impl<'gc> Resolver<'gc, '_> {
 
 impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
     fn call(&mut self, lock: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        if lock.ctx().is_cancelled() {
+            return;
+        }
         match node {
             Node::Program(_) => {
                 panic!("visited unexpected {}", node.name())
@@ -1300,17 +1487,25 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
             Node::Identifier(ident) => self.visit_identifier(lock, ident, node, path.unwrap()),
 
             Node::AssignmentExpression(asgn) => {
-                self.validate_assignment_target(lock, asgn.left);
+                // Only `=` can destructure.
+                if asgn.operator == ast::AssignmentExpressionOperator::Assign {
+                    self.validate_assignment_target(lock, asgn.left);
+                } else {
+                    self.validate_simple_assignment_target(
+                        lock,
+                        asgn.left,
+                        "invalid compound assignment left-hand side",
+                    );
+                }
                 node.visit_children(lock, self);
             }
 
             Node::UpdateExpression(update) => {
-                if !self.is_lvalue(update.argument) {
-                    lock.sm().error(
-                        *update.argument.range(),
-                        "invalid operand in update operation",
-                    );
-                }
+                self.validate_simple_assignment_target(
+                    lock,
+                    update.argument,
+                    "invalid operand in update operation",
+                );
                 node.visit_children(lock, self);
             }
 
@@ -1337,10 +1532,15 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                 });
             }
 
-            Node::ForInStatement(ast::ForInStatement { left, .. })
-            | Node::ForOfStatement(ast::ForOfStatement { left, .. }) => {
+            Node::ForInStatement(ast::ForInStatement { left, .. }) => {
                 self.visit_for_in_of(lock, node, left)
             }
+            Node::ForOfStatement(for_of) => {
+                if for_of.is_await {
+                    self.check_await(lock, node);
+                }
+                self.visit_for_in_of(lock, node, for_of.left)
+            }
 
             Node::ForStatement(_) => {
                 self.with_new_label(lock, None, node, |pself| {
@@ -1372,17 +1572,25 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                 label: Some(label), ..
             }) => {
                 let name = node_cast!(Node::Identifier, label).name;
-                if self.function_context().label_table.get(&name).is_none() {
-                    lock.sm().error(
-                        *label.range(),
-                        format!("label '{}' is not defined", lock.str(name)),
-                    );
+                match self.function_context().label_table.get(&name) {
+                    Some(label) => {
+                        let target = label.labeled_statement;
+                        self.set_jump_target(lock, node, target);
+                    }
+                    None => {
+                        lock.sm().error(
+                            *label.range(),
+                            format!("label '{}' is not defined", lock.str(name)),
+                        );
+                    }
                 }
             }
             Node::BreakStatement(ast::BreakStatement { label: None, .. }) => {
-                if self.current_loop_or_switch.is_none() {
-                    lock.sm()
-                        .error(*node.range(), "'break' not within a loop or switch");
+                match self.current_loop_or_switch {
+                    Some(target) => self.set_jump_target(lock, node, target),
+                    None => lock
+                        .sm()
+                        .error(*node.range(), "'break' not within a loop or switch"),
                 }
             }
 
@@ -1401,6 +1609,9 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                                     lock.str(name)
                                 ),
                             );
+                        } else {
+                            let target = label.target_statement;
+                            self.set_jump_target(lock, node, target);
                         }
                     }
                     None => {
@@ -1412,9 +1623,11 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                 }
             }
             Node::ContinueStatement(ast::ContinueStatement { label: None, .. }) => {
-                if self.current_loop.is_none() {
-                    lock.sm()
-                        .error(*node.range(), "'continue' not within a loop or switch");
+                match self.current_loop {
+                    Some(target) => self.set_jump_target(lock, node, target),
+                    None => lock
+                        .sm()
+                        .error(*node.range(), "'continue' not within a loop or switch"),
                 }
             }
 
@@ -1446,6 +1659,41 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                 });
             }
 
+            Node::ThisExpression(_) => {
+                if self.this_undefined {
+                    lock.sm().warning(
+                        *node.range(),
+                        "'this' is undefined at the top level of a module",
+                    );
+                }
+            }
+
+            Node::ClassProperty(_)
+            | Node::ClassPrivateProperty(_)
+            | Node::ClassAccessorProperty(_) => {
+                // `this` is the instance or the class in field initializers.
+                let save_this_undefined = self.this_undefined;
+                self.this_undefined = false;
+                node.visit_children(lock, self);
+                self.this_undefined = save_this_undefined;
+            }
+
+            Node::StaticBlock(_) => {
+                // Static blocks are evaluated like methods of the class, with their own `this`
+                // and `var` scope.
+                self.in_new_function(lock, node, |pself| {
+                    pself.in_new_scope(lock, node, |pself| {
+                        pself.process_collected_declarations(lock, node);
+                        node.visit_children(lock, pself);
+                    })
+                });
+            }
+
+            Node::AwaitExpression(_) => {
+                self.check_await(lock, node);
+                node.visit_children(lock, self);
+            }
+
             Node::MetaProperty(ast::MetaProperty {
                 meta: Node::Identifier(meta),
                 property: Node::Identifier(prop),
@@ -1463,35 +1711,80 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                     lock.sm()
                         .error(*node.range(), "'new.target' outside of a function");
                 }
+                // Validate "import.meta"
+                if meta.name == self.kw.ident_import
+                    && prop.name == self.kw.ident_meta
+                    && !matches!(self.func_stack[0].node, Node::Module(_))
+                {
+                    lock.sm()
+                        .error(*node.range(), "'import.meta' is only valid in a module");
+                }
+            }
+
+            Node::ImportDeclaration(import) if matches!(import.source, Node::StringLiteral(_)) => {
+                node.visit_children(lock, self);
+                let value = node_cast!(Node::StringLiteral, import.source).value;
+                self.resolve_import(lock, node, value, DependencyKind::Import);
             }
 
-            Node::ImportDeclaration(ast::ImportDeclaration {
+            Node::ImportExpression(ast::ImportExpression {
                 source: Node::StringLiteral(ast::StringLiteral { value, .. }),
                 ..
             }) => {
                 node.visit_children(lock, self);
-                if let ResolverMode::Module {
-                    dependency_resolver,
-                } = self.mode
-                {
-                    // Resolve `import`.
-                    let target = String::from_utf16_lossy(lock.str_u16(*value));
-                    match dependency_resolver.resolve_dependency(
-                        lock,
-                        self.file_id,
-                        &target,
-                        DependencyKind::Import,
-                    ) {
-                        Some(file_id) => {
-                            self.sem.add_require(NodeRc::from_node(lock, node), file_id);
-                        }
-                        None => {
-                            lock.sm().warning(
-                                *node.range(),
-                                format!("Unable to resolve import for {}", target),
-                            );
-                        }
+                self.resolve_import(lock, node, *value, DependencyKind::DynamicImport);
+            }
+
+            Node::ObjectExpression(_) | Node::ClassBody(_) => {
+                for d in duplicates::find_member_duplicates(lock, node) {
+                    // Without Annex B, `__proto__` is an ordinary property name.
+                    if d.kind == DuplicateKind::Proto && !lock.ctx().annex_b() {
+                        continue;
                     }
+                    if d.kind.is_early_error() {
+                        lock.sm().error(d.range, d.message());
+                        lock.sm().note(d.first, "first definition is here");
+                    }
+                }
+                if let Node::ClassBody(_) = node {
+                    // The heritage of the class has already been visited, since it can't
+                    // refer to the private names of the class.
+                    self.private_name_table.push_scope();
+                    self.declare_private_names(lock, node);
+                    node.visit_children(lock, self);
+                    self.private_name_table.pop_scope();
+                } else {
+                    node.visit_children(lock, self);
+                }
+            }
+
+            Node::UnaryExpression(ast::UnaryExpression {
+                operator: UnaryExpressionOperator::Delete,
+                argument:
+                    Node::MemberExpression(ast::MemberExpression {
+                        property: Node::PrivateName(_),
+                        ..
+                    })
+                    | Node::OptionalMemberExpression(ast::OptionalMemberExpression {
+                        property: Node::PrivateName(_),
+                        ..
+                    }),
+                ..
+            }) => {
+                lock.sm()
+                    .error(*node.range(), "private fields can't be deleted");
+                node.visit_children(lock, self);
+            }
+
+            Node::PrivateName(_) => {
+                // Declarations have been processed with their class body, and the
+                // identifier doesn't refer to a variable.
+                if self
+                    .sem
+                    .private_name_ref(&NodeRc::from_node(lock, node))
+                    .is_none()
+                {
+                    self.resolve_private_name(lock, node);
                 }
             }
 
@@ -1503,6 +1796,12 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
             }
 
             Node::CallExpression(call @ ast::CallExpression { arguments, .. }) => {
+                if matches!(call.callee, Node::Super(_)) && self.in_static_block() {
+                    lock.sm().error(
+                        *node.range(),
+                        "'super()' is not allowed in class static blocks",
+                    );
+                }
                 // Check for a direct call to local `eval()`.
                 if let Node::Identifier(identifier) = call.callee {
                     if identifier.name == self.kw.ident_eval {
@@ -1557,13 +1856,11 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                         lock.sm()
                             .error(*node.range(), "'yield' not in a generator function");
                     }
-                    Node::FunctionExpression(ast::FunctionExpression {
-                        generator: false, ..
-                    })
-                    | Node::FunctionDeclaration(ast::FunctionDeclaration {
-                        generator: false,
-                        ..
-                    }) => {
+                    Node::FunctionExpression(func) if !func.generator => {
+                        lock.sm()
+                            .error(*node.range(), "'yield' not in a generator function");
+                    }
+                    Node::FunctionDeclaration(func) if !func.generator => {
                         lock.sm()
                             .error(*node.range(), "'yield' not in a generator function");
                     }
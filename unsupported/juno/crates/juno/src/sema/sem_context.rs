@@ -8,6 +8,8 @@
 use std::collections::HashMap;
 
 use juno_ast::node_cast;
+use juno_ast::AnnotationValue;
+use juno_ast::Annotator;
 use juno_ast::GCLock;
 use juno_ast::Node;
 use juno_support::atom_table::Atom;
@@ -15,11 +17,14 @@ use juno_support::declare_opaque_id;
 use juno_support::source_manager::SourceId;
 
 use crate::ast::NodeRc;
+use crate::eval::ConstEvaluator;
+use crate::eval::JsValue;
 
 declare_opaque_id!(DeclId);
 declare_opaque_id!(LexicalScopeId);
 declare_opaque_id!(FunctionInfoId);
 declare_opaque_id!(LabelId);
+declare_opaque_id!(PrivateNameId);
 
 impl LexicalScopeId {
     pub const GLOBAL_SCOPE_ID: LexicalScopeId = unsafe { LexicalScopeId::new_unchecked(0) };
@@ -56,6 +61,7 @@ macro_rules! declare_opaque_list {
 declare_opaque_list!(DeclList, Decl, DeclId);
 declare_opaque_list!(LexicalScopeList, LexicalScope, LexicalScopeId);
 declare_opaque_list!(FunctionInfoList, FunctionInfo, FunctionInfoId);
+declare_opaque_list!(PrivateNameList, PrivateNameDecl, PrivateNameId);
 
 #[derive(Debug, PartialOrd, PartialEq, Eq, Copy, Clone)]
 pub enum DeclKind {
@@ -259,6 +265,28 @@ impl FunctionInfo {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PrivateNameKind {
+    Field,
+    Method,
+    Getter,
+    Setter,
+    /// A getter and a setter sharing the name, or an `accessor` field.
+    GetterSetter,
+}
+
+/// A private name declared by a class body. Private names live in their own namespace,
+/// separate from variables: `#x` never refers to a variable `x`, and is only visible
+/// inside the body of the class declaring it, including nested classes.
+pub struct PrivateNameDecl {
+    /// The name, without the leading `#`.
+    pub name: Atom,
+    pub kind: PrivateNameKind,
+    pub is_static: bool,
+    /// The ClassBody declaring the name.
+    pub class_body: NodeRc,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum Resolution {
     /// Unable to resolve due to presence of `eval` or `with`.
@@ -272,6 +300,7 @@ pub struct SemContext {
     pub decls: DeclList,
     pub scopes: LexicalScopeList,
     pub funcs: FunctionInfoList,
+    pub private_names: PrivateNameList,
     /// Declarations associated with Identifier AST nodes or marked [`Resolution::Unresolvable`].
     ident_decls: HashMap<NodeRc, Resolution>,
     /// Lexical scopes associated with AST nodes. Usually BlockStatement, but
@@ -279,6 +308,11 @@ pub struct SemContext {
     node_scopes: HashMap<NodeRc, LexicalScopeId>,
     /// Resolved `require` calls.
     requires: HashMap<NodeRc, SourceId>,
+    /// Statements targeted by `break` and `continue` statements.
+    jump_targets: HashMap<NodeRc, NodeRc>,
+    /// Private names associated with the keys declaring them and the PrivateName nodes
+    /// referring to them.
+    private_name_refs: HashMap<NodeRc, PrivateNameId>,
 }
 
 impl SemContext {
@@ -417,6 +451,57 @@ impl SemContext {
         self.requires.insert(call, file_id);
     }
 
+    pub fn all_jump_targets(&self) -> &HashMap<NodeRc, NodeRc> {
+        &self.jump_targets
+    }
+    pub(super) fn set_jump_target(&mut self, node: NodeRc, target: NodeRc) {
+        self.jump_targets.insert(node, target);
+    }
+    /// Return the statement targeted by the `break` or `continue` statement `node`.
+    /// A `break` targets the loop, switch or LabeledStatement it exits, and a `continue`
+    /// targets the loop it continues.
+    /// Returns `None` if `node` isn't a valid `break` or `continue` statement.
+    pub fn jump_target(&self, node: &NodeRc) -> Option<&NodeRc> {
+        self.jump_targets.get(node)
+    }
+
+    pub(super) fn new_private_name(
+        &mut self,
+        name: Atom,
+        kind: PrivateNameKind,
+        is_static: bool,
+        class_body: NodeRc,
+    ) -> PrivateNameId {
+        self.private_names.0.push(PrivateNameDecl {
+            name,
+            kind,
+            is_static,
+            class_body,
+        });
+        PrivateNameId::new(self.private_names.0.len() - 1)
+    }
+    pub fn all_private_names(&self) -> &[PrivateNameDecl] {
+        self.private_names.as_slice()
+    }
+    pub fn private_name(&self, id: PrivateNameId) -> &PrivateNameDecl {
+        self.private_names.get(id)
+    }
+    pub(super) fn private_name_mut(&mut self, id: PrivateNameId) -> &mut PrivateNameDecl {
+        self.private_names.get_mut(id)
+    }
+
+    pub fn all_private_name_refs(&self) -> &HashMap<NodeRc, PrivateNameId> {
+        &self.private_name_refs
+    }
+    pub(super) fn set_private_name_ref(&mut self, node: NodeRc, id: PrivateNameId) {
+        self.private_name_refs.insert(node, id);
+    }
+    /// Return the private name declared by the class member key `node` or referred to by
+    /// the PrivateName `node`.
+    pub fn private_name_ref(&self, node: &NodeRc) -> Option<PrivateNameId> {
+        self.private_name_refs.get(node).copied()
+    }
+
     /// Return the id of the global scope in the context. This may seem
     /// redundant, since the ID is constant. The idea here that the global scope
     /// may not have been created yet.
@@ -501,6 +586,9 @@ impl SemContext {
         if !self.all_requires().is_empty() {
             println!("{:7} require resolutions", self.all_requires().len());
         }
+        if !self.all_private_names().is_empty() {
+            println!("{:7} private names", self.all_private_names().len());
+        }
         println!();
 
         let mut children = HashMap::<FunctionInfoId, Vec<FunctionInfoId>>::new();
@@ -542,3 +630,75 @@ impl SemContext {
         }
     }
 }
+
+/// Annotates the nodes dumped with [`juno_ast::dump_json_annotated`] with the results of
+/// semantic analysis:
+/// - `declId`: the declaration an identifier resolves to, or `null` if it is unresolvable.
+/// - `scopeId`: the lexical scope associated with the node.
+/// - `strict`: whether the code in that scope is strict mode.
+/// - `constant`: the value of an expression which can be evaluated by `constants`,
+///   if it is a primitive other than `undefined`. Literals aren't annotated.
+pub struct SemAnnotator<'a> {
+    pub sem: &'a SemContext,
+
+    /// Evaluator for the `constant` annotations, which are omitted if `None`.
+    pub constants: Option<&'a ConstEvaluator>,
+}
+
+impl Annotator for SemAnnotator<'_> {
+    fn annotate<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        out: &mut Vec<(&'static str, AnnotationValue)>,
+    ) {
+        let rc = NodeRc::from_node(gc, node);
+        match self.sem.ident_decl(&rc) {
+            Some(Resolution::Decl(id)) => {
+                out.push(("declId", AnnotationValue::Number(id.as_usize() as f64)))
+            }
+            Some(Resolution::Unresolvable) => out.push(("declId", AnnotationValue::Null)),
+            None => {}
+        }
+        if let Some(scope) = self.sem.node_scope(rc) {
+            let strict = self
+                .sem
+                .function(self.sem.scope(scope).parent_function)
+                .strict;
+            out.push(("scopeId", AnnotationValue::Number(scope.as_usize() as f64)));
+            out.push(("strict", AnnotationValue::Bool(strict)));
+        }
+        if let Some(constants) = self.constants {
+            if !matches!(
+                node,
+                Node::NullLiteral(_)
+                    | Node::BooleanLiteral(_)
+                    | Node::NumericLiteral(_)
+                    | Node::StringLiteral(_)
+            ) {
+                // Only globals have the values of `undefined`, `NaN` and the defines.
+                let is_global = |ident: &'gc Node<'gc>| match self
+                    .sem
+                    .ident_decl(&NodeRc::from_node(gc, ident))
+                {
+                    Some(Resolution::Decl(id)) => {
+                        self.sem.decl(id).kind == DeclKind::UndeclaredGlobalProperty
+                    }
+                    Some(Resolution::Unresolvable) | None => true,
+                };
+                let value = match constants.eval_in(gc, node, &is_global) {
+                    Some(JsValue::Null) => Some(AnnotationValue::Null),
+                    Some(JsValue::Boolean(b)) => Some(AnnotationValue::Bool(b)),
+                    Some(JsValue::Number(n)) => Some(AnnotationValue::Number(n)),
+                    Some(JsValue::String(s)) => {
+                        Some(AnnotationValue::String(String::from_utf16_lossy(&s)))
+                    }
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    out.push(("constant", value));
+                }
+            }
+        }
+    }
+}
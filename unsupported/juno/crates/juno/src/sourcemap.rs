@@ -5,45 +5,288 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceManager;
 use sourcemap::SourceMap;
 use sourcemap::SourceMapBuilder;
 
-/// Return a merged version of the `input` and `output` source maps.
-/// Currently this only supports a single input source map.
-/// The function works by iterating through every `Token` in `output` and attempting
-/// to look it up in `input` (use the `lookup_token` method to get the closest token near it).
-/// If `input` does not contain the token, use the `output` token.
-/// Currently the only metadata retained from the `input` map is `names` and `sources`.
-/// TODO: Add support for other sourcemap metadata fields such as `metadata` and `sourcesContent`.
-pub fn merge_sourcemaps(input: &SourceMap, output: &SourceMap) -> SourceMap {
-    let mut merged = SourceMapBuilder::new(output.get_file());
-
-    for name in input.names() {
-        merged.add_name(name);
-    }
-
-    let mut output_src_id = 0;
-    for src in input.sources() {
-        output_src_id = merged.add_source(src) + 1;
-    }
-    for output_token in output.tokens() {
-        let (merged_token, src_id) =
-            match input.lookup_token(output_token.get_src_line(), output_token.get_src_col()) {
-                Some(input_token) => (input_token, input_token.get_src_id()),
-                None => (output_token, output_src_id),
-            };
-
-        merged.add_raw(
-            output_token.get_dst_line(),
-            output_token.get_dst_col(),
-            merged_token.get_src_line(),
-            merged_token.get_src_col(),
+/// Original location of a generated position, found by [`lookup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalLocation<'a> {
+    /// Name of the original source file.
+    pub source: &'a str,
+
+    /// 1-based line number in the original source.
+    pub line: u32,
+
+    /// 1-based column number in the original source.
+    pub col: u32,
+
+    /// Original name of the identifier at the location, if the map records it.
+    pub name: Option<&'a str>,
+}
+
+/// Map the position at 1-based `line` and `col` of the generated file described by `map`
+/// back to its original location.
+/// The closest mapping at or before `col` on the same line is used.
+/// Returns `None` if the position isn't mapped to any source.
+pub fn lookup(map: &SourceMap, line: u32, col: u32) -> Option<OriginalLocation<'_>> {
+    // Source maps are 0-based.
+    let (line, col) = (line.checked_sub(1)?, col.checked_sub(1)?);
+    let token = map.lookup_token(line, col)?;
+    if token.get_dst_line() != line {
+        return None;
+    }
+    Some(OriginalLocation {
+        source: token.get_source()?,
+        line: token.get_src_line() + 1,
+        col: token.get_src_col() + 1,
+        name: token.get_name(),
+    })
+}
+
+/// Compose the `input` and `output` source maps, where `output` maps the generated code
+/// to its sources and `input` maps those sources to their originals.
+/// The result maps the generated code directly to the original sources.
+/// Every source of `output` is looked up in `input`; use [`compose_inputs`] when
+/// the sources have separate input maps.
+pub fn compose(input: &SourceMap, output: &SourceMap) -> SourceMap {
+    compose_inputs(output, |_| Some(input))
+}
+
+/// Compose `output` with the input source maps registered in `sm`.
+/// `output` must use the indices of the `SourceId`s as source indices,
+/// which is the case for the maps produced by `gen_js`.
+/// Returns `None` if no input source maps are registered.
+pub fn compose_registered(sm: &SourceManager, output: &SourceMap) -> Option<SourceMap> {
+    if !sm.has_input_source_maps() {
+        return None;
+    }
+    Some(compose_inputs(output, |src_id| {
+        sm.input_source_map(SourceId(src_id))
+    }))
+}
+
+/// Compose `output` with the source map returned by `input_for` for each of its sources.
+/// Tokens of sources without an input map are copied unchanged.
+/// Tokens of sources with an input map which don't map to anything in it
+/// are emitted without a source.
+/// The names and source contents of the input maps are retained.
+pub fn compose_inputs<'m, F>(output: &SourceMap, input_for: F) -> SourceMap
+where
+    F: Fn(u32) -> Option<&'m SourceMap>,
+{
+    let mut composed = SourceMapBuilder::new(output.get_file());
+
+    for token in output.tokens() {
+        let input = if token.has_source() {
+            input_for(token.get_src_id())
+        } else {
+            None
+        };
+        let (map, original) = match input {
+            None => (output, Some(token)),
+            // The input map must have a token on the same line, at or before the column.
+            Some(input) => (
+                input,
+                input
+                    .lookup_token(token.get_src_line(), token.get_src_col())
+                    .filter(|t| t.get_dst_line() == token.get_src_line() && t.has_source()),
+            ),
+        };
+        let original = match original {
+            Some(original) if original.has_source() => original,
+            _ => {
+                composed.add_raw(token.get_dst_line(), token.get_dst_col(), 0, 0, None, None);
+                continue;
+            }
+        };
+
+        let src_id = composed.add_source(original.get_source().unwrap_or_default());
+        if !composed.has_source_contents(src_id) {
+            composed.set_source_contents(src_id, map.get_source_contents(original.get_src_id()));
+        }
+        // Prefer the original name, since it's closer to what the user wrote.
+        let name_id = original
+            .get_name()
+            .or_else(|| token.get_name())
+            .map(|name| composed.add_name(name));
+        composed.add_raw(
+            token.get_dst_line(),
+            token.get_dst_col(),
+            original.get_src_line(),
+            original.get_src_col(),
             Some(src_id),
-            match merged_token.get_name() {
-                Some(_) => Some(merged_token.get_name_id()),
-                _ => None,
-            },
+            name_id,
+        );
+    }
+    composed.into_sourcemap()
+}
+
+/// Embed the contents of the sources of `map` which are registered in `sm`
+/// and don't already have contents.
+pub fn add_sources_content(sm: &SourceManager, map: &mut SourceMap) {
+    for idx in 0..map.get_source_count() {
+        if map.get_source_contents(idx).is_some() {
+            continue;
+        }
+        let id = match map.get_source(idx).and_then(|name| sm.lookup_name(name)) {
+            Some(id) => id,
+            None => continue,
+        };
+        let buf = sm.source_buffer(id).as_bytes();
+        // Exclude the null terminator.
+        let contents = String::from_utf8_lossy(&buf[..buf.len() - 1]).into_owned();
+        map.set_source_contents(idx, Some(&contents));
+    }
+}
+
+/// Return a `data:` URL containing `map`, for embedding it in the generated JS.
+pub fn data_url(map: &SourceMap) -> String {
+    let mut json = vec![];
+    map.to_writer(&mut json)
+        .expect("Writing a source map to memory failed");
+    format!(
+        "data:application/json;charset=utf-8;base64,{}",
+        base64::encode(json)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use juno_support::NullTerminatedBuf;
+
+    use super::*;
+
+    fn map(json: &str) -> SourceMap {
+        SourceMap::from_slice(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let sm = map(r#"{
+            "version": 3,
+            "sources": ["a.js"],
+            "names": ["foo"],
+            "mappings": "AAAAA,IAAI;;AAEA"
+        }"#);
+        assert_eq!(
+            lookup(&sm, 1, 1),
+            Some(OriginalLocation {
+                source: "a.js",
+                line: 1,
+                col: 1,
+                name: Some("foo"),
+            })
+        );
+        // Columns between mappings use the preceding one.
+        assert_eq!(
+            lookup(&sm, 1, 7),
+            Some(OriginalLocation {
+                source: "a.js",
+                line: 1,
+                col: 5,
+                name: None,
+            })
         );
+        assert_eq!(lookup(&sm, 3, 1).map(|loc| loc.line), Some(3));
+        // The second line has no mappings.
+        assert_eq!(lookup(&sm, 2, 1), None);
+        assert_eq!(lookup(&sm, 0, 1), None);
+    }
+
+    #[test]
+    fn test_compose() {
+        // gen.js <- mid.js <- orig.ts
+        let output = map(r#"{
+            "version": 3,
+            "sources": ["mid.js"],
+            "names": ["foo"],
+            "mappings": "AAAAA,IAAI;AACA"
+        }"#);
+        let input = map(r#"{
+            "version": 3,
+            "sources": ["orig.ts"],
+            "sourcesContent": ["// original"],
+            "names": ["bar"],
+            "mappings": "AAEAA,IAAI"
+        }"#);
+        let composed = compose(&input, &output);
+        assert_eq!(composed.get_token_count(), 3);
+
+        // (0, 0) -> mid.js (0, 0) -> orig.ts (2, 0), with the original name.
+        let t = composed.lookup_token(0, 0).unwrap();
+        assert_eq!(t.get_source(), Some("orig.ts"));
+        assert_eq!(t.get_src(), (2, 0));
+        assert_eq!(t.get_name(), Some("bar"));
+        assert_eq!(
+            composed.get_source_contents(t.get_src_id()),
+            Some("// original")
+        );
+
+        // (0, 4) -> mid.js (0, 4) -> orig.ts (2, 4).
+        let t = composed.lookup_token(0, 4).unwrap();
+        assert_eq!(t.get_src(), (2, 4));
+
+        // (1, 0) -> mid.js (1, 4), which isn't mapped by the input.
+        let t = composed.lookup_token(1, 0).unwrap();
+        assert_eq!(t.get_dst(), (1, 0));
+        assert!(!t.has_source());
+    }
+
+    #[test]
+    fn test_compose_registered() {
+        let mut sm = SourceManager::new();
+        let a = sm.add_source("a.js", NullTerminatedBuf::from_str_copy(""));
+        let b = sm.add_source("b.js", NullTerminatedBuf::from_str_copy(""));
+        let output = map(r#"{
+            "version": 3,
+            "sources": ["a.js", "b.js"],
+            "names": [],
+            "mappings": "AAAA;ACAA"
+        }"#);
+        assert!(compose_registered(&sm, &output).is_none());
+
+        sm.set_input_source_map(
+            b,
+            map(r#"{
+                "version": 3,
+                "sources": ["b.ts"],
+                "names": [],
+                "mappings": ";AAAC"
+            }"#),
+        );
+        assert!(sm.input_source_map(a).is_none());
+        let composed = compose_registered(&sm, &output).unwrap();
+        // a.js has no input map and is unchanged.
+        let t = composed.lookup_token(0, 0).unwrap();
+        assert_eq!(t.get_source(), Some("a.js"));
+        assert_eq!(t.get_src(), (0, 0));
+        // b.js (0, 0) has no mapping in the input, since the input starts on line 1.
+        let t = composed.lookup_token(1, 0).unwrap();
+        assert!(!t.has_source());
+    }
+
+    #[test]
+    fn test_sources_content() {
+        let mut sm = SourceManager::new();
+        sm.add_source("a.js", NullTerminatedBuf::from_str_copy("a;"));
+        let mut output = map(r#"{
+            "version": 3,
+            "sources": ["a.js", "b.js"],
+            "names": [],
+            "mappings": "AAAA;ACAA"
+        }"#);
+        add_sources_content(&sm, &mut output);
+        assert_eq!(output.get_source_contents(0), Some("a;"));
+        assert_eq!(output.get_source_contents(1), None);
+
+        let url = data_url(&output);
+        let prefix = "data:application/json;charset=utf-8;base64,";
+        assert!(url.starts_with(prefix));
+        let decoded =
+            map(&String::from_utf8(base64::decode(&url[prefix.len()..]).unwrap()).unwrap());
+        assert_eq!(decoded.get_source_contents(0), Some("a;"));
+        assert_eq!(decoded.get_token_count(), 2);
     }
-    merged.into_sourcemap()
 }
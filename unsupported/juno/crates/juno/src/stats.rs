@@ -0,0 +1,367 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Statistics of a corpus of sources, for a quick characterization of it, e.g. to pick
+//! representative benchmarks or to find the files which dominate the parsing time.
+//!
+//! Every source is parsed into its own [`ast::Context`], then the context is collected with
+//! the AST live, and the report lists, for every file and in total, the size of the AST and
+//! of the string table, and the time both steps took. It is printed as a table, or as JSON
+//! by [`Stats::write_json`].
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use juno_support::json::JSONEmitter;
+use juno_support::json::Pretty;
+use juno_support::HeapSize;
+
+use crate::ast;
+use crate::ast::GCLock;
+use crate::ast::NodeVariant;
+use crate::hparser;
+
+/// Statistics of a single source, or the totals of several.
+#[derive(Debug, Clone, Default)]
+pub struct FileStats {
+    /// Name of the source, empty for totals.
+    pub name: String,
+
+    /// Size of the source in bytes.
+    pub bytes: usize,
+
+    /// Number of nodes in the AST.
+    pub nodes: usize,
+
+    /// Number of nodes of each kind in the AST.
+    pub kinds: HashMap<NodeVariant, usize>,
+
+    /// Number of functions, including arrow functions and methods.
+    pub functions: usize,
+
+    /// Number of nodes on the longest path from the root to a leaf.
+    /// The maximum of the files in totals.
+    pub max_depth: usize,
+
+    /// Number of identifiers in the string table.
+    pub identifiers: usize,
+
+    /// Number of string literals in the string table.
+    pub strings: usize,
+
+    /// Bytes of memory used by the string table.
+    pub string_table_bytes: usize,
+
+    /// Time to parse the source and convert it to an AST.
+    pub parse_time: Duration,
+
+    /// Time to collect the context after parsing, with the AST live.
+    pub gc_time: Duration,
+}
+
+impl FileStats {
+    /// Number of nodes of kind `variant` in the AST.
+    pub fn kind_count(&self, variant: NodeVariant) -> usize {
+        self.kinds.get(&variant).copied().unwrap_or(0)
+    }
+
+    /// Add the statistics of `other` to these totals.
+    fn accumulate(&mut self, other: &FileStats) {
+        self.bytes += other.bytes;
+        self.nodes += other.nodes;
+        for (&variant, &count) in &other.kinds {
+            *self.kinds.entry(variant).or_insert(0) += count;
+        }
+        self.functions += other.functions;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.identifiers += other.identifiers;
+        self.strings += other.strings;
+        self.string_table_bytes += other.string_table_bytes;
+        self.parse_time += other.parse_time;
+        self.gc_time += other.gc_time;
+    }
+
+    /// Emit the statistics as a JSON object.
+    fn emit_json<W: Write>(&self, emitter: &mut JSONEmitter<W>) {
+        emitter.open_dict();
+        if !self.name.is_empty() {
+            emitter.emit_key("file");
+            emitter.emit_string(&self.name);
+        }
+        for (key, value) in [
+            ("bytes", self.bytes),
+            ("nodes", self.nodes),
+            ("functions", self.functions),
+            ("maxDepth", self.max_depth),
+            ("identifiers", self.identifiers),
+            ("strings", self.strings),
+            ("stringTableBytes", self.string_table_bytes),
+        ] {
+            emitter.emit_key(key);
+            emitter.emit_number(value as f64);
+        }
+        emitter.emit_key("parseMs");
+        emitter.emit_number(millis(self.parse_time));
+        emitter.emit_key("gcMs");
+        emitter.emit_number(millis(self.gc_time));
+        emitter.emit_key("kinds");
+        emitter.open_dict();
+        for &kind in NodeVariant::KINDS {
+            let count = self.kind_count(kind);
+            if count != 0 {
+                emitter.emit_key(&format!("{:?}", kind));
+                emitter.emit_number(count as f64);
+            }
+        }
+        emitter.close_dict();
+        emitter.close_dict();
+    }
+}
+
+/// Duration in fractional milliseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Statistics of a corpus of sources.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Statistics of every source which was parsed successfully, in order.
+    pub files: Vec<FileStats>,
+
+    /// Path and error of every source which couldn't be parsed.
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+impl Stats {
+    /// Parse `source`, named `name`, with `flags`, and record its statistics.
+    /// Returns the parser error if it couldn't be parsed.
+    pub fn add_source(
+        &mut self,
+        flags: hparser::ParserFlags,
+        name: &str,
+        source: &str,
+    ) -> Result<(), hparser::ParseError> {
+        let mut ctx = ast::Context::new();
+        let start = Instant::now();
+        let ast = hparser::parse_with_flags(flags, source, &mut ctx)?;
+        let parse_time = start.elapsed();
+        let start = Instant::now();
+        ctx.gc();
+        let gc_time = start.elapsed();
+
+        let atoms = ctx.atom_table();
+        let mut file = FileStats {
+            name: name.to_string(),
+            bytes: source.len(),
+            identifiers: atoms.num_atoms(),
+            strings: atoms.num_atoms_u16(),
+            string_table_bytes: atoms.heap_size(),
+            parse_time,
+            gc_time,
+            ..Default::default()
+        };
+        let gc = GCLock::new(&mut ctx);
+        // Iterative, so that deeply nested sources can't overflow the stack.
+        for (node, depth, _) in ast::iter_preorder(&gc, ast.node(&gc)) {
+            file.nodes += 1;
+            *file.kinds.entry(node.variant()).or_insert(0) += 1;
+            if node.is_function_like() {
+                file.functions += 1;
+            }
+            file.max_depth = file.max_depth.max(depth + 1);
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// Add every source under `path`, which may be a single file or a directory.
+    /// Files with a `.mjs` extension are parsed as modules, other `.js`, `.cjs` and `.jsx`
+    /// files with `flags`, and a single file with any extension is parsed with `flags`.
+    /// Sources which fail to parse are recorded in `failures`.
+    pub fn add_path(&mut self, flags: hparser::ParserFlags, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                if entry.is_dir() || is_source(&entry) {
+                    self.add_path(flags, &entry)?;
+                }
+            }
+            return Ok(());
+        }
+        let flags = match path.extension().and_then(OsStr::to_str) {
+            Some("mjs") => hparser::ParserFlags {
+                source_type: hparser::SourceType::Module,
+                ..flags
+            },
+            _ => flags,
+        };
+        let source = fs::read_to_string(path)?;
+        if let Err(e) = self.add_source(flags, &path.display().to_string(), &source) {
+            self.failures.push((path.to_path_buf(), e.to_string()));
+        }
+        Ok(())
+    }
+
+    /// The totals of every source.
+    pub fn total(&self) -> FileStats {
+        let mut total = FileStats::default();
+        for file in &self.files {
+            total.accumulate(file);
+        }
+        total
+    }
+
+    /// Write the statistics as a JSON document:
+    /// ```json
+    /// {
+    ///   "files": [{"file": "a.js", "bytes": 120, "nodes": 42, ..., "kinds": {"Program": 1}}],
+    ///   "total": {"bytes": 120, "nodes": 42, ...},
+    ///   "failures": [{"file": "b.js", "message": "..."}]
+    /// }
+    /// ```
+    pub fn write_json<W: Write>(&self, out: W, pretty: Pretty) -> io::Result<()> {
+        let mut emitter = JSONEmitter::new(out, pretty);
+        emitter.open_dict();
+        emitter.emit_key("files");
+        emitter.open_array();
+        for file in &self.files {
+            file.emit_json(&mut emitter);
+        }
+        emitter.close_array();
+        emitter.emit_key("total");
+        self.total().emit_json(&mut emitter);
+        emitter.emit_key("failures");
+        emitter.open_array();
+        for (path, message) in &self.failures {
+            emitter.open_dict();
+            emitter.emit_key("file");
+            emitter.emit_string(&path.display().to_string());
+            emitter.emit_key("message");
+            emitter.emit_string(message);
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.close_dict();
+        emitter.end()
+    }
+}
+
+/// Whether the file at `path` in a directory is a source.
+fn is_source(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str),
+        Some("js" | "mjs" | "cjs" | "jsx")
+    )
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, msg) in &self.failures {
+            writeln!(f, "FAIL {}: {}", path.display(), msg)?;
+        }
+
+        writeln!(
+            f,
+            "{:<40} {:>10} {:>9} {:>9} {:>6} {:>8} {:>8} {:>10} {:>9} {:>9}",
+            "File",
+            "Bytes",
+            "Nodes",
+            "Functions",
+            "Depth",
+            "Idents",
+            "Strings",
+            "StrBytes",
+            "Parse ms",
+            "GC ms"
+        )?;
+        let total = FileStats {
+            name: "Total".to_string(),
+            ..self.total()
+        };
+        for file in self.files.iter().chain(std::iter::once(&total)) {
+            writeln!(
+                f,
+                "{:<40} {:>10} {:>9} {:>9} {:>6} {:>8} {:>8} {:>10} {:>9.3} {:>9.3}",
+                file.name,
+                file.bytes,
+                file.nodes,
+                file.functions,
+                file.max_depth,
+                file.identifiers,
+                file.strings,
+                file.string_table_bytes,
+                millis(file.parse_time),
+                millis(file.gc_time)
+            )?;
+        }
+
+        let mut kinds = NodeVariant::KINDS
+            .iter()
+            .copied()
+            .filter(|&kind| total.kind_count(kind) != 0)
+            .collect::<Vec<_>>();
+        kinds.sort_by_key(|&kind| std::cmp::Reverse(total.kind_count(kind)));
+        writeln!(f, "Node kinds:")?;
+        for kind in kinds {
+            writeln!(
+                f,
+                "  {:<40} {:>8}",
+                format!("{:?}", kind),
+                total.kind_count(kind)
+            )?;
+        }
+        writeln!(
+            f,
+            "{} files parsed, {} failed",
+            self.files.len(),
+            self.failures.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total() {
+        let mut stats = Stats::default();
+        for (nodes, depth) in [(10, 4), (5, 7)] {
+            let mut file = FileStats {
+                nodes,
+                max_depth: depth,
+                parse_time: Duration::from_millis(2),
+                ..Default::default()
+            };
+            file.kinds.insert(NodeVariant::Program, 1);
+            stats.files.push(file);
+        }
+        let total = stats.total();
+        assert_eq!(total.nodes, 15);
+        assert_eq!(total.max_depth, 7);
+        assert_eq!(total.kind_count(NodeVariant::Program), 2);
+        assert_eq!(total.kind_count(NodeVariant::Module), 0);
+        assert_eq!(total.parse_time, Duration::from_millis(4));
+
+        let mut out = vec![];
+        stats.write_json(&mut out, Pretty::No).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.contains(r#""total":{"bytes":0,"nodes":15,"#));
+    }
+}
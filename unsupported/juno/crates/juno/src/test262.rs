@@ -0,0 +1,560 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Runner for the test262 conformance suite.
+//!
+//! Every test file starts with a YAML frontmatter block delimited by `/*---` and `---*/`.
+//! The runner extracts the metadata from it, prepends the requested harness includes and
+//! drives the selected pipeline stages over the result, collecting pass/fail statistics
+//! for each feature listed in the metadata.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::ast;
+use crate::hparser;
+
+/// The phase in which a negative test is expected to fail.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    Parse,
+    Resolution,
+    Runtime,
+}
+
+/// Expected failure of a negative test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negative {
+    pub phase: Phase,
+    /// Name of the expected error constructor, e.g. `SyntaxError`.
+    pub error_type: String,
+}
+
+/// Frontmatter of a single test.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub description: String,
+    /// Harness files to prepend to the test, in addition to the default ones.
+    pub includes: Vec<String>,
+    pub flags: Vec<String>,
+    pub features: Vec<String>,
+    pub negative: Option<Negative>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MetadataError {
+    #[error("missing test262 frontmatter")]
+    Missing,
+    #[error("frontmatter line {line}: {message}")]
+    Malformed { line: usize, message: String },
+}
+
+impl Metadata {
+    /// Extract and parse the frontmatter from the test `source`.
+    /// Only the subset of YAML used by test262 is supported.
+    pub fn parse(source: &str) -> Result<Metadata, MetadataError> {
+        let start = source.find("/*---").ok_or(MetadataError::Missing)? + "/*---".len();
+        let len = source[start..]
+            .find("---*/")
+            .ok_or(MetadataError::Missing)?;
+        let yaml = &source[start..start + len];
+
+        let mut md = Metadata::default();
+        let lines: Vec<&str> = yaml.lines().collect();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            i += 1;
+            if line.trim().is_empty() || line.starts_with(char::is_whitespace) {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| MetadataError::Malformed {
+                    line: i,
+                    message: format!("expected 'key: value', found '{}'", line),
+                })?;
+            let value = value.trim();
+
+            // Collect the indented lines belonging to this key.
+            let block_start = i;
+            while i < lines.len()
+                && (lines[i].trim().is_empty() || lines[i].starts_with(char::is_whitespace))
+            {
+                i += 1;
+            }
+            let block = &lines[block_start..i];
+
+            match key.trim() {
+                "description" => md.description = parse_scalar(value, block),
+                "includes" => md.includes = parse_list(value, block),
+                "flags" => md.flags = parse_list(value, block),
+                "features" => md.features = parse_list(value, block),
+                "negative" => md.negative = Some(parse_negative(block_start, block)?),
+                _ => {}
+            }
+        }
+        Ok(md)
+    }
+
+    /// Return true if `flag` is present in the `flags` list.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+/// Parse a scalar which is either inline or a `|`/`>` block.
+fn parse_scalar(value: &str, block: &[&str]) -> String {
+    match value {
+        "|" | ">" | "|-" | ">-" => {
+            let sep = if value.starts_with('|') { "\n" } else { " " };
+            block
+                .iter()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(sep)
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Parse a list which is either `[a, b]` inline or a sequence of `- a` lines.
+fn parse_list(value: &str, block: &[&str]) -> Vec<String> {
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        inner
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect()
+    } else {
+        block
+            .iter()
+            .filter_map(|l| l.trim().strip_prefix('-'))
+            .map(|s| s.trim().to_string())
+            .collect()
+    }
+}
+
+fn parse_negative(first_line: usize, block: &[&str]) -> Result<Negative, MetadataError> {
+    let mut phase = None;
+    let mut error_type = None;
+    for (idx, line) in block.iter().enumerate() {
+        let malformed = |message: String| MetadataError::Malformed {
+            line: first_line + idx + 1,
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| malformed(format!("expected 'key: value', found '{}'", line)))?;
+        match key.trim() {
+            "phase" => {
+                phase = Some(match value.trim() {
+                    "parse" | "early" => Phase::Parse,
+                    "resolution" => Phase::Resolution,
+                    "runtime" => Phase::Runtime,
+                    p => return Err(malformed(format!("unknown phase '{}'", p))),
+                })
+            }
+            "type" => error_type = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    match (phase, error_type) {
+        (Some(phase), Some(error_type)) => Ok(Negative { phase, error_type }),
+        _ => Err(MetadataError::Malformed {
+            line: first_line,
+            message: "negative requires 'phase' and 'type'".into(),
+        }),
+    }
+}
+
+/// The last pipeline stage to run on every test.
+/// Stages are ordered, so running a stage implies running all the previous ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Only parse the test.
+    Parse,
+    /// Parse the test and validate the resulting AST.
+    Validate,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunnerOptions {
+    /// Directory containing the harness include files (`test262/harness`).
+    /// When `None`, includes are not prepended.
+    pub harness_dir: Option<PathBuf>,
+    pub stage: Stage,
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        RunnerOptions {
+            harness_dir: None,
+            stage: Stage::Validate,
+        }
+    }
+}
+
+/// The outcome of running a single test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub path: PathBuf,
+    pub features: Vec<String>,
+    pub outcome: Outcome,
+}
+
+/// Pass/fail counters.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl Stats {
+    fn add(&mut self, outcome: &Outcome) {
+        match outcome {
+            Outcome::Pass => self.passed += 1,
+            Outcome::Fail(_) => self.failed += 1,
+            Outcome::Skip(_) => self.skipped += 1,
+        }
+    }
+}
+
+/// Aggregated results of a test262 run.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub total: Stats,
+    /// Stats for every feature flag, tests without features are not included.
+    pub features: BTreeMap<String, Stats>,
+    /// Path and reason of every failing test.
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+impl Summary {
+    pub fn add(&mut self, result: &TestResult) {
+        self.total.add(&result.outcome);
+        for feature in &result.features {
+            self.features
+                .entry(feature.clone())
+                .or_default()
+                .add(&result.outcome);
+        }
+        if let Outcome::Fail(msg) = &result.outcome {
+            self.failures.push((result.path.clone(), msg.clone()));
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, msg) in &self.failures {
+            writeln!(f, "FAIL {}: {}", path.display(), msg)?;
+        }
+        if !self.features.is_empty() {
+            writeln!(
+                f,
+                "{:<40} {:>8} {:>8} {:>8}",
+                "Feature", "Pass", "Fail", "Skip"
+            )?;
+            for (name, stats) in &self.features {
+                writeln!(
+                    f,
+                    "{:<40} {:>8} {:>8} {:>8}",
+                    name, stats.passed, stats.failed, stats.skipped
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.total.passed, self.total.failed, self.total.skipped
+        )
+    }
+}
+
+/// Runs test262 tests, caching the harness includes between tests.
+pub struct Runner {
+    opt: RunnerOptions,
+    includes: HashMap<String, Rc<String>>,
+}
+
+impl Runner {
+    pub fn new(opt: RunnerOptions) -> Runner {
+        Runner {
+            opt,
+            includes: HashMap::new(),
+        }
+    }
+
+    /// Run every test under `path`, which may be a single file or a directory.
+    /// Fixture files (`*_FIXTURE.js`) are not tests and are ignored.
+    pub fn run_path(&mut self, path: &Path, summary: &mut Summary) -> io::Result<()> {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                self.run_path(&entry, summary)?;
+            }
+        } else if path.extension() == Some(OsStr::new("js"))
+            && !path.to_string_lossy().ends_with("_FIXTURE.js")
+        {
+            summary.add(&self.run_file(path)?);
+        }
+        Ok(())
+    }
+
+    /// Run the test in the file at `path`.
+    pub fn run_file(&mut self, path: &Path) -> io::Result<TestResult> {
+        let source = fs::read_to_string(path)?;
+        let mut result = self.run_source(&source);
+        result.path = path.to_path_buf();
+        Ok(result)
+    }
+
+    /// Run the test in `source`.
+    /// The returned result has an empty path.
+    pub fn run_source(&mut self, source: &str) -> TestResult {
+        let md = match Metadata::parse(source) {
+            Ok(md) => md,
+            Err(e) => {
+                return TestResult {
+                    path: PathBuf::new(),
+                    features: Vec::new(),
+                    outcome: Outcome::Fail(e.to_string()),
+                };
+            }
+        };
+        let outcome = self.run_with_metadata(&md, source);
+        TestResult {
+            path: PathBuf::new(),
+            features: md.features,
+            outcome,
+        }
+    }
+
+    fn run_with_metadata(&mut self, md: &Metadata, source: &str) -> Outcome {
+        if let Some(Negative {
+            phase: Phase::Resolution,
+            ..
+        }) = md.negative
+        {
+            return Outcome::Skip("module resolution is not supported".into());
+        }
+
+        let full_source = match self.with_includes(md, source) {
+            Ok(s) => s,
+            Err(e) => return Outcome::Fail(e),
+        };
+
+        // Tests are run in both modes unless they specify otherwise.
+        let mut modes = Vec::with_capacity(2);
+        if !md.has_flag("onlyStrict") {
+            modes.push(false);
+        }
+        if !md.has_flag("noStrict") && !md.has_flag("raw") && !md.has_flag("module") {
+            modes.push(true);
+        }
+
+        for strict_mode in modes {
+            let outcome = self.run_once(md, &full_source, strict_mode);
+            if outcome != Outcome::Pass {
+                return outcome;
+            }
+        }
+        Outcome::Pass
+    }
+
+    /// Run the stages on a single source in the specified mode.
+    fn run_once(&self, md: &Metadata, source: &str, strict_mode: bool) -> Outcome {
+        let expect_early_error = matches!(
+            md.negative,
+            Some(Negative {
+                phase: Phase::Parse,
+                ..
+            })
+        );
+        let mode = if strict_mode { "strict" } else { "sloppy" };
+
+        let source_type = if md.has_flag("module") {
+            hparser::SourceType::Module
+        } else {
+            hparser::SourceType::Script
+        };
+        match self.check(source, strict_mode, source_type) {
+            Ok(()) if expect_early_error => Outcome::Fail(format!(
+                "{}: expected {} but no error was reported",
+                mode,
+                md.negative.as_ref().unwrap().error_type,
+            )),
+            Err(_) if expect_early_error => Outcome::Pass,
+            Ok(()) => Outcome::Pass,
+            Err(msg) => Outcome::Fail(format!("{}: {}", mode, msg)),
+        }
+    }
+
+    /// Run the configured stages and return the first error message.
+    fn check(
+        &self,
+        source: &str,
+        strict_mode: bool,
+        source_type: hparser::SourceType,
+    ) -> Result<(), String> {
+        let mut ctx = ast::Context::new();
+        let ast = hparser::parse_with_flags(
+            hparser::ParserFlags {
+                strict_mode,
+                source_type,
+                ..Default::default()
+            },
+            source,
+            &mut ctx,
+        )
+        .map_err(|e| e.to_string())?;
+        if self.opt.stage >= Stage::Validate {
+            ast::validate_tree_pure(&mut ctx, &ast)
+                .map_err(|errors| format!("{} validation error(s)", errors.len()))?;
+        }
+        Ok(())
+    }
+
+    /// Return `source` with the harness includes prepended.
+    fn with_includes(&mut self, md: &Metadata, source: &str) -> Result<String, String> {
+        let harness_dir = match (&self.opt.harness_dir, md.has_flag("raw")) {
+            (Some(dir), false) => dir.clone(),
+            _ => return Ok(source.to_string()),
+        };
+
+        let mut names = vec!["assert.js", "sta.js"];
+        if md.has_flag("async") {
+            names.push("doneprintHandle.js");
+        }
+        names.extend(md.includes.iter().map(|s| s.as_str()));
+
+        let mut result = String::new();
+        for name in names {
+            let include = match self.includes.get(name) {
+                Some(inc) => inc.clone(),
+                None => {
+                    let path = harness_dir.join(name);
+                    let inc = Rc::new(
+                        fs::read_to_string(&path)
+                            .map_err(|e| format!("{}: {}", path.display(), e))?,
+                    );
+                    self.includes.insert(name.to_string(), inc.clone());
+                    inc
+                }
+            };
+            result.push_str(&include);
+            result.push('\n');
+        }
+        result.push_str(source);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata() {
+        let md = Metadata::parse(
+            r#"// Copyright
+/*---
+esid: sec-foo
+description: >
+  Multi line
+  description.
+info: |
+  Ignored.
+includes: [compareArray.js, propertyHelper.js]
+flags:
+  - onlyStrict
+  - async
+features: [BigInt]
+negative:
+  phase: parse
+  type: SyntaxError
+---*/
+$DONOTEVALUATE();
+"#,
+        )
+        .unwrap();
+        assert_eq!(md.description, "Multi line description.");
+        assert_eq!(md.includes, vec!["compareArray.js", "propertyHelper.js"]);
+        assert_eq!(md.flags, vec!["onlyStrict", "async"]);
+        assert!(md.has_flag("async"));
+        assert_eq!(md.features, vec!["BigInt"]);
+        assert_eq!(
+            md.negative,
+            Some(Negative {
+                phase: Phase::Parse,
+                error_type: "SyntaxError".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_metadata_errors() {
+        assert_eq!(Metadata::parse("var x;"), Err(MetadataError::Missing));
+        assert!(matches!(
+            Metadata::parse("/*---\nnegative:\n  phase: parse\n---*/"),
+            Err(MetadataError::Malformed { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::default();
+        summary.add(&TestResult {
+            path: PathBuf::from("a.js"),
+            features: vec!["BigInt".into()],
+            outcome: Outcome::Pass,
+        });
+        summary.add(&TestResult {
+            path: PathBuf::from("b.js"),
+            features: vec!["BigInt".into(), "Symbol".into()],
+            outcome: Outcome::Fail("oops".into()),
+        });
+        assert_eq!(
+            summary.total,
+            Stats {
+                passed: 1,
+                failed: 1,
+                skipped: 0
+            }
+        );
+        assert_eq!(summary.features["BigInt"].passed, 1);
+        assert_eq!(summary.features["Symbol"].failed, 1);
+        assert_eq!(summary.failures.len(), 1);
+    }
+}
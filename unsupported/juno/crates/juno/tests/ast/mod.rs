@@ -8,6 +8,7 @@
 use juno::ast::node_cast;
 use juno::ast::*;
 use juno::hparser;
+use juno_support::NullTerminatedBuf;
 
 mod validate;
 
@@ -35,6 +36,30 @@ fn test_node_outlives_context() {
     }
 }
 
+#[test]
+#[should_panic(expected = "NodeRc must not outlive Context")]
+fn test_node_outlives_context_tracked() {
+    let mut ctx = Context::new();
+    ctx.set_track_noderc_origins(true);
+    ctx.set_noderc_tag(Some("test"));
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(
+            &gc,
+            builder::NullLiteral::build_template(
+                &gc,
+                template::NullLiteral {
+                    metadata: Default::default(),
+                },
+            ),
+        )
+    };
+    // Dropped NodeRcs are no longer reported.
+    drop(ast.clone());
+    std::mem::forget(ast);
+    drop(ctx);
+}
+
 #[test]
 #[should_panic]
 #[allow(clippy::redundant_clone)]
@@ -525,40 +550,1822 @@ fn test_many_nodes() {
 }
 
 #[test]
-fn test_store_node() {
+fn test_gc_safepoint() {
     let mut ctx = Context::new();
-
-    struct Foo<'gc> {
-        n: Option<&'gc Node<'gc>>,
+    let mut gc = GCLock::new(&mut ctx);
+    let kept = NodeRc::from_node(
+        &gc,
+        builder::NumericLiteral::build_template(
+            &gc,
+            template::NumericLiteral {
+                metadata: Default::default(),
+                value: 1.0,
+            },
+        ),
+    );
+    for i in 0..10_000 {
+        builder::NumericLiteral::build_template(
+            &gc,
+            template::NumericLiteral {
+                metadata: Default::default(),
+                value: i as f64,
+            },
+        );
+    }
+    assert!(!gc.collect_if_needed());
+    let num_nodes = gc.ctx().num_nodes();
+    gc.gc();
+    // The garbage was freed, so allocating again must reuse the free list.
+    for i in 0..10_000 {
+        builder::NumericLiteral::build_template(
+            &gc,
+            template::NumericLiteral {
+                metadata: Default::default(),
+                value: i as f64,
+            },
+        );
     }
+    assert_eq!(gc.ctx().num_nodes(), num_nodes);
+    assert!(matches!(
+        kept.node(&gc),
+        Node::NumericLiteral(NumericLiteral { value, .. }) if *value == 1.0
+    ));
+}
 
-    impl<'gc> Foo<'gc> {
-        fn set_n(&mut self, node: &'gc Node<'gc>) {
-            self.n = Some(node);
+#[test]
+fn test_gc_policy() {
+    fn alloc_garbage(ctx: &mut Context) {
+        let gc = GCLock::new(ctx);
+        for i in 0..10_000 {
+            builder::NumericLiteral::build_template(
+                &gc,
+                template::NumericLiteral {
+                    metadata: Default::default(),
+                    value: i as f64,
+                },
+            );
         }
     }
 
-    impl<'gc> Visitor<'gc> for Foo<'gc> {
-        fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
-            self.set_n(node);
-            node.visit_children(gc, self)
+    for policy in [GcPolicy::NodeCount(5_000), GcPolicy::AllocatedBytes(10_000)] {
+        let mut ctx = Context::new();
+        ctx.set_gc_policy(policy);
+        alloc_garbage(&mut ctx);
+        let num_nodes = ctx.num_nodes();
+        // Releasing the lock doesn't collect, only the safepoint does.
+        alloc_garbage(&mut ctx);
+        assert!(ctx.num_nodes() > num_nodes, "{:?}", policy);
+        let num_nodes = ctx.num_nodes();
+        assert!(ctx.collect_if_needed(), "{:?}", policy);
+        assert!(!ctx.collect_if_needed(), "{:?}", policy);
+        // The garbage was collected, so the storage is reused.
+        alloc_garbage(&mut ctx);
+        assert_eq!(ctx.num_nodes(), num_nodes, "{:?}", policy);
+    }
+
+    // Enough garbage for the default policy to collect it.
+    let mut ctx = Context::new();
+    assert_eq!(ctx.gc_policy(), GcPolicy::Auto);
+    alloc_garbage(&mut ctx);
+    assert!(!ctx.collect_if_needed());
+    for _ in 0..6 {
+        alloc_garbage(&mut ctx);
+    }
+    assert!(ctx.collect_if_needed());
+
+    let mut ctx = Context::new();
+    ctx.set_gc_policy(GcPolicy::Manual);
+    for _ in 0..7 {
+        alloc_garbage(&mut ctx);
+    }
+    assert!(!ctx.collect_if_needed());
+    let num_nodes = ctx.num_nodes();
+    ctx.gc();
+    alloc_garbage(&mut ctx);
+    assert_eq!(ctx.num_nodes(), num_nodes);
+}
+
+#[test]
+fn test_reset() {
+    let mut ctx = Context::new();
+    let alloc = |ctx: &mut Context| {
+        let gc = GCLock::new(ctx);
+        builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(
+                    &gc,
+                    (0..10_000).map(|i| {
+                        builder::NumericLiteral::build_template(
+                            &gc,
+                            template::NumericLiteral {
+                                metadata: Default::default(),
+                                value: i as f64,
+                            },
+                        )
+                    }),
+                ),
+            },
+        );
+    };
+    alloc(&mut ctx);
+    assert_eq!(ctx.num_nodes(), 10_001);
+    let atom = ctx.atom("foo");
+    ctx.reset(ClearAtoms::No);
+    assert_eq!(ctx.num_nodes(), 0);
+    assert_eq!(ctx.str(atom), "foo");
+    alloc(&mut ctx);
+    assert_eq!(ctx.num_nodes(), 10_001);
+    ctx.reset(ClearAtoms::Yes);
+    assert!(ctx.atom_table().try_str(atom).is_none());
+}
+
+#[test]
+fn test_fork() {
+    let mut ctx = Context::new();
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        let program = parse_text(
+            &gc,
+            "Program\n  body:\n    - ExpressionStatement\n      expression: Identifier\n        name: \"x\"\n",
+        )
+        .unwrap();
+        NodeRc::from_node(&gc, program)
+    };
+    let num_nodes = ctx.num_nodes();
+    let original = {
+        let gc = GCLock::new(&mut ctx);
+        dump_text(&gc, root.node(&gc))
+    };
+
+    {
+        let mut child = ctx.fork();
+        assert!(child.is_fork());
+        let speculative = {
+            let gc = GCLock::new(&mut child);
+            let program = node_cast!(Node::Program, root.node(&gc));
+            // Share the parent's statement in a new program.
+            let stmt = program.body.head().unwrap();
+            let number = builder::ExpressionStatement::build_template(
+                &gc,
+                template::ExpressionStatement {
+                    metadata: Default::default(),
+                    expression: builder::NumericLiteral::build_template(
+                        &gc,
+                        template::NumericLiteral {
+                            metadata: Default::default(),
+                            value: 1.0,
+                        },
+                    ),
+                    directive: None,
+                },
+            );
+            let new_program = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, [stmt, number]),
+                },
+            );
+            gc.atom("only_in_child");
+            NodeRc::from_node(&gc, new_program)
+        };
+        // Collecting in the child keeps the parent's nodes intact.
+        child.gc();
+        let gc = GCLock::new(&mut child);
+        let program = node_cast!(Node::Program, speculative.node(&gc));
+        assert_eq!(program.body.len(), 2);
+        assert!(dump_text(&gc, speculative.node(&gc)).contains("name: \"x\""));
+        drop(gc);
+        drop(speculative);
+    }
+
+    // The parent is unchanged once the child is discarded.
+    assert_eq!(ctx.num_nodes(), num_nodes);
+    assert!(!ctx.is_fork());
+    ctx.gc();
+    let gc = GCLock::new(&mut ctx);
+    assert_eq!(dump_text(&gc, root.node(&gc)), original);
+    drop(gc);
+    drop(root);
+}
+
+/// Increment every numeric literal.
+struct Increment;
+
+impl<'gc> VisitorMut<'gc> for Increment {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            Node::NumericLiteral(NumericLiteral { value, .. }) => {
+                TransformResult::Changed(builder::NumericLiteral::build_template(
+                    gc,
+                    template::NumericLiteral {
+                        metadata: (*node.range()).into(),
+                        value: value + 1.0,
+                    },
+                ))
+            }
+            _ => node.visit_children_mut(gc, self),
         }
     }
+}
+
+/// Return the values of the numeric literals of `root`, in preorder.
+fn numbers(ctx: &mut Context, root: &NodeRc) -> Vec<f64> {
+    let gc = GCLock::new(ctx);
+    iter_preorder(&gc, root.node(&gc))
+        .filter_map(|(node, _, _)| match node {
+            Node::NumericLiteral(lit) => Some(lit.value),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parse `1; 2;` with the first statement frozen.
+fn frozen_program(ctx: &mut Context) -> NodeRc {
+    let gc = GCLock::new(ctx);
+    let program = parse_text(
+        &gc,
+        "Program
+  body:
+    - ExpressionStatement
+      expression: NumericLiteral
+        value: 1.0
+    - ExpressionStatement
+      expression: NumericLiteral
+        value: 2.0
+",
+    )
+    .unwrap();
+    let first = node_cast!(Node::Program, program).body.head().unwrap();
+    gc.freeze(first);
+    assert!(gc.is_frozen(first));
+    assert!(!gc.is_frozen(program));
+    NodeRc::from_node(&gc, program)
+}
+
+#[test]
+fn test_freeze() {
+    let mut ctx = Context::new();
+    assert_eq!(ctx.frozen_policy(), FrozenPolicy::Skip);
+    let root = frozen_program(&mut ctx);
+    let new_root = {
+        let gc = GCLock::new(&mut ctx);
+        let new_root = root.node(&gc).visit_mut(&gc, &mut Increment, None).unwrap();
+        NodeRc::from_node(&gc, new_root)
+    };
+    assert_eq!(numbers(&mut ctx, &new_root), [1.0, 3.0]);
 
+    // Copies aren't frozen.
     {
         let gc = GCLock::new(&mut ctx);
-        let mut pass = Foo { n: None };
-        let ast = NodeRc::from_node(
-            &gc,
+        let copy = deep_clone(&gc, root.node(&gc));
+        let new_copy = copy.visit_mut(&gc, &mut Increment, None).unwrap();
+        let values = iter_preorder(&gc, new_copy)
+            .filter_map(|(node, _, _)| match node {
+                Node::NumericLiteral(lit) => Some(lit.value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, [2.0, 3.0]);
+    }
+
+    // Frozen nodes which the visitor leaves unchanged are fine.
+    ctx.set_frozen_policy(FrozenPolicy::Panic);
+    {
+        let gc = GCLock::new(&mut ctx);
+        struct Nop;
+        impl<'gc> VisitorMut<'gc> for Nop {
+            fn call(
+                &mut self,
+                gc: &'gc GCLock,
+                node: &'gc Node<'gc>,
+                _path: Option<Path<'gc>>,
+            ) -> TransformResult<&'gc Node<'gc>> {
+                node.visit_children_mut(gc, self)
+            }
+        }
+        assert!(std::ptr::eq(
+            root.node(&gc).visit_mut(&gc, &mut Nop, None).unwrap(),
+            root.node(&gc)
+        ));
+    }
+    drop(new_root);
+    drop(root);
+}
+
+#[test]
+#[should_panic(expected = "Attempt to replace a frozen")]
+fn test_freeze_panic() {
+    let mut ctx = Context::new();
+    ctx.set_frozen_policy(FrozenPolicy::Panic);
+    let root = frozen_program(&mut ctx);
+    let gc = GCLock::new(&mut ctx);
+    let node = root.node(&gc);
+    // Drop `root` in order to prevent the `Drop` impl of `ctx` from panicking again.
+    drop(root);
+    node.visit_mut(&gc, &mut Increment, None);
+}
+
+#[test]
+fn test_juno_ignore() {
+    let mut ctx = Context::new();
+    let root = hparser::parse_with_flags(
+        Default::default(),
+        "a = 1;\n/* juno-ignore */\nb = 2;\nc = 3; // juno-ignore\nd = 4;\n",
+        &mut ctx,
+    )
+    .unwrap();
+    let new_root = {
+        let gc = GCLock::new(&mut ctx);
+        let node = root.node(&gc);
+        let file = node.range().file;
+        assert_eq!(
+            juno::ignore::ignore_comments(gc.sm().source_buffer(file)),
+            [
+                SourceLoc { line: 2, col: 18 },
+                SourceLoc { line: 4, col: 22 }
+            ]
+        );
+        assert_eq!(juno::ignore::freeze_ignored(&gc, node, file), 2);
+        NodeRc::from_node(&gc, node.visit_mut(&gc, &mut Increment, None).unwrap())
+    };
+    assert_eq!(numbers(&mut ctx, &new_root), [2.0, 2.0, 4.0, 4.0]);
+
+    // The program starts at its first statement, but only the statement is frozen.
+    let root = hparser::parse_with_flags(
+        Default::default(),
+        "/* juno-ignore */\na = 1;\nb = 2;\n",
+        &mut ctx,
+    )
+    .unwrap();
+    let new_root = {
+        let gc = GCLock::new(&mut ctx);
+        let node = root.node(&gc);
+        assert_eq!(
+            juno::ignore::freeze_ignored(&gc, node, node.range().file),
+            1
+        );
+        NodeRc::from_node(&gc, node.visit_mut(&gc, &mut Increment, None).unwrap())
+    };
+    assert_eq!(numbers(&mut ctx, &new_root), [1.0, 3.0]);
+}
+
+#[test]
+fn test_jsdoc() {
+    let mut ctx = Context::new();
+    let root = hparser::parse_with_flags(
+        hparser::ParserFlags {
+            source_type: hparser::SourceType::Module,
+            ..Default::default()
+        },
+        "/**\n * Adds.\n * @param {number} a\n */\nexport function add(a) {}\n\
+        /** The class. */\n// Not a JSDoc comment.\nclass C {\n  /** @type {string} */\n  name = '';\n}\n\
+        f(/** Not attached. */ (x));\n",
+        &mut ctx,
+    )
+    .unwrap();
+    let gc = GCLock::new(&mut ctx);
+    let node = root.node(&gc);
+    let docs = juno::jsdoc::attach(&gc, node, node.range().file);
+    // The export and its function, the class and its property.
+    assert_eq!(docs.len(), 4);
+    for (node, _, _) in iter_preorder(&gc, node) {
+        let doc = docs.get(node);
+        match node {
+            Node::FunctionDeclaration(_) => {
+                let doc = doc.unwrap();
+                assert_eq!(doc.description, "Adds.");
+                assert_eq!(doc.param("a").unwrap().type_expr.as_deref(), Some("number"));
+            }
+            Node::ClassDeclaration(_) => assert_eq!(doc.unwrap().description, "The class."),
+            Node::ClassProperty(_) => {
+                assert_eq!(doc.unwrap().type_expr.as_deref(), Some("string"))
+            }
+            Node::ExportNamedDeclaration(_) => assert!(doc.is_some()),
+            _ => assert!(doc.is_none()),
+        }
+    }
+}
+
+#[test]
+fn test_docgen() {
+    let mut ctx = Context::new();
+    let root = hparser::parse_with_flags(
+        hparser::ParserFlags {
+            source_type: hparser::SourceType::Module,
+            ..Default::default()
+        },
+        "/** @file Arithmetic. */\n/** Adds. */\nexport function add(a, b) {}\n\
+        /** The answer. */\nconst answer = 42;\nexport { answer as default, answer };\n\
+        export const x = 1;\n",
+        &mut ctx,
+    )
+    .unwrap();
+    let gc = GCLock::new(&mut ctx);
+    let module = juno::docgen::document(&gc, root.node(&gc), "a.js");
+    assert_eq!(module.description, "Arithmetic.");
+    let items = module
+        .items
+        .iter()
+        .map(|item| {
+            (
+                item.export.name.as_str(),
+                item.doc.as_ref().map(|doc| doc.description.as_str()),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        [
+            ("add", Some("Adds.")),
+            ("default", Some("The answer.")),
+            ("answer", Some("The answer.")),
+            ("x", None),
+        ]
+    );
+}
+
+#[test]
+fn test_source_atoms() {
+    let mut ctx = Context::new();
+    ctx.set_source_atoms(true);
+    let root =
+        hparser::parse_with_flags(Default::default(), "foo(\\u0062ar, foo);", &mut ctx).unwrap();
+    let gc = GCLock::new(&mut ctx);
+    let node = root.node(&gc);
+    let source = gc
+        .sm()
+        .source_buffer(node.range().file)
+        .as_bytes()
+        .as_ptr_range();
+    let names = iter_preorder(&gc, node)
+        .filter_map(|(node, _, _)| match node {
+            Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["foo", "bar", "foo"]);
+    // Escaped names are copied.
+    assert!(source.contains(&names[0].as_ptr()));
+    assert!(!source.contains(&names[1].as_ptr()));
+    drop(gc);
+    drop(root);
+}
+
+#[test]
+fn test_userdata() {
+    #[derive(Debug, PartialEq)]
+    struct Depth(usize);
+
+    let mut ctx = Context::new();
+    let literal = |gc: &GCLock, value| {
+        NodeRc::from_node(
+            gc,
             builder::NumericLiteral::build_template(
-                &gc,
+                gc,
                 template::NumericLiteral {
                     metadata: Default::default(),
-                    value: 1.0f64,
+                    value,
                 },
             ),
+        )
+    };
+    let (one, two) = {
+        let gc = GCLock::new(&mut ctx);
+        (literal(&gc, 1.0), literal(&gc, 2.0))
+    };
+    {
+        let gc = GCLock::new(&mut ctx);
+        gc.set_userdata(one.node(&gc), Depth(1));
+        gc.set_userdata(one.node(&gc), "one");
+        gc.set_userdata(one.node(&gc), Depth(3));
+        assert_eq!(
+            gc.get_userdata::<Depth>(one.node(&gc)).as_deref(),
+            Some(&Depth(3))
         );
-        ast.node(&gc).visit(&gc, &mut pass, None);
-        assert!(pass.n.is_some());
+        assert_eq!(
+            gc.get_userdata::<&str>(one.node(&gc)).as_deref(),
+            Some(&"one")
+        );
+        assert_eq!(gc.get_userdata::<Depth>(two.node(&gc)), None);
+        gc.set_userdata(two.node(&gc), Depth(2));
+        assert_eq!(
+            gc.remove_userdata::<Depth>(two.node(&gc)).as_deref(),
+            Some(&Depth(2))
+        );
+        assert_eq!(gc.get_userdata::<Depth>(two.node(&gc)), None);
+    }
+
+    // The values attached to a freed node don't leak into the node reusing its storage.
+    drop(one);
+    ctx.gc();
+    let three = {
+        let gc = GCLock::new(&mut ctx);
+        literal(&gc, 3.0)
+    };
+    let gc = GCLock::new(&mut ctx);
+    assert_eq!(gc.get_userdata::<Depth>(three.node(&gc)), None);
+    assert_eq!(gc.get_userdata::<&str>(three.node(&gc)), None);
+    drop(gc);
+    drop(two);
+    drop(three);
+}
+
+#[test]
+fn test_visit_all() {
+    /// Count the identifiers.
+    #[derive(Default)]
+    struct Identifiers(usize);
+
+    impl<'gc> KindVisitor<'gc> for Identifiers {
+        fn kinds(&self) -> &'static [NodeVariant] {
+            &[NodeVariant::Identifier]
+        }
+        fn enter(&mut self, _gc: &'gc GCLock, _node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.0 += 1;
+        }
+    }
+
+    /// Record the depth of nesting of expressions, and the maximum reached.
+    #[derive(Default)]
+    struct Depth {
+        depth: usize,
+        max: usize,
+    }
+
+    impl<'gc> KindVisitor<'gc> for Depth {
+        fn kinds(&self) -> &'static [NodeVariant] {
+            &[NodeVariant::Expression]
+        }
+        fn enter(&mut self, _gc: &'gc GCLock, _node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.depth += 1;
+            self.max = self.max.max(self.depth);
+        }
+        fn leave(&mut self, _gc: &'gc GCLock, _node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.depth -= 1;
+        }
+    }
+
+    /// Record every node.
+    #[derive(Default)]
+    struct All(Vec<&'static str>);
+
+    impl<'gc> KindVisitor<'gc> for All {
+        fn enter(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.0.push(node.name());
+        }
+    }
+
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    // f(x + g(y));
+    let program = parse_text(
+        &gc,
+        r#"Program
+  body:
+    - ExpressionStatement
+      expression: CallExpression
+        callee: Identifier
+          name: "f"
+        arguments:
+          - BinaryExpression
+            left: Identifier
+              name: "x"
+            right: CallExpression
+              callee: Identifier
+                name: "g"
+              arguments:
+                - Identifier
+                  name: "y"
+            operator: +
+"#,
+    )
+    .unwrap();
+    let mut identifiers = Identifiers::default();
+    let mut depth = Depth::default();
+    let mut all = All::default();
+    VisitAll::new(vec![&mut identifiers, &mut depth, &mut all]).visit(&gc, program);
+    assert_eq!(identifiers.0, 4);
+    assert_eq!(depth.depth, 0);
+    assert_eq!(depth.max, 4);
+    assert_eq!(all.0.len(), 9);
+    assert_eq!(
+        all.0[..3],
+        ["Program", "ExpressionStatement", "CallExpression"]
+    );
+}
+
+#[test]
+fn test_kind_visitor() {
+    /// Record the callees and count the other nodes.
+    #[derive(Default)]
+    struct Calls {
+        callees: Vec<String>,
+        others: usize,
+    }
+
+    #[kind_visitor]
+    impl<'gc> Calls {
+        fn visit_call_expression(
+            &mut self,
+            ctx: &'gc GCLock,
+            node: &'gc Node<'gc>,
+            call: &'gc CallExpression<'gc>,
+            _path: Option<Path<'gc>>,
+        ) {
+            if let Node::Identifier(Identifier { name, .. }) = call.callee {
+                self.callees.push(ctx.str(*name).to_string());
+            }
+            node.visit_children(ctx, self);
+        }
+
+        fn visit_node(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.others += 1;
+            node.visit_children(ctx, self);
+        }
+    }
+
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    // f(g(x), 1);
+    let program = parse_text(
+        &gc,
+        r#"Program
+  body:
+    - ExpressionStatement
+      expression: CallExpression
+        callee: Identifier
+          name: "f"
+        arguments:
+          - CallExpression
+            callee: Identifier
+              name: "g"
+            arguments:
+              - Identifier
+                name: "x"
+          - NumericLiteral
+            value: 1
+"#,
+    )
+    .unwrap();
+    let mut calls = Calls::default();
+    program.visit(&gc, &mut calls, None);
+    assert_eq!(calls.callees, ["f", "g"]);
+    assert_eq!(calls.others, 6);
+}
+
+#[test]
+fn test_synthesized() {
+    /// Count the numbers written by the user.
+    #[derive(Default)]
+    struct Numbers(usize);
+
+    impl<'gc> KindVisitor<'gc> for Numbers {
+        fn kinds(&self) -> &'static [NodeVariant] {
+            &[NodeVariant::NumericLiteral]
+        }
+        fn skip_synthesized(&self) -> bool {
+            true
+        }
+        fn enter(&mut self, _gc: &'gc GCLock, _node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.0 += 1;
+        }
     }
+
+    let mut ctx = Context::new();
+    let file = ctx
+        .sm_mut()
+        .add_source("test.js", NullTerminatedBuf::from_str_copy("1 + 2"));
+    let gc = GCLock::new(&mut ctx);
+    let range = |col: u32| SourceRange {
+        file,
+        start: SourceLoc { line: 1, col },
+        end: SourceLoc { line: 1, col },
+    };
+    let one = builder::NumericLiteral::build_template(
+        &gc,
+        template::NumericLiteral {
+            metadata: range(1).into(),
+            value: 1.0,
+        },
+    );
+    let two = builder::NumericLiteral::build_template(
+        &gc,
+        template::NumericLiteral {
+            metadata: TemplateMetadata {
+                range: range(5),
+                synthesized: true,
+                ..Default::default()
+            },
+            value: 2.0,
+        },
+    );
+    let three = builders::number(&gc, 3.0);
+    assert!(!one.is_synthesized());
+    assert!(two.is_synthesized());
+    assert!(three.is_synthesized());
+
+    // Rebuilding a node keeps the flag.
+    let mut builder = builder::NumericLiteral::from_node(node_cast!(Node::NumericLiteral, one));
+    builder.value(4.0);
+    let four = builder.build_forced(&gc);
+    assert!(!four.is_synthesized());
+
+    let f = builders::ident(&gc, "f");
+    let call = builders::call(&gc, f, [one, two, three, four]);
+    let mut numbers = Numbers::default();
+    VisitAll::new(vec![&mut numbers]).visit(&gc, call);
+    assert_eq!(numbers.0, 2);
+}
+
+#[test]
+fn test_memory_limit() {
+    let mut ctx = Context::new();
+    ctx.set_memory_limit(Some(10_000));
+    {
+        let gc = GCLock::new(&mut ctx);
+        let mut count = 0;
+        let err = loop {
+            match builder::NullLiteral::try_build_template(
+                &gc,
+                template::NullLiteral {
+                    metadata: Default::default(),
+                },
+            ) {
+                Ok(_) => count += 1,
+                Err(e) => break e,
+            }
+        };
+        assert!(count > 0);
+        assert_eq!(err.limit, 10_000);
+    }
+    // Freed storage can be reused past the limit.
+    ctx.gc();
+    {
+        let gc = GCLock::new(&mut ctx);
+        assert!(builder::NullLiteral::try_build_template(
+            &gc,
+            template::NullLiteral {
+                metadata: Default::default(),
+            },
+        )
+        .is_ok());
+    }
+
+    // Lists fail the same way instead of panicking.
+    let mut ctx = Context::new();
+    ctx.set_memory_limit(Some(10_000));
+    {
+        let gc = GCLock::new(&mut ctx);
+        let f = builders::ident(&gc, "f");
+        let arg = builders::number(&gc, 1.0);
+        assert!(NodeList::try_from_iter(&gc, [arg, arg]).is_ok());
+        let err = builders::try_call(&gc, f, vec![arg; 10_000]).unwrap_err();
+        assert_eq!(err.limit, 10_000);
+    }
+
+    let exceeded = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut ctx = Context::new();
+    ctx.set_memory_limit(Some(0));
+    ctx.set_memory_limit_handler({
+        let exceeded = exceeded.clone();
+        move |_| exceeded.set(exceeded.get() + 1)
+    });
+    let gc = GCLock::new(&mut ctx);
+    builder::NullLiteral::build_template(
+        &gc,
+        template::NullLiteral {
+            metadata: Default::default(),
+        },
+    );
+    assert_eq!(exceeded.get(), 1);
+}
+
+/// `-(-(...-1))`, whose literal has depth `depth`.
+fn nested_negations<'gc>(gc: &'gc GCLock, depth: usize) -> &'gc Node<'gc> {
+    let mut node = builder::NumericLiteral::build_template(
+        gc,
+        template::NumericLiteral {
+            metadata: Default::default(),
+            value: 1.0,
+        },
+    );
+    for _ in 0..depth {
+        node = builder::UnaryExpression::build_template(
+            gc,
+            template::UnaryExpression {
+                metadata: Default::default(),
+                prefix: true,
+                operator: UnaryExpressionOperator::Minus,
+                argument: node,
+            },
+        );
+    }
+    node
+}
+
+#[test]
+fn test_max_depth() {
+    // Counts the nodes it visits.
+    struct Count(usize);
+
+    impl<'gc> Visitor<'gc> for Count {
+        fn call(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.0 += 1;
+            node.visit_children(ctx, self);
+        }
+    }
+
+    let mut ctx = Context::new();
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, nested_negations(&gc, 50))
+    };
+
+    ctx.set_max_depth(Some(50));
+    {
+        let gc = GCLock::new(&mut ctx);
+        assert!(check_depth(&gc, ast.node(&gc)).is_err());
+    }
+    ctx.set_max_depth(Some(51));
+    {
+        let gc = GCLock::new(&mut ctx);
+        assert!(check_depth(&gc, ast.node(&gc)).is_ok());
+        let mut count = Count(0);
+        ast.node(&gc).visit(&gc, &mut count, None);
+        assert_eq!(count.0, 51);
+    }
+    assert!(ctx.take_too_deep().is_none());
+
+    ctx.set_max_depth(Some(10));
+    {
+        let gc = GCLock::new(&mut ctx);
+        let mut count = Count(0);
+        ast.node(&gc).visit(&gc, &mut count, None);
+        assert_eq!(count.0, 11);
+    }
+    assert_eq!(ctx.take_too_deep().unwrap().limit, 10);
+    assert!(ctx.take_too_deep().is_none());
+
+    let errors = validate_tree_pure(&mut ctx, &ast).err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "AST nesting depth limit of 10 exceeded");
+
+    let err = dump_json(std::io::sink(), &mut ctx, &ast, Pretty::No).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_gc_past_max_depth() {
+    let mut ctx = Context::new();
+    ctx.set_max_depth(Some(10));
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        let ast = NodeRc::from_node(&gc, nested_negations(&gc, 50));
+        gc.freeze(ast.node(&gc));
+        ast
+    };
+    ctx.gc();
+    {
+        // Reuse the storage of the nodes if they were freed.
+        let gc = GCLock::new(&mut ctx);
+        for _ in 0..100 {
+            builder::NullLiteral::build_template(
+                &gc,
+                template::NullLiteral {
+                    metadata: Default::default(),
+                },
+            );
+        }
+    }
+
+    {
+        let gc = GCLock::new(&mut ctx);
+        let nodes: Vec<_> = iter_preorder(&gc, ast.node(&gc))
+            .map(|(node, _, _)| node)
+            .collect();
+        assert_eq!(nodes.len(), 51);
+        assert_eq!(node_cast!(Node::NumericLiteral, nodes[50]).value, 1.0);
+        assert!(nodes.iter().all(|node| gc.is_frozen(node)));
+    }
+    assert!(ctx.take_too_deep().is_none());
+}
+
+#[test]
+fn test_with_gc() {
+    let mut ctx1 = Context::new();
+    let mut ctx2 = Context::new();
+    let len = ctx1.with_gc(|gc| {
+        let list = NodeList::from_iter(
+            gc,
+            [builder::NullLiteral::build_template(
+                gc,
+                template::NullLiteral {
+                    metadata: Default::default(),
+                },
+            )],
+        );
+        assert!(GCLock::try_new(&mut ctx2).is_err());
+        assert!(ctx2.try_with_gc(|_| ()).is_err());
+        list.len()
+    });
+    assert_eq!(len, 1);
+    assert!(ctx2.try_with_gc(|_| ()).is_ok());
+    assert!(GCLock::try_new(&mut ctx1).is_ok());
+}
+
+#[test]
+fn test_context_config() {
+    let small = Context::new();
+    let mut large = Context::with_config(ContextConfig::for_source_len(1 << 20));
+    assert!(large.storage_size() > small.storage_size() * 16);
+    let size = large.storage_size();
+    {
+        let gc = GCLock::new(&mut large);
+        for i in 0..(1 << 15) {
+            builder::NumericLiteral::build_template(
+                &gc,
+                template::NumericLiteral {
+                    metadata: Default::default(),
+                    value: i as f64,
+                },
+            );
+        }
+    }
+    assert_eq!(large.storage_size(), size);
+
+    let ctx = Context::with_config(ContextConfig {
+        node_chunks: ChunkConfig {
+            min_capacity: 16,
+            max_capacity: 1 << 10,
+            growth_factor: 4,
+        },
+        ..Default::default()
+    });
+    assert!(ctx.storage_size() < small.storage_size());
+}
+
+#[test]
+fn test_alloc_histogram() {
+    let mut ctx = Context::new();
+    ctx.set_track_alloc_stats(true);
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        // Garbage.
+        builders::ident(&gc, "unused");
+        let call = builders::call(
+            &gc,
+            builders::ident(&gc, "f"),
+            [builders::ident(&gc, "x"), builders::number(&gc, 1.0)],
+        );
+        NodeRc::from_node(&gc, call)
+    };
+    ctx.gc();
+
+    let histogram = ctx.alloc_histogram();
+    assert_eq!(histogram.allocated(), 5);
+    assert_eq!(histogram.kinds[0].variant, NodeVariant::Identifier);
+    let ident = histogram.get(NodeVariant::Identifier).unwrap();
+    assert_eq!((ident.allocated, ident.live), (3, 2));
+    assert_eq!(ident.live_bytes * 2, histogram.live_bytes());
+    assert!(histogram.get(NodeVariant::Program).is_none());
+    assert!(histogram.to_string().contains("CallExpression"));
+
+    let stats = ctx.heap_stats();
+    assert_eq!(stats.live_nodes, 4);
+    assert_eq!(stats.live_list_elements, 2);
+    assert_eq!(
+        stats.live_payload_bytes,
+        histogram
+            .kinds
+            .iter()
+            .map(|kind| kind.live_payload_bytes)
+            .sum::<usize>()
+    );
+    assert!(stats.padding_bytes() > 0);
+    drop(root);
+}
+
+#[test]
+fn test_boxed_kinds() {
+    // The kinds larger than an identifier are boxed, so that they don't make every node larger.
+    let limit = NodeVariant::Identifier.payload_size();
+    for &kind in NodeVariant::KINDS {
+        assert_eq!(kind.is_boxed(), kind.payload_size() > limit, "{:?}", kind);
+    }
+    assert!(std::mem::size_of::<Node>() <= limit + std::mem::size_of::<usize>());
+
+    let mut ctx = Context::new();
+    let ast =
+        hparser::parse_with_flags(Default::default(), "for (x of y) f(x);", &mut ctx).unwrap();
+    ctx.gc();
+    let stats = ctx.heap_stats();
+    assert_eq!(stats.live_boxed_bytes, NodeVariant::ForOfStatement.payload_size());
+    assert!(stats.to_string().contains("boxed"));
+    drop(ast);
+    ctx.gc();
+    assert_eq!(ctx.heap_stats().live_boxed_bytes, 0);
+}
+
+#[test]
+fn test_gclock_origin() {
+    GCLock::set_track_origins(true);
+    let mut ctx1 = Context::new();
+    let mut ctx2 = Context::new();
+    let gc = GCLock::new(&mut ctx1);
+    let line = line!() - 1;
+    let e = GCLock::try_new(&mut ctx2).err().unwrap();
+    assert_eq!(e.origin.unwrap().line(), line);
+    assert!(e.to_string().contains(file!()));
+    drop(gc);
+
+    let guard = GCLockGuard::enter().unwrap();
+    let gc = GCLock::new(&mut ctx1);
+    assert!(GCLockGuard::enter().is_err());
+    drop(gc);
+    assert!(guard.exit().is_ok());
+
+    let guard = GCLockGuard::enter().unwrap();
+    let gc = GCLock::new(&mut ctx1);
+    assert!(guard.exit().is_err());
+    drop(gc);
+}
+
+#[test]
+fn test_panic_free() {
+    let mut ctx1 = Context::try_new().unwrap();
+    let mut ctx2 = Context::try_new().unwrap();
+    let node = {
+        let gc = ctx1.try_lock().unwrap();
+        let node = builder::NullLiteral::build_template(
+            &gc,
+            template::NullLiteral {
+                metadata: Default::default(),
+            },
+        );
+        NodeRc::from_node(&gc, node)
+    };
+    {
+        let gc = ctx2.try_lock().unwrap();
+        let e = node.try_node(&gc).unwrap_err();
+        assert_ne!(e.node_ctx, e.gc_ctx);
+    }
+    {
+        let mut child = ctx1.try_fork().unwrap();
+        let gc = GCLock::new(&mut child);
+        assert!(node.try_node(&gc).is_ok());
+    }
+    assert_eq!(ctx1.try_reset(ClearAtoms::No).unwrap_err().count, 1);
+    let gc = GCLock::new(&mut ctx1);
+    assert!(matches!(node.try_node(&gc), Ok(Node::NullLiteral(_))));
+    drop(gc);
+
+    // The context is leaked, so `node` can still be dropped safely.
+    assert_eq!(ctx1.try_close().unwrap_err().count, 1);
+    drop(node);
+    assert!(ctx2.try_close().is_ok());
+}
+
+#[test]
+fn test_noderc_list() {
+    let mut ctx = Context::new();
+    let mut list = NodeRcList::new(&ctx);
+    {
+        let gc = GCLock::new(&mut ctx);
+        for i in 0..1_000 {
+            let node = builder::NumericLiteral::build_template(
+                &gc,
+                template::NumericLiteral {
+                    metadata: Default::default(),
+                    value: i as f64,
+                },
+            );
+            if i % 2 == 0 {
+                list.push(&gc, node);
+            }
+        }
+    }
+    ctx.gc();
+    let gc = GCLock::new(&mut ctx);
+    assert_eq!(list.len(), 500);
+    for (i, node) in list.iter(&gc).enumerate() {
+        assert!(matches!(
+            node,
+            Node::NumericLiteral(NumericLiteral { value, .. }) if *value == (i * 2) as f64
+        ));
+    }
+    assert!(matches!(
+        list.pop(&gc),
+        Some(Node::NumericLiteral(NumericLiteral { value, .. })) if *value == 998.0
+    ));
+    list.clear();
+    assert!(list.get(&gc, 0).is_none());
+}
+
+#[test]
+fn test_field_reflection() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let one = builder::NumericLiteral::build_template(
+        &gc,
+        template::NumericLiteral {
+            metadata: Default::default(),
+            value: 1.0,
+        },
+    );
+    let x = builder::Identifier::build_template(
+        &gc,
+        template::Identifier {
+            metadata: Default::default(),
+            name: gc.atom("x"),
+            type_annotation: None,
+            optional: false,
+        },
+    );
+    let add = builder::BinaryExpression::build_template(
+        &gc,
+        template::BinaryExpression {
+            metadata: Default::default(),
+            left: one,
+            right: x,
+            operator: BinaryExpressionOperator::Plus,
+        },
+    );
+
+    let names = add.fields().iter().map(|f| f.name).collect::<Vec<_>>();
+    assert_eq!(names, ["left", "right", "operator"]);
+    assert_eq!(add.fields()[0].category, FieldCategory::Node);
+    assert_eq!(add.fields()[2].category, FieldCategory::Enum);
+    assert!(matches!(add.field(NodeField::left), Some(FieldValue::Node(n)) if n.ptr_eq(one)));
+    assert!(matches!(
+        add.field_by_name("operator"),
+        Some(FieldValue::Enum("+"))
+    ));
+    assert!(add.field(NodeField::body).is_none());
+
+    let info = NodeVariant::Identifier.fields();
+    assert_eq!(info[0].category, FieldCategory::Label);
+    assert!(info[1].optional);
+    assert!(matches!(
+        x.field(NodeField::type_annotation),
+        Some(FieldValue::Null)
+    ));
+    assert!(matches!(x.field(NodeField::name), Some(FieldValue::Label(a)) if gc.str(a) == "x"));
+    assert!(NodeVariant::Expression.fields().is_empty());
+}
+
+#[test]
+fn test_variant_hierarchy() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let x = builder::Identifier::build_template(
+        &gc,
+        template::Identifier {
+            metadata: Default::default(),
+            name: gc.atom("x"),
+            type_annotation: None,
+            optional: false,
+        },
+    );
+    let stmt = builder::ExpressionStatement::build_template(
+        &gc,
+        template::ExpressionStatement {
+            metadata: Default::default(),
+            expression: x,
+            directive: None,
+        },
+    );
+    assert!(x.is_expression());
+    assert!(x.is_lval());
+    assert!(!x.is_pattern());
+    assert!(!x.is_statement());
+    assert!(stmt.is_statement());
+    assert!(!stmt.is_expression());
+
+    assert_eq!(NodeVariant::Identifier.parent_variant(), Some(NodeVariant::Pattern));
+    assert_eq!(NodeVariant::Expression.parent_variant(), None);
+    assert!(NodeVariant::NumericLiteral.is_subtype_of(NodeVariant::Expression));
+    assert!(!NodeVariant::NumericLiteral.is_subtype_of(NodeVariant::Pattern));
+}
+
+#[test]
+fn test_builders() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let console = builders::ident(&gc, "console");
+    let callee = builders::member(&gc, console, "log");
+    let args = [
+        builders::string(&gc, "hi"),
+        builders::number(&gc, 1.0),
+        builders::boolean(&gc, true),
+        builders::null(&gc),
+    ];
+    let stmt = builders::expr_stmt(&gc, builders::call(&gc, callee, args));
+
+    let call = node_cast!(
+        Node::CallExpression,
+        node_cast!(Node::ExpressionStatement, stmt).expression
+    );
+    assert!(call.callee.ptr_eq(callee));
+    let member = node_cast!(Node::MemberExpression, call.callee);
+    assert_eq!(
+        gc.str(node_cast!(Node::Identifier, member.property).name),
+        "log"
+    );
+    assert!(!member.computed);
+    let args = call.arguments.iter().collect::<Vec<_>>();
+    assert_eq!(args.len(), 4);
+    assert_eq!(
+        gc.str_u16(node_cast!(Node::StringLiteral, args[0]).value),
+        "hi".encode_utf16().collect::<Vec<_>>()
+    );
+    assert_eq!(node_cast!(Node::NumericLiteral, args[1]).value, 1.0);
+    assert!(node_cast!(Node::BooleanLiteral, args[2]).value);
+    assert!(matches!(args[3], Node::NullLiteral(_)));
+}
+
+#[test]
+fn test_deep_clone() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let callee = builders::ident(&gc, "f");
+    let arg = builders::number(&gc, 1.0);
+    let call = builders::call(&gc, callee, [arg]);
+
+    let cloned = deep_clone(&gc, call);
+    assert!(!cloned.ptr_eq(call));
+    let cloned_call = node_cast!(Node::CallExpression, cloned);
+    assert!(!cloned_call.callee.ptr_eq(callee));
+    assert_eq!(
+        node_cast!(Node::Identifier, cloned_call.callee).name,
+        node_cast!(Node::Identifier, callee).name
+    );
+    let cloned_arg = cloned_call.arguments.head().unwrap();
+    assert!(!cloned_arg.ptr_eq(arg));
+    assert_eq!(node_cast!(Node::NumericLiteral, cloned_arg).value, 1.0);
+    assert_eq!(cloned_call.arguments.len(), 1);
+    assert!(cloned_call.type_arguments.is_none());
+}
+
+#[test]
+fn test_metrics() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let one = builders::number(&gc, 1.0);
+    let call = builders::call(&gc, builders::ident(&gc, "f"), [one, one]);
+    let stmt = builders::expr_stmt(&gc, call);
+
+    let m = metrics(&gc, stmt);
+    assert_eq!(m.node_count, 5);
+    assert_eq!(m.max_depth, 3);
+    assert_eq!(m.count(NodeVariant::NumericLiteral), 2);
+    assert_eq!(m.count(NodeVariant::Identifier), 1);
+    assert_eq!(m.count(NodeVariant::BlockStatement), 0);
+    assert!(m.heap_bytes > metrics(&gc, one).heap_bytes * 5);
+
+    let leaf = metrics(&gc, one);
+    assert_eq!(leaf.node_count, 1);
+    assert_eq!(leaf.max_depth, 1);
+}
+
+#[test]
+fn test_iter() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let f = builders::ident(&gc, "f");
+    let one = builders::number(&gc, 1.0);
+    let two = builders::number(&gc, 2.0);
+    let call = builders::call(&gc, f, [one, two]);
+    let stmt = builders::expr_stmt(&gc, call);
+
+    let pre = iter_preorder(&gc, stmt).collect::<Vec<_>>();
+    let nodes = pre.iter().map(|&(node, _, _)| node).collect::<Vec<_>>();
+    assert_eq!(nodes.len(), 5);
+    assert!(nodes
+        .iter()
+        .zip([stmt, call, f, one, two])
+        .all(|(a, b)| std::ptr::eq(*a, b)));
+    assert_eq!(pre.iter().map(|&(_, depth, _)| depth).max(), Some(2));
+    assert!(pre[0].2.is_none());
+    assert!(std::ptr::eq(pre[4].2.unwrap(), call));
+
+    let post = iter_postorder(&gc, stmt)
+        .map(|(node, depth, _)| (node.variant(), depth))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        post,
+        [
+            (NodeVariant::Identifier, 2),
+            (NodeVariant::NumericLiteral, 2),
+            (NodeVariant::NumericLiteral, 2),
+            (NodeVariant::CallExpression, 1),
+            (NodeVariant::ExpressionStatement, 0),
+        ]
+    );
+
+    let literals = iter_preorder(&gc, stmt)
+        .filter(|(node, _, _)| matches!(node, Node::NumericLiteral(_)))
+        .count();
+    assert_eq!(literals, 2);
+}
+
+#[test]
+fn test_budgeted() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let f = builders::ident(&gc, "f");
+    let one = builders::number(&gc, 1.0);
+    let two = builders::number(&gc, 2.0);
+    let call = builders::call(&gc, f, [one, two]);
+    let stmt = builders::expr_stmt(&gc, call);
+
+    let items = iter_preorder(&gc, stmt).budgeted(5).collect::<Vec<_>>();
+    assert_eq!(items.len(), 5);
+    assert!(items.iter().all(Result::is_ok));
+    let items = iter_preorder(&gc, stmt).budgeted(3).collect::<Vec<_>>();
+    assert_eq!(items.len(), 4);
+    assert!(matches!(items[3], Err(BudgetExceeded { budget: 3 })));
+    let count = || -> Result<usize, BudgetExceeded> {
+        let mut count = 0;
+        for item in iter_preorder(&gc, stmt).budgeted(2) {
+            item?;
+            count += 1;
+        }
+        Ok(count)
+    };
+    assert!(count().is_err());
+
+    assert_eq!(metrics_budgeted(&gc, stmt, 5).unwrap().node_count, 5);
+    assert!(metrics_budgeted(&gc, stmt, 4).is_err());
+    assert!(metrics_budgeted(&gc, stmt, 0).is_err());
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn test_node_at() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let metadata = |start: u32, end: u32| TemplateMetadata {
+        range: SourceRange {
+            file: SourceId(0),
+            start: SourceLoc {
+                line: 1,
+                col: start,
+            },
+            end: SourceLoc { line: 1, col: end },
+        },
+        ..Default::default()
+    };
+    let number = |value: f64, start: u32, end: u32| {
+        builder::NumericLiteral::build_template(
+            &gc,
+            template::NumericLiteral {
+                metadata: metadata(start, end),
+                value,
+            },
+        )
+    };
+    // f(1, 22);
+    let f = builder::Identifier::build_template(
+        &gc,
+        template::Identifier {
+            metadata: metadata(1, 1),
+            name: gc.atom("f"),
+            type_annotation: None,
+            optional: false,
+        },
+    );
+    let call = builder::CallExpression::build_template(
+        &gc,
+        template::CallExpression {
+            metadata: metadata(1, 8),
+            callee: f,
+            type_arguments: None,
+            arguments: NodeList::from_iter(&gc, [number(1.0, 3, 3), number(22.0, 6, 7)]),
+        },
+    );
+    let stmt = builder::ExpressionStatement::build_template(
+        &gc,
+        template::ExpressionStatement {
+            metadata: metadata(1, 9),
+            expression: call,
+            directive: None,
+        },
+    );
+
+    let at = |col| node_at(&gc, stmt, SourceLoc { line: 1, col });
+    let found = at(7).unwrap();
+    assert!(matches!(
+        found.node,
+        Node::NumericLiteral(NumericLiteral { value, .. }) if *value == 22.0
+    ));
+    assert_eq!(found.ancestors.len(), 2);
+    assert!(std::ptr::eq(found.ancestors[0], stmt));
+    assert!(std::ptr::eq(found.ancestors[1], call));
+
+    // Between arguments, the call is the innermost node.
+    assert!(std::ptr::eq(at(4).unwrap().node, call));
+    assert!(std::ptr::eq(at(9).unwrap().node, stmt));
+    assert!(at(10).is_none());
+    assert!(node_at(&gc, stmt, SourceLoc { line: 2, col: 1 }).is_none());
+}
+
+#[test]
+fn test_concat_programs() {
+    fn directive<'gc>(gc: &'gc GCLock, text: &str) -> &'gc Node<'gc> {
+        builder::ExpressionStatement::build_template(
+            gc,
+            template::ExpressionStatement {
+                metadata: Default::default(),
+                expression: builders::string(gc, text),
+                directive: Some(gc.atom_u16(text.encode_utf16().collect::<Vec<u16>>())),
+            },
+        )
+    }
+    fn stmt<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builders::expr_stmt(gc, builders::ident(gc, name))
+    }
+    fn program<'gc>(gc: &'gc GCLock, body: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::Program::build_template(
+            gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(gc, body),
+            },
+        )
+    }
+    /// Text of the directives and names of the identifier statements in `node`.
+    fn names(gc: &GCLock, node: &Node) -> Vec<String> {
+        node_cast!(Node::Program, node)
+            .body
+            .iter()
+            .map(|stmt| match stmt {
+                Node::ExpressionStatement(ExpressionStatement {
+                    directive: Some(text),
+                    ..
+                }) => String::from_utf16_lossy(gc.str_u16(*text)),
+                Node::ExpressionStatement(ExpressionStatement {
+                    expression: Node::Identifier(Identifier { name, .. }),
+                    ..
+                }) => gc.str(*name).to_string(),
+                _ => panic!("Unexpected statement"),
+            })
+            .collect()
+    }
+
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let a = program(
+        &gc,
+        vec![
+            directive(&gc, "use strict"),
+            stmt(&gc, "a1"),
+            stmt(&gc, "a2"),
+        ],
+    );
+    let b = program(
+        &gc,
+        vec![
+            directive(&gc, "use strict"),
+            directive(&gc, "use b"),
+            stmt(&gc, "b1"),
+        ],
+    );
+    let c = program(&gc, vec![stmt(&gc, "c1")]);
+    let concat = concat_programs(&gc, &[a, b, c]);
+    assert_eq!(
+        names(&gc, concat),
+        ["use strict", "use b", "a1", "a2", "b1", "c1"]
+    );
+
+    let prelude = inject_prelude(&gc, concat, [stmt(&gc, "p1"), stmt(&gc, "p2")]);
+    assert_eq!(
+        names(&gc, prelude),
+        ["use strict", "use b", "p1", "p2", "a1", "a2", "b1", "c1"]
+    );
+    // The original program is unchanged.
+    assert_eq!(names(&gc, concat).len(), 6);
+}
+
+#[test]
+fn test_store_node() {
+    let mut ctx = Context::new();
+
+    struct Foo<'gc> {
+        n: Option<&'gc Node<'gc>>,
+    }
+
+    impl<'gc> Foo<'gc> {
+        fn set_n(&mut self, node: &'gc Node<'gc>) {
+            self.n = Some(node);
+        }
+    }
+
+    impl<'gc> Visitor<'gc> for Foo<'gc> {
+        fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            self.set_n(node);
+            node.visit_children(gc, self)
+        }
+    }
+
+    {
+        let gc = GCLock::new(&mut ctx);
+        let mut pass = Foo { n: None };
+        let ast = NodeRc::from_node(
+            &gc,
+            builder::NumericLiteral::build_template(
+                &gc,
+                template::NumericLiteral {
+                    metadata: Default::default(),
+                    value: 1.0f64,
+                },
+            ),
+        );
+        ast.node(&gc).visit(&gc, &mut pass, None);
+        assert!(pass.n.is_some());
+    }
+}
+
+#[test]
+fn test_dump_locations() {
+    let mut ctx = Context::new();
+    let file = ctx.sm_mut().add_source(
+        "<input>",
+        juno_support::NullTerminatedBuf::from_str_copy("a;\r\nbé;"),
+    );
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        // `bé`, whose last character is two bytes long.
+        NodeRc::from_node(
+            &gc,
+            builder::Identifier::build_template(
+                &gc,
+                template::Identifier {
+                    metadata: TemplateMetadata {
+                        range: SourceRange {
+                            file,
+                            start: SourceLoc { line: 2, col: 1 },
+                            end: SourceLoc { line: 2, col: 2 },
+                        },
+                        ..Default::default()
+                    },
+                    name: gc.atom("bé"),
+                    type_annotation: None,
+                    optional: false,
+                },
+            ),
+        )
+    };
+    let mut dump = |locations, columns| {
+        let mut out = vec![];
+        dump_json_opt(
+            &mut out,
+            &mut ctx,
+            &root,
+            &DumpOpt {
+                pretty: Pretty::No,
+                locations,
+                columns,
+                ..DumpOpt::new()
+            },
+        )
+        .unwrap();
+        let json = String::from_utf8(out).unwrap();
+        json[json.find(r#""optional":false"#).unwrap()..]
+            .trim_end()
+            .to_string()
+    };
+    assert_eq!(
+        dump(LocationFormat::None, ColumnBase::Zero),
+        r#""optional":false}"#
+    );
+    assert_eq!(
+        dump(LocationFormat::Both, ColumnBase::Zero),
+        r#""optional":false,"loc":{"start":{"line":2,"column":0},"end":{"line":2,"column":3}},"range":[4,7]}"#
+    );
+    assert_eq!(
+        dump(LocationFormat::Loc, ColumnBase::One),
+        r#""optional":false,"loc":{"start":{"line":2,"column":1},"end":{"line":2,"column":4}}}"#
+    );
+    assert_eq!(
+        dump(LocationFormat::Range, ColumnBase::One),
+        r#""optional":false,"range":[4,7]}"#
+    );
+}
+
+#[test]
+fn test_text_ast() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    // x += "a\n" + f(1.5, y);
+    let text = r#"Program
+  body:
+    - ExpressionStatement
+      expression: AssignmentExpression
+        operator: +=
+        left: Identifier
+          name: "x"
+        right: BinaryExpression
+          left: StringLiteral
+            value: "a\né\uD800"
+          right: CallExpression
+            callee: Identifier
+              name: "f"
+            arguments:
+              - NumericLiteral
+                value: 1.5
+              - Identifier
+                name: "y"
+                optional: true
+          operator: +
+"#;
+    let program = parse_text(&gc, text).unwrap();
+    let call = iter_preorder(&gc, program)
+        .find_map(|(node, _, _)| match node {
+            Node::CallExpression(call) => Some(call),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(call.arguments.len(), 2);
+    let value = iter_preorder(&gc, program)
+        .find_map(|(node, _, _)| match node {
+            Node::StringLiteral(literal) => Some(literal),
+            _ => None,
+        })
+        .unwrap()
+        .value;
+    assert_eq!(gc.str_u16(value), &[0x61, 0x0A, 0xE9, 0xD800]);
+    assert_eq!(dump_text(&gc, program), text);
+
+    // Comments, blank lines and omitted optional fields.
+    let node = parse_text(&gc, "# A comment.\n\nReturnStatement\n").unwrap();
+    assert!(matches!(
+        node,
+        Node::ReturnStatement(ReturnStatement { argument: None, .. })
+    ));
+
+    let error = |text| parse_text(&gc, text).unwrap_err().to_string();
+    assert_eq!(error("Foo\n"), "line 1: unknown kind of node 'Foo'");
+    assert_eq!(error("Identifier\n"), "line 1: field 'name': missing");
+    assert_eq!(
+        error("Identifier\n  name: x\n"),
+        "line 2: field 'name': expected a quoted string, found 'x'"
+    );
+    assert_eq!(
+        error("Identifier\n  name: \"x\"\n  value: 1\n"),
+        "line 3: Identifier has no field 'value'"
+    );
+    assert_eq!(
+        error("ReturnStatement\n    argument: NullLiteral\n"),
+        "line 2: unexpected indentation"
+    );
+}
+
+#[test]
+fn test_snapshot() {
+    let mut ctx = Context::new();
+    ctx.enable_strict_mode();
+    let file = ctx
+        .sm_mut()
+        .add_source("a.js", NullTerminatedBuf::from_str_copy("x = 'a'"));
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        let text = "AssignmentExpression\n  operator: =\n  left: Identifier\n    name: \"x\"\n  right: StringLiteral\n    value: \"a\"\n";
+        let assign = parse_text(&gc, text).unwrap();
+        // The same node twice, to check that sharing is preserved.
+        let seq = builder::SequenceExpression::build_template(
+            &gc,
+            template::SequenceExpression {
+                metadata: SourceRange {
+                    file,
+                    start: SourceLoc { line: 1, col: 1 },
+                    end: SourceLoc { line: 1, col: 7 },
+                }
+                .into(),
+                expressions: NodeList::from_iter(&gc, [assign, assign]),
+            },
+        );
+        NodeRc::from_node(&gc, seq)
+    };
+    let mut saved = vec![];
+    save_context(&mut ctx, std::slice::from_ref(&root), &mut saved).unwrap();
+
+    let mut restored = Context::new();
+    restored
+        .sm_mut()
+        .add_source("other.js", NullTerminatedBuf::from_str_copy(""));
+    restored.atom("other");
+    let roots = load_context(&mut restored, saved.as_slice()).unwrap();
+    assert!(restored.strict_mode());
+    assert_eq!(restored.sm().num_sources(), 2);
+    assert_eq!(restored.sm().source_name(SourceId(1)), "a.js");
+    {
+        let gc = GCLock::new(&mut ctx);
+        let text = dump_text(&gc, root.node(&gc));
+        drop(gc);
+        let gc = GCLock::new(&mut restored);
+        let node = roots[0].node(&gc);
+        assert_eq!(dump_text(&gc, node), text);
+        assert_eq!(node.range().file, SourceId(1));
+        assert_eq!(node.range().end.col, 7);
+        match node {
+            Node::SequenceExpression(seq) => {
+                let elems = seq.expressions.iter().collect::<Vec<_>>();
+                assert!(std::ptr::eq(elems[0], elems[1]));
+            }
+            _ => panic!("expected a SequenceExpression"),
+        }
+    }
+    drop(root);
+    drop(roots);
+
+    let mut empty = Context::new();
+    assert!(matches!(
+        load_context(&mut empty, &b"JUNOAST\0\x03\0\0\0"[..]),
+        Err(SnapshotError::Version(3))
+    ));
+    assert!(matches!(
+        load_context(&mut empty, &b"#!/bin/sh"[..]),
+        Err(SnapshotError::BadMagic)
+    ));
+    assert!(matches!(
+        load_context(&mut empty, &saved[..saved.len() - 1]),
+        Err(SnapshotError::Io(_))
+    ));
+
+    let deep = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, nested_negations(&gc, 50))
+    };
+    let mut saved = vec![];
+    save_context(&mut ctx, std::slice::from_ref(&deep), &mut saved).unwrap();
+    let mut limited = Context::new();
+    limited.set_max_depth(Some(50));
+    assert!(matches!(
+        load_context(&mut limited, saved.as_slice()),
+        Err(SnapshotError::TooDeep(TooDeep { limit: 50, .. }))
+    ));
+    limited.set_max_depth(Some(51));
+    assert_eq!(load_context(&mut limited, saved.as_slice()).unwrap().len(), 1);
+}
+
+#[test]
+fn test_bound_names() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    // {a, b: [, c = 1, ...d], ...e}
+    let text = r#"ObjectPattern
+  properties:
+    - Property
+      key: Identifier
+        name: "a"
+      value: Identifier
+        name: "a"
+      kind: init
+      shorthand: true
+    - Property
+      key: Identifier
+        name: "b"
+      value: ArrayPattern
+        elements:
+          - Empty
+          - AssignmentPattern
+            left: Identifier
+              name: "c"
+            right: NumericLiteral
+              value: 1.0
+          - RestElement
+            argument: Identifier
+              name: "d"
+      kind: init
+    - RestElement
+      argument: Identifier
+        name: "e"
+"#;
+    let pattern = parse_text(&gc, text).unwrap();
+    let names = bound_names(pattern)
+        .into_iter()
+        .map(|ident| match ident {
+            Node::Identifier(Identifier { name, .. }) => gc.str(*name),
+            _ => panic!("not an identifier"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "c", "d", "e"]);
+
+    let mut details = vec![];
+    visit_bound_names(pattern, &mut |name| {
+        let name = name.unwrap();
+        details.push((name.default.is_some(), name.is_rest));
+    });
+    assert_eq!(
+        details,
+        [(false, false), (true, false), (false, true), (false, true)]
+    );
 }
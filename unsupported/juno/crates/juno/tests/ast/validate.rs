@@ -9,6 +9,8 @@ use juno::ast::*;
 use juno::hparser;
 use juno::hparser::ParserDialect;
 use juno::hparser::ParserFlags;
+use juno::hparser::SourceType;
+use juno_support::NullTerminatedBuf;
 
 fn validate_src_with_flags(
     flags: hparser::ParserFlags,
@@ -159,6 +161,91 @@ fn test_error() {
     }
 }
 
+#[test]
+fn test_validation_report() {
+    let mut ctx = Context::new();
+    let file = ctx
+        .sm_mut()
+        .add_source("a.js", NullTerminatedBuf::from_str_copy("1x"));
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(
+            &gc,
+            builder::Identifier::build_template(
+                &gc,
+                template::Identifier {
+                    metadata: TemplateMetadata {
+                        range: SourceRange {
+                            file,
+                            start: SourceLoc { line: 1, col: 1 },
+                            end: SourceLoc { line: 1, col: 2 },
+                        },
+                        ..Default::default()
+                    },
+                    name: gc.atom("1x"),
+                    type_annotation: None,
+                    optional: false,
+                },
+            ),
+        )
+    };
+    let mut out = vec![];
+    let count = dump_validation_report(
+        &mut out,
+        &mut ctx,
+        &ast,
+        &DumpOpt {
+            pretty: Pretty::No,
+            locations: LocationFormat::Range,
+            ..DumpOpt::new()
+        },
+    )
+    .unwrap();
+    assert_eq!(count, 1);
+    let json = String::from_utf8(out).unwrap();
+    assert!(json.starts_with(
+        r#"{"sources":[{"id":0,"name":"a.js","text":"1x"}],"diagnostics":[{"severity":"error","message":"#
+    ));
+    assert!(json.contains(
+        r#""nodeType":"Identifier","file":0,"range":[0,2]}],"ast":{"type":"Identifier","name":"1x""#
+    ));
+}
+
+#[test]
+fn test_cancelled() {
+    let mut ctx = Context::new();
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, builders::ident(&gc, "1x"))
+    };
+    let token = CancellationToken::new();
+    ctx.set_cancellation_token(Some(token.clone()));
+    assert!(validate_tree_pure(&mut ctx, &ast).is_err());
+    // Validation stops before reaching the invalid node.
+    token.cancel();
+    assert!(ctx.is_cancelled());
+    assert!(validate_tree_pure(&mut ctx, &ast).is_ok());
+}
+
+#[test]
+fn test_identifier() {
+    let mut ctx = Context::new();
+    let mut ident = |name: &str| {
+        let node = {
+            let gc = GCLock::new(&mut ctx);
+            NodeRc::from_node(&gc, builders::ident(&gc, name))
+        };
+        validate_tree_pure(&mut ctx, &node)
+    };
+    assert!(ident("foo").is_ok());
+    assert!(ident("café").is_ok());
+    // Reserved words are valid property names.
+    assert!(ident("if").is_ok());
+    assert!(ident("").is_err());
+    assert!(ident("1x").is_err());
+    assert!(ident("a-b").is_err());
+}
+
 #[test]
 fn test_literals() {
     validate_src("({});").unwrap();
@@ -193,6 +280,82 @@ fn test_import_export() {
     validate_src("export * as x from 'foo';").unwrap();
 }
 
+#[test]
+fn test_source_type() {
+    let flags = |source_type| ParserFlags {
+        source_type,
+        ..Default::default()
+    };
+    let mut ctx = Context::new();
+    assert!(
+        hparser::parse_with_flags(flags(SourceType::Script), "export var x;", &mut ctx).is_err()
+    );
+    let module =
+        hparser::parse_with_flags(flags(SourceType::Unambiguous), "import 'foo';", &mut ctx)
+            .unwrap();
+    let script =
+        hparser::parse_with_flags(flags(SourceType::Unambiguous), "var x;", &mut ctx).unwrap();
+    let forced = hparser::parse_with_flags(flags(SourceType::Module), "var x;", &mut ctx).unwrap();
+    let program = {
+        let gc = GCLock::new(&mut ctx);
+        assert!(matches!(module.node(&gc), Node::Module(_)));
+        assert!(matches!(script.node(&gc), Node::Program(_)));
+        assert!(matches!(forced.node(&gc), Node::Module(_)));
+        NodeRc::from_node(
+            &gc,
+            builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: node_cast!(Node::Module, module.node(&gc)).body,
+                },
+            ),
+        )
+    };
+    // Import declarations are only valid in a module.
+    assert!(validate_tree_pure(&mut ctx, &module).is_ok());
+    assert!(validate_tree_pure(&mut ctx, &program).is_err());
+}
+
+#[test]
+fn test_top_level_await() {
+    let flags = |source_type| ParserFlags {
+        source_type,
+        ..Default::default()
+    };
+    let mut ctx = Context::new();
+    assert!(hparser::parse_with_flags(flags(SourceType::Script), "await x;", &mut ctx).is_err());
+    let nested = hparser::parse_with_flags(
+        flags(SourceType::Script),
+        "async function f() { await x; }",
+        &mut ctx,
+    )
+    .unwrap();
+    let module = hparser::parse_with_flags(
+        flags(SourceType::Module),
+        "await x; for await (const y of z);",
+        &mut ctx,
+    )
+    .unwrap();
+    let program = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(
+            &gc,
+            builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: node_cast!(Node::Module, module.node(&gc)).body,
+                },
+            ),
+        )
+    };
+    assert!(validate_tree_pure(&mut ctx, &nested).is_ok());
+    assert!(validate_tree_pure(&mut ctx, &module).is_ok());
+    // Top-level await is only valid in a module.
+    assert!(validate_tree_pure(&mut ctx, &program).is_err());
+}
+
 #[test]
 fn test_jsx() {
     validate_src_jsx("<foo />").unwrap();
@@ -0,0 +1,124 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast::*;
+use juno::eval::ConstEvaluator;
+use juno::eval::JsValue;
+use juno::hparser;
+
+/// Evaluate the expression `src` and return the result as a string,
+/// or `None` if it isn't constant.
+fn eval_with(evaluator: &ConstEvaluator, src: &str) -> Option<String> {
+    let mut ctx = Context::new();
+    let ast = hparser::parse(&mut ctx, &format!("({});", src)).unwrap();
+    let gc = GCLock::new(&mut ctx);
+    let expr = match ast.node(&gc) {
+        Node::Program(Program { body, .. }) => match body.head() {
+            Some(Node::ExpressionStatement(ExpressionStatement { expression, .. })) => expression,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+    evaluator.eval(&gc, expr).map(|v| match v {
+        JsValue::String(_) => format!("'{}'", v),
+        _ => v.to_string(),
+    })
+}
+
+fn eval(src: &str) -> Option<String> {
+    eval_with(&ConstEvaluator::new(), src)
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(eval("1 + 2 * 3").as_deref(), Some("7"));
+    assert_eq!(eval("2 ** 10 - 1").as_deref(), Some("1023"));
+    assert_eq!(eval("-1 >>> 28").as_deref(), Some("15"));
+    assert_eq!(eval("~5 | 0x10").as_deref(), Some("-6"));
+    assert_eq!(eval("1 / 0").as_deref(), Some("Infinity"));
+    assert_eq!(eval("0 / 0").as_deref(), Some("NaN"));
+    assert_eq!(eval("'3' * '4'").as_deref(), Some("12"));
+}
+
+#[test]
+fn test_strings() {
+    assert_eq!(eval("'a' + 1 + 2").as_deref(), Some("'a12'"));
+    assert_eq!(eval("`x${1 + 1}y`").as_deref(), Some("'x2y'"));
+    assert_eq!(eval("'abc'.length").as_deref(), Some("3"));
+    assert_eq!(eval("'abc'[1]").as_deref(), Some("'b'"));
+    assert_eq!(eval("typeof 'abc'").as_deref(), Some("'string'"));
+    assert_eq!(eval("[1, [2, 3]] + ''").as_deref(), Some("'1,2,3'"));
+}
+
+#[test]
+fn test_logical() {
+    assert_eq!(eval("true ? 1 : 2").as_deref(), Some("1"));
+    assert_eq!(eval("0 || 'x'").as_deref(), Some("'x'"));
+    assert_eq!(eval("null ?? 3").as_deref(), Some("3"));
+    assert_eq!(eval("false && foo()").as_deref(), Some("false"));
+    assert_eq!(eval("1 == '1'").as_deref(), Some("true"));
+    assert_eq!(eval("null === undefined").as_deref(), Some("false"));
+}
+
+#[test]
+fn test_literals() {
+    assert_eq!(eval("[1, ...[2, 3]].length").as_deref(), Some("3"));
+    assert_eq!(
+        eval("({a: 1, ['b']: 2, ...{a: 3}}).a").as_deref(),
+        Some("3")
+    );
+    assert_eq!(eval("({a: 1}).b"), None);
+}
+
+#[test]
+fn test_not_constant() {
+    assert_eq!(eval("foo"), None);
+    assert_eq!(eval("foo()"), None);
+    assert_eq!(eval("x = 1"), None);
+    assert_eq!(eval("delete a.b"), None);
+    assert_eq!(eval("true || foo()").as_deref(), Some("true"));
+}
+
+#[test]
+fn test_defines() {
+    let mut evaluator = ConstEvaluator::new();
+    evaluator.define("__DEV__", JsValue::Boolean(false));
+    evaluator.define("process.env.NODE_ENV", JsValue::string("production"));
+    assert_eq!(
+        eval_with(&evaluator, "__DEV__ ? 1 : 2").as_deref(),
+        Some("2")
+    );
+    assert_eq!(
+        eval_with(&evaluator, "process.env.NODE_ENV === 'production'").as_deref(),
+        Some("true")
+    );
+    assert_eq!(eval_with(&evaluator, "process.env.OTHER"), None);
+}
+
+#[test]
+fn test_to_node() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    let range = TemplateMetadata::default().range;
+    let evaluator = ConstEvaluator::new();
+    for value in [
+        JsValue::Undefined,
+        JsValue::Number(-2.5),
+        JsValue::string("s"),
+        JsValue::Array(vec![JsValue::Null, JsValue::Boolean(true)].into()),
+    ] {
+        let node = value.to_node(&gc, range).unwrap();
+        let result = evaluator.eval(&gc, node).unwrap();
+        assert!(
+            result.to_string() == value.to_string(),
+            "{} != {}",
+            result,
+            value
+        );
+    }
+    assert!(JsValue::Number(f64::NAN).to_node(&gc, range).is_none());
+}
@@ -0,0 +1,192 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast::*;
+use juno::gen_dts;
+use juno::hparser;
+
+fn test_dts(src: &str, expected: &str) {
+    let mut ctx = Context::new();
+    let ast = hparser::parse_with_flags(
+        hparser::ParserFlags {
+            strict_mode: true,
+            enable_jsx: false,
+            dialect: hparser::ParserDialect::Flow,
+            store_doc_block: false,
+            annex_b: false,
+            html_like_comments: false,
+            record_recovery: false,
+            error_recovery: false,
+            error_limit: 0,
+            max_depth: 0,
+            source_type: hparser::SourceType::Module,
+        },
+        src,
+        &mut ctx,
+    )
+    .unwrap();
+    let mut out: Vec<u8> = vec![];
+    gen_dts::generate(&mut out, &mut ctx, &ast).unwrap();
+    let dts = String::from_utf8(out).expect("Invalid UTF-8 output in test");
+    assert_eq!(dts, expected, "Source:\n{}", src);
+}
+
+#[test]
+fn test_functions() {
+    test_dts(
+        "export function f(x: number, y?: string): void {}",
+        "export declare function f(x: number, y?: string): void;\n",
+    );
+    test_dts(
+        "function g<T: {}>(t: T, n = 1, ...rest: Array<T>) { return t; }",
+        "declare function g<T extends {}>(t: T, n?: number, ...rest: Array<T>): any;\n",
+    );
+    test_dts(
+        "export async function h() {}",
+        "export declare function h(): Promise<any>;\n",
+    );
+    test_dts(
+        "export const k = (a: number, b = 'x'): number => a;",
+        "export declare const k: (a: number, b?: string) => number;\n",
+    );
+}
+
+#[test]
+fn test_variables() {
+    test_dts(
+        "export const x = 1, s = 'a', b: ?boolean = null; let r = /a/; var u = f();",
+        "export declare const x: number;\n\
+        export declare const s: string;\n\
+        export declare const b: boolean | null | undefined;\n\
+        declare let r: RegExp;\n\
+        declare var u: any;\n",
+    );
+    test_dts(
+        "export const {a, b: c}: {a: number, b: string} = o;",
+        "export declare const a: any;\nexport declare const c: any;\n",
+    );
+}
+
+#[test]
+fn test_types() {
+    test_dts(
+        "export type T = ?{+a: string, b?: Array<number>, [string]: mixed};",
+        "export type T = { readonly a: string; b?: Array<number>; [key: string]: unknown } \
+        | null | undefined;\n",
+    );
+    test_dts(
+        "export type U = $ReadOnly<{a: $Keys<O>, ...B}>; type V = $Values<typeof o>;",
+        "export type U = Readonly<B & { a: keyof O }>;\ntype V = typeof o[keyof typeof o];\n",
+    );
+    test_dts(
+        "export type F = (string, cb?: () => void) => number;",
+        "export type F = (arg0: string, cb?: () => void) => number;\n",
+    );
+    test_dts(
+        "type A = Array<string | number>; type B = (string | number)[]; type C = (() => void)[];",
+        "type A = Array<string | number>;\ntype B = (string | number)[];\ntype C = (() => void)[];\n",
+    );
+    test_dts(
+        "type X = [a: number, b?: string]; type Y = O['k']; type Z = O?.['k'];",
+        "type X = [a: number, b?: string];\ntype Y = O['k'];\ntype Z = NonNullable<O>['k'];\n",
+    );
+    test_dts(
+        "export opaque type ID: string = string; opaque type Secret = number;",
+        "export type ID = string;\ntype Secret = unknown;\n",
+    );
+    test_dts(
+        "export interface I<T> extends J { m(T): void; get g(): number; }",
+        "export interface I<T> extends J {\n  m(arg0: T): void;\n  readonly g: number;\n}\n",
+    );
+}
+
+#[test]
+fn test_classes() {
+    test_dts(
+        "export default class A<T> extends B<T> implements I {
+          +x: number;
+          #p: string;
+          static s = 'a';
+          constructor(a: number) {}
+          get g(): number { return 1; }
+          m<U>(u: U): T { return this.x; }
+          #q() {}
+        }",
+        "export default class A<T> extends B<T> implements I {\n  \
+        readonly x: number;\n  \
+        static s: string;\n  \
+        constructor(a: number);\n  \
+        get g(): number;\n  \
+        m<U>(u: U): T;\n\
+        }\n",
+    );
+    test_dts(
+        "class C extends React.Component<Props> {}",
+        "declare class C extends React.Component<Props> {}\n",
+    );
+}
+
+#[test]
+fn test_modules() {
+    test_dts(
+        "import type {T} from './t'; import D, {a as b, type C} from './d'; \
+        import * as N from './n';",
+        "import type { T } from \"./t\";\n\
+        import D from \"./d\";\n\
+        import { a as b, type C } from \"./d\";\n\
+        import * as N from \"./n\";\n",
+    );
+    test_dts(
+        "const x = 1; console.log(x); export {x as y}; export type {T} from './t'; \
+        export * from './z';",
+        "declare const x: number;\n\
+        export { x as y };\n\
+        export type { T } from \"./t\";\n\
+        export * from \"./z\";\n",
+    );
+    test_dts(
+        "export default 42;",
+        "declare const _default: number;\nexport default _default;\n",
+    );
+}
+
+#[test]
+fn test_declare() {
+    test_dts(
+        "declare export function f(number): string; declare export var v: ?T; \
+        declare class K { static create(): K; x: number }",
+        "export declare function f(arg0: number): string;\n\
+        export declare var v: T | null | undefined;\n\
+        declare class K {\n  static create(): K;\n  x: number;\n}\n",
+    );
+}
+
+#[test]
+fn test_jsdoc() {
+    test_dts(
+        "/**
+          * @param {number} a
+          * @param {string=} b
+          * @param {...number} rest
+          * @returns {?string}
+          */
+        export function f(a, b, ...rest) {}
+        /** @type {Array.<string>} */
+        export const names = [];
+        /** @param {number} x */
+        export const g = (x) => x;
+        class C {
+          /** @type {!Object} */
+          o = {};
+        }",
+        "export declare function f(a: number, b?: string, ...rest: Array<number>): \
+        string | null;\n\
+        export declare const names: Array<string>;\n\
+        export declare const g: (x: number) => any;\n\
+        declare class C {\n  o: Object;\n}\n",
+    );
+}
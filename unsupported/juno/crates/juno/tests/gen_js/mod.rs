@@ -8,7 +8,7 @@
 use juno::ast::*;
 use juno::gen_js;
 use juno::hparser;
-use juno::sourcemap::merge_sourcemaps;
+use juno::sourcemap::compose;
 
 fn do_gen<'ast>(ctx: &mut Context<'ast>, node: &NodeRc, pretty: gen_js::Pretty) -> String {
     use juno::gen_js::*;
@@ -67,6 +67,13 @@ fn test_roundtrip_flow(src1: &str) {
             enable_jsx: false,
             dialect: hparser::ParserDialect::Flow,
             store_doc_block: false,
+            annex_b: true,
+            html_like_comments: false,
+            record_recovery: false,
+            error_recovery: false,
+            error_limit: 0,
+            max_depth: 0,
+            source_type: hparser::SourceType::Unambiguous,
         },
         src1,
     );
@@ -79,6 +86,13 @@ fn test_roundtrip_jsx(src1: &str) {
             enable_jsx: true,
             dialect: hparser::ParserDialect::JavaScript,
             store_doc_block: false,
+            annex_b: true,
+            html_like_comments: false,
+            record_recovery: false,
+            error_recovery: false,
+            error_limit: 0,
+            max_depth: 0,
+            source_type: hparser::SourceType::Unambiguous,
         },
         src1,
     )
@@ -230,6 +244,7 @@ fn test_calls() {
     test_roundtrip("new(fn(foo).bar)()");
     test_roundtrip("new(fn(foo).bar)(c)");
     test_roundtrip("import('foo')");
+    test_roundtrip("import('foo', {with: {type: 'json'}})");
 }
 
 #[test]
@@ -469,6 +484,16 @@ fn test_classes() {
             ;
         })",
     );
+    test_roundtrip(
+        "class C {
+            static {
+                var x = this.y;
+            }
+            static accessor y = 1;
+            accessor #z;
+            accessor [w];
+        }",
+    );
 }
 
 #[test]
@@ -480,6 +505,8 @@ fn test_import() {
     test_roundtrip("import {a, b, c} from 'foo'");
     test_roundtrip("import 'foo';");
     test_roundtrip("import 'foo' assert {kind: 'json'};");
+    test_roundtrip("import 'foo' with {type: 'json'};");
+    test_roundtrip("import styles from 'foo' with {'type': 'css'};");
     test_roundtrip(
         "
         import 'foo';
@@ -495,6 +522,7 @@ fn test_export() {
     test_roundtrip("export default function foo() {}");
     test_roundtrip("export {x as y};");
     test_roundtrip("export * from 'foo';");
+    test_roundtrip("export * as foo from 'foo';");
     test_roundtrip_flow("export type Foo = number;");
     test_roundtrip_flow("export type { x as y } from 'foo';");
 }
@@ -736,10 +764,379 @@ fn test_sourcemap_merged() {
     let output = String::from_utf8(out).expect("Invalid UTF-8 output in test");
     assert_eq!(output, "function foo() {\n  1;\n}\n",);
 
-    let merged = merge_sourcemaps(&input_map, &output_map);
+    let merged = compose(&input_map, &output_map);
 
     // Source maps are 0-indexed, so use those for testing purposes.
     let input_token: sourcemap::Token = merged.lookup_token(1, 2).unwrap();
     assert_eq!(input_token.get_source().unwrap(), "test.ts");
     assert_eq!(input_token.get_src(), (1, 17));
 }
+
+#[test]
+fn test_sourcemap_url() {
+    use juno::gen_js::*;
+    let mut ctx = Context::new();
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, builders::expr_stmt(&gc, builders::ident(&gc, "x")))
+    };
+    let mut gen = |source_map| {
+        let mut out: Vec<u8> = vec![];
+        generate(
+            &mut out,
+            &mut ctx,
+            &ast,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                source_map,
+                source_map_url: Some("out.js.map".to_string()),
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).expect("Invalid UTF-8 output in test")
+    };
+    assert_eq!(gen(SourceMapKind::None), "x\n");
+    assert_eq!(
+        gen(SourceMapKind::File),
+        "x\n//# sourceMappingURL=out.js.map\n"
+    );
+    let inline = gen(SourceMapKind::Inline);
+    assert!(
+        inline.starts_with("x\n//# sourceMappingURL=data:application/json;charset=utf-8;base64,")
+    );
+    assert_eq!(gen(SourceMapKind::Both), inline);
+}
+
+#[test]
+fn test_identifier_escape() {
+    let mut ctx = Context::new();
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(
+            &gc,
+            builders::expr_stmt(&gc, builders::ident(&gc, "caf\u{e9}\u{1d49c}")),
+        )
+    };
+    assert_eq!(
+        do_gen(&mut ctx, &ast, gen_js::Pretty::No),
+        "caf\\u00e9\\u{1d49c}\n"
+    );
+}
+
+#[test]
+fn test_inline_script_safe() {
+    let mut ctx = Context::new();
+    // "</script><!--"; a-- > b; a < !--b; `</Script>\u2028\<!--`; /<!--/;
+    let text = r#"Program
+  body:
+    - ExpressionStatement
+      expression: StringLiteral
+        value: "</script><!--"
+    - ExpressionStatement
+      expression: BinaryExpression
+        left: UpdateExpression
+          operator: --
+          argument: Identifier
+            name: "a"
+        right: Identifier
+          name: "b"
+        operator: >
+    - ExpressionStatement
+      expression: BinaryExpression
+        left: Identifier
+          name: "a"
+        right: UnaryExpression
+          operator: !
+          argument: UpdateExpression
+            operator: --
+            argument: Identifier
+              name: "b"
+            prefix: true
+          prefix: true
+        operator: <
+    - ExpressionStatement
+      expression: TemplateLiteral
+        quasis:
+          - TemplateElement
+            tail: true
+            raw: "</Script>\u2028\\<!--"
+    - ExpressionStatement
+      expression: RegExpLiteral
+        pattern: "<!--"
+        flags: ""
+"#;
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, parse_text(&gc, text).unwrap())
+    };
+    let mut gen = |inline_script_safe| {
+        let mut out: Vec<u8> = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &ast,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                inline_script_safe,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).expect("Invalid UTF-8 output in test")
+    };
+    assert_eq!(
+        gen(false),
+        "'</script><!--';a-->b;a<!--b;`</Script>\u{2028}\\<!--`;/<!--/;\n"
+    );
+    assert_eq!(
+        gen(true),
+        "'\\u003c/script>\\u003c!--';a-- >b;a< !--b;`\\u003c/Script>\\u2028\\u003c!--`;/\\u003c!--/;\n"
+    );
+}
+
+#[test]
+fn test_ascii_only() {
+    let mut ctx = Context::new();
+    // café; 'é😀\u2028'; `é\é`; /😀/u;
+    let text = r#"Program
+  body:
+    - ExpressionStatement
+      expression: Identifier
+        name: "café"
+    - ExpressionStatement
+      expression: StringLiteral
+        value: "é😀\u2028"
+    - ExpressionStatement
+      expression: TemplateLiteral
+        quasis:
+          - TemplateElement
+            tail: true
+            raw: "é\\é"
+    - ExpressionStatement
+      expression: RegExpLiteral
+        pattern: "😀"
+        flags: "u"
+"#;
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, parse_text(&gc, text).unwrap())
+    };
+    let mut gen = |ascii_only| {
+        let mut out: Vec<u8> = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &ast,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ascii_only,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).expect("Invalid UTF-8 output in test")
+    };
+    assert_eq!(
+        gen(false),
+        "caf\\u00e9;'\\u00e9\\ud83d\\ude00\\u2028';`é\\é`;/😀/u;\n"
+    );
+    assert_eq!(
+        gen(true),
+        "caf\\u00e9;'\\u00e9\\ud83d\\ude00\\u2028';`\\u00e9\\u00e9`;/\\ud83d\\ude00/u;\n"
+    );
+}
+
+#[test]
+fn test_compress() {
+    let mut ctx = Context::new();
+    // var a = 1000, b = 0.5; var c = true; let d = 0.0012; let e = 1e21;
+    // function f() { g(); return 1099511627775; }
+    // true ** 2; 255..toString();
+    let text = r#"Program
+  body:
+    - VariableDeclaration
+      kind: var
+      declarations:
+        - VariableDeclarator
+          init: NumericLiteral
+            value: 1000.0
+          id: Identifier
+            name: "a"
+        - VariableDeclarator
+          init: NumericLiteral
+            value: 0.5
+          id: Identifier
+            name: "b"
+    - VariableDeclaration
+      kind: var
+      declarations:
+        - VariableDeclarator
+          init: BooleanLiteral
+            value: true
+          id: Identifier
+            name: "c"
+    - VariableDeclaration
+      kind: let
+      declarations:
+        - VariableDeclarator
+          init: NumericLiteral
+            value: 0.0012
+          id: Identifier
+            name: "d"
+    - VariableDeclaration
+      kind: let
+      declarations:
+        - VariableDeclarator
+          init: NumericLiteral
+            value: 1e21
+          id: Identifier
+            name: "e"
+    - FunctionDeclaration
+      id: Identifier
+        name: "f"
+      body: BlockStatement
+        body:
+          - ExpressionStatement
+            expression: CallExpression
+              callee: Identifier
+                name: "g"
+          - ReturnStatement
+            argument: NumericLiteral
+              value: 1099511627775.0
+    - ExpressionStatement
+      expression: BinaryExpression
+        left: BooleanLiteral
+          value: true
+        right: NumericLiteral
+          value: 2.0
+        operator: **
+    - ExpressionStatement
+      expression: CallExpression
+        callee: MemberExpression
+          object: NumericLiteral
+            value: 255.0
+          property: Identifier
+            name: "toString"
+"#;
+    let ast = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, parse_text(&gc, text).unwrap())
+    };
+    let mut gen = |compress, compress_booleans| {
+        let mut out: Vec<u8> = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &ast,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                compress,
+                compress_booleans,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).expect("Invalid UTF-8 output in test")
+    };
+    assert_eq!(
+        gen(false, false),
+        "var a=1000,b=0.5;var c=true;let d=0.0012;let e=1e+21;\
+        function f(){g();return 1099511627775;}true**2;255..toString();\n"
+    );
+    assert_eq!(
+        gen(true, false),
+        "var a=1e3,b=.5,c=true;let d=.0012,e=1e21;\
+        function f(){g();return 0xffffffffff}true**2;255..toString()\n"
+    );
+    assert_eq!(
+        gen(true, true),
+        "var a=1e3,b=.5,c=!0;let d=.0012,e=1e21;\
+        function f(){g();return 0xffffffffff}true**2;255..toString()\n"
+    );
+}
+
+#[test]
+fn test_compress_roundtrip() {
+    let mut ctx = Context::new();
+    for src in ["{a:;}", "{b:c:;}", "if(x){;}", "function f(){d:;}"] {
+        let ast1 = hparser::parse(&mut ctx, src).unwrap();
+        let mut out: Vec<u8> = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &ast1,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                compress: true,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        let src2 = String::from_utf8(out).expect("Invalid UTF-8 output in test");
+        assert_eq!(src2.trim(), src);
+        let ast2 = hparser::parse(&mut ctx, &src2).unwrap();
+        let mut dump1: Vec<u8> = vec![];
+        dump_json(&mut dump1, &mut ctx, &ast1, juno::ast::Pretty::No).unwrap();
+        let mut dump2: Vec<u8> = vec![];
+        dump_json(&mut dump2, &mut ctx, &ast2, juno::ast::Pretty::No).unwrap();
+        assert_eq!(dump1, dump2, "AST mismatch for {}", src);
+    }
+}
+
+#[test]
+fn test_emit_verified() {
+    let mut ctx = Context::new();
+    let mut emit = |text: &str, compress: bool| {
+        let ast = {
+            let gc = GCLock::new(&mut ctx);
+            NodeRc::from_node(&gc, parse_text(&gc, text).unwrap())
+        };
+        let mut out: Vec<u8> = vec![];
+        gen_js::emit_verified(
+            &mut out,
+            &mut ctx,
+            &ast,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                compress,
+                ..gen_js::Opt::new()
+            },
+            Default::default(),
+        )
+        .map(|_| String::from_utf8(out).expect("Invalid UTF-8 output in test"))
+    };
+
+    // x + 1;
+    let ok = r#"Program
+  body:
+    - ExpressionStatement
+      expression: BinaryExpression
+        left: Identifier
+          name: "x"
+        right: NumericLiteral
+          value: 1.0
+        operator: +
+"#;
+    assert_eq!(emit(ok, false).unwrap(), "x+1;\n");
+    // Compressed JS doesn't parse to the same AST.
+    assert!(matches!(
+        emit(ok, true),
+        Err(gen_js::VerifyError::UnsupportedOption("compress"))
+    ));
+
+    // A negative literal is parsed back as a unary expression.
+    let negative = r#"Program
+  body:
+    - ExpressionStatement
+      expression: NumericLiteral
+        value: -1.0
+"#;
+    match emit(negative, false) {
+        Err(gen_js::VerifyError::Mismatch { line, output }) => {
+            assert_eq!(line, 4);
+            assert_eq!(output, "-1;\n");
+        }
+        result => panic!("Expected a mismatch, found {:?}", result),
+    }
+}
@@ -8,4 +8,7 @@
 #[macro_use]
 mod ast;
 
+mod eval;
+mod gen_dts;
 mod gen_js;
+mod sema;
@@ -0,0 +1,1167 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast::*;
+use juno::resolve_dependency;
+use juno::sema;
+use juno_support::NullTerminatedBuf;
+
+#[test]
+fn test_jump_targets() {
+
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+    let gc = GCLock::new(&mut ctx);
+
+    // a: while (x) {
+    //   break;
+    //   continue a;
+    //   switch (x) { default: break; }
+    //   break a;
+    // }
+    let break_loop = builder::BreakStatement::build_template(
+        &gc,
+        template::BreakStatement {
+            metadata: Default::default(),
+            label: None,
+        },
+    );
+    let continue_label = builder::ContinueStatement::build_template(
+        &gc,
+        template::ContinueStatement {
+            metadata: Default::default(),
+            label: Some(builders::ident(&gc, "a")),
+        },
+    );
+    let break_switch = builder::BreakStatement::build_template(
+        &gc,
+        template::BreakStatement {
+            metadata: Default::default(),
+            label: None,
+        },
+    );
+    let switch = builder::SwitchStatement::build_template(
+        &gc,
+        template::SwitchStatement {
+            metadata: Default::default(),
+            discriminant: builders::ident(&gc, "x"),
+            cases: NodeList::from_iter(
+                &gc,
+                [builder::SwitchCase::build_template(
+                    &gc,
+                    template::SwitchCase {
+                        metadata: Default::default(),
+                        test: None,
+                        consequent: NodeList::from_iter(&gc, [break_switch]),
+                    },
+                )],
+            ),
+        },
+    );
+    let break_label = builder::BreakStatement::build_template(
+        &gc,
+        template::BreakStatement {
+            metadata: Default::default(),
+            label: Some(builders::ident(&gc, "a")),
+        },
+    );
+    let while_stmt = builder::WhileStatement::build_template(
+        &gc,
+        template::WhileStatement {
+            metadata: Default::default(),
+            body: builders::block(&gc, [break_loop, continue_label, switch, break_label]),
+            test: builders::ident(&gc, "x"),
+        },
+    );
+    let labeled = builder::LabeledStatement::build_template(
+        &gc,
+        template::LabeledStatement {
+            metadata: Default::default(),
+            label: builders::ident(&gc, "a"),
+            body: while_stmt,
+        },
+    );
+    let program = builder::Program::build_template(
+        &gc,
+        template::Program {
+            metadata: Default::default(),
+            body: NodeList::from_iter(&gc, [labeled]),
+        },
+    );
+
+    let sem = sema::resolve_program(&gc, file_id, program);
+    assert_eq!(gc.sm().num_errors(), 0);
+    let target = |node| {
+        sem.jump_target(&NodeRc::from_node(&gc, node))
+            .map(|target| target.node(&gc))
+    };
+    assert!(std::ptr::eq(target(break_loop).unwrap(), while_stmt));
+    assert!(std::ptr::eq(target(continue_label).unwrap(), while_stmt));
+    assert!(std::ptr::eq(target(break_switch).unwrap(), switch));
+    assert!(std::ptr::eq(target(break_label).unwrap(), labeled));
+    assert!(target(switch).is_none());
+    assert_eq!(sem.all_jump_targets().len(), 4);
+}
+
+#[test]
+fn test_annotations() {
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+    let (program, x, sem) = {
+        let gc = GCLock::new(&mut ctx);
+        // var x; f(x, 1 + 2);
+        let x = builders::ident(&gc, "x");
+        let decl = builder::VariableDeclaration::build_template(
+            &gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind: VariableDeclarationKind::Var,
+                declarations: NodeList::from_iter(
+                    &gc,
+                    [builder::VariableDeclarator::build_template(
+                        &gc,
+                        template::VariableDeclarator {
+                            metadata: Default::default(),
+                            init: None,
+                            id: x,
+                        },
+                    )],
+                ),
+            },
+        );
+        let sum = builder::BinaryExpression::build_template(
+            &gc,
+            template::BinaryExpression {
+                metadata: Default::default(),
+                left: builders::number(&gc, 1.0),
+                right: builders::number(&gc, 2.0),
+                operator: BinaryExpressionOperator::Plus,
+            },
+        );
+        let call = builders::call(
+            &gc,
+            builders::ident(&gc, "f"),
+            vec![builders::ident(&gc, "x"), sum],
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [decl, builders::expr_stmt(&gc, call)]),
+            },
+        );
+        let sem = sema::resolve_program(&gc, file_id, program);
+        assert_eq!(gc.sm().num_errors(), 0);
+        (
+            NodeRc::from_node(&gc, program),
+            NodeRc::from_node(&gc, x),
+            sem,
+        )
+    };
+
+    let constants = juno::eval::ConstEvaluator::new();
+    let annotator = sema::SemAnnotator {
+        sem: &sem,
+        constants: Some(&constants),
+    };
+    let mut out = vec![];
+    dump_json_opt(
+        &mut out,
+        &mut ctx,
+        &program,
+        &DumpOpt {
+            pretty: Pretty::No,
+            annotator: Some(&annotator),
+            ..DumpOpt::new()
+        },
+    )
+    .unwrap();
+    let json = String::from_utf8(out).unwrap();
+    let decl_id = match sem.ident_decl(&x) {
+        Some(sema::Resolution::Decl(id)) => id,
+        _ => panic!("x is not resolved"),
+    };
+    let x = format!(r#""juno":{{"declId":{}}}"#, decl_id);
+    assert_eq!(json.matches(&x).count(), 2, "{}", json);
+    assert!(
+        json.contains(r#""operator":"+","juno":{"constant":3}"#),
+        "{}",
+        json
+    );
+    assert!(
+        json.contains(r#""juno":{"scopeId":0,"strict":false}"#),
+        "{}",
+        json
+    );
+    // Literals are not annotated.
+    assert_eq!(json.matches(r#""constant""#).count(), 1, "{}", json);
+}
+
+#[test]
+fn test_assignment_targets() {
+    type Build = for<'gc> fn(&'gc GCLock<'_, '_>, SourceRange) -> &'gc Node<'gc>;
+
+    /// Resolve a program consisting of the statement returned by `build` and return the
+    /// number of errors. `build` receives a valid range to attach to nodes which may be
+    /// reported.
+    fn errors(strict: bool, build: Build) -> usize {
+        let mut ctx = Context::new();
+        if strict {
+            ctx.enable_strict_mode();
+        }
+        let file_id = ctx
+            .sm_mut()
+            .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+        let gc = GCLock::new(&mut ctx);
+        let range = SourceRange::from_loc(file_id, SourceLoc { line: 1, col: 1 });
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [build(&gc, range)]),
+            },
+        );
+        sema::resolve_program(&gc, file_id, program);
+        gc.sm().num_errors()
+    }
+    fn at<'gc>(range: SourceRange) -> TemplateMetadata<'gc> {
+        TemplateMetadata {
+            range,
+            ..Default::default()
+        }
+    }
+    fn ident<'gc>(gc: &'gc GCLock, range: SourceRange, name: &str) -> &'gc Node<'gc> {
+        builder::Identifier::build_template(
+            gc,
+            template::Identifier {
+                metadata: at(range),
+                name: gc.atom(name),
+                type_annotation: None,
+                optional: false,
+            },
+        )
+    }
+    fn array<'gc>(
+        gc: &'gc GCLock,
+        range: SourceRange,
+        elements: &[&'gc Node<'gc>],
+    ) -> &'gc Node<'gc> {
+        builder::ArrayPattern::build_template(
+            gc,
+            template::ArrayPattern {
+                metadata: at(range),
+                elements: NodeList::from_iter(gc, elements.iter().copied()),
+                type_annotation: None,
+            },
+        )
+    }
+    fn rest<'gc>(gc: &'gc GCLock, argument: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::RestElement::build_template(
+            gc,
+            template::RestElement {
+                metadata: Default::default(),
+                argument,
+            },
+        )
+    }
+    fn assign<'gc>(
+        gc: &'gc GCLock,
+        operator: AssignmentExpressionOperator,
+        left: &'gc Node<'gc>,
+    ) -> &'gc Node<'gc> {
+        builders::expr_stmt(
+            gc,
+            builder::AssignmentExpression::build_template(
+                gc,
+                template::AssignmentExpression {
+                    metadata: Default::default(),
+                    operator,
+                    left,
+                    right: builders::number(gc, 1.0),
+                },
+            ),
+        )
+    }
+
+    // [a, ...b] = 1
+    fn array_rest<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        let pattern = array(gc, r, &[ident(gc, r, "a"), rest(gc, ident(gc, r, "b"))]);
+        assign(gc, AssignmentExpressionOperator::Assign, pattern)
+    }
+    assert_eq!(errors(true, array_rest), 0);
+
+    // eval = 1
+    fn assign_eval<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        assign(
+            gc,
+            AssignmentExpressionOperator::Assign,
+            ident(gc, r, "eval"),
+        )
+    }
+    assert_eq!(errors(false, assign_eval), 0);
+    assert_eq!(errors(true, assign_eval), 1);
+
+    // [arguments] = 1
+    fn nested_arguments<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        let pattern = array(gc, r, &[ident(gc, r, "arguments")]);
+        assign(gc, AssignmentExpressionOperator::Assign, pattern)
+    }
+    assert_eq!(errors(true, nested_arguments), 1);
+
+    // a += 1
+    fn compound<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        assign(
+            gc,
+            AssignmentExpressionOperator::PlusAssign,
+            ident(gc, r, "a"),
+        )
+    }
+    assert_eq!(errors(true, compound), 0);
+
+    // [a] += 1
+    fn compound_pattern<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        let pattern = array(gc, r, &[ident(gc, r, "a")]);
+        assign(gc, AssignmentExpressionOperator::PlusAssign, pattern)
+    }
+    assert_eq!(errors(false, compound_pattern), 1);
+
+    // ({...[a]} = 1)
+    fn object_rest_pattern<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        let pattern = builder::ObjectPattern::build_template(
+            gc,
+            template::ObjectPattern {
+                metadata: at(r),
+                properties: NodeList::from_iter(gc, [rest(gc, array(gc, r, &[ident(gc, r, "a")]))]),
+                type_annotation: None,
+            },
+        );
+        assign(gc, AssignmentExpressionOperator::Assign, pattern)
+    }
+    assert_eq!(errors(false, object_rest_pattern), 1);
+
+    // arguments++
+    fn update_arguments<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builders::expr_stmt(
+            gc,
+            builder::UpdateExpression::build_template(
+                gc,
+                template::UpdateExpression {
+                    metadata: Default::default(),
+                    operator: UpdateExpressionOperator::Increment,
+                    argument: ident(gc, r, "arguments"),
+                    prefix: false,
+                },
+            ),
+        )
+    }
+    assert_eq!(errors(true, update_arguments), 1);
+
+    // for (eval of x);
+    fn for_of_eval<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::ForOfStatement::build_template(
+            gc,
+            template::ForOfStatement {
+                metadata: Default::default(),
+                left: ident(gc, r, "eval"),
+                right: ident(gc, r, "x"),
+                body: builder::EmptyStatement::build_template(
+                    gc,
+                    template::EmptyStatement {
+                        metadata: Default::default(),
+                    },
+                ),
+                is_await: false,
+            },
+        )
+    }
+    assert_eq!(errors(true, for_of_eval), 1);
+}
+
+#[test]
+fn test_hoisted_declarations() {
+    let mut ctx = Context::new();
+    let gc = GCLock::new(&mut ctx);
+    // var a, [b] = x;
+    // function f() { var inner; }
+    // let c;
+    // {
+    //   function c() {}
+    //   function d() {}
+    //   { function d() {} }
+    // }
+    // for (var e in x) {}
+    let text = r#"Program
+  body:
+    - VariableDeclaration
+      kind: var
+      declarations:
+        - VariableDeclarator
+          id: Identifier
+            name: "a"
+        - VariableDeclarator
+          init: Identifier
+            name: "x"
+          id: ArrayPattern
+            elements:
+              - Identifier
+                name: "b"
+    - FunctionDeclaration
+      id: Identifier
+        name: "f"
+      body: BlockStatement
+        body:
+          - VariableDeclaration
+            kind: var
+            declarations:
+              - VariableDeclarator
+                id: Identifier
+                  name: "inner"
+    - VariableDeclaration
+      kind: let
+      declarations:
+        - VariableDeclarator
+          id: Identifier
+            name: "c"
+    - BlockStatement
+      body:
+        - FunctionDeclaration
+          id: Identifier
+            name: "c"
+          body: BlockStatement
+        - FunctionDeclaration
+          id: Identifier
+            name: "d"
+          body: BlockStatement
+        - BlockStatement
+          body:
+            - FunctionDeclaration
+              id: Identifier
+                name: "d"
+              body: BlockStatement
+    - ForInStatement
+      left: VariableDeclaration
+        kind: var
+        declarations:
+          - VariableDeclarator
+            id: Identifier
+              name: "e"
+      right: Identifier
+        name: "x"
+      body: BlockStatement
+"#;
+    let program = parse_text(&gc, text).unwrap();
+    let hoisted = |annex_b| {
+        sema::hoisted_declarations(&gc, program, annex_b)
+            .into_iter()
+            .map(|decl| match decl.ident {
+                Node::Identifier(Identifier { name, .. }) => (gc.str(*name), decl.kind),
+                _ => panic!("not an identifier"),
+            })
+            .collect::<Vec<_>>()
+    };
+    use sema::HoistedKind::*;
+    assert_eq!(
+        hoisted(true),
+        [
+            ("a", Var),
+            ("b", Var),
+            ("f", Function),
+            ("d", AnnexBFunction),
+            ("e", Var)
+        ]
+    );
+    assert_eq!(
+        hoisted(false),
+        [("a", Var), ("b", Var), ("f", Function), ("e", Var)]
+    );
+
+    let function = iter_preorder(&gc, program)
+        .map(|(node, _, _)| node)
+        .find(|node| matches!(node, Node::FunctionDeclaration(_)))
+        .unwrap();
+    let inner = sema::hoisted_declarations(&gc, function, true);
+    assert_eq!(inner.len(), 1);
+    assert!(matches!(inner[0].decl, Node::VariableDeclaration(_)));
+}
+
+#[test]
+fn test_annex_b_proto() {
+    /// Resolve `({__proto__: a, __proto__: b})` and return the number of errors.
+    fn errors(annex_b: bool) -> usize {
+        let mut ctx = Context::new();
+        ctx.set_annex_b(annex_b);
+        let file_id = ctx
+            .sm_mut()
+            .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+        let gc = GCLock::new(&mut ctx);
+        let range = SourceRange::from_loc(file_id, SourceLoc { line: 1, col: 1 });
+        let ident = |name: &str| {
+            builder::Identifier::build_template(
+                &gc,
+                template::Identifier {
+                    metadata: TemplateMetadata {
+                        range,
+                        ..Default::default()
+                    },
+                    name: gc.atom(name),
+                    type_annotation: None,
+                    optional: false,
+                },
+            )
+        };
+        let proto = |value| {
+            builder::Property::build_template(
+                &gc,
+                template::Property {
+                    metadata: Default::default(),
+                    key: ident("__proto__"),
+                    value: ident(value),
+                    kind: PropertyKind::Init,
+                    computed: false,
+                    method: false,
+                    shorthand: false,
+                },
+            )
+        };
+        let object = builder::ObjectExpression::build_template(
+            &gc,
+            template::ObjectExpression {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(&gc, [proto("a"), proto("b")]),
+            },
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(
+                    &gc,
+                    [builder::ExpressionStatement::build_template(
+                        &gc,
+                        template::ExpressionStatement {
+                            metadata: Default::default(),
+                            expression: object,
+                            directive: None,
+                        },
+                    )],
+                ),
+            },
+        );
+        sema::resolve_program(&gc, file_id, program);
+        gc.sm().num_errors()
+    }
+    assert_eq!(errors(true), 1);
+    // Without Annex B, `__proto__` is an ordinary property.
+    assert_eq!(errors(false), 0);
+}
+
+#[test]
+fn test_source_type() {
+    type Build = for<'gc> fn(&'gc GCLock<'_, '_>, SourceRange) -> &'gc Node<'gc>;
+
+    /// Resolve a script or a module consisting of the expression statement returned by
+    /// `build` and return the number of errors and warnings.
+    fn diagnostics(module: bool, build: Build) -> (usize, usize) {
+        let mut ctx = Context::new();
+        let file_id = ctx
+            .sm_mut()
+            .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+        let gc = GCLock::new(&mut ctx);
+        let range = SourceRange::from_loc(file_id, SourceLoc { line: 1, col: 1 });
+        let body = NodeList::from_iter(
+            &gc,
+            [builder::ExpressionStatement::build_template(
+                &gc,
+                template::ExpressionStatement {
+                    metadata: Default::default(),
+                    expression: build(&gc, range),
+                    directive: None,
+                },
+            )],
+        );
+        if module {
+            let module = builder::Module::build_template(
+                &gc,
+                template::Module {
+                    metadata: Default::default(),
+                    body,
+                },
+            );
+            let resolver = resolve_dependency::DefaultResolver::new(gc.sm());
+            sema::resolve_module(&gc, module, file_id, &resolver);
+        } else {
+            let program = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body,
+                },
+            );
+            sema::resolve_program(&gc, file_id, program);
+        }
+        (gc.sm().num_errors(), gc.sm().num_warnings())
+    }
+    fn this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::ThisExpression::build_template(
+            gc,
+            template::ThisExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+            },
+        )
+    }
+    // this
+    assert_eq!(diagnostics(true, this), (0, 1));
+    assert_eq!(diagnostics(false, this), (0, 0));
+
+    // () => { this }
+    fn arrow_this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::ArrowFunctionExpression::build_template(
+            gc,
+            template::ArrowFunctionExpression {
+                metadata: Default::default(),
+                id: None,
+                params: NodeList::new(gc),
+                body: builders::block(gc, [builders::expr_stmt(gc, this(gc, r))]),
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                expression: false,
+                is_async: false,
+            },
+        )
+    }
+    assert_eq!(diagnostics(true, arrow_this), (0, 1));
+
+    // function () { this }
+    fn function_this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::FunctionExpression::build_template(
+            gc,
+            template::FunctionExpression {
+                metadata: Default::default(),
+                id: None,
+                params: NodeList::new(gc),
+                body: builders::block(gc, [builders::expr_stmt(gc, this(gc, r))]),
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                generator: false,
+                is_async: false,
+            },
+        )
+    }
+    assert_eq!(diagnostics(true, function_this), (0, 0));
+
+    // await 1
+    fn await_one<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::AwaitExpression::build_template(
+            gc,
+            template::AwaitExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+                argument: builder::NumericLiteral::build_template(
+                    gc,
+                    template::NumericLiteral {
+                        metadata: Default::default(),
+                        value: 1.0,
+                    },
+                ),
+            },
+        )
+    }
+    assert_eq!(diagnostics(true, await_one), (0, 0));
+    assert_eq!(diagnostics(false, await_one), (1, 0));
+
+    // async () => { await 1 }
+    fn async_await_one<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::ArrowFunctionExpression::build_template(
+            gc,
+            template::ArrowFunctionExpression {
+                metadata: Default::default(),
+                id: None,
+                params: NodeList::new(gc),
+                body: builders::block(gc, [builders::expr_stmt(gc, await_one(gc, r))]),
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                expression: false,
+                is_async: true,
+            },
+        )
+    }
+    assert_eq!(diagnostics(false, async_await_one), (0, 0));
+
+    // eval = 1, which is an error in strict mode.
+    fn assign_eval<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::AssignmentExpression::build_template(
+            gc,
+            template::AssignmentExpression {
+                metadata: Default::default(),
+                operator: AssignmentExpressionOperator::Assign,
+                left: builder::Identifier::build_template(
+                    gc,
+                    template::Identifier {
+                        metadata: TemplateMetadata {
+                            range: r,
+                            ..Default::default()
+                        },
+                        name: gc.atom("eval"),
+                        type_annotation: None,
+                        optional: false,
+                    },
+                ),
+                right: builder::NumericLiteral::build_template(
+                    gc,
+                    template::NumericLiteral {
+                        metadata: Default::default(),
+                        value: 1.0,
+                    },
+                ),
+            },
+        )
+    }
+    assert_eq!(diagnostics(true, assign_eval).0, 1);
+    assert_eq!(diagnostics(false, assign_eval).0, 0);
+}
+
+#[test]
+fn test_static_blocks() {
+    type Build = for<'gc> fn(&'gc GCLock<'_, '_>, SourceRange) -> &'gc Node<'gc>;
+
+    /// Resolve `(class { static { <expr> } })` with the expression returned by `build` and
+    /// return the number of errors.
+    fn errors(build: Build) -> usize {
+        let mut ctx = Context::new();
+        let file_id = ctx
+            .sm_mut()
+            .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+        let gc = GCLock::new(&mut ctx);
+        let range = SourceRange::from_loc(file_id, SourceLoc { line: 1, col: 1 });
+        let statement = |expression| {
+            builder::ExpressionStatement::build_template(
+                &gc,
+                template::ExpressionStatement {
+                    metadata: Default::default(),
+                    expression,
+                    directive: None,
+                },
+            )
+        };
+        let static_block = builder::StaticBlock::build_template(
+            &gc,
+            template::StaticBlock {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [statement(build(&gc, range))]),
+            },
+        );
+        let class = builder::ClassExpression::build_template(
+            &gc,
+            template::ClassExpression {
+                metadata: Default::default(),
+                id: None,
+                type_parameters: None,
+                super_class: None,
+                super_type_parameters: None,
+                implements: NodeList::new(&gc),
+                decorators: NodeList::new(&gc),
+                body: builder::ClassBody::build_template(
+                    &gc,
+                    template::ClassBody {
+                        metadata: Default::default(),
+                        body: NodeList::from_iter(&gc, [static_block]),
+                    },
+                ),
+            },
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [statement(class)]),
+            },
+        );
+        sema::resolve_program(&gc, file_id, program);
+        gc.sm().num_errors()
+    }
+    fn this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::ThisExpression::build_template(
+            gc,
+            template::ThisExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+            },
+        )
+    }
+    fn arguments<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::Identifier::build_template(
+            gc,
+            template::Identifier {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+                name: gc.atom("arguments"),
+                type_annotation: None,
+                optional: false,
+            },
+        )
+    }
+    fn await_this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::AwaitExpression::build_template(
+            gc,
+            template::AwaitExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+                argument: this(gc, r),
+            },
+        )
+    }
+    fn super_call<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::CallExpression::build_template(
+            gc,
+            template::CallExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+                callee: builder::Super::build_template(
+                    gc,
+                    template::Super {
+                        metadata: Default::default(),
+                    },
+                ),
+                type_arguments: None,
+                arguments: NodeList::new(gc),
+            },
+        )
+    }
+
+    assert_eq!(errors(this), 0);
+    assert_eq!(errors(arguments), 1);
+    assert_eq!(errors(await_this), 1);
+    assert_eq!(errors(super_call), 1);
+}
+
+#[test]
+fn test_private_names() {
+    type Build = for<'gc> fn(&'gc GCLock<'_, '_>, SourceRange) -> &'gc Node<'gc>;
+
+    /// Resolve `(class { #x; static { <expr> } })` with the expression returned by `build`
+    /// and return the number of errors and of resolved private name keys and references.
+    fn resolve(build: Build) -> (usize, usize) {
+        let mut ctx = Context::new();
+        let file_id = ctx
+            .sm_mut()
+            .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+        let gc = GCLock::new(&mut ctx);
+        let range = SourceRange::from_loc(file_id, SourceLoc { line: 1, col: 1 });
+        let statement = |expression| {
+            builder::ExpressionStatement::build_template(
+                &gc,
+                template::ExpressionStatement {
+                    metadata: Default::default(),
+                    expression,
+                    directive: None,
+                },
+            )
+        };
+        let field = builder::ClassPrivateProperty::build_template(
+            &gc,
+            template::ClassPrivateProperty {
+                metadata: Default::default(),
+                key: private_name(&gc, range, "x"),
+                value: None,
+                is_static: false,
+                declare: false,
+                optional: false,
+                variance: None,
+                type_annotation: None,
+                ts_modifiers: None,
+            },
+        );
+        let static_block = builder::StaticBlock::build_template(
+            &gc,
+            template::StaticBlock {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [statement(build(&gc, range))]),
+            },
+        );
+        let class = builder::ClassExpression::build_template(
+            &gc,
+            template::ClassExpression {
+                metadata: Default::default(),
+                id: None,
+                type_parameters: None,
+                super_class: None,
+                super_type_parameters: None,
+                implements: NodeList::new(&gc),
+                decorators: NodeList::new(&gc),
+                body: builder::ClassBody::build_template(
+                    &gc,
+                    template::ClassBody {
+                        metadata: Default::default(),
+                        body: NodeList::from_iter(&gc, [field, static_block]),
+                    },
+                ),
+            },
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(&gc, [statement(class)]),
+            },
+        );
+        let sem = sema::resolve_program(&gc, file_id, program);
+        (gc.sm().num_errors(), sem.all_private_name_refs().len())
+    }
+    fn private_name<'gc>(gc: &'gc GCLock, r: SourceRange, name: &str) -> &'gc Node<'gc> {
+        let metadata = TemplateMetadata {
+            range: r,
+            ..Default::default()
+        };
+        builder::PrivateName::build_template(
+            gc,
+            template::PrivateName {
+                metadata: metadata.clone(),
+                id: builder::Identifier::build_template(
+                    gc,
+                    template::Identifier {
+                        metadata,
+                        name: gc.atom(name),
+                        type_annotation: None,
+                        optional: false,
+                    },
+                ),
+            },
+        )
+    }
+    fn this_member<'gc>(gc: &'gc GCLock, r: SourceRange, name: &str) -> &'gc Node<'gc> {
+        builder::MemberExpression::build_template(
+            gc,
+            template::MemberExpression {
+                metadata: Default::default(),
+                object: builder::ThisExpression::build_template(
+                    gc,
+                    template::ThisExpression {
+                        metadata: Default::default(),
+                    },
+                ),
+                property: private_name(gc, r, name),
+                computed: false,
+            },
+        )
+    }
+
+    // this.#x
+    assert_eq!(resolve(|gc, r| this_member(gc, r, "x")), (0, 2));
+    // this.#y
+    assert_eq!(resolve(|gc, r| this_member(gc, r, "y")), (1, 1));
+
+    // #x in this
+    fn x_in_this<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::BinaryExpression::build_template(
+            gc,
+            template::BinaryExpression {
+                metadata: Default::default(),
+                left: private_name(gc, r, "x"),
+                right: builder::ThisExpression::build_template(
+                    gc,
+                    template::ThisExpression {
+                        metadata: Default::default(),
+                    },
+                ),
+                operator: BinaryExpressionOperator::In,
+            },
+        )
+    }
+    assert_eq!(resolve(x_in_this), (0, 2));
+
+    // delete this.#x
+    fn delete_x<'gc>(gc: &'gc GCLock, r: SourceRange) -> &'gc Node<'gc> {
+        builder::UnaryExpression::build_template(
+            gc,
+            template::UnaryExpression {
+                metadata: TemplateMetadata {
+                    range: r,
+                    ..Default::default()
+                },
+                operator: UnaryExpressionOperator::Delete,
+                argument: this_member(gc, r, "x"),
+                prefix: true,
+            },
+        )
+    }
+    assert_eq!(resolve(delete_x), (1, 2));
+}
+
+#[test]
+fn test_import_attributes() {
+    use resolve_dependency::DependencyKind;
+    use resolve_dependency::DependencyResolver;
+    use resolve_dependency::ImportAttribute;
+    use std::cell::RefCell;
+
+    /// Records the imports it is asked to resolve, and resolves them all to the same file.
+    #[derive(Default)]
+    struct Recorder {
+        imports: RefCell<Vec<(String, DependencyKind, Option<String>)>>,
+    }
+    impl DependencyResolver for Recorder {
+        fn resolve_dependency(
+            &self,
+            _lock: &GCLock,
+            file: SourceId,
+            _path: &str,
+            _kind: DependencyKind,
+        ) -> Option<SourceId> {
+            Some(file)
+        }
+        fn resolve_import(
+            &self,
+            lock: &GCLock,
+            file: SourceId,
+            path: &str,
+            kind: DependencyKind,
+            attributes: &[ImportAttribute],
+        ) -> Option<SourceId> {
+            self.imports.borrow_mut().push((
+                path.to_string(),
+                kind,
+                resolve_dependency::module_type(attributes).map(str::to_string),
+            ));
+            self.resolve_dependency(lock, file, path, kind)
+        }
+    }
+
+    fn string<'gc>(gc: &'gc GCLock, s: &str) -> &'gc Node<'gc> {
+        builder::StringLiteral::build_template(
+            gc,
+            template::StringLiteral {
+                metadata: Default::default(),
+                value: gc.atom_u16(s.encode_utf16().collect::<Vec<_>>()),
+            },
+        )
+    }
+    fn ident<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builder::Identifier::build_template(
+            gc,
+            template::Identifier {
+                metadata: Default::default(),
+                name: gc.atom(name),
+                type_annotation: None,
+                optional: false,
+            },
+        )
+    }
+    fn object<'gc>(gc: &'gc GCLock, key: &str, value: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::ObjectExpression::build_template(
+            gc,
+            template::ObjectExpression {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(
+                    gc,
+                    [builder::Property::build_template(
+                        gc,
+                        template::Property {
+                            metadata: Default::default(),
+                            key: ident(gc, key),
+                            value,
+                            kind: PropertyKind::Init,
+                            computed: false,
+                            method: false,
+                            shorthand: false,
+                        },
+                    )],
+                ),
+            },
+        )
+    }
+
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_copy(""));
+    let gc = GCLock::new(&mut ctx);
+
+    // import 'data.json' with {type: 'json'};
+    let import_json = builder::ImportDeclaration::build_template(
+        &gc,
+        template::ImportDeclaration {
+            metadata: Default::default(),
+            specifiers: NodeList::new(&gc),
+            source: string(&gc, "data.json"),
+            assertions: Some(NodeList::from_iter(
+                &gc,
+                [builder::ImportAttribute::build_template(
+                    &gc,
+                    template::ImportAttribute {
+                        metadata: Default::default(),
+                        key: ident(&gc, "type"),
+                        value: string(&gc, "json"),
+                    },
+                )],
+            )),
+            import_kind: ImportKind::Value,
+        },
+    );
+    // import('styles.css', {with: {type: 'css'}});
+    let import_css = builder::ExpressionStatement::build_template(
+        &gc,
+        template::ExpressionStatement {
+            metadata: Default::default(),
+            expression: builder::ImportExpression::build_template(
+                &gc,
+                template::ImportExpression {
+                    metadata: Default::default(),
+                    source: string(&gc, "styles.css"),
+                    attributes: Some(object(&gc, "with", object(&gc, "type", string(&gc, "css")))),
+                },
+            ),
+            directive: None,
+        },
+    );
+    let module = builder::Module::build_template(
+        &gc,
+        template::Module {
+            metadata: Default::default(),
+            body: NodeList::from_iter(&gc, [import_json, import_css]),
+        },
+    );
+
+    let recorder = Recorder::default();
+    let sem = sema::resolve_module(&gc, module, file_id, &recorder);
+    assert_eq!(gc.sm().num_errors(), 0);
+    assert_eq!(sem.all_requires().len(), 2);
+    assert_eq!(
+        recorder.imports.into_inner(),
+        vec![
+            (
+                "data.json".to_string(),
+                DependencyKind::Import,
+                Some("json".to_string())
+            ),
+            (
+                "styles.css".to_string(),
+                DependencyKind::DynamicImport,
+                Some("css".to_string())
+            ),
+        ]
+    );
+}
@@ -0,0 +1,187 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Per-kind statistics of the nodes allocated in a [`crate::Context`], and of the use of its
+//! storage, for memory investigations.
+//!
+//! Every node takes a storage entry sized by the largest kind of node which isn't boxed
+//! (see [`NodeVariant::is_boxed`]), so the smallest kinds waste part of their entry, and the
+//! boxed kinds take a box in addition to their entry. [`HeapStats`] measures both.
+
+use std::fmt;
+
+use crate::NodeVariant;
+
+/// Allocation statistics of one kind of node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindAllocStats {
+    pub variant: NodeVariant,
+
+    /// Number of nodes allocated since tracking was enabled, including freed ones.
+    pub allocated: usize,
+
+    /// Number of nodes currently in storage, which haven't been freed by a collection.
+    pub live: usize,
+
+    /// Bytes of storage used by the live nodes, including their boxes.
+    pub live_bytes: usize,
+
+    /// Bytes of the live nodes used by the struct of the kind,
+    /// see [`NodeVariant::payload_size`].
+    pub live_payload_bytes: usize,
+}
+
+/// Allocation statistics of every kind of node, computed by
+/// [`crate::Context::alloc_histogram`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllocHistogram {
+    /// Kinds with any allocated or live node, by decreasing live bytes, then decreasing
+    /// allocations.
+    pub kinds: Vec<KindAllocStats>,
+}
+
+impl AllocHistogram {
+    /// Statistics of `variant`, if any of its nodes were allocated or are live.
+    pub fn get(&self, variant: NodeVariant) -> Option<&KindAllocStats> {
+        self.kinds.iter().find(|stats| stats.variant == variant)
+    }
+
+    /// Total number of allocated nodes.
+    pub fn allocated(&self) -> usize {
+        self.kinds.iter().map(|stats| stats.allocated).sum()
+    }
+
+    /// Total bytes of storage used by the live nodes.
+    pub fn live_bytes(&self) -> usize {
+        self.kinds.iter().map(|stats| stats.live_bytes).sum()
+    }
+}
+
+/// Use of the storage of a [`crate::Context`], computed by [`crate::Context::heap_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Size of the storage entry of every node.
+    pub node_entry_size: usize,
+
+    /// Number of allocated node entries, including the free ones.
+    pub node_entries: usize,
+
+    /// Number of live nodes.
+    pub live_nodes: usize,
+
+    /// Bytes of the live nodes used by the structs of their kinds.
+    pub live_payload_bytes: usize,
+
+    /// Bytes of the boxes of the live nodes of boxed kinds, outside of their entries.
+    pub live_boxed_bytes: usize,
+
+    /// Size of the storage entry of every list element.
+    pub list_element_entry_size: usize,
+
+    /// Number of allocated list element entries, including the free ones.
+    pub list_element_entries: usize,
+
+    /// Number of live list elements.
+    pub live_list_elements: usize,
+}
+
+impl HeapStats {
+    /// Bytes of the live node entries.
+    pub fn live_node_bytes(&self) -> usize {
+        self.live_nodes * self.node_entry_size
+    }
+
+    /// Bytes of the live node entries and boxes which aren't used by the struct of their
+    /// kind: the header of the entry, the tag of the `Node`, the pointer to the box of boxed
+    /// kinds and the padding to the largest kind which isn't boxed.
+    pub fn padding_bytes(&self) -> usize {
+        self.live_node_bytes() + self.live_boxed_bytes - self.live_payload_bytes
+    }
+
+    /// Bytes of the free node and list element entries.
+    pub fn free_bytes(&self) -> usize {
+        (self.node_entries - self.live_nodes) * self.node_entry_size
+            + (self.list_element_entries - self.live_list_elements) * self.list_element_entry_size
+    }
+}
+
+impl fmt::Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let live_bytes = self.live_node_bytes() + self.live_boxed_bytes;
+        writeln!(
+            f,
+            "Nodes:          {} live of {} entries of {} bytes",
+            self.live_nodes, self.node_entries, self.node_entry_size
+        )?;
+        writeln!(
+            f,
+            "List elements:  {} live of {} entries of {} bytes",
+            self.live_list_elements, self.list_element_entries, self.list_element_entry_size
+        )?;
+        writeln!(
+            f,
+            "Live nodes:     {} bytes, {} of them boxed",
+            live_bytes, self.live_boxed_bytes
+        )?;
+        writeln!(
+            f,
+            "Padding:        {} bytes ({:.1}%)",
+            self.padding_bytes(),
+            if live_bytes == 0 {
+                0.0
+            } else {
+                self.padding_bytes() as f64 * 100.0 / live_bytes as f64
+            }
+        )?;
+        writeln!(f, "Free entries:   {} bytes", self.free_bytes())
+    }
+}
+
+/// Width of the longest bar of the histogram.
+const BAR_WIDTH: usize = 40;
+
+impl fmt::Display for AllocHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.live_bytes();
+        let max = self
+            .kinds
+            .iter()
+            .map(|stats| stats.live_bytes)
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "  {:<32} {:>10} {:>10} {:>12} {:>6}",
+            "Kind", "Allocated", "Live", "Live bytes", "%"
+        )?;
+        for stats in &self.kinds {
+            let bar = (stats.live_bytes * BAR_WIDTH).checked_div(max).unwrap_or(0);
+            writeln!(
+                f,
+                "  {:<32} {:>10} {:>10} {:>12} {:>5.1}% {}",
+                format!("{:?}", stats.variant),
+                stats.allocated,
+                stats.live,
+                stats.live_bytes,
+                if total == 0 {
+                    0.0
+                } else {
+                    stats.live_bytes as f64 * 100.0 / total as f64
+                },
+                "#".repeat(bar),
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<32} {:>10} {:>10} {:>12}",
+            "Total",
+            self.allocated(),
+            self.kinds.iter().map(|stats| stats.live).sum::<usize>(),
+            total
+        )
+    }
+}
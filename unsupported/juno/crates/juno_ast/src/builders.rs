@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Shorthand constructors for the most commonly built nodes.
+//! All nodes are created with default metadata (an invalid source range);
+//! use the templates in [`crate::template`] when more control is needed.
+
+use crate::builder;
+use crate::template;
+use crate::GCLock;
+use crate::MemoryLimitExceeded;
+use crate::Node;
+use crate::NodeList;
+
+/// `name`
+pub fn ident<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+    builder::Identifier::build_template(
+        gc,
+        template::Identifier {
+            metadata: Default::default(),
+            name: gc.atom(name),
+            type_annotation: None,
+            optional: false,
+        },
+    )
+}
+
+/// Numeric literal `value`.
+pub fn number<'gc>(gc: &'gc GCLock, value: f64) -> &'gc Node<'gc> {
+    builder::NumericLiteral::build_template(
+        gc,
+        template::NumericLiteral {
+            metadata: Default::default(),
+            value,
+        },
+    )
+}
+
+/// String literal with the contents `value`.
+pub fn string<'gc>(gc: &'gc GCLock, value: &str) -> &'gc Node<'gc> {
+    builder::StringLiteral::build_template(
+        gc,
+        template::StringLiteral {
+            metadata: Default::default(),
+            value: gc.atom_u16(value.encode_utf16().collect::<Vec<u16>>()),
+        },
+    )
+}
+
+/// `true` or `false`.
+pub fn boolean<'gc>(gc: &'gc GCLock, value: bool) -> &'gc Node<'gc> {
+    builder::BooleanLiteral::build_template(
+        gc,
+        template::BooleanLiteral {
+            metadata: Default::default(),
+            value,
+        },
+    )
+}
+
+/// `null`
+pub fn null<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+    builder::NullLiteral::build_template(
+        gc,
+        template::NullLiteral {
+            metadata: Default::default(),
+        },
+    )
+}
+
+/// `callee(args...)`
+pub fn call<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+    gc: &'gc GCLock,
+    callee: &'gc Node<'gc>,
+    args: I,
+) -> &'gc Node<'gc> {
+    builder::CallExpression::build_template(
+        gc,
+        template::CallExpression {
+            metadata: Default::default(),
+            callee,
+            type_arguments: None,
+            arguments: NodeList::from_iter(gc, args),
+        },
+    )
+}
+
+/// `callee(args...)`, or an error if the memory limit would be exceeded.
+pub fn try_call<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+    gc: &'gc GCLock,
+    callee: &'gc Node<'gc>,
+    args: I,
+) -> Result<&'gc Node<'gc>, MemoryLimitExceeded> {
+    builder::CallExpression::try_build_template(
+        gc,
+        template::CallExpression {
+            metadata: Default::default(),
+            callee,
+            type_arguments: None,
+            arguments: NodeList::try_from_iter(gc, args)?,
+        },
+    )
+}
+
+/// `object.name`
+pub fn member<'gc>(gc: &'gc GCLock, object: &'gc Node<'gc>, name: &str) -> &'gc Node<'gc> {
+    builder::MemberExpression::build_template(
+        gc,
+        template::MemberExpression {
+            metadata: Default::default(),
+            object,
+            property: ident(gc, name),
+            computed: false,
+        },
+    )
+}
+
+/// `expression;`
+pub fn expr_stmt<'gc>(gc: &'gc GCLock, expression: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    builder::ExpressionStatement::build_template(
+        gc,
+        template::ExpressionStatement {
+            metadata: Default::default(),
+            expression,
+            directive: None,
+        },
+    )
+}
+
+/// `{ body... }`
+pub fn block<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+    gc: &'gc GCLock,
+    body: I,
+) -> &'gc Node<'gc> {
+    builder::BlockStatement::build_template(
+        gc,
+        template::BlockStatement {
+            metadata: Default::default(),
+            body: NodeList::from_iter(gc, body),
+        },
+    )
+}
+
+/// A script made of the statements `body`.
+pub fn program<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+    gc: &'gc GCLock,
+    body: I,
+) -> &'gc Node<'gc> {
+    builder::Program::build_template(
+        gc,
+        template::Program {
+            metadata: Default::default(),
+            body: NodeList::from_iter(gc, body),
+        },
+    )
+}
+
+/// A module made of the statements `body`.
+pub fn module<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+    gc: &'gc GCLock,
+    body: I,
+) -> &'gc Node<'gc> {
+    builder::Module::build_template(
+        gc,
+        template::Module {
+            metadata: Default::default(),
+            body: NodeList::from_iter(gc, body),
+        },
+    )
+}
@@ -0,0 +1,120 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use super::AssignmentExpressionOperator;
+use super::BinaryExpressionOperator;
+use super::ExportKind;
+use super::GCLock;
+use super::ImportKind;
+use super::LogicalExpressionOperator;
+use super::MethodDefinitionKind;
+use super::Node;
+use super::NodeLabel;
+use super::NodeList;
+use super::NodeString;
+use super::PropertyKind;
+use super::UnaryExpressionOperator;
+use super::UpdateExpressionOperator;
+use super::VariableDeclarationKind;
+
+/// Generate the function which copies a single node and recursively clones its children.
+macro_rules! gen_deep_clone {
+    ($name:ident {
+        $(
+            $(#[boxed])?
+            $kind:ident $([ $parent:ident ])? $({
+                $(
+                    $field:ident : $type:ty
+                    $( [ $( $constraint:ident ),* ] )?
+                ),*
+                $(,)?
+            })?
+        ),*
+        $(,)?
+    }) => {
+        fn clone_node<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> &'gc Node<'gc> {
+            use crate::*;
+            crate::grow_stack(|| match node {
+                $(
+                    Node::$kind(inner) => {
+                        let $kind { metadata, $($($field,)*)? }: &$kind = inner;
+                        builder::$kind::build_template(
+                            gc,
+                            template::$kind {
+                                metadata: metadata.into(),
+                                $($(
+                                    $field: $field.deep_clone(gc),
+                                )*)?
+                            },
+                        )
+                    }
+                ),*
+            })
+        }
+    }
+}
+
+nodekind_defs! { gen_deep_clone }
+
+/// Recursively duplicate the subtree rooted at `node`, returning the new root.
+/// Every node and list in the subtree is freshly allocated,
+/// while atoms and source ranges are shared with the original.
+pub fn deep_clone<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    clone_node(gc, node)
+}
+
+trait DeepClone<'gc> {
+    fn deep_clone(&self, gc: &'gc GCLock) -> Self;
+}
+
+impl<'gc> DeepClone<'gc> for &'gc Node<'gc> {
+    fn deep_clone(&self, gc: &'gc GCLock) -> Self {
+        clone_node(gc, self)
+    }
+}
+
+impl<'gc> DeepClone<'gc> for NodeList<'gc> {
+    fn deep_clone(&self, gc: &'gc GCLock) -> Self {
+        NodeList::from_iter(gc, self.iter().map(|elem| clone_node(gc, elem)))
+    }
+}
+
+impl<'gc, T: DeepClone<'gc>> DeepClone<'gc> for Option<T> {
+    fn deep_clone(&self, gc: &'gc GCLock) -> Self {
+        self.as_ref().map(|t| t.deep_clone(gc))
+    }
+}
+
+/// Children which don't contain nodes are simply copied.
+macro_rules! impl_deep_clone_copy {
+    ($($type:ty),* $(,)?) => {
+        $(
+        impl<'gc> DeepClone<'gc> for $type {
+            fn deep_clone(&self, _gc: &'gc GCLock) -> Self {
+                *self
+            }
+        }
+        )*
+    };
+}
+
+impl_deep_clone_copy!(
+    f64,
+    bool,
+    NodeLabel,
+    NodeString,
+    UnaryExpressionOperator,
+    BinaryExpressionOperator,
+    LogicalExpressionOperator,
+    UpdateExpressionOperator,
+    AssignmentExpressionOperator,
+    VariableDeclarationKind,
+    PropertyKind,
+    MethodDefinitionKind,
+    ImportKind,
+    ExportKind,
+);
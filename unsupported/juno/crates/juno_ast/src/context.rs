@@ -7,37 +7,220 @@
 
 //! Garbage-collected Storage structures for AST nodes.
 
+use std::any::Any;
+use std::any::TypeId;
 use std::cell::Cell;
+use std::cell::RefCell;
 use std::cell::UnsafeCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::marker::PhantomData;
 use std::ops::Deref;
+use std::panic::Location;
 use std::pin::Pin;
 use std::ptr::NonNull;
+use std::rc::Rc;
+use std::rc::Weak;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::Ordering;
 
 use juno_support::atom_table::Atom;
 use juno_support::atom_table::AtomTable;
 use juno_support::atom_table::AtomU16;
+use juno_support::CancellationToken;
+use juno_support::ChunkConfig;
 use juno_support::Deque;
 use juno_support::HeapSize;
 use libc::c_void;
 use memoffset::offset_of;
+use thiserror::Error;
 
+use crate::AllocHistogram;
+use crate::Empty;
+use crate::HeapStats;
+use crate::KindAllocStats;
 use crate::Node;
+use crate::NodeMetadata;
+use crate::NodeVariant;
 use crate::Path;
 use crate::SourceManager;
+use crate::SourceRange;
 use crate::Visitor;
 
 /// ID which indicates a `StorageEntry` is free.
 const FREE_ENTRY: u32 = 0;
 
+/// Bit of `StorageEntry::ctx_id_markbit` set when the node is frozen.
+const FROZEN_BIT: u32 = 1 << 30;
+
+/// Minimum number of nodes which must be allocated since the last GC before
+/// [`GCLock::collect_if_needed`] runs a collection with [`GcPolicy::Auto`].
+const SAFEPOINT_MIN_ALLOCATIONS: usize = 1 << 16;
+
+/// Number of bytes of storage used by each allocated node.
+pub(crate) const NODE_STORAGE_SIZE: usize = std::mem::size_of::<StorageEntry>();
+
+/// Number of bytes of storage used by each element of a [`crate::NodeList`].
+pub(crate) const LIST_ELEMENT_STORAGE_SIZE: usize = std::mem::size_of::<NodeListElement>();
+
+/// Controls when a [`Context`] collects garbage without an explicit call to [`Context::gc`].
+///
+/// The policy is checked at the safepoints [`Context::collect_if_needed`] and
+/// [`GCLock::collect_if_needed`], which drivers call between units of work, like passes.
+/// Releasing a [`GCLock`] never collects, so a collection can't happen unexpectedly, e.g.
+/// while unwinding.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum GcPolicy {
+    /// Collect once enough nodes have been allocated since the last collection for it to be
+    /// worthwhile, i.e. as many as were live after it.
+    #[default]
+    Auto,
+    /// Only collect when requested explicitly, safepoints never collect.
+    Manual,
+    /// Collect once this many nodes have been allocated since the last collection.
+    NodeCount(usize),
+    /// Collect once approximately this many bytes of AST storage have been allocated since the
+    /// last collection.
+    AllocatedBytes(usize),
+}
+
+/// Error returned when an allocation would exceed the memory limit of a [`Context`].
+#[derive(Debug, Copy, Clone, Error)]
+#[error("AST memory limit of {limit} bytes exceeded")]
+pub struct MemoryLimitExceeded {
+    pub limit: usize,
+}
+
+/// Error recorded when a traversal of the AST exceeds the depth limit of its [`Context`]
+/// (see [`Context::set_max_depth`]).
+#[derive(Debug, Copy, Clone, Error)]
+#[error("AST nesting depth limit of {limit} exceeded")]
+pub struct TooDeep {
+    pub limit: usize,
+
+    /// Range of the node whose children weren't visited.
+    pub range: SourceRange,
+}
+
+/// Nesting level of a traversal of the AST, see [`Context::enter_children`].
+pub(crate) struct DepthGuard<'a>(&'a Cell<usize>);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// Stack which must remain for a recursive traversal to descend one more level of the AST.
+/// Covers the frames of the visitor between two levels, which are larger in debug builds.
+const STACK_RED_ZONE: usize = 256 * 1024;
+
+/// Size of the stack segments allocated when less than [`STACK_RED_ZONE`] remains.
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+/// Run `f`, which recurses into the children of a node, on a new stack segment allocated on
+/// the heap if the current stack is nearly exhausted. Traversals call this at each level, so
+/// their depth is bounded by memory, not by the stack of the thread, like the 2 MiB stacks
+/// of the worker threads of a server.
+pub fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
+
+/// How a [`Context`] allocates its storage.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ContextConfig {
+    /// Sizes of the chunks of node storage.
+    pub node_chunks: ChunkConfig,
+
+    /// Sizes of the chunks of list element storage.
+    pub list_chunks: ChunkConfig,
+
+    /// Number of nodes to reserve room for up front.
+    pub reserve_nodes: usize,
+
+    /// Number of list elements to reserve room for up front.
+    pub reserve_list_elements: usize,
+}
+
+impl ContextConfig {
+    /// Rough number of source bytes per node in typical JS.
+    const BYTES_PER_NODE: usize = 8;
+
+    /// Rough number of source bytes per list element in typical JS.
+    const BYTES_PER_LIST_ELEMENT: usize = 32;
+
+    /// The default configuration, reserving room for the AST of a source of `len` bytes,
+    /// so that parsing a large file doesn't grow through many chunks.
+    /// The estimate is rough: more storage is allocated as usual if it is too low.
+    pub fn for_source_len(len: usize) -> Self {
+        ContextConfig {
+            reserve_nodes: len / Self::BYTES_PER_NODE,
+            reserve_list_elements: len / Self::BYTES_PER_LIST_ELEMENT,
+            ..Default::default()
+        }
+    }
+}
+
+/// Error returned when no more [`Context`]s can be created in this process.
+#[derive(Debug, Copy, Clone, Error)]
+#[error("Too many contexts")]
+pub struct TooManyContexts;
+
+/// Error returned when [`NodeRc`]s into a [`Context`] are still alive when it is reset or
+/// closed, and would be left dangling.
+#[derive(Debug, Copy, Clone, Error)]
+#[error("NodeRc must not outlive Context: {count} still alive")]
+pub struct LiveNodeRcs {
+    pub count: usize,
+}
+
+/// Error returned when a [`NodeRc`] is used with a [`GCLock`] on a context it doesn't
+/// belong to.
+#[derive(Debug, Copy, Clone, Error)]
+#[error("Attempt to dereference NodeRc allocated context {node_ctx} in context {gc_ctx}")]
+pub struct ContextMismatch {
+    pub node_ctx: u32,
+    pub gc_ctx: u32,
+}
+
+/// Callback invoked by infallible allocations which exceed the memory limit.
+struct MemoryLimitHandler(Option<Box<dyn Fn(MemoryLimitExceeded)>>);
+
+impl std::fmt::Debug for MemoryLimitHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            None => write!(f, "None"),
+            Some(_) => write!(f, "Some(<handler>)"),
+        }
+    }
+}
+
+/// What happens when a [`crate::VisitorMut`] reaches a node frozen with [`GCLock::freeze`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FrozenPolicy {
+    /// Don't call the visitor on frozen nodes, leaving them and their children unchanged.
+    #[default]
+    Skip,
+    /// Call the visitor as usual, but panic if it replaces or removes a frozen node.
+    Panic,
+}
+
+/// Whether [`Context::reset`] also clears the atom table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClearAtoms {
+    Yes,
+    No,
+}
+
 /// A single entry in the heap.
 #[derive(Debug)]
 struct StorageEntry<'ctx> {
     /// ID of the context to which this entry belongs.
     /// Top bit is used as a mark bit, and flips meaning every time a GC happens.
+    /// The next bit is [`FROZEN_BIT`].
     /// If this field is `0`, then this entry is free.
     ctx_id_markbit: Cell<u32>,
 
@@ -72,6 +255,22 @@ impl<'ctx> StorageEntry<'ctx> {
         (self.ctx_id_markbit.get() >> 31) != 0
     }
 
+    /// ID of the context which allocated this entry.
+    #[inline]
+    fn ctx_id(&self) -> u32 {
+        self.ctx_id_markbit.get() & !(1 << 31 | FROZEN_BIT)
+    }
+
+    #[inline]
+    fn is_frozen(&self) -> bool {
+        self.ctx_id_markbit.get() & FROZEN_BIT != 0
+    }
+
+    fn freeze(&self) {
+        self.ctx_id_markbit
+            .set(self.ctx_id_markbit.get() | FROZEN_BIT);
+    }
+
     fn is_free(&self) -> bool {
         self.ctx_id_markbit.get() == FREE_ENTRY
     }
@@ -112,6 +311,12 @@ impl<'ctx> NodeListElement<'ctx> {
         (self.ctx_id_markbit.get() >> 31) != 0
     }
 
+    /// ID of the context which allocated this entry.
+    #[inline]
+    fn ctx_id(&self) -> u32 {
+        self.ctx_id_markbit.get() & !(1 << 31)
+    }
+
     fn is_free(&self) -> bool {
         self.ctx_id_markbit.get() == FREE_ENTRY
     }
@@ -127,17 +332,73 @@ struct NodeRcCounter {
     /// Number of [`NodeRc`]s allocated in this `Context`.
     /// Must be `0` when `Context` is dropped.
     count: Cell<usize>,
+
+    /// Where the live `NodeRc`s were created, keyed by the address of their `StorageEntry`.
+    /// `None` unless origin tracking is enabled.
+    origins: RefCell<Option<HashMap<usize, Vec<NodeRcOrigin>>>>,
+
+    /// Tag recorded in the origin of new `NodeRc`s.
+    tag: RefCell<Option<Rc<str>>>,
+}
+
+impl NodeRcCounter {
+    /// Record the origin of a new `NodeRc` to `entry` if tracking is enabled.
+    #[track_caller]
+    fn record_origin(&self, entry: usize) {
+        if let Some(origins) = self.origins.borrow_mut().as_mut() {
+            origins.entry(entry).or_default().push(NodeRcOrigin {
+                location: Location::caller(),
+                tag: self.tag.borrow().clone(),
+            });
+        }
+    }
+
+    /// Forget the origin of a `NodeRc` to `entry` which is being dropped.
+    /// It isn't known which of the `NodeRc`s to the same entry is dropped,
+    /// so the most recent origin is removed.
+    fn forget_origin(&self, entry: usize) {
+        if let Some(origins) = self.origins.borrow_mut().as_mut() {
+            if let Some(list) = origins.get_mut(&entry) {
+                list.pop();
+                if list.is_empty() {
+                    origins.remove(&entry);
+                }
+            }
+        }
+    }
+}
+
+/// Where a [`NodeRc`] was created, recorded when origin tracking is enabled.
+#[derive(Debug, Clone)]
+struct NodeRcOrigin {
+    location: &'static Location<'static>,
+    tag: Option<Rc<str>>,
+}
+
+impl std::fmt::Display for NodeRcOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.location)?;
+        if let Some(tag) = &self.tag {
+            write!(f, " [{}]", tag)?;
+        }
+        Ok(())
+    }
 }
 
 /// The storage for AST nodes.
 ///
 /// Can be used to allocate and free nodes.
-/// Nodes allocated in one `Context` must not be referenced by another `Context`'s AST.
+/// Nodes allocated in one `Context` must not be referenced by another `Context`'s AST,
+/// except by the contexts forked from it (see [`Context::fork`]).
 #[derive(Debug)]
 pub struct Context<'ast> {
     /// Unique number used to identify this context.
     id: u32,
 
+    /// IDs of the contexts this one was forked from, nearest first.
+    /// Their nodes may be referenced, but are never marked or freed by this context.
+    ancestors: Vec<u32>,
+
     /// List of all the nodes stored in this context.
     /// Each element is a "chunk" of nodes.
     /// None of the chunks are ever resized after allocation.
@@ -153,6 +414,10 @@ pub struct Context<'ast> {
     /// Free list for `NodeListElement`s.
     free_list_elements: UnsafeCell<Vec<NonNull<NodeListElement<'ast>>>>,
 
+    /// Entries of every [`NodeRcList`] created in this context, which are GC roots.
+    /// Lists which have been dropped are pruned at the next GC.
+    root_lists: RefCell<Vec<Weak<RootListEntries>>>,
+
     /// `NodeRc` count stored in a `Box` to ensure that `NodeRc`s can also point to it
     /// and decrement the count on drop.
     /// Placed separately to guard against `Context` moving, though relying on that behavior is
@@ -169,11 +434,68 @@ pub struct Context<'ast> {
     /// Flipped every time GC occurs.
     markbit_marked: bool,
 
+    /// Number of nodes allocated since the last GC.
+    allocated_since_gc: Cell<usize>,
+
+    /// Bytes of nodes and list elements allocated since the last GC.
+    bytes_since_gc: Cell<usize>,
+
+    /// When to collect automatically.
+    gc_policy: GcPolicy,
+
+    /// Bytes of storage allocated for nodes and list elements.
+    /// Reusing free entries doesn't increase this.
+    storage_bytes: Cell<usize>,
+
+    /// Bytes of the boxes of the nodes of boxed kinds which haven't been freed,
+    /// see [`NodeVariant::is_boxed`].
+    boxed_bytes: Cell<usize>,
+
+    /// Maximum value for `storage_bytes` and `boxed_bytes` together, if any.
+    memory_limit: Option<usize>,
+
+    /// Called when an infallible allocation exceeds `memory_limit`.
+    memory_limit_handler: MemoryLimitHandler,
+
+    /// Number of nodes which survived the last GC.
+    live_after_gc: usize,
+
+    /// Number of nodes of each kind allocated since tracking was enabled,
+    /// indexed like [`NodeVariant::KINDS`]. `None` unless tracking is enabled.
+    alloc_counts: RefCell<Option<Vec<usize>>>,
+
     /// Whether strict mode has been forced.
     strict_mode: bool,
 
+    /// Whether the Annex B web compatibility extensions are enabled.
+    annex_b: bool,
+
+    /// Whether parsed identifiers point into the source instead of being copied.
+    source_atoms: bool,
+
     /// Whether to warn about undefined variables in strict mode functions.
     pub warn_undefined: bool,
+
+    /// How mutable visitors treat frozen nodes.
+    frozen_policy: FrozenPolicy,
+
+    /// Values attached to nodes with [`GCLock::set_userdata`], keyed by the address of the
+    /// `StorageEntry` of the node and the type of the value.
+    /// Removed when the node is freed.
+    userdata: RefCell<HashMap<UserdataKey, Rc<dyn Any>>>,
+
+    /// Polled by long operations on the AST to stop early.
+    cancellation: Option<CancellationToken>,
+
+    /// Maximum number of nested visits of children, if any.
+    max_depth: Option<usize>,
+
+    /// Number of nested visits of children in progress.
+    depth: Cell<usize>,
+
+    /// The first time a traversal exceeded `max_depth`, since the last call to
+    /// [`Context::take_too_deep`].
+    too_deep: Cell<Option<TooDeep>>,
 }
 
 impl Default for Context<'_> {
@@ -184,38 +506,255 @@ impl Default for Context<'_> {
 
 impl<'ast> Context<'ast> {
     /// Allocate a new `Context` with a new ID.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the IDs of contexts are exhausted.
     pub fn new() -> Self {
+        match Self::try_new() {
+            Ok(ctx) => ctx,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Allocate a new `Context` with a new ID, or return an error if the IDs of contexts
+    /// are exhausted.
+    pub fn try_new() -> Result<Self, TooManyContexts> {
+        Self::try_with_config(Default::default())
+    }
+
+    /// Allocate a new `Context` with a new ID, whose storage is allocated according to
+    /// `config`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the IDs of contexts are exhausted.
+    pub fn with_config(config: ContextConfig) -> Self {
+        match Self::try_with_config(config) {
+            Ok(ctx) => ctx,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Allocate a new `Context` like [`Context::with_config`], or return an error if the IDs
+    /// of contexts are exhausted.
+    pub fn try_with_config(config: ContextConfig) -> Result<Self, TooManyContexts> {
         static NEXT_ID: AtomicU32 = AtomicU32::new(FREE_ENTRY + 1);
         let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-        Self {
+        if id >= FROZEN_BIT {
+            return Err(TooManyContexts);
+        }
+        let mut ctx = Self {
             id,
-            nodes: Default::default(),
+            ancestors: Vec::new(),
+            nodes: UnsafeCell::new(Deque::with_config(config.node_chunks)),
             free_nodes: Default::default(),
-            list_elements: Default::default(),
+            list_elements: UnsafeCell::new(Deque::with_config(config.list_chunks)),
             free_list_elements: Default::default(),
+            root_lists: Default::default(),
             noderc_count: Pin::new(Box::new(NodeRcCounter {
                 ctx_id: id,
                 count: Cell::new(0),
+                origins: RefCell::new(None),
+                tag: RefCell::new(None),
             })),
             atom_table: Default::default(),
             source_mgr: Default::default(),
             markbit_marked: true,
+            allocated_since_gc: Cell::new(0),
+            bytes_since_gc: Cell::new(0),
+            gc_policy: Default::default(),
+            storage_bytes: Cell::new(0),
+            boxed_bytes: Cell::new(0),
+            memory_limit: None,
+            memory_limit_handler: MemoryLimitHandler(None),
+            live_after_gc: 0,
+            alloc_counts: RefCell::new(None),
             strict_mode: false,
+            annex_b: true,
+            source_atoms: false,
             warn_undefined: false,
+            frozen_policy: Default::default(),
+            userdata: Default::default(),
+            cancellation: None,
+            max_depth: None,
+            depth: Cell::new(0),
+            too_deep: Cell::new(None),
+        };
+        ctx.reserve(config.reserve_nodes, config.reserve_list_elements);
+        Ok(ctx)
+    }
+
+    /// Ensure that `nodes` more nodes and `list_elements` more list elements can be
+    /// allocated without allocating more storage, e.g. before parsing a large file.
+    pub fn reserve(&mut self, nodes: usize, list_elements: usize) {
+        self.nodes.get_mut().reserve(nodes);
+        self.list_elements.get_mut().reserve(list_elements);
+    }
+
+    /// Create a child context for speculative transforms.
+    ///
+    /// The nodes of `self` can be used in the child, in particular through the [`NodeRc`]s of
+    /// `self`, and new nodes are allocated in the child, leaving `self` unmodified.
+    /// Dropping the child discards everything it allocated at once, without touching the
+    /// nodes of `self`, which can't be collected or replaced while the child is alive.
+    /// The child starts with a copy of the atom table, the sources and the settings of `self`;
+    /// atoms and sources added to it are not visible in `self`.
+    pub fn fork(&self) -> Context<'_> {
+        match self.try_fork() {
+            Ok(child) => child,
+            Err(e) => panic!("{}", e),
         }
     }
 
+    /// Create a child context like [`Context::fork`], or return an error if the IDs of
+    /// contexts are exhausted.
+    pub fn try_fork(&self) -> Result<Context<'_>, TooManyContexts> {
+        let mut child = Context::try_new()?;
+        child.ancestors.push(self.id);
+        child.ancestors.extend_from_slice(&self.ancestors);
+        child.atom_table = self.atom_table.clone();
+        child.source_mgr = self.source_mgr.clone();
+        child.gc_policy = self.gc_policy;
+        child.strict_mode = self.strict_mode;
+        child.annex_b = self.annex_b;
+        child.source_atoms = self.source_atoms;
+        child.warn_undefined = self.warn_undefined;
+        child.frozen_policy = self.frozen_policy;
+        Ok(child)
+    }
+
+    /// Whether this context was forked from another one.
+    pub fn is_fork(&self) -> bool {
+        !self.ancestors.is_empty()
+    }
+
     /// Acquire a [`GCLock`] on this `Context`.
     /// This is just a more ergonomic way to call `GCLock::new`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is already an active `GCLock` on this thread.
+    #[track_caller]
     pub fn lock<'ctx>(&'ctx mut self) -> GCLock<'ast, 'ctx> {
         GCLock::new(self)
     }
 
+    /// Acquire a [`GCLock`] on this `Context`, or return an error if there is already an
+    /// active `GCLock` on this thread.
+    #[track_caller]
+    pub fn try_lock<'ctx>(&'ctx mut self) -> Result<GCLock<'ast, 'ctx>, GCLockInUse> {
+        GCLock::try_new(self)
+    }
+
+    /// Set the maximum number of bytes of AST storage, or `None` for no limit.
+    /// Allocating past the limit fails in the `try_` builder functions, and invokes the
+    /// handler set by [`Context::set_memory_limit_handler`] in the others.
+    /// Reusing storage freed by a collection never fails, except for the boxes of the large
+    /// kinds of nodes (see [`NodeVariant::is_boxed`]), which are freed by the collection.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// Return the memory limit, if any.
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    /// Set the handler invoked when an infallible allocation exceeds the memory limit.
+    /// The allocation proceeds if the handler returns, so it should usually panic or abort.
+    /// Without a handler, exceeding the limit panics.
+    pub fn set_memory_limit_handler<F: Fn(MemoryLimitExceeded) + 'static>(&mut self, handler: F) {
+        self.memory_limit_handler = MemoryLimitHandler(Some(Box::new(handler)));
+    }
+
+    /// Return an error if allocating `size` more bytes of storage would exceed the limit.
+    fn check_memory_limit(&self, size: usize) -> Result<(), MemoryLimitExceeded> {
+        match self.memory_limit {
+            Some(limit) if self.storage_bytes.get() + self.boxed_bytes.get() + size > limit => {
+                Err(MemoryLimitExceeded { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the memory limit for an infallible allocation of `size` bytes.
+    fn enforce_memory_limit(&self, size: usize) {
+        if let Err(e) = self.check_memory_limit(size) {
+            match &self.memory_limit_handler.0 {
+                Some(handler) => handler(e),
+                None => panic!("{}", e),
+            }
+        }
+    }
+
+    /// Run `f` with a [`GCLock`] on this `Context`, releasing the lock afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is already an active `GCLock` on this thread.
+    #[track_caller]
+    pub fn with_gc<R, F: FnOnce(&mut GCLock<'ast, '_>) -> R>(&mut self, f: F) -> R {
+        f(&mut GCLock::new(self))
+    }
+
+    /// Run `f` with a [`GCLock`] on this `Context`, releasing the lock afterwards.
+    /// Returns an error without running `f` if there is already an active `GCLock` on this
+    /// thread.
+    #[track_caller]
+    pub fn try_with_gc<R, F: FnOnce(&mut GCLock<'ast, '_>) -> R>(
+        &mut self,
+        f: F,
+    ) -> Result<R, GCLockInUse> {
+        Ok(f(&mut GCLock::try_new(self)?))
+    }
+
+    /// Allocate a new `Node` in this `Context`, failing if the memory limit would be exceeded.
+    pub(crate) fn try_alloc<'s>(
+        &'s self,
+        n: Node<'_>,
+    ) -> Result<&'s Node<'s>, MemoryLimitExceeded> {
+        let size = self.new_storage_size(&n);
+        if size != 0 {
+            self.check_memory_limit(size)?;
+        }
+        Ok(self.alloc_unchecked(n))
+    }
+
     /// Allocate a new `Node` in this `Context`.
     pub(crate) fn alloc<'s>(&'s self, n: Node<'_>) -> &'s Node<'s> {
+        let size = self.new_storage_size(&n);
+        if size != 0 {
+            self.enforce_memory_limit(size);
+        }
+        self.alloc_unchecked(n)
+    }
+
+    /// Bytes which allocating `n` adds to the storage: an entry unless there is a free one,
+    /// and the box of `n` if it has one.
+    fn new_storage_size(&self, n: &Node<'_>) -> usize {
+        let entry = if unsafe { &*self.free_nodes.get() }.is_empty() {
+            std::mem::size_of::<StorageEntry>()
+        } else {
+            0
+        };
+        entry + n.variant().boxed_size()
+    }
+
+    /// Allocate a new `Node` in this `Context` without checking the memory limit.
+    fn alloc_unchecked<'s>(&'s self, n: Node<'_>) -> &'s Node<'s> {
+        if let Some(counts) = self.alloc_counts.borrow_mut().as_mut() {
+            counts[n.variant().kind_index()] += 1;
+        }
         let free = unsafe { &mut *self.free_nodes.get() };
         let nodes: &mut Deque<StorageEntry<'ast>> = unsafe { &mut *self.nodes.get() };
+        let boxed_size = n.variant().boxed_size();
         let node = unsafe { std::mem::transmute(n) };
+        self.allocated_since_gc
+            .set(self.allocated_since_gc.get() + 1);
+        self.bytes_since_gc
+            .set(self.bytes_since_gc.get() + std::mem::size_of::<StorageEntry>() + boxed_size);
+        self.boxed_bytes.set(self.boxed_bytes.get() + boxed_size);
         let entry: &StorageEntry<'ast> = if let Some(mut entry) = free.pop() {
             let entry: &mut StorageEntry<'ast> = unsafe { entry.as_mut() };
             debug_assert!(
@@ -228,6 +767,8 @@ impl<'ast> Context<'ast> {
             entry.inner = node;
             entry
         } else {
+            self.storage_bytes
+                .set(self.storage_bytes.get() + std::mem::size_of::<StorageEntry>());
             let entry: &StorageEntry = nodes.push(StorageEntry {
                 ctx_id_markbit: Cell::new(self.id),
                 count: Cell::new(0),
@@ -241,12 +782,50 @@ impl<'ast> Context<'ast> {
         unsafe { std::mem::transmute(&entry.inner) }
     }
 
+    /// Allocate a list element in the context with the provided previous element if it exists,
+    /// failing if the memory limit would be exceeded.
+    /// `prev` will be updated to point to `node` as its next element.
+    pub(crate) fn try_append_list_element<'a>(
+        &'a self,
+        prev: Option<&'a NodeListElement<'a>>,
+        node: &'a Node<'a>,
+    ) -> Result<&'a NodeListElement<'a>, MemoryLimitExceeded> {
+        let size = self.new_list_element_size();
+        if size != 0 {
+            self.check_memory_limit(size)?;
+        }
+        Ok(self.append_list_element_unchecked(prev, node))
+    }
+
     /// Allocate a list element in the context with the provided previous element if it exists.
     /// `prev` will be updated to point to `node` as its next element.
     pub(crate) fn append_list_element<'a>(
         &'a self,
         prev: Option<&'a NodeListElement<'a>>,
         node: &'a Node<'a>,
+    ) -> &'a NodeListElement<'a> {
+        let size = self.new_list_element_size();
+        if size != 0 {
+            self.enforce_memory_limit(size);
+        }
+        self.append_list_element_unchecked(prev, node)
+    }
+
+    /// Bytes which allocating a list element adds to the storage: an entry unless there is a
+    /// free one.
+    fn new_list_element_size(&self) -> usize {
+        if unsafe { &*self.free_list_elements.get() }.is_empty() {
+            std::mem::size_of::<NodeListElement>()
+        } else {
+            0
+        }
+    }
+
+    /// Allocate a list element without checking the memory limit.
+    fn append_list_element_unchecked<'a>(
+        &'a self,
+        prev: Option<&'a NodeListElement<'a>>,
+        node: &'a Node<'a>,
     ) -> &'a NodeListElement<'a> {
         let elements: &mut Deque<NodeListElement<'ast>> = unsafe { &mut *self.list_elements.get() };
         let free = unsafe { &mut *self.free_list_elements.get() };
@@ -254,6 +833,8 @@ impl<'ast> Context<'ast> {
         // this path and only one GCLock can be made available at a time per thread.
         let node: &'ast Node<'ast> = unsafe { std::mem::transmute(node) };
         let prev: Option<&'ast NodeListElement<'ast>> = unsafe { std::mem::transmute(prev) };
+        self.bytes_since_gc
+            .set(self.bytes_since_gc.get() + std::mem::size_of::<NodeListElement>());
         let entry = if let Some(mut entry) = free.pop() {
             let entry: &mut NodeListElement<'ast> = unsafe { entry.as_mut() };
             debug_assert!(
@@ -269,6 +850,8 @@ impl<'ast> Context<'ast> {
             }
             entry
         } else {
+            let size = std::mem::size_of::<NodeListElement>();
+            self.storage_bytes.set(self.storage_bytes.get() + size);
             let entry = elements.push(NodeListElement {
                 ctx_id_markbit: Cell::new(self.id),
                 inner: node,
@@ -335,107 +918,283 @@ impl<'ast> Context<'ast> {
         self.strict_mode = true;
     }
 
-    pub fn gc(&mut self) {
-        let nodes = unsafe { &mut *self.nodes.get() };
-        let free_nodes = unsafe { &mut *self.free_nodes.get() };
+    /// Return true if the Annex B web compatibility extensions are enabled.
+    /// They are enabled by default. Apart from `__proto__` in object literals,
+    /// they only apply to non-strict code.
+    pub fn annex_b(&self) -> bool {
+        self.annex_b
+    }
 
-        let list_elements = unsafe { &mut *self.list_elements.get() };
-        let free_list_elements = unsafe { &mut *self.free_list_elements.get() };
+    /// Enable or disable the Annex B web compatibility extensions.
+    pub fn set_annex_b(&mut self, annex_b: bool) {
+        self.annex_b = annex_b;
+    }
 
-        {
-            // Begin by collecting all the roots: entries with non-zero refcount.
-            let mut roots: Vec<&StorageEntry> = vec![];
-            for entry in nodes.iter() {
-                if entry.is_free() {
-                    continue;
-                }
-                debug_assert!(
-                    entry.markbit() != self.markbit_marked,
-                    "Entry marked before start of GC: \
-                        {:?}\nentry.markbit()={}\nmarkbit_marked={}",
-                    &entry,
-                    entry.markbit(),
-                    self.markbit_marked,
-                );
-                if entry.count.get() > 0 {
-                    // Transmuting the lifetime here because we have to store the roots from
-                    // across accesses to `nodes`, meaning we must translate
-                    // from `'ast` to the lifetime of this scope.
-                    roots.push(unsafe { std::mem::transmute(entry) });
-                }
-            }
+    /// Return true if the names of parsed identifiers point into the source buffer.
+    pub fn source_atoms(&self) -> bool {
+        self.source_atoms
+    }
 
-            struct Marker {
-                markbit_marked: bool,
-            }
+    /// Make the names of identifiers which appear verbatim in the source point into its
+    /// buffer when converting a parsed AST, instead of copying them into the atom table,
+    /// which then keeps the buffer alive. Off by default.
+    /// This saves memory on parse-only workloads, unless the buffer is freed anyway.
+    pub fn set_source_atoms(&mut self, source_atoms: bool) {
+        self.source_atoms = source_atoms;
+    }
 
-            impl<'gc> Visitor<'gc> for Marker {
-                fn call(
-                    &mut self,
-                    gc: &'gc GCLock,
-                    node: &'gc Node<'gc>,
-                    _path: Option<Path<'gc>>,
-                ) {
-                    let entry = unsafe { StorageEntry::from_node(node) };
-                    if entry.markbit() == self.markbit_marked {
-                        // Stop visiting early if we've already marked this part,
-                        // because we must have also marked all the children.
-                        return;
-                    }
-                    entry.set_markbit(self.markbit_marked);
-                    node.mark_lists(gc, |elem| {
-                        elem.set_markbit(self.markbit_marked);
-                    });
-                    node.visit_children(gc, self);
+    /// Set the token polled by validation, semantic resolution and passes,
+    /// which stop early once it is cancelled, leaving their results incomplete.
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Whether the cancellation token of this context has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(&self.cancellation, Some(token) if token.is_cancelled())
+    }
+
+    /// Set the maximum depth of the traversals of the AST, or `None` for no limit.
+    /// Traversals grow their stack as needed (see [`grow_stack`]), so the limit bounds the
+    /// time and memory spent on deeply nested inputs rather than protecting the stack.
+    /// Past the limit, [`Node::visit_children`], validation and dumping don't visit the
+    /// children of a node, and a [`TooDeep`] error is recorded, which
+    /// [`Context::take_too_deep`] returns. Mutable visitors ignore the limit.
+    pub fn set_max_depth(&mut self, limit: Option<usize>) {
+        self.max_depth = limit;
+    }
+
+    /// Return the maximum depth of the traversals of the AST, if any.
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Return the first [`TooDeep`] error recorded since the last call, if any.
+    /// When there is one, the results of the traversals since then may be incomplete.
+    pub fn take_too_deep(&self) -> Option<TooDeep> {
+        self.too_deep.take()
+    }
+
+    /// Enter the children of `node` in a traversal, until the returned guard is dropped.
+    /// If this exceeds the depth limit, records and returns the error instead.
+    pub(crate) fn enter_children(&self, node: &Node) -> Result<DepthGuard<'_>, TooDeep> {
+        let depth = self.depth.get();
+        match self.max_depth {
+            Some(limit) if depth >= limit => {
+                let e = TooDeep {
+                    limit,
+                    range: *node.range(),
+                };
+                if self.too_deep.get().is_none() {
+                    self.too_deep.set(Some(e));
                 }
+                Err(e)
+            }
+            _ => {
+                self.depth.set(depth + 1);
+                Ok(DepthGuard(&self.depth))
             }
+        }
+    }
 
-            // Use a visitor to mark every node reachable from roots.
-            let mut marker = Marker {
-                markbit_marked: self.markbit_marked,
-            };
-            {
-                let gc: GCLock = GCLock::new(self);
-                for root in roots {
-                    root.inner.visit(&gc, &mut marker, None);
-                }
+    /// Return the policy for automatic collections.
+    pub fn gc_policy(&self) -> GcPolicy {
+        self.gc_policy
+    }
+
+    /// Set the policy for automatic collections.
+    pub fn set_gc_policy(&mut self, policy: GcPolicy) {
+        self.gc_policy = policy;
+    }
+
+    /// Return how mutable visitors treat frozen nodes.
+    pub fn frozen_policy(&self) -> FrozenPolicy {
+        self.frozen_policy
+    }
+
+    /// Set how mutable visitors treat frozen nodes.
+    pub fn set_frozen_policy(&mut self, policy: FrozenPolicy) {
+        self.frozen_policy = policy;
+    }
+
+    /// Return true if the `GcPolicy` threshold has been reached.
+    fn policy_threshold_reached(&self) -> bool {
+        match self.gc_policy {
+            GcPolicy::Auto => {
+                self.allocated_since_gc.get() >= self.live_after_gc.max(SAFEPOINT_MIN_ALLOCATIONS)
             }
+            GcPolicy::Manual => false,
+            GcPolicy::NodeCount(n) => self.allocated_since_gc.get() >= n,
+            GcPolicy::AllocatedBytes(n) => self.bytes_since_gc.get() >= n,
+        }
+    }
+
+    /// Free every node which isn't reachable from a [`NodeRc`].
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is an active `GCLock` on this thread,
+    /// use [`GCLock::gc`] to collect while holding a lock.
+    pub fn gc(&mut self) {
+        GCLock::new(self).gc();
+    }
+
+    /// A safepoint: collect garbage if required by the `GcPolicy`,
+    /// see [`GCLock::collect_if_needed`].
+    /// Returns `true` if a collection was performed.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there is an active `GCLock` on this thread.
+    pub fn collect_if_needed(&mut self) -> bool {
+        GCLock::new(self).collect_if_needed()
+    }
+
+    /// Enable or disable recording where every [`NodeRc`] is created.
+    /// When enabled, dropping the `Context` with outstanding `NodeRc`s prints the source
+    /// location (and tag, see [`Context::set_noderc_tag`]) where each of them was created.
+    /// Only `NodeRc`s created while tracking is enabled are recorded.
+    pub fn set_track_noderc_origins(&mut self, enabled: bool) {
+        let mut origins = self.noderc_count.origins.borrow_mut();
+        match (enabled, origins.is_some()) {
+            (true, false) => *origins = Some(HashMap::new()),
+            (false, true) => *origins = None,
+            _ => {}
+        }
+    }
+
+    /// Enable or disable counting the nodes allocated of each kind, see
+    /// [`Context::alloc_histogram`]. Enabling it again resets the counts.
+    pub fn set_track_alloc_stats(&mut self, enabled: bool) {
+        *self.alloc_counts.get_mut() = if enabled {
+            Some(vec![0; NodeVariant::KINDS.len()])
+        } else {
+            None
+        };
+    }
+
+    /// The number of nodes of each kind allocated since [`Context::set_track_alloc_stats`]
+    /// was called (zero if it wasn't), and the number and size of the live ones.
+    /// Freed nodes are only known after a collection, so call [`Context::gc`] first to
+    /// count only the reachable nodes as live.
+    pub fn alloc_histogram(&self) -> AllocHistogram {
+        let nodes = unsafe { &*self.nodes.get() };
+        let mut live = vec![0; NodeVariant::KINDS.len()];
+        for entry in nodes.iter().filter(|entry| !entry.is_free()) {
+            live[entry.inner.variant().kind_index()] += 1;
         }
+        let allocated = self.alloc_counts.borrow();
+        let mut kinds = NodeVariant::KINDS
+            .iter()
+            .enumerate()
+            .map(|(i, &variant)| KindAllocStats {
+                variant,
+                allocated: allocated.as_ref().map_or(0, |counts| counts[i]),
+                live: live[i],
+                live_bytes: live[i] * (NODE_STORAGE_SIZE + variant.boxed_size()),
+                live_payload_bytes: live[i] * variant.payload_size(),
+            })
+            .filter(|stats| stats.allocated != 0 || stats.live != 0)
+            .collect::<Vec<_>>();
+        kinds.sort_by_key(|stats| Reverse((stats.live_bytes, stats.allocated)));
+        AllocHistogram { kinds }
+    }
 
-        for entry in nodes.iter_mut() {
-            if entry.is_free() {
-                // Skip free entries.
-                continue;
-            }
-            if entry.count.get() > 0 {
-                // Keep referenced entries alive.
-                continue;
+    /// Measure the use of the storage of this context.
+    /// Freed nodes are only known after a collection, see [`Context::alloc_histogram`].
+    pub fn heap_stats(&self) -> HeapStats {
+        let nodes = unsafe { &*self.nodes.get() };
+        let list_elements = unsafe { &*self.list_elements.get() };
+        let mut stats = HeapStats {
+            node_entry_size: NODE_STORAGE_SIZE,
+            list_element_entry_size: LIST_ELEMENT_STORAGE_SIZE,
+            ..Default::default()
+        };
+        for entry in nodes.iter() {
+            stats.node_entries += 1;
+            if !entry.is_free() {
+                stats.live_nodes += 1;
+                stats.live_payload_bytes += entry.inner.variant().payload_size();
+                stats.live_boxed_bytes += entry.inner.variant().boxed_size();
             }
-            if entry.markbit() == self.markbit_marked {
-                // Keep marked entries alive.
-                continue;
+        }
+        for element in list_elements.iter() {
+            stats.list_element_entries += 1;
+            if !element.is_free() {
+                stats.live_list_elements += 1;
             }
-            // Passed all checks, this entry is free.
-            entry.ctx_id_markbit.set(FREE_ENTRY);
-            free_nodes.push(unsafe { NonNull::new_unchecked(entry as *mut StorageEntry) });
         }
+        stats
+    }
 
-        for element in list_elements.iter_mut() {
-            if element.is_free() {
-                // Skip free entries.
-                continue;
+    /// Set the tag recorded for `NodeRc`s created from now on, when origin tracking is enabled.
+    /// Useful to identify the pass or phase which created a leaked `NodeRc`.
+    pub fn set_noderc_tag(&self, tag: Option<&str>) {
+        *self.noderc_count.tag.borrow_mut() = tag.map(Rc::from);
+    }
+
+    /// Free every node and list element in the context at once.
+    /// This is much cheaper than dropping the `Context` and creating a new one, because the
+    /// storage is kept for reuse.
+    /// The source manager is not affected.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if there are any `NodeRc`s into this `Context`.
+    pub fn reset(&mut self, clear_atoms: ClearAtoms) {
+        assert!(
+            self.noderc_count.count.get() == 0,
+            "NodeRc must not outlive Context::reset"
+        );
+        self.reset_unchecked(clear_atoms);
+    }
+
+    /// Free every node and list element in the context at once, like [`Context::reset`],
+    /// or return an error and leave the context unchanged if there are any `NodeRc`s into it.
+    pub fn try_reset(&mut self, clear_atoms: ClearAtoms) -> Result<(), LiveNodeRcs> {
+        self.check_no_noderc()?;
+        self.reset_unchecked(clear_atoms);
+        Ok(())
+    }
+
+    fn reset_unchecked(&mut self, clear_atoms: ClearAtoms) {
+        self.nodes.get_mut().clear();
+        self.free_nodes.get_mut().clear();
+        self.list_elements.get_mut().clear();
+        self.free_list_elements.get_mut().clear();
+        self.userdata.get_mut().clear();
+        self.allocated_since_gc.set(0);
+        self.bytes_since_gc.set(0);
+        self.live_after_gc = 0;
+        self.storage_bytes.set(0);
+        self.boxed_bytes.set(0);
+        if clear_atoms == ClearAtoms::Yes {
+            self.atom_table.clear();
+        }
+    }
+
+    /// Return an error if there are any `NodeRc`s or `NodeRcList`s into this context.
+    pub fn check_no_noderc(&self) -> Result<(), LiveNodeRcs> {
+        match self.noderc_count.count.get() {
+            0 => Ok(()),
+            count => Err(LiveNodeRcs { count }),
+        }
+    }
+
+    /// Drop the context, or return an error if there are any `NodeRc`s into it instead of
+    /// panicking like `drop` does.
+    /// On error the context is leaked, so that the remaining `NodeRc`s stay valid.
+    pub fn try_close(self) -> Result<(), LiveNodeRcs> {
+        match self.check_no_noderc() {
+            Ok(()) => {
+                drop(self);
+                Ok(())
             }
-            if element.markbit() == self.markbit_marked {
-                // Keep marked entries alive.
-                continue;
+            Err(e) => {
+                std::mem::forget(self);
+                Err(e)
             }
-            // Passed all checks, this element is free.
-            element.ctx_id_markbit.set(FREE_ENTRY);
-            free_list_elements
-                .push(unsafe { NonNull::new_unchecked(element as *mut NodeListElement) });
         }
-
-        self.markbit_marked = !self.markbit_marked;
     }
 
     /// Returns the number of node slots which have been allocated.
@@ -446,13 +1205,13 @@ impl<'ast> Context<'ast> {
     }
 
     /// Returns the approximate size of just the AST storages in bytes.
-    /// Includes the allocated nodes, lists, as well as free lists for both.
+    /// Includes the allocated nodes and their boxes, lists, as well as free lists for both.
     pub fn storage_size(&self) -> usize {
         let nodes = unsafe { &*self.nodes.get() };
         let free_nodes = unsafe { &*self.free_nodes.get() };
         let list_elements = unsafe { &*self.list_elements.get() };
         let free_list_elements = unsafe { &*self.free_list_elements.get() };
-        let mut result = 0;
+        let mut result = self.boxed_bytes.get();
         result += nodes.heap_size();
         result += free_nodes.heap_size();
         result += list_elements.heap_size();
@@ -467,7 +1226,7 @@ impl HeapSize for Context<'_> {
         let free_nodes = unsafe { &*self.free_nodes.get() };
         let list_elements = unsafe { &*self.list_elements.get() };
         let free_list_elements = unsafe { &*self.free_list_elements.get() };
-        let mut result = 0;
+        let mut result = self.boxed_bytes.get();
         result += nodes.heap_size();
         result += free_nodes.heap_size();
         result += list_elements.heap_size();
@@ -488,6 +1247,11 @@ impl Drop for Context<'_> {
     /// Will panic if there are any `NodeRc`s stored when this `Context` is dropped.
     fn drop(&mut self) {
         if self.noderc_count.count.get() > 0 {
+            if let Some(origins) = self.noderc_count.origins.borrow().as_ref() {
+                for origin in origins.values().flatten() {
+                    eprintln!("NodeRc outlives Context, created at {}", origin);
+                }
+            }
             #[cfg(debug_assertions)]
             {
                 // In debug mode, provide more information on which node was leaked.
@@ -509,6 +1273,32 @@ impl Drop for Context<'_> {
 thread_local! {
     /// Whether there exists a `GCLock` on the current thread.
     static GCLOCK_IN_USE: Cell<bool> = Cell::new(false);
+
+    /// Where the active `GCLock` of the current thread was created, if origins are tracked.
+    static GCLOCK_ORIGIN: Cell<Option<&'static Location<'static>>> = const { Cell::new(None) };
+}
+
+/// Whether to record where each `GCLock` is created, see [`GCLock::set_track_origins`].
+static TRACK_GCLOCK_ORIGINS: AtomicBool = AtomicBool::new(false);
+
+/// Error returned when attempting to create a [`GCLock`] while another one is active
+/// on the same thread.
+#[derive(Debug, Copy, Clone, Error)]
+#[error(
+    "Attempt to create multiple GCLocks in a single thread{}",
+    origin_suffix(.origin)
+)]
+pub struct GCLockInUse {
+    /// Where the active `GCLock` was created, if origins are tracked.
+    pub origin: Option<&'static Location<'static>>,
+}
+
+/// Describe `origin` at the end of an error message.
+fn origin_suffix(origin: &Option<&'static Location<'static>>) -> String {
+    match origin {
+        Some(origin) => format!(", the active one was created at {}", origin),
+        None => String::new(),
+    }
 }
 
 /// A way to view the [`Context`].
@@ -526,6 +1316,58 @@ impl Drop for GCLock<'_, '_> {
         GCLOCK_IN_USE.with(|flag| {
             flag.set(false);
         });
+        GCLOCK_ORIGIN.with(|origin| origin.set(None));
+    }
+}
+
+/// Checks that a scope, like a job of a thread pool or the poll of a future, neither starts
+/// nor ends with an active [`GCLock`] on its thread.
+///
+/// Worker threads are reused, so a `GCLock` which is leaked by one job, or held while
+/// yielding to the executor, would make an unrelated job fail later on the same thread.
+/// The guard reports the overlap where it happens instead; enable
+/// [`GCLock::set_track_origins`] to know where the offending lock was created.
+///
+/// ```
+/// # use juno_ast::*;
+/// let _guard = GCLockGuard::enter().unwrap();
+/// let mut ctx = Context::new();
+/// ctx.with_gc(|_gc| ());
+/// ```
+#[must_use]
+pub struct GCLockGuard {
+    /// `GCLock`s are per thread, so the guard must not move to another one.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl GCLockGuard {
+    /// Enter a scope, or return an error if there is already an active `GCLock` on this
+    /// thread.
+    pub fn enter() -> Result<Self, GCLockInUse> {
+        GCLock::check_not_in_use()?;
+        Ok(GCLockGuard {
+            _not_send: Default::default(),
+        })
+    }
+
+    /// Leave the scope, or return an error if a `GCLock` created in it is still active.
+    pub fn exit(self) -> Result<(), GCLockInUse> {
+        std::mem::forget(self);
+        GCLock::check_not_in_use()
+    }
+}
+
+impl Drop for GCLockGuard {
+    /// # Panics
+    ///
+    /// Will panic if a `GCLock` created in the scope is still active, unless the thread is
+    /// already panicking.
+    fn drop(&mut self) {
+        if let Err(e) = GCLock::check_not_in_use() {
+            if !std::thread::panicking() {
+                panic!("{} when leaving a GCLockGuard", e);
+            }
+        }
     }
 }
 
@@ -533,14 +1375,51 @@ impl<'ast, 'ctx> GCLock<'ast, 'ctx> {
     /// # Panics
     ///
     /// Will panic if there is already an active `GCLock` on this thread.
+    #[track_caller]
     pub fn new(ctx: &'ctx mut Context<'ast>) -> Self {
+        match Self::try_new(ctx) {
+            Ok(lock) => lock,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a `GCLock`, or return an error if there is already an active `GCLock`
+    /// on this thread.
+    #[track_caller]
+    pub fn try_new(ctx: &'ctx mut Context<'ast>) -> Result<Self, GCLockInUse> {
         GCLOCK_IN_USE.with(|flag| {
             if flag.get() {
-                panic!("Attempt to create multiple GCLocks in a single thread");
+                return Err(GCLockInUse {
+                    origin: GCLOCK_ORIGIN.with(Cell::get),
+                });
             }
             flag.set(true);
-        });
-        GCLock { ctx }
+            Ok(())
+        })?;
+        if TRACK_GCLOCK_ORIGINS.load(Ordering::Relaxed) {
+            let caller = Location::caller();
+            GCLOCK_ORIGIN.with(|origin| origin.set(Some(caller)));
+        }
+        Ok(GCLock { ctx })
+    }
+
+    /// Whether to record where each `GCLock` is created, on every thread, so that the
+    /// error or panic on creating a second one on a thread names the call site of the
+    /// first one.
+    /// Off by default, since it adds a little work to every lock.
+    pub fn set_track_origins(enabled: bool) {
+        TRACK_GCLOCK_ORIGINS.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Return an error if there is an active `GCLock` on this thread.
+    pub fn check_not_in_use() -> Result<(), GCLockInUse> {
+        if GCLOCK_IN_USE.with(Cell::get) {
+            Err(GCLockInUse {
+                origin: GCLOCK_ORIGIN.with(Cell::get),
+            })
+        } else {
+            Ok(())
+        }
     }
 
     /// Allocate a node in the `ctx`.
@@ -549,6 +1428,15 @@ impl<'ast, 'ctx> GCLock<'ast, 'ctx> {
         self.ctx.alloc(n)
     }
 
+    /// Allocate a node in the `ctx`, failing if the memory limit would be exceeded.
+    #[inline]
+    pub(crate) fn try_alloc<'s>(
+        &'s self,
+        n: Node<'s>,
+    ) -> Result<&'s Node<'s>, MemoryLimitExceeded> {
+        self.ctx.try_alloc(n)
+    }
+
     /// Append `node` to the `prev` element if provided, else create the element as the first
     /// element in the `NodeList`.
     #[inline]
@@ -560,6 +1448,17 @@ impl<'ast, 'ctx> GCLock<'ast, 'ctx> {
         self.ctx.append_list_element(prev, n)
     }
 
+    /// Append `node` to the `prev` element if provided, else create the element as the first
+    /// element in the `NodeList`, failing if the memory limit would be exceeded.
+    #[inline]
+    pub(crate) fn try_append_list_element<'s>(
+        &'s self,
+        prev: Option<&'s NodeListElement<'s>>,
+        n: &'s Node<'s>,
+    ) -> Result<&'s NodeListElement<'s>, MemoryLimitExceeded> {
+        self.ctx.try_append_list_element(prev, n)
+    }
+
     /// Return a reference to the owning Context.
     pub fn ctx(&self) -> &Context<'ast> {
         self.ctx
@@ -600,6 +1499,242 @@ impl<'ast, 'ctx> GCLock<'ast, 'ctx> {
     pub fn sm_mut(&mut self) -> &mut SourceManager {
         self.ctx.sm_mut()
     }
+
+    /// Freeze `node` and every node reachable from it, so that mutable visitors don't
+    /// replace them, as configured by [`Context::set_frozen_policy`].
+    /// Used to protect code which later passes must leave alone, like injected helpers.
+    /// Copies of frozen nodes, e.g. made by [`crate::deep_clone`], aren't frozen.
+    /// In a forked context, nodes of the parent are left as they are.
+    pub fn freeze<'gc>(&'gc self, node: &'gc Node<'gc>) {
+        struct Freezer {
+            ctx_id: u32,
+        }
+
+        impl<'gc> Visitor<'gc> for Freezer {
+            fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+                let entry = unsafe { StorageEntry::from_node(node) };
+                if entry.ctx_id() != self.ctx_id || entry.is_frozen() {
+                    return;
+                }
+                entry.freeze();
+                // Not limited by the depth of the context, like marking.
+                crate::grow_stack(|| node.visit_fields(gc, self));
+            }
+        }
+
+        node.visit(
+            self,
+            &mut Freezer {
+                ctx_id: self.ctx.id,
+            },
+            None,
+        );
+    }
+
+    /// Whether `node` was frozen with [`GCLock::freeze`].
+    pub fn is_frozen<'gc>(&self, node: &'gc Node<'gc>) -> bool {
+        unsafe { StorageEntry::from_node(node) }.is_frozen()
+    }
+
+    /// Attach `value` to `node`, replacing any value of the same type.
+    /// Each node can hold one value of each type, e.g. the results of an analysis, until it
+    /// is freed. Copies of the node don't have the value.
+    pub fn set_userdata<'gc, T: 'static>(&self, node: &'gc Node<'gc>, value: T) {
+        self.ctx
+            .userdata
+            .borrow_mut()
+            .insert(userdata_key::<T>(node), Rc::new(value));
+    }
+
+    /// Return the value of type `T` attached to `node`, if any.
+    pub fn get_userdata<'gc, T: 'static>(&self, node: &'gc Node<'gc>) -> Option<Rc<T>> {
+        let value = self
+            .ctx
+            .userdata
+            .borrow()
+            .get(&userdata_key::<T>(node))?
+            .clone();
+        Some(value.downcast().unwrap())
+    }
+
+    /// Remove the value of type `T` attached to `node` and return it, if any.
+    pub fn remove_userdata<'gc, T: 'static>(&self, node: &'gc Node<'gc>) -> Option<Rc<T>> {
+        let value = self
+            .ctx
+            .userdata
+            .borrow_mut()
+            .remove(&userdata_key::<T>(node))?;
+        Some(value.downcast().unwrap())
+    }
+
+    /// Run a garbage collection while holding the lock.
+    ///
+    /// This is a safepoint: it takes `&mut self`, so no `&Node` obtained from this lock
+    /// can be alive at this point and the only roots are the [`NodeRc`]s.
+    /// Any node which must survive the collection has to be converted to a `NodeRc` first.
+    pub fn gc(&mut self) {
+        let ctx: &Context = self.ctx;
+        let nodes = unsafe { &mut *ctx.nodes.get() };
+        let free_nodes = unsafe { &mut *ctx.free_nodes.get() };
+
+        let list_elements = unsafe { &mut *ctx.list_elements.get() };
+        let free_list_elements = unsafe { &mut *ctx.free_list_elements.get() };
+
+        {
+            // Begin by collecting all the roots: entries with non-zero refcount.
+            let mut roots: Vec<&StorageEntry> = vec![];
+            for entry in nodes.iter() {
+                if entry.is_free() {
+                    continue;
+                }
+                debug_assert!(
+                    entry.markbit() != ctx.markbit_marked,
+                    "Entry marked before start of GC: \
+                        {:?}\nentry.markbit()={}\nmarkbit_marked={}",
+                    &entry,
+                    entry.markbit(),
+                    ctx.markbit_marked,
+                );
+                if entry.count.get() > 0 {
+                    // Transmuting the lifetime here because we have to store the roots from
+                    // across accesses to `nodes`, meaning we must translate
+                    // from `'ast` to the lifetime of this scope.
+                    roots.push(unsafe { std::mem::transmute(entry) });
+                }
+            }
+
+            // Entries in `NodeRcList`s are roots as well.
+            let mut root_lists = ctx.root_lists.borrow_mut();
+            root_lists.retain(|list| list.strong_count() > 0);
+            for list in root_lists.iter().filter_map(Weak::upgrade) {
+                for entry in list.borrow().iter() {
+                    roots.push(unsafe { &*(entry.as_ptr() as *const StorageEntry) });
+                }
+            }
+            drop(root_lists);
+
+            struct Marker {
+                markbit_marked: bool,
+                /// Nodes of other contexts, i.e. of the ancestors of a fork, are left alone.
+                ctx_id: u32,
+            }
+
+            impl<'gc> Visitor<'gc> for Marker {
+                fn call(
+                    &mut self,
+                    gc: &'gc GCLock,
+                    node: &'gc Node<'gc>,
+                    _path: Option<Path<'gc>>,
+                ) {
+                    let entry = unsafe { StorageEntry::from_node(node) };
+                    if entry.ctx_id() != self.ctx_id {
+                        return;
+                    }
+                    if entry.markbit() == self.markbit_marked {
+                        // Stop visiting early if we've already marked this part,
+                        // because we must have also marked all the children.
+                        return;
+                    }
+                    entry.set_markbit(self.markbit_marked);
+                    node.mark_lists(gc, |elem| {
+                        if elem.ctx_id() == self.ctx_id {
+                            elem.set_markbit(self.markbit_marked);
+                        }
+                    });
+                    // Every reachable node must be marked, however deep, so this ignores
+                    // the depth limit of the context.
+                    crate::grow_stack(|| node.visit_fields(gc, self));
+                }
+            }
+
+            // Use a visitor to mark every node reachable from roots.
+            let mut marker = Marker {
+                markbit_marked: ctx.markbit_marked,
+                ctx_id: ctx.id,
+            };
+            for root in roots {
+                root.inner.visit(self, &mut marker, None);
+            }
+        }
+
+        for entry in nodes.iter_mut() {
+            if entry.is_free() {
+                // Skip free entries.
+                continue;
+            }
+            if entry.count.get() > 0 {
+                // Keep referenced entries alive.
+                continue;
+            }
+            if entry.markbit() == ctx.markbit_marked {
+                // Keep marked entries alive.
+                continue;
+            }
+            // Passed all checks, this entry is free.
+            entry.ctx_id_markbit.set(FREE_ENTRY);
+            let boxed_size = entry.inner.variant().boxed_size();
+            if boxed_size != 0 {
+                // Free the box now rather than when the entry is reused.
+                entry.inner = Node::Empty(Empty {
+                    metadata: NodeMetadata {
+                        phantom: PhantomData,
+                        range: *entry.inner.range(),
+                        synthesized: false,
+                    },
+                });
+                ctx.boxed_bytes.set(ctx.boxed_bytes.get() - boxed_size);
+            }
+            free_nodes.push(unsafe { NonNull::new_unchecked(entry as *mut StorageEntry) });
+        }
+
+        for element in list_elements.iter_mut() {
+            if element.is_free() {
+                // Skip free entries.
+                continue;
+            }
+            if element.markbit() == ctx.markbit_marked {
+                // Keep marked entries alive.
+                continue;
+            }
+            // Passed all checks, this element is free.
+            element.ctx_id_markbit.set(FREE_ENTRY);
+            free_list_elements
+                .push(unsafe { NonNull::new_unchecked(element as *mut NodeListElement) });
+        }
+
+        // Values attached to freed nodes must not be seen by the nodes reusing their entries.
+        ctx.userdata.borrow_mut().retain(|(entry, _), _| {
+            !unsafe { &*(entry.as_ptr() as *const StorageEntry) }.is_free()
+        });
+
+        let live = nodes.len() - free_nodes.len();
+        self.ctx.markbit_marked = !self.ctx.markbit_marked;
+        self.ctx.live_after_gc = live;
+        self.ctx.allocated_since_gc.set(0);
+        self.ctx.bytes_since_gc.set(0);
+    }
+
+    /// Run [`GCLock::gc`] if the `GcPolicy` threshold has been reached.
+    /// Returns `true` if a collection was performed.
+    pub fn collect_if_needed(&mut self) -> bool {
+        if !self.ctx.policy_threshold_reached() {
+            return false;
+        }
+        self.gc();
+        true
+    }
+}
+
+/// The address of the `StorageEntry` of a node and the type of a value attached to it.
+type UserdataKey = (NonNull<c_void>, TypeId);
+
+/// Key of the value of type `T` attached to `node` in `Context::userdata`.
+fn userdata_key<'gc, T: 'static>(node: &'gc Node<'gc>) -> UserdataKey {
+    let entry = unsafe { StorageEntry::from_node(node) };
+    (
+        unsafe { NonNull::new_unchecked(entry as *const StorageEntry as *mut c_void) },
+        TypeId::of::<T>(),
+    )
 }
 
 /// A wrapper around Node&, with "shallow" hashing and equality, suitable for
@@ -683,11 +1818,13 @@ impl Drop for NodeRc {
         let c = noderc_count.count.get();
         debug_assert!(c > 0);
         noderc_count.count.set(c - 1);
+        noderc_count.forget_origin(self.entry.as_ptr() as usize);
     }
 }
 
 impl Clone for NodeRc {
     /// Cloning a `NodeRc` increments refcounts on the entry and the context.
+    #[track_caller]
     fn clone(&self) -> Self {
         let mut cloned = NodeRc { ..*self };
 
@@ -698,6 +1835,7 @@ impl Clone for NodeRc {
         let noderc_count = unsafe { cloned.counter.as_mut() };
         let c = noderc_count.count.get();
         noderc_count.count.set(c + 1);
+        noderc_count.record_origin(cloned.entry.as_ptr() as usize);
 
         cloned
     }
@@ -705,6 +1843,7 @@ impl Clone for NodeRc {
 
 impl NodeRc {
     /// Turn a node reference into a `NodeRc` for storage outside `GCLock`.
+    #[track_caller]
     pub fn from_node<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> NodeRc {
         let inner_offset = offset_of!(StorageEntry, inner) as isize;
         let inner = node as *const Node<'gc>;
@@ -718,17 +1857,33 @@ impl NodeRc {
     ///
     /// # Panics
     ///
-    /// Will panic if `gc` is not for the same context as this `NodeRc` was created in.
-    pub fn node<'gc>(&'_ self, gc: &'gc GCLock<'_, '_>) -> &'gc Node {
+    /// Will panic if `gc` is not for the same context as this `NodeRc` was created in,
+    /// or for a context forked from it.
+    pub fn node<'gc>(&'_ self, gc: &'gc GCLock<'_, '_>) -> &'gc Node<'gc> {
+        match self.try_node(gc) {
+            Ok(node) => node,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Return the actual `Node` that `self` points to, or an error if `gc` is not for the
+    /// context this `NodeRc` was created in or a context forked from it.
+    pub fn try_node<'gc>(
+        &'_ self,
+        gc: &'gc GCLock<'_, '_>,
+    ) -> Result<&'gc Node<'gc>, ContextMismatch> {
         unsafe {
-            assert_eq!(
-                self.counter.as_ref().ctx_id,
-                gc.ctx.id,
-                "Attempt to derefence NodeRc allocated context {} in context {}",
-                self.counter.as_ref().ctx_id,
-                gc.ctx.id
-            );
-            &self.entry().as_ref().inner
+            let ctx_id = self.counter.as_ref().ctx_id;
+            if ctx_id != gc.ctx.id && !gc.ctx.ancestors.contains(&ctx_id) {
+                return Err(ContextMismatch {
+                    node_ctx: ctx_id,
+                    gc_ctx: gc.ctx.id,
+                });
+            }
+            // The node can't be collected while `gc` is borrowed, even if `self` is dropped,
+            // so it lives as long as the other nodes obtained from `gc`.
+            let entry = self.entry().as_ptr() as *const StorageEntry<'gc>;
+            Ok(&(*entry).inner)
         }
     }
 
@@ -738,12 +1893,16 @@ impl NodeRc {
         NonNull::new_unchecked(outer)
     }
 
+    #[track_caller]
     unsafe fn from_entry(gc: &GCLock, entry: &StorageEntry<'_>) -> NodeRc {
         let c = entry.count.get();
         entry.count.set(c + 1);
 
         let c = gc.ctx.noderc_count.count.get();
         gc.ctx.noderc_count.count.set(c + 1);
+        gc.ctx
+            .noderc_count
+            .record_origin(entry as *const StorageEntry as usize);
 
         NodeRc {
             counter: NonNull::new_unchecked(gc.ctx.noderc_count.as_ref().get_ref()
@@ -753,3 +1912,105 @@ impl NodeRc {
         }
     }
 }
+
+/// Storage for the entries of a [`NodeRcList`], shared with the owning [`Context`].
+type RootListEntries = RefCell<Vec<NonNull<c_void>>>;
+
+/// A list of strong references to [`Node`]s, for holding many roots across collections.
+///
+/// Behaves like a `Vec<NodeRc>`, but the list is registered with the `Context` once,
+/// so pushing and popping nodes doesn't touch their refcounts.
+/// This makes it suitable for worklists which are kept between GCs.
+/// Like `NodeRc`, it must not outlive the `Context`.
+#[derive(Debug)]
+pub struct NodeRcList {
+    /// The `NodeRcCounter` counting for the `Context` to which this belongs.
+    counter: NonNull<NodeRcCounter>,
+
+    /// Pointers to the `StorageEntry`s of the nodes in the list.
+    entries: Rc<RootListEntries>,
+}
+
+impl NodeRcList {
+    /// Create an empty list registered with `ctx`.
+    pub fn new(ctx: &Context) -> NodeRcList {
+        let entries = Rc::new(RefCell::new(Vec::new()));
+        ctx.root_lists.borrow_mut().push(Rc::downgrade(&entries));
+        let c = ctx.noderc_count.count.get();
+        ctx.noderc_count.count.set(c + 1);
+        NodeRcList {
+            counter: unsafe {
+                NonNull::new_unchecked(ctx.noderc_count.as_ref().get_ref() as *const NodeRcCounter
+                    as *mut NodeRcCounter)
+            },
+            entries,
+        }
+    }
+
+    /// Panic if `gc` is not for the context this list was created in.
+    fn check_ctx(&self, gc: &GCLock) {
+        let ctx_id = unsafe { self.counter.as_ref().ctx_id };
+        assert_eq!(
+            ctx_id, gc.ctx.id,
+            "Attempt to use NodeRcList allocated context {} in context {}",
+            ctx_id, gc.ctx.id
+        );
+    }
+
+    /// Append `node` to the end of the list.
+    pub fn push<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        self.check_ctx(gc);
+        let entry = unsafe { StorageEntry::from_node(node) };
+        self.entries
+            .borrow_mut()
+            .push(unsafe { NonNull::new_unchecked(entry as *const StorageEntry as *mut c_void) });
+    }
+
+    /// Remove the last node from the list and return it.
+    pub fn pop<'gc>(&mut self, gc: &'gc GCLock) -> Option<&'gc Node<'gc>> {
+        self.check_ctx(gc);
+        let entry = self.entries.borrow_mut().pop()?;
+        Some(unsafe { &(*(entry.as_ptr() as *const StorageEntry<'gc>)).inner })
+    }
+
+    /// Return the node at `index`, if any.
+    pub fn get<'gc>(&self, gc: &'gc GCLock, index: usize) -> Option<&'gc Node<'gc>> {
+        self.check_ctx(gc);
+        let entry = *self.entries.borrow().get(index)?;
+        Some(unsafe { &(*(entry.as_ptr() as *const StorageEntry<'gc>)).inner })
+    }
+
+    /// Iterate over the nodes in the list, without copying it.
+    pub fn iter<'a, 'gc>(
+        &'a self,
+        gc: &'gc GCLock,
+    ) -> impl ExactSizeIterator<Item = &'gc Node<'gc>> + 'a {
+        self.check_ctx(gc);
+        // The list can't change while it is borrowed by the iterator.
+        let entries = self.entries.borrow();
+        (0..entries.len())
+            .map(move |i| unsafe { &(*(entries[i].as_ptr() as *const StorageEntry<'gc>)).inner })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// Remove every node from the list.
+    pub fn clear(&mut self) {
+        self.entries.borrow_mut().clear();
+    }
+}
+
+impl Drop for NodeRcList {
+    fn drop(&mut self) {
+        let noderc_count = unsafe { self.counter.as_ref() };
+        let c = noderc_count.count.get();
+        debug_assert!(c > 0);
+        noderc_count.count.set(c - 1);
+    }
+}
@@ -14,11 +14,15 @@
 ///       field1: type1[constraint_a, constraint_b],
 ///       field2: type2,
 ///     },
+///     #[boxed]
 ///     Node2,
 /// }
 /// }
 /// ```
 /// in order to avoid excessive copy/paste boilerplate.
+/// Kinds marked `#[boxed]` are stored in a `Box` by the `Node` enum, which is sized by the
+/// largest of the other kinds; every kind larger than `Identifier` must be boxed
+/// (see [`NodeVariant::is_boxed`]).
 /// Parents and constraints can be any member of `NodeVariant`,
 /// which includes any constructible `NodeKind`s` as well as interfaces like
 /// `Statement`, `Expression`, etc.
@@ -38,6 +42,7 @@ macro_rules! nodekind_defs {
             Module {
                 body: NodeList<'a>[Directive, Statement],
             },
+            #[boxed]
             FunctionExpression[Expression] {
                 id: Option<&'a Node<'a>>[Identifier],
                 params: NodeList<'a>[Pattern],
@@ -48,6 +53,7 @@ macro_rules! nodekind_defs {
                 generator: bool,
                 is_async: bool,
             },
+            #[boxed]
             ArrowFunctionExpression[Expression] {
                 id: Option<&'a Node<'a>>[Identifier],
                 params: NodeList<'a>[Pattern],
@@ -58,6 +64,7 @@ macro_rules! nodekind_defs {
                 expression: bool,
                 is_async: bool,
             },
+            #[boxed]
             FunctionDeclaration[Declaration] {
                 id: Option<&'a Node<'a>>[Identifier],
                 params: NodeList<'a>[Pattern],
@@ -68,6 +75,7 @@ macro_rules! nodekind_defs {
                 generator: bool,
                 is_async: bool,
             },
+            #[boxed]
             ComponentDeclaration[Declaration] {
                 id: &'a Node<'a>[Identifier],
                 params: NodeList<'a>[Pattern],
@@ -88,12 +96,14 @@ macro_rules! nodekind_defs {
                 right: &'a Node<'a>[Expression],
                 body: &'a Node<'a>[Statement],
             },
+            #[boxed]
             ForOfStatement[ForInStatement] {
                 left: &'a Node<'a>[VariableDeclaration, Pattern],
                 right: &'a Node<'a>[Expression],
                 body: &'a Node<'a>[Statement],
                 is_await: bool,
             },
+            #[boxed]
             ForStatement[Statement] {
                 init: Option<&'a Node<'a>>[VariableDeclaration, Expression],
                 test: Option<&'a Node<'a>>[Expression],
@@ -196,6 +206,7 @@ macro_rules! nodekind_defs {
                 type_arguments: Option<&'a Node<'a>>[TypeParameterInstantiation],
                 arguments: NodeList<'a>[Expression, SpreadElement],
             },
+            #[boxed]
             OptionalCallExpression[Expression] {
                 callee: &'a Node<'a>[Expression, Super],
                 type_arguments: Option<&'a Node<'a>>[TypeParameterInstantiation],
@@ -298,6 +309,7 @@ macro_rules! nodekind_defs {
                 method: bool,
                 shorthand: bool,
             },
+            #[boxed]
             ClassDeclaration[Declaration] {
                 id: Option<&'a Node<'a>>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
@@ -307,6 +319,7 @@ macro_rules! nodekind_defs {
                 decorators: NodeList<'a>,
                 body: &'a Node<'a>[ClassBody],
             },
+            #[boxed]
             ClassExpression[Expression] {
                 id: Option<&'a Node<'a>>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
@@ -317,8 +330,15 @@ macro_rules! nodekind_defs {
                 body: &'a Node<'a>[ClassBody],
             },
             ClassBody {
-                body: NodeList<'a>[ClassProperty, ClassPrivateProperty, MethodDefinition],
-            },
+                body: NodeList<'a>[
+                    ClassProperty,
+                    ClassPrivateProperty,
+                    ClassAccessorProperty,
+                    MethodDefinition,
+                    StaticBlock
+                ],
+            },
+            #[boxed]
             ClassProperty {
                 key: &'a Node<'a>[Expression],
                 value: Option<&'a Node<'a>>[Expression],
@@ -330,6 +350,7 @@ macro_rules! nodekind_defs {
                 type_annotation: Option<&'a Node<'a>>[TypeAnnotation],
                 ts_modifiers: Option<&'a Node<'a>>[TSModifiers],
             },
+            #[boxed]
             ClassPrivateProperty {
                 key: &'a Node<'a>[PrivateName],
                 value: Option<&'a Node<'a>>[Expression],
@@ -347,6 +368,16 @@ macro_rules! nodekind_defs {
                 computed: bool,
                 is_static: bool,
             },
+            ClassAccessorProperty {
+                key: &'a Node<'a>[Expression, PrivateName],
+                value: Option<&'a Node<'a>>[Expression],
+                computed: bool,
+                is_static: bool,
+            },
+            StaticBlock {
+                body: NodeList<'a>[Statement],
+            },
+            #[boxed]
             ImportDeclaration[Declaration] {
                 specifiers: NodeList<'a>[ImportSpecifier],
                 source: &'a Node<'a>[StringLiteral],
@@ -365,9 +396,10 @@ macro_rules! nodekind_defs {
                 local: &'a Node<'a>[Identifier],
             },
             ImportAttribute {
-                key: &'a Node<'a>[StringLiteral],
+                key: &'a Node<'a>[Identifier, StringLiteral],
                 value: &'a Node<'a>[Expression],
             },
+            #[boxed]
             ExportNamedDeclaration[Declaration] {
                 declaration: Option<&'a Node<'a>>[Declaration, Identifier],
                 specifiers: NodeList<'a>[ExportSpecifier, ExportNamespaceSpecifier],
@@ -422,6 +454,7 @@ macro_rules! nodekind_defs {
             JSXSpreadChild[JSXChild] {
                 expression: &'a Node<'a>[Expression],
             },
+            #[boxed]
             JSXOpeningElement {
                 name: &'a Node<'a>[JSXIdentifier, JSXMemberExpression, JSXNamespacedName],
                 attributes: NodeList<'a>[JSXAttribute, JSXSpreadAttribute],
@@ -485,6 +518,7 @@ macro_rules! nodekind_defs {
             AnyTypeAnnotation[FlowType],
             MixedTypeAnnotation[FlowType],
             VoidTypeAnnotation[FlowType],
+            #[boxed]
             FunctionTypeAnnotation[FlowType] {
                 params: NodeList<'a>[FunctionTypeParam],
                 this: Option<&'a Node<'a>>[FunctionTypeParam],
@@ -497,6 +531,7 @@ macro_rules! nodekind_defs {
                 type_annotation: &'a Node<'a>[FlowType],
                 optional: bool,
             },
+            #[boxed]
             ComponentTypeAnnotation[FlowType] {
                 params: NodeList<'a>[FunctionTypeParam],
                 rest: Option<&'a Node<'a>>[FunctionTypeParam],
@@ -532,6 +567,7 @@ macro_rules! nodekind_defs {
                 label: Option<&'a Node<'a>>[Identifier],
                 type_annotation: &'a Node<'a>[FlowType],
             },
+            #[boxed]
             TupleTypeLabeledElement[FlowType] {
                 label: &'a Node<'a>[Identifier],
                 element_type: &'a Node<'a>[FlowType],
@@ -563,6 +599,7 @@ macro_rules! nodekind_defs {
                 index_type: &'a Node<'a>[FlowType],
                 optional: bool,
             },
+            #[boxed]
             ConditionalTypeAnnotation[FlowType] {
                 check_type: &'a Node<'a>[FlowType],
                 extends_type: &'a Node<'a>[FlowType],
@@ -583,12 +620,14 @@ macro_rules! nodekind_defs {
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
                 right: &'a Node<'a>[FlowType],
             },
+            #[boxed]
             OpaqueType[FlowDeclaration] {
                 id: &'a Node<'a>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
                 impltype: &'a Node<'a>[FlowType],
                 supertype: Option<&'a Node<'a>>[FlowType],
             },
+            #[boxed]
             InterfaceDeclaration[FlowDeclaration] {
                 id: &'a Node<'a>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
@@ -600,18 +639,21 @@ macro_rules! nodekind_defs {
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
                 right: &'a Node<'a>[FlowType],
             },
+            #[boxed]
             DeclareOpaqueType[FlowDeclaration] {
                 id: &'a Node<'a>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
                 impltype: Option<&'a Node<'a>>[FlowType],
                 supertype: Option<&'a Node<'a>>[FlowType],
             },
+            #[boxed]
             DeclareInterface[FlowDeclaration] {
                 id: &'a Node<'a>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
                 extends: NodeList<'a>[InterfaceExtends],
                 body: &'a Node<'a>[ObjectTypeAnnotation],
             },
+            #[boxed]
             DeclareClass[FlowDeclaration] {
                 id: &'a Node<'a>[Identifier],
                 type_parameters: Option<&'a Node<'a>>[TypeParameterDeclaration],
@@ -624,6 +666,7 @@ macro_rules! nodekind_defs {
                 id: &'a Node<'a>[Identifier],
                 predicate: Option<&'a Node<'a>>[DeclaredPredicate],
             },
+            #[boxed]
             DeclareComponent[Declaration] {
                 id: &'a Node<'a>[Identifier],
                 params: NodeList<'a>[Pattern],
@@ -639,6 +682,7 @@ macro_rules! nodekind_defs {
                 id: &'a Node<'a>[Identifier],
                 body: &'a Node<'a>[FlowEnumBody],
             },
+            #[boxed]
             DeclareExportDeclaration[FlowDeclaration] {
                 declaration: Option<&'a Node<'a>>[Flow, FlowDeclaration],
                 specifiers: NodeList<'a>[ExportSpecifier, ExportNamespaceSpecifier],
@@ -667,6 +711,7 @@ macro_rules! nodekind_defs {
             TypeAnnotation[Flow] {
                 type_annotation: &'a Node<'a>[FlowType],
             },
+            #[boxed]
             ObjectTypeAnnotation[FlowType] {
                 properties: NodeList<'a>[ObjectTypeProperty, ObjectTypeSpreadProperty],
                 indexers: NodeList<'a>[ObjectTypeIndexer],
@@ -675,6 +720,7 @@ macro_rules! nodekind_defs {
                 inexact: bool,
                 exact: bool,
             },
+            #[boxed]
             ObjectTypeProperty[Flow] {
                 key: &'a Node<'a>[Identifier, StringLiteral],
                 value: &'a Node<'a>[FlowType],
@@ -699,6 +745,7 @@ macro_rules! nodekind_defs {
                 value: &'a Node<'a>[FlowType],
                 is_static: bool,
             },
+            #[boxed]
             ObjectTypeIndexer[Flow] {
                 id: Option<&'a Node<'a>>[Identifier],
                 key: &'a Node<'a>[FlowType],
@@ -706,6 +753,7 @@ macro_rules! nodekind_defs {
                 is_static: bool,
                 variance: Option<&'a Node<'a>>[Variance],
             },
+            #[boxed]
             ObjectTypeMappedTypeProperty[Flow] {
                 key_tparam: &'a Node<'a>[FlowType],
                 prop_type: &'a Node<'a>[FlowType],
@@ -719,6 +767,7 @@ macro_rules! nodekind_defs {
             TypeParameterDeclaration[Flow] {
                 params: NodeList<'a>[TypeParameter],
             },
+            #[boxed]
             TypeParameter[Flow] {
                 name: NodeLabel,
                 bound: Option<&'a Node<'a>>[TypeAnnotation],
@@ -846,6 +895,7 @@ macro_rules! nodekind_defs {
                 type_parameters: Option<&'a Node<'a>>,
                 type_annotation: &'a Node<'a>,
             },
+            #[boxed]
             TSInterfaceDeclaration {
                 id: &'a Node<'a>,
                 body: &'a Node<'a>,
@@ -898,6 +948,7 @@ macro_rules! nodekind_defs {
             TSTypeQuery {
                 expr_name: &'a Node<'a>,
             },
+            #[boxed]
             TSConditionalType {
                 extends_type: &'a Node<'a>,
                 check_type: &'a Node<'a>,
@@ -907,6 +958,7 @@ macro_rules! nodekind_defs {
             TSTypeLiteral {
                 members: NodeList<'a>,
             },
+            #[boxed]
             TSPropertySignature {
                 key: &'a Node<'a>,
                 type_annotation: Option<&'a Node<'a>>,
@@ -917,6 +969,7 @@ macro_rules! nodekind_defs {
                 is_static: bool,
                 export: bool,
             },
+            #[boxed]
             TSMethodSignature {
                 key: &'a Node<'a>,
                 params: NodeList<'a>,
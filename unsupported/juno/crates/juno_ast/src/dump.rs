@@ -5,12 +5,15 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::collections::HashMap;
 use std::io;
 use std::io::Write;
 
 use juno_support::case::ascii_snake_to_camel;
+use juno_support::convert::number_to_string;
 pub use juno_support::json::Pretty;
 use juno_support::json::*;
+use juno_support::source_manager::line_starts;
 
 use super::AssignmentExpressionOperator;
 use super::BinaryExpressionOperator;
@@ -26,14 +29,20 @@ use super::NodeList;
 use super::NodeRc;
 use super::NodeString;
 use super::PropertyKind;
+use super::SourceId;
+use super::SourceLoc;
+use super::SourceRange;
+use super::TooDeep;
 use super::UnaryExpressionOperator;
 use super::UpdateExpressionOperator;
 use super::VariableDeclarationKind;
+use crate::validate_tree_pure;
 
 /// Generate boilerplate code for the `NodeKind` enum.
 macro_rules! gen_dumper {
     ($name:ident {
         $(
+            $(#[boxed])?
             $kind:ident $([ $parent:ident ])? $({
                 $(
                     $field:ident : $type:ty
@@ -47,23 +56,34 @@ macro_rules! gen_dumper {
         fn dump_node<'gc, W: Write>(
             ctx: &'gc GCLock,
             node: &'gc Node<'gc>,
-            emitter: &mut JSONEmitter<W>,
+            out: &mut Dumper<W>,
         ) {
             use crate::*;
-            emitter.open_dict();
-            emitter.emit_key("type");
-            emitter.emit_string(node.name());
+            // Past the depth limit, the node is emitted as null.
+            let _depth = match ctx.ctx().enter_children(node) {
+                Ok(depth) => depth,
+                Err(e) => {
+                    out.too_deep.get_or_insert(e);
+                    out.emitter.emit_null();
+                    return;
+                }
+            };
+            out.emitter.open_dict();
+            out.emitter.emit_key("type");
+            out.emitter.emit_string(node.name());
             match node {
                 $(
-                    Node::$kind($kind {$($($field,)*)? .. }) => {
+                    Node::$kind(inner) => {
+                        let $kind {$($($field,)*)? .. }: &$kind = inner;
                         $($(
-                            emitter.emit_key(&ascii_snake_to_camel(stringify!($field)));
-                            $field.dump(ctx, emitter);
+                            out.emitter.emit_key(&ascii_snake_to_camel(stringify!($field)));
+                            $field.dump(ctx, out);
                         )*)?
                     }
                 ),*
             }
-            emitter.close_dict();
+            out.emit_extras(ctx, node);
+            out.emitter.close_dict();
         }
     }
 }
@@ -71,90 +91,90 @@ macro_rules! gen_dumper {
 nodekind_defs! { gen_dumper }
 
 trait DumpChild<'gc> {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>);
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>);
 }
 
 impl<'gc> DumpChild<'gc> for f64 {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_number(*self);
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_number(*self);
     }
 }
 
 impl<'gc> DumpChild<'gc> for bool {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_bool(*self);
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_bool(*self);
     }
 }
 
 impl<'gc> DumpChild<'gc> for NodeLabel {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(ctx.str(*self));
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(ctx.str(*self));
     }
 }
 
 impl<'gc> DumpChild<'gc> for UnaryExpressionOperator {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for BinaryExpressionOperator {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for LogicalExpressionOperator {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for UpdateExpressionOperator {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for AssignmentExpressionOperator {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for VariableDeclarationKind {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for PropertyKind {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for MethodDefinitionKind {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for ImportKind {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for ExportKind {
-    fn dump<W: Write>(&self, _ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(self.as_str());
+    fn dump<W: Write>(&self, _ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(self.as_str());
     }
 }
 
 impl<'gc> DumpChild<'gc> for NodeString {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.emit_string(&format!(
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.emit_string(&format!(
             "u{:?}",
             String::from_utf16_lossy(ctx.str_u16(*self))
         ))
@@ -162,27 +182,244 @@ impl<'gc> DumpChild<'gc> for NodeString {
 }
 
 impl<'gc, T: DumpChild<'gc>> DumpChild<'gc> for Option<T> {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>) {
         match self {
-            None => emitter.emit_null(),
-            Some(t) => t.dump(ctx, emitter),
+            None => out.emitter.emit_null(),
+            Some(t) => t.dump(ctx, out),
         };
     }
 }
 
 impl<'gc> DumpChild<'gc> for &'gc Node<'gc> {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        dump_node(ctx, self, emitter);
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        dump_node(ctx, self, out);
     }
 }
 
 impl<'gc> DumpChild<'gc> for NodeList<'gc> {
-    fn dump<W: Write>(&self, ctx: &'gc GCLock, emitter: &mut JSONEmitter<W>) {
-        emitter.open_array();
+    fn dump<W: Write>(&self, ctx: &'gc GCLock, out: &mut Dumper<W>) {
+        out.emitter.open_array();
         for elem in self.iter() {
-            dump_node(ctx, elem, emitter);
+            dump_node(ctx, elem, out);
         }
-        emitter.close_array();
+        out.emitter.close_array();
+    }
+}
+
+/// How the locations of the nodes are emitted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LocationFormat {
+    /// No locations.
+    None,
+
+    /// ESTree `loc` objects:
+    /// `"loc": {"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 5}}`.
+    Loc,
+
+    /// `"range": [start, end]` byte offsets in the file, as emitted by Babel and ESLint.
+    Range,
+
+    /// Both `loc` and `range`.
+    Both,
+}
+
+/// Base of the columns in `loc` objects.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColumnBase {
+    /// The first column is 0, as in ESTree and LSP.
+    Zero,
+
+    /// The first column is 1, as in [`SourceLoc`](crate::SourceLoc).
+    One,
+}
+
+/// Options for dumping the AST as JSON.
+pub struct DumpOpt<'a> {
+    /// Whether to pretty-print the JSON.
+    pub pretty: Pretty,
+
+    /// How to emit the locations of the nodes.
+    /// Ends are exclusive in both formats, i.e. they point after the last character of the
+    /// node. Lines start at 1 and columns count bytes.
+    /// Nodes without a valid range, like synthesized nodes, have no location.
+    pub locations: LocationFormat,
+
+    /// Base of the columns in `loc` objects.
+    pub columns: ColumnBase,
+
+    /// If `Some`, adds the annotations it computes to every node which has any,
+    /// under the key `"juno"`.
+    pub annotator: Option<&'a dyn Annotator>,
+}
+
+impl Default for DumpOpt<'_> {
+    fn default() -> Self {
+        DumpOpt {
+            pretty: Pretty::Yes,
+            locations: LocationFormat::None,
+            columns: ColumnBase::Zero,
+            annotator: None,
+        }
+    }
+}
+
+impl DumpOpt<'_> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+/// Value of an annotation added to the JSON by an [`Annotator`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationValue {
+    Null,
+    Bool(bool),
+    /// Non-finite numbers are emitted as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`,
+    /// since JSON can't represent them.
+    Number(f64),
+    String(String),
+}
+
+/// Computes extra information about nodes, e.g. the results of semantic analysis,
+/// to be embedded in the JSON with [`DumpOpt::annotator`].
+pub trait Annotator {
+    /// Append the annotations of `node` to `out`, as (key, value) pairs.
+    fn annotate<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        out: &mut Vec<(&'static str, AnnotationValue)>,
+    );
+}
+
+/// Emits the JSON of the nodes.
+struct Dumper<'a, W: Write> {
+    emitter: JSONEmitter<W>,
+    opt: &'a DumpOpt<'a>,
+
+    /// Byte offsets of the start of each line, computed when first needed for each file.
+    line_starts: HashMap<SourceId, Vec<usize>>,
+
+    /// The first node which exceeded the depth limit of the context, if any.
+    too_deep: Option<TooDeep>,
+}
+
+impl<W: Write> Dumper<'_, W> {
+    /// Emit the keys which don't correspond to fields of `node`: its location and
+    /// annotations.
+    fn emit_extras<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        self.emit_location(gc, *node.range());
+        self.emit_annotations(gc, node);
+    }
+
+    fn emit_location(&mut self, gc: &GCLock, range: SourceRange) {
+        if self.opt.locations == LocationFormat::None
+            || !range.file.is_valid()
+            || range.start.line == 0
+            || range.start.col == 0
+            || range.end.line == 0
+            || range.end.col == 0
+        {
+            return;
+        }
+        // The end of the range is the first byte of the last character, so add the length
+        // of the character to make it exclusive.
+        let end_len = self.offset(gc, range.file, range.end).map_or(1, |offset| {
+            utf8_len(gc.sm().source_buffer(range.file).as_bytes()[offset])
+        });
+        if matches!(
+            self.opt.locations,
+            LocationFormat::Loc | LocationFormat::Both
+        ) {
+            let base = match self.opt.columns {
+                ColumnBase::Zero => 0,
+                ColumnBase::One => 1,
+            };
+            self.emitter.emit_key("loc");
+            self.emitter.open_dict();
+            for (key, line, col) in [
+                ("start", range.start.line, range.start.col - 1),
+                ("end", range.end.line, range.end.col - 1 + end_len as u32),
+            ] {
+                self.emitter.emit_key(key);
+                self.emitter.open_dict();
+                self.emitter.emit_key("line");
+                self.emitter.emit_number(line as f64);
+                self.emitter.emit_key("column");
+                self.emitter.emit_number((col + base) as f64);
+                self.emitter.close_dict();
+            }
+            self.emitter.close_dict();
+        }
+        if matches!(
+            self.opt.locations,
+            LocationFormat::Range | LocationFormat::Both
+        ) {
+            if let (Some(start), Some(end)) = (
+                self.offset(gc, range.file, range.start),
+                self.offset(gc, range.file, range.end),
+            ) {
+                self.emitter.emit_key("range");
+                self.emitter.open_array();
+                self.emitter.emit_number(start as f64);
+                self.emitter.emit_number((end + end_len) as f64);
+                self.emitter.close_array();
+            }
+        }
+    }
+
+    /// The byte offset of `loc` in `file`, or `None` if it is outside the file.
+    fn offset(&mut self, gc: &GCLock, file: SourceId, loc: SourceLoc) -> Option<usize> {
+        if file.0 as usize >= gc.sm().num_sources() {
+            return None;
+        }
+        let buf = gc.sm().source_buffer(file).as_bytes();
+        let starts = self
+            .line_starts
+            .entry(file)
+            .or_insert_with(|| line_starts(buf));
+        let offset = starts.get(loc.line as usize - 1)? + loc.col as usize - 1;
+        if offset < buf.len() {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// Emit the annotations of `node`, if any, as a dict under the key `"juno"`.
+    fn emit_annotations<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        let annotator = match self.opt.annotator {
+            Some(annotator) => annotator,
+            None => return,
+        };
+        let mut annotations = vec![];
+        annotator.annotate(gc, node, &mut annotations);
+        if annotations.is_empty() {
+            return;
+        }
+        self.emitter.emit_key("juno");
+        self.emitter.open_dict();
+        for (key, value) in annotations {
+            self.emitter.emit_key(key);
+            match value {
+                AnnotationValue::Null => self.emitter.emit_null(),
+                AnnotationValue::Bool(b) => self.emitter.emit_bool(b),
+                AnnotationValue::Number(n) if n.is_finite() => self.emitter.emit_number(n),
+                AnnotationValue::Number(n) => self.emitter.emit_string(&number_to_string(n)),
+                AnnotationValue::String(s) => self.emitter.emit_string(&s),
+            }
+        }
+        self.emitter.close_dict();
+    }
+}
+
+/// Length of the UTF-8 sequence starting with `byte`.
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
     }
 }
 
@@ -192,8 +429,110 @@ pub fn dump_json<W: Write>(
     root: &NodeRc,
     pretty: Pretty,
 ) -> io::Result<()> {
+    dump_json_opt(
+        writer,
+        ctx,
+        root,
+        &DumpOpt {
+            pretty,
+            ..DumpOpt::new()
+        },
+    )
+}
+
+/// Dump the AST rooted at `root` as JSON, with the options `opt`.
+/// Fails with a [`TooDeep`] error if the tree exceeds the depth limit of `ctx`.
+pub fn dump_json_opt<W: Write>(
+    writer: W,
+    ctx: &mut Context,
+    root: &NodeRc,
+    opt: &DumpOpt,
+) -> io::Result<()> {
+    let gc = GCLock::new(ctx);
+    let mut out = Dumper {
+        emitter: JSONEmitter::new(writer, opt.pretty),
+        opt,
+        line_starts: HashMap::new(),
+        too_deep: None,
+    };
+    dump_node(&gc, root.node(&gc), &mut out);
+    out.emitter.end()?;
+    match out.too_deep {
+        Some(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        None => Ok(()),
+    }
+}
+
+/// Dump a self-contained report for debugging: the registered sources, the errors found by
+/// [`validate_tree_pure`] in the AST rooted at `root`, and the AST itself, as a single JSON
+/// document with the options `opt`:
+/// ```json
+/// {
+///   "sources": [{"id": 0, "name": "a.js", "text": "..."}],
+///   "diagnostics": [{"severity": "error", "message": "...", "nodeType": "...", "file": 0}],
+///   "ast": {...}
+/// }
+/// ```
+/// Diagnostics have the same location keys as the nodes.
+/// Returns the number of diagnostics.
+pub fn dump_validation_report<W: Write>(
+    writer: W,
+    ctx: &mut Context,
+    root: &NodeRc,
+    opt: &DumpOpt,
+) -> io::Result<usize> {
+    let errors = validate_tree_pure(ctx, root).err().unwrap_or_default();
     let gc = GCLock::new(ctx);
-    let mut emitter = JSONEmitter::new(writer, pretty);
-    dump_node(&gc, root.node(&gc), &mut emitter);
-    emitter.end()
+    let mut out = Dumper {
+        emitter: JSONEmitter::new(writer, opt.pretty),
+        opt,
+        line_starts: HashMap::new(),
+        too_deep: None,
+    };
+    out.emitter.open_dict();
+
+    out.emitter.emit_key("sources");
+    out.emitter.open_array();
+    for id in 0..gc.sm().num_sources() {
+        let file = SourceId(id as u32);
+        let buf = gc.sm().source_buffer(file).as_bytes();
+        out.emitter.open_dict();
+        out.emitter.emit_key("id");
+        out.emitter.emit_number(id as f64);
+        out.emitter.emit_key("name");
+        out.emitter.emit_string(gc.sm().source_name(file));
+        out.emitter.emit_key("text");
+        out.emitter
+            .emit_string(&String::from_utf8_lossy(&buf[..buf.len() - 1]));
+        out.emitter.close_dict();
+    }
+    out.emitter.close_array();
+
+    out.emitter.emit_key("diagnostics");
+    out.emitter.open_array();
+    for e in &errors {
+        let node = e.node.node(&gc);
+        out.emitter.open_dict();
+        out.emitter.emit_key("severity");
+        out.emitter.emit_string("error");
+        out.emitter.emit_key("message");
+        out.emitter.emit_string(&e.message);
+        out.emitter.emit_key("nodeType");
+        out.emitter.emit_string(node.name());
+        let range = *node.range();
+        if range.file.is_valid() {
+            out.emitter.emit_key("file");
+            out.emitter.emit_number(range.file.0 as f64);
+        }
+        out.emit_location(&gc, range);
+        out.emitter.close_dict();
+    }
+    out.emitter.close_array();
+
+    out.emitter.emit_key("ast");
+    dump_node(&gc, root.node(&gc), &mut out);
+
+    out.emitter.close_dict();
+    out.emitter.end()?;
+    Ok(errors.len())
 }
@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Iterators over the nodes of a subtree, an alternative to implementing [`Visitor`]
+//! for simple analyses which can be written with iterator adapters.
+
+use thiserror::Error;
+
+use crate::GCLock;
+use crate::Node;
+use crate::Path;
+use crate::TooDeep;
+use crate::Visitor;
+
+/// Item of the iterators: a node, its depth and its parent.
+/// The root has depth 0 and no parent, even if it has one in the AST.
+pub type IterItem<'gc> = (&'gc Node<'gc>, usize, Option<&'gc Node<'gc>>);
+
+/// Iterate over the subtree rooted at `root`, visiting each node before its children.
+/// Nodes are produced in the same order as [`Node::visit`].
+pub fn iter_preorder<'gc, 'ast: 'gc, 'ctx>(
+    gc: &'gc GCLock<'ast, 'ctx>,
+    root: &'gc Node<'gc>,
+) -> Preorder<'gc, 'ast, 'ctx> {
+    Preorder {
+        gc,
+        stack: vec![(root, 0, None)],
+    }
+}
+
+/// Error produced by traversals which reach more nodes than their budget,
+/// like [`Preorder::budgeted`] and [`crate::metrics_budgeted`].
+#[derive(Debug, Copy, Clone, Error)]
+#[error("traversal budget of {budget} nodes exceeded")]
+pub struct BudgetExceeded {
+    pub budget: usize,
+}
+
+/// Check that the subtree rooted at `root` doesn't exceed the depth limit of the context
+/// (see [`crate::Context::set_max_depth`]), without recursing, so that it is safe to
+/// traverse it recursively afterwards, e.g. right after parsing untrusted input.
+/// Returns the error for the first node, in preorder, which is too deep.
+pub fn check_depth<'gc, 'ast: 'gc>(
+    gc: &'gc GCLock<'ast, '_>,
+    root: &'gc Node<'gc>,
+) -> Result<(), TooDeep> {
+    let limit = match gc.ctx().max_depth() {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    match iter_preorder(gc, root).find(|&(_, depth, _)| depth >= limit) {
+        Some((node, _, _)) => Err(TooDeep {
+            limit,
+            range: *node.range(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Iterate over the subtree rooted at `root`, visiting each node after its children.
+pub fn iter_postorder<'gc, 'ast: 'gc, 'ctx>(
+    gc: &'gc GCLock<'ast, 'ctx>,
+    root: &'gc Node<'gc>,
+) -> Postorder<'gc, 'ast, 'ctx> {
+    Postorder {
+        gc,
+        stack: vec![((root, 0, None), false)],
+    }
+}
+
+/// Iterator returned by [`iter_preorder`].
+pub struct Preorder<'gc, 'ast: 'gc, 'ctx> {
+    gc: &'gc GCLock<'ast, 'ctx>,
+
+    /// Nodes left to produce, the next one last.
+    stack: Vec<IterItem<'gc>>,
+}
+
+impl<'gc, 'ast: 'gc, 'ctx> Preorder<'gc, 'ast, 'ctx> {
+    /// Stop after `budget` nodes, e.g. to bound the time spent on untrusted input.
+    /// Each node is produced as `Ok`; if the subtree has more than `budget` nodes,
+    /// a single [`BudgetExceeded`] is produced in place of the rest.
+    pub fn budgeted(self, budget: usize) -> Budgeted<Self> {
+        Budgeted {
+            inner: self,
+            budget,
+            remaining: Some(budget),
+        }
+    }
+}
+
+impl<'gc, 'ast: 'gc, 'ctx> Iterator for Preorder<'gc, 'ast, 'ctx> {
+    type Item = IterItem<'gc>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.stack.pop()?;
+        let (node, depth, _) = item;
+        let start = self.stack.len();
+        push_children(self.gc, node, |child| {
+            self.stack.push((child, depth + 1, Some(node)))
+        });
+        self.stack[start..].reverse();
+        Some(item)
+    }
+}
+
+/// Iterator returned by [`iter_postorder`].
+pub struct Postorder<'gc, 'ast: 'gc, 'ctx> {
+    gc: &'gc GCLock<'ast, 'ctx>,
+
+    /// Nodes left to produce, the next one last,
+    /// with whether their children have already been pushed.
+    stack: Vec<(IterItem<'gc>, bool)>,
+}
+
+impl<'gc, 'ast: 'gc, 'ctx> Iterator for Postorder<'gc, 'ast, 'ctx> {
+    type Item = IterItem<'gc>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (item, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(item);
+            }
+            let (node, depth, _) = item;
+            self.stack.push((item, true));
+            let start = self.stack.len();
+            push_children(self.gc, node, |child| {
+                self.stack.push(((child, depth + 1, Some(node)), false))
+            });
+            self.stack[start..].reverse();
+        }
+    }
+}
+
+/// Iterator returned by [`Preorder::budgeted`].
+pub struct Budgeted<I> {
+    inner: I,
+    budget: usize,
+
+    /// Number of nodes left to produce, `None` once the budget has been exceeded.
+    remaining: Option<usize>,
+}
+
+impl<'gc, I: Iterator<Item = IterItem<'gc>>> Iterator for Budgeted<I> {
+    type Item = Result<IterItem<'gc>, BudgetExceeded>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.remaining?;
+        let item = self.inner.next()?;
+        if remaining == 0 {
+            self.remaining = None;
+            return Some(Err(BudgetExceeded {
+                budget: self.budget,
+            }));
+        }
+        self.remaining = Some(remaining - 1);
+        Some(Ok(item))
+    }
+}
+
+/// Call `f` on each child of `node`, in order.
+fn push_children<'gc, 'ast: 'gc>(
+    gc: &'gc GCLock<'ast, '_>,
+    node: &'gc Node<'gc>,
+    f: impl FnMut(&'gc Node<'gc>),
+) {
+    struct Children<F> {
+        f: F,
+    }
+    impl<'gc, F: FnMut(&'gc Node<'gc>)> Visitor<'gc> for Children<F> {
+        fn call(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            (self.f)(node);
+        }
+    }
+    node.visit_fields(gc, &mut Children { f });
+}
@@ -7,6 +7,32 @@
 
 use super::*;
 
+/// The type stored by the `Node` enum for a kind, in a `Box` for the kinds marked `boxed`.
+macro_rules! node_payload {
+    (boxed $type:ty) => { Box<$type> };
+    ($type:ty) => { $type };
+}
+
+/// Wrap the struct of a kind into the type stored by the `Node` enum, see [`node_payload`].
+macro_rules! new_node_payload {
+    (boxed $value:expr) => {
+        Box::new($value)
+    };
+    ($value:expr) => {
+        $value
+    };
+}
+
+/// Whether a kind is marked `boxed`, see [`node_payload`].
+macro_rules! is_boxed {
+    (boxed) => {
+        true
+    };
+    () => {
+        false
+    };
+}
+
 /// Generate boilerplate code for the `Node` enum.
 ///
 /// The `Node` enum has variants for each of the different types of AST nodes
@@ -18,9 +44,12 @@ use super::*;
 /// to ensure that the shared fields are in the same place
 /// in all the structs. This means that the identical match arms will be optimized
 /// away into some fast pointer arithmetic that's easy to inline.
+/// The variants of the kinds marked `#[boxed]` contain a `Box` of the struct instead,
+/// so that the large kinds don't make every `Node` as large as them.
 macro_rules! gen_nodekind_enum {
     ($name:ident {
         $(
+            $(#[$boxed:ident])?
             $kind:ident $([ $parent:ident ])? $({
                 $(
                     $field:ident : $type:ty
@@ -48,7 +77,7 @@ macro_rules! gen_nodekind_enum {
         #[repr(C)]
         pub enum Node<'a> {
             // Create each field in the enum.
-            $($kind($kind<'a>),)*
+            $($kind(node_payload!($($boxed)? $kind<'a>)),)*
         }
 
         $(
@@ -72,17 +101,43 @@ macro_rules! gen_nodekind_enum {
             }
 
             /// Visit the child fields of `self`.
+            /// Past the depth limit of the context, the children aren't visited,
+            /// see [`Node::try_visit_children`].
             pub fn visit_children<'ast: 'gc, V: Visitor<'gc>>(
                 &'gc self,
                 ctx: &'gc GCLock<'ast, '_>,
                 visitor: &mut V,
+            ) {
+                let _ = self.try_visit_children(ctx, visitor);
+            }
+
+            /// Visit the child fields of `self`, or return an error without visiting them
+            /// if this exceeds the depth limit of the context (see
+            /// [`crate::Context::set_max_depth`]).
+            pub fn try_visit_children<'ast: 'gc, V: Visitor<'gc>>(
+                &'gc self,
+                ctx: &'gc GCLock<'ast, '_>,
+                visitor: &mut V,
+            ) -> Result<(), TooDeep> {
+                let _depth = ctx.ctx().enter_children(self)?;
+                crate::grow_stack(|| self.visit_fields(ctx, visitor));
+                Ok(())
+            }
+
+            /// Visit the child fields of `self` regardless of the depth limit, for iterative
+            /// traversals which don't recurse.
+            pub(crate) fn visit_fields<'ast: 'gc, V: Visitor<'gc>>(
+                &'gc self,
+                ctx: &'gc GCLock<'ast, '_>,
+                visitor: &mut V,
             ) {
                 match self {
                     $(
-                        Node::$kind($kind {
-                            $($($field,)*)?
-                            ..
-                        }) => {
+                        Node::$kind(inner) => {
+                            let $kind {
+                                $($($field,)*)?
+                                ..
+                            }: &$kind = inner;
                             $($(
                                 $field.visit_child(
                                     ctx,
@@ -104,10 +159,11 @@ macro_rules! gen_nodekind_enum {
             ) {
                 match self {
                     $(
-                        Node::$kind($kind {
-                            $($($field,)*)?
-                            ..
-                        }) => {
+                        Node::$kind(inner) => {
+                            let $kind {
+                                $($($field,)*)?
+                                ..
+                            }: &$kind = inner;
                             $($(
                                 $field.mark_list(ctx, &cb);
                             )*)?
@@ -119,29 +175,34 @@ macro_rules! gen_nodekind_enum {
             /// Visit the child fields of this node.
             /// `self` is the *original* parent of the children to visit.
             /// Will only allocate a new node if one of the children was changed.
+            /// Unlike [`Node::visit_children`], this ignores the depth limit of the context,
+            /// since skipping children would silently drop changes: all the children are
+            /// visited, on a stack grown as needed (see [`crate::grow_stack`]).
             pub fn visit_children_mut< V: VisitorMut<'gc>>(
                 &'gc self,
                 ctx: &'gc GCLock,
                 visitor: &mut V,
             ) -> TransformResult<&'gc Node<'gc>> {
                 let builder = builder::Builder::from_node(self);
-                #[allow(unused_mut)]
-                match builder {
-                    $(
-                        builder::Builder::$kind(mut builder) => {
-                            $($(
-                                if let TransformResult::Changed($field) = (&builder.inner.$field)
-                                    .visit_child_mut(
-                                        ctx,
-                                        visitor,
-                                        Path::new(self, NodeField::$field)) {
-                                    builder.$field($field);
-                                }
-                            )*)?
-                            builder.build(ctx)
-                        }
-                    ),*
-                }
+                crate::grow_stack(|| {
+                    #[allow(unused_mut)]
+                    match builder {
+                        $(
+                            builder::Builder::$kind(mut builder) => {
+                                $($(
+                                    if let TransformResult::Changed($field) =
+                                        (&builder.inner.$field).visit_child_mut(
+                                            ctx,
+                                            visitor,
+                                            Path::new(self, NodeField::$field)) {
+                                        builder.$field($field);
+                                    }
+                                )*)?
+                                builder.build(ctx)
+                            }
+                        ),*
+                    }
+                })
             }
 
             /// Replace `self` in the AST with the result of the `builder`.
@@ -154,23 +215,25 @@ macro_rules! gen_nodekind_enum {
                 ctx: &'gc GCLock<'ast, '_>,
                 visitor: &mut V,
             ) -> TransformResult<&'gc Node<'gc>> {
-                #[allow(unused_mut)]
-                match builder {
-                    $(
-                        builder::Builder::$kind(mut builder) => {
-                            $($(
-                                if let TransformResult::Changed($field) = (&builder.inner.$field)
-                                    .visit_child_mut(
-                                        ctx,
-                                        visitor,
-                                        Path::new(self, NodeField::$field)) {
-                                    builder.$field($field);
-                                }
-                            )*)?
-                            builder.build(ctx)
-                        }
-                    ),*
-                }
+                crate::grow_stack(|| {
+                    #[allow(unused_mut)]
+                    match builder {
+                        $(
+                            builder::Builder::$kind(mut builder) => {
+                                $($(
+                                    if let TransformResult::Changed($field) =
+                                        (&builder.inner.$field).visit_child_mut(
+                                            ctx,
+                                            visitor,
+                                            Path::new(self, NodeField::$field)) {
+                                        builder.$field($field);
+                                    }
+                                )*)?
+                                builder.build(ctx)
+                            }
+                        ),*
+                    }
+                })
             }
 
             /// Replace `self` in the AST with the result of the `builders`.
@@ -184,25 +247,27 @@ macro_rules! gen_nodekind_enum {
                 visitor: &mut V,
             ) -> TransformResult<&'gc Node<'gc>> {
                 let mut expanded = Vec::new();
-                for builder in builders {
-                    #[allow(unused_mut)]
-                    match builder {
-                        $(
-                            builder::Builder::$kind(mut builder) => {
-                                $($(
-                                        if let TransformResult::Changed($field) = (&builder.inner.$field)
-                                        .visit_child_mut(
-                                            ctx,
-                                            visitor,
-                                            Path::new(self, NodeField::$field)) {
-                                            builder.$field($field);
-                                        }
-                                )*)?
-                                expanded.push(builder.build_forced(ctx));
-                            }
-                        ),*
+                crate::grow_stack(|| {
+                    for builder in builders {
+                        #[allow(unused_mut)]
+                        match builder {
+                            $(
+                                builder::Builder::$kind(mut builder) => {
+                                    $($(
+                                            if let TransformResult::Changed($field) = (&builder.inner.$field)
+                                            .visit_child_mut(
+                                                ctx,
+                                                visitor,
+                                                Path::new(self, NodeField::$field)) {
+                                                builder.$field($field);
+                                            }
+                                    )*)?
+                                    expanded.push(builder.build_forced(ctx));
+                                }
+                            ),*
+                        }
                     }
-                }
+                });
                 TransformResult::Expanded(expanded)
             }
 
@@ -246,7 +311,18 @@ macro_rules! gen_nodekind_enum {
             pub fn range(&self) -> &SourceRange {
                 match self {
                     $(
-                        Self::$kind($kind { metadata, .. }) => &metadata.range
+                        Self::$kind(inner) => &inner.metadata.range
+                    ),*
+                }
+            }
+
+            /// Whether the node was generated by a transform instead of parsed
+            /// from the source (see [`NodeMetadata::synthesized`]).
+            #[inline]
+            pub fn is_synthesized(&self) -> bool {
+                match self {
+                    $(
+                        Self::$kind(inner) => inner.metadata.synthesized
                     ),*
                 }
             }
@@ -255,7 +331,7 @@ macro_rules! gen_nodekind_enum {
             pub fn range_mut(&mut self) -> &mut SourceRange {
                 match self {
                     $(
-                        Self::$kind($kind { metadata, .. }) => &mut metadata.range
+                        Self::$kind(inner) => &mut inner.metadata.range
                     ),*
                 }
             }
@@ -275,7 +351,7 @@ macro_rules! gen_nodekind_enum {
         /// Used for performing tasks based only on the type of the AST node
         /// without having to know more about it.
         /// Includes "abstract" nodes which cannot be truly constructed.
-        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
         pub enum NodeVariant {
             Expression,
             Statement,
@@ -294,9 +370,56 @@ macro_rules! gen_nodekind_enum {
         }
 
         impl NodeVariant {
-            /// The `parent` of the variant in ESTree, used for validation.
+            /// Every concrete node kind, excluding the abstract variants, in definition order.
+            pub const KINDS: &'static [NodeVariant] = &[$(Self::$kind),*];
+
+            /// Size in bytes of the struct of this kind of node, without the tag of the
+            /// `Node` enum. Most kinds are smaller than a `Node`, whose storage is sized
+            /// by the largest kind which isn't boxed. Abstract variants have a size of zero.
+            pub fn payload_size(self) -> usize {
+                match self {
+                    $(
+                        Self::$kind => std::mem::size_of::<$kind>(),
+                    )*
+                    _ => 0,
+                }
+            }
+
+            /// Whether the `Node` enum stores the struct of this kind in a `Box`, outside of
+            /// the storage of the node, because it is larger than the common kinds.
+            pub fn is_boxed(self) -> bool {
+                match self {
+                    $(
+                        Self::$kind => is_boxed!($($boxed)?),
+                    )*
+                    _ => false,
+                }
+            }
+
+            /// Size in bytes of the box of a node of this kind, or zero if it isn't boxed.
+            pub fn boxed_size(self) -> usize {
+                if self.is_boxed() {
+                    self.payload_size()
+                } else {
+                    0
+                }
+            }
+
+            /// The index of this kind in [`NodeVariant::KINDS`].
+            ///
+            /// # Panics
+            ///
+            /// Will panic if `self` is an abstract variant.
+            pub(crate) fn kind_index(self) -> usize {
+                const ABSTRACT: usize = NodeVariant::FlowEnumBody as usize + 1;
+                let index = (self as usize).wrapping_sub(ABSTRACT);
+                assert!(index < Self::KINDS.len(), "{:?} is not a kind", self);
+                index
+            }
+
+            /// The parent of the variant in ESTree, used for validation.
             /// Return `None` if there is no parent.
-            pub fn parent(&self) -> Option<NodeVariant> {
+            pub fn parent_variant(&self) -> Option<NodeVariant> {
                 match self {
                     Self::Expression => None,
                     Self::Statement => None,
@@ -366,6 +489,7 @@ macro_rules! gen_nodekind_enum {
                 GCLock,
                 ImportKind,
                 LogicalExpressionOperator,
+                MemoryLimitExceeded,
                 MethodDefinitionKind,
                 Node,
                 NodeLabel,
@@ -422,6 +546,7 @@ macro_rules! gen_nodekind_enum {
                             metadata: NodeMetadata {
                                 phantom: node.metadata.phantom,
                                 range: node.metadata.range,
+                                synthesized: node.metadata.synthesized,
                             },
                             $($(
                                 $field: (&node.$field).duplicate(),
@@ -442,7 +567,15 @@ macro_rules! gen_nodekind_enum {
 
                 /// Return the new node.
                 pub fn build_forced(self, gc: &'a GCLock) -> &'a Node<'a> {
-                    gc.alloc(super::Node::$kind(self.inner))
+                    gc.alloc(super::Node::$kind(new_node_payload!($($boxed)? self.inner)))
+                }
+
+                /// Return the new node, or an error if the memory limit would be exceeded.
+                pub fn try_build_forced(
+                    self,
+                    gc: &'a GCLock,
+                ) -> Result<&'a Node<'a>, MemoryLimitExceeded> {
+                    gc.try_alloc(super::Node::$kind(new_node_payload!($($boxed)? self.inner)))
                 }
 
                 /// Make a builder from a template.
@@ -452,10 +585,7 @@ macro_rules! gen_nodekind_enum {
                     Self {
                         is_changed: true,
                         inner: super::$kind {
-                            metadata: NodeMetadata {
-                                phantom: node.metadata.phantom,
-                                range: node.metadata.range,
-                            },
+                            metadata: NodeMetadata::build_template(node.metadata),
                             $($(
                                 $field: (&node.$field).duplicate(),
                             )*)?
@@ -468,12 +598,28 @@ macro_rules! gen_nodekind_enum {
                     gc: &'a GCLock,
                     node: super::template::$kind<'a>,
                 ) -> &'a Node<'a> {
-                    gc.alloc(super::Node::$kind(super::$kind {
+                    gc.alloc(super::Node::$kind(new_node_payload!($($boxed)? super::$kind {
                         metadata: NodeMetadata::build_template(node.metadata),
                         $($(
                                 $field: node.$field,
                         )*)?
-                    }))
+                    })))
+                }
+
+                /// Build from a template, or return an error if the memory limit would be
+                /// exceeded.
+                /// The lists of the template can be built with [`NodeList::try_from_iter`]
+                /// so that they fail the same way.
+                pub fn try_build_template(
+                    gc: &'a GCLock,
+                    node: super::template::$kind<'a>,
+                ) -> Result<&'a Node<'a>, MemoryLimitExceeded> {
+                    gc.try_alloc(super::Node::$kind(new_node_payload!($($boxed)? super::$kind {
+                        metadata: NodeMetadata::build_template(node.metadata),
+                        $($(
+                                $field: node.$field,
+                        )*)?
+                    })))
                 }
 
                 // Setters for the fields.
@@ -515,25 +661,25 @@ impl<'gc> Node<'gc> {
     }
     pub fn function_like_id(&self) -> Option<&'gc Node<'gc>> {
         match self {
-            Node::FunctionExpression(FunctionExpression { id, .. })
-            | Node::ArrowFunctionExpression(ArrowFunctionExpression { id, .. })
-            | Node::FunctionDeclaration(FunctionDeclaration { id, .. }) => *id,
+            Node::FunctionExpression(f) => f.id,
+            Node::ArrowFunctionExpression(f) => f.id,
+            Node::FunctionDeclaration(f) => f.id,
             _ => self.function_like_panic(),
         }
     }
     pub fn function_like_params(&self) -> NodeList<'gc> {
         match self {
-            Node::FunctionExpression(FunctionExpression { params, .. })
-            | Node::ArrowFunctionExpression(ArrowFunctionExpression { params, .. })
-            | Node::FunctionDeclaration(FunctionDeclaration { params, .. }) => *params,
+            Node::FunctionExpression(f) => f.params,
+            Node::ArrowFunctionExpression(f) => f.params,
+            Node::FunctionDeclaration(f) => f.params,
             _ => self.function_like_panic(),
         }
     }
     pub fn function_like_body(&self) -> &'gc Node<'gc> {
         match self {
-            Node::FunctionExpression(FunctionExpression { body, .. })
-            | Node::ArrowFunctionExpression(ArrowFunctionExpression { body, .. })
-            | Node::FunctionDeclaration(FunctionDeclaration { body, .. }) => body,
+            Node::FunctionExpression(f) => f.body,
+            Node::ArrowFunctionExpression(f) => f.body,
+            Node::FunctionDeclaration(f) => f.body,
             _ => self.function_like_panic(),
         }
     }
@@ -556,6 +702,43 @@ impl<'gc> Node<'gc> {
                 | Node::RestElement(..)
         )
     }
+
+    pub fn is_expression(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::Expression)
+    }
+    pub fn is_statement(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::Statement)
+    }
+    pub fn is_declaration(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::Declaration)
+    }
+    pub fn is_literal(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::Literal)
+    }
+    pub fn is_lval(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::LVal)
+    }
+    pub fn is_flow_type(&self) -> bool {
+        self.variant().is_subtype_of(NodeVariant::FlowType)
+    }
+}
+
+impl NodeVariant {
+    /// Return whether `supertype` is `self` or in the parent chain of `self`.
+    pub fn is_subtype_of(self, supertype: NodeVariant) -> bool {
+        let mut cur = self;
+        loop {
+            if cur == supertype {
+                return true;
+            }
+            match cur.parent_variant() {
+                None => return false,
+                Some(next) => {
+                    cur = next;
+                }
+            }
+        }
+    }
 }
 
 #[macro_export]
@@ -24,44 +24,116 @@
 //! which have the same general structure as the various node kinds, but are only used
 //! for building/allocating nodes in the `Context`.
 //!
-//! Visitor patterns are provided by [`Visitor`] and [`VisitorMut`].
+//! Visitor patterns are provided by [`Visitor`] and [`VisitorMut`],
+//! and iterators over nodes by [`iter_preorder`] and [`iter_postorder`].
+//! [`macro@kind_visitor`] derives a [`Visitor`] from per-kind methods.
 
 #[macro_use]
 mod def;
 
+mod alloc_stats;
+pub mod builders;
+mod clone;
 mod context;
 mod dump;
 mod field;
+mod iter;
 mod kind;
+mod locate;
+mod metrics;
 mod node_child;
 mod node_enums;
+mod pattern;
+mod program;
+mod reflect;
+mod snapshot;
+mod text;
 mod validate;
 mod visitor;
 
+pub use alloc_stats::AllocHistogram;
+pub use alloc_stats::HeapStats;
+pub use alloc_stats::KindAllocStats;
+pub use clone::deep_clone;
+pub use context::grow_stack;
+pub use context::ClearAtoms;
 pub use context::Context;
+pub use context::ContextConfig;
+pub use context::ContextMismatch;
+pub use context::FrozenPolicy;
 pub use context::GCLock;
+pub use context::GCLockGuard;
+pub use context::GCLockInUse;
+pub use context::GcPolicy;
+pub use context::LiveNodeRcs;
+pub use context::MemoryLimitExceeded;
 pub use context::NodePtr;
 pub use context::NodeRc;
+pub use context::NodeRcList;
+pub use context::TooDeep;
+pub use context::TooManyContexts;
 pub use dump::dump_json;
+pub use dump::dump_json_opt;
+pub use dump::dump_validation_report;
+pub use dump::AnnotationValue;
+pub use dump::Annotator;
+pub use dump::ColumnBase;
+pub use dump::DumpOpt;
+pub use dump::LocationFormat;
 pub use dump::Pretty;
 pub use field::NodeField;
+pub use iter::check_depth;
+pub use iter::iter_postorder;
+pub use iter::iter_preorder;
+pub use iter::BudgetExceeded;
+pub use iter::Budgeted;
+pub use iter::IterItem;
+pub use iter::Postorder;
+pub use iter::Preorder;
+pub use juno_ast_macros::kind_visitor;
 pub use juno_support::source_manager::SourceId;
 pub use juno_support::source_manager::SourceLoc;
 pub use juno_support::source_manager::SourceManager;
 pub use juno_support::source_manager::SourceRange;
+pub use juno_support::CancellationToken;
+pub use juno_support::ChunkConfig;
 pub use kind::NodeVariant;
 pub use kind::*;
+pub use locate::node_at;
+pub use locate::NodeAt;
+pub use metrics::metrics;
+pub use metrics::metrics_budgeted;
+pub use metrics::Metrics;
 pub use node_child::NodeLabel;
 pub use node_child::NodeList;
 pub use node_child::NodeMetadata;
 pub use node_child::NodeString;
 pub use node_child::TemplateMetadata;
 pub use node_enums::*;
+pub use pattern::bound_names;
+pub use pattern::visit_bound_names;
+pub use pattern::BoundName;
+pub use program::concat_programs;
+pub use program::find_top_level_await;
+pub use program::inject_prelude;
+pub use program::is_directive;
+pub use program::is_module_declaration;
+pub use reflect::FieldCategory;
+pub use reflect::FieldInfo;
+pub use reflect::FieldValue;
+pub use snapshot::load_context;
+pub use snapshot::save_context;
+pub use snapshot::SnapshotError;
+pub use text::dump_text;
+pub use text::parse_text;
+pub use text::TextAstError;
 pub use validate::validate_tree;
 pub use validate::validate_tree_pure;
 pub use validate::TreeValidationError;
 pub use validate::ValidationError;
+pub use visitor::KindVisitor;
 pub use visitor::Path;
 pub use visitor::TransformResult;
+pub use visitor::VisitAll;
 pub use visitor::Visitor;
 pub use visitor::VisitorMut;
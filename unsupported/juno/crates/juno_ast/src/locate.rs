@@ -0,0 +1,64 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Lookup of the node at a source location, e.g. for hover or go-to-definition.
+
+use crate::GCLock;
+use crate::Node;
+use crate::Path;
+use crate::SourceLoc;
+use crate::Visitor;
+
+/// Result of [`node_at`].
+#[derive(Debug, Clone)]
+pub struct NodeAt<'gc> {
+    /// Innermost node whose range covers the location.
+    pub node: &'gc Node<'gc>,
+
+    /// Ancestors of `node`, starting with the root and ending with its parent.
+    pub ancestors: Vec<&'gc Node<'gc>>,
+}
+
+/// Find the innermost node in the subtree rooted at `root` whose range covers `loc`.
+/// Returns `None` if the range of `root` doesn't cover `loc`.
+/// Only the line and column are compared, so all the nodes are assumed to be in the same
+/// file. Nodes without a valid range, like synthesized nodes, never cover a location.
+pub fn node_at<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>, loc: SourceLoc) -> Option<NodeAt<'gc>> {
+    if !root.range().contains(loc) {
+        return None;
+    }
+    let mut result = NodeAt {
+        node: root,
+        ancestors: vec![],
+    };
+    loop {
+        let mut finder = ChildFinder { loc, found: None };
+        // This loop doesn't recurse, so it ignores the depth limit of the context.
+        result.node.visit_fields(gc, &mut finder);
+        match finder.found {
+            Some(child) => {
+                result.ancestors.push(result.node);
+                result.node = child;
+            }
+            None => return Some(result),
+        }
+    }
+}
+
+/// Finds the first child which covers `loc`.
+struct ChildFinder<'gc> {
+    loc: SourceLoc,
+    found: Option<&'gc Node<'gc>>,
+}
+
+impl<'gc> Visitor<'gc> for ChildFinder<'gc> {
+    fn call(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if self.found.is_none() && node.range().contains(self.loc) {
+            self.found = Some(node);
+        }
+    }
+}
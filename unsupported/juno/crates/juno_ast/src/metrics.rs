@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Size and shape metrics of AST subtrees, used by pass heuristics
+//! and complexity reporting.
+
+use std::collections::HashMap;
+
+use crate::context::LIST_ELEMENT_STORAGE_SIZE;
+use crate::context::NODE_STORAGE_SIZE;
+use crate::grow_stack;
+use crate::BudgetExceeded;
+use crate::FieldValue;
+use crate::GCLock;
+use crate::Node;
+use crate::NodeVariant;
+use crate::Path;
+use crate::Visitor;
+
+/// Metrics describing a subtree, computed by [`metrics`].
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// Total number of nodes in the subtree, including the root.
+    pub node_count: usize,
+
+    /// Number of nodes on the longest path from the root to a leaf.
+    /// A single node has depth 1.
+    pub max_depth: usize,
+
+    /// Number of nodes of each kind in the subtree.
+    pub variant_counts: HashMap<NodeVariant, usize>,
+
+    /// Estimated number of bytes of GC storage used by the nodes and lists in the subtree.
+    pub heap_bytes: usize,
+}
+
+impl Metrics {
+    /// Number of nodes of kind `variant` in the subtree.
+    pub fn count(&self, variant: NodeVariant) -> usize {
+        self.variant_counts.get(&variant).copied().unwrap_or(0)
+    }
+}
+
+/// Compute the [`Metrics`] of the subtree rooted at `node`.
+pub fn metrics<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> Metrics {
+    match metrics_budgeted(gc, node, usize::MAX) {
+        Ok(metrics) => metrics,
+        Err(_) => unreachable!("unlimited budget exceeded"),
+    }
+}
+
+/// Compute the [`Metrics`] of the subtree rooted at `node`,
+/// giving up if it has more than `budget` nodes.
+pub fn metrics_budgeted<'gc>(
+    gc: &'gc GCLock,
+    node: &'gc Node<'gc>,
+    budget: usize,
+) -> Result<Metrics, BudgetExceeded> {
+    let mut collector = MetricsCollector {
+        metrics: Default::default(),
+        depth: 0,
+        budget,
+    };
+    node.visit(gc, &mut collector, None);
+    if collector.metrics.node_count > budget {
+        return Err(BudgetExceeded { budget });
+    }
+    Ok(collector.metrics)
+}
+
+struct MetricsCollector {
+    metrics: Metrics,
+
+    /// Depth of the node currently being visited.
+    depth: usize,
+
+    /// Maximum number of nodes to visit.
+    /// The first node over the budget is counted, but its children aren't visited.
+    budget: usize,
+}
+
+impl<'gc> Visitor<'gc> for MetricsCollector {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if self.metrics.node_count > self.budget {
+            return;
+        }
+        self.depth += 1;
+        let metrics = &mut self.metrics;
+        metrics.node_count += 1;
+        metrics.max_depth = metrics.max_depth.max(self.depth);
+        *metrics.variant_counts.entry(node.variant()).or_insert(0) += 1;
+        metrics.heap_bytes += NODE_STORAGE_SIZE;
+        for info in node.fields() {
+            if let Some(FieldValue::NodeList(list)) = node.field(info.field) {
+                metrics.heap_bytes += list.len() * LIST_ELEMENT_STORAGE_SIZE;
+            }
+        }
+        // Not limited by the depth of the context, which would make the metrics wrong.
+        grow_stack(|| node.visit_fields(gc, self));
+        self.depth -= 1;
+    }
+}
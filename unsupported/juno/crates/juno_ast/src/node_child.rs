@@ -19,7 +19,9 @@ use juno_support::source_manager::SourceRange;
 use crate::builder;
 use crate::context::NodeListElement;
 use crate::template;
+use crate::FrozenPolicy;
 use crate::GCLock;
+use crate::MemoryLimitExceeded;
 use crate::Node;
 use crate::Path;
 use crate::TransformResult;
@@ -36,13 +38,17 @@ use crate::VisitorMut;
 pub struct NodeMetadata<'a> {
     pub(crate) phantom: PhantomData<&'a Node<'a>>,
     pub range: SourceRange,
+    /// Whether the node was generated by a transform instead of parsed from the source.
+    pub synthesized: bool,
 }
 
 impl<'a> NodeMetadata<'a> {
+    /// Nodes built without a valid range are always synthesized.
     pub(crate) fn build_template(template: TemplateMetadata<'a>) -> NodeMetadata<'a> {
         NodeMetadata {
             phantom: template.phantom,
             range: template.range,
+            synthesized: template.synthesized || !template.range.file.is_valid(),
         }
     }
 }
@@ -53,6 +59,9 @@ impl<'a> NodeMetadata<'a> {
 pub struct TemplateMetadata<'a> {
     pub phantom: PhantomData<&'a Node<'a>>,
     pub range: SourceRange,
+    /// Mark the node as synthesized even though it has a valid range,
+    /// e.g. code generated from the node whose range it borrows.
+    pub synthesized: bool,
 }
 
 impl Default for TemplateMetadata<'_> {
@@ -64,6 +73,7 @@ impl Default for TemplateMetadata<'_> {
                 start: SourceLoc::invalid(),
                 end: SourceLoc::invalid(),
             },
+            synthesized: false,
         }
     }
 }
@@ -73,6 +83,7 @@ impl<'a> From<&NodeMetadata<'a>> for TemplateMetadata<'a> {
         Self {
             phantom: Default::default(),
             range: metadata.range,
+            synthesized: metadata.synthesized,
         }
     }
 }
@@ -82,6 +93,7 @@ impl<'a> From<SourceRange> for TemplateMetadata<'a> {
         Self {
             phantom: Default::default(),
             range,
+            synthesized: false,
         }
     }
 }
@@ -93,6 +105,8 @@ pub type NodeLabel = Atom;
 ///
 /// Implemented as a linked list internally to avoid extra overhead that would exist if it were
 /// to allocate a `Vec` or some other structure that required allocating on the native heap.
+/// The elements are allocated in the [`crate::Context`] and collected like nodes, and are
+/// counted by [`crate::Context::heap_stats`].
 ///
 /// Because this is just a pointer to the head of the list, it implements `Copy` much like any other
 /// pointer/reference, allowing to user to handle it much like `&Node` in many cases.
@@ -139,6 +153,28 @@ impl<'a> NodeList<'a> {
         }
     }
 
+    /// Connect the provided pre-existing nodes into a `NodeList` like [`Self::from_iter`],
+    /// or return an error if the memory limit of the context would be exceeded.
+    /// The elements allocated before the error are garbage.
+    pub fn try_from_iter<'gc, I: IntoIterator<Item = &'gc Node<'gc>>>(
+        lock: &'gc GCLock<'_, '_>,
+        nodes: I,
+    ) -> Result<NodeList<'gc>, MemoryLimitExceeded> {
+        let mut it = nodes.into_iter();
+        match it.next() {
+            Some(first) => {
+                let head_elem: &'gc NodeListElement<'gc> =
+                    lock.try_append_list_element(None, first)?;
+                let mut prev_elem = head_elem;
+                for next in it {
+                    prev_elem = lock.try_append_list_element(Some(prev_elem), next)?;
+                }
+                Ok(NodeList { head: head_elem })
+            }
+            None => Ok(Self::new(lock)),
+        }
+    }
+
     pub fn iter(self) -> NodeListIterator<'a> {
         NodeListIterator { ptr: self.head }
     }
@@ -197,8 +233,10 @@ impl<'a> Iterator for NodeListIterator<'a> {
 }
 
 /// JS string literals don't have to contain valid UTF-8,
-/// so we wrap a `Vec<u16>`, which allows us to represent UTF-16 characters
-/// without being subject to Rust's restrictions on [`String`].
+/// so they are UTF-16 atoms, which aren't subject to Rust's restrictions on [`String`].
+/// Like identifiers, they are interned in the atom table of the [`crate::Context`], so
+/// identical literals share a single copy. Atoms are only freed by
+/// [`crate::Context::reset`] with [`crate::ClearAtoms::Yes`].
 pub type NodeString = AtomU16;
 
 impl<'gc> Node<'gc> {
@@ -219,7 +257,7 @@ impl<'gc> Node<'gc> {
         visitor: &mut V,
         path: Option<Path<'gc>>,
     ) -> Option<&'gc Node<'gc>> {
-        match visitor.call(ctx, self, path) {
+        match call_mut(ctx, visitor, self, path) {
             TransformResult::Unchanged => Some(self),
             TransformResult::Removed => None,
             TransformResult::Changed(new_node) => Some(new_node),
@@ -230,6 +268,29 @@ impl<'gc> Node<'gc> {
     }
 }
 
+/// Call the mutable `visitor` on `node`, unless it is frozen and the context's
+/// [`FrozenPolicy`] says to skip it.
+fn call_mut<'gc, V: VisitorMut<'gc>>(
+    ctx: &'gc GCLock,
+    visitor: &mut V,
+    node: &'gc Node<'gc>,
+    path: Option<Path<'gc>>,
+) -> TransformResult<&'gc Node<'gc>> {
+    if !ctx.is_frozen(node) {
+        return visitor.call(ctx, node, path);
+    }
+    match ctx.ctx().frozen_policy() {
+        FrozenPolicy::Skip => TransformResult::Unchanged,
+        FrozenPolicy::Panic => match visitor.call(ctx, node, path) {
+            TransformResult::Unchanged => TransformResult::Unchanged,
+            TransformResult::Changed(new_node) if std::ptr::eq(new_node, node) => {
+                TransformResult::Unchanged
+            }
+            _ => panic!("Attempt to replace a frozen {:?}", node.variant()),
+        },
+    }
+}
+
 /// Trait implemented by possible child types of `NodeKind`.
 pub(crate) trait NodeChild<'gc>
 where
@@ -393,7 +454,7 @@ impl<'gc> NodeChild<'gc> for &'gc Node<'gc> {
         visitor: &mut V,
         path: Path<'gc>,
     ) -> TransformResult<Self::Out> {
-        match visitor.call(ctx, self, Some(path)) {
+        match call_mut(ctx, visitor, self, Some(path)) {
             TransformResult::Removed => {
                 TransformResult::Changed(builder::EmptyStatement::build_template(
                     ctx,
@@ -405,6 +466,7 @@ impl<'gc> NodeChild<'gc> for &'gc Node<'gc> {
                                 start: self.range().start,
                                 end: self.range().start,
                             },
+                            synthesized: true,
                         },
                     },
                 ))
@@ -438,7 +500,7 @@ impl<'gc> NodeChild<'gc> for NodeList<'gc> {
         let mut it: NodeListIterator<'gc> = self.iter();
         // Assume no copies to start.
         while let Some(elem) = it.next() {
-            let node = visitor.call(ctx, elem, Some(path));
+            let node = call_mut(ctx, visitor, elem, Some(path));
             if let Unchanged = node {
                 index += 1;
                 continue;
@@ -460,7 +522,7 @@ impl<'gc> NodeChild<'gc> for NodeList<'gc> {
             index += 1;
             // Fill the rest of the elements.
             for elem in it.by_ref() {
-                match visitor.call(ctx, elem, Some(path)) {
+                match call_mut(ctx, visitor, elem, Some(path)) {
                     Unchanged => result.push(elem),
                     Removed => {}
                     Changed(new_node) => result.push(new_node),
@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Enumerating the names bound by binding patterns,
+//! shared by semantic analysis and the passes which lower destructuring.
+
+use crate::Node;
+
+/// A name bound by a pattern.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundName<'gc> {
+    /// The `Identifier` which is bound.
+    pub ident: &'gc Node<'gc>,
+
+    /// The default value of the identifier, in `{a = 1}` or `[a = 1]`.
+    /// Defaults of enclosing patterns, as in `[{a} = {}]`, are not included.
+    pub default: Option<&'gc Node<'gc>>,
+
+    /// Whether the identifier is the argument of a rest element, as in `[...a]`.
+    pub is_rest: bool,
+}
+
+/// Call `f` with every name bound by `pattern`, in source order.
+/// `pattern` may be an `Identifier` or any destructuring pattern.
+/// Array holes are skipped. Nodes which can't be bound,
+/// e.g. member expressions, are passed to `f` as `Err`, and the walk continues.
+pub fn visit_bound_names<'gc, F>(pattern: &'gc Node<'gc>, f: &mut F)
+where
+    F: FnMut(Result<BoundName<'gc>, &'gc Node<'gc>>),
+{
+    visit(pattern, None, false, f)
+}
+
+/// Return the identifiers bound by `pattern`, in source order,
+/// skipping nodes which can't be bound.
+pub fn bound_names<'gc>(pattern: &'gc Node<'gc>) -> Vec<&'gc Node<'gc>> {
+    let mut result = vec![];
+    visit_bound_names(pattern, &mut |name| {
+        if let Ok(BoundName { ident, .. }) = name {
+            result.push(ident);
+        }
+    });
+    result
+}
+
+fn visit<'gc, F>(node: &'gc Node<'gc>, default: Option<&'gc Node<'gc>>, is_rest: bool, f: &mut F)
+where
+    F: FnMut(Result<BoundName<'gc>, &'gc Node<'gc>>),
+{
+    match node {
+        Node::Identifier(_) => f(Ok(BoundName {
+            ident: node,
+            default,
+            is_rest,
+        })),
+        Node::Empty(_) => {}
+        Node::AssignmentPattern(n) => visit(n.left, Some(n.right), false, f),
+        Node::RestElement(n) => visit(n.argument, None, true, f),
+        Node::ArrayPattern(n) => {
+            for elem in n.elements {
+                visit(elem, None, false, f);
+            }
+        }
+        Node::ObjectPattern(n) => {
+            for prop in n.properties {
+                match prop {
+                    Node::Property(p) => visit(p.value, None, false, f),
+                    Node::RestElement(_) => visit(prop, None, false, f),
+                    _ => f(Err(prop)),
+                }
+            }
+        }
+        _ => f(Err(node)),
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Combining the bodies of programs, for bundling and for passes which need
+//! to add runtime helpers to the start of a program.
+
+use crate::builder;
+use crate::grow_stack;
+use crate::node_cast;
+use crate::template;
+use crate::Directive;
+use crate::DirectiveLiteral;
+use crate::ExpressionStatement;
+use crate::GCLock;
+use crate::Node;
+use crate::NodeList;
+use crate::NodeString;
+use crate::Path;
+use crate::SourceRange;
+use crate::StringLiteral;
+use crate::Visitor;
+
+/// Whether `stmt` is a directive such as `'use strict'`.
+pub fn is_directive(stmt: &Node) -> bool {
+    matches!(
+        stmt,
+        Node::ExpressionStatement(ExpressionStatement {
+            directive: Some(_),
+            ..
+        }) | Node::Directive(_)
+    )
+}
+
+/// Whether `stmt` is an import or export declaration, which can only appear in a module.
+pub fn is_module_declaration(stmt: &Node) -> bool {
+    matches!(
+        stmt,
+        Node::ImportDeclaration(_)
+            | Node::ExportNamedDeclaration(_)
+            | Node::ExportDefaultDeclaration(_)
+            | Node::ExportAllDeclaration(_)
+    )
+}
+
+/// Find the first `await` expression or `for await` loop in `root` outside of any function,
+/// which makes a module evaluate asynchronously and is an error in a script.
+pub fn find_top_level_await<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+    let mut finder = TopLevelAwaitFinder { found: None };
+    grow_stack(|| root.visit_fields(gc, &mut finder));
+    finder.found
+}
+
+/// Finds the first top-level `await`, without entering functions.
+struct TopLevelAwaitFinder<'gc> {
+    found: Option<&'gc Node<'gc>>,
+}
+
+impl<'gc> Visitor<'gc> for TopLevelAwaitFinder<'gc> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if self.found.is_some() || node.is_function_like() {
+            return;
+        }
+        match node {
+            Node::AwaitExpression(_) => self.found = Some(node),
+            Node::ForOfStatement(for_of) if for_of.is_await => self.found = Some(node),
+            // Not limited by the depth of the context, which could hide an `await`.
+            _ => grow_stack(|| node.visit_fields(gc, self)),
+        }
+    }
+}
+
+/// Concatenate the bodies of `programs`, which must all be `Program` nodes,
+/// into a single `Program`.
+/// The directives of every program are hoisted to the start of the result,
+/// without duplicates, so they apply to all of it.
+/// Statements keep their source ranges.
+/// The result spans from the start of the first program to the end of the last one
+/// if they are in the same file, otherwise it has the range of the first program.
+/// Panics if `programs` is empty.
+pub fn concat_programs<'gc>(gc: &'gc GCLock, programs: &[&'gc Node<'gc>]) -> &'gc Node<'gc> {
+    let first = *programs.first().expect("no programs to concatenate");
+    let last = *programs.last().unwrap();
+    let range = if first.range().file == last.range().file {
+        SourceRange {
+            end: last.range().end,
+            ..*first.range()
+        }
+    } else {
+        *first.range()
+    };
+
+    let mut directives: Vec<&'gc Node<'gc>> = vec![];
+    let mut stmts = vec![];
+    for program in programs {
+        for stmt in node_cast!(Node::Program, program).body.iter() {
+            if !is_directive(stmt) {
+                stmts.push(stmt);
+            } else if !directives
+                .iter()
+                .any(|d| directive_text(d) == directive_text(stmt))
+            {
+                directives.push(stmt);
+            }
+        }
+    }
+
+    builder::Program::build_template(
+        gc,
+        template::Program {
+            metadata: range.into(),
+            body: NodeList::from_iter(gc, directives.into_iter().chain(stmts)),
+        },
+    )
+}
+
+/// Return a copy of `program`, which must be a `Program` or `Module`, with `stmts`
+/// inserted at the start of its body, after any directives.
+pub fn inject_prelude<'gc, I>(gc: &'gc GCLock, program: &'gc Node<'gc>, stmts: I) -> &'gc Node<'gc>
+where
+    I: IntoIterator<Item = &'gc Node<'gc>>,
+{
+    let body = match program {
+        Node::Program(p) => p.body,
+        Node::Module(m) => m.body,
+        _ => panic!("Expected Program or Module, found {:?}", program.variant()),
+    };
+    let new_body = NodeList::from_iter(
+        gc,
+        body.iter()
+            .take_while(|stmt| is_directive(stmt))
+            .chain(stmts)
+            .chain(body.iter().skip_while(|stmt| is_directive(stmt))),
+    );
+    match program {
+        Node::Program(p) => builder::Program::build_template(
+            gc,
+            template::Program {
+                metadata: (&p.metadata).into(),
+                body: new_body,
+            },
+        ),
+        Node::Module(m) => builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: (&m.metadata).into(),
+                body: new_body,
+            },
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// The text of the directive `stmt`, used to find duplicates.
+fn directive_text(stmt: &Node) -> Option<NodeString> {
+    match stmt {
+        Node::ExpressionStatement(ExpressionStatement {
+            directive: Some(text),
+            ..
+        }) => Some(*text),
+        Node::Directive(Directive {
+            value:
+                Node::StringLiteral(StringLiteral { value, .. })
+                | Node::DirectiveLiteral(DirectiveLiteral { value, .. }),
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
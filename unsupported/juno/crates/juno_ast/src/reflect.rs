@@ -0,0 +1,235 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Runtime reflection on the fields of AST nodes.
+//! Allows generic tools (serializers, diffing, selectors) to inspect nodes
+//! without matching on every kind of node.
+
+use super::AssignmentExpressionOperator;
+use super::BinaryExpressionOperator;
+use super::ExportKind;
+use super::ImportKind;
+use super::LogicalExpressionOperator;
+use super::MethodDefinitionKind;
+use super::Node;
+use super::NodeField;
+use super::NodeLabel;
+use super::NodeList;
+use super::NodeString;
+use super::NodeVariant;
+use super::PropertyKind;
+use super::UnaryExpressionOperator;
+use super::UpdateExpressionOperator;
+use super::VariableDeclarationKind;
+
+/// The type of data stored in a field of a node.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FieldCategory {
+    Node,
+    NodeList,
+    Label,
+    String,
+    Number,
+    Bool,
+    Enum,
+}
+
+/// Description of a single field of a kind of node.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FieldInfo {
+    pub field: NodeField,
+    /// Name of the field as used in the Rust definition (snake case).
+    pub name: &'static str,
+    pub category: FieldCategory,
+    /// Whether the field may be null.
+    pub optional: bool,
+}
+
+/// The value of a field of a node, retrieved by reflection.
+#[derive(Debug, Copy, Clone)]
+pub enum FieldValue<'gc> {
+    /// An optional field which is not present.
+    Null,
+    Node(&'gc Node<'gc>),
+    NodeList(NodeList<'gc>),
+    Label(NodeLabel),
+    String(NodeString),
+    Number(f64),
+    Bool(bool),
+    /// One of the simple enums from `node_enums`, as its source string.
+    Enum(&'static str),
+}
+
+/// Generate the reflection tables and getters for every kind of node.
+macro_rules! gen_reflection {
+    ($name:ident {
+        $(
+            $(#[boxed])?
+            $kind:ident $([ $parent:ident ])? $({
+                $(
+                    $field:ident : $type:ty
+                    $( [ $( $constraint:ident ),* ] )?
+                ),*
+                $(,)?
+            })?
+        ),*
+        $(,)?
+    }) => {
+        $(
+        impl<'a> crate::$kind<'a> {
+            /// The fields of this kind of node, in definition order.
+            pub const FIELDS: &'static [FieldInfo] = &[
+                $($(
+                    FieldInfo {
+                        field: NodeField::$field,
+                        name: stringify!($field),
+                        category: <$type as ReflectChild<'a>>::CATEGORY,
+                        optional: <$type as ReflectChild<'a>>::OPTIONAL,
+                    },
+                )*)?
+            ];
+        }
+        )*
+
+        impl NodeVariant {
+            /// The fields of this kind of node, in definition order.
+            /// Abstract variants (e.g. `Expression`) have no fields.
+            pub fn fields(&self) -> &'static [FieldInfo] {
+                match self {
+                    $(
+                        Self::$kind => crate::$kind::FIELDS,
+                    )*
+                    _ => &[],
+                }
+            }
+        }
+
+        impl<'gc> Node<'gc> {
+            /// Return the value of `field`,
+            /// or `None` if this kind of node doesn't have that field.
+            #[allow(clippy::match_single_binding)]
+            pub fn field(&self, field: NodeField) -> Option<FieldValue<'gc>> {
+                use crate::*;
+                match self {
+                    $(
+                        Node::$kind(inner) => {
+                            let $kind { $($($field,)*)? .. }: &$kind = inner;
+                            match field {
+                                $($(
+                                    NodeField::$field => Some($field.reflect()),
+                                )*)?
+                                _ => None,
+                            }
+                        }
+                    ),*
+                }
+            }
+        }
+    }
+}
+
+nodekind_defs! { gen_reflection }
+
+impl<'gc> Node<'gc> {
+    /// The fields of this node, in definition order.
+    pub fn fields(&self) -> &'static [FieldInfo] {
+        self.variant().fields()
+    }
+
+    /// Return the value of the field called `name` (in snake case),
+    /// or `None` if this kind of node doesn't have that field.
+    pub fn field_by_name(&self, name: &str) -> Option<FieldValue<'gc>> {
+        let info = self.fields().iter().find(|info| info.name == name)?;
+        self.field(info.field)
+    }
+}
+
+/// Implemented by every type which can be stored in a field of a node.
+trait ReflectChild<'gc> {
+    const CATEGORY: FieldCategory;
+    const OPTIONAL: bool = false;
+    fn reflect(&self) -> FieldValue<'gc>;
+}
+
+impl<'gc> ReflectChild<'gc> for f64 {
+    const CATEGORY: FieldCategory = FieldCategory::Number;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::Number(*self)
+    }
+}
+
+impl<'gc> ReflectChild<'gc> for bool {
+    const CATEGORY: FieldCategory = FieldCategory::Bool;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::Bool(*self)
+    }
+}
+
+impl<'gc> ReflectChild<'gc> for NodeLabel {
+    const CATEGORY: FieldCategory = FieldCategory::Label;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::Label(*self)
+    }
+}
+
+impl<'gc> ReflectChild<'gc> for NodeString {
+    const CATEGORY: FieldCategory = FieldCategory::String;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::String(*self)
+    }
+}
+
+impl<'gc> ReflectChild<'gc> for &'gc Node<'gc> {
+    const CATEGORY: FieldCategory = FieldCategory::Node;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::Node(self)
+    }
+}
+
+impl<'gc> ReflectChild<'gc> for NodeList<'gc> {
+    const CATEGORY: FieldCategory = FieldCategory::NodeList;
+    fn reflect(&self) -> FieldValue<'gc> {
+        FieldValue::NodeList(*self)
+    }
+}
+
+impl<'gc, T: ReflectChild<'gc>> ReflectChild<'gc> for Option<T> {
+    const CATEGORY: FieldCategory = T::CATEGORY;
+    const OPTIONAL: bool = true;
+    fn reflect(&self) -> FieldValue<'gc> {
+        match self {
+            None => FieldValue::Null,
+            Some(t) => t.reflect(),
+        }
+    }
+}
+
+macro_rules! impl_reflect_enum {
+    ($($enum:ident),* $(,)?) => {
+        $(
+        impl<'gc> ReflectChild<'gc> for $enum {
+            const CATEGORY: FieldCategory = FieldCategory::Enum;
+            fn reflect(&self) -> FieldValue<'gc> {
+                FieldValue::Enum(self.as_str())
+            }
+        }
+        )*
+    };
+}
+
+impl_reflect_enum!(
+    UnaryExpressionOperator,
+    BinaryExpressionOperator,
+    LogicalExpressionOperator,
+    UpdateExpressionOperator,
+    AssignmentExpressionOperator,
+    VariableDeclarationKind,
+    PropertyKind,
+    MethodDefinitionKind,
+    ImportKind,
+    ExportKind,
+);
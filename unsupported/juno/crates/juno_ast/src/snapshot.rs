@@ -0,0 +1,597 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Binary snapshots of a [`Context`], which can be written to disk and restored later.
+//!
+//! A snapshot contains the settings of the context, its whole atom table, the name and
+//! contents of every source and the trees reachable from a list of roots, with their
+//! source ranges. Nodes reachable from several places are stored once, so sharing is
+//! preserved. Input source maps, the GC policy and the memory limit are not saved.
+//!
+//! All integers are little-endian. Strings are a `u32` length followed by their UTF-8
+//! bytes (or UTF-16 units for string atoms). Each node is written as its number in
+//! preorder; the first time a node appears, the number is followed by its kind, its range,
+//! whether it is synthesized and its fields in definition order. Later occurrences only write the number.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use juno_support::atom_table::Atom;
+use juno_support::atom_table::AtomU16;
+use juno_support::NullTerminatedBuf;
+use thiserror::Error;
+
+use super::grow_stack;
+use super::AssignmentExpressionOperator;
+use super::BinaryExpressionOperator;
+use super::Context;
+use super::ExportKind;
+use super::FieldValue;
+use super::GCLock;
+use super::ImportKind;
+use super::LogicalExpressionOperator;
+use super::MethodDefinitionKind;
+use super::Node;
+use super::NodeLabel;
+use super::NodeList;
+use super::NodePtr;
+use super::NodeRc;
+use super::NodeString;
+use super::PropertyKind;
+use super::SourceId;
+use super::SourceLoc;
+use super::SourceRange;
+use super::TemplateMetadata;
+use super::TooDeep;
+use super::UnaryExpressionOperator;
+use super::UpdateExpressionOperator;
+use super::VariableDeclarationKind;
+
+/// First bytes of every snapshot.
+const MAGIC: &[u8; 8] = b"JUNOAST\0";
+
+/// Incremented whenever the format changes.
+const VERSION: u32 = 2;
+
+/// Tags of the field values.
+const TAG_NULL: u8 = 0;
+const TAG_NODE: u8 = 1;
+const TAG_LIST: u8 = 2;
+const TAG_LABEL: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_NUMBER: u8 = 5;
+const TAG_BOOL: u8 = 6;
+const TAG_ENUM: u8 = 7;
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("not a Juno AST snapshot")]
+    BadMagic,
+    #[error("unsupported snapshot version {0}")]
+    Version(u32),
+    #[error("corrupt snapshot: {0}")]
+    Corrupt(String),
+    #[error("{0}")]
+    TooDeep(#[from] TooDeep),
+}
+
+fn corrupt<S: Into<String>>(message: S) -> SnapshotError {
+    SnapshotError::Corrupt(message.into())
+}
+
+/// Write a snapshot of `ctx` and of the trees rooted at `roots` to `out`.
+pub fn save_context<W: Write>(ctx: &mut Context, roots: &[NodeRc], out: W) -> io::Result<()> {
+    let mut w = Writer {
+        out,
+        ids: HashMap::new(),
+    };
+    w.out.write_all(MAGIC)?;
+    w.u32(VERSION)?;
+    w.bool(ctx.strict_mode())?;
+    w.bool(ctx.annex_b())?;
+    w.bool(ctx.warn_undefined)?;
+
+    let atoms = ctx.atom_table();
+    w.len(atoms.num_atoms())?;
+    for index in 0..atoms.num_atoms() {
+        w.str(atoms.str(atoms.atom_at(index).unwrap()))?;
+    }
+    w.len(atoms.num_atoms_u16())?;
+    for index in 0..atoms.num_atoms_u16() {
+        let units = atoms.str_u16(atoms.atom_u16_at(index).unwrap());
+        w.len(units.len())?;
+        for unit in units {
+            w.out.write_all(&unit.to_le_bytes())?;
+        }
+    }
+
+    let sm = ctx.sm();
+    w.len(sm.num_sources())?;
+    for index in 0..sm.num_sources() {
+        let id = SourceId(index as u32);
+        w.str(sm.source_name(id))?;
+        w.bytes(sm.source_buffer(id).as_bytes())?;
+    }
+
+    let gc = GCLock::new(ctx);
+    w.len(roots.len())?;
+    for root in roots {
+        w.node(root.node(&gc))?;
+    }
+    w.out.flush()
+}
+
+/// Restore the snapshot read from `input` into `ctx`, returning its roots in the order in
+/// which they were saved.
+///
+/// The saved sources are added after the existing sources of `ctx`, and the ranges of the
+/// restored nodes refer to them. Strict mode and Annex B are set as they were when saving.
+/// Fails with [`SnapshotError::TooDeep`] if a tree exceeds the depth limit of `ctx`
+/// (see [`Context::set_max_depth`]), like [`crate::check_depth`] would.
+pub fn load_context<R: Read>(ctx: &mut Context, input: R) -> Result<Vec<NodeRc>, SnapshotError> {
+    let mut r = Reader {
+        input,
+        atoms: vec![],
+        atoms_u16: vec![],
+        sources: vec![],
+        nodes: vec![],
+        max_depth: ctx.max_depth(),
+        depth: 0,
+    };
+    let mut magic = [0; 8];
+    r.input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(SnapshotError::Version(version));
+    }
+    if r.bool()? {
+        ctx.enable_strict_mode();
+    }
+    ctx.set_annex_b(r.bool()?);
+    ctx.warn_undefined = r.bool()?;
+
+    for _ in 0..r.len()? {
+        let s = r.string()?;
+        r.atoms.push(ctx.atom(s));
+    }
+    for _ in 0..r.len()? {
+        let len = r.len()?;
+        let mut units = Vec::with_capacity(len.min(1 << 16));
+        for _ in 0..len {
+            let mut buf = [0; 2];
+            r.input.read_exact(&mut buf)?;
+            units.push(u16::from_le_bytes(buf));
+        }
+        r.atoms_u16.push(ctx.atom_u16(units));
+    }
+
+    for _ in 0..r.len()? {
+        let name = r.string()?;
+        let buf = NullTerminatedBuf::from_slice_check(&r.bytes()?);
+        r.sources.push(ctx.sm_mut().add_source(name, buf));
+    }
+
+    let gc = GCLock::new(ctx);
+    let mut roots = vec![];
+    for _ in 0..r.len()? {
+        let root = r.node(&gc)?;
+        roots.push(NodeRc::from_node(&gc, root));
+    }
+    Ok(roots)
+}
+
+struct Writer<'gc, W: Write> {
+    out: W,
+    /// Number of each node which has been written.
+    ids: HashMap<NodePtr<'gc>, u32>,
+}
+
+impl<'gc, W: Write> Writer<'gc, W> {
+    fn u32(&mut self, value: u32) -> io::Result<()> {
+        self.out.write_all(&value.to_le_bytes())
+    }
+
+    fn len(&mut self, len: usize) -> io::Result<()> {
+        let len = u32::try_from(len)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many elements"))?;
+        self.u32(len)
+    }
+
+    fn bool(&mut self, value: bool) -> io::Result<()> {
+        self.out.write_all(&[value as u8])
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.len(bytes.len())?;
+        self.out.write_all(bytes)
+    }
+
+    fn str(&mut self, s: &str) -> io::Result<()> {
+        self.bytes(s.as_bytes())
+    }
+
+    fn range(&mut self, range: &SourceRange) -> io::Result<()> {
+        self.u32(range.file.0)?;
+        self.u32(range.start.line)?;
+        self.u32(range.start.col)?;
+        self.u32(range.end.line)?;
+        self.u32(range.end.col)
+    }
+
+    fn node(&mut self, node: &'gc Node<'gc>) -> io::Result<()> {
+        if let Some(&id) = self.ids.get(&NodePtr::from_node(node)) {
+            return self.u32(id);
+        }
+        let id = self.ids.len() as u32;
+        self.ids.insert(NodePtr::from_node(node), id);
+        self.u32(id)?;
+        self.str(node.name())?;
+        self.range(node.range())?;
+        self.out.write_all(&[node.is_synthesized() as u8])?;
+        grow_stack(|| self.fields(node))
+    }
+
+    /// Write the fields of `node`, in definition order.
+    fn fields(&mut self, node: &'gc Node<'gc>) -> io::Result<()> {
+        for info in node.fields() {
+            match node.field(info.field).unwrap() {
+                FieldValue::Null => self.out.write_all(&[TAG_NULL])?,
+                FieldValue::Node(child) => {
+                    self.out.write_all(&[TAG_NODE])?;
+                    self.node(child)?;
+                }
+                FieldValue::NodeList(list) => {
+                    self.out.write_all(&[TAG_LIST])?;
+                    self.len(list.len())?;
+                    for elem in list.iter() {
+                        self.node(elem)?;
+                    }
+                }
+                FieldValue::Label(label) => {
+                    self.out.write_all(&[TAG_LABEL])?;
+                    self.len(label.index())?;
+                }
+                FieldValue::String(string) => {
+                    self.out.write_all(&[TAG_STRING])?;
+                    self.len(string.index())?;
+                }
+                FieldValue::Number(n) => {
+                    self.out.write_all(&[TAG_NUMBER])?;
+                    self.out.write_all(&n.to_bits().to_le_bytes())?;
+                }
+                FieldValue::Bool(b) => {
+                    self.out.write_all(&[TAG_BOOL])?;
+                    self.bool(b)?;
+                }
+                FieldValue::Enum(s) => {
+                    self.out.write_all(&[TAG_ENUM])?;
+                    self.str(s)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'gc, R: Read> {
+    input: R,
+    /// Restored atoms, indexed by their position in the saved table.
+    atoms: Vec<Atom>,
+    atoms_u16: Vec<AtomU16>,
+    /// Restored sources, indexed by their saved ID.
+    sources: Vec<SourceId>,
+    /// Restored nodes, indexed by their number.
+    /// `None` for nodes whose fields are still being read.
+    nodes: Vec<Option<&'gc Node<'gc>>>,
+    /// Depth limit of the context, see [`Context::set_max_depth`].
+    max_depth: Option<usize>,
+    /// Depth of the node being read, the roots being at depth 0.
+    depth: usize,
+}
+
+/// The value of a field, before it is converted to the type of the field.
+enum Value<'gc> {
+    Null,
+    Node(&'gc Node<'gc>),
+    List(Vec<&'gc Node<'gc>>),
+    Label(Atom),
+    String(AtomU16),
+    Number(f64),
+    Bool(bool),
+    Enum(String),
+}
+
+impl<'gc, R: Read> Reader<'gc, R> {
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.input.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.input.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        self.u32().map(|len| len as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(corrupt(format!("invalid boolean {}", b))),
+        }
+    }
+
+    fn bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.len()?;
+        let mut bytes = vec![];
+        (&mut self.input).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, SnapshotError> {
+        String::from_utf8(self.bytes()?).map_err(|_| corrupt("invalid UTF-8"))
+    }
+
+    fn range(&mut self) -> Result<SourceRange, SnapshotError> {
+        let file = match self.u32()? {
+            id if id == SourceId::INVALID.0 => SourceId::INVALID,
+            id => *self
+                .sources
+                .get(id as usize)
+                .ok_or_else(|| corrupt(format!("invalid source {}", id)))?,
+        };
+        let mut loc = || -> io::Result<SourceLoc> {
+            Ok(SourceLoc {
+                line: self.u32()?,
+                col: self.u32()?,
+            })
+        };
+        Ok(SourceRange {
+            file,
+            start: loc()?,
+            end: loc()?,
+        })
+    }
+
+    fn node(&mut self, gc: &'gc GCLock) -> Result<&'gc Node<'gc>, SnapshotError> {
+        let id = self.len()?;
+        if id < self.nodes.len() {
+            return self.nodes[id].ok_or_else(|| corrupt("node contains itself"));
+        }
+        if id != self.nodes.len() {
+            return Err(corrupt(format!("invalid node number {}", id)));
+        }
+        self.nodes.push(None);
+        let kind = self.string()?;
+        let metadata = TemplateMetadata {
+            range: self.range()?,
+            synthesized: self.u8()? != 0,
+            ..Default::default()
+        };
+        if let Some(limit) = self.max_depth {
+            if self.depth >= limit {
+                return Err(TooDeep {
+                    limit,
+                    range: metadata.range,
+                }
+                .into());
+            }
+        }
+        self.depth += 1;
+        let node = grow_stack(|| build_node(gc, self, &kind, metadata));
+        self.depth -= 1;
+        let node = node?;
+        self.nodes[id] = Some(node);
+        Ok(node)
+    }
+
+    fn value(&mut self, gc: &'gc GCLock) -> Result<Value<'gc>, SnapshotError> {
+        Ok(match self.u8()? {
+            TAG_NULL => Value::Null,
+            TAG_NODE => Value::Node(self.node(gc)?),
+            TAG_LIST => {
+                let len = self.len()?;
+                let mut list = Vec::with_capacity(len.min(1 << 16));
+                for _ in 0..len {
+                    list.push(self.node(gc)?);
+                }
+                Value::List(list)
+            }
+            TAG_LABEL => {
+                let index = self.len()?;
+                Value::Label(
+                    *self
+                        .atoms
+                        .get(index)
+                        .ok_or_else(|| corrupt(format!("invalid atom {}", index)))?,
+                )
+            }
+            TAG_STRING => {
+                let index = self.len()?;
+                Value::String(
+                    *self
+                        .atoms_u16
+                        .get(index)
+                        .ok_or_else(|| corrupt(format!("invalid string atom {}", index)))?,
+                )
+            }
+            TAG_NUMBER => {
+                let mut buf = [0; 8];
+                self.input.read_exact(&mut buf)?;
+                Value::Number(f64::from_bits(u64::from_le_bytes(buf)))
+            }
+            TAG_BOOL => Value::Bool(self.bool()?),
+            TAG_ENUM => Value::Enum(self.string()?),
+            tag => return Err(corrupt(format!("invalid tag {}", tag))),
+        })
+    }
+}
+
+/// Read the next field of a node of kind `kind` and convert it to the type of the field.
+fn read_field<'gc, T: FromValue<'gc>, R: Read>(
+    gc: &'gc GCLock,
+    r: &mut Reader<'gc, R>,
+    kind: &str,
+    name: &str,
+) -> Result<T, SnapshotError> {
+    let value = r.value(gc)?;
+    T::from_value(gc, value).map_err(|message| corrupt(format!("{}.{}: {}", kind, name, message)))
+}
+
+/// Generate the function which reads the fields of a node of a kind given by its name.
+macro_rules! gen_build_node {
+    ($name:ident {
+        $(
+            $(#[boxed])?
+            $kind:ident $([ $parent:ident ])? $({
+                $(
+                    $field:ident : $type:ty
+                    $( [ $( $constraint:ident ),* ] )?
+                ),*
+                $(,)?
+            })?
+        ),*
+        $(,)?
+    }) => {
+        fn build_node<'gc, R: Read>(
+            gc: &'gc GCLock,
+            r: &mut Reader<'gc, R>,
+            kind: &str,
+            metadata: TemplateMetadata<'gc>,
+        ) -> Result<&'gc Node<'gc>, SnapshotError> {
+            use crate::*;
+            match kind {
+                $(
+                    stringify!($kind) => Ok(builder::$kind::build_template(
+                        gc,
+                        template::$kind {
+                            metadata,
+                            $($(
+                                $field: read_field(gc, r, kind, stringify!($field))?,
+                            )*)?
+                        },
+                    )),
+                )*
+                _ => Err(corrupt(format!("unknown kind of node '{}'", kind))),
+            }
+        }
+    }
+}
+
+nodekind_defs! { gen_build_node }
+
+/// Implemented by every type which can be stored in a field of a node.
+trait FromValue<'gc>: Sized {
+    fn from_value(gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str>;
+}
+
+impl<'gc> FromValue<'gc> for &'gc Node<'gc> {
+    fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::Node(node) => Ok(node),
+            _ => Err("expected a node"),
+        }
+    }
+}
+
+impl<'gc> FromValue<'gc> for NodeList<'gc> {
+    fn from_value(gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::List(list) => Ok(NodeList::from_iter(gc, list)),
+            _ => Err("expected a list"),
+        }
+    }
+}
+
+impl<'gc, T: FromValue<'gc>> FromValue<'gc> for Option<T> {
+    fn from_value(gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(gc, value).map(Some),
+        }
+    }
+}
+
+impl<'gc> FromValue<'gc> for bool {
+    fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err("expected a boolean"),
+        }
+    }
+}
+
+impl<'gc> FromValue<'gc> for f64 {
+    fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err("expected a number"),
+        }
+    }
+}
+
+impl<'gc> FromValue<'gc> for NodeLabel {
+    fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::Label(label) => Ok(label),
+            _ => Err("expected a label"),
+        }
+    }
+}
+
+impl<'gc> FromValue<'gc> for NodeString {
+    fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+        match value {
+            Value::String(string) => Ok(string),
+            _ => Err("expected a string"),
+        }
+    }
+}
+
+macro_rules! impl_from_value_enum {
+    ($($enum:ident),* $(,)?) => {
+        $(
+        impl<'gc> FromValue<'gc> for $enum {
+            fn from_value(_gc: &'gc GCLock, value: Value<'gc>) -> Result<Self, &'static str> {
+                match value {
+                    Value::Enum(s) => s.parse().map_err(|_| concat!("invalid ", stringify!($enum))),
+                    _ => Err(concat!("expected a ", stringify!($enum))),
+                }
+            }
+        }
+        )*
+    };
+}
+
+impl_from_value_enum!(
+    UnaryExpressionOperator,
+    BinaryExpressionOperator,
+    LogicalExpressionOperator,
+    UpdateExpressionOperator,
+    AssignmentExpressionOperator,
+    VariableDeclarationKind,
+    PropertyKind,
+    MethodDefinitionKind,
+    ImportKind,
+    ExportKind,
+);
@@ -0,0 +1,485 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Textual AST format, which can be written and reviewed by hand, e.g. for test fixtures.
+//!
+//! Each node is its kind, followed by one line per field, indented two more spaces than the
+//! line on which the node starts. Lists have one element per line, prefixed with `- ` and
+//! indented two more spaces than the field:
+//! ```text
+//! Program
+//!   body:
+//!     - ExpressionStatement
+//!       expression: CallExpression
+//!         callee: Identifier
+//!           name: "f"
+//!         arguments:
+//!           - NumericLiteral
+//!             value: 1.0
+//! ```
+//! Field names are in snake case, as in the Rust definitions. Labels and strings are quoted,
+//! with the escapes `\\`, `\"`, `\n`, `\r`, `\t` and `\uXXXX`. Numbers are written as Rust
+//! writes `f64` (`1.0`, `NaN`, `inf`). Enums are written as their source string, e.g. `+`.
+//!
+//! Null fields, empty lists and `false` fields are omitted by [`dump_text`] and may be
+//! omitted by hand; an optional list which is present but empty is written `[]`.
+//! Blank lines and lines starting with `#` are ignored. Source ranges are not represented,
+//! so parsed nodes have invalid ranges.
+
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use super::AssignmentExpressionOperator;
+use super::BinaryExpressionOperator;
+use super::ExportKind;
+use super::FieldValue;
+use super::GCLock;
+use super::ImportKind;
+use super::LogicalExpressionOperator;
+use super::MethodDefinitionKind;
+use super::Node;
+use super::NodeLabel;
+use super::NodeList;
+use super::NodeString;
+use super::PropertyKind;
+use super::UnaryExpressionOperator;
+use super::UpdateExpressionOperator;
+use super::VariableDeclarationKind;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct TextAstError {
+    /// 1-based line of the error.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Return the textual representation of the subtree rooted at `node`.
+pub fn dump_text<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> String {
+    let mut out = String::new();
+    dump_node(gc, node, 0, &mut out);
+    out
+}
+
+/// Write the kind of `node`, which starts on a line indented by `indent`, and its fields.
+fn dump_node<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>, indent: usize, out: &mut String) {
+    out.push_str(node.name());
+    out.push('\n');
+    let indent = indent + 2;
+    for info in node.fields() {
+        let value = node.field(info.field).unwrap();
+        match value {
+            FieldValue::Null => continue,
+            FieldValue::Bool(false) if !info.optional => continue,
+            FieldValue::NodeList(list) if list.is_empty() && !info.optional => continue,
+            _ => {}
+        }
+        write!(out, "{:indent$}{}:", "", info.name, indent = indent).unwrap();
+        match value {
+            FieldValue::Null => unreachable!(),
+            FieldValue::Node(child) => {
+                out.push(' ');
+                dump_node(gc, child, indent, out);
+                continue;
+            }
+            FieldValue::NodeList(list) if list.is_empty() => out.push_str(" []"),
+            FieldValue::NodeList(list) => {
+                out.push('\n');
+                for elem in list.iter() {
+                    write!(out, "{:indent$}- ", "", indent = indent + 2).unwrap();
+                    dump_node(gc, elem, indent + 2, out);
+                }
+                continue;
+            }
+            FieldValue::Label(label) => {
+                out.push(' ');
+                quote(gc.str(label).encode_utf16(), out);
+            }
+            FieldValue::String(string) => {
+                out.push(' ');
+                quote(gc.str_u16(string).iter().copied(), out);
+            }
+            FieldValue::Number(n) => write!(out, " {:?}", n).unwrap(),
+            FieldValue::Bool(b) => write!(out, " {}", b).unwrap(),
+            FieldValue::Enum(s) => write!(out, " {}", s).unwrap(),
+        }
+        out.push('\n');
+    }
+}
+
+/// Write the UTF-16 string `s` as a quoted string. Unpaired surrogates are escaped.
+fn quote(s: impl Iterator<Item = u16>, out: &mut String) {
+    out.push('"');
+    for c in char::decode_utf16(s) {
+        match c {
+            Ok('\\') => out.push_str("\\\\"),
+            Ok('"') => out.push_str("\\\""),
+            Ok('\n') => out.push_str("\\n"),
+            Ok('\r') => out.push_str("\\r"),
+            Ok('\t') => out.push_str("\\t"),
+            Ok(c) if c.is_control() => {
+                let mut buf = [0; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    write!(out, "\\u{:04X}", unit).unwrap();
+                }
+            }
+            Ok(c) => out.push(c),
+            Err(e) => write!(out, "\\u{:04X}", e.unpaired_surrogate()).unwrap(),
+        }
+    }
+    out.push('"');
+}
+
+/// Parse the textual representation of a tree, returning its root.
+pub fn parse_text<'gc>(gc: &'gc GCLock, text: &str) -> Result<&'gc Node<'gc>, TextAstError> {
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                None
+            } else {
+                Some(Line {
+                    number: i + 1,
+                    indent: line.len() - content.len(),
+                    content: content.trim_end(),
+                })
+            }
+        })
+        .collect::<Vec<_>>();
+    let first = match lines.first() {
+        Some(first) => *first,
+        None => {
+            return Err(TextAstError {
+                line: 1,
+                message: "expected a node".to_string(),
+            });
+        }
+    };
+    let mut parser = Parser { gc, lines, pos: 1 };
+    let root = parser.parse_node(first.content, first.indent, first.number)?;
+    match parser.lines.get(parser.pos) {
+        Some(line) => Err(line.error("unexpected line after the root node")),
+        None => Ok(root),
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Line<'t> {
+    /// 1-based line number.
+    number: usize,
+    indent: usize,
+    /// The line without the indentation.
+    content: &'t str,
+}
+
+impl Line<'_> {
+    fn error<S: Into<String>>(&self, message: S) -> TextAstError {
+        TextAstError {
+            line: self.number,
+            message: message.into(),
+        }
+    }
+}
+
+struct Parser<'gc, 'ast: 'gc, 'ctx, 't> {
+    gc: &'gc GCLock<'ast, 'ctx>,
+    lines: Vec<Line<'t>>,
+    /// Index of the next line in `lines`.
+    pos: usize,
+}
+
+/// The value of a field, before it is converted to the type of the field.
+enum Value<'gc, 't> {
+    Node(&'gc Node<'gc>),
+    List(Vec<&'gc Node<'gc>>),
+    /// Any other value, unparsed.
+    Text(&'t str),
+}
+
+/// The fields of a node being parsed.
+struct Fields<'gc, 't> {
+    /// The line on which the node starts.
+    line: usize,
+    /// Name, line and value of each field, removed as they are used.
+    values: Vec<(&'t str, usize, Value<'gc, 't>)>,
+}
+
+impl<'gc, 't> Parser<'gc, '_, '_, 't> {
+    /// Parse a node of kind `kind`, which starts on line `number` indented by `indent`.
+    fn parse_node(
+        &mut self,
+        kind: &str,
+        indent: usize,
+        number: usize,
+    ) -> Result<&'gc Node<'gc>, TextAstError> {
+        let mut fields = Fields {
+            line: number,
+            values: vec![],
+        };
+        while let Some(line) = self.lines.get(self.pos).copied() {
+            if line.indent < indent + 2 {
+                break;
+            }
+            if line.indent > indent + 2 {
+                return Err(line.error("unexpected indentation"));
+            }
+            self.pos += 1;
+            let (name, value) = match line.content.split_once(':') {
+                Some((name, value)) => (name.trim_end(), value.trim_start()),
+                None => return Err(line.error("expected 'field: value'")),
+            };
+            if fields.values.iter().any(|(n, _, _)| *n == name) {
+                return Err(line.error(format!("duplicate field '{}'", name)));
+            }
+            let value = if value.is_empty() {
+                Value::List(self.parse_list(line.indent)?)
+            } else if value == "[]" {
+                Value::List(vec![])
+            } else if value.starts_with(|c: char| c.is_ascii_uppercase()) {
+                Value::Node(self.parse_node(value, line.indent, line.number)?)
+            } else {
+                Value::Text(value)
+            };
+            fields.values.push((name, line.number, value));
+        }
+        let node = build_node(self.gc, kind, &mut fields)?;
+        match fields.values.first() {
+            Some((name, line, _)) => Err(TextAstError {
+                line: *line,
+                message: format!("{} has no field '{}'", kind, name),
+            }),
+            None => Ok(node),
+        }
+    }
+
+    /// Parse the elements of a list field on a line indented by `indent`.
+    fn parse_list(&mut self, indent: usize) -> Result<Vec<&'gc Node<'gc>>, TextAstError> {
+        let mut result = vec![];
+        while let Some(line) = self.lines.get(self.pos).copied() {
+            if line.indent < indent + 2 {
+                break;
+            }
+            let kind = match line.content.strip_prefix("- ") {
+                Some(kind) if line.indent == indent + 2 => kind.trim_start(),
+                _ => return Err(line.error("expected '- Kind'")),
+            };
+            self.pos += 1;
+            result.push(self.parse_node(kind, line.indent, line.number)?);
+        }
+        Ok(result)
+    }
+}
+
+/// Remove the field `name` from `fields` and convert it to the type of the field.
+fn take_field<'gc, T: FromText<'gc>>(
+    gc: &'gc GCLock,
+    fields: &mut Fields<'gc, '_>,
+    name: &str,
+) -> Result<T, TextAstError> {
+    let (line, value) = match fields.values.iter().position(|(n, _, _)| *n == name) {
+        Some(i) => {
+            let (_, line, value) = fields.values.remove(i);
+            (line, Some(value))
+        }
+        None => (fields.line, None),
+    };
+    T::from_text(gc, value).map_err(|message| TextAstError {
+        line,
+        message: format!("field '{}': {}", name, message),
+    })
+}
+
+/// Generate the function which builds a node of a kind given by its name.
+macro_rules! gen_build_node {
+    ($name:ident {
+        $(
+            $(#[boxed])?
+            $kind:ident $([ $parent:ident ])? $({
+                $(
+                    $field:ident : $type:ty
+                    $( [ $( $constraint:ident ),* ] )?
+                ),*
+                $(,)?
+            })?
+        ),*
+        $(,)?
+    }) => {
+        fn build_node<'gc>(
+            gc: &'gc GCLock,
+            kind: &str,
+            fields: &mut Fields<'gc, '_>,
+        ) -> Result<&'gc Node<'gc>, TextAstError> {
+            use crate::*;
+            match kind {
+                $(
+                    stringify!($kind) => Ok(builder::$kind::build_template(
+                        gc,
+                        template::$kind {
+                            metadata: Default::default(),
+                            $($(
+                                $field: take_field(gc, fields, stringify!($field))?,
+                            )*)?
+                        },
+                    )),
+                )*
+                _ => Err(TextAstError {
+                    line: fields.line,
+                    message: format!("unknown kind of node '{}'", kind),
+                }),
+            }
+        }
+    }
+}
+
+nodekind_defs! { gen_build_node }
+
+/// Implemented by every type which can be stored in a field of a node.
+/// `value` is `None` if the field was omitted.
+trait FromText<'gc>: Sized {
+    fn from_text(gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String>;
+}
+
+/// Return the unparsed text of `value`.
+fn text<'t>(value: Option<Value<'_, 't>>) -> Result<&'t str, String> {
+    match value {
+        Some(Value::Text(text)) => Ok(text),
+        Some(_) => Err("expected a value, not a node".to_string()),
+        None => Err("missing".to_string()),
+    }
+}
+
+/// Parse the quoted string `s` into UTF-16.
+fn unquote(s: &str) -> Result<Vec<u16>, String> {
+    let inner = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .filter(|_| s.len() >= 2)
+        .ok_or_else(|| format!("expected a quoted string, found '{}'", s))?;
+    let mut result = vec![];
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('\\') => '\\',
+                Some('"') => '"',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('u') => {
+                    let hex = chars.as_str().get(..4).unwrap_or_default();
+                    let unit = u16::from_str_radix(hex, 16)
+                        .map_err(|_| format!("invalid escape '\\u{}'", hex))?;
+                    result.push(unit);
+                    chars = chars.as_str()[4..].chars();
+                    continue;
+                }
+                _ => return Err("invalid escape".to_string()),
+            },
+            '"' => return Err("unescaped '\"' in string".to_string()),
+            c => c,
+        };
+        let mut buf = [0; 2];
+        result.extend_from_slice(c.encode_utf16(&mut buf));
+    }
+    Ok(result)
+}
+
+impl<'gc> FromText<'gc> for &'gc Node<'gc> {
+    fn from_text(_gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        match value {
+            Some(Value::Node(node)) => Ok(node),
+            Some(_) => Err("expected a node".to_string()),
+            None => Err("missing".to_string()),
+        }
+    }
+}
+
+impl<'gc> FromText<'gc> for NodeList<'gc> {
+    fn from_text(gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        match value {
+            Some(Value::List(list)) => Ok(NodeList::from_iter(gc, list)),
+            Some(_) => Err("expected a list".to_string()),
+            None => Ok(NodeList::new(gc)),
+        }
+    }
+}
+
+impl<'gc, T: FromText<'gc>> FromText<'gc> for Option<T> {
+    fn from_text(gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        match value {
+            None | Some(Value::Text("null")) => Ok(None),
+            value => T::from_text(gc, value).map(Some),
+        }
+    }
+}
+
+impl<'gc> FromText<'gc> for bool {
+    fn from_text(_gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        match value {
+            None => Ok(false),
+            value => match text(value)? {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                text => Err(format!("expected a boolean, found '{}'", text)),
+            },
+        }
+    }
+}
+
+impl<'gc> FromText<'gc> for f64 {
+    fn from_text(_gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        let text = text(value)?;
+        text.parse()
+            .map_err(|_| format!("expected a number, found '{}'", text))
+    }
+}
+
+impl<'gc> FromText<'gc> for NodeLabel {
+    fn from_text(gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        let units = unquote(text(value)?)?;
+        let s = String::from_utf16(&units).map_err(|_| "unpaired surrogate in label")?;
+        Ok(gc.atom(s))
+    }
+}
+
+impl<'gc> FromText<'gc> for NodeString {
+    fn from_text(gc: &'gc GCLock, value: Option<Value<'gc, '_>>) -> Result<Self, String> {
+        Ok(gc.atom_u16(unquote(text(value)?)?))
+    }
+}
+
+macro_rules! impl_from_text_enum {
+    ($($enum:ident),* $(,)?) => {
+        $(
+        impl<'gc> FromText<'gc> for $enum {
+            fn from_text(
+                _gc: &'gc GCLock,
+                value: Option<Value<'gc, '_>>,
+            ) -> Result<Self, String> {
+                let text = text(value)?;
+                text.parse().map_err(|_| format!("invalid {} '{}'", stringify!($enum), text))
+            }
+        }
+        )*
+    };
+}
+
+impl_from_text_enum!(
+    UnaryExpressionOperator,
+    BinaryExpressionOperator,
+    LogicalExpressionOperator,
+    UpdateExpressionOperator,
+    AssignmentExpressionOperator,
+    VariableDeclarationKind,
+    PropertyKind,
+    MethodDefinitionKind,
+    ImportKind,
+    ExportKind,
+);
@@ -5,8 +5,11 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use juno_support::ident;
 use thiserror::Error;
 
+use super::find_top_level_await;
+use super::is_module_declaration;
 use super::kind::*;
 use super::AssignmentExpressionOperator;
 use super::BinaryExpressionOperator;
@@ -32,6 +35,7 @@ use super::Visitor;
 macro_rules! gen_validate_fn {
     ($name:ident {
         $(
+            $(#[boxed])?
             $kind:ident $([ $parent:ident ])? $({
                 $(
                     $field:ident : $type:ty
@@ -49,7 +53,8 @@ macro_rules! gen_validate_fn {
             ) -> Result<(), ValidationError> {
                 match node {
                     $(
-                        Node::$kind($kind {$($($field,)*)? .. }) => {
+                        Node::$kind(inner) => {
+                            let $kind {$($($field,)*)? .. }: &$kind = inner;
                             // Run the validation for each child.
                             // Use `true &&` to make it work when there's no children.
                             $($(
@@ -115,7 +120,7 @@ impl<'gc> ValidChild<'gc> for &Node<'gc> {
         constraints: &[NodeVariant],
     ) -> Result<(), ValidationError> {
         for &constraint in constraints {
-            if instanceof(self.variant(), constraint) {
+            if self.variant().is_subtype_of(constraint) {
                 return Ok(());
             }
         }
@@ -136,7 +141,7 @@ impl<'gc> ValidChild<'gc> for NodeList<'gc> {
     ) -> Result<(), ValidationError> {
         'elems: for elem in self.iter() {
             for &constraint in constraints {
-                if instanceof(elem.variant(), constraint) {
+                if elem.variant().is_subtype_of(constraint) {
                     // Found a valid constraint for this element,
                     // move on to the next element.
                     continue 'elems;
@@ -153,26 +158,58 @@ impl<'gc> ValidChild<'gc> for NodeList<'gc> {
     }
 }
 
-/// Return whether `subtype` contains `supertype` in its parent chain.
-fn instanceof(subtype: NodeVariant, supertype: NodeVariant) -> bool {
-    let mut cur = subtype;
-    loop {
-        if cur == supertype {
-            return true;
-        }
-        match cur.parent() {
-            None => return false,
-            Some(next) => {
-                cur = next;
-            }
-        }
-    }
-}
-
 /// Custom validation function for constraints which can't be expressed
 /// using just the inheritance structure in Node.
 fn validate_custom<'gc>(ctx: &'gc GCLock, node: &'gc Node<'gc>) -> Result<(), ValidationError> {
     match node {
+        Node::Program(Program { body, .. }) => {
+            if let Some(decl) = body.iter().find(|stmt| is_module_declaration(stmt)) {
+                return Err(ValidationError::new(
+                    ctx,
+                    node,
+                    format!("Unexpected {:?} in a script", decl.variant()),
+                ));
+            }
+            if let Some(await_node) = find_top_level_await(ctx, node) {
+                return Err(ValidationError::new(
+                    ctx,
+                    await_node,
+                    "Unexpected top-level await in a script".to_string(),
+                ));
+            }
+        }
+
+        Node::MetaProperty(MetaProperty {
+            metadata: _,
+            meta: Node::Identifier(meta),
+            property: Node::Identifier(property),
+        }) => {
+            let valid = matches!(
+                (ctx.str(meta.name), ctx.str(property.name)),
+                ("new", "target") | ("import", "meta")
+            );
+            if !valid {
+                return Err(ValidationError::new(
+                    ctx,
+                    node,
+                    format!(
+                        "Invalid meta property '{}.{}'",
+                        ctx.str(meta.name),
+                        ctx.str(property.name)
+                    ),
+                ));
+            }
+        }
+
+        // Reserved words are allowed, since identifiers are also used for property names.
+        Node::Identifier(Identifier { name, .. }) if !ident::is_identifier_name(ctx.str(*name)) => {
+            return Err(ValidationError::new(
+                ctx,
+                node,
+                format!("Invalid identifier name '{}'", ctx.str(*name)),
+            ));
+        }
+
         Node::MemberExpression(MemberExpression {
             metadata: _,
             property,
@@ -262,10 +299,16 @@ impl Validator {
 
     /// Validate `node` and recursively validate its children.
     fn validate_node<'gc>(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>) {
+        if ctx.ctx().is_cancelled() {
+            return;
+        }
         if let Err(e) = validate_node(ctx, node) {
             self.errors.push(e);
         }
-        node.visit_children(ctx, self);
+        if let Err(e) = node.try_visit_children(ctx, self) {
+            self.errors
+                .push(ValidationError::new(ctx, node, e.to_string()));
+        }
     }
 }
 
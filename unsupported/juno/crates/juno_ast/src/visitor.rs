@@ -7,9 +7,12 @@
 
 //! Visitor structures and helpers for the AST.
 
+use std::collections::HashMap;
+
 use crate::GCLock;
 use crate::Node;
 use crate::NodeField;
+use crate::NodeVariant;
 
 /// Indicates the path to the current node.
 #[derive(Debug, Copy, Clone)]
@@ -62,3 +65,93 @@ pub trait VisitorMut<'gc> {
         path: Option<Path<'gc>>,
     ) -> TransformResult<&'gc Node<'gc>>;
 }
+
+/// A visitor which only looks at some kinds of nodes and doesn't control the traversal,
+/// so that several of them can share a single traversal with [`VisitAll`].
+pub trait KindVisitor<'gc> {
+    /// The kinds of nodes passed to [`KindVisitor::enter`] and [`KindVisitor::leave`].
+    /// Abstract variants, like `Expression`, include all their subtypes.
+    /// An empty list, the default, means every node.
+    fn kinds(&self) -> &'static [NodeVariant] {
+        &[]
+    }
+
+    /// Whether to ignore synthesized nodes (see [`Node::is_synthesized`]), e.g. for lint
+    /// rules which shouldn't report code injected by transforms.
+    /// Their children are still visited.
+    fn skip_synthesized(&self) -> bool {
+        false
+    }
+
+    /// Called on each node of interest before its children.
+    fn enter(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>);
+
+    /// Called on each node of interest after its children.
+    fn leave(&mut self, _ctx: &'gc GCLock, _node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {}
+}
+
+/// Runs several [`KindVisitor`]s in a single traversal of the tree.
+///
+/// On each node, the visitors interested in its kind are entered in order, then the
+/// children are visited, then the visitors are left in reverse order.
+pub struct VisitAll<'v, 'gc> {
+    visitors: Vec<&'v mut dyn KindVisitor<'gc>>,
+    /// [`KindVisitor::skip_synthesized`] of each visitor.
+    skip_synthesized: Vec<bool>,
+    /// Indices in `visitors` of the visitors interested in each kind of node.
+    /// Kinds which no visitor is interested in are missing.
+    dispatch: HashMap<NodeVariant, Vec<usize>>,
+}
+
+impl<'v, 'gc> VisitAll<'v, 'gc> {
+    pub fn new(visitors: Vec<&'v mut dyn KindVisitor<'gc>>) -> Self {
+        let mut dispatch = HashMap::new();
+        for &kind in NodeVariant::KINDS {
+            let interested = visitors
+                .iter()
+                .enumerate()
+                .filter(|(_, visitor)| {
+                    let kinds = visitor.kinds();
+                    kinds.is_empty() || kinds.iter().any(|&k| kind.is_subtype_of(k))
+                })
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            if !interested.is_empty() {
+                dispatch.insert(kind, interested);
+            }
+        }
+        let skip_synthesized = visitors.iter().map(|v| v.skip_synthesized()).collect();
+        Self {
+            visitors,
+            skip_synthesized,
+            dispatch,
+        }
+    }
+
+    /// Visit the tree rooted at `root` with every visitor.
+    pub fn visit(&mut self, ctx: &'gc GCLock, root: &'gc Node<'gc>) {
+        root.visit(ctx, self, None);
+    }
+}
+
+impl<'gc> Visitor<'gc> for VisitAll<'_, 'gc> {
+    fn call(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        let variant = node.variant();
+        let synthesized = node.is_synthesized();
+        if let Some(interested) = self.dispatch.get(&variant) {
+            for &i in interested {
+                if !(synthesized && self.skip_synthesized[i]) {
+                    self.visitors[i].enter(ctx, node, path);
+                }
+            }
+        }
+        node.visit_children(ctx, self);
+        if let Some(interested) = self.dispatch.get(&variant) {
+            for &i in interested.iter().rev() {
+                if !(synthesized && self.skip_synthesized[i]) {
+                    self.visitors[i].leave(ctx, node, path);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Procedural macros for `juno_ast`, which re-exports them.
+//!
+//! This crate has no dependencies, so the input is scanned as plain token trees
+//! and the output is generated as text.
+
+use proc_macro::Delimiter;
+use proc_macro::TokenStream;
+use proc_macro::TokenTree;
+
+/// Prefix of the per-kind methods.
+const VISIT_PREFIX: &str = "visit_";
+
+/// Name of the method called on the kinds which have no method of their own.
+const FALLTHROUGH: &str = "visit_node";
+
+/// Implement `Visitor` for a type from the per-kind methods of an inherent `impl` block.
+///
+/// Each method named `visit_<kind>`, where `<kind>` is the snake case name of a node kind,
+/// like `visit_call_expression` for `CallExpression`, is called on the nodes of that kind as
+/// `self.visit_call_expression(ctx, node, inner, path)`, where `node` is the `&'gc Node<'gc>`
+/// and `inner` the `&'gc CallExpression<'gc>` in it.
+/// The other nodes are passed to `visit_node(ctx, node, path)` if the block defines it,
+/// or else have their children visited.
+/// As with any `Visitor`, the methods must visit the children themselves if needed.
+///
+/// The lifetime of the AST is the first lifetime parameter of the `impl`.
+/// The argument of the attribute is the path to `juno_ast`, which defaults to `::juno_ast`,
+/// e.g. `#[kind_visitor(juno::ast)]` in crates which only depend on `juno`.
+#[proc_macro_attribute]
+pub fn kind_visitor(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let krate = if attr.is_empty() {
+        "::juno_ast".to_string()
+    } else {
+        attr.to_string()
+    };
+    let generated = match gen_kind_visitor(&krate, item.clone()) {
+        Ok(generated) => generated,
+        Err(msg) => format!("compile_error!({:?});", msg),
+    };
+    let mut result = item;
+    result.extend(generated.parse::<TokenStream>().unwrap());
+    result
+}
+
+/// Generate the `Visitor` impl for the `impl` block `item`, as text.
+fn gen_kind_visitor(krate: &str, item: TokenStream) -> Result<String, String> {
+    let mut tokens = item.into_iter().peekable();
+
+    // Skip the attributes and qualifiers up to `impl`.
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "impl" => break,
+            Some(_) => {}
+            None => return Err("#[kind_visitor] must be used on an impl block".into()),
+        }
+    }
+
+    // Split the header into generics, self type and where clause.
+    let mut generics = vec![];
+    if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '<') {
+        let mut depth = 0;
+        for token in &mut tokens {
+            if let TokenTree::Punct(p) = &token {
+                match p.as_char() {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+            }
+            generics.push(token);
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+    let mut self_ty = vec![];
+    let mut where_clause = vec![];
+    let body = loop {
+        match tokens.next() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+                break group.stream();
+            }
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "for" => {
+                return Err("#[kind_visitor] must be used on an inherent impl block".into());
+            }
+            Some(token) => {
+                if where_clause.is_empty()
+                    && !matches!(&token, TokenTree::Ident(ident) if ident.to_string() == "where")
+                {
+                    self_ty.push(token);
+                } else {
+                    where_clause.push(token);
+                }
+            }
+            None => return Err("#[kind_visitor] must be used on an impl block".into()),
+        }
+    };
+    let generics = generics.into_iter().collect::<TokenStream>().to_string();
+    let self_ty = self_ty.into_iter().collect::<TokenStream>().to_string();
+    let where_clause = where_clause
+        .into_iter()
+        .collect::<TokenStream>()
+        .to_string();
+    let lifetime = first_lifetime(&generics)
+        .ok_or("#[kind_visitor] needs a lifetime parameter for the AST, like impl<'gc>")?;
+
+    // Collect the names of the methods.
+    let mut kinds = vec![];
+    let mut has_fallthrough = false;
+    let mut body = body.into_iter();
+    while let Some(token) = body.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "fn" => {}
+            _ => continue,
+        }
+        let name = match body.next() {
+            Some(TokenTree::Ident(name)) => name.to_string(),
+            _ => continue,
+        };
+        if name == FALLTHROUGH {
+            has_fallthrough = true;
+        } else if let Some(kind) = name.strip_prefix(VISIT_PREFIX) {
+            kinds.push((name.clone(), kind_name(kind)));
+        }
+    }
+
+    let mut arms = String::new();
+    for (method, kind) in &kinds {
+        arms.push_str(&format!(
+            "{krate}::Node::{kind}(inner) => self.{method}(ctx, node, inner, path),\n",
+            krate = krate,
+            kind = kind,
+            method = method,
+        ));
+    }
+    let fallthrough = if has_fallthrough {
+        format!("self.{}(ctx, node, path)", FALLTHROUGH)
+    } else {
+        "node.visit_children(ctx, self)".to_string()
+    };
+    Ok(format!(
+        r#"
+impl {generics} {krate}::Visitor<{lt}> for {self_ty} {where_clause} {{
+    #[allow(unreachable_patterns)]
+    fn call(
+        &mut self,
+        ctx: &{lt} {krate}::GCLock,
+        node: &{lt} {krate}::Node<{lt}>,
+        path: ::std::option::Option<{krate}::Path<{lt}>>,
+    ) {{
+        match node {{
+            {arms}
+            _ => {fallthrough},
+        }}
+    }}
+}}
+"#,
+        generics = generics,
+        krate = krate,
+        lt = lifetime,
+        self_ty = self_ty,
+        where_clause = where_clause,
+        arms = arms,
+        fallthrough = fallthrough,
+    ))
+}
+
+/// The first lifetime in `generics`, as text.
+fn first_lifetime(generics: &str) -> Option<String> {
+    let start = generics.find('\'')?;
+    let len = generics[start + 1..]
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(generics.len() - start - 1);
+    Some(generics[start..start + 1 + len].to_string())
+}
+
+/// Convert the snake case name of a node kind to the name of its variant,
+/// e.g. `call_expression` to `CallExpression`.
+/// The `jsx` and `ts` prefixes are upper case, e.g. `jsx_element` is `JSXElement`.
+fn kind_name(snake: &str) -> String {
+    let mut result = String::new();
+    for (i, word) in snake.split('_').enumerate() {
+        if i == 0 && (word == "jsx" || word == "ts") {
+            result.push_str(&word.to_uppercase());
+            continue;
+        }
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            result.extend(first.to_uppercase());
+            result.push_str(chars.as_str());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_name() {
+        assert_eq!(kind_name("identifier"), "Identifier");
+        assert_eq!(kind_name("call_expression"), "CallExpression");
+        assert_eq!(kind_name("jsx_opening_element"), "JSXOpeningElement");
+        assert_eq!(kind_name("ts_type_annotation"), "TSTypeAnnotation");
+    }
+
+    #[test]
+    fn test_first_lifetime() {
+        assert_eq!(first_lifetime("<'gc>").as_deref(), Some("'gc"));
+        assert_eq!(first_lifetime("<T, 'a, 'b>").as_deref(), Some("'a"));
+        assert_eq!(first_lifetime("<T>"), None);
+    }
+}
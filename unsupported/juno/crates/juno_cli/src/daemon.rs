@@ -0,0 +1,260 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! `--daemon` mode, a long-running server for watch-based bundlers like Metro, which keeps
+//! the results of unchanged files across rebuilds.
+//!
+//! Clients connect to a Unix socket and send JSON-RPC 2.0 requests, one per line, and get
+//! one response per line, in order:
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "method": "emit", "params": {"file": "a.js", "ops": ["strip-flow"]}}
+//! {"jsonrpc": "2.0", "id": 1, "result": {"js": "..."}}
+//! ```
+//! The params are the fields of the requests of `--pipe` mode (see [`crate::pipe`]).
+//! The methods are:
+//! - `parse`, whose result is the AST of the file in `ast`;
+//! - `transform`, which performs the `ops` and whose result has the outputs of the ops;
+//! - `emit`, which performs the `ops` and whose result has the generated JS in `js`.
+//!
+//! Errors in the file, like syntax errors, are reported as a JSON-RPC error with code
+//! [`SOURCE_ERROR`], whose data has the errors in the format of `--pipe` mode. Requests
+//! exceeding their limits (see [`crate::pipe::RequestLimits`]) fail with [`LIMIT_EXCEEDED`].
+//!
+//! Every connection is served by its own thread, and every request is performed in its
+//! own [`juno::ast::Context`]. Results are cached in memory by the contents of the file and
+//! the operations, and the cache is shared by all connections, so a file which didn't
+//! change since a previous request, by any client, isn't processed again.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use juno_support::cache::CacheKey;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::pipe::parse_request;
+use crate::pipe::process;
+use crate::pipe::Config;
+use crate::pipe::Diagnostic;
+use crate::pipe::Op;
+use crate::pipe::Request;
+
+/// The request isn't valid JSON.
+const PARSE_ERROR: i64 = -32700;
+
+/// The request isn't a valid JSON-RPC request.
+const INVALID_REQUEST: i64 = -32600;
+
+/// The method doesn't exist.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The params aren't valid for the method.
+const INVALID_PARAMS: i64 = -32602;
+
+/// The file couldn't be read, or has errors.
+const SOURCE_ERROR: i64 = 1;
+
+/// The request exceeded its time or memory limits, or its source is too large.
+const LIMIT_EXCEEDED: i64 = 2;
+
+/// Number of results above which the cache is emptied, to bound the memory of the daemon.
+const MAX_CACHED_RESULTS: usize = 4096;
+
+/// Results of requests, shared by all the connections, and by the workers of the HTTP
+/// server (see [`crate::server`]).
+#[derive(Default)]
+pub struct ResultCache {
+    results: Mutex<HashMap<CacheKey, Map<String, Value>>>,
+}
+
+impl ResultCache {
+    /// Perform `request` with `config`, or return its cached result.
+    pub fn process(
+        &self,
+        config: &Config,
+        request: &Request,
+    ) -> Result<Map<String, Value>, Vec<Diagnostic>> {
+        let buf = request.read_source(config)?;
+        let key = CacheKey::new(buf.as_bytes(), &format!("{:?} {:?}", config, request.ops));
+        if let Some(result) = self.results.lock().unwrap().get(&key) {
+            return Ok(result.clone());
+        }
+        let result = process(config, request, buf)?;
+        let mut results = self.results.lock().unwrap();
+        if results.len() >= MAX_CACHED_RESULTS {
+            results.clear();
+        }
+        results.insert(key, result.clone());
+        Ok(result)
+    }
+}
+
+/// Adjust the operations of `request` for `method`.
+/// Returns false if there is no such method.
+pub fn apply_method(request: &mut Request, method: &str) -> bool {
+    match method {
+        "parse" => request.ops = vec![Op::GenAst],
+        "transform" => {}
+        "emit" => request.ops.push(Op::GenJs),
+        _ => return false,
+    }
+    true
+}
+
+/// JSON-RPC error response.
+fn error_response(id: Value, code: i64, message: &str, errors: &[Diagnostic]) -> Value {
+    let mut error = json!({
+        "code": code,
+        "message": message,
+    });
+    if !errors.is_empty() {
+        error["data"] = json!({
+            "errors": errors.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+        });
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": error,
+    })
+}
+
+/// Perform the JSON-RPC request `message`, whose ID is `id`, and return the response.
+fn handle(config: &Config, cache: &ResultCache, message: &Value, id: Value) -> Value {
+    let method = match message.get("method") {
+        Some(Value::String(method)) => method.as_str(),
+        _ => return error_response(id, INVALID_REQUEST, "Missing method", &[]),
+    };
+    let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+    let mut request = match parse_request(&params) {
+        Ok(request) => request,
+        Err(e) => return error_response(id, INVALID_PARAMS, "Invalid params", &[e]),
+    };
+    if !apply_method(&mut request, method) {
+        return error_response(
+            id,
+            METHOD_NOT_FOUND,
+            &format!("Unknown method: {}", method),
+            &[],
+        );
+    }
+    match cache.process(config, &request) {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        }),
+        Err(errors) if errors.iter().any(Diagnostic::is_limit) => {
+            error_response(id, LIMIT_EXCEEDED, "Resource limit exceeded", &errors)
+        }
+        Err(errors) => error_response(id, SOURCE_ERROR, "Errors in the source", &errors),
+    }
+}
+
+/// Build the response to the JSON-RPC request whose JSON is `line`.
+/// Returns `None` for notifications, which are requests without an ID.
+fn respond(config: &Config, cache: &ResultCache, line: &str) -> Option<Value> {
+    let message = match serde_json::from_str::<Value>(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                &format!("Invalid JSON: {}", e),
+                &[],
+            ));
+        }
+    };
+    let response = handle(
+        config,
+        cache,
+        &message,
+        message.get("id").cloned().unwrap_or(Value::Null),
+    );
+    message.get("id").map(|_| response)
+}
+
+#[cfg(unix)]
+mod server {
+    use std::fs;
+    use std::io;
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::thread;
+
+    use anyhow::Context;
+
+    use super::respond;
+    use super::ResultCache;
+    use crate::pipe::Config;
+
+    /// Respond to the requests of `stream` until the client disconnects.
+    fn serve(config: &Config, cache: &ResultCache, stream: UnixStream) -> io::Result<()> {
+        let mut out = &stream;
+        for line in BufReader::new(&stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = respond(config, cache, &line) {
+                serde_json::to_writer(&mut out, &response)?;
+                out.write_all(b"\n")?;
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Listen on the Unix socket `path` and serve every connection, until killed.
+    pub fn listen(config: Config, path: &Path) -> anyhow::Result<()> {
+        // The socket of a previous daemon, which wasn't removed when it was killed,
+        // would make binding fail.
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                fs::remove_file(path).with_context(|| path.display().to_string())?;
+            }
+        }
+        let listener = UnixListener::bind(path).with_context(|| path.display().to_string())?;
+        let cache = Arc::new(ResultCache::default());
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let config = config.clone();
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                if let Err(e) = serve(&config, &cache, stream) {
+                    eprintln!("Connection failed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Serve requests on the Unix socket `path` until killed.
+#[cfg(unix)]
+pub fn run_daemon(config: Config, path: &std::path::Path) -> anyhow::Result<()> {
+    server::listen(config, path)
+}
+
+/// Serve requests on the Unix socket `path` until killed.
+#[cfg(not(unix))]
+pub fn run_daemon(_config: Config, _path: &std::path::Path) -> anyhow::Result<()> {
+    anyhow::bail!("--daemon is only supported on Unix")
+}
@@ -22,39 +22,76 @@ use command_line::CommandLine;
 use command_line::Hidden;
 use command_line::Opt;
 use command_line::OptDesc;
+use juno::analysis::api_diff;
+use juno::analysis::asi;
+use juno::analysis::complexity;
+use juno::analysis::duplicates;
+use juno::analysis::exports;
+use juno::analysis::exports::ModuleInterface;
+use juno::analysis::hermes;
+use juno::analysis::react;
+use juno::analysis::switch;
 use juno::ast;
-use juno::ast::node_cast;
 use juno::ast::validate_tree;
 use juno::ast::NodeRc;
 use juno::ast::SourceRange;
+use juno::coverage::Coverage;
+use juno::docgen;
+use juno::docgen::ModuleDoc;
+use juno::eval::ConstEvaluator;
+use juno::gen_dts;
 use juno::gen_js;
+use juno::gen_js::SourceMapKind;
 use juno::hparser;
 use juno::hparser::MagicCommentKind;
 use juno::hparser::ParsedJS;
 use juno::hparser::ParserDialect;
+use juno::hparser::SourceType;
+use juno::reduce;
 use juno::resolve_dependency;
 use juno::sema;
 use juno::sema::SemContext;
-use juno::sourcemap::merge_sourcemaps;
+use juno::stats::Stats;
+use juno::test262;
+use juno_pass::anonymize::Anonymize;
+use juno_pass::constant_fold::ConstantFold;
+use juno_pass::macros::Macros;
+use juno_pass::mangle_properties::MangleProperties;
+use juno_pass::plugin::PluginRegistry;
+use juno_pass::run_pass;
+use juno_pass::NameMap;
+use juno_pass::Pass;
 use juno_pass::PassManager;
 use juno_support::fetchurl;
+use juno_support::json::JSONEmitter;
 use juno_support::source_manager::SourceId;
 use juno_support::HeapSize;
 use juno_support::NullTerminatedBuf;
+use juno_support::Progress;
+use juno_support::ProgressEvent;
 use juno_support::Timer;
 use sourcemap::SourceMap;
 use url::Url;
 
+mod daemon;
+mod metro;
+mod pipe;
+mod server;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum Gen {
     /// Dump the Semantic resolution information.
     Sema,
     /// Dump the AST as JSON.
     Ast,
+    /// Dump the sources, the AST validation errors and the AST as a single JSON document.
+    Report,
     /// Generate JavaScript source.
     Js,
     /// Generate JavaScript source with annotations about variable resolution.
     ResolvedJs,
+    /// Generate TypeScript declarations from the Flow types.
+    Dts,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -70,9 +107,33 @@ struct Options {
     /// Select what to emit.
     gen: Opt<Gen>,
 
+    /// Embed the results of semantic analysis in the AST JSON.
+    ast_annotations: Opt<bool>,
+
+    /// How to emit the locations of the nodes in the AST JSON.
+    ast_locations: Opt<ast::LocationFormat>,
+
+    /// Base of the columns in the AST JSON.
+    ast_columns: Opt<ast::ColumnBase>,
+
     /// Perform AST validation.
     validate_ast: Opt<bool>,
 
+    /// Warn about suspicious code.
+    lint: Opt<bool>,
+
+    /// Plugin libraries providing passes and lint rules.
+    plugin: Opt<PathBuf>,
+
+    /// Plugins compiled to WebAssembly providing passes.
+    wasm_plugin: Opt<PathBuf>,
+
+    /// Macros to expand, as `name=template`.
+    macros: Opt<String>,
+
+    /// Values to substitute for global names, as `name=value`.
+    defines: Opt<String>,
+
     /// Perform semantic analysis.
     sema: Opt<bool>,
 
@@ -84,10 +145,16 @@ struct Options {
     // #[structopt(long = "out", short = "o", default_value = "-", parse(from_os_str))]
     output_path: Opt<PathBuf>,
 
-    /// Whether to output a source map.
+    /// How to output a source map.
     /// The source map will be merged with an input source map if provided.
     /// Can only be used when generating JS.
-    sourcemap: Opt<bool>,
+    source_map: Opt<SourceMapKind>,
+
+    /// Whether to embed the contents of the sources in the source map.
+    sources_content: Opt<bool>,
+
+    /// Whether to output a map of the names changed by renaming passes.
+    name_map: Opt<bool>,
 
     /// Base URL to prepend to relative URLs.
     base_url: Opt<Option<Url>>,
@@ -101,6 +168,15 @@ struct Options {
     /// Whether to run strip flow types.
     strip_flow: Opt<bool>,
 
+    /// Rename properties whose names match this regular expression.
+    mangle_props: Opt<Option<String>>,
+
+    /// File to write the map from original to renamed property names to.
+    mangle_props_map: Opt<Option<PathBuf>>,
+
+    /// Replace identifiers, strings and numbers with placeholders before generating output.
+    anonymize: Opt<bool>,
+
     /// Whether to force a space after the `async` keyword in arrow functions.
     force_async_arrow_space: Opt<bool>,
 
@@ -111,26 +187,133 @@ struct Options {
     /// Whether to use double quotes on string literals.
     double_quote_strings: Opt<bool>,
 
+    /// Whether to make the generated JS safe to embed in an HTML `<script>` element.
+    inline_script_safe: Opt<bool>,
+
+    /// Whether to escape all non-ASCII characters in the generated JS.
+    ascii_only: Opt<bool>,
+
+    /// Whether to save bytes in the generated JS beyond removing whitespace.
+    compress: Opt<bool>,
+
+    /// Whether to write booleans as `!0` and `!1` in the generated JS.
+    compress_booleans: Opt<bool>,
+
     /// Whether to run the parsed AST.
     run: Opt<bool>,
 
+    /// Print a complexity report instead of generating output.
+    complexity: Opt<bool>,
+
     /// Control the recognized JavaScript dialect.
     dialect: Opt<ParserDialect>,
 
+    /// Whether the inputs are scripts or modules.
+    source_type: Opt<SourceType>,
+
     /// Enable JSX parsing.
     jsx: Opt<bool>,
 
     /// Enable strict mode.
     strict_mode: Opt<bool>,
 
+    /// Enable the Annex B web compatibility extensions.
+    annex_b: Opt<bool>,
+
+    /// Report every error recovery action of the parser.
+    record_recovery: Opt<bool>,
+
+    /// Maximum number of syntax errors to report per input, 0 for no limit.
+    max_errors: Opt<u32>,
+
+    /// Maximum nesting depth of the parser, 0 for its built-in limit.
+    max_parser_depth: Opt<u32>,
+
+    /// Maximum nesting depth of the AST, 0 for no limit.
+    max_ast_depth: Opt<u32>,
+
     /// Warn about undefined variables in strict mode functions.
     warn_undefined: Opt<bool>,
 
+    /// Run the inputs as test262 tests.
+    test262: Opt<bool>,
+
+    /// Directory containing the test262 harness includes.
+    test262_harness: Opt<Option<PathBuf>>,
+
+    /// Report the grammar coverage of the inputs.
+    coverage: Opt<bool>,
+
+    /// Report statistics of the ASTs of the inputs.
+    stats: Opt<bool>,
+
+    /// Report the statistics as JSON.
+    stats_json: Opt<bool>,
+
+    /// Report the interfaces of the input modules.
+    exports: Opt<bool>,
+
+    /// Report the differences between the interfaces of two versions of a module.
+    api_diff: Opt<bool>,
+
+    /// Generate the API documentation of the input modules.
+    docgen: Opt<bool>,
+
+    /// Generate the API documentation as JSON.
+    docgen_json: Opt<bool>,
+
+    /// Reduce the input while the test command succeeds on it.
+    reduce: Opt<bool>,
+
+    /// Shell command deciding whether a reduced input is still interesting.
+    test_cmd: Opt<Option<String>>,
+
+    /// Respond to a stream of requests on stdin.
+    pipe: Opt<bool>,
+
+    /// Transform the files of a stream of requests on stdin for Metro.
+    metro: Opt<bool>,
+
+    /// Serve requests on this Unix socket.
+    daemon: Opt<Option<PathBuf>>,
+
+    /// Serve HTTP requests on this address.
+    serve: Opt<Option<String>>,
+
+    /// Number of HTTP requests handled concurrently, 0 for the number of CPUs.
+    serve_workers: Opt<u32>,
+
+    /// Maximum size of the body of an HTTP request, in bytes.
+    serve_max_body: Opt<u32>,
+
+    /// Time after which a request of a service mode is cancelled, in milliseconds,
+    /// 0 for no limit.
+    request_timeout: Opt<u32>,
+
+    /// Maximum size of the AST of a request of a service mode, in bytes, 0 for no limit.
+    request_max_memory: Opt<u32>,
+
+    /// Maximum size of the source of a request of a service mode, in bytes, 0 for no limit.
+    request_max_source: Opt<u32>,
+
+    /// Maximum length of a line of the source of a request of a service mode, in bytes,
+    /// 0 for no limit.
+    request_max_line: Opt<u32>,
+
+    /// Print the progress of parsing and passes to stderr.
+    progress: Opt<bool>,
+
     /// Measure and print times.
     xtime: Opt<bool>,
 
     /// Measure and print memory.
     xmem: Opt<bool>,
+
+    /// Print a histogram of the allocated nodes by kind.
+    xalloc_stats: Opt<bool>,
+
+    /// Make identifiers point into the source instead of copying them.
+    xsource_atoms: Opt<bool>,
 }
 
 impl Options {
@@ -156,12 +339,59 @@ impl Options {
                     values: Some(&[
                         ("gen-sema", Gen::Sema, "Dump the Sema data."),
                         ("gen-ast", Gen::Ast, "Dump the AST as JSON."),
+                        (
+                            "gen-report",
+                            Gen::Report,
+                            "Dump the sources, AST validation errors and AST as one JSON \
+                            document.",
+                        ),
                         ("gen-js", Gen::Js, "Generate JavaScript source."),
                         (
                             "gen-resolved-js",
                             Gen::ResolvedJs,
                             "Generate resolution information.",
                         ),
+                        ("gen-dts", Gen::Dts, "Generate TypeScript declarations."),
+                    ]),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            ast_annotations: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("ast-annotations"),
+                    desc: Some(
+                        "With -gen-ast, add resolved declarations, scopes, strictness and \
+                        constant values to the nodes, under the key \"juno\".",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            ast_locations: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("ast-locations"),
+                    desc: Some("With -gen-ast, how to emit the locations of the nodes."),
+                    values: Some(&[
+                        ("none", ast::LocationFormat::None, "No locations"),
+                        ("loc", ast::LocationFormat::Loc, "ESTree loc objects"),
+                        ("range", ast::LocationFormat::Range, "Byte offset ranges"),
+                        ("both", ast::LocationFormat::Both, "Both loc and range"),
+                    ]),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            ast_columns: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("ast-columns"),
+                    desc: Some("With -gen-ast, base of the columns in loc objects (default: 0)."),
+                    values: Some(&[
+                        ("0", ast::ColumnBase::Zero, "The first column is 0"),
+                        ("1", ast::ColumnBase::One, "The first column is 1"),
                     ]),
                     category: output_cat,
                     ..Default::default()
@@ -176,6 +406,69 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            lint: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("lint"),
+                    desc: Some(
+                        "Warn about suspicious code, like switch cases which fall through, \
+                        duplicate keys or line breaks relying on semicolon insertion.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            plugin: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("plugin"),
+                    desc: Some(
+                        "Load the passes and lint rules of the plugin library at this path. \
+                        Its passes run after the other passes, and its lint rules with --lint.",
+                    ),
+                    value_desc: Some("path"),
+                    ..Default::default()
+                },
+            ),
+            wasm_plugin: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("wasm-plugin"),
+                    desc: Some(
+                        "Load the pass of the plugin compiled to WebAssembly at this path, \
+                        which runs in a sandbox after the passes of the plugin libraries. \
+                        Requires the wasmi feature.",
+                    ),
+                    value_desc: Some("path"),
+                    ..Default::default()
+                },
+            ),
+            macros: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("macro"),
+                    desc: Some(
+                        "Expand the calls of a macro, like `invariant` or `graphql`, with a \
+                        template expression in which `$0`, `$1`... are the arguments. \
+                        An empty template removes the calls.",
+                    ),
+                    value_desc: Some("name=template"),
+                    ..Default::default()
+                },
+            ),
+            defines: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("define"),
+                    desc: Some(
+                        "Replace a global name or a dotted path, like `__DEV__` or \
+                        `process.env.NODE_ENV`, with the value of a constant expression, \
+                        and fold the constant expressions using it. \
+                        With -O, the branches which are dead for the value are removed.",
+                    ),
+                    value_desc: Some("name=value"),
+                    ..Default::default()
+                },
+            ),
             sema: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -189,7 +482,6 @@ impl Options {
                 cl,
                 OptDesc {
                     desc: Some("'input-path'"),
-                    min_count: 1,
                     list: true,
                     ..Default::default()
                 },
@@ -206,11 +498,50 @@ impl Options {
                     ..Default::default()
                 },
             ),
-            sourcemap: Opt::new_bool(
+            source_map: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("source-map"),
+                    desc: Some("How to generate a source map (default: none)."),
+                    values: Some(&[
+                        ("none", SourceMapKind::None, "No source map"),
+                        (
+                            "inline",
+                            SourceMapKind::Inline,
+                            "Embed the source map in the output as a data URL",
+                        ),
+                        (
+                            "file",
+                            SourceMapKind::File,
+                            "Write the source map to <output>.map",
+                        ),
+                        (
+                            "both",
+                            SourceMapKind::Both,
+                            "Embed the source map and write it to <output>.map",
+                        ),
+                    ]),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            sources_content: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("sources-content"),
+                    desc: Some("Embed the contents of the sources in the source map."),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            name_map: Opt::new_bool(
                 cl,
                 OptDesc {
-                    long: Some("sourcemap"),
-                    desc: Some("Whether to generate a source map."),
+                    long: Some("name-map"),
+                    desc: Some(
+                        "Whether to write the names changed by renaming passes \
+                        to <output>.names.json, for de-obfuscating stack traces.",
+                    ),
                     category: output_cat,
                     ..Default::default()
                 },
@@ -262,6 +593,42 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            mangle_props: Opt::<Option<String>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("mangle-props"),
+                    desc: Some(
+                        "Rename properties whose names match the regular expression \
+                        to short names, consistently across all inputs.",
+                    ),
+                    value_desc: Some("regex"),
+                    ..Default::default()
+                },
+            ),
+            mangle_props_map: Opt::<Option<PathBuf>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("mangle-props-map"),
+                    desc: Some(
+                        "Write a JSON map from original to renamed property names to the file.",
+                    ),
+                    value_desc: Some("file"),
+                    ..Default::default()
+                },
+            ),
+            anonymize: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("anonymize"),
+                    desc: Some(
+                        "Replace identifiers, strings and numbers with placeholders, keeping \
+                        the structure of the AST, e.g. with -gen-ast to share an AST \
+                        reproducing a bug without sharing the code.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
             force_async_arrow_space: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -290,6 +657,44 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            inline_script_safe: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("inline-script-safe"),
+                    desc: Some(
+                        "When generating JS, avoid sequences which would end or confuse \
+                        an inline HTML <script> element",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            ascii_only: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("ascii-only"),
+                    desc: Some("When generating JS, escape all non-ASCII characters"),
+                    ..Default::default()
+                },
+            ),
+            compress: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("compress"),
+                    desc: Some(
+                        "When generating JS, use the shortest numbers, merge declarations \
+                        and omit redundant semicolons",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            compress_booleans: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("compress-booleans"),
+                    desc: Some("When generating JS, write booleans as !0 and !1"),
+                    ..Default::default()
+                },
+            ),
             run: Opt::new_flag(
                 cl,
                 OptDesc {
@@ -298,54 +703,400 @@ impl Options {
                     ..Default::default()
                 },
             ),
-            dialect: Opt::new_enum(
+            complexity: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("complexity"),
+                    desc: Some(
+                        "Print the cyclomatic and cognitive complexity of each function \
+                        instead of generating output.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            dialect: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("dialect"),
+                    desc: Some("Control the recognized JavaScript dialect (default: js)."),
+                    values: Some(&[
+                        ("js", ParserDialect::JavaScript, "JavaScript"),
+                        ("flow", ParserDialect::Flow, "Flow"),
+                        (
+                            "flow-unambiguous",
+                            ParserDialect::FlowUnambiguous,
+                            "Flow unambiguous",
+                        ),
+                        (
+                            "flow-detect",
+                            ParserDialect::FlowDetect,
+                            "Detect @flow pragma, otherwise only parse unambiguous Flow",
+                        ),
+                        ("ts", ParserDialect::TypeScript, "TypeScript"),
+                    ]),
+                    init: Some(ParserDialect::JavaScript),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            source_type: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("source-type"),
+                    desc: Some("Whether the inputs are scripts or modules (default: unambiguous)."),
+                    values: Some(&[
+                        ("script", SourceType::Script, "Script"),
+                        ("module", SourceType::Module, "Module"),
+                        (
+                            "unambiguous",
+                            SourceType::Unambiguous,
+                            "Module if it contains import or export declarations, otherwise script",
+                        ),
+                    ]),
+                    init: Some(SourceType::Unambiguous),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            jsx: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("jsx"),
+                    desc: Some("Enable JSX parsing."),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            strict_mode: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("strict-mode"),
+                    desc: Some("Enable strict mode."),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            annex_b: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("annex-b"),
+                    desc: Some("Enable the Annex B web compatibility extensions."),
+                    init: Some(true),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            record_recovery: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("record-recovery"),
+                    desc: Some(
+                        "Report every character skipped or node dropped by the parser \
+                        when recovering from an error.",
+                    ),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            max_errors: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("max-errors"),
+                    desc: Some(
+                        "Report up to N syntax errors per input (0 for no limit), resuming \
+                        parsing at the next statement after each one, and lint the rest \
+                        (default: 1).",
+                    ),
+                    value_desc: Some("N"),
+                    init: Some(1),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            max_parser_depth: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("max-parser-depth"),
+                    desc: Some(
+                        "Report a syntax error on inputs nested deeper than N levels, like \
+                        deeply nested parentheses, 0 for the built-in limit, which also caps \
+                        larger values (default: 0).",
+                    ),
+                    value_desc: Some("N"),
+                    init: Some(0),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            max_ast_depth: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("max-ast-depth"),
+                    desc: Some(
+                        "Reject inputs whose AST is nested deeper than N levels, which are slow \
+                        and costly to traverse, 0 for no limit (default: 10000).",
+                    ),
+                    value_desc: Some("N"),
+                    init: Some(10_000),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            warn_undefined: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("warn-undefined"),
+                    desc: Some("Warn about undefined variables in strict mode functions."),
+                    ..Default::default()
+                },
+            ),
+            test262: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("test262"),
+                    desc: Some("Run the input files and directories as test262 tests."),
+                    ..Default::default()
+                },
+            ),
+            test262_harness: Opt::<Option<PathBuf>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("test262-harness"),
+                    desc: Some("Directory containing the test262 harness includes."),
+                    value_desc: Some("dir"),
+                    ..Default::default()
+                },
+            ),
+            coverage: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("coverage"),
+                    desc: Some(
+                        "Parse the input files and directories and report the node kinds \
+                        and grammar productions they exercise, and the ones they don't.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            stats: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("stats"),
+                    desc: Some(
+                        "Parse the input files and directories and report, for each file and \
+                        in total, node counts by kind, function count, maximum depth, \
+                        string table sizes and parse and GC times.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            stats_json: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("stats-json"),
+                    desc: Some("With --stats, report the statistics as JSON instead of a table."),
+                    ..Default::default()
+                },
+            ),
+            exports: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("exports"),
+                    desc: Some(
+                        "Parse the input files as modules and print, as JSON, the names each \
+                        one exports, their kinds, and whether its top-level code has side \
+                        effects.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            api_diff: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("api-diff"),
+                    desc: Some(
+                        "Compare the exports of two versions of a module, the old and the new \
+                        input file, and report the added, removed and changed exports. Fails \
+                        if any change is breaking.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            docgen: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("docgen"),
+                    desc: Some(
+                        "Parse the input files as modules and print the Markdown API \
+                        documentation of their exports, from their JSDoc comments.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            docgen_json: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("docgen-json"),
+                    desc: Some("With --docgen, print the documentation as JSON instead."),
+                    ..Default::default()
+                },
+            ),
+            reduce: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("reduce"),
+                    desc: Some(
+                        "Remove as much of the input file as possible while the test command \
+                        still succeeds on it, and output the reduced file.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            test_cmd: Opt::<Option<String>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("test-cmd"),
+                    desc: Some(
+                        "Shell command run on each candidate file by --reduce, \
+                        which is appended to it. Exits with 0 if the file is interesting.",
+                    ),
+                    value_desc: Some("command"),
+                    ..Default::default()
+                },
+            ),
+            pipe: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("pipe"),
+                    desc: Some(
+                        "Read requests naming a file and operations from stdin, one JSON \
+                        object per line, and write a JSON response per request to stdout, \
+                        until stdin is closed.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            metro: Opt::new_flag(
+                cl,
+                OptDesc {
+                    long: Some("metro"),
+                    desc: Some(
+                        "Read requests naming a file from stdin, one JSON object per line, \
+                        and write the transformed code, source map and dependencies of each \
+                        file to stdout for the Metro bundler, until stdin is closed.",
+                    ),
+                    ..Default::default()
+                },
+            ),
+            daemon: Opt::<Option<PathBuf>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("daemon"),
+                    desc: Some(
+                        "Keep running and serve JSON-RPC requests to parse, transform and \
+                        emit files on the Unix socket, caching the results of unchanged files.",
+                    ),
+                    value_desc: Some("socket"),
+                    ..Default::default()
+                },
+            ),
+            serve: Opt::<Option<String>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("serve"),
+                    desc: Some(
+                        "Keep running and serve HTTP requests to parse, transform and emit \
+                        sources on the address, like 127.0.0.1:8080. \
+                        Requires the server feature.",
+                    ),
+                    value_desc: Some("address"),
+                    ..Default::default()
+                },
+            ),
+            serve_workers: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("serve-workers"),
+                    desc: Some(
+                        "With --serve, number of requests handled concurrently \
+                        (default: 0, the number of CPUs).",
+                    ),
+                    value_desc: Some("N"),
+                    init: Some(0),
+                    ..Default::default()
+                },
+            ),
+            serve_max_body: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("serve-max-body"),
+                    desc: Some(
+                        "With --serve, reject requests larger than this many bytes \
+                        (default: 16 MiB).",
+                    ),
+                    value_desc: Some("bytes"),
+                    init: Some(16 << 20),
+                    ..Default::default()
+                },
+            ),
+            request_timeout: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("request-timeout"),
+                    desc: Some(
+                        "With --pipe, --daemon or --serve, fail requests taking longer than \
+                        this many milliseconds, 0 for no limit (default: 30000).",
+                    ),
+                    value_desc: Some("ms"),
+                    init: Some(30_000),
+                    ..Default::default()
+                },
+            ),
+            request_max_memory: Opt::new(
                 cl,
                 OptDesc {
-                    long: Some("dialect"),
-                    desc: Some("Control the recognized JavaScript dialect (default: js)."),
-                    values: Some(&[
-                        ("js", ParserDialect::JavaScript, "JavaScript"),
-                        ("flow", ParserDialect::Flow, "Flow"),
-                        (
-                            "flow-unambiguous",
-                            ParserDialect::FlowUnambiguous,
-                            "Flow unambiguous",
-                        ),
-                        (
-                            "flow-detect",
-                            ParserDialect::FlowDetect,
-                            "Detect @flow pragma, otherwise only parse unambiguous Flow",
-                        ),
-                        ("ts", ParserDialect::TypeScript, "TypeScript"),
-                    ]),
-                    init: Some(ParserDialect::JavaScript),
-                    category: input_cat,
+                    long: Some("request-max-memory"),
+                    desc: Some(
+                        "With --pipe, --daemon or --serve, fail requests whose AST takes more \
+                        than this many bytes, 0 for no limit (default: 0).",
+                    ),
+                    value_desc: Some("bytes"),
+                    init: Some(0),
                     ..Default::default()
                 },
             ),
-            jsx: Opt::new_bool(
+            request_max_source: Opt::new(
                 cl,
                 OptDesc {
-                    long: Some("jsx"),
-                    desc: Some("Enable JSX parsing."),
-                    category: input_cat,
+                    long: Some("request-max-source"),
+                    desc: Some(
+                        "With --pipe, --daemon or --serve, fail requests whose source is \
+                        larger than this many bytes, 0 for no limit (default: 0).",
+                    ),
+                    value_desc: Some("bytes"),
+                    init: Some(0),
                     ..Default::default()
                 },
             ),
-            strict_mode: Opt::new_bool(
+            request_max_line: Opt::new(
                 cl,
                 OptDesc {
-                    long: Some("strict-mode"),
-                    desc: Some("Enable strict mode."),
-                    category: input_cat,
+                    long: Some("request-max-line"),
+                    desc: Some(
+                        "With --pipe, --daemon or --serve, fail requests whose source has a \
+                        line longer than this many bytes, 0 for no limit (default: 0).",
+                    ),
+                    value_desc: Some("bytes"),
+                    init: Some(0),
                     ..Default::default()
                 },
             ),
-            warn_undefined: Opt::new_bool(
+            progress: Opt::new_flag(
                 cl,
                 OptDesc {
-                    long: Some("warn-undefined"),
-                    desc: Some("Warn about undefined variables in strict mode functions."),
+                    long: Some("progress"),
+                    desc: Some("Print the progress of parsing and passes to stderr."),
                     ..Default::default()
                 },
             ),
@@ -367,23 +1118,117 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            xalloc_stats: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("Xalloc-stats"),
+                    desc: Some("Print a histogram of the allocated nodes by kind."),
+                    hidden: Hidden::Yes,
+                    ..Default::default()
+                },
+            ),
+            xsource_atoms: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("Xsource-atoms"),
+                    desc: Some(
+                        "Make identifiers point into the source instead of copying them, \
+                        which keeps the source in memory.",
+                    ),
+                    hidden: Hidden::Yes,
+                    ..Default::default()
+                },
+            ),
         }
     }
 
     /// Ensure the arguments are valid.
     /// Return `Err` if there are any conflicts.
     fn validate(&self) -> anyhow::Result<()> {
-        if *self.sourcemap {
+        let services = [
+            *self.pipe,
+            *self.metro,
+            self.daemon.is_some(),
+            self.serve.is_some(),
+        ];
+        if services.contains(&true) {
             ensure!(
-                *self.output_path != Path::new("-"),
-                "Source map requires an output path",
+                services.iter().filter(|&&enabled| enabled).count() == 1,
+                "--pipe, --metro, --daemon and --serve are exclusive",
+            );
+            ensure!(
+                self.input_path.values().is_empty(),
+                "--pipe, --metro, --daemon and --serve read the inputs from the requests",
+            );
+        } else {
+            ensure!(
+                !self.input_path.values().is_empty(),
+                "'input-path': a value must be supplied",
             );
+        }
+        if *self.source_map != SourceMapKind::None {
             ensure!(*self.gen == Gen::Js, "Source map requires JS output");
         }
+        if *self.gen == Gen::Dts {
+            ensure!(
+                !*self.strip_flow,
+                "--gen-dts requires the Flow types, which --strip-flow removes",
+            );
+        }
+        if matches!(*self.source_map, SourceMapKind::File | SourceMapKind::Both) {
+            ensure!(
+                *self.output_path != Path::new("-"),
+                "Source map file requires an output path",
+            );
+        }
+        if *self.name_map {
+            ensure!(
+                *self.output_path != Path::new("-"),
+                "Name map requires an output path",
+            );
+        }
+        if self.mangle_props_map.is_some() {
+            ensure!(
+                self.mangle_props.is_some(),
+                "Property name map requires --mangle-props",
+            );
+        }
+        if *self.stats_json {
+            ensure!(*self.stats, "--stats-json requires --stats");
+        }
+        if *self.docgen_json {
+            ensure!(*self.docgen, "--docgen-json requires --docgen");
+        }
+        if *self.api_diff {
+            ensure!(
+                self.input_path.values().len() == 2,
+                "--api-diff requires the old and the new input file",
+            );
+        }
+        if *self.reduce {
+            ensure!(self.test_cmd.is_some(), "--reduce requires --test-cmd");
+            ensure!(
+                self.input_path.values().len() == 1,
+                "--reduce requires a single input file",
+            );
+        }
         Ok(())
     }
 }
 
+/// Print `event` to stderr, for `--progress`.
+fn print_progress(event: ProgressEvent) {
+    match event {
+        ProgressEvent::FileParsed { name, done, total } => {
+            eprintln!("[{}/{}] Parsed {}", done, total, name)
+        }
+        ProgressEvent::PassCompleted { name, done, total } => {
+            eprintln!("[{}/{}] {}", done, total, name)
+        }
+        ProgressEvent::NodesProcessed { count } => eprintln!("{} nodes", count),
+    }
+}
+
 /// Read the specified file or stdin into a null terminated buffer.
 fn read_file_or_stdin(input: &Path) -> anyhow::Result<NullTerminatedBuf> {
     if input == Path::new("-") {
@@ -429,10 +1274,7 @@ fn script_to_module<'gc>(
     ast::builder::Module::build_template(
         lock,
         ast::template::Module {
-            metadata: ast::TemplateMetadata {
-                phantom: Default::default(),
-                range: program.metadata.range,
-            },
+            metadata: (&program.metadata).into(),
             body: program.body,
         },
     )
@@ -440,11 +1282,17 @@ fn script_to_module<'gc>(
 
 /// Generate the specified output, if any.
 /// Returns whether any output was generated.
+#[allow(clippy::too_many_arguments)]
 fn gen_output(
     opt: &Options,
     ctx: &mut ast::Context,
     sem: Option<&SemContext>,
     js_module: &ParsedJSModule,
+    macros: Option<&mut Macros>,
+    defines: Option<&mut ConstantFold>,
+    mangle_props: Option<&mut MangleProperties>,
+    plugins: &mut PluginRegistry,
+    progress: &mut dyn Progress,
 ) -> anyhow::Result<bool> {
     let output_path = &*opt.output_path;
     let mut out: Box<dyn Write> = if output_path == Path::new("-") {
@@ -454,37 +1302,102 @@ fn gen_output(
     };
 
     let final_ast = if *opt.strip_flow {
-        PassManager::strip_flow().run(ctx, js_module.ast.clone())
+        PassManager::strip_flow().run_with_progress(ctx, js_module.ast.clone(), progress)
     } else {
         js_module.ast.clone()
     };
 
+    let final_ast = match macros {
+        Some(pass) => run_pass(ctx, pass, final_ast),
+        None => final_ast,
+    };
+
+    let final_ast = match defines {
+        Some(pass) => run_pass(ctx, pass, final_ast),
+        None => final_ast,
+    };
+
     let final_ast = if *opt.optimize {
-        PassManager::standard().run(ctx, final_ast)
+        PassManager::standard().run_with_progress(ctx, final_ast, progress)
+    } else {
+        final_ast
+    };
+
+    let final_ast = match mangle_props {
+        Some(pass) => run_pass(ctx, pass, final_ast),
+        None => final_ast,
+    };
+
+    let final_ast = if *opt.anonymize {
+        run_pass(ctx, &mut Anonymize::new(), final_ast)
     } else {
         final_ast
     };
 
+    let final_ast = plugins.run_passes(ctx, final_ast)?;
+
     if *opt.run {
         juno_eval::run(&final_ast);
         return Ok(true);
     }
 
+    if *opt.complexity {
+        let gc = ast::GCLock::new(ctx);
+        let results = complexity::analyze(&gc, final_ast.node(&gc));
+        complexity::write_report(gc.sm(), &results, out.deref_mut())?;
+        return Ok(true);
+    }
+
     match *opt.gen {
         Gen::Ast => {
-            ast::dump_json(
+            let constants = ConstEvaluator::new();
+            let annotator = sem.map(|sem| sema::SemAnnotator {
+                sem,
+                constants: Some(&constants),
+            });
+            ast::dump_json_opt(
+                out,
+                ctx,
+                &final_ast,
+                &ast::DumpOpt {
+                    pretty: if !*opt.pretty {
+                        ast::Pretty::No
+                    } else {
+                        ast::Pretty::Yes
+                    },
+                    locations: *opt.ast_locations,
+                    columns: *opt.ast_columns,
+                    annotator: match &annotator {
+                        Some(annotator) if *opt.ast_annotations => Some(annotator),
+                        _ => None,
+                    },
+                },
+            )?;
+            Ok(true)
+        }
+        Gen::Report => {
+            ast::dump_validation_report(
                 out,
                 ctx,
                 &final_ast,
-                if !*opt.pretty {
-                    ast::Pretty::No
-                } else {
-                    ast::Pretty::Yes
+                &ast::DumpOpt {
+                    pretty: if !*opt.pretty {
+                        ast::Pretty::No
+                    } else {
+                        ast::Pretty::Yes
+                    },
+                    locations: *opt.ast_locations,
+                    columns: *opt.ast_columns,
+                    annotator: None,
                 },
             )?;
             Ok(true)
         }
         Gen::Js | Gen::ResolvedJs => {
+            // Workaround because `PathBuf` doesn't have a way to append an extension,
+            // only to replace the existing one.
+            let mut map_path = output_path.clone().into_os_string();
+            map_path.push(".map");
             let generated_map = gen_js::generate(
                 out.deref_mut(),
                 ctx,
@@ -500,29 +1413,36 @@ fn gen_output(
                         _ => gen_js::Annotation::No,
                     },
                     force_async_arrow_space: *opt.force_async_arrow_space,
-                    doc_block: js_module.doc_block.clone(),
+                    // The doc block is made of comments, which can't be anonymized.
+                    doc_block: if *opt.anonymize {
+                        None
+                    } else {
+                        js_module.doc_block.clone()
+                    },
                     quote: if *opt.double_quote_strings {
                         gen_js::QuoteChar::Double
                     } else {
                         gen_js::QuoteChar::Single
                     },
+                    source_map: *opt.source_map,
+                    source_map_url: Some(map_path.to_string_lossy().into_owned()),
+                    sources_content: *opt.sources_content,
+                    inline_script_safe: *opt.inline_script_safe,
+                    ascii_only: *opt.ascii_only,
+                    compress: *opt.compress,
+                    compress_booleans: *opt.compress_booleans,
                 },
             )?;
-            if *opt.sourcemap {
-                // Workaround because `PathBuf` doesn't have a way to append an extension,
-                // only to replace the existing one.
-                let mut path = output_path.clone().into_os_string();
-                path.push(".map");
-                let sourcemap_file = File::create(PathBuf::from(&path))?;
-                let merged_map = match &js_module.source_map {
-                    None => generated_map,
-                    Some(input_map) => merge_sourcemaps(input_map, &generated_map),
-                };
-                merged_map.to_writer(sourcemap_file)?;
-                write!(out, "\n//# sourceMappingURL={}", path.to_str().unwrap())?;
+            if matches!(*opt.source_map, SourceMapKind::File | SourceMapKind::Both) {
+                let sourcemap_file = File::create(PathBuf::from(&map_path))?;
+                generated_map.to_writer(sourcemap_file)?;
             }
             Ok(true)
         }
+        Gen::Dts => {
+            gen_dts::generate(out.deref_mut(), ctx, &final_ast)?;
+            Ok(true)
+        }
         Gen::Sema => {
             if let Some(sem) = sem {
                 sem.dump(&ast::GCLock::new(ctx));
@@ -548,41 +1468,429 @@ enum TransformStatus {
     Error,
 }
 
-/// Parsed JS file with its associated sourcemap.
+/// Parsed JS file.
+/// Its input source map, if any, is registered in the `SourceManager`.
 struct ParsedJSModule {
     id: SourceId,
     /// AST node, may be either `Program` or `Module`.
     ast: NodeRc,
-    source_map: Option<SourceMap>,
     /// Doc block for the file if it exists.
     doc_block: Option<Rc<String>>,
 }
 
+/// Run the input paths as test262 tests and print a summary.
+fn run_test262(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let mut runner = test262::Runner::new(test262::RunnerOptions {
+        harness_dir: (*opt.test262_harness).clone(),
+        stage: if *opt.validate_ast {
+            test262::Stage::Validate
+        } else {
+            test262::Stage::Parse
+        },
+    });
+    let mut summary = test262::Summary::default();
+    for path in opt.input_path.values() {
+        runner
+            .run_path(path, &mut summary)
+            .with_context(|| path.display().to_string())?;
+    }
+    print!("{}", summary);
+    Ok(if summary.total.failed == 0 {
+        TransformStatus::Success
+    } else {
+        TransformStatus::Error
+    })
+}
+
+/// Parse the input paths and print their grammar coverage.
+fn run_coverage(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let flags = hparser::ParserFlags {
+        strict_mode: *opt.strict_mode,
+        enable_jsx: *opt.jsx,
+        dialect: *opt.dialect,
+        annex_b: *opt.annex_b,
+        html_like_comments: *opt.annex_b,
+        source_type: *opt.source_type,
+        ..Default::default()
+    };
+    let mut coverage = Coverage::default();
+    for path in opt.input_path.values() {
+        coverage
+            .add_path(flags, path)
+            .with_context(|| path.display().to_string())?;
+    }
+    print!("{}", coverage);
+    Ok(if coverage.failures.is_empty() {
+        TransformStatus::Success
+    } else {
+        TransformStatus::Error
+    })
+}
+
+/// Parse the input paths and print their statistics.
+fn run_stats(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let flags = hparser::ParserFlags {
+        strict_mode: *opt.strict_mode,
+        enable_jsx: *opt.jsx,
+        dialect: *opt.dialect,
+        annex_b: *opt.annex_b,
+        html_like_comments: *opt.annex_b,
+        source_type: *opt.source_type,
+        ..Default::default()
+    };
+    let mut stats = Stats::default();
+    for path in opt.input_path.values() {
+        stats
+            .add_path(flags, path)
+            .with_context(|| path.display().to_string())?;
+    }
+    if *opt.stats_json {
+        let pretty = if *opt.pretty {
+            ast::Pretty::Yes
+        } else {
+            ast::Pretty::No
+        };
+        stats.write_json(std::io::stdout(), pretty)?;
+        println!();
+    } else {
+        print!("{}", stats);
+    }
+    Ok(if stats.failures.is_empty() {
+        TransformStatus::Success
+    } else {
+        TransformStatus::Error
+    })
+}
+
+/// Parser flags for the input modules of `--exports`, `--api-diff` and `--docgen`.
+fn module_flags(opt: &Options) -> hparser::ParserFlags {
+    hparser::ParserFlags {
+        strict_mode: *opt.strict_mode,
+        enable_jsx: *opt.jsx,
+        dialect: *opt.dialect,
+        source_type: SourceType::Module,
+        ..Default::default()
+    }
+}
+
+/// Parse the module at `path` and compute its interface.
+fn parse_interface(flags: hparser::ParserFlags, path: &Path) -> anyhow::Result<ModuleInterface> {
+    let source = std::fs::read_to_string(path).with_context(|| path.display().to_string())?;
+    let mut ctx = ast::Context::new();
+    let ast = hparser::parse_with_flags(flags, &source, &mut ctx)
+        .with_context(|| path.display().to_string())?;
+    let gc = ast::GCLock::new(&mut ctx);
+    Ok(exports::analyze(&gc, ast.node(&gc)))
+}
+
+/// Print the interfaces of the input modules as a JSON object keyed by their paths.
+fn run_exports(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let flags = module_flags(opt);
+    let pretty = if *opt.pretty {
+        ast::Pretty::Yes
+    } else {
+        ast::Pretty::No
+    };
+    let mut status = TransformStatus::Success;
+    let mut emitter = JSONEmitter::new(std::io::stdout(), pretty);
+    emitter.open_dict();
+    for path in opt.input_path.values() {
+        match parse_interface(flags, path) {
+            Ok(interface) => {
+                emitter.emit_key(&path.display().to_string());
+                interface.emit_json(&mut emitter);
+            }
+            Err(e) => {
+                eprintln!("{:#}", e);
+                status = TransformStatus::Error;
+            }
+        }
+    }
+    emitter.close_dict();
+    emitter.end()?;
+    println!();
+    Ok(status)
+}
+
+/// Print the changes between the interfaces of the two input modules.
+fn run_api_diff(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let flags = module_flags(opt);
+    let (old_path, new_path) = match opt.input_path.values().as_slice() {
+        [old_path, new_path] => (old_path, new_path),
+        _ => unreachable!("validated by Options::validate"),
+    };
+    let old = parse_interface(flags, old_path)?;
+    let new = parse_interface(flags, new_path)?;
+    let changes = api_diff::diff(&old, &new);
+    api_diff::write_report(
+        &old_path.display().to_string(),
+        &new_path.display().to_string(),
+        &changes,
+        &mut std::io::stdout(),
+    )?;
+    Ok(if changes.iter().any(api_diff::ApiChange::is_breaking) {
+        TransformStatus::Error
+    } else {
+        TransformStatus::Success
+    })
+}
+
+/// Parse the module at `path` and document its exports.
+fn parse_module_doc(flags: hparser::ParserFlags, path: &Path) -> anyhow::Result<ModuleDoc> {
+    let name = path.display().to_string();
+    let source = std::fs::read_to_string(path).with_context(|| name.clone())?;
+    let mut ctx = ast::Context::new();
+    let ast = hparser::parse_with_flags(flags, &source, &mut ctx).with_context(|| name.clone())?;
+    let gc = ast::GCLock::new(&mut ctx);
+    Ok(docgen::document(&gc, ast.node(&gc), &name))
+}
+
+/// Print the API documentation of the input modules.
+fn run_docgen(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let flags = module_flags(opt);
+    let mut status = TransformStatus::Success;
+    let mut modules = vec![];
+    for path in opt.input_path.values() {
+        match parse_module_doc(flags, path) {
+            Ok(module) => modules.push(module),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                status = TransformStatus::Error;
+            }
+        }
+    }
+    if *opt.docgen_json {
+        let pretty = if *opt.pretty {
+            ast::Pretty::Yes
+        } else {
+            ast::Pretty::No
+        };
+        docgen::write_json(&modules, std::io::stdout(), pretty)?;
+        println!();
+    } else {
+        docgen::write_markdown(&modules, &mut std::io::stdout())?;
+    }
+    Ok(status)
+}
+
+/// Reduce the input file while the test command succeeds on it, and write the result.
+fn run_reduce(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let mut ctx = ast::Context::new();
+    let input = opt.input_path.values()[0].as_path();
+    let file_id = ctx
+        .sm_mut()
+        .add_source(input.display().to_string(), read_file_or_stdin(input)?);
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            strict_mode: *opt.strict_mode,
+            enable_jsx: *opt.jsx,
+            dialect: *opt.dialect,
+            annex_b: *opt.annex_b,
+            html_like_comments: *opt.annex_b,
+            source_type: *opt.source_type,
+            ..Default::default()
+        },
+        &buf,
+    );
+    if let Some(e) = parsed.first_error() {
+        ctx.sm().error(SourceRange::from_loc(file_id, e.0), e.1);
+        return Ok(TransformStatus::Error);
+    }
+    let root = {
+        let lock = ast::GCLock::new(&mut ctx);
+        match parsed.to_ast(&lock, file_id) {
+            Some(program) => NodeRc::from_node(&lock, program),
+            None => return Ok(TransformStatus::Error),
+        }
+    };
+    drop(parsed);
+
+    let mut candidate_path = std::env::temp_dir();
+    candidate_path.push(format!("juno-reduce-{}.js", std::process::id()));
+    let test = reduce::CommandTest::new(
+        opt.test_cmd.as_ref().unwrap().clone(),
+        candidate_path.clone(),
+    );
+    ensure!(
+        test.run(&mut ctx, &root)?,
+        "The test command fails on the input",
+    );
+    let mut stats = reduce::ReduceStats::default();
+    let root = reduce::reduce(
+        &mut ctx,
+        root,
+        &mut |ctx, root| test.run(ctx, root).unwrap_or(false),
+        &mut stats,
+    );
+    let _ = std::fs::remove_file(&candidate_path);
+    eprintln!(
+        "Reduced with {} edits in {} tests",
+        stats.edits, stats.tests
+    );
+
+    let output_path = &*opt.output_path;
+    let mut out: Box<dyn Write> = if output_path == Path::new("-") {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(File::create(output_path).with_context(|| output_path.display().to_string())?)
+    };
+    gen_js::generate(&mut out, &mut ctx, &root, gen_js::Opt::new())?;
+    Ok(TransformStatus::Success)
+}
+
+/// The maximum depth of the AST set by `--max-ast-depth`, if any.
+fn max_ast_depth(opt: &Options) -> Option<usize> {
+    match *opt.max_ast_depth {
+        0 => None,
+        depth => Some(depth as usize),
+    }
+}
+
+/// The pass expanding the macros of `--macro`, if any.
+fn macro_pass(opt: &Options, ctx: &mut ast::Context) -> anyhow::Result<Option<Macros>> {
+    if opt.macros.values().is_empty() {
+        return Ok(None);
+    }
+    let mut macros = Macros::new();
+    for value in opt.macros.values() {
+        let (name, template) = value
+            .split_once('=')
+            .with_context(|| format!("--macro {}: expected name=template", value))?;
+        macros
+            .add_template_from_source(ctx, name.trim(), template)
+            .with_context(|| format!("--macro {}", value))?;
+    }
+    Ok(Some(macros))
+}
+
+/// Add the plugins of `--wasm-plugin` to `plugins`.
+#[cfg(feature = "wasmi")]
+fn load_wasm_plugins(opt: &Options, plugins: &mut PluginRegistry) -> anyhow::Result<()> {
+    for path in opt.wasm_plugin.values() {
+        plugins.load_wasm(path, &Default::default())?;
+    }
+    Ok(())
+}
+
+/// Add the plugins of `--wasm-plugin` to `plugins`.
+#[cfg(not(feature = "wasmi"))]
+fn load_wasm_plugins(opt: &Options, _plugins: &mut PluginRegistry) -> anyhow::Result<()> {
+    if !opt.wasm_plugin.values().is_empty() {
+        anyhow::bail!("--wasm-plugin requires juno to be built with the wasmi feature");
+    }
+    Ok(())
+}
+
+/// The pass substituting the values of `--define`, if any.
+fn define_pass(opt: &Options, ctx: &mut ast::Context) -> anyhow::Result<Option<ConstantFold>> {
+    if opt.defines.values().is_empty() {
+        return Ok(None);
+    }
+    let mut pass = ConstantFold::new();
+    for value in opt.defines.values() {
+        let (name, source) = value
+            .split_once('=')
+            .with_context(|| format!("--define {}: expected name=value", value))?;
+        pass.define_from_source(ctx, name.trim(), source)
+            .with_context(|| format!("--define {}", value))?;
+    }
+    Ok(Some(pass))
+}
+
 fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
     opt.validate()?;
 
+    if *opt.test262 {
+        return run_test262(opt);
+    }
+    if *opt.coverage {
+        return run_coverage(opt);
+    }
+    if *opt.stats {
+        return run_stats(opt);
+    }
+    if *opt.exports {
+        return run_exports(opt);
+    }
+    if *opt.api_diff {
+        return run_api_diff(opt);
+    }
+    if *opt.docgen {
+        return run_docgen(opt);
+    }
+    if *opt.reduce {
+        return run_reduce(opt);
+    }
+    if *opt.pipe {
+        return pipe::run_pipe(opt);
+    }
+    if *opt.metro {
+        return metro::run_metro();
+    }
+    if let Some(path) = &*opt.daemon {
+        daemon::run_daemon(pipe::Config::new(opt), path)?;
+        return Ok(TransformStatus::Success);
+    }
+    if let Some(addr) = &*opt.serve {
+        let limits = server::ServerLimits {
+            workers: match *opt.serve_workers {
+                0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+                n => n as usize,
+            },
+            max_body: *opt.serve_max_body as usize,
+        };
+        server::run_server(pipe::Config::new(opt), addr, limits)?;
+        return Ok(TransformStatus::Success);
+    }
+
+    let mut plugins = PluginRegistry::new();
+    for path in opt.plugin.values() {
+        plugins.load(path)?;
+    }
+    load_wasm_plugins(opt, &mut plugins)?;
+
     let mut ctx = ast::Context::new();
 
     // Propagate flags.
     if *opt.strict_mode {
         ctx.enable_strict_mode();
     }
+    ctx.set_annex_b(*opt.annex_b);
     ctx.warn_undefined = *opt.warn_undefined;
+    ctx.set_track_alloc_stats(*opt.xalloc_stats);
+    ctx.set_source_atoms(*opt.xsource_atoms);
+    ctx.set_max_depth(max_ast_depth(opt));
+
+    let mut macros = macro_pass(opt, &mut ctx)?;
+    let mut defines = define_pass(opt, &mut ctx)?;
 
     // Start measuring time.
     let mut timer = Timer::new();
 
+    let mut progress: Box<dyn Progress> = if *opt.progress {
+        Box::new(print_progress)
+    } else {
+        Box::new(|_: ProgressEvent| {})
+    };
+
     // Read the input into memory.
     let input_paths = opt.input_path.values();
 
     let mut js_modules = HashMap::<SourceId, ParsedJSModule>::new();
+    // Inputs with syntax errors are only linted, with error recovery.
+    let error_recovery = *opt.max_errors != 1;
+    let mut parse_failed = false;
 
-    for path in input_paths {
+    for (i, path) in input_paths.iter().enumerate() {
         let input = path.as_path();
         let file_id = ctx
             .sm_mut()
             .add_source(input.display().to_string(), read_file_or_stdin(input)?);
         let buf = ctx.sm().source_buffer_rc(file_id);
+        // Make room for the AST up front rather than growing the storage chunk by chunk.
+        let estimate = ast::ContextConfig::for_source_len(buf.len());
+        ctx.reserve(estimate.reserve_nodes, estimate.reserve_list_elements);
 
         // Parse.
         let parsed = hparser::ParsedJS::parse(
@@ -591,11 +1899,34 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
                 enable_jsx: *opt.jsx,
                 dialect: *opt.dialect,
                 store_doc_block: *opt.emit_doc_block,
+                annex_b: ctx.annex_b(),
+                html_like_comments: ctx.annex_b(),
+                record_recovery: *opt.record_recovery,
+                error_recovery,
+                error_limit: if error_recovery { *opt.max_errors } else { 0 },
+                max_depth: *opt.max_parser_depth,
+                source_type: *opt.source_type,
             },
             &buf,
         );
         timer.mark("Parse");
-        if let Some(e) = parsed.first_error() {
+        for recovery in parsed.recoveries() {
+            ctx.sm().note(
+                SourceRange {
+                    file: file_id,
+                    start: recovery.start,
+                    end: recovery.end,
+                },
+                recovery.msg,
+            );
+        }
+        let has_errors = parsed.has_errors();
+        if error_recovery {
+            for e in parsed.errors() {
+                ctx.sm().error(SourceRange::from_loc(file_id, e.loc), e.msg);
+            }
+            parse_failed |= has_errors;
+        } else if let Some(e) = parsed.first_error() {
             ctx.sm().error(SourceRange::from_loc(file_id, e.0), e.1);
             return Ok(TransformStatus::Error);
         }
@@ -612,16 +1943,10 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
             let lock = ast::GCLock::new(&mut ctx);
             match parsed.to_ast(&lock, file_id) {
                 None => return Ok(TransformStatus::Error),
-                Some(program) => {
-                    if input_paths.len() > 1 {
-                        NodeRc::from_node(
-                            &lock,
-                            script_to_module(&lock, node_cast!(ast::Node::Program, program)),
-                        )
-                    } else {
-                        NodeRc::from_node(&lock, program)
-                    }
+                Some(ast::Node::Program(program)) if input_paths.len() > 1 => {
+                    NodeRc::from_node(&lock, script_to_module(&lock, program))
                 }
+                Some(program) => NodeRc::from_node(&lock, program),
             }
         };
         let doc_block = parsed.get_doc_block().map(|s| Rc::new(s.to_string()));
@@ -629,30 +1954,90 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
         drop(parsed);
         timer.mark("Cvt");
 
+        // Everything after this traverses the AST recursively.
+        {
+            let lock = ast::GCLock::new(&mut ctx);
+            if let Err(e) = ast::check_depth(&lock, ast.node(&lock)) {
+                lock.sm().error(e.range, e.to_string());
+                return Ok(TransformStatus::Error);
+            }
+        }
+
+        progress.report(ProgressEvent::FileParsed {
+            name: ctx.sm().source_name(file_id),
+            done: i + 1,
+            total: input_paths.len(),
+        });
+        if *opt.progress {
+            let lock = ast::GCLock::new(&mut ctx);
+            let count = ast::metrics(&lock, ast.node(&lock)).node_count;
+            progress.report(ProgressEvent::NodesProcessed { count });
+        }
+
         if *opt.validate_ast {
             validate_tree(&mut ctx, &ast).with_context(|| input.display().to_string())?;
             timer.mark("Validate AST");
         }
 
+        if *opt.lint {
+            let lock = ast::GCLock::new(&mut ctx);
+            switch::lint(&lock, ast.node(&lock));
+            duplicates::lint(&lock, ast.node(&lock));
+            asi::lint(&lock, ast.node(&lock));
+            hermes::lint(&lock, ast.node(&lock));
+            react::lint(&lock, ast.node(&lock));
+            plugins.lint(&lock, ast.node(&lock))?;
+            timer.mark("Lint");
+        }
+
+        if has_errors {
+            continue;
+        }
+
         // Fetch and parse the source map before we generate the output.
-        let source_map = sm_url.map(load_source_map).transpose()?;
+        // It is composed with the generated source map automatically.
+        if let Some(source_map) = sm_url.map(load_source_map).transpose()? {
+            ctx.sm_mut().set_input_source_map(file_id, source_map);
+        }
 
         js_modules.insert(
             file_id,
             ParsedJSModule {
                 id: file_id,
                 ast,
-                source_map,
                 doc_block,
             },
         );
     }
 
+    if parse_failed {
+        return Ok(TransformStatus::Error);
+    }
+
+    // Property renaming must see every module before renaming any of them.
+    let mut mangle_props = match &*opt.mangle_props {
+        Some(pattern) => {
+            let mut pass = MangleProperties::new(pattern).context("--mangle-props")?;
+            let lock = ast::GCLock::new(&mut ctx);
+            for module in js_modules.values() {
+                pass.collect(&lock, module.ast.node(&lock));
+            }
+            Some(pass)
+        }
+        None => None,
+    };
+
     if js_modules.len() == 1 {
         let js_module = js_modules.into_values().next().unwrap();
         let sem = if *opt.sema {
             let lock = ast::GCLock::new(&mut ctx);
-            let sem = sema::resolve_program(&lock, js_module.id, js_module.ast.node(&lock));
+            let root = js_module.ast.node(&lock);
+            let sem = if let ast::Node::Module(_) = root {
+                let resolver = resolve_dependency::DefaultResolver::new(lock.sm());
+                sema::resolve_module(&lock, root, js_module.id, &resolver)
+            } else {
+                sema::resolve_program(&lock, js_module.id, root)
+            };
             if lock.sm().num_errors() != 0 || lock.sm().num_warnings() != 0 {
                 eprintln!(
                     "{} error(s), {} warning(s)",
@@ -671,7 +2056,17 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
         };
 
         // Generate output.
-        if gen_output(opt, &mut ctx, sem.as_ref(), &js_module)? {
+        if gen_output(
+            opt,
+            &mut ctx,
+            sem.as_ref(),
+            &js_module,
+            macros.as_mut(),
+            defines.as_mut(),
+            mangle_props.as_mut(),
+            &mut plugins,
+            progress.as_mut(),
+        )? {
             timer.mark("Gen");
         }
     } else {
@@ -698,7 +2093,17 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
                     }
                 }
                 // Generate output.
-                if gen_output(opt, &mut ctx, Some(&sem), &module)? {
+                if gen_output(
+                    opt,
+                    &mut ctx,
+                    Some(&sem),
+                    &module,
+                    macros.as_mut(),
+                    defines.as_mut(),
+                    mangle_props.as_mut(),
+                    &mut plugins,
+                    progress.as_mut(),
+                )? {
                     timer.mark("Gen");
                 }
                 sems.push(sem);
@@ -710,12 +2115,37 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
         }
     }
 
+    if let (Some(pass), Some(path)) = (&mangle_props, &*opt.mangle_props_map) {
+        let file = File::create(path).with_context(|| path.display().to_string())?;
+        pass.write_name_map(file)
+            .with_context(|| path.display().to_string())?;
+    }
+
+    if *opt.name_map {
+        let mut name_map = NameMap::new();
+        if let Some(pass) = &mangle_props {
+            pass.record_names(&mut name_map);
+        }
+        let output_path = &*opt.output_path;
+        let mut path = output_path.clone().into_os_string();
+        path.push(".names.json");
+        let file = File::create(PathBuf::from(&path))?;
+        name_map.write_json(ctx.sm(), &output_path.display().to_string(), file)?;
+    }
+
     // Optionally print memory usage.
     if *opt.xmem {
         println!("Context size:  {} MB", ctx.heap_size() / 1_000_000);
         println!("Storage size:  {} MB", ctx.storage_size() / 1_000_000);
         println!("# nodes:       {}", ctx.num_nodes());
+        print!("{}", ctx.heap_stats());
     }
+    if *opt.xalloc_stats {
+        print!("{}", ctx.alloc_histogram());
+    }
+
+    // The templates of the macros are nodes, which must not outlive the context.
+    drop(macros);
 
     // Drop the AST. We are doing it explicitly just to measure the time.
     drop(ctx);
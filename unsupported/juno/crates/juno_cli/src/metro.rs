@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! `--metro` mode, where a single process transforms files for Metro, the React Native
+//! bundler (see [`juno_pass::metro`]). A Metro transformer written in JS starts the process
+//! and forwards the files it is given.
+//!
+//! Requests are read from stdin as newline delimited JSON, one object per line:
+//! ```json
+//! {"id": 1, "filename": "src/App.js", "source": "...", "options": {"minify": false}}
+//! ```
+//! - `id` is any JSON value, which is copied to the response.
+//! - `filename` is the path of the file, which is read unless `source` is present.
+//! - `options` are optional: `minify`, and `jsxPragma` and `jsxPragmaFrag`, which default
+//!   to `React.createElement` and `React.Fragment`.
+//!
+//! Every request gets one response on stdout, in order, as a single line, with the code,
+//! its source map and the dependencies in the format of Metro:
+//! ```json
+//! {"id": 1, "ok": true, "code": "...", "map": {"version": 3, ...},
+//!  "dependencies": [{"name": "react", "data": {"asyncType": null, "isOptional": false,
+//!    "locs": [{"start": {"line": 1, "column": 0}, "end": {"line": 1, "column": 26}}]}}]}
+//! {"id": 2, "ok": false, "errors": [{"line": 3, "column": 7, "message": "..."}]}
+//! ```
+//! Columns of `locs` start at 0 and their ends are exclusive, as in Babel.
+//! Requests are read and responses written by the loop of `--pipe`
+//! (see [`crate::pipe::run_requests`]).
+
+use std::fs;
+
+use juno::ast::SourceRange;
+use juno_pass::metro;
+use juno_pass::metro::TransformError;
+use juno_pass::metro::TransformOptions;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::pipe;
+use crate::pipe::Diagnostic;
+use crate::TransformStatus;
+
+/// Extract the options of a request from the JSON `value`.
+fn parse_options(value: Option<&Value>) -> Result<TransformOptions, String> {
+    let mut options = TransformOptions::default();
+    let value = match value {
+        None | Some(Value::Null) => return Ok(options),
+        Some(Value::Object(value)) => value,
+        Some(_) => return Err("\"options\" must be an object".into()),
+    };
+    if let Some(minify) = value.get("minify") {
+        options.minify = minify.as_bool().ok_or("\"minify\" must be a boolean")?;
+    }
+    for (key, option) in [
+        ("jsxPragma", &mut options.jsx_pragma),
+        ("jsxPragmaFrag", &mut options.jsx_pragma_frag),
+    ] {
+        if let Some(pragma) = value.get(key) {
+            *option = pragma
+                .as_str()
+                .ok_or_else(|| format!("\"{}\" must be a string", key))?
+                .to_string();
+        }
+    }
+    Ok(options)
+}
+
+/// `range` as a Babel `SourceLocation`.
+fn location_json(range: &SourceRange) -> Value {
+    // The end of the range is the last character, which ends a `require` call or an
+    // `import` declaration, so it is a single byte.
+    json!({
+        "start": {"line": range.start.line, "column": range.start.col - 1},
+        "end": {"line": range.end.line, "column": range.end.col},
+    })
+}
+
+/// Transform the file of the request `value` and return the fields of the response.
+fn transform(value: &Value) -> Result<Map<String, Value>, Vec<Diagnostic>> {
+    let error = |message: String| vec![Diagnostic::new(message)];
+    let filename = value
+        .get("filename")
+        .and_then(Value::as_str)
+        .ok_or_else(|| error("Request must have a \"filename\" string".into()))?;
+    let source = match value.get("source") {
+        None | Some(Value::Null) => {
+            fs::read_to_string(filename).map_err(|e| error(format!("{}: {}", filename, e)))?
+        }
+        Some(Value::String(source)) => source.clone(),
+        Some(_) => return Err(error("\"source\" must be a string".into())),
+    };
+    let options = parse_options(value.get("options")).map_err(error)?;
+    let output = metro::transform(filename, &source, &options).map_err(|e| match e {
+        TransformError::Parse { error, .. } => vec![Diagnostic::from(error)],
+        e => error(e.to_string()),
+    })?;
+    let map = serde_json::from_str::<Value>(&output.map)
+        .map_err(|e| error(format!("Invalid source map: {}", e)))?;
+    let dependencies = output
+        .dependencies
+        .iter()
+        .map(|dep| {
+            json!({
+                "name": dep.name,
+                "data": {
+                    "asyncType": if dep.is_async() { json!("async") } else { Value::Null },
+                    "isOptional": dep.optional,
+                    "locs": dep.locs.iter().map(location_json).collect::<Vec<_>>(),
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut fields = Map::new();
+    fields.insert("code".into(), Value::String(output.code));
+    fields.insert("map".into(), map);
+    fields.insert("dependencies".into(), Value::Array(dependencies));
+    Ok(fields)
+}
+
+/// Respond to the requests on stdin until it is closed.
+/// Failed requests are reported in their responses, and don't make the process fail.
+pub fn run_metro() -> anyhow::Result<TransformStatus> {
+    pipe::run_requests(transform)
+}
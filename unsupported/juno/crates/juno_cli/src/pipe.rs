@@ -0,0 +1,609 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! `--pipe` mode, where a single process handles a stream of requests, so that build
+//! systems don't pay for starting a process for every file.
+//!
+//! Requests are read from stdin as newline delimited JSON, one object per line:
+//! ```json
+//! {"id": 1, "file": "src/a.js", "ops": ["strip-flow", "gen-js"]}
+//! ```
+//! - `id` is any JSON value, which is copied to the response.
+//! - `file` is the path of the file, which is read unless `source` is present.
+//! - `source` is the optional contents of the file, e.g. unsaved changes in an editor.
+//! - `ops` are the operations to perform, in order, after parsing the file:
+//!   - `strip-flow`, `optimize` and `anonymize` transform the AST, like the options of
+//!     the same name;
+//!   - `validate` checks the AST;
+//!   - `gen-js` and `gen-ast` add the generated JS and the AST to the response.
+//!
+//! The parser and output options are taken from the command line and apply to every request.
+//! Every request gets one response on stdout, in order, as a single line:
+//! ```json
+//! {"id": 1, "ok": true, "js": "..."}
+//! {"id": 2, "ok": false, "errors": [{"line": 3, "column": 7, "message": "..."}]}
+//! ```
+//! The errors of requests which can't be processed, like an unknown operation, have no
+//! location. Every request is processed in a new [`ast::Context`], so requests don't
+//! affect each other, and the process exits when stdin is closed.
+//!
+//! Requests are subject to the [`RequestLimits`] of the command line, so that a pathological
+//! file can't stall or exhaust a service shared by many clients. A request which exceeds
+//! a limit fails with a single error, marked with `"limit": true`.
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::Context;
+use juno::ast;
+use juno::ast::validate_tree_pure;
+use juno::ast::NodeRc;
+use juno::ast::SourceLoc;
+use juno::gen_js;
+use juno::hparser;
+use juno_pass::anonymize::Anonymize;
+use juno_pass::run_pass;
+use juno_pass::PassManager;
+use juno_support::CancellationToken;
+use juno_support::NullTerminatedBuf;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+
+use crate::max_ast_depth;
+use crate::Options;
+use crate::TransformStatus;
+
+/// The options of the command line which apply to every request.
+/// Unlike [`Options`], it can be sent to other threads.
+#[derive(Debug, Clone)]
+pub struct Config {
+    strict_mode: bool,
+    annex_b: bool,
+    jsx: bool,
+    dialect: hparser::ParserDialect,
+    source_type: hparser::SourceType,
+    max_errors: u32,
+    max_parser_depth: u32,
+    max_ast_depth: Option<usize>,
+    emit_doc_block: bool,
+    pretty: bool,
+    force_async_arrow_space: bool,
+    double_quote_strings: bool,
+    inline_script_safe: bool,
+    ascii_only: bool,
+    compress: bool,
+    compress_booleans: bool,
+    ast_locations: ast::LocationFormat,
+    ast_columns: ast::ColumnBase,
+    limits: RequestLimits,
+}
+
+impl Config {
+    pub fn new(opt: &Options) -> Config {
+        Config {
+            strict_mode: *opt.strict_mode,
+            annex_b: *opt.annex_b,
+            jsx: *opt.jsx,
+            dialect: *opt.dialect,
+            source_type: *opt.source_type,
+            max_errors: *opt.max_errors,
+            max_parser_depth: *opt.max_parser_depth,
+            max_ast_depth: max_ast_depth(opt),
+            emit_doc_block: *opt.emit_doc_block,
+            pretty: *opt.pretty,
+            force_async_arrow_space: *opt.force_async_arrow_space,
+            double_quote_strings: *opt.double_quote_strings,
+            inline_script_safe: *opt.inline_script_safe,
+            ascii_only: *opt.ascii_only,
+            compress: *opt.compress,
+            compress_booleans: *opt.compress_booleans,
+            ast_locations: *opt.ast_locations,
+            ast_columns: *opt.ast_columns,
+            limits: RequestLimits::new(opt),
+        }
+    }
+}
+
+/// Resources a single request may use, `None` meaning no limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestLimits {
+    /// Time after which the request is cancelled.
+    pub timeout: Option<Duration>,
+
+    /// Maximum size of the AST storage of the request, in bytes.
+    pub max_memory: Option<usize>,
+
+    /// Maximum size of the source, in bytes.
+    pub max_source: Option<usize>,
+
+    /// Maximum length of a line of the source, in bytes.
+    pub max_line: Option<usize>,
+}
+
+impl RequestLimits {
+    pub fn new(opt: &Options) -> RequestLimits {
+        let limit = |value: u32| match value {
+            0 => None,
+            n => Some(n as usize),
+        };
+        RequestLimits {
+            timeout: limit(*opt.request_timeout).map(|ms| Duration::from_millis(ms as u64)),
+            max_memory: limit(*opt.request_max_memory),
+            max_source: limit(*opt.request_max_source),
+            max_line: limit(*opt.request_max_line),
+        }
+    }
+
+    /// Check the size of `source` and of its lines.
+    /// This is done before parsing, since the parser can't be cancelled.
+    fn check_source(&self, source: &[u8]) -> Result<(), Diagnostic> {
+        if let Some(max) = self.max_source {
+            if source.len() > max {
+                return Err(Diagnostic::limit(format!(
+                    "Source of {} bytes exceeds the limit of {} bytes",
+                    source.len(),
+                    max
+                )));
+            }
+        }
+        if let Some(max) = self.max_line {
+            let long_line = source
+                .split(|&c| c == b'\n')
+                .position(|line| line.len() > max);
+            if let Some(index) = long_line {
+                return Err(Diagnostic {
+                    loc: Some(SourceLoc {
+                        line: index as u32 + 1,
+                        col: max as u32 + 1,
+                    }),
+                    message: format!("Line exceeds the limit of {} bytes", max),
+                    limit: true,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cancels a token once a timeout elapses, unless it is dropped before.
+struct Watchdog {
+    done: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    fn start(timeout: Duration, token: CancellationToken) -> Watchdog {
+        let (done, wait) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(timeout) {
+                token.cancel();
+            }
+        });
+        Watchdog {
+            done: Some(done),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes up the thread.
+        self.done.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// An operation performed on the file of a request.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Op {
+    StripFlow,
+    Optimize,
+    Anonymize,
+    Validate,
+    GenJs,
+    GenAst,
+}
+
+impl Op {
+    /// The operation named `name` in requests.
+    fn from_name(name: &str) -> Option<Op> {
+        Some(match name {
+            "strip-flow" => Op::StripFlow,
+            "optimize" => Op::Optimize,
+            "anonymize" => Op::Anonymize,
+            "validate" => Op::Validate,
+            "gen-js" => Op::GenJs,
+            "gen-ast" => Op::GenAst,
+            _ => return None,
+        })
+    }
+}
+
+/// A request read from stdin.
+#[derive(Debug)]
+pub struct Request {
+    pub file: String,
+    pub source: Option<String>,
+    pub ops: Vec<Op>,
+}
+
+impl Request {
+    /// Read the source of the request, from the file unless it is in the request.
+    /// Files are only read up to the `max_source` limit of `config`, so that a huge file
+    /// or a device like `/dev/zero` fails with a limit error without being read whole.
+    pub fn read_source(&self, config: &Config) -> Result<NullTerminatedBuf, Vec<Diagnostic>> {
+        if let Some(source) = &self.source {
+            return Ok(NullTerminatedBuf::from_str_copy(source));
+        }
+        let io_error = |e: io::Error| vec![Diagnostic::new(format!("{}: {}", self.file, e))];
+        let mut file = File::open(&self.file).map_err(io_error)?;
+        let max = match config.limits.max_source {
+            Some(max) => max,
+            None => return NullTerminatedBuf::from_file(&mut file).map_err(io_error),
+        };
+        // Read one byte more than the limit, to tell whether the file exceeds it.
+        let buf =
+            NullTerminatedBuf::from_reader(&mut file.take(max as u64 + 1)).map_err(io_error)?;
+        if buf.len() - 1 > max {
+            return Err(vec![Diagnostic::limit(format!(
+                "Source exceeds the limit of {} bytes",
+                max
+            ))]);
+        }
+        Ok(buf)
+    }
+}
+
+/// An error reported in a response.
+pub struct Diagnostic {
+    /// Location in the file, if the error is in the file.
+    loc: Option<SourceLoc>,
+    message: String,
+    /// Whether the request exceeded one of its [`RequestLimits`].
+    limit: bool,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Self {
+            loc: None,
+            message: message.into(),
+            limit: false,
+        }
+    }
+
+    /// An error reporting that the request exceeded one of its [`RequestLimits`].
+    pub fn limit<S: Into<String>>(message: S) -> Self {
+        Self {
+            limit: true,
+            ..Self::new(message)
+        }
+    }
+
+    pub fn is_limit(&self) -> bool {
+        self.limit
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut json = match self.loc {
+            Some(loc) => json!({
+                "line": loc.line,
+                "column": loc.col,
+                "message": self.message,
+            }),
+            None => json!({ "message": self.message }),
+        };
+        if self.limit {
+            json["limit"] = Value::Bool(true);
+        }
+        json
+    }
+}
+
+impl From<hparser::ParseError> for Diagnostic {
+    fn from(error: hparser::ParseError) -> Self {
+        Self {
+            loc: Some(error.loc),
+            message: error.msg,
+            limit: false,
+        }
+    }
+}
+
+/// Extract a request from the JSON `value`.
+pub fn parse_request(value: &Value) -> Result<Request, Diagnostic> {
+    let file = match value.get("file") {
+        Some(Value::String(file)) => file.clone(),
+        _ => return Err(Diagnostic::new("Request must have a \"file\" string")),
+    };
+    let source = match value.get("source") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(source)) => Some(source.clone()),
+        Some(_) => return Err(Diagnostic::new("\"source\" must be a string")),
+    };
+    let ops = match value.get("ops") {
+        None | Some(Value::Null) => vec![],
+        Some(Value::Array(ops)) => ops
+            .iter()
+            .map(|op| {
+                op.as_str()
+                    .and_then(Op::from_name)
+                    .ok_or_else(|| Diagnostic::new(format!("Unknown operation: {}", op)))
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(Diagnostic::new("\"ops\" must be an array")),
+    };
+    Ok(Request { file, source, ops })
+}
+
+/// Perform `request` on its source `buf` and return the fields of the response added by
+/// its operations.
+///
+/// The request fails with a single limit error if it exceeds the limits of `config`.
+/// Memory and time are checked by the operations as they go, so a request may use somewhat
+/// more than its limits before it is stopped.
+pub fn process(
+    config: &Config,
+    request: &Request,
+    buf: NullTerminatedBuf,
+) -> Result<Map<String, Value>, Vec<Diagnostic>> {
+    let limits = &config.limits;
+    limits
+        .check_source(&buf.as_bytes()[..buf.len() - 1])
+        .map_err(|e| vec![e])?;
+
+    let mut ctx = ast::Context::new();
+    if config.strict_mode {
+        ctx.enable_strict_mode();
+    }
+    ctx.set_annex_b(config.annex_b);
+    ctx.set_max_depth(config.max_ast_depth);
+
+    // Exceeding a limit cancels the context, so the operation in progress stops early.
+    let cancellation = CancellationToken::new();
+    ctx.set_cancellation_token(Some(cancellation.clone()));
+    let _watchdog = limits
+        .timeout
+        .map(|timeout| Watchdog::start(timeout, cancellation.clone()));
+    let memory_exceeded = Rc::new(Cell::new(None));
+    ctx.set_memory_limit(limits.max_memory);
+    {
+        let memory_exceeded = Rc::clone(&memory_exceeded);
+        let cancellation = cancellation.clone();
+        ctx.set_memory_limit_handler(move |e| {
+            memory_exceeded.set(Some(e));
+            cancellation.cancel();
+        });
+    }
+
+    let result = perform(config, request, buf, &mut ctx);
+    if let Some(e) = memory_exceeded.get() {
+        return Err(vec![Diagnostic::limit(e.to_string())]);
+    }
+    if cancellation.is_cancelled() {
+        return Err(vec![Diagnostic::limit(format!(
+            "Timed out after {} ms",
+            limits.timeout.unwrap_or_default().as_millis()
+        ))]);
+    }
+    result
+}
+
+/// Perform `request` on its source `buf` in `ctx`, until `ctx` is cancelled.
+fn perform(
+    config: &Config,
+    request: &Request,
+    buf: NullTerminatedBuf,
+    ctx: &mut ast::Context,
+) -> Result<Map<String, Value>, Vec<Diagnostic>> {
+    let file_id = ctx.sm_mut().add_source(request.file.clone(), buf);
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let error_recovery = config.max_errors != 1;
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            strict_mode: ctx.strict_mode(),
+            enable_jsx: config.jsx,
+            dialect: config.dialect,
+            store_doc_block: config.emit_doc_block,
+            annex_b: ctx.annex_b(),
+            html_like_comments: ctx.annex_b(),
+            record_recovery: false,
+            error_recovery,
+            error_limit: if error_recovery { config.max_errors } else { 0 },
+            max_depth: config.max_parser_depth,
+            source_type: config.source_type,
+        },
+        &buf,
+    );
+    if parsed.has_errors() {
+        return Err(parsed.errors().into_iter().map(Diagnostic::from).collect());
+    }
+    let mut root = {
+        let lock = ast::GCLock::new(ctx);
+        match parsed.to_ast(&lock, file_id) {
+            Some(program) => NodeRc::from_node(&lock, program),
+            None => return Err(vec![Diagnostic::new("invalid AST produced")]),
+        }
+    };
+    {
+        let lock = ast::GCLock::new(ctx);
+        if let Err(e) = ast::check_depth(&lock, root.node(&lock)) {
+            return Err(vec![Diagnostic {
+                loc: Some(e.range.start),
+                message: e.to_string(),
+                limit: true,
+            }]);
+        }
+    }
+    let doc_block = parsed.get_doc_block().map(|s| Rc::new(s.to_string()));
+    drop(parsed);
+
+    let mut result = Map::new();
+    for op in &request.ops {
+        if ctx.is_cancelled() {
+            break;
+        }
+        match op {
+            Op::StripFlow => root = PassManager::strip_flow().run(ctx, root),
+            Op::Optimize => root = PassManager::standard().run(ctx, root),
+            Op::Anonymize => root = run_pass(ctx, &mut Anonymize::new(), root),
+            Op::Validate => {
+                if let Err(errors) = validate_tree_pure(ctx, &root) {
+                    let lock = ast::GCLock::new(ctx);
+                    return Err(errors
+                        .iter()
+                        .map(|e| Diagnostic {
+                            loc: Some(e.node.node(&lock).range().start),
+                            message: e.message.clone(),
+                            limit: false,
+                        })
+                        .collect());
+                }
+            }
+            Op::GenJs => {
+                let mut out = vec![];
+                gen_js::generate(
+                    &mut out,
+                    ctx,
+                    &root,
+                    gen_js::Opt {
+                        pretty: if config.pretty {
+                            gen_js::Pretty::Yes
+                        } else {
+                            gen_js::Pretty::No
+                        },
+                        force_async_arrow_space: config.force_async_arrow_space,
+                        // The doc block is made of comments, which can't be anonymized.
+                        doc_block: if request.ops.contains(&Op::Anonymize) {
+                            None
+                        } else {
+                            doc_block.clone()
+                        },
+                        quote: if config.double_quote_strings {
+                            gen_js::QuoteChar::Double
+                        } else {
+                            gen_js::QuoteChar::Single
+                        },
+                        inline_script_safe: config.inline_script_safe,
+                        ascii_only: config.ascii_only,
+                        compress: config.compress,
+                        compress_booleans: config.compress_booleans,
+                        ..gen_js::Opt::new()
+                    },
+                )
+                .map_err(|e| vec![Diagnostic::new(e.to_string())])?;
+                result.insert(
+                    "js".into(),
+                    Value::String(String::from_utf8_lossy(&out).into_owned()),
+                );
+            }
+            Op::GenAst => {
+                let mut out = vec![];
+                ast::dump_json_opt(
+                    &mut out,
+                    ctx,
+                    &root,
+                    &ast::DumpOpt {
+                        pretty: ast::Pretty::No,
+                        locations: config.ast_locations,
+                        columns: config.ast_columns,
+                        annotator: None,
+                    },
+                )
+                .map_err(|e| vec![Diagnostic::new(e.to_string())])?;
+                let ast = serde_json::from_slice(&out)
+                    .map_err(|e| vec![Diagnostic::new(format!("Invalid AST JSON: {}", e))])?;
+                result.insert("ast".into(), ast);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Perform the request `value` and return the fields of its response.
+fn handle(config: &Config, value: &Value) -> Result<Map<String, Value>, Vec<Diagnostic>> {
+    let request = parse_request(value).map_err(|e| vec![e])?;
+    process(config, &request, request.read_source(config)?)
+}
+
+/// Build the response to the request whose JSON is `line`, with the fields returned by
+/// `handle` for the request, or the errors it failed with.
+fn respond<F>(line: &str, handle: F) -> Value
+where
+    F: FnOnce(&Value) -> Result<Map<String, Value>, Vec<Diagnostic>>,
+{
+    let (id, result) = match serde_json::from_str::<Value>(line) {
+        Ok(value) => (
+            value.get("id").cloned().unwrap_or(Value::Null),
+            handle(&value),
+        ),
+        Err(e) => (
+            Value::Null,
+            Err(vec![Diagnostic::new(format!("Invalid request: {}", e))]),
+        ),
+    };
+    let mut response = Map::new();
+    response.insert("id".into(), id);
+    match result {
+        Ok(fields) => {
+            response.insert("ok".into(), Value::Bool(true));
+            response.extend(fields);
+        }
+        Err(errors) => {
+            response.insert("ok".into(), Value::Bool(false));
+            response.insert(
+                "errors".into(),
+                Value::Array(errors.iter().map(Diagnostic::to_json).collect()),
+            );
+        }
+    }
+    Value::Object(response)
+}
+
+/// Respond to the requests on stdin until it is closed.
+/// Failed requests are reported in their responses, and don't make the process fail.
+pub fn run_pipe(opt: &Options) -> anyhow::Result<TransformStatus> {
+    let config = Config::new(opt);
+    run_requests(|value| handle(&config, value))
+}
+
+/// Respond to the newline delimited JSON requests on stdin until it is closed, in the
+/// format of `--pipe`, with `handle` returning the fields of the responses.
+/// This is shared by the modes reading requests from stdin, so they frame their
+/// responses and errors the same way.
+pub fn run_requests<F>(mut handle: F) -> anyhow::Result<TransformStatus>
+where
+    F: FnMut(&Value) -> Result<Map<String, Value>, Vec<Diagnostic>>,
+{
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = line.context("stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        serde_json::to_writer(&mut out, &respond(&line, &mut handle)).context("stdout")?;
+        out.write_all(b"\n").context("stdout")?;
+        out.flush().context("stdout")?;
+    }
+    Ok(TransformStatus::Success)
+}
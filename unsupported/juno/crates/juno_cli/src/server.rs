@@ -0,0 +1,167 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! `--serve` mode, an HTTP/JSON service, so that a large repository can send its files to
+//! a few shared machines instead of transforming them on every client.
+//!
+//! The service is only available when built with the `server` feature.
+//! Requests are `POST`s to `/parse`, `/transform` or `/emit`, the methods of `--daemon` mode
+//! (see [`crate::daemon`]), whose body is a request of `--pipe` mode (see [`crate::pipe`]).
+//! The `source` of the file is required, since the files of the server must not be exposed.
+//! The response is the result of the method, as JSON, with the status:
+//! - 200 if it succeeded;
+//! - 422 if the source has errors, with the errors in the format of `--pipe` mode;
+//! - 400, 404, 405 or 413 if the request is invalid, too large, or for an unknown method;
+//! - 503 if it exceeded its time or memory limits (see [`crate::pipe::RequestLimits`]).
+//!
+//! Requests are handled concurrently by a fixed number of worker threads, each of which
+//! performs every request in its own [`juno::ast::Context`]. Once a request exceeds its
+//! limits, its context is cancelled, so the worker moves on to the next request as soon as
+//! the operation in progress notices it. Results are cached like in `--daemon` mode.
+
+use crate::pipe::Config;
+
+/// Limits protecting the service from too many or too large requests.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "server"), allow(dead_code))]
+pub struct ServerLimits {
+    /// Number of requests handled concurrently.
+    pub workers: usize,
+
+    /// Maximum size of the body of a request, in bytes.
+    pub max_body: usize,
+}
+
+#[cfg(feature = "server")]
+mod http {
+    use std::io::Cursor;
+    use std::io::Read;
+    use std::sync::Arc;
+    use std::thread;
+
+    use serde_json::json;
+    use serde_json::Value;
+    use tiny_http::Header;
+    use tiny_http::Method;
+    use tiny_http::Request;
+    use tiny_http::Response;
+    use tiny_http::Server;
+
+    use super::ServerLimits;
+    use crate::daemon::apply_method;
+    use crate::daemon::ResultCache;
+    use crate::pipe::parse_request;
+    use crate::pipe::Config;
+    use crate::pipe::Diagnostic;
+
+    /// Body of an error response.
+    fn error_body(message: &str, errors: &[Diagnostic]) -> Value {
+        json!({
+            "error": message,
+            "errors": errors.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Handle `request` and return the status and body of the response.
+    fn handle(
+        config: &Config,
+        cache: &ResultCache,
+        limits: &ServerLimits,
+        request: &mut Request,
+    ) -> (u16, Value) {
+        if *request.method() != Method::Post {
+            return (405, error_body("Requests must be POSTs", &[]));
+        }
+        let method = request.url().trim_start_matches('/').to_string();
+        if matches!(request.body_length(), Some(len) if len > limits.max_body) {
+            return (413, error_body("Request too large", &[]));
+        }
+        let mut body = vec![];
+        if let Err(e) = request
+            .as_reader()
+            .take(limits.max_body as u64 + 1)
+            .read_to_end(&mut body)
+        {
+            return (400, error_body(&e.to_string(), &[]));
+        }
+        if body.len() > limits.max_body {
+            return (413, error_body("Request too large", &[]));
+        }
+        let params = match serde_json::from_slice::<Value>(&body) {
+            Ok(params) => params,
+            Err(e) => return (400, error_body(&format!("Invalid JSON: {}", e), &[])),
+        };
+        let mut params = match parse_request(&params) {
+            Ok(params) => params,
+            Err(e) => return (400, error_body("Invalid request", &[e])),
+        };
+        if params.source.is_none() {
+            return (400, error_body("The request must have a \"source\"", &[]));
+        }
+        if !apply_method(&mut params, &method) {
+            return (404, error_body(&format!("Unknown method: {}", method), &[]));
+        }
+        match cache.process(config, &params) {
+            Ok(fields) => (200, Value::Object(fields)),
+            Err(errors) if errors.iter().any(Diagnostic::is_limit) => {
+                (503, error_body("Resource limit exceeded", &errors))
+            }
+            Err(errors) => (422, error_body("Errors in the source", &errors)),
+        }
+    }
+
+    /// JSON response with `status` and `body`.
+    fn response(status: u16, body: &Value) -> Response<Cursor<Vec<u8>>> {
+        Response::from_data(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
+            )
+    }
+
+    /// Serve requests on `addr` with `limits.workers` threads, until killed.
+    pub fn serve(config: Config, addr: &str, limits: ServerLimits) -> anyhow::Result<()> {
+        let server = Arc::new(Server::http(addr).map_err(|e| anyhow::anyhow!("{}: {}", addr, e))?);
+        let cache = Arc::new(ResultCache::default());
+        let workers = (0..limits.workers.max(1))
+            .map(|_| {
+                let server = Arc::clone(&server);
+                let cache = Arc::clone(&cache);
+                let config = config.clone();
+                thread::spawn(move || loop {
+                    let mut request = match server.recv() {
+                        Ok(request) => request,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+                    let (status, body) = handle(&config, &cache, &limits, &mut request);
+                    if let Err(e) = request.respond(response(status, &body)) {
+                        eprintln!("{}", e);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+}
+
+/// Serve requests on the address `addr`, like `127.0.0.1:8080`, until killed.
+#[cfg(feature = "server")]
+pub fn run_server(config: Config, addr: &str, limits: ServerLimits) -> anyhow::Result<()> {
+    http::serve(config, addr, limits)
+}
+
+/// Serve requests on the address `addr`, like `127.0.0.1:8080`, until killed.
+#[cfg(not(feature = "server"))]
+pub fn run_server(_config: Config, _addr: &str, _limits: ServerLimits) -> anyhow::Result<()> {
+    anyhow::bail!("--serve requires juno to be built with the server feature")
+}
@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// Benchmark corpus: ES5 library code, with closures, prototypes and loops.
+
+(function (global) {
+  'use strict';
+
+  var hasOwn = Object.prototype.hasOwnProperty;
+
+  function EventEmitter() {
+    this._listeners = {};
+    this._count = 0;
+  }
+
+  EventEmitter.prototype.on = function (name, listener) {
+    if (typeof listener !== 'function') {
+      throw new TypeError('listener must be a function');
+    }
+    if (!hasOwn.call(this._listeners, name)) {
+      this._listeners[name] = [];
+    }
+    this._listeners[name].push(listener);
+    this._count++;
+    return this;
+  };
+
+  EventEmitter.prototype.off = function (name, listener) {
+    var list = this._listeners[name];
+    if (!list) {
+      return this;
+    }
+    for (var i = list.length - 1; i >= 0; i--) {
+      if (list[i] === listener) {
+        list.splice(i, 1);
+        this._count--;
+      }
+    }
+    return this;
+  };
+
+  EventEmitter.prototype.emit = function (name) {
+    var list = this._listeners[name];
+    if (!list || list.length === 0) {
+      return false;
+    }
+    var args = Array.prototype.slice.call(arguments, 1);
+    list = list.slice();
+    for (var i = 0, n = list.length; i < n; i++) {
+      list[i].apply(this, args);
+    }
+    return true;
+  };
+
+  function clamp(value, min, max) {
+    return value < min ? min : value > max ? max : value;
+  }
+
+  function debounce(fn, wait) {
+    var timer = null;
+    return function () {
+      var self = this;
+      var args = arguments;
+      if (timer !== null) {
+        clearTimeout(timer);
+      }
+      timer = setTimeout(function () {
+        timer = null;
+        fn.apply(self, args);
+      }, wait);
+    };
+  }
+
+  function formatNumber(n, digits) {
+    var parts = n.toFixed(digits || 0).split('.');
+    var int = parts[0];
+    var out = '';
+    while (int.length > 3) {
+      out = ',' + int.slice(-3) + out;
+      int = int.slice(0, -3);
+    }
+    out = int + out;
+    return parts.length > 1 ? out + '.' + parts[1] : out;
+  }
+
+  function parseQuery(query) {
+    var result = {};
+    if (!query) {
+      return result;
+    }
+    var pairs = query.replace(/^\?/, '').split('&');
+    for (var i = 0; i < pairs.length; i++) {
+      var pair = pairs[i].split('=');
+      var key = decodeURIComponent(pair[0]);
+      var value = pair.length > 1 ? decodeURIComponent(pair[1]) : '';
+      if (hasOwn.call(result, key)) {
+        if (Object.prototype.toString.call(result[key]) !== '[object Array]') {
+          result[key] = [result[key]];
+        }
+        result[key].push(value);
+      } else {
+        result[key] = value;
+      }
+    }
+    return result;
+  }
+
+  function deepEqual(a, b) {
+    if (a === b) {
+      return true;
+    }
+    if (a === null || b === null || typeof a !== 'object' || typeof b !== 'object') {
+      return a !== a && b !== b;
+    }
+    var keysA = Object.keys(a);
+    var keysB = Object.keys(b);
+    if (keysA.length !== keysB.length) {
+      return false;
+    }
+    for (var i = 0; i < keysA.length; i++) {
+      var key = keysA[i];
+      if (!hasOwn.call(b, key) || !deepEqual(a[key], b[key])) {
+        return false;
+      }
+    }
+    return true;
+  }
+
+  var DEBUG = false;
+  var MAX_RETRIES = 3;
+
+  function retry(task, callback) {
+    var attempts = 0;
+    function attempt() {
+      attempts++;
+      task(function (err, result) {
+        if (err && attempts < MAX_RETRIES) {
+          if (DEBUG) {
+            console.log('retrying after error: ' + err.message);
+          }
+          setTimeout(attempt, 100 * attempts);
+          return;
+        }
+        callback(err, result);
+      });
+    }
+    attempt();
+  }
+
+  global.utils = {
+    EventEmitter: EventEmitter,
+    clamp: clamp,
+    debounce: debounce,
+    formatNumber: formatNumber,
+    parseQuery: parseQuery,
+    deepEqual: deepEqual,
+    retry: retry,
+  };
+})(this);
@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ *
+ * @flow
+ */
+
+// Benchmark corpus: a Flow component, with type annotations, type declarations and JSX.
+
+import * as React from 'react';
+import {useCallback, useMemo, useState} from 'react';
+import type {Node} from 'react';
+
+export type Todo = {|
+  +id: number,
+  +title: string,
+  +done: boolean,
+  +tags: $ReadOnlyArray<string>,
+|};
+
+type Filter = 'all' | 'active' | 'done';
+
+type Props = $ReadOnly<{|
+  todos: $ReadOnlyArray<Todo>,
+  onToggle: (id: number) => void,
+  onRemove?: ?(id: number) => void,
+  title?: string,
+|}>;
+
+opaque type TodoID: number = number;
+
+export interface Store<T> {
+  get(id: number): ?T;
+  set(id: number, value: T): void;
+}
+
+function matches(todo: Todo, filter: Filter): boolean {
+  switch (filter) {
+    case 'active':
+      return !todo.done;
+    case 'done':
+      return todo.done;
+    default:
+      return true;
+  }
+}
+
+function countBy<T>(items: $ReadOnlyArray<T>, predicate: T => boolean): number {
+  let count: number = 0;
+  for (const item of items) {
+    if (predicate(item)) {
+      count++;
+    }
+  }
+  return count;
+}
+
+function TodoItem({
+  todo,
+  onToggle,
+  onRemove,
+}: {
+  todo: Todo,
+  onToggle: (id: number) => void,
+  onRemove?: ?(id: number) => void,
+}): Node {
+  return (
+    <li className={todo.done ? 'todo done' : 'todo'}>
+      <input type="checkbox" checked={todo.done} onChange={() => onToggle(todo.id)} />
+      <span>{todo.title}</span>
+      {todo.tags.map(tag => (
+        <span key={tag} className="tag">
+          {tag}
+        </span>
+      ))}
+      {onRemove != null && <button onClick={() => onRemove(todo.id)}>Remove</button>}
+    </li>
+  );
+}
+
+export default function TodoList({todos, onToggle, onRemove, title = 'Todos'}: Props): Node {
+  const [filter, setFilter] = useState<Filter>('all');
+  const visible = useMemo(
+    (): $ReadOnlyArray<Todo> => todos.filter(todo => matches(todo, filter)),
+    [todos, filter],
+  );
+  const remaining = countBy(todos, (todo: Todo) => !todo.done);
+  const select = useCallback((next: Filter) => () => setFilter(next), []);
+
+  return (
+    <section>
+      <h1>
+        {title} ({remaining} left)
+      </h1>
+      <nav>
+        {(['all', 'active', 'done']: Array<Filter>).map(name => (
+          <a key={name} href="#" className={name === filter ? 'selected' : ''} onClick={select(name)}>
+            {name}
+          </a>
+        ))}
+      </nav>
+      <ul>
+        {visible.map(todo => (
+          <TodoItem key={todo.id} todo={todo} onToggle={onToggle} onRemove={onRemove} />
+        ))}
+      </ul>
+      {visible.length === 0 ? <p>Nothing to show.</p> : null}
+    </section>
+  );
+}
+
+export function makeStore<T>(): Store<T> {
+  const map: Map<number, T> = new Map();
+  return {
+    get: (id: number): ?T => map.get(id),
+    set: (id: number, value: T): void => {
+      map.set(id, value);
+    },
+  };
+}
+
+export function toID(n: number): TodoID {
+  return (n: any);
+}
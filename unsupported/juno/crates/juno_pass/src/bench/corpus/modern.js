@@ -0,0 +1,134 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// Benchmark corpus: a modern module, with classes, async functions, destructuring,
+// spread and template literals.
+
+import {fetchJSON, sleep} from './network';
+import * as log from './log';
+
+const DEFAULT_OPTIONS = {
+  retries: 3,
+  timeout: 5000,
+  headers: {accept: 'application/json'},
+};
+
+export class Cache {
+  #entries = new Map();
+  static instances = 0;
+
+  constructor({capacity = 100, ttl = 60_000} = {}) {
+    this.capacity = capacity;
+    this.ttl = ttl;
+    Cache.instances++;
+  }
+
+  get size() {
+    return this.#entries.size;
+  }
+
+  get(key) {
+    const entry = this.#entries.get(key);
+    if (entry === undefined) {
+      return undefined;
+    }
+    if (Date.now() - entry.time > this.ttl) {
+      this.#entries.delete(key);
+      return undefined;
+    }
+    this.#entries.delete(key);
+    this.#entries.set(key, entry);
+    return entry.value;
+  }
+
+  set(key, value) {
+    if (this.#entries.size >= this.capacity) {
+      const [oldest] = this.#entries.keys();
+      this.#entries.delete(oldest);
+    }
+    this.#entries.set(key, {value, time: Date.now()});
+  }
+
+  *entries() {
+    for (const [key, {value}] of this.#entries) {
+      yield [key, value];
+    }
+  }
+}
+
+export class Client {
+  constructor(baseURL, options = {}) {
+    this.baseURL = baseURL;
+    this.options = {...DEFAULT_OPTIONS, ...options};
+    this.cache = new Cache();
+  }
+
+  async request(path, {method = 'GET', body, ...rest} = {}) {
+    const url = `${this.baseURL}/${path.replace(/^\/+/, '')}`;
+    const cached = method === 'GET' ? this.cache.get(url) : undefined;
+    if (cached !== undefined) {
+      return cached;
+    }
+    let lastError = null;
+    for (let attempt = 0; attempt <= this.options.retries; attempt++) {
+      try {
+        const result = await fetchJSON(url, {
+          method,
+          body: body != null ? JSON.stringify(body) : undefined,
+          headers: {...this.options.headers, ...rest.headers},
+          timeout: this.options.timeout,
+        });
+        if (method === 'GET') {
+          this.cache.set(url, result);
+        }
+        return result;
+      } catch (error) {
+        lastError = error;
+        log.warn(`request ${method} ${url} failed (attempt ${attempt + 1}): ${error?.message}`);
+        await sleep(2 ** attempt * 100);
+      }
+    }
+    throw lastError ?? new Error(`request ${url} failed`);
+  }
+
+  async all(paths) {
+    const results = await Promise.allSettled(paths.map(path => this.request(path)));
+    return results
+      .filter(({status}) => status === 'fulfilled')
+      .map(({value}) => value);
+  }
+}
+
+export function groupBy(items, key) {
+  return items.reduce((groups, item) => {
+    const k = typeof key === 'function' ? key(item) : item[key];
+    (groups[k] ||= []).push(item);
+    return groups;
+  }, {});
+}
+
+export const pipe = (...fns) => input => fns.reduce((acc, fn) => fn(acc), input);
+
+export function* range(start, end, step = 1) {
+  for (let i = start; step > 0 ? i < end : i > end; i += step) {
+    yield i;
+  }
+}
+
+export const summarize = (numbers) => {
+  const sorted = [...numbers].sort((a, b) => a - b);
+  const sum = sorted.reduce((a, b) => a + b, 0);
+  const mid = sorted.length >> 1;
+  return {
+    min: sorted[0],
+    max: sorted[sorted.length - 1],
+    mean: sum / sorted.length,
+    median: sorted.length % 2 ? sorted[mid] : (sorted[mid - 1] + sorted[mid]) / 2,
+  };
+};
+
+export default new Client('https://example.com/api');
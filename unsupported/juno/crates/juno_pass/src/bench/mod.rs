@@ -0,0 +1,245 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Benchmarks of the main stages of Juno, for comparing performance between changes.
+//!
+//! Every benchmark runs on each of the [`corpora`], which are committed with the crate so
+//! that results are reproducible across machines and forks:
+//! - `parse`: parsing into a new [`Context`];
+//! - `gc`: collecting a context whose tree is live, and one whose tree was dropped;
+//! - `traversal`: a preorder iteration and a [`Visitor`] walk of the tree;
+//! - `dump`: dumping the tree as JSON;
+//! - `transforms`: running the standard pipeline, and stripping Flow types.
+//!
+//! Run them with `cargo bench -p juno_pass --features bench`, which accepts the usual
+//! criterion arguments, e.g. `--save-baseline` and `--baseline` to compare two commits.
+//! A fork can also call [`run_benchmarks`] from its own harness, or add [`benchmarks`]
+//! to its own [`Criterion`] alongside other benchmarks.
+
+use std::io;
+
+use criterion::BatchSize;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use juno::ast::*;
+use juno::hparser;
+use juno::hparser::ParserDialect;
+use juno::hparser::ParserFlags;
+use juno::hparser::SourceType;
+
+use crate::PassManager;
+
+/// A source file benchmarks run on.
+#[derive(Debug, Clone, Copy)]
+pub struct Corpus {
+    /// Name of the corpus in the benchmark IDs.
+    pub name: &'static str,
+
+    /// Flags to parse `source` with.
+    pub flags: ParserFlags,
+
+    /// JS source.
+    pub source: &'static str,
+}
+
+/// The corpora every benchmark runs on.
+pub fn corpora() -> [Corpus; 3] {
+    [
+        Corpus {
+            name: "es5",
+            flags: ParserFlags {
+                source_type: SourceType::Script,
+                ..Default::default()
+            },
+            source: include_str!("corpus/es5.js"),
+        },
+        Corpus {
+            name: "modern",
+            flags: ParserFlags {
+                source_type: SourceType::Module,
+                ..Default::default()
+            },
+            source: include_str!("corpus/modern.js"),
+        },
+        Corpus {
+            name: "flow",
+            flags: ParserFlags {
+                enable_jsx: true,
+                dialect: ParserDialect::Flow,
+                source_type: SourceType::Module,
+                ..Default::default()
+            },
+            source: include_str!("corpus/flow.js"),
+        },
+    ]
+}
+
+/// Parse `corpus` into `ctx`.
+///
+/// # Panics
+///
+/// Will panic if the corpus doesn't parse, which makes its benchmarks meaningless.
+fn parse(ctx: &mut Context, corpus: &Corpus) -> NodeRc {
+    match hparser::parse_with_flags(corpus.flags, corpus.source, ctx) {
+        Ok(root) => root,
+        Err(e) => panic!("corpus {} doesn't parse: {}", corpus.name, e.msg),
+    }
+}
+
+/// Parse `corpus` into a new context.
+fn parsed(corpus: &Corpus) -> (Context<'static>, NodeRc) {
+    let mut ctx = Context::new();
+    let root = parse(&mut ctx, corpus);
+    (ctx, root)
+}
+
+/// Counts the nodes it visits.
+struct CountNodes(usize);
+
+impl<'gc> Visitor<'gc> for CountNodes {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        self.0 += 1;
+        node.visit_children(gc, self);
+    }
+}
+
+/// Add the `parse` benchmarks to `c`.
+pub fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for corpus in &corpora() {
+        group.throughput(Throughput::Bytes(corpus.source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(corpus.name),
+            corpus,
+            |b, corpus| {
+                b.iter_batched(
+                    Context::new,
+                    |mut ctx| {
+                        drop(parse(&mut ctx, corpus));
+                        ctx
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Add the `gc` benchmarks to `c`.
+pub fn bench_gc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gc");
+    for corpus in &corpora() {
+        group.bench_with_input(
+            BenchmarkId::new("live", corpus.name),
+            corpus,
+            |b, corpus| {
+                let (mut ctx, root) = parsed(corpus);
+                b.iter(|| ctx.gc());
+                drop(root);
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("free", corpus.name),
+            corpus,
+            |b, corpus| {
+                b.iter_batched(
+                    || parsed(corpus).0,
+                    |mut ctx| {
+                        ctx.gc();
+                        ctx
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Add the `traversal` benchmarks to `c`.
+pub fn bench_traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("traversal");
+    for corpus in &corpora() {
+        let (mut ctx, root) = parsed(corpus);
+        {
+            let gc = GCLock::new(&mut ctx);
+            let node = root.node(&gc);
+            group.bench_function(BenchmarkId::new("preorder", corpus.name), |b| {
+                b.iter(|| iter_preorder(&gc, node).count())
+            });
+            group.bench_function(BenchmarkId::new("visitor", corpus.name), |b| {
+                b.iter(|| {
+                    let mut count = CountNodes(0);
+                    node.visit(&gc, &mut count, None);
+                    count.0
+                })
+            });
+        }
+        drop(root);
+    }
+    group.finish();
+}
+
+/// Add the `dump` benchmarks to `c`.
+pub fn bench_dump(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dump");
+    for corpus in &corpora() {
+        let (mut ctx, root) = parsed(corpus);
+        group.bench_function(BenchmarkId::new("json", corpus.name), |b| {
+            b.iter(|| dump_json(io::sink(), &mut ctx, &root, Pretty::Yes).unwrap())
+        });
+        drop(root);
+    }
+    group.finish();
+}
+
+/// Creates the pipeline of passes measured by a benchmark.
+type Pipeline = fn() -> PassManager;
+
+/// Add the `transforms` benchmarks to `c`.
+/// Every iteration runs on a freshly parsed tree, whose parsing isn't measured.
+pub fn bench_transforms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transforms");
+    let pipelines: &[(&str, Pipeline)] = &[
+        ("standard", PassManager::standard),
+        ("strip_flow", PassManager::strip_flow),
+    ];
+    for corpus in &corpora() {
+        for &(name, pipeline) in pipelines {
+            group.bench_with_input(BenchmarkId::new(name, corpus.name), corpus, |b, corpus| {
+                b.iter_batched(
+                    || parsed(corpus),
+                    |(mut ctx, root)| {
+                        drop(pipeline().run(&mut ctx, root));
+                        ctx
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Add every benchmark of this module to `c`.
+pub fn benchmarks(c: &mut Criterion) {
+    bench_parse(c);
+    bench_gc(c);
+    bench_traversal(c);
+    bench_dump(c);
+    bench_transforms(c);
+}
+
+/// Run every benchmark, configured from the command line arguments like a criterion
+/// harness, and print the summary.
+pub fn run_benchmarks() {
+    let mut c = Criterion::default().configure_from_args();
+    benchmarks(&mut c);
+    c.final_summary();
+}
@@ -0,0 +1,299 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Runtime helpers shared by the passes which lower language features,
+//! e.g. classes, async functions or spread.
+//!
+//! Passes register the helpers they may need in a [`Helpers`] registry and call
+//! [`Helpers::require`] to obtain a reference to a helper wherever it is used.
+//! Once all passes have run on a module, [`Helpers::inject`] adds every helper required
+//! in that module, and the helpers they depend on, exactly once at the start of the module:
+//! either as inline declarations or as imports from an external helpers module.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use juno::ast::*;
+
+/// Definition of a runtime helper.
+#[derive(Clone, Copy)]
+pub struct HelperDef {
+    /// Name of the helper, which is also the name of the binding it declares.
+    pub name: &'static str,
+
+    /// Names of the other helpers used by this one.
+    pub deps: &'static [&'static str],
+
+    /// Build the declaration of the helper, binding `name`.
+    pub build: for<'gc> fn(&'gc GCLock) -> &'gc Node<'gc>,
+}
+
+/// Where the helpers come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelperMode {
+    /// Declare the helpers in every module which uses them.
+    Inline,
+
+    /// Import the helpers from the module with this specifier.
+    /// Scripts can't import, so helpers are always declared inline in them.
+    Import(String),
+}
+
+/// Registry of runtime helpers, tracking which helpers are used.
+pub struct Helpers {
+    mode: HelperMode,
+
+    /// All the helpers which can be required, by name.
+    defs: HashMap<&'static str, HelperDef>,
+
+    /// Helpers required in the current module, not including their dependencies.
+    module_helpers: BTreeSet<&'static str>,
+
+    /// Helpers requested by each pass, over all modules.
+    requests: BTreeMap<String, BTreeSet<&'static str>>,
+}
+
+impl Helpers {
+    pub fn new(mode: HelperMode) -> Self {
+        Self {
+            mode,
+            defs: HashMap::new(),
+            module_helpers: BTreeSet::new(),
+            requests: BTreeMap::new(),
+        }
+    }
+
+    /// Make the helper `def` available.
+    /// Replaces any previously registered helper with the same name.
+    pub fn register(&mut self, def: HelperDef) {
+        self.defs.insert(def.name, def);
+    }
+
+    /// Record that the pass called `pass` uses the helper `name` in the current module,
+    /// and return an identifier referring to it.
+    /// Panics if no helper called `name` has been registered.
+    pub fn require<'gc>(&mut self, gc: &'gc GCLock, pass: &str, name: &str) -> &'gc Node<'gc> {
+        let def = match self.defs.get(name) {
+            Some(def) => def,
+            None => panic!("Unknown helper {}", name),
+        };
+        self.module_helpers.insert(def.name);
+        self.requests
+            .entry(pass.to_string())
+            .or_default()
+            .insert(def.name);
+        builders::ident(gc, def.name)
+    }
+
+    /// Names of the helpers requested by the pass called `pass` so far, sorted.
+    pub fn requested_by(&self, pass: &str) -> impl Iterator<Item = &'static str> + '_ {
+        self.requests
+            .get(pass)
+            .into_iter()
+            .flat_map(|names| names.iter().copied())
+    }
+
+    /// Names of the helpers required in the current module, including dependencies,
+    /// ordered so that every helper comes after the helpers it depends on.
+    pub fn module_helpers(&self) -> Vec<&'static str> {
+        let mut result = vec![];
+        let mut visited = BTreeSet::new();
+        for name in &self.module_helpers {
+            self.add_with_deps(name, &mut visited, &mut result);
+        }
+        result
+    }
+
+    fn add_with_deps(
+        &self,
+        name: &'static str,
+        visited: &mut BTreeSet<&'static str>,
+        result: &mut Vec<&'static str>,
+    ) {
+        if !visited.insert(name) {
+            return;
+        }
+        let def = match self.defs.get(name) {
+            Some(def) => def,
+            None => panic!("Unknown helper {}", name),
+        };
+        for dep in def.deps {
+            self.add_with_deps(dep, visited, result);
+        }
+        result.push(name);
+    }
+
+    /// Add the helpers required in the current module to the start of `root`,
+    /// which must be a `Program` or `Module`, and start tracking a new module.
+    /// Returns `root` unchanged if no helpers were required.
+    pub fn inject<'gc>(&mut self, gc: &'gc GCLock, root: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        if self.module_helpers.is_empty() {
+            return root;
+        }
+        let result = match (&self.mode, root) {
+            // The helpers module takes care of the dependencies.
+            (HelperMode::Import(source), Node::Module(_)) => {
+                let names = self.module_helpers.iter().copied().collect::<Vec<_>>();
+                inject_prelude(gc, root, [build_import(gc, source, &names)])
+            }
+            _ => {
+                let decls = self
+                    .module_helpers()
+                    .into_iter()
+                    .map(|name| (self.defs[name].build)(gc));
+                inject_prelude(gc, root, decls)
+            }
+        };
+        self.module_helpers.clear();
+        result
+    }
+}
+
+/// `import {name1, name2, ...} from 'source';`
+fn build_import<'gc>(gc: &'gc GCLock, source: &str, names: &[&str]) -> &'gc Node<'gc> {
+    let specifiers = names.iter().map(|name| {
+        builder::ImportSpecifier::build_template(
+            gc,
+            template::ImportSpecifier {
+                metadata: Default::default(),
+                imported: builders::ident(gc, name),
+                local: builders::ident(gc, name),
+                import_kind: ImportKind::Value,
+            },
+        )
+    });
+    builder::ImportDeclaration::build_template(
+        gc,
+        template::ImportDeclaration {
+            metadata: Default::default(),
+            specifiers: NodeList::from_iter(gc, specifiers),
+            source: builders::string(gc, source),
+            assertions: None,
+            import_kind: ImportKind::Value,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::gen_js;
+
+    use super::*;
+
+    /// `var name = dep;`, or `var name = 0;` without `dep`.
+    fn build_var<'gc>(gc: &'gc GCLock, name: &str, dep: Option<&str>) -> &'gc Node<'gc> {
+        builder::VariableDeclaration::build_template(
+            gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind: VariableDeclarationKind::Var,
+                declarations: NodeList::from_iter(
+                    gc,
+                    [builder::VariableDeclarator::build_template(
+                        gc,
+                        template::VariableDeclarator {
+                            metadata: Default::default(),
+                            init: Some(match dep {
+                                Some(dep) => builders::ident(gc, dep),
+                                None => builders::number(gc, 0.0),
+                            }),
+                            id: builders::ident(gc, name),
+                        },
+                    )],
+                ),
+            },
+        )
+    }
+
+    fn helpers(mode: HelperMode) -> Helpers {
+        let mut helpers = Helpers::new(mode);
+        helpers.register(HelperDef {
+            name: "_base",
+            deps: &[],
+            build: |gc| build_var(gc, "_base", None),
+        });
+        helpers.register(HelperDef {
+            name: "_derived",
+            deps: &["_base"],
+            build: |gc| build_var(gc, "_derived", Some("_base")),
+        });
+        helpers
+    }
+
+    /// Build a module calling the helpers required by each pass,
+    /// inject the helpers and return the generated JS.
+    fn run(helpers: &mut Helpers, calls: &[(&str, &str)]) -> String {
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let body = calls.iter().map(|(pass, name)| {
+                let callee = helpers.require(&gc, pass, name);
+                builders::expr_stmt(&gc, builders::call(&gc, callee, []))
+            });
+            let module = builder::Module::build_template(
+                &gc,
+                template::Module {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, body.collect::<Vec<_>>()),
+                },
+            );
+            NodeRc::from_node(&gc, helpers.inject(&gc, module))
+        };
+        let mut out = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &root,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_inline() {
+        let mut helpers = helpers(HelperMode::Inline);
+        let js = run(
+            &mut helpers,
+            &[
+                ("classes", "_derived"),
+                ("spread", "_base"),
+                ("classes", "_derived"),
+            ],
+        );
+        assert_eq!(
+            js,
+            "var _base=0;var _derived=_base;_derived();_base();_derived();\n"
+        );
+        assert_eq!(
+            helpers.requested_by("classes").collect::<Vec<_>>(),
+            ["_derived"]
+        );
+        assert_eq!(
+            helpers.requested_by("spread").collect::<Vec<_>>(),
+            ["_base"]
+        );
+        assert_eq!(helpers.requested_by("async").count(), 0);
+
+        // The next module doesn't use any helpers.
+        assert_eq!(run(&mut helpers, &[]), "\n");
+    }
+
+    #[test]
+    fn test_import() {
+        let mut helpers = helpers(HelperMode::Import("helpers".to_string()));
+        let js = run(&mut helpers, &[("classes", "_derived")]);
+        assert_eq!(
+            js,
+            "import {_derived as _derived}from 'helpers';_derived();\n"
+        );
+    }
+}
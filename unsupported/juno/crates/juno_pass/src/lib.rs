@@ -9,9 +9,26 @@
 //!
 //! Provides transformation traits and the ability to compose them in a pipeline.
 
+#[cfg(feature = "bench")]
+pub mod bench;
+
 mod manager;
+pub use manager::run_pass;
 pub use manager::Pass;
 pub use manager::PassManager;
 
+pub mod helpers;
+pub mod metro;
+pub mod modules;
+pub mod name_map;
+pub use name_map::NameMap;
+pub mod plugin;
+
+mod transaction;
+pub use transaction::Transaction;
+
+#[cfg(test)]
+mod test_util;
+
 mod passes;
 pub use passes::*;
@@ -10,8 +10,12 @@ use juno::ast::GCLock;
 use juno::ast::Node;
 use juno::ast::NodeRc;
 use juno::ast::TransformResult;
+use juno_support::Progress;
+use juno_support::ProgressEvent;
 
 use crate::passes::*;
+use crate::NameMap;
+use crate::Transaction;
 
 /// Manager to create pipelines of multiple passes over the AST.
 #[derive(Default)]
@@ -45,26 +49,66 @@ impl PassManager {
     }
 
     /// Run the pipeline on `node`, consuming it in the process.
-    pub fn run(mut self, ctx: &mut Context, node: NodeRc) -> NodeRc {
+    /// If the cancellation token of `ctx` is cancelled, the remaining passes are skipped.
+    pub fn run(self, ctx: &mut Context, node: NodeRc) -> NodeRc {
+        self.run_with_progress(ctx, node, &mut |_: ProgressEvent| {})
+    }
+
+    /// Run the pipeline on `node` like [`PassManager::run`],
+    /// reporting each completed pass to `progress`.
+    pub fn run_with_progress(
+        mut self,
+        ctx: &mut Context,
+        node: NodeRc,
+        progress: &mut dyn Progress,
+    ) -> NodeRc {
         let mut result = node;
-        for pass in &mut self.passes {
-            {
-                let gc = GCLock::new(ctx);
-                result = match pass.run(&gc, result.node(&gc)) {
-                    TransformResult::Unchanged => result,
-                    TransformResult::Removed => {
-                        panic!("Program node removed");
-                    }
-                    TransformResult::Changed(new_node) => NodeRc::from_node(&gc, new_node),
-                    TransformResult::Expanded(..) => {
-                        panic!("Program node cannot be expanded");
-                    }
-                };
+        let total = self.passes.len();
+        for (i, pass) in self.passes.iter_mut().enumerate() {
+            if ctx.is_cancelled() {
+                break;
             }
-            ctx.gc();
+            result = run_pass(ctx, pass.as_mut(), result);
+            progress.report(ProgressEvent::PassCompleted {
+                name: pass.name(),
+                done: i + 1,
+                total,
+            });
         }
         result
     }
+
+    /// Run the pipeline on the current root of `transaction`, recording each pass which
+    /// changes the tree as a separate change, so the passes can be undone one by one.
+    pub fn run_in_transaction(mut self, ctx: &mut Context, transaction: &mut Transaction) {
+        for pass in self.passes.iter_mut() {
+            if ctx.is_cancelled() {
+                break;
+            }
+            transaction.apply_pass(ctx, pass.as_mut());
+            ctx.collect_if_needed();
+        }
+    }
+}
+
+/// Run a single `pass` on `node`, consuming it in the process.
+/// Useful for passes which must outlive the pipeline, e.g. to be run on several modules.
+pub fn run_pass(ctx: &mut Context, pass: &mut dyn Pass, node: NodeRc) -> NodeRc {
+    let result = {
+        let gc = GCLock::new(ctx);
+        match pass.run(&gc, node.node(&gc)) {
+            TransformResult::Unchanged => node,
+            TransformResult::Removed => {
+                panic!("Program node removed");
+            }
+            TransformResult::Changed(new_node) => NodeRc::from_node(&gc, new_node),
+            TransformResult::Expanded(..) => {
+                panic!("Program node cannot be expanded");
+            }
+        }
+    };
+    ctx.collect_if_needed();
+    result
 }
 
 /// A single pass over the AST.
@@ -81,4 +125,8 @@ pub trait Pass {
         gc: &'gc GCLock,
         node: &'gc Node<'gc>,
     ) -> TransformResult<&'gc Node<'gc>>;
+
+    /// Add the names changed by the pass so far to `map`.
+    /// Only passes which rename identifiers or properties need to implement this.
+    fn record_names(&self, _map: &mut NameMap) {}
 }
@@ -0,0 +1,378 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Adapter implementing the contract of Metro transformers, so that React Native projects
+//! can transform their files with Juno instead of Babel.
+//!
+//! Metro gives its transformer a file, its source and options, and expects the transformed
+//! code, its source map and the dependencies of the module. [`transform`] parses the file
+//! as Flow with JSX, strips the Flow types, transforms the JSX with the [`Jsx`] pass,
+//! optionally optimizes the code, and collects the dependencies of the result:
+//! - `import` declarations, `export ... from` declarations and `require` calls with a string
+//!   literal are synchronous dependencies;
+//! - `import()` calls with a string literal are asynchronous dependencies.
+//!
+//! Like in Metro, a dependency only required in `try` blocks is optional, and dependencies with
+//! the same name and asynchronicity are merged into one with every location.
+//! `require` is assumed to refer to the global function, even if it is shadowed.
+//!
+//! The module keeps its `import` and `export` declarations and isn't wrapped in Metro's
+//! module factory: converting it to CommonJS and wrapping it are left to the caller.
+
+use std::io;
+
+use juno::ast::*;
+use juno::gen_js;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::resolve_dependency::DependencyKind;
+use juno_support::NullTerminatedBuf;
+use thiserror::Error;
+
+use crate::jsx::Jsx;
+use crate::jsx::DEFAULT_PRAGMA;
+use crate::jsx::DEFAULT_PRAGMA_FRAG;
+use crate::strip_flow::StripFlow;
+use crate::PassManager;
+
+/// Options of a transformation, the subset of Metro's which affect Juno.
+#[derive(Debug, Clone)]
+pub struct TransformOptions {
+    /// Whether to optimize the code and omit the whitespace, like `minify` in Metro.
+    pub minify: bool,
+
+    /// Function creating JSX elements, as a dotted path.
+    pub jsx_pragma: String,
+
+    /// Component of JSX fragments, as a dotted path.
+    pub jsx_pragma_frag: String,
+
+    /// Whether to embed the source in the source map.
+    pub sources_content: bool,
+}
+
+impl Default for TransformOptions {
+    fn default() -> Self {
+        TransformOptions {
+            minify: false,
+            jsx_pragma: DEFAULT_PRAGMA.to_string(),
+            jsx_pragma_frag: DEFAULT_PRAGMA_FRAG.to_string(),
+            sources_content: false,
+        }
+    }
+}
+
+/// A module the transformed module depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The specifier of the module, as written in the source.
+    pub name: String,
+
+    /// How the module is first required.
+    pub kind: DependencyKind,
+
+    /// Whether every `require` of the module is in a `try` block, so it may be missing.
+    pub optional: bool,
+
+    /// Locations of the `import` declarations and calls requiring the module.
+    pub locs: Vec<SourceRange>,
+}
+
+impl Dependency {
+    /// Whether the module is loaded asynchronously, with `import()`.
+    pub fn is_async(&self) -> bool {
+        self.kind == DependencyKind::DynamicImport
+    }
+}
+
+/// The result of a transformation.
+#[derive(Debug, Clone)]
+pub struct TransformOutput {
+    pub code: String,
+
+    /// The source map of `code`, as JSON.
+    pub map: String,
+
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("{file}:{error}")]
+    Parse { file: String, error: ParseError },
+
+    #[error("{0}")]
+    Io(#[from] io::Error),
+}
+
+/// Transform the file `file`, whose contents are `source`, for Metro.
+pub fn transform(
+    file: &str,
+    source: &str,
+    options: &TransformOptions,
+) -> Result<TransformOutput, TransformError> {
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source(file, NullTerminatedBuf::from_str_copy(source));
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            enable_jsx: true,
+            dialect: hparser::ParserDialect::Flow,
+            source_type: hparser::SourceType::Unambiguous,
+            ..Default::default()
+        },
+        &buf,
+    );
+    let parse_error = |loc, msg| TransformError::Parse {
+        file: file.to_string(),
+        error: ParseError { loc, msg },
+    };
+    if let Some((loc, msg)) = parsed.first_error() {
+        return Err(parse_error(loc, msg));
+    }
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        match parsed.to_ast(&gc, file_id) {
+            Some(program) => NodeRc::from_node(&gc, program),
+            None => {
+                return Err(parse_error(
+                    SourceLoc::invalid(),
+                    "invalid AST produced".into(),
+                ));
+            }
+        }
+    };
+    drop(parsed);
+
+    let mut passes = PassManager::new();
+    passes.add_pass(Box::new(StripFlow::new()));
+    passes.add_pass(Box::new(Jsx::with_pragma(
+        &options.jsx_pragma,
+        &options.jsx_pragma_frag,
+    )));
+    let mut root = passes.run(&mut ctx, root);
+    if options.minify {
+        root = PassManager::standard().run(&mut ctx, root);
+    }
+
+    let dependencies = {
+        let gc = GCLock::new(&mut ctx);
+        collect_dependencies(&gc, root.node(&gc))
+    };
+
+    let mut code = vec![];
+    let map = gen_js::generate(
+        &mut code,
+        &mut ctx,
+        &root,
+        gen_js::Opt {
+            pretty: if options.minify {
+                gen_js::Pretty::No
+            } else {
+                gen_js::Pretty::Yes
+            },
+            compress: options.minify,
+            sources_content: options.sources_content,
+            ..gen_js::Opt::new()
+        },
+    )?;
+    let mut map_json = vec![];
+    map.to_writer(&mut map_json).map_err(io::Error::other)?;
+    Ok(TransformOutput {
+        code: String::from_utf8_lossy(&code).into_owned(),
+        map: String::from_utf8_lossy(&map_json).into_owned(),
+        dependencies,
+    })
+}
+
+/// Collect the dependencies of the module `root`, in the order they are first required.
+pub fn collect_dependencies<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Vec<Dependency> {
+    let mut collector = DependencyCollector {
+        dependencies: vec![],
+        try_depth: 0,
+    };
+    root.visit(gc, &mut collector, None);
+    collector.dependencies
+}
+
+struct DependencyCollector {
+    dependencies: Vec<Dependency>,
+
+    /// Number of `try` blocks enclosing the current node.
+    try_depth: u32,
+}
+
+impl DependencyCollector {
+    fn add(&mut self, name: String, kind: DependencyKind, range: SourceRange) {
+        let optional = self.try_depth != 0;
+        let is_async = kind == DependencyKind::DynamicImport;
+        match self
+            .dependencies
+            .iter_mut()
+            .find(|dep| dep.name == name && dep.is_async() == is_async)
+        {
+            Some(dep) => {
+                dep.optional &= optional;
+                dep.locs.push(range);
+            }
+            None => self.dependencies.push(Dependency {
+                name,
+                kind,
+                optional,
+                locs: vec![range],
+            }),
+        }
+    }
+}
+
+/// The value of `node` if it is a string literal.
+fn string_value(gc: &GCLock, node: &Node) -> Option<String> {
+    match node {
+        Node::StringLiteral(StringLiteral { value, .. }) => {
+            Some(String::from_utf16_lossy(gc.str_u16(*value)))
+        }
+        _ => None,
+    }
+}
+
+impl<'gc> Visitor<'gc> for DependencyCollector {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        let dependency = match node {
+            Node::ImportDeclaration(import) => {
+                string_value(gc, import.source).map(|name| (name, DependencyKind::Import))
+            }
+            Node::ExportNamedDeclaration(export) => export
+                .source
+                .and_then(|source| string_value(gc, source))
+                .map(|name| (name, DependencyKind::Import)),
+            Node::ExportAllDeclaration(ExportAllDeclaration { source, .. }) => {
+                string_value(gc, source).map(|name| (name, DependencyKind::Import))
+            }
+            Node::ImportExpression(ImportExpression { source, .. }) => {
+                string_value(gc, source).map(|name| (name, DependencyKind::DynamicImport))
+            }
+            Node::CallExpression(CallExpression {
+                callee: Node::Identifier(Identifier { name, .. }),
+                arguments,
+                ..
+            }) if gc.str(*name) == "require" && arguments.len() == 1 => arguments
+                .head()
+                .and_then(|arg| string_value(gc, arg))
+                .map(|name| (name, DependencyKind::Require)),
+            _ => None,
+        };
+        if let Some((name, kind)) = dependency {
+            self.add(name, kind, *node.range());
+        }
+
+        let in_try = matches!(
+            path,
+            Some(Path {
+                parent: Node::TryStatement(_),
+                field: NodeField::block,
+            })
+        );
+        if in_try {
+            self.try_depth += 1;
+        }
+        node.visit_children(gc, self);
+        if in_try {
+            self.try_depth -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn require<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builders::expr_stmt(
+            gc,
+            builders::call(
+                gc,
+                builders::ident(gc, "require"),
+                [builders::string(gc, name)],
+            ),
+        )
+    }
+
+    fn import<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builders::expr_stmt(
+            gc,
+            builder::ImportExpression::build_template(
+                gc,
+                template::ImportExpression {
+                    metadata: Default::default(),
+                    source: builders::string(gc, name),
+                    attributes: None,
+                },
+            ),
+        )
+    }
+
+
+    #[test]
+    fn test_dependencies() {
+        let mut ctx = Context::new();
+        let gc = GCLock::new(&mut ctx);
+
+        // require('a');
+        // try { require('b'); require('a'); } catch {}
+        // import('a');
+        // require(b);
+        let try_stmt = builder::TryStatement::build_template(
+            &gc,
+            template::TryStatement {
+                metadata: Default::default(),
+                block: builders::block(&gc, [require(&gc, "b"), require(&gc, "a")]),
+                handler: Some(builder::CatchClause::build_template(
+                    &gc,
+                    template::CatchClause {
+                        metadata: Default::default(),
+                        param: None,
+                        body: builders::block(&gc, []),
+                    },
+                )),
+                finalizer: None,
+            },
+        );
+        let dynamic = builders::expr_stmt(
+            &gc,
+            builders::call(
+                &gc,
+                builders::ident(&gc, "require"),
+                [builders::ident(&gc, "b")],
+            ),
+        );
+        let program = builder::Program::build_template(
+            &gc,
+            template::Program {
+                metadata: Default::default(),
+                body: NodeList::from_iter(
+                    &gc,
+                    [require(&gc, "a"), try_stmt, import(&gc, "a"), dynamic],
+                ),
+            },
+        );
+
+        let deps = collect_dependencies(&gc, program)
+            .into_iter()
+            .map(|dep| (dep.name, dep.kind, dep.optional, dep.locs.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deps,
+            [
+                ("a".to_string(), DependencyKind::Require, false, 2),
+                ("b".to_string(), DependencyKind::Require, true, 1),
+                ("a".to_string(), DependencyKind::DynamicImport, false, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1229 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Utilities for passes which change the imports and exports of a module, like the JSX
+//! automatic runtime, runtime helpers, instrumentation or codemods restructuring the API of
+//! a module.
+//!
+//! [`ensure_import`] makes names of a module available, reusing the imports which already
+//! exist, so it can be called any number of times:
+//! ```js
+//! import {jsx as _jsx} from 'react/jsx-runtime';
+//! ```
+//! with `jsx` and `jsxs` from `react/jsx-runtime` becomes
+//! ```js
+//! import {jsx as _jsx, jsxs} from 'react/jsx-runtime';
+//! ```
+//! and the callers must refer to `jsx` as `_jsx`.
+//!
+//! [`add_export`], [`remove_export`], [`rename_export`] and [`default_to_named`] change
+//! the exports of a module and [`build_barrel`] builds a module re-exporting others.
+//! They fail with an [`ExportError`] rather than produce exports which are invalid
+//! according to the spec, which [`validate_exports`] checks.
+
+use std::collections::HashSet;
+
+use juno::ast::*;
+use juno_support::ident;
+use juno_support::keywords;
+use juno_support::keywords::EsVersion;
+use thiserror::Error;
+
+/// A name imported from a module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImportName<'a> {
+    /// The default export, as in `import x from 'module'`.
+    Default,
+
+    /// The namespace object, as in `import * as x from 'module'`.
+    Namespace,
+
+    /// A named export, as in `import {name} from 'module'`.
+    Named(&'a str),
+}
+
+/// The result of [`ensure_import`].
+pub struct EnsuredImport<'gc> {
+    /// The program, which is new if imports were added.
+    pub program: &'gc Node<'gc>,
+
+    /// The local names bound to the requested names, in the same order.
+    pub locals: Vec<String>,
+}
+
+/// Make `names` of `module` available in `program`, which must be a `Program` or `Module`,
+/// and return the names they are bound to.
+///
+/// Names which are already imported keep their existing local name, including aliases.
+/// Type imports and imports with attributes are ignored.
+/// Missing names are added to an existing import of `module` when possible, or to a new
+/// import after the other imports at the start of the program. Their local name is the
+/// imported name, or `_` followed by the name of the module for default and namespace
+/// imports, prefixed with `_` and suffixed with a number as needed to avoid every name
+/// used in `program`.
+pub fn ensure_import<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    module: &str,
+    names: &[ImportName],
+) -> EnsuredImport<'gc> {
+    let body = body_of(program);
+
+    // The existing imports of the module, which may gain specifiers.
+    let mut imports = body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Node::ImportDeclaration(import)
+                if import.assertions.is_none()
+                    && import.import_kind == ImportKind::Value
+                    && matches!(
+                        import.source,
+                        Node::StringLiteral(StringLiteral { value, .. })
+                            if String::from_utf16_lossy(gc.str_u16(*value)) == module
+                    ) =>
+            {
+                Some((stmt, import.specifiers.iter().collect::<Vec<_>>()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut used = used_names(gc, program);
+    let mut locals = Vec::with_capacity(names.len());
+    // Imports which can't be merged into the existing ones.
+    let mut new_imports: Vec<Vec<&'gc Node<'gc>>> = vec![];
+    for &name in names {
+        if let Some(local) = imports
+            .iter()
+            .flat_map(|(_, specifiers)| specifiers)
+            .find_map(|spec| imported_local(gc, spec, name))
+        {
+            locals.push(local);
+            continue;
+        }
+        let local = fresh_name(&mut used, &preferred_local(module, name));
+        let spec = build_specifier(gc, name, &local);
+        locals.push(local);
+        match imports
+            .iter_mut()
+            .map(|(_, specifiers)| specifiers)
+            .chain(new_imports.iter_mut())
+            .find(|specifiers| can_add(specifiers, name))
+        {
+            Some(specifiers) => add_specifier(specifiers, spec),
+            None => new_imports.push(vec![spec]),
+        }
+    }
+
+    let changed = imports.iter().any(|(decl, specifiers)| match decl {
+        Node::ImportDeclaration(import) => import.specifiers.len() != specifiers.len(),
+        _ => unreachable!(),
+    });
+    if !changed && new_imports.is_empty() {
+        return EnsuredImport { program, locals };
+    }
+
+    let source = builders::string(gc, module);
+    let new_decls = new_imports
+        .into_iter()
+        .map(|specifiers| build_import(gc, None, specifiers, source));
+    // New imports go after the last import of the prologue, or at the start.
+    let prologue = body
+        .iter()
+        .take_while(|stmt| matches!(stmt, Node::ImportDeclaration(_)) || is_directive(stmt))
+        .count();
+    let has_imports = body
+        .iter()
+        .take(prologue)
+        .any(|stmt| matches!(stmt, Node::ImportDeclaration(_)));
+    let mut stmts = Vec::with_capacity(body.len() + 1);
+    for stmt in body.iter() {
+        let stmt = match imports.iter().position(|(decl, _)| decl.ptr_eq(stmt)) {
+            Some(i) => {
+                let (decl, specifiers) = imports.swap_remove(i);
+                build_import(gc, Some(decl), specifiers, source)
+            }
+            None => stmt,
+        };
+        stmts.push(stmt);
+    }
+    let program = if has_imports {
+        let tail = stmts.split_off(prologue);
+        stmts.extend(new_decls);
+        stmts.extend(tail);
+        with_body(gc, program, stmts)
+    } else {
+        inject_prelude(gc, with_body(gc, program, stmts), new_decls)
+    };
+    EnsuredImport { program, locals }
+}
+
+/// The local name of `spec` if it imports `name`.
+fn imported_local<'gc>(gc: &GCLock, spec: &'gc Node<'gc>, name: ImportName) -> Option<String> {
+    let local = match (spec, name) {
+        (
+            Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local, .. }),
+            ImportName::Default,
+        )
+        | (
+            Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local, .. }),
+            ImportName::Namespace,
+        ) => local,
+        (
+            Node::ImportSpecifier(ImportSpecifier {
+                imported: Node::Identifier(Identifier { name: imported, .. }),
+                local,
+                import_kind: ImportKind::Value,
+                ..
+            }),
+            _,
+        ) => match name {
+            ImportName::Named(name) if gc.str(*imported) == name => local,
+            ImportName::Default if gc.str(*imported) == "default" => local,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match local {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        _ => None,
+    }
+}
+
+/// Whether an import with `specifiers` can also import `name`.
+fn can_add(specifiers: &[&Node], name: ImportName) -> bool {
+    let has = |f: fn(&Node) -> bool| specifiers.iter().any(|spec| f(spec));
+    let has_namespace = has(|spec| matches!(spec, Node::ImportNamespaceSpecifier(_)));
+    match name {
+        ImportName::Default => !has(|spec| matches!(spec, Node::ImportDefaultSpecifier(_))),
+        ImportName::Namespace => {
+            !has_namespace && !has(|spec| matches!(spec, Node::ImportSpecifier(_)))
+        }
+        ImportName::Named(_) => !has_namespace,
+    }
+}
+
+/// Add `spec` to `specifiers`, keeping the default import first.
+fn add_specifier<'gc>(specifiers: &mut Vec<&'gc Node<'gc>>, spec: &'gc Node<'gc>) {
+    match spec {
+        Node::ImportDefaultSpecifier(_) => specifiers.insert(0, spec),
+        _ => specifiers.push(spec),
+    }
+}
+
+fn build_specifier<'gc>(gc: &'gc GCLock, name: ImportName, local: &str) -> &'gc Node<'gc> {
+    match name {
+        ImportName::Default => builder::ImportDefaultSpecifier::build_template(
+            gc,
+            template::ImportDefaultSpecifier {
+                metadata: Default::default(),
+                local: builders::ident(gc, local),
+            },
+        ),
+        ImportName::Namespace => builder::ImportNamespaceSpecifier::build_template(
+            gc,
+            template::ImportNamespaceSpecifier {
+                metadata: Default::default(),
+                local: builders::ident(gc, local),
+            },
+        ),
+        ImportName::Named(name) => builder::ImportSpecifier::build_template(
+            gc,
+            template::ImportSpecifier {
+                metadata: Default::default(),
+                imported: builders::ident(gc, name),
+                local: builders::ident(gc, local),
+                import_kind: ImportKind::Value,
+            },
+        ),
+    }
+}
+
+/// An import of `source` with `specifiers`, which replaces `decl` if present.
+fn build_import<'gc>(
+    gc: &'gc GCLock,
+    decl: Option<&'gc Node<'gc>>,
+    specifiers: Vec<&'gc Node<'gc>>,
+    source: &'gc Node<'gc>,
+) -> &'gc Node<'gc> {
+    let (metadata, source) = match decl {
+        Some(Node::ImportDeclaration(decl)) => ((&decl.metadata).into(), decl.source),
+        _ => (Default::default(), source),
+    };
+    builder::ImportDeclaration::build_template(
+        gc,
+        template::ImportDeclaration {
+            metadata,
+            specifiers: NodeList::from_iter(gc, specifiers),
+            source,
+            assertions: None,
+            import_kind: ImportKind::Value,
+        },
+    )
+}
+
+/// A copy of `program`, a `Program` or `Module`, with `body`.
+fn with_body<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    body: Vec<&'gc Node<'gc>>,
+) -> &'gc Node<'gc> {
+    let body = NodeList::from_iter(gc, body);
+    match program {
+        Node::Program(p) => builder::Program::build_template(
+            gc,
+            template::Program {
+                metadata: (&p.metadata).into(),
+                body,
+            },
+        ),
+        Node::Module(m) => builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: (&m.metadata).into(),
+                body,
+            },
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// Every identifier used in `root`, including JSX names.
+fn used_names<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> HashSet<String> {
+    iter_preorder(gc, root)
+        .filter_map(|(node, _, _)| match node {
+            Node::Identifier(Identifier { name, .. })
+            | Node::JSXIdentifier(JSXIdentifier { name, .. }) => Some(gc.str(*name).to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The local name `name` of `module` is bound to if it is available.
+fn preferred_local(module: &str, name: ImportName) -> String {
+    match name {
+        ImportName::Named(name) => name.to_string(),
+        ImportName::Default | ImportName::Namespace => {
+            // `_` and the last component of the path, in camel case: `_jsxRuntime`.
+            let base = module.rsplit('/').next().unwrap_or(module);
+            let base = base.split('.').next().unwrap_or(base);
+            let mut result = "_".to_string();
+            let mut upper = false;
+            for c in base.chars() {
+                if c.is_ascii_alphanumeric() || c == '$' {
+                    if upper && result.len() > 1 {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.push(c);
+                    }
+                    upper = false;
+                } else {
+                    upper = true;
+                }
+            }
+            result
+        }
+    }
+}
+
+/// `name`, prefixed with `_` and suffixed with a number as needed to not be in `used`,
+/// which it is added to.
+fn fresh_name(used: &mut HashSet<String>, name: &str) -> String {
+    let mut result = name.to_string();
+    if used.contains(&result) {
+        let base = if name.starts_with('_') {
+            name.to_string()
+        } else {
+            format!("_{}", name)
+        };
+        result = base.clone();
+        let mut i = 2;
+        while used.contains(&result) {
+            result = format!("{}{}", base, i);
+            i += 1;
+        }
+    }
+    used.insert(result.clone());
+    result
+}
+
+/// An error which would make the exports of a module invalid.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ExportError {
+    #[error("duplicate export '{0}'")]
+    Duplicate(String),
+
+    #[error("no export named '{0}'")]
+    NotFound(String),
+
+    #[error("exported binding '{0}' is not declared in the module")]
+    Undeclared(String),
+
+    #[error("'{0}' is not a valid identifier")]
+    InvalidName(String),
+}
+
+/// A module re-exported by a barrel built by [`build_barrel`].
+#[derive(Debug, Copy, Clone)]
+pub enum Reexport<'a> {
+    /// `export * from 'source';`, which doesn't re-export the default export.
+    All,
+
+    /// `export * as name from 'source';`
+    Namespace(&'a str),
+
+    /// `export {imported as exported, ...} from 'source';`
+    Named(&'a [(&'a str, &'a str)]),
+}
+
+/// The names of the values exported by `program`, in source order.
+/// The names exported by `export * from` are only known once the module is resolved,
+/// so they are not included.
+pub fn exported_names<'gc>(gc: &'gc GCLock, program: &'gc Node<'gc>) -> Vec<String> {
+    let mut result = vec![];
+    for stmt in body_of(program).iter() {
+        match stmt {
+            Node::ExportNamedDeclaration(export) if export.export_kind == ExportKind::Value => {
+                if let Some(decl) = export.declaration {
+                    result.extend(declared_names(gc, decl));
+                }
+                result.extend(export.specifiers.iter().map(|spec| exported_name(gc, spec)));
+            }
+            Node::ExportDefaultDeclaration(_) => result.push("default".to_string()),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Check that every name is exported at most once by `program`, and that the bindings
+/// exported without `from` are declared at its top level, as required by the spec.
+pub fn validate_exports<'gc>(gc: &'gc GCLock, program: &'gc Node<'gc>) -> Result<(), ExportError> {
+    let mut exported = HashSet::new();
+    for name in exported_names(gc, program) {
+        if !exported.insert(name.clone()) {
+            return Err(ExportError::Duplicate(name));
+        }
+    }
+    let body = body_of(program);
+    let declared = body
+        .iter()
+        .flat_map(|stmt| top_level_names(gc, stmt))
+        .collect::<HashSet<_>>();
+    for stmt in body.iter() {
+        if let Node::ExportNamedDeclaration(export) = stmt {
+            if export.source.is_some() || export.export_kind != ExportKind::Value {
+                continue;
+            }
+            for spec in export.specifiers.iter() {
+                if let Node::ExportSpecifier(ExportSpecifier { local, .. }) = spec {
+                    let local = name_of(gc, local);
+                    if !declared.contains(&local) {
+                        return Err(ExportError::Undeclared(local));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Export the top-level binding `local` of `program` as `exported`, by adding
+/// `export {local as exported};` at the end of `program`.
+pub fn add_export<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    local: &str,
+    exported: &str,
+) -> Result<&'gc Node<'gc>, ExportError> {
+    check_binding(local)?;
+    check_name(exported)?;
+    let mut stmts = body_of(program).iter().collect::<Vec<_>>();
+    stmts.push(build_export(gc, &[(local, exported)], None));
+    validated(gc, with_body(gc, program, stmts))
+}
+
+/// Stop exporting `exported` from `program`.
+/// Exported declarations are kept as local declarations, and the expression of
+/// `export default` is still evaluated, unless it is an anonymous function.
+/// A re-export whose last name is removed becomes an import of the module,
+/// which is still evaluated.
+pub fn remove_export<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    exported: &str,
+) -> Result<&'gc Node<'gc>, ExportError> {
+    let body = body_of(program);
+    let (index, export) = find_export(gc, body, exported)?;
+    let stmt = body.iter().nth(index).unwrap();
+    let replacement = match (export, stmt) {
+        (Export::Specifier(i), _) => edit_specifier(gc, stmt, i, None).into_iter().collect(),
+        (Export::Default, Node::ExportDefaultDeclaration(decl)) => match decl.declaration {
+            Node::FunctionDeclaration(func) if func.id.is_none() => vec![],
+            Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => {
+                unexport(gc, program, stmt, exported).0
+            }
+            expr => vec![builders::expr_stmt(gc, expr)],
+        },
+        _ => unexport(gc, program, stmt, exported).0,
+    };
+    validated(gc, splice(gc, program, index, replacement))
+}
+
+/// Export `from` as `to` instead.
+/// Exported declarations are exported with `export {local as to};` after them. A default
+/// export which isn't a named function or class is first bound to a new name, prefixed
+/// with `_default`, as in `const _default = value;`.
+pub fn rename_export<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    from: &str,
+    to: &str,
+) -> Result<&'gc Node<'gc>, ExportError> {
+    check_name(to)?;
+    let body = body_of(program);
+    let (index, export) = find_export(gc, body, from)?;
+    if from == to {
+        return Ok(program);
+    }
+    if exported_names(gc, program).iter().any(|name| name == to) {
+        return Err(ExportError::Duplicate(to.to_string()));
+    }
+    let stmt = body.iter().nth(index).unwrap();
+    let replacement = match export {
+        Export::Specifier(i) => {
+            let spec = match stmt {
+                Node::ExportNamedDeclaration(decl) => decl.specifiers.iter().nth(i).unwrap(),
+                _ => unreachable!(),
+            };
+            let exported = builders::ident(gc, to);
+            let renamed = match spec {
+                Node::ExportSpecifier(spec) => builder::ExportSpecifier::build_template(
+                    gc,
+                    template::ExportSpecifier {
+                        metadata: (&spec.metadata).into(),
+                        exported,
+                        local: spec.local,
+                    },
+                ),
+                Node::ExportNamespaceSpecifier(spec) => {
+                    builder::ExportNamespaceSpecifier::build_template(
+                        gc,
+                        template::ExportNamespaceSpecifier {
+                            metadata: (&spec.metadata).into(),
+                            exported,
+                        },
+                    )
+                }
+                _ => unreachable!(),
+            };
+            edit_specifier(gc, stmt, i, Some(renamed))
+                .into_iter()
+                .collect()
+        }
+        Export::Declaration | Export::Default => {
+            let (mut stmts, local) = unexport(gc, program, stmt, from);
+            stmts.push(build_export(gc, &[(&local, to)], None));
+            stmts
+        }
+    };
+    validated(gc, splice(gc, program, index, replacement))
+}
+
+/// Replace the default export of `program` with the named export `name`.
+/// See [`rename_export`].
+pub fn default_to_named<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    name: &str,
+) -> Result<&'gc Node<'gc>, ExportError> {
+    rename_export(gc, program, "default", name)
+}
+
+/// Build a module re-exporting each module in `reexports`, like the `index.js` of a package.
+pub fn build_barrel<'gc>(
+    gc: &'gc GCLock,
+    reexports: &[(&str, Reexport)],
+) -> Result<&'gc Node<'gc>, ExportError> {
+    let mut stmts = vec![];
+    for &(source, reexport) in reexports {
+        let source = builders::string(gc, source);
+        stmts.push(match reexport {
+            Reexport::All => builder::ExportAllDeclaration::build_template(
+                gc,
+                template::ExportAllDeclaration {
+                    metadata: Default::default(),
+                    source,
+                    export_kind: ExportKind::Value,
+                },
+            ),
+            Reexport::Namespace(name) => {
+                check_name(name)?;
+                let spec = builder::ExportNamespaceSpecifier::build_template(
+                    gc,
+                    template::ExportNamespaceSpecifier {
+                        metadata: Default::default(),
+                        exported: builders::ident(gc, name),
+                    },
+                );
+                build_export_declaration(gc, vec![spec], Some(source))
+            }
+            Reexport::Named(names) => {
+                for (imported, exported) in names {
+                    check_name(imported)?;
+                    check_name(exported)?;
+                }
+                build_export(gc, names, Some(source))
+            }
+        });
+    }
+    validated(
+        gc,
+        builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: Default::default(),
+                body: NodeList::from_iter(gc, stmts),
+            },
+        ),
+    )
+}
+
+/// How a name is exported by a statement.
+#[derive(Debug, Copy, Clone)]
+enum Export {
+    /// By the specifier at the index in `export {...}`, with or without `from`.
+    Specifier(usize),
+    /// By an exported declaration, as in `export function f() {}`.
+    Declaration,
+    /// By `export default`.
+    Default,
+}
+
+/// The index of the statement of `body` exporting `exported`, and how it does.
+fn find_export<'gc>(
+    gc: &'gc GCLock,
+    body: NodeList<'gc>,
+    exported: &str,
+) -> Result<(usize, Export), ExportError> {
+    for (index, stmt) in body.iter().enumerate() {
+        match stmt {
+            Node::ExportNamedDeclaration(export) if export.export_kind == ExportKind::Value => {
+                if let Some(decl) = export.declaration {
+                    if declared_names(gc, decl).iter().any(|name| name == exported) {
+                        return Ok((index, Export::Declaration));
+                    }
+                }
+                if let Some(i) = export
+                    .specifiers
+                    .iter()
+                    .position(|spec| exported_name(gc, spec) == exported)
+                {
+                    return Ok((index, Export::Specifier(i)));
+                }
+            }
+            Node::ExportDefaultDeclaration(_) if exported == "default" => {
+                return Ok((index, Export::Default));
+            }
+            _ => {}
+        }
+    }
+    Err(ExportError::NotFound(exported.to_string()))
+}
+
+/// The statements replacing `stmt`, an exported declaration or `export default`, so that it
+/// no longer exports `exported`, and the local name of the value it exported.
+fn unexport<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    stmt: &'gc Node<'gc>,
+    exported: &str,
+) -> (Vec<&'gc Node<'gc>>, String) {
+    match stmt {
+        Node::ExportNamedDeclaration(export) => {
+            let decl = export.declaration.unwrap();
+            // The other names declared together are still exported.
+            let others = declared_names(gc, decl)
+                .into_iter()
+                .filter(|name| name != exported)
+                .collect::<Vec<_>>();
+            let mut stmts = vec![decl];
+            if !others.is_empty() {
+                let names = others
+                    .iter()
+                    .map(|name| (name.as_str(), name.as_str()))
+                    .collect::<Vec<_>>();
+                stmts.push(build_export(gc, &names, None));
+            }
+            (stmts, exported.to_string())
+        }
+        Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+            match declared_names(gc, declaration).pop() {
+                Some(name) => (vec![declaration], name),
+                None => {
+                    let name = fresh_name(&mut used_names(gc, program), "_default");
+                    (vec![bind_default(gc, declaration, &name)], name)
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// The declaration of `name` with the value `declaration` of `export default`, which is an
+/// anonymous function or class, or an expression.
+fn bind_default<'gc>(gc: &'gc GCLock, declaration: &'gc Node<'gc>, name: &str) -> &'gc Node<'gc> {
+    let id = Some(builders::ident(gc, name));
+    match declaration {
+        Node::FunctionDeclaration(f) => builder::FunctionDeclaration::build_template(
+            gc,
+            template::FunctionDeclaration {
+                metadata: (&f.metadata).into(),
+                id,
+                params: f.params,
+                body: f.body,
+                type_parameters: f.type_parameters,
+                return_type: f.return_type,
+                predicate: f.predicate,
+                generator: f.generator,
+                is_async: f.is_async,
+            },
+        ),
+        Node::ClassDeclaration(c) => builder::ClassDeclaration::build_template(
+            gc,
+            template::ClassDeclaration {
+                metadata: (&c.metadata).into(),
+                id,
+                type_parameters: c.type_parameters,
+                super_class: c.super_class,
+                super_type_parameters: c.super_type_parameters,
+                implements: c.implements,
+                decorators: c.decorators,
+                body: c.body,
+            },
+        ),
+        expr => builder::VariableDeclaration::build_template(
+            gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind: VariableDeclarationKind::Const,
+                declarations: NodeList::from_iter(
+                    gc,
+                    [builder::VariableDeclarator::build_template(
+                        gc,
+                        template::VariableDeclarator {
+                            metadata: Default::default(),
+                            init: Some(expr),
+                            id: builders::ident(gc, name),
+                        },
+                    )],
+                ),
+            },
+        ),
+    }
+}
+
+/// `stmt`, an `export {...}`, with the specifier at `index` replaced by `spec`, or removed.
+/// Returns `None` if no specifiers remain in an export without `from`.
+fn edit_specifier<'gc>(
+    gc: &'gc GCLock,
+    stmt: &'gc Node<'gc>,
+    index: usize,
+    spec: Option<&'gc Node<'gc>>,
+) -> Option<&'gc Node<'gc>> {
+    let decl = match stmt {
+        Node::ExportNamedDeclaration(decl) => decl,
+        _ => unreachable!(),
+    };
+    let specifiers = decl
+        .specifiers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, old)| if i == index { spec } else { Some(old) })
+        .collect::<Vec<_>>();
+    match (specifiers.is_empty(), decl.source) {
+        (true, None) => None,
+        // Keep evaluating the module.
+        (true, Some(source)) => Some(builder::ImportDeclaration::build_template(
+            gc,
+            template::ImportDeclaration {
+                metadata: (&decl.metadata).into(),
+                specifiers: NodeList::new(gc),
+                source,
+                assertions: None,
+                import_kind: ImportKind::Value,
+            },
+        )),
+        (false, source) => Some(builder::ExportNamedDeclaration::build_template(
+            gc,
+            template::ExportNamedDeclaration {
+                metadata: (&decl.metadata).into(),
+                declaration: None,
+                specifiers: NodeList::from_iter(gc, specifiers),
+                source,
+                export_kind: decl.export_kind,
+            },
+        )),
+    }
+}
+
+/// `export {local as exported, ...} from source;`, without `from` if `source` is `None`.
+fn build_export<'gc>(
+    gc: &'gc GCLock,
+    names: &[(&str, &str)],
+    source: Option<&'gc Node<'gc>>,
+) -> &'gc Node<'gc> {
+    let specifiers = names
+        .iter()
+        .map(|(local, exported)| {
+            builder::ExportSpecifier::build_template(
+                gc,
+                template::ExportSpecifier {
+                    metadata: Default::default(),
+                    exported: builders::ident(gc, exported),
+                    local: builders::ident(gc, local),
+                },
+            )
+        })
+        .collect();
+    build_export_declaration(gc, specifiers, source)
+}
+
+fn build_export_declaration<'gc>(
+    gc: &'gc GCLock,
+    specifiers: Vec<&'gc Node<'gc>>,
+    source: Option<&'gc Node<'gc>>,
+) -> &'gc Node<'gc> {
+    builder::ExportNamedDeclaration::build_template(
+        gc,
+        template::ExportNamedDeclaration {
+            metadata: Default::default(),
+            declaration: None,
+            specifiers: NodeList::from_iter(gc, specifiers),
+            source,
+            export_kind: ExportKind::Value,
+        },
+    )
+}
+
+/// A copy of `program` with the statement at `index` replaced by `stmts`.
+fn splice<'gc>(
+    gc: &'gc GCLock,
+    program: &'gc Node<'gc>,
+    index: usize,
+    stmts: Vec<&'gc Node<'gc>>,
+) -> &'gc Node<'gc> {
+    let mut body = body_of(program).iter().collect::<Vec<_>>();
+    body.splice(index..index + 1, stmts);
+    with_body(gc, program, body)
+}
+
+fn validated<'gc>(gc: &'gc GCLock, program: &'gc Node<'gc>) -> Result<&'gc Node<'gc>, ExportError> {
+    validate_exports(gc, program)?;
+    Ok(program)
+}
+
+/// The names declared at the top level by `stmt`.
+fn top_level_names<'gc>(gc: &'gc GCLock, stmt: &'gc Node<'gc>) -> Vec<String> {
+    match stmt {
+        Node::ImportDeclaration(import) => {
+            let ImportDeclaration { specifiers, .. } = &**import;
+            specifiers
+                .iter()
+                .filter_map(|spec| match spec {
+                    Node::ImportSpecifier(ImportSpecifier { local, .. })
+                    | Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local, .. })
+                    | Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local, .. }) => {
+                        Some(name_of(gc, local))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+        Node::ExportNamedDeclaration(export) => match export.declaration {
+            Some(decl) => declared_names(gc, decl),
+            None => declared_names(gc, stmt),
+        },
+        Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+            declared_names(gc, declaration)
+        }
+        _ => declared_names(gc, stmt),
+    }
+}
+
+/// The names declared by `decl`: the names bound by a variable declaration, or the `id`
+/// of other declarations like functions, classes or components.
+fn declared_names<'gc>(gc: &'gc GCLock, decl: &'gc Node<'gc>) -> Vec<String> {
+    match decl {
+        Node::VariableDeclaration(VariableDeclaration { declarations, .. }) => declarations
+            .iter()
+            .flat_map(|declarator| match declarator {
+                Node::VariableDeclarator(VariableDeclarator { id, .. }) => bound_names(id),
+                _ => vec![],
+            })
+            .map(|ident| name_of(gc, ident))
+            .collect(),
+        _ => match decl.field_by_name("id") {
+            Some(FieldValue::Node(id @ Node::Identifier(_))) => vec![name_of(gc, id)],
+            _ => vec![],
+        },
+    }
+}
+
+/// The name exported by `spec`, an `ExportSpecifier` or `ExportNamespaceSpecifier`.
+fn exported_name(gc: &GCLock, spec: &Node) -> String {
+    match spec {
+        Node::ExportSpecifier(ExportSpecifier { exported, .. })
+        | Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier { exported, .. }) => {
+            name_of(gc, exported)
+        }
+        _ => panic!("Expected export specifier, found {:?}", spec.variant()),
+    }
+}
+
+fn name_of(gc: &GCLock, ident: &Node) -> String {
+    match ident {
+        Node::Identifier(Identifier { name, .. }) => gc.str(*name).to_string(),
+        _ => panic!("Expected Identifier, found {:?}", ident.variant()),
+    }
+}
+
+/// Check that `name` can be exported or imported.
+fn check_name(name: &str) -> Result<(), ExportError> {
+    if ident::is_identifier_name(name) {
+        Ok(())
+    } else {
+        Err(ExportError::InvalidName(name.to_string()))
+    }
+}
+
+/// Check that `name` can be bound in a module.
+fn check_binding(name: &str) -> Result<(), ExportError> {
+    if ident::is_identifier_name(name) && !keywords::is_reserved_in_module(name, EsVersion::ES2017)
+    {
+        Ok(())
+    } else {
+        Err(ExportError::InvalidName(name.to_string()))
+    }
+}
+
+/// The body of `program`, which must be a `Program` or `Module`.
+fn body_of<'gc>(program: &'gc Node<'gc>) -> NodeList<'gc> {
+    match program {
+        Node::Program(Program { body, .. }) | Node::Module(Module { body, .. }) => *body,
+        _ => panic!("Expected Program or Module, found {:?}", program.variant()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::gen_js;
+
+    use super::*;
+
+    /// `import {imported as local, ...} from 'source';`
+    fn build_named_import<'gc>(
+        gc: &'gc GCLock,
+        import_kind: ImportKind,
+        names: &[(&str, &str)],
+        source: &str,
+    ) -> &'gc Node<'gc> {
+        let specifiers = names.iter().map(|(imported, local)| {
+            builder::ImportSpecifier::build_template(
+                gc,
+                template::ImportSpecifier {
+                    metadata: Default::default(),
+                    imported: builders::ident(gc, imported),
+                    local: builders::ident(gc, local),
+                    import_kind: ImportKind::Value,
+                },
+            )
+        });
+        builder::ImportDeclaration::build_template(
+            gc,
+            template::ImportDeclaration {
+                metadata: Default::default(),
+                specifiers: NodeList::from_iter(gc, specifiers),
+                source: builders::string(gc, source),
+                assertions: None,
+                import_kind,
+            },
+        )
+    }
+
+    fn build_module<'gc>(gc: &'gc GCLock, body: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: Default::default(),
+                body: NodeList::from_iter(gc, body),
+            },
+        )
+    }
+
+    /// `function name() {}`, anonymous without `name`.
+    fn build_function<'gc>(gc: &'gc GCLock, name: Option<&str>) -> &'gc Node<'gc> {
+        builder::FunctionDeclaration::build_template(
+            gc,
+            template::FunctionDeclaration {
+                metadata: Default::default(),
+                id: name.map(|name| builders::ident(gc, name)),
+                params: NodeList::new(gc),
+                body: builder::BlockStatement::build_template(
+                    gc,
+                    template::BlockStatement {
+                        metadata: Default::default(),
+                        body: NodeList::new(gc),
+                    },
+                ),
+                type_parameters: None,
+                return_type: None,
+                predicate: None,
+                generator: false,
+                is_async: false,
+            },
+        )
+    }
+
+    /// `export const name = 0, ...;`
+    fn build_export_const<'gc>(gc: &'gc GCLock, names: &[&str]) -> &'gc Node<'gc> {
+        let declarators = names.iter().map(|name| {
+            builder::VariableDeclarator::build_template(
+                gc,
+                template::VariableDeclarator {
+                    metadata: Default::default(),
+                    init: Some(builders::number(gc, 0.0)),
+                    id: builders::ident(gc, name),
+                },
+            )
+        });
+        let decl = builder::VariableDeclaration::build_template(
+            gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind: VariableDeclarationKind::Const,
+                declarations: NodeList::from_iter(gc, declarators),
+            },
+        );
+        builder::ExportNamedDeclaration::build_template(
+            gc,
+            template::ExportNamedDeclaration {
+                metadata: Default::default(),
+                declaration: Some(decl),
+                specifiers: NodeList::new(gc),
+                source: None,
+                export_kind: ExportKind::Value,
+            },
+        )
+    }
+
+    fn build_export_default<'gc>(gc: &'gc GCLock, declaration: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::ExportDefaultDeclaration::build_template(
+            gc,
+            template::ExportDefaultDeclaration {
+                metadata: Default::default(),
+                declaration,
+            },
+        )
+    }
+
+    fn generate(ctx: &mut Context, root: &NodeRc) -> String {
+        let mut out = vec![];
+        gen_js::generate(
+            &mut out,
+            ctx,
+            root,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        use ImportName::*;
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let module = build_module(
+                &gc,
+                vec![
+                    build_named_import(
+                        &gc,
+                        ImportKind::Value,
+                        &[("jsx", "_jsx")],
+                        "react/jsx-runtime",
+                    ),
+                    builders::expr_stmt(&gc, builders::ident(&gc, "jsx")),
+                ],
+            );
+
+            let names = [Named("jsx"), Named("jsxs"), Default];
+            let result = ensure_import(&gc, module, "react/jsx-runtime", &names);
+            assert_eq!(result.locals, ["_jsx", "jsxs", "_jsxRuntime"]);
+            let module = result.program;
+
+            // Nothing changes when the names are already imported.
+            let again = ensure_import(&gc, module, "react/jsx-runtime", &names);
+            assert!(again.program.ptr_eq(module));
+            assert_eq!(again.locals, result.locals);
+
+            // The namespace can't be imported with named imports.
+            let result = ensure_import(&gc, module, "react/jsx-runtime", &[Namespace]);
+            assert_eq!(result.locals, ["_jsxRuntime2"]);
+            let module = result.program;
+
+            // Another module is imported separately.
+            let result = ensure_import(&gc, module, "other", &[Named("jsxs")]);
+            assert_eq!(result.locals, ["_jsxs"]);
+            NodeRc::from_node(&gc, result.program)
+        };
+        assert_eq!(
+            generate(&mut ctx, &root),
+            "import _jsxRuntime,{jsx as _jsx,jsxs as jsxs}from 'react/jsx-runtime';\
+             import * as _jsxRuntime2 from 'react/jsx-runtime';\
+             import {jsxs as _jsxs}from 'other';jsx;\n"
+        );
+    }
+
+    #[test]
+    fn test_type_import() {
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let module = build_module(
+                &gc,
+                vec![
+                    build_named_import(&gc, ImportKind::Type, &[("a", "a")], "m"),
+                    builders::expr_stmt(&gc, builders::ident(&gc, "b")),
+                ],
+            );
+            let result = ensure_import(&gc, module, "m", &[ImportName::Named("a")]);
+            assert_eq!(result.locals, ["_a"]);
+            NodeRc::from_node(&gc, result.program)
+        };
+        assert_eq!(
+            generate(&mut ctx, &root),
+            "import type {a as a}from 'm';import {a as _a}from 'm';b;\n"
+        );
+    }
+
+    #[test]
+    fn test_exports() {
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let module = build_module(
+                &gc,
+                vec![
+                    build_export_const(&gc, &["a", "b"]),
+                    build_function(&gc, Some("f")),
+                    build_export(&gc, &[("f", "g")], None),
+                    build_export_default(&gc, build_function(&gc, None)),
+                    build_export(&gc, &[("x", "y")], Some(builders::string(&gc, "m"))),
+                ],
+            );
+            assert_eq!(exported_names(&gc, module), ["a", "b", "g", "default", "y"]);
+
+            assert_eq!(
+                add_export(&gc, module, "f", "g").unwrap_err(),
+                ExportError::Duplicate("g".to_string())
+            );
+            assert_eq!(
+                add_export(&gc, module, "z", "z").unwrap_err(),
+                ExportError::Undeclared("z".to_string())
+            );
+            assert_eq!(
+                add_export(&gc, module, "class", "c").unwrap_err(),
+                ExportError::InvalidName("class".to_string())
+            );
+            assert_eq!(
+                remove_export(&gc, module, "h").unwrap_err(),
+                ExportError::NotFound("h".to_string())
+            );
+            assert_eq!(
+                rename_export(&gc, module, "g", "b").unwrap_err(),
+                ExportError::Duplicate("b".to_string())
+            );
+
+            let module = add_export(&gc, module, "f", "h").unwrap();
+            let module = remove_export(&gc, module, "a").unwrap();
+            let module = rename_export(&gc, module, "g", "k").unwrap();
+            let module = default_to_named(&gc, module, "main").unwrap();
+            let module = remove_export(&gc, module, "y").unwrap();
+            assert_eq!(exported_names(&gc, module), ["b", "k", "main", "h"]);
+            NodeRc::from_node(&gc, module)
+        };
+        assert_eq!(
+            generate(&mut ctx, &root),
+            "const a=0,b=0;export {b as b};function f(){}export {f as k};\
+             function _default(){}export {_default as main};import 'm';export {f as h};\n"
+        );
+    }
+
+    #[test]
+    fn test_default_expression() {
+        let mut ctx = Context::new();
+        let (renamed, removed) = {
+            let gc = GCLock::new(&mut ctx);
+            let call = builders::call(&gc, builders::ident(&gc, "init"), []);
+            let module = build_module(
+                &gc,
+                vec![
+                    build_export_default(&gc, call),
+                    builders::expr_stmt(&gc, builders::ident(&gc, "_default")),
+                ],
+            );
+            (
+                NodeRc::from_node(&gc, default_to_named(&gc, module, "main").unwrap()),
+                NodeRc::from_node(&gc, remove_export(&gc, module, "default").unwrap()),
+            )
+        };
+        assert_eq!(
+            generate(&mut ctx, &renamed),
+            "const _default2=init();export {_default2 as main};_default;\n"
+        );
+        assert_eq!(generate(&mut ctx, &removed), "init();_default;\n");
+    }
+
+    #[test]
+    fn test_barrel() {
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            assert_eq!(
+                build_barrel(
+                    &gc,
+                    &[
+                        ("./a", Reexport::Named(&[("a", "a")])),
+                        ("./b", Reexport::Namespace("a")),
+                    ],
+                )
+                .unwrap_err(),
+                ExportError::Duplicate("a".to_string())
+            );
+            let barrel = build_barrel(
+                &gc,
+                &[
+                    ("./a", Reexport::All),
+                    ("./b", Reexport::Namespace("b")),
+                    ("./c", Reexport::Named(&[("default", "C"), ("c", "c")])),
+                ],
+            )
+            .unwrap();
+            NodeRc::from_node(&gc, barrel)
+        };
+        assert_eq!(
+            generate(&mut ctx, &root),
+            "export * from './a';export * as b from './b';\
+             export {default as C,c as c} from './c';\n"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Record of the names changed by renaming passes, so that stack traces of the
+//! generated code can be de-obfuscated.
+//!
+//! The map is emitted as JSON alongside the source map:
+//! ```json
+//! {
+//!   "version": 1,
+//!   "file": "out.js",
+//!   "scopes": [
+//!     {
+//!       "name": "<properties>",
+//!       "names": { "_privateLongName": "a" }
+//!     },
+//!     {
+//!       "name": "foo",
+//!       "source": "in.js",
+//!       "line": 10,
+//!       "column": 1,
+//!       "names": { "longVariable": "b" }
+//!     }
+//!   ]
+//! }
+//! ```
+//! Scopes with a location are identified by the start of their node in the original source,
+//! which is what a symbolicator obtains by looking up a generated location in the source map.
+//! Scopes without a location, like the one for property names, apply to the whole program.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+
+use juno::ast::SourceManager;
+use juno::ast::SourceRange;
+use juno_support::json::JSONEmitter;
+use juno_support::json::Pretty;
+
+/// Version of the emitted JSON format.
+const FORMAT_VERSION: f64 = 1.0;
+
+/// Names renamed in a single scope.
+#[derive(Debug, Clone)]
+pub struct ScopeNames {
+    /// Human readable name of the scope, e.g. the name of the function.
+    pub name: String,
+
+    /// Location of the scope in the original source,
+    /// or `None` if the names apply to the whole program.
+    pub range: Option<SourceRange>,
+
+    /// Map from original to new names, sorted for deterministic output.
+    pub names: BTreeMap<String, String>,
+}
+
+/// Names renamed by one or more passes, grouped by scope.
+#[derive(Debug, Clone, Default)]
+pub struct NameMap {
+    scopes: Vec<ScopeNames>,
+}
+
+impl NameMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Whether no names have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.scopes.iter().all(|scope| scope.names.is_empty())
+    }
+
+    /// All the scopes, in the order they were first recorded.
+    pub fn scopes(&self) -> &[ScopeNames] {
+        &self.scopes
+    }
+
+    /// Record that `original` was renamed to `renamed` in the scope called `scope_name`
+    /// located at `range`.
+    pub fn add(
+        &mut self,
+        scope_name: &str,
+        range: Option<SourceRange>,
+        original: &str,
+        renamed: &str,
+    ) {
+        let scope = match self
+            .scopes
+            .iter()
+            .position(|scope| scope.name == scope_name && scope.range == range)
+        {
+            Some(index) => &mut self.scopes[index],
+            None => {
+                self.scopes.push(ScopeNames {
+                    name: scope_name.to_string(),
+                    range,
+                    names: BTreeMap::new(),
+                });
+                self.scopes.last_mut().unwrap()
+            }
+        };
+        scope
+            .names
+            .insert(original.to_string(), renamed.to_string());
+    }
+
+    /// Write the map as JSON for the generated file `file`.
+    /// `sm` is used to resolve the source names of scope locations.
+    pub fn write_json<W: Write>(&self, sm: &SourceManager, file: &str, out: W) -> io::Result<()> {
+        let mut emitter = JSONEmitter::new(out, Pretty::Yes);
+        emitter.open_dict();
+        emitter.emit_key("version");
+        emitter.emit_number(FORMAT_VERSION);
+        emitter.emit_key("file");
+        emitter.emit_string(file);
+        emitter.emit_key("scopes");
+        emitter.open_array();
+        for scope in &self.scopes {
+            emitter.open_dict();
+            emitter.emit_key("name");
+            emitter.emit_string(&scope.name);
+            if let Some(range) = scope.range {
+                emitter.emit_key("source");
+                emitter.emit_string(sm.source_name(range.file));
+                emitter.emit_key("line");
+                emitter.emit_number(range.start.line as f64);
+                emitter.emit_key("column");
+                emitter.emit_number(range.start.col as f64);
+            }
+            emitter.emit_key("names");
+            emitter.open_dict();
+            for (original, renamed) in &scope.names {
+                emitter.emit_key(original);
+                emitter.emit_string(renamed);
+            }
+            emitter.close_dict();
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.close_dict();
+        emitter.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::ast::SourceLoc;
+    use juno_support::NullTerminatedBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_write_json() {
+        let mut sm = SourceManager::new();
+        let file = sm.add_source("in.js", NullTerminatedBuf::from_str_copy(""));
+        let range = SourceRange::from_loc(file, SourceLoc { line: 3, col: 5 });
+
+        let mut map = NameMap::new();
+        assert!(map.is_empty());
+        map.add("<properties>", None, "_foo", "a");
+        map.add("f", Some(range), "longName", "b");
+        map.add("<properties>", None, "_bar", "c");
+        assert_eq!(map.scopes().len(), 2);
+
+        let mut out = vec![];
+        map.write_json(&sm, "out.js", &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{
+  "version": 1,
+  "file": "out.js",
+  "scopes": [
+    {
+      "name": "<properties>",
+      "names": {
+        "_bar": "c",
+        "_foo": "a"
+      }
+    },
+    {
+      "name": "f",
+      "source": "in.js",
+      "line": 3,
+      "column": 5,
+      "names": {
+        "longName": "b"
+      }
+    }
+  ]
+}
+"#
+        );
+    }
+}
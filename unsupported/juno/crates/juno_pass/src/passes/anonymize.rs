@@ -0,0 +1,268 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass replacing the names and values in the code with placeholders, so that an AST
+//! reproducing a bug can be shared without sharing the code it came from.
+//!
+//! The structure of the AST is preserved, and so are the equalities between names and
+//! values: every occurrence of an identifier is replaced with the same placeholder,
+//! and likewise for strings and numbers. Two properties with the same key still have the
+//! same key, and a variable still refers to the same declaration.
+//! ```js
+//! const answer = compute('question', 42);
+//! ```
+//! becomes
+//! ```js
+//! const v0 = v1('s0', 0);
+//! ```
+//! Directives, meta properties (`new.target`, `import.meta`) and a few identifiers with
+//! a special meaning, like `arguments` or `undefined`, are kept as they are.
+//! Comments aren't part of the AST, but the doc block emitted by the JS generator must
+//! be disabled.
+
+use std::collections::HashMap;
+
+use juno::ast::*;
+
+use crate::Pass;
+
+/// Identifiers whose meaning depends on their name.
+const RESERVED_NAMES: &[&str] = &[
+    "arguments",
+    "eval",
+    "undefined",
+    "NaN",
+    "Infinity",
+    "constructor",
+    "prototype",
+    "__proto__",
+];
+
+#[derive(Default)]
+pub struct Anonymize {
+    /// Placeholder of each identifier.
+    names: HashMap<String, String>,
+
+    /// Placeholder of each string, including template strings, JSX text and regexp
+    /// patterns.
+    strings: HashMap<Vec<u16>, String>,
+
+    /// Placeholder of each number, keyed by its bits.
+    numbers: HashMap<u64, f64>,
+
+    /// Number of placeholders created for big integers.
+    bigints: usize,
+}
+
+impl Anonymize {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The placeholder of the identifier `name`, or `None` if it is kept.
+    /// Placeholders start with an upper case letter when `name` does, so JSX component
+    /// names stay distinct from intrinsic element names.
+    fn name(&mut self, name: &str) -> Option<String> {
+        if RESERVED_NAMES.contains(&name) {
+            return None;
+        }
+        let next = self.names.len();
+        let placeholder = self.names.entry(name.to_string()).or_insert_with(|| {
+            let prefix = if name.starts_with(|c: char| c.is_uppercase()) {
+                'V'
+            } else {
+                'v'
+            };
+            format!("{}{}", prefix, next)
+        });
+        Some(placeholder.clone())
+    }
+
+    /// The placeholder of the string `value`.
+    fn string(&mut self, value: &[u16]) -> String {
+        let next = self.strings.len();
+        self.strings
+            .entry(value.to_vec())
+            .or_insert_with(|| format!("s{}", next))
+            .clone()
+    }
+
+    /// The placeholder of the string `value`, as UTF-16.
+    fn string_u16(&mut self, value: &[u16]) -> Vec<u16> {
+        self.string(value).encode_utf16().collect()
+    }
+
+    /// Return a builder for the anonymized version of `node`, or `None` if it is kept.
+    fn anonymize<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+    ) -> Option<builder::Builder<'gc>> {
+        Some(match node {
+            Node::Identifier(inner) => {
+                let name = self.name(gc.str(inner.name))?;
+                let mut builder = builder::Identifier::from_node(inner);
+                builder.name(gc.atom(name));
+                builder.into()
+            }
+            Node::JSXIdentifier(inner) => {
+                let name = self.name(gc.str(inner.name))?;
+                let mut builder = builder::JSXIdentifier::from_node(inner);
+                builder.name(gc.atom(name));
+                builder.into()
+            }
+            Node::StringLiteral(inner) => {
+                let value = self.string_u16(gc.str_u16(inner.value));
+                let mut builder = builder::StringLiteral::from_node(inner);
+                builder.value(gc.atom_u16(value));
+                builder.into()
+            }
+            Node::JSXStringLiteral(inner) => {
+                let value = self.string(gc.str_u16(inner.value));
+                let mut builder = builder::JSXStringLiteral::from_node(inner);
+                builder.raw(gc.atom(format!("\"{}\"", value)));
+                builder.value(gc.atom_u16(value.encode_utf16().collect::<Vec<_>>()));
+                builder.into()
+            }
+            // Whitespace is significant in JSX.
+            Node::JSXText(inner) if !gc.str(inner.raw).trim().is_empty() => {
+                let value = self.string(gc.str_u16(inner.value));
+                let mut builder = builder::JSXText::from_node(inner);
+                builder.raw(gc.atom(value.as_str()));
+                builder.value(gc.atom_u16(value.encode_utf16().collect::<Vec<_>>()));
+                builder.into()
+            }
+            Node::TemplateElement(inner) => {
+                let raw = gc.str(inner.raw);
+                if raw.is_empty() {
+                    return None;
+                }
+                let value = self.string(&raw.encode_utf16().collect::<Vec<_>>());
+                let mut builder = builder::TemplateElement::from_node(inner);
+                builder.raw(gc.atom(value.as_str()));
+                builder.cooked(Some(gc.atom_u16(value.encode_utf16().collect::<Vec<_>>())));
+                builder.into()
+            }
+            Node::RegExpLiteral(inner) => {
+                let pattern = gc.str(inner.pattern).encode_utf16().collect::<Vec<_>>();
+                let pattern = self.string(&pattern);
+                let mut builder = builder::RegExpLiteral::from_node(inner);
+                builder.pattern(gc.atom(pattern));
+                builder.into()
+            }
+            Node::NumericLiteral(inner) => {
+                let next = self.numbers.len() as f64;
+                let value = *self.numbers.entry(inner.value.to_bits()).or_insert(next);
+                let mut builder = builder::NumericLiteral::from_node(inner);
+                builder.value(value);
+                builder.into()
+            }
+            Node::BigIntLiteral(inner) => {
+                let bigint = format!("{}n", self.bigints);
+                self.bigints += 1;
+                let mut builder = builder::BigIntLiteral::from_node(inner);
+                builder.bigint(gc.atom(bigint));
+                builder.into()
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl Pass for Anonymize {
+    fn name(&self) -> &'static str {
+        "Anonymize"
+    }
+    fn description(&self) -> &'static str {
+        "Replaces identifiers, strings and numbers with placeholders, preserving the structure"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Anonymize {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            // `'use strict'` and the like have a meaning.
+            Node::ExpressionStatement(ExpressionStatement {
+                directive: Some(_), ..
+            })
+            | Node::Directive(_)
+            | Node::MetaProperty(_) => TransformResult::Unchanged,
+            _ => match self.anonymize(gc, node) {
+                Some(builder) => node.replace_with_new(builder, gc, self),
+                None => node.visit_children_mut(gc, self),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::gen_js;
+
+    use super::*;
+
+    #[test]
+    fn test_anonymize() {
+        let mut ctx = Context::new();
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            // x.f(x, 'a', 1, 'a', 2, 1, undefined);
+            let x = builders::ident(&gc, "x");
+            let call = builders::call(
+                &gc,
+                builders::member(&gc, x, "f"),
+                [
+                    x,
+                    builders::string(&gc, "a"),
+                    builders::number(&gc, 1.0),
+                    builders::string(&gc, "a"),
+                    builders::number(&gc, 2.0),
+                    builders::number(&gc, 1.0),
+                    builders::ident(&gc, "undefined"),
+                ],
+            );
+            let program = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, [builders::expr_stmt(&gc, call)]),
+                },
+            );
+            match Anonymize::new().run(&gc, program) {
+                TransformResult::Changed(new_node) => NodeRc::from_node(&gc, new_node),
+                _ => panic!("expected a change"),
+            }
+        };
+        let mut out = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &root,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "v0.v1(v0,'s0',0,'s0',1,0,undefined);\n"
+        );
+    }
+}
@@ -0,0 +1,335 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass for rewriting code with "pattern → template" rules.
+//!
+//! A pattern is an AST in which identifiers starting with `$`, like `$a`, are metavariables
+//! which match any node. The rest of the pattern must match structurally, ignoring source
+//! ranges. When a node matches a pattern, it is replaced with a copy of the template of the
+//! rule, in which every metavariable is replaced with the node it matched.
+//! For example, with the rule `$a.indexOf($b) !== -1` → `$a.includes($b)`,
+//! ```js
+//! if (list.indexOf(x + 1) !== -1) {}
+//! ```
+//! becomes
+//! ```js
+//! if (list.includes(x + 1)) {}
+//! ```
+//! Rules can be written as source with [`Codemod::add_rule_from_source`].
+//! A metavariable which appears several times in a pattern must match equal nodes each time.
+//! Metavariables only match single nodes, not sequences of list elements.
+//!
+//! Rules are tried in the order they were added. Once a node is replaced, neither the new
+//! node nor its children are rewritten again, so rules can't loop.
+//! The generated code can then be emitted with `gen_js`.
+//!
+//! The pass holds the patterns and templates as [`NodeRc`], so it must be dropped before the
+//! [`Context`] it was created with.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use juno::ast::*;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::hparser::SnippetGoal;
+use thiserror::Error;
+
+use crate::Pass;
+
+#[derive(Debug, Error)]
+pub enum CodemodError {
+    #[error("invalid rule: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("metavariable '{0}' in the template is not in the pattern")]
+    UnboundMetavariable(String),
+}
+
+/// A rewrite rule.
+struct Rule {
+    pattern: NodeRc,
+    template: NodeRc,
+}
+
+#[derive(Default)]
+pub struct Codemod {
+    rules: Vec<Rule>,
+
+    /// Number of nodes replaced so far.
+    num_rewrites: usize,
+}
+
+impl Codemod {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a rule replacing the nodes which match `pattern` with `template`.
+    /// Every metavariable of the template must appear in the pattern.
+    pub fn add_rule(
+        &mut self,
+        ctx: &mut Context,
+        pattern: NodeRc,
+        template: NodeRc,
+    ) -> Result<(), CodemodError> {
+        {
+            let gc = GCLock::new(ctx);
+            let bound = metavariables(&gc, pattern.node(&gc));
+            if let Some(name) = metavariables(&gc, template.node(&gc))
+                .into_iter()
+                .find(|name| !bound.contains(name))
+            {
+                return Err(CodemodError::UnboundMetavariable(gc.str(name).to_string()));
+            }
+        }
+        self.rules.push(Rule { pattern, template });
+        Ok(())
+    }
+
+    /// Parse `pattern` and `template` as `goal` and add them as a rule.
+    pub fn add_rule_from_source(
+        &mut self,
+        ctx: &mut Context,
+        goal: SnippetGoal,
+        pattern: &str,
+        template: &str,
+    ) -> Result<(), CodemodError> {
+        let pattern = hparser::parse_snippet(ctx, Default::default(), goal, pattern)?;
+        let template = hparser::parse_snippet(ctx, Default::default(), goal, template)?;
+        self.add_rule(ctx, pattern, template)
+    }
+
+    /// Number of nodes replaced so far, over every run of the pass.
+    pub fn num_rewrites(&self) -> usize {
+        self.num_rewrites
+    }
+}
+
+impl Pass for Codemod {
+    fn name(&self) -> &'static str {
+        "Codemod"
+    }
+    fn description(&self) -> &'static str {
+        "Rewrites the code matching the pattern of a rule with its template"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Codemod {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        for rule in &self.rules {
+            let mut bindings = HashMap::new();
+            if match_pattern(gc, rule.pattern.node(gc), node, Some(&mut bindings)) {
+                self.num_rewrites += 1;
+                let template = deep_clone(gc, rule.template.node(gc));
+                let result = template
+                    .visit_mut(gc, &mut Substitute { bindings }, None)
+                    .unwrap();
+                return TransformResult::Changed(result);
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Return the name of `node` if it is a metavariable.
+fn metavariable(gc: &GCLock, node: &Node) -> Option<NodeLabel> {
+    match node {
+        Node::Identifier(Identifier { name, .. })
+            if gc.str(*name).len() > 1 && gc.str(*name).starts_with('$') =>
+        {
+            Some(*name)
+        }
+        _ => None,
+    }
+}
+
+/// The names of the metavariables in the subtree rooted at `node`.
+fn metavariables<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> HashSet<NodeLabel> {
+    iter_preorder(gc, node)
+        .filter_map(|(node, _, _)| metavariable(gc, node))
+        .collect()
+}
+
+/// Whether `node` matches `pattern`, binding the metavariables of `pattern` in `bindings`.
+/// If `bindings` is `None`, identifiers are never metavariables, so `node` must be
+/// structurally equal to `pattern`.
+fn match_pattern<'gc>(
+    gc: &'gc GCLock,
+    pattern: &'gc Node<'gc>,
+    node: &'gc Node<'gc>,
+    mut bindings: Option<&mut HashMap<NodeLabel, &'gc Node<'gc>>>,
+) -> bool {
+    if let Some(bindings) = &mut bindings {
+        if let Some(name) = metavariable(gc, pattern) {
+            return match bindings.get(&name) {
+                Some(bound) => match_pattern(gc, bound, node, None),
+                None => {
+                    bindings.insert(name, node);
+                    true
+                }
+            };
+        }
+    }
+    if pattern.variant() != node.variant() {
+        return false;
+    }
+    for info in pattern.fields() {
+        let matched = match (pattern.field(info.field), node.field(info.field)) {
+            (Some(FieldValue::Node(p)), Some(FieldValue::Node(n))) => {
+                match_pattern(gc, p, n, bindings.as_deref_mut())
+            }
+            (Some(FieldValue::NodeList(p)), Some(FieldValue::NodeList(n))) => {
+                p.len() == n.len()
+                    && p.iter()
+                        .zip(n.iter())
+                        .all(|(p, n)| match_pattern(gc, p, n, bindings.as_deref_mut()))
+            }
+            (Some(FieldValue::Null), Some(FieldValue::Null)) => true,
+            (Some(FieldValue::Label(p)), Some(FieldValue::Label(n))) => p == n,
+            (Some(FieldValue::String(p)), Some(FieldValue::String(n))) => {
+                gc.str_u16(p) == gc.str_u16(n)
+            }
+            (Some(FieldValue::Number(p)), Some(FieldValue::Number(n))) => {
+                p.to_bits() == n.to_bits()
+            }
+            (Some(FieldValue::Bool(p)), Some(FieldValue::Bool(n))) => p == n,
+            (Some(FieldValue::Enum(p)), Some(FieldValue::Enum(n))) => p == n,
+            _ => false,
+        };
+        if !matched {
+            return false;
+        }
+    }
+    true
+}
+
+/// Replaces the metavariables in a template with copies of the nodes they matched.
+pub(crate) struct Substitute<'gc> {
+    pub(crate) bindings: HashMap<NodeLabel, &'gc Node<'gc>>,
+}
+
+impl<'gc> VisitorMut<'gc> for Substitute<'gc> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match metavariable(gc, node).and_then(|name| self.bindings.get(&name)) {
+            Some(bound) => TransformResult::Changed(deep_clone(gc, bound)),
+            None => node.visit_children_mut(gc, self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::gen_js;
+
+    use super::*;
+
+    /// Run a codemod with the rules built by `rules` on the statements built by `body`,
+    /// and return the generated JS.
+    fn run_codemod<R, B>(rules: R, body: B) -> Result<String, CodemodError>
+    where
+        R: for<'gc> FnOnce(&'gc GCLock) -> Vec<(&'gc Node<'gc>, &'gc Node<'gc>)>,
+        B: for<'gc> FnOnce(&'gc GCLock) -> Vec<&'gc Node<'gc>>,
+    {
+        let mut ctx = Context::new();
+        let mut pass = Codemod::new();
+        let (rules, root) = {
+            let gc = GCLock::new(&mut ctx);
+            let rules = rules(&gc)
+                .into_iter()
+                .map(|(p, t)| (NodeRc::from_node(&gc, p), NodeRc::from_node(&gc, t)))
+                .collect::<Vec<_>>();
+            let root = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, body(&gc)),
+                },
+            );
+            (rules, NodeRc::from_node(&gc, root))
+        };
+        for (pattern, template) in rules {
+            pass.add_rule(&mut ctx, pattern, template)?;
+        }
+        let root = crate::run_pass(&mut ctx, &mut pass, root);
+        let mut out = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &root,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn call<'gc>(gc: &'gc GCLock, callee: &str, args: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builders::call(gc, builders::ident(gc, callee), args)
+    }
+
+    #[test]
+    fn test_rewrite() {
+        // f($a, $a) -> g($a)
+        let js = run_codemod(
+            |gc| {
+                let a = builders::ident(gc, "$a");
+                vec![(call(gc, "f", vec![a, a]), call(gc, "g", vec![a]))]
+            },
+            |gc| {
+                let one = builders::number(gc, 1.0);
+                let two = builders::number(gc, 2.0);
+                vec![
+                    builders::expr_stmt(gc, call(gc, "f", vec![one, one])),
+                    builders::expr_stmt(gc, call(gc, "f", vec![one, two])),
+                    // Distinct nodes which are structurally equal.
+                    builders::expr_stmt(
+                        gc,
+                        call(
+                            gc,
+                            "f",
+                            vec![
+                                builders::member(gc, builders::ident(gc, "o"), "p"),
+                                builders::member(gc, builders::ident(gc, "o"), "p"),
+                            ],
+                        ),
+                    ),
+                ]
+            },
+        )
+        .unwrap();
+        assert_eq!(js, "g(1);f(1,2);g((o.p));\n");
+    }
+
+    #[test]
+    fn test_unbound() {
+        let result = run_codemod(
+            |gc| vec![(builders::ident(gc, "$a"), builders::ident(gc, "$b"))],
+            |_| vec![],
+        );
+        assert!(matches!(result, Err(CodemodError::UnboundMetavariable(name)) if name == "$b"));
+    }
+}
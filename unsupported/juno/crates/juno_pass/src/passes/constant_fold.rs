@@ -0,0 +1,281 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass folding constant expressions and substituting defines.
+//!
+//! Expressions which the [`ConstEvaluator`] evaluates to a primitive value are replaced with
+//! a literal of that value: `1 + 2 * 3` becomes `7` and `'a' + 'b'` becomes `'ab'`.
+//! Defines substitute a value for a global name or a dotted path, like `__DEV__` or
+//! `process.env.NODE_ENV`, and are folded with the expressions around them, so with
+//! `process.env.NODE_ENV` defined as `'production'`,
+//! ```js
+//! if (process.env.NODE_ENV !== 'production') check();
+//! ```
+//! becomes
+//! ```js
+//! if (false) check();
+//! ```
+//! which [`ReduceConditional`](super::reduce_conditional::ReduceConditional) then removes.
+//!
+//! `undefined`, `NaN`, `Infinity` and defines are only substituted for global names:
+//! names declared anywhere in the program, e.g. by `function f(undefined) {}`, are left
+//! alone, and so is every name in programs using `with` or a direct `eval`.
+//! Only expressions whose value is read are folded: declared names, assignment targets,
+//! property keys and the contents of destructuring patterns are left alone.
+
+use std::collections::HashSet;
+
+use juno::ast::*;
+use juno::eval::ConstEvaluator;
+use juno::eval::JsValue;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::hparser::SnippetGoal;
+use juno_support::atom_table::Atom;
+use thiserror::Error;
+
+use crate::Pass;
+
+#[derive(Debug, Error)]
+pub enum DefineError {
+    #[error("invalid define value: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("define value is not a constant expression")]
+    NotConstant,
+}
+
+#[derive(Default)]
+pub struct ConstantFold {
+    evaluator: ConstEvaluator,
+    /// Names declared in the program being folded, which may shadow globals.
+    declared: HashSet<Atom>,
+    /// Whether the program uses `with` or a direct `eval`,
+    /// so that any name may refer to a local binding.
+    dynamic_scope: bool,
+}
+
+impl ConstantFold {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Substitute `value` for the global name or dotted path `name`.
+    pub fn define<S: Into<String>>(&mut self, name: S, value: JsValue) {
+        self.evaluator.define(name, value);
+    }
+
+    /// Parse `source` as a constant expression, like `'production'` or `{DEBUG: false}`,
+    /// and substitute its value for `name`.
+    /// The expression can't refer to other defines.
+    pub fn define_from_source(
+        &mut self,
+        ctx: &mut Context,
+        name: &str,
+        source: &str,
+    ) -> Result<(), DefineError> {
+        let value =
+            hparser::parse_snippet(ctx, Default::default(), SnippetGoal::Expression, source)?;
+        let gc = GCLock::new(ctx);
+        let value = ConstEvaluator::new()
+            .eval(&gc, value.node(&gc))
+            .ok_or(DefineError::NotConstant)?;
+        self.define(name, value);
+        Ok(())
+    }
+
+    /// Return the literal `node` folds to, if it is a constant expression with a primitive
+    /// value which isn't already a literal.
+    fn fold<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> Option<&'gc Node<'gc>> {
+        if !is_read(path) || is_folded(gc, node) {
+            return None;
+        }
+        let is_global = |ident: &Node| match ident {
+            Node::Identifier(Identifier { name, .. }) => {
+                !self.dynamic_scope && !self.declared.contains(name)
+            }
+            _ => false,
+        };
+        match self.evaluator.eval_in(gc, node, &is_global)? {
+            value @ (JsValue::Undefined
+            | JsValue::Null
+            | JsValue::Boolean(_)
+            | JsValue::Number(_)
+            | JsValue::String(_)) => value.to_node(gc, *node.range()),
+            // Folding arrays and objects would create a new value on every evaluation.
+            JsValue::Array(_) | JsValue::Object(_) => None,
+        }
+    }
+}
+
+impl Pass for ConstantFold {
+    fn name(&self) -> &'static str {
+        "Constant fold"
+    }
+    fn description(&self) -> &'static str {
+        "Replaces constant expressions and defines with their values"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let mut collector = DeclCollector::default();
+        node.visit(gc, &mut collector, None);
+        self.declared = collector.declared;
+        self.dynamic_scope = collector.dynamic_scope;
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for ConstantFold {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        // The names in patterns are bound, not read.
+        if matches!(node, Node::ObjectPattern(_) | Node::ArrayPattern(_)) {
+            return TransformResult::Unchanged;
+        }
+        // Fold the children first, so each expression is only evaluated once
+        // its operands are literals.
+        let children = node.visit_children_mut(gc, self);
+        let cur = match children {
+            TransformResult::Changed(new_node) => new_node,
+            _ => node,
+        };
+        match self.fold(gc, cur, path) {
+            Some(folded) => TransformResult::Changed(folded),
+            None => children,
+        }
+    }
+}
+
+/// Collects the names declared anywhere in a program.
+#[derive(Default)]
+struct DeclCollector {
+    declared: HashSet<Atom>,
+    dynamic_scope: bool,
+}
+
+impl DeclCollector {
+    fn declare<'gc>(&mut self, pattern: &'gc Node<'gc>) {
+        for ident in bound_names(pattern) {
+            if let Node::Identifier(Identifier { name, .. }) = ident {
+                self.declared.insert(*name);
+            }
+        }
+    }
+}
+
+impl<'gc> Visitor<'gc> for DeclCollector {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        match node {
+            _ if node.is_function_like() => {
+                if let Some(id) = node.function_like_id() {
+                    self.declare(id);
+                }
+                for param in node.function_like_params() {
+                    self.declare(param);
+                }
+            }
+            Node::VariableDeclarator(VariableDeclarator { id, .. }) => self.declare(id),
+            Node::CatchClause(CatchClause {
+                param: Some(param), ..
+            }) => self.declare(param),
+            Node::ClassDeclaration(class) => {
+                if let Some(id) = class.id {
+                    self.declare(id);
+                }
+            }
+            Node::ClassExpression(class) => {
+                if let Some(id) = class.id {
+                    self.declare(id);
+                }
+            }
+            Node::ImportSpecifier(ImportSpecifier { local: id, .. })
+            | Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local: id, .. })
+            | Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local: id, .. }) => {
+                self.declare(id)
+            }
+            Node::WithStatement(_) => self.dynamic_scope = true,
+            Node::CallExpression(CallExpression {
+                callee: Node::Identifier(Identifier { name, .. }),
+                ..
+            }) if gc.str(*name) == "eval" => self.dynamic_scope = true,
+            _ => {}
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// Whether an expression at `path` is read for its value.
+fn is_read(path: Option<Path>) -> bool {
+    let Path { parent, field } = match path {
+        Some(path) => path,
+        None => return false,
+    };
+    match parent {
+        Node::CallExpression(_) | Node::OptionalCallExpression(_) | Node::NewExpression(_) => {
+            field == NodeField::arguments
+        }
+        Node::MemberExpression(MemberExpression { computed, .. })
+        | Node::OptionalMemberExpression(OptionalMemberExpression { computed, .. }) => {
+            field == NodeField::object || *computed
+        }
+        Node::Property(Property { computed, .. }) => field == NodeField::value || *computed,
+        Node::AssignmentExpression(_) | Node::AssignmentPattern(_) => field == NodeField::right,
+        Node::VariableDeclarator(_) => field == NodeField::init,
+        Node::TemplateLiteral(_) => field == NodeField::expressions,
+        Node::ArrowFunctionExpression(_) => field == NodeField::body,
+        Node::UnaryExpression(UnaryExpression { operator, .. }) => {
+            *operator != UnaryExpressionOperator::Delete
+        }
+        Node::IfStatement(_) | Node::WhileStatement(_) | Node::DoWhileStatement(_) => {
+            field == NodeField::test
+        }
+        Node::ForStatement(_) => field != NodeField::body,
+        Node::SwitchStatement(_) => field == NodeField::discriminant,
+        Node::BinaryExpression(_)
+        | Node::LogicalExpression(_)
+        | Node::ConditionalExpression(_)
+        | Node::SequenceExpression(_)
+        | Node::ArrayExpression(_)
+        | Node::SpreadElement(_)
+        | Node::AwaitExpression(_)
+        | Node::ReturnStatement(_)
+        | Node::ThrowStatement(_)
+        | Node::SwitchCase(_)
+        | Node::JSXExpressionContainer(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether `node` is already the literal which folding it would produce.
+fn is_folded(gc: &GCLock, node: &Node) -> bool {
+    match node {
+        Node::NullLiteral(_)
+        | Node::BooleanLiteral(_)
+        | Node::NumericLiteral(_)
+        | Node::StringLiteral(_) => true,
+        Node::UnaryExpression(UnaryExpression {
+            operator: UnaryExpressionOperator::Minus | UnaryExpressionOperator::Void,
+            argument: Node::NumericLiteral(_),
+            ..
+        }) => true,
+        // Keep `undefined` rather than turning it into `void 0`.
+        Node::Identifier(Identifier { name, .. }) => gc.str(*name) == "undefined",
+        _ => false,
+    }
+}
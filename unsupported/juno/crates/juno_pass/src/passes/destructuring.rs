@@ -0,0 +1,1101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass for lowering destructuring to ES5.
+//!
+//! Destructuring in variable declarations, assignments, function parameters,
+//! `for-in`/`for-of` heads and `catch` parameters is replaced with simple assignments
+//! through temporaries. For example, transforms
+//! ```js
+//! var {a, b: [c = 1], ...rest} = obj;
+//! ```
+//! into
+//! ```js
+//! var _ref = obj, a = _ref.a, _ref2 = _ref.b, _ref3 = _ref2[0],
+//!   c = _ref3 === void 0 ? 1 : _ref3,
+//!   rest = _objectWithoutProperties(_ref, ['a', 'b']);
+//! ```
+//! The lowering is loose: array patterns index their source and use `slice()`
+//! for rest elements instead of iterating it, and destructuring `null` or `undefined`
+//! into an empty pattern doesn't throw.
+//!
+//! Temporaries needed by assignments are declared with `var` at the start of the
+//! enclosing function or program. Object rest elements require the
+//! [`OBJECT_WITHOUT_PROPERTIES`] helper, which is registered in the [`Helpers`]
+//! passed to [`Destructuring::new`] and must be injected by its owner.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use juno::ast::*;
+use juno_support::convert::number_to_string;
+
+use crate::helpers::HelperDef;
+use crate::helpers::Helpers;
+use crate::Pass;
+
+/// Prefix of the names of the temporaries.
+const TEMP_PREFIX: &str = "_ref";
+
+/// Name under which the pass requires its helpers.
+const HELPER_USER: &str = "destructuring";
+
+/// `_objectWithoutProperties(source, excluded)` returns a copy of the own enumerable
+/// properties of `source` whose keys are not in the array `excluded`.
+pub const OBJECT_WITHOUT_PROPERTIES: HelperDef = HelperDef {
+    name: "_objectWithoutProperties",
+    deps: &[],
+    build: build_object_without_properties,
+};
+
+pub struct Destructuring {
+    helpers: Rc<RefCell<Helpers>>,
+}
+
+impl Destructuring {
+    /// Create the pass, registering its helpers in `helpers`.
+    pub fn new(helpers: Rc<RefCell<Helpers>>) -> Self {
+        helpers.borrow_mut().register(OBJECT_WITHOUT_PROPERTIES);
+        Self { helpers }
+    }
+}
+
+impl Pass for Destructuring {
+    fn name(&self) -> &'static str {
+        "Lower destructuring"
+    }
+    fn description(&self) -> &'static str {
+        "Replaces destructuring with assignments through temporaries"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let mut names = NameCollector::default();
+        node.visit(gc, &mut names, None);
+        let mut helpers = self.helpers.borrow_mut();
+        let mut lowerer = Lowerer {
+            helpers: &mut helpers,
+            names: names.names,
+            next_temp: 1,
+            temps: HashSet::new(),
+            frames: vec![],
+        };
+        VisitorMut::call(&mut lowerer, gc, node, None)
+    }
+}
+
+/// Collects every identifier name in use, so temporaries don't shadow them.
+#[derive(Default)]
+struct NameCollector {
+    names: HashSet<String>,
+}
+
+impl<'gc> Visitor<'gc> for NameCollector {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if let Node::Identifier(Identifier { name, .. }) = node {
+            self.names.insert(gc.str(*name).to_string());
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// How the simple bindings produced by lowering a pattern are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// As declarators, so temporaries are declared along with the bound names.
+    Declare,
+    /// As assignment expressions, so temporaries must be declared in the enclosing function.
+    Assign,
+}
+
+/// A simple assignment produced by lowering a pattern: the target and its value.
+type Binding<'gc> = (&'gc Node<'gc>, &'gc Node<'gc>);
+
+/// Declarations to add to the start of a function or program.
+#[derive(Default)]
+struct Frame<'gc> {
+    /// Temporaries used by assignments.
+    temps: Vec<String>,
+
+    /// Statements which initialize destructured parameters.
+    prelude: Vec<&'gc Node<'gc>>,
+}
+
+struct Lowerer<'h, 'gc> {
+    helpers: &'h mut Helpers,
+
+    /// Names which can't be used for temporaries.
+    names: HashSet<String>,
+
+    /// Suffix of the next temporary name to try.
+    next_temp: usize,
+
+    /// Names of the temporaries created so far.
+    temps: HashSet<String>,
+
+    /// One frame for every enclosing function and program.
+    frames: Vec<Frame<'gc>>,
+}
+
+impl<'gc> VisitorMut<'gc> for Lowerer<'_, 'gc> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let is_scope = matches!(
+            node,
+            Node::Program(_)
+                | Node::Module(_)
+                | Node::FunctionDeclaration(_)
+                | Node::FunctionExpression(_)
+                | Node::ArrowFunctionExpression(_)
+        );
+        if is_scope {
+            self.frames.push(Frame::default());
+        }
+        let children = node.visit_children_mut(gc, self);
+        let cur = match children {
+            TransformResult::Changed(new_node) => new_node,
+            _ => node,
+        };
+        let mut lowered = self.lower(gc, cur, path);
+        if is_scope {
+            let frame = self.frames.pop().unwrap();
+            if !frame.temps.is_empty() || !frame.prelude.is_empty() {
+                lowered = Some(add_frame(gc, lowered.unwrap_or(cur), frame));
+            }
+        }
+        match lowered {
+            Some(new_node) => TransformResult::Changed(new_node),
+            None => children,
+        }
+    }
+}
+
+impl<'gc> Lowerer<'_, 'gc> {
+    /// Return the lowered version of `node`, whose children have already been lowered,
+    /// or `None` if it doesn't contain destructuring.
+    fn lower(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> Option<&'gc Node<'gc>> {
+        match node {
+            Node::VariableDeclaration(decl) => {
+                let needs_lowering = decl.declarations.iter().any(|d| {
+                    matches!(
+                        d,
+                        Node::VariableDeclarator(VariableDeclarator { id, init: Some(_), .. })
+                            if is_destructuring(id)
+                    )
+                });
+                if !needs_lowering {
+                    return None;
+                }
+                let mut new_declarators = vec![];
+                for declarator in decl.declarations.iter() {
+                    match declarator {
+                        Node::VariableDeclarator(VariableDeclarator {
+                            id,
+                            init: Some(init),
+                            ..
+                        }) if is_destructuring(id) => {
+                            let mut bindings = vec![];
+                            self.lower_pattern(gc, id, init, Mode::Declare, &mut bindings);
+                            new_declarators.extend(declarators(gc, &bindings));
+                        }
+                        _ => new_declarators.push(declarator),
+                    }
+                }
+                Some(var_decl(
+                    gc,
+                    decl.kind,
+                    new_declarators,
+                    Some(&decl.metadata),
+                ))
+            }
+            Node::AssignmentExpression(AssignmentExpression {
+                metadata,
+                operator: AssignmentExpressionOperator::Assign,
+                left,
+                right,
+            }) if is_destructuring(left) => {
+                let mut bindings = vec![];
+                let src = self.reuse(gc, right, Mode::Assign, &mut bindings);
+                self.lower_pattern(gc, left, src, Mode::Assign, &mut bindings);
+                let mut exprs = assignments(gc, &bindings);
+                // The value of the assignment is only needed if it isn't a statement.
+                if !matches!(
+                    path,
+                    Some(Path {
+                        parent: Node::ExpressionStatement(_),
+                        ..
+                    })
+                ) {
+                    exprs.push(src);
+                }
+                Some(sequence(gc, exprs, Some(metadata)))
+            }
+            Node::ForInStatement(_) | Node::ForOfStatement(_) => {
+                let (left, body) = match node {
+                    Node::ForInStatement(ForInStatement { left, body, .. }) => (*left, *body),
+                    Node::ForOfStatement(for_of) => (for_of.left, for_of.body),
+                    _ => unreachable!(),
+                };
+                let (new_left, prefix) = match left {
+                    Node::VariableDeclaration(VariableDeclaration {
+                        metadata,
+                        kind,
+                        declarations,
+                    }) => {
+                        let id =
+                            match declarations.head() {
+                                Some(Node::VariableDeclarator(VariableDeclarator {
+                                    id, ..
+                                })) if is_destructuring(id) => id,
+                                _ => return None,
+                            };
+                        let temp = self.fresh_temp();
+                        let mut bindings = vec![];
+                        let src = builders::ident(gc, &temp);
+                        self.lower_pattern(gc, id, src, Mode::Declare, &mut bindings);
+                        let left = var_decl(
+                            gc,
+                            *kind,
+                            vec![declarator(gc, builders::ident(gc, &temp), None)],
+                            Some(metadata),
+                        );
+                        (left, var_decl(gc, *kind, declarators(gc, &bindings), None))
+                    }
+                    _ if is_destructuring(left) => {
+                        let temp = self.fresh_temp();
+                        self.frames.last_mut().unwrap().temps.push(temp.clone());
+                        let mut bindings = vec![];
+                        let src = builders::ident(gc, &temp);
+                        self.lower_pattern(gc, left, src, Mode::Assign, &mut bindings);
+                        let exprs = assignments(gc, &bindings);
+                        (
+                            builders::ident(gc, &temp),
+                            builders::expr_stmt(gc, sequence(gc, exprs, None)),
+                        )
+                    }
+                    _ => return None,
+                };
+                let new_body = prepend_to_block(gc, body, [prefix]);
+                Some(match node {
+                    Node::ForInStatement(n) => {
+                        let mut builder = builder::ForInStatement::from_node(n);
+                        builder.left(new_left);
+                        builder.body(new_body);
+                        builder.build_forced(gc)
+                    }
+                    Node::ForOfStatement(n) => {
+                        let mut builder = builder::ForOfStatement::from_node(n);
+                        builder.left(new_left);
+                        builder.body(new_body);
+                        builder.build_forced(gc)
+                    }
+                    _ => unreachable!(),
+                })
+            }
+            Node::CatchClause(
+                n @ CatchClause {
+                    param: Some(param),
+                    body,
+                    ..
+                },
+            ) if is_destructuring(param) => {
+                let temp = self.fresh_temp();
+                let mut bindings = vec![];
+                let src = builders::ident(gc, &temp);
+                self.lower_pattern(gc, param, src, Mode::Declare, &mut bindings);
+                let prefix = var_decl(
+                    gc,
+                    VariableDeclarationKind::Let,
+                    declarators(gc, &bindings),
+                    None,
+                );
+                let mut builder = builder::CatchClause::from_node(n);
+                builder.param(Some(builders::ident(gc, &temp)));
+                builder.body(prepend_to_block(gc, body, [prefix]));
+                Some(builder.build_forced(gc))
+            }
+            Node::FunctionDeclaration(_)
+            | Node::FunctionExpression(_)
+            | Node::ArrowFunctionExpression(_) => {
+                let new_params = self.lower_params(gc, &node.function_like_params())?;
+                Some(match node {
+                    Node::FunctionDeclaration(n) => {
+                        let mut builder = builder::FunctionDeclaration::from_node(n);
+                        builder.params(new_params);
+                        builder.build_forced(gc)
+                    }
+                    Node::FunctionExpression(n) => {
+                        let mut builder = builder::FunctionExpression::from_node(n);
+                        builder.params(new_params);
+                        builder.build_forced(gc)
+                    }
+                    Node::ArrowFunctionExpression(n) => {
+                        let mut builder = builder::ArrowFunctionExpression::from_node(n);
+                        builder.params(new_params);
+                        builder.build_forced(gc)
+                    }
+                    _ => unreachable!(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Replace the destructured parameters in `params` with temporaries,
+    /// adding their initialization to the prelude of the current frame.
+    /// Return `None` if no parameter is destructured.
+    fn lower_params(&mut self, gc: &'gc GCLock, params: &NodeList<'gc>) -> Option<NodeList<'gc>> {
+        let mut bindings = vec![];
+        let mut new_params = vec![];
+        for param in params.iter() {
+            let pattern = match param {
+                Node::RestElement(RestElement { argument, .. }) => argument,
+                _ => param,
+            };
+            if !is_destructuring(pattern) {
+                new_params.push(param);
+                continue;
+            }
+            let temp = self.fresh_temp();
+            let src = builders::ident(gc, &temp);
+            self.lower_pattern(gc, pattern, src, Mode::Declare, &mut bindings);
+            new_params.push(match param {
+                Node::RestElement(rest) => {
+                    let mut builder = builder::RestElement::from_node(rest);
+                    builder.argument(builders::ident(gc, &temp));
+                    builder.build_forced(gc)
+                }
+                _ => builders::ident(gc, &temp),
+            });
+        }
+        if bindings.is_empty() {
+            return None;
+        }
+        let decl = var_decl(
+            gc,
+            VariableDeclarationKind::Var,
+            declarators(gc, &bindings),
+            None,
+        );
+        self.frames.last_mut().unwrap().prelude.push(decl);
+        Some(NodeList::from_iter(gc, new_params))
+    }
+
+    /// Append to `out` the bindings which assign `value` to `target`.
+    fn lower_pattern(
+        &mut self,
+        gc: &'gc GCLock,
+        target: &'gc Node<'gc>,
+        value: &'gc Node<'gc>,
+        mode: Mode,
+        out: &mut Vec<Binding<'gc>>,
+    ) {
+        match target {
+            Node::AssignmentPattern(AssignmentPattern { left, right, .. }) => {
+                // `value === void 0 ? right : value`
+                let src = self.reuse(gc, value, mode, out);
+                let test = builder::BinaryExpression::build_template(
+                    gc,
+                    template::BinaryExpression {
+                        metadata: Default::default(),
+                        left: src,
+                        right: void_0(gc),
+                        operator: BinaryExpressionOperator::StrictEquals,
+                    },
+                );
+                let value = builder::ConditionalExpression::build_template(
+                    gc,
+                    template::ConditionalExpression {
+                        metadata: Default::default(),
+                        test,
+                        consequent: right,
+                        alternate: self.reuse(gc, src, mode, out),
+                    },
+                );
+                self.lower_pattern(gc, left, value, mode, out);
+            }
+            Node::ArrayPattern(ArrayPattern { elements, .. }) => {
+                let src = self.reuse(gc, value, mode, out);
+                for (i, elem) in elements.iter().enumerate() {
+                    match elem {
+                        Node::Empty(_) => {}
+                        Node::RestElement(RestElement { argument, .. }) => {
+                            // `src.slice(i)`
+                            let slice = builders::call(
+                                gc,
+                                builders::member(gc, self.reuse(gc, src, mode, out), "slice"),
+                                [builders::number(gc, i as f64)],
+                            );
+                            self.lower_pattern(gc, argument, slice, mode, out);
+                        }
+                        _ => {
+                            let index = builders::number(gc, i as f64);
+                            let elem_value =
+                                computed_member(gc, self.reuse(gc, src, mode, out), index);
+                            self.lower_pattern(gc, elem, elem_value, mode, out);
+                        }
+                    }
+                }
+            }
+            Node::ObjectPattern(ObjectPattern { properties, .. }) => {
+                let src = self.reuse(gc, value, mode, out);
+                let has_rest = properties
+                    .iter()
+                    .any(|prop| matches!(prop, Node::RestElement(_)));
+                // Keys of the properties before the rest element, as strings.
+                let mut excluded = vec![];
+                for prop in properties.iter() {
+                    match prop {
+                        Node::Property(Property {
+                            key,
+                            value: prop_value,
+                            computed,
+                            ..
+                        }) => {
+                            let object = self.reuse(gc, src, mode, out);
+                            let member = match key {
+                                Node::Identifier(Identifier { name, .. }) if !*computed => {
+                                    excluded.push(builders::string(gc, gc.str(*name)));
+                                    builders::member(gc, object, gc.str(*name))
+                                }
+                                Node::StringLiteral(_) => {
+                                    excluded.push(key);
+                                    computed_member(gc, object, key)
+                                }
+                                Node::NumericLiteral(NumericLiteral { value, .. }) => {
+                                    excluded.push(builders::string(gc, &number_to_string(*value)));
+                                    computed_member(gc, object, key)
+                                }
+                                _ if has_rest => {
+                                    // Evaluate the key once and convert it to a string
+                                    // for the rest element.
+                                    let key = self.reuse(gc, key, mode, out);
+                                    excluded.push(builder::BinaryExpression::build_template(
+                                        gc,
+                                        template::BinaryExpression {
+                                            metadata: Default::default(),
+                                            left: self.reuse(gc, key, mode, out),
+                                            right: builders::string(gc, ""),
+                                            operator: BinaryExpressionOperator::Plus,
+                                        },
+                                    ));
+                                    computed_member(gc, object, key)
+                                }
+                                _ => computed_member(gc, object, key),
+                            };
+                            self.lower_pattern(gc, prop_value, member, mode, out);
+                        }
+                        Node::RestElement(RestElement { argument, .. }) => {
+                            let callee = self.helpers.require(
+                                gc,
+                                HELPER_USER,
+                                OBJECT_WITHOUT_PROPERTIES.name,
+                            );
+                            let keys = builder::ArrayExpression::build_template(
+                                gc,
+                                template::ArrayExpression {
+                                    metadata: Default::default(),
+                                    elements: NodeList::from_iter(gc, excluded.iter().copied()),
+                                    trailing_comma: false,
+                                },
+                            );
+                            let rest =
+                                builders::call(gc, callee, [self.reuse(gc, src, mode, out), keys]);
+                            self.lower_pattern(gc, argument, rest, mode, out);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => out.push((target, value)),
+        }
+    }
+
+    /// Return an expression which evaluates to `value` and can be used more than once:
+    /// a copy of `value` if it's a temporary, otherwise a new temporary which is
+    /// initialized to `value` by a binding appended to `out`.
+    fn reuse(
+        &mut self,
+        gc: &'gc GCLock,
+        value: &'gc Node<'gc>,
+        mode: Mode,
+        out: &mut Vec<Binding<'gc>>,
+    ) -> &'gc Node<'gc> {
+        if let Node::Identifier(Identifier { name, .. }) = value {
+            if self.temps.contains(gc.str(*name)) {
+                return builders::ident(gc, gc.str(*name));
+            }
+        }
+        let temp = self.fresh_temp();
+        if mode == Mode::Assign {
+            self.frames.last_mut().unwrap().temps.push(temp.clone());
+        }
+        out.push((builders::ident(gc, &temp), value));
+        builders::ident(gc, &temp)
+    }
+
+    /// Return the name of a new temporary.
+    fn fresh_temp(&mut self) -> String {
+        loop {
+            let name = if self.next_temp == 1 {
+                TEMP_PREFIX.to_string()
+            } else {
+                format!("{}{}", TEMP_PREFIX, self.next_temp)
+            };
+            self.next_temp += 1;
+            if !self.names.contains(&name) {
+                self.temps.insert(name.clone());
+                return name;
+            }
+        }
+    }
+}
+
+/// Whether `node` is an object or array pattern, possibly with a default value.
+fn is_destructuring(node: &Node) -> bool {
+    match node {
+        Node::ObjectPattern(_) | Node::ArrayPattern(_) => true,
+        Node::AssignmentPattern(AssignmentPattern { left, .. }) => is_destructuring(left),
+        _ => false,
+    }
+}
+
+/// Whether `stmt` is a directive such as `'use strict'`.
+fn is_directive(stmt: &Node) -> bool {
+    matches!(
+        stmt,
+        Node::ExpressionStatement(ExpressionStatement {
+            directive: Some(_),
+            ..
+        }) | Node::Directive(_)
+    )
+}
+
+/// Add the declarations of `frame` to the start of the body of `node`,
+/// which is a function, a `Program` or a `Module`.
+fn add_frame<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>, frame: Frame<'gc>) -> &'gc Node<'gc> {
+    let mut stmts = vec![];
+    if !frame.temps.is_empty() {
+        let temps = frame
+            .temps
+            .iter()
+            .map(|name| declarator(gc, builders::ident(gc, name), None))
+            .collect();
+        stmts.push(var_decl(gc, VariableDeclarationKind::Var, temps, None));
+    }
+    stmts.extend(frame.prelude);
+
+    match node {
+        Node::Program(_) | Node::Module(_) => inject_prelude(gc, node, stmts),
+        Node::FunctionDeclaration(n) => {
+            let mut builder = builder::FunctionDeclaration::from_node(n);
+            builder.body(prepend_to_block(gc, n.body, stmts));
+            builder.build_forced(gc)
+        }
+        Node::FunctionExpression(n) => {
+            let mut builder = builder::FunctionExpression::from_node(n);
+            builder.body(prepend_to_block(gc, n.body, stmts));
+            builder.build_forced(gc)
+        }
+        Node::ArrowFunctionExpression(n) => {
+            let mut builder = builder::ArrowFunctionExpression::from_node(n);
+            if n.expression {
+                // `=> expr` becomes `=> { stmts; return expr; }`.
+                let ret = builder::ReturnStatement::build_template(
+                    gc,
+                    template::ReturnStatement {
+                        metadata: Default::default(),
+                        argument: Some(n.body),
+                    },
+                );
+                stmts.push(ret);
+                builder.body(builders::block(gc, stmts));
+                builder.expression(false);
+            } else {
+                builder.body(prepend_to_block(gc, n.body, stmts));
+            }
+            builder.build_forced(gc)
+        }
+        _ => unreachable!("Unexpected scope {:?}", node.variant()),
+    }
+}
+
+/// Return a block statement containing `stmts` followed by the statements of `body`,
+/// or by `body` itself if it isn't a block. Directives stay at the start of the block.
+fn prepend_to_block<'gc, I>(gc: &'gc GCLock, body: &'gc Node<'gc>, stmts: I) -> &'gc Node<'gc>
+where
+    I: IntoIterator<Item = &'gc Node<'gc>>,
+{
+    match body {
+        Node::BlockStatement(n) => {
+            let new_body = n
+                .body
+                .iter()
+                .take_while(|stmt| is_directive(stmt))
+                .chain(stmts)
+                .chain(n.body.iter().skip_while(|stmt| is_directive(stmt)))
+                .collect::<Vec<_>>();
+            let mut builder = builder::BlockStatement::from_node(n);
+            builder.body(NodeList::from_iter(gc, new_body));
+            builder.build_forced(gc)
+        }
+        _ => builders::block(gc, stmts.into_iter().chain([body])),
+    }
+}
+
+
+fn var_decl<'gc>(
+    gc: &'gc GCLock,
+    kind: VariableDeclarationKind,
+    declarators: Vec<&'gc Node<'gc>>,
+    metadata: Option<&NodeMetadata<'gc>>,
+) -> &'gc Node<'gc> {
+    builder::VariableDeclaration::build_template(
+        gc,
+        template::VariableDeclaration {
+            metadata: metadata.map(Into::into).unwrap_or_default(),
+            kind,
+            declarations: NodeList::from_iter(gc, declarators),
+        },
+    )
+}
+
+fn declarator<'gc>(
+    gc: &'gc GCLock,
+    id: &'gc Node<'gc>,
+    init: Option<&'gc Node<'gc>>,
+) -> &'gc Node<'gc> {
+    builder::VariableDeclarator::build_template(
+        gc,
+        template::VariableDeclarator {
+            metadata: Default::default(),
+            init,
+            id,
+        },
+    )
+}
+
+/// A declarator for each binding.
+fn declarators<'gc>(gc: &'gc GCLock, bindings: &[Binding<'gc>]) -> Vec<&'gc Node<'gc>> {
+    bindings
+        .iter()
+        .map(|&(id, init)| declarator(gc, id, Some(init)))
+        .collect()
+}
+
+/// An assignment expression for each binding.
+fn assignments<'gc>(gc: &'gc GCLock, bindings: &[Binding<'gc>]) -> Vec<&'gc Node<'gc>> {
+    bindings
+        .iter()
+        .map(|&(left, right)| {
+            builder::AssignmentExpression::build_template(
+                gc,
+                template::AssignmentExpression {
+                    metadata: Default::default(),
+                    operator: AssignmentExpressionOperator::Assign,
+                    left,
+                    right,
+                },
+            )
+        })
+        .collect()
+}
+
+/// `exprs` as a single expression: a sequence unless there is only one.
+fn sequence<'gc>(
+    gc: &'gc GCLock,
+    mut exprs: Vec<&'gc Node<'gc>>,
+    metadata: Option<&NodeMetadata<'gc>>,
+) -> &'gc Node<'gc> {
+    if exprs.len() == 1 {
+        return exprs.pop().unwrap();
+    }
+    builder::SequenceExpression::build_template(
+        gc,
+        template::SequenceExpression {
+            metadata: metadata.map(Into::into).unwrap_or_default(),
+            expressions: NodeList::from_iter(gc, exprs),
+        },
+    )
+}
+
+/// `object[property]`
+fn computed_member<'gc>(
+    gc: &'gc GCLock,
+    object: &'gc Node<'gc>,
+    property: &'gc Node<'gc>,
+) -> &'gc Node<'gc> {
+    builder::MemberExpression::build_template(
+        gc,
+        template::MemberExpression {
+            metadata: Default::default(),
+            object,
+            property,
+            computed: true,
+        },
+    )
+}
+
+/// `void 0`
+fn void_0<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+    builder::UnaryExpression::build_template(
+        gc,
+        template::UnaryExpression {
+            metadata: Default::default(),
+            operator: UnaryExpressionOperator::Void,
+            argument: builders::number(gc, 0.0),
+            prefix: true,
+        },
+    )
+}
+
+/// ```js
+/// function _objectWithoutProperties(source, excluded) {
+///   var target = {};
+///   for (var key in source) {
+///     if (Object.prototype.hasOwnProperty.call(source, key) && excluded.indexOf(key) < 0) {
+///       target[key] = source[key];
+///     }
+///   }
+///   return target;
+/// }
+/// ```
+fn build_object_without_properties<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+    let id = |name| builders::ident(gc, name);
+    let has_own = builders::call(
+        gc,
+        builders::member(
+            gc,
+            builders::member(
+                gc,
+                builders::member(gc, id("Object"), "prototype"),
+                "hasOwnProperty",
+            ),
+            "call",
+        ),
+        [id("source"), id("key")],
+    );
+    let not_excluded = builder::BinaryExpression::build_template(
+        gc,
+        template::BinaryExpression {
+            metadata: Default::default(),
+            left: builders::call(
+                gc,
+                builders::member(gc, id("excluded"), "indexOf"),
+                [id("key")],
+            ),
+            right: builders::number(gc, 0.0),
+            operator: BinaryExpressionOperator::Less,
+        },
+    );
+    let test = builder::LogicalExpression::build_template(
+        gc,
+        template::LogicalExpression {
+            metadata: Default::default(),
+            left: has_own,
+            right: not_excluded,
+            operator: LogicalExpressionOperator::And,
+        },
+    );
+    let copy = builder::AssignmentExpression::build_template(
+        gc,
+        template::AssignmentExpression {
+            metadata: Default::default(),
+            operator: AssignmentExpressionOperator::Assign,
+            left: computed_member(gc, id("target"), id("key")),
+            right: computed_member(gc, id("source"), id("key")),
+        },
+    );
+    let if_stmt = builder::IfStatement::build_template(
+        gc,
+        template::IfStatement {
+            metadata: Default::default(),
+            test,
+            consequent: builders::block(gc, [builders::expr_stmt(gc, copy)]),
+            alternate: None,
+        },
+    );
+    let for_in = builder::ForInStatement::build_template(
+        gc,
+        template::ForInStatement {
+            metadata: Default::default(),
+            left: var_decl(
+                gc,
+                VariableDeclarationKind::Var,
+                vec![declarator(gc, id("key"), None)],
+                None,
+            ),
+            right: id("source"),
+            body: builders::block(gc, [if_stmt]),
+        },
+    );
+    let empty_object = builder::ObjectExpression::build_template(
+        gc,
+        template::ObjectExpression {
+            metadata: Default::default(),
+            properties: NodeList::new(gc),
+        },
+    );
+    let body = vec![
+        var_decl(
+            gc,
+            VariableDeclarationKind::Var,
+            vec![declarator(gc, id("target"), Some(empty_object))],
+            None,
+        ),
+        for_in,
+        builder::ReturnStatement::build_template(
+            gc,
+            template::ReturnStatement {
+                metadata: Default::default(),
+                argument: Some(id("target")),
+            },
+        ),
+    ];
+    builder::FunctionDeclaration::build_template(
+        gc,
+        template::FunctionDeclaration {
+            metadata: Default::default(),
+            id: Some(id(OBJECT_WITHOUT_PROPERTIES.name)),
+            params: NodeList::from_iter(gc, [id("source"), id("excluded")]),
+            body: builders::block(gc, body),
+            type_parameters: None,
+            return_type: None,
+            predicate: None,
+            generator: false,
+            is_async: false,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::HelperMode;
+    use crate::test_util::gen;
+
+    fn object_pattern<'gc>(gc: &'gc GCLock, properties: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::ObjectPattern::build_template(
+            gc,
+            template::ObjectPattern {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(gc, properties),
+                type_annotation: None,
+            },
+        )
+    }
+
+    fn array_pattern<'gc>(gc: &'gc GCLock, elements: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::ArrayPattern::build_template(
+            gc,
+            template::ArrayPattern {
+                metadata: Default::default(),
+                elements: NodeList::from_iter(gc, elements),
+                type_annotation: None,
+            },
+        )
+    }
+
+    /// `key: value`, or `key` if `value` is `None`.
+    fn property<'gc>(gc: &'gc GCLock, key: &str, value: Option<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builder::Property::build_template(
+            gc,
+            template::Property {
+                metadata: Default::default(),
+                key: builders::ident(gc, key),
+                value: value.unwrap_or_else(|| builders::ident(gc, key)),
+                kind: PropertyKind::Init,
+                computed: false,
+                method: false,
+                shorthand: value.is_none(),
+            },
+        )
+    }
+
+    fn rest<'gc>(gc: &'gc GCLock, argument: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::RestElement::build_template(
+            gc,
+            template::RestElement {
+                metadata: Default::default(),
+                argument,
+            },
+        )
+    }
+
+    fn empty<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+        builder::Empty::build_template(
+            gc,
+            template::Empty {
+                metadata: Default::default(),
+            },
+        )
+    }
+
+    /// Run the pass on a `Program` containing `body`, inject the helpers it requires
+    /// and return the generated JS.
+    fn run_pass<F>(body: F) -> String
+    where
+        F: for<'gc> FnOnce(&'gc GCLock) -> Vec<&'gc Node<'gc>>,
+    {
+        let mut ctx = Context::new();
+        let helpers = Rc::new(RefCell::new(Helpers::new(HelperMode::Inline)));
+        let mut pass = Destructuring::new(Rc::clone(&helpers));
+        let root = {
+            let gc = GCLock::new(&mut ctx);
+            let program = builders::program(&gc, body(&gc));
+            let result = match pass.run(&gc, program) {
+                TransformResult::Changed(new_node) => new_node,
+                _ => program,
+            };
+            NodeRc::from_node(&gc, helpers.borrow_mut().inject(&gc, result))
+        };
+        gen(&mut ctx, &root)
+    }
+
+    #[test]
+    fn test_declaration() {
+        // var {a, b: [c = 1], ...rest} = obj;
+        let js = run_pass(|gc| {
+            let c = builder::AssignmentPattern::build_template(
+                gc,
+                template::AssignmentPattern {
+                    metadata: Default::default(),
+                    left: builders::ident(gc, "c"),
+                    right: builders::number(gc, 1.0),
+                },
+            );
+            let pattern = object_pattern(
+                gc,
+                vec![
+                    property(gc, "a", None),
+                    property(gc, "b", Some(array_pattern(gc, vec![c]))),
+                    rest(gc, builders::ident(gc, "rest")),
+                ],
+            );
+            vec![var_decl(
+                gc,
+                VariableDeclarationKind::Var,
+                vec![declarator(gc, pattern, Some(builders::ident(gc, "obj")))],
+                None,
+            )]
+        });
+        assert_eq!(
+            js,
+            "function _objectWithoutProperties(source,excluded){var target={};\
+             for(var key in source){if(Object.prototype.hasOwnProperty.call(source,key)&&\
+             excluded.indexOf(key)<0){target[key]=source[key];}}return target;}\
+             var _ref=obj,a=_ref.a,_ref2=_ref.b,_ref3=_ref2[0],c=_ref3===void 0?1:_ref3,\
+             rest=_objectWithoutProperties(_ref,['a','b']);\n"
+        );
+    }
+
+    #[test]
+    fn test_assignment_and_params() {
+        // function f({x}, ...[y]) {
+        //   [x, , ...y] = g();
+        //   return h([_ref] = y);
+        // }
+        let js = run_pass(|gc| {
+            let assign = |left, right| {
+                builder::AssignmentExpression::build_template(
+                    gc,
+                    template::AssignmentExpression {
+                        metadata: Default::default(),
+                        operator: AssignmentExpressionOperator::Assign,
+                        left,
+                        right,
+                    },
+                )
+            };
+            let stmt = builders::expr_stmt(
+                gc,
+                assign(
+                    array_pattern(
+                        gc,
+                        vec![
+                            builders::ident(gc, "x"),
+                            empty(gc),
+                            rest(gc, builders::ident(gc, "y")),
+                        ],
+                    ),
+                    builders::call(gc, builders::ident(gc, "g"), []),
+                ),
+            );
+            let nested = assign(
+                array_pattern(gc, vec![builders::ident(gc, "_ref")]),
+                builders::ident(gc, "y"),
+            );
+            let ret = builder::ReturnStatement::build_template(
+                gc,
+                template::ReturnStatement {
+                    metadata: Default::default(),
+                    argument: Some(builders::call(gc, builders::ident(gc, "h"), [nested])),
+                },
+            );
+            let params = [
+                object_pattern(gc, vec![property(gc, "x", None)]),
+                rest(gc, array_pattern(gc, vec![builders::ident(gc, "y")])),
+            ];
+            vec![builder::FunctionDeclaration::build_template(
+                gc,
+                template::FunctionDeclaration {
+                    metadata: Default::default(),
+                    id: Some(builders::ident(gc, "f")),
+                    params: NodeList::from_iter(gc, params),
+                    body: builders::block(gc, [stmt, ret]),
+                    type_parameters: None,
+                    return_type: None,
+                    predicate: None,
+                    generator: false,
+                    is_async: false,
+                },
+            )]
+        });
+        assert_eq!(
+            js,
+            "function f(_ref4,..._ref5){var _ref2,_ref3;var x=_ref4.x,y=_ref5[0];\
+             (_ref2=g(),x=_ref2[0],y=_ref2.slice(2));return h(((_ref3=y,_ref=_ref3[0],_ref3)));}\n"
+        );
+    }
+
+    #[test]
+    fn test_for_of() {
+        // for (const [k, v] of m) k;
+        let js = run_pass(|gc| {
+            let pattern =
+                array_pattern(gc, vec![builders::ident(gc, "k"), builders::ident(gc, "v")]);
+            vec![builder::ForOfStatement::build_template(
+                gc,
+                template::ForOfStatement {
+                    metadata: Default::default(),
+                    left: var_decl(
+                        gc,
+                        VariableDeclarationKind::Const,
+                        vec![declarator(gc, pattern, None)],
+                        None,
+                    ),
+                    right: builders::ident(gc, "m"),
+                    body: builders::expr_stmt(gc, builders::ident(gc, "k")),
+                    is_await: false,
+                },
+            )]
+        });
+        assert_eq!(js, "for(const _ref of m){const k=_ref[0],v=_ref[1];k;}\n");
+    }
+}
@@ -0,0 +1,498 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass to transform JSX into calls, using React's classic runtime.
+//!
+//! For example, transforms
+//! ```js
+//! <View style={s} {...props}>
+//!   Hello {name}
+//!   <>{items}</>
+//! </View>
+//! ```
+//! into
+//! ```js
+//! React.createElement(View, {style: s, ...props}, 'Hello ', name,
+//!   React.createElement(React.Fragment, null, items));
+//! ```
+//! Like Babel, tags starting with a lowercase letter or containing `-` are intrinsic
+//! elements, passed as strings, and the whitespace of text is collapsed: lines are trimmed
+//! and non-empty lines are joined with a space. Attributes are spread with object spread.
+
+use juno::ast::*;
+use juno_support::ident::is_identifier_name;
+
+use crate::Pass;
+
+/// Function creating elements, by default.
+pub const DEFAULT_PRAGMA: &str = "React.createElement";
+
+/// Component of fragments, by default.
+pub const DEFAULT_PRAGMA_FRAG: &str = "React.Fragment";
+
+pub struct Jsx {
+    /// Function creating elements, as a dotted path.
+    pragma: String,
+
+    /// Component of fragments, as a dotted path.
+    pragma_frag: String,
+}
+
+impl Default for Jsx {
+    fn default() -> Self {
+        Self::with_pragma(DEFAULT_PRAGMA, DEFAULT_PRAGMA_FRAG)
+    }
+}
+
+impl Jsx {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create the pass with the function creating elements `pragma`, e.g. `h`,
+    /// and the component of fragments `pragma_frag`, e.g. `Fragment`.
+    pub fn with_pragma(pragma: &str, pragma_frag: &str) -> Self {
+        Self {
+            pragma: pragma.to_string(),
+            pragma_frag: pragma_frag.to_string(),
+        }
+    }
+}
+
+impl Pass for Jsx {
+    fn name(&self) -> &'static str {
+        "Transform JSX"
+    }
+    fn description(&self) -> &'static str {
+        "Replaces JSX with calls creating the elements"
+    }
+
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Jsx {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            Node::JSXElement(_) | Node::JSXFragment(_) => {
+                TransformResult::Changed(self.element(gc, node))
+            }
+            _ => node.visit_children_mut(gc, self),
+        }
+    }
+}
+
+impl Jsx {
+    /// Transform the JSX in the expression `node`.
+    fn expr<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        match VisitorMut::call(self, gc, node, None) {
+            TransformResult::Changed(new_node) => new_node,
+            _ => node,
+        }
+    }
+
+    /// The call creating the element or fragment `node`.
+    fn element<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        let (metadata, element_type, props, children) = match node {
+            Node::JSXElement(JSXElement {
+                metadata,
+                opening_element,
+                children,
+                ..
+            }) => {
+                let opening = node_cast!(Node::JSXOpeningElement, opening_element);
+                let props = self.props(gc, &opening.attributes);
+                (metadata, element_type(gc, opening.name), props, children)
+            }
+            Node::JSXFragment(JSXFragment {
+                metadata, children, ..
+            }) => (
+                metadata,
+                dotted_path(gc, &self.pragma_frag),
+                builders::null(gc),
+                children,
+            ),
+            _ => unreachable!("not JSX"),
+        };
+        let mut arguments = vec![element_type, props];
+        for child in children.iter() {
+            match child {
+                Node::JSXText(JSXText { value, .. }) => {
+                    let text = clean_text(&String::from_utf16_lossy(gc.str_u16(*value)));
+                    if !text.is_empty() {
+                        arguments.push(builders::string(gc, &text));
+                    }
+                }
+                Node::JSXExpressionContainer(JSXExpressionContainer {
+                    expression: Node::JSXEmptyExpression(_),
+                    ..
+                }) => {}
+                Node::JSXExpressionContainer(JSXExpressionContainer { expression, .. }) => {
+                    arguments.push(self.expr(gc, expression));
+                }
+                Node::JSXSpreadChild(JSXSpreadChild { expression, .. }) => {
+                    arguments.push(builder::SpreadElement::build_template(
+                        gc,
+                        template::SpreadElement {
+                            metadata: Default::default(),
+                            argument: self.expr(gc, expression),
+                        },
+                    ));
+                }
+                _ => arguments.push(self.element(gc, child)),
+            }
+        }
+        builder::CallExpression::build_template(
+            gc,
+            template::CallExpression {
+                metadata: metadata.into(),
+                callee: dotted_path(gc, &self.pragma),
+                type_arguments: None,
+                arguments: NodeList::from_iter(gc, arguments),
+            },
+        )
+    }
+
+    /// The props object of `attributes`, or `null` if there are none.
+    fn props<'gc>(&mut self, gc: &'gc GCLock, attributes: &NodeList<'gc>) -> &'gc Node<'gc> {
+        if attributes.is_empty() {
+            return builders::null(gc);
+        }
+        let mut properties = vec![];
+        for attr in attributes.iter() {
+            match attr {
+                Node::JSXAttribute(JSXAttribute {
+                    metadata,
+                    name,
+                    value,
+                }) => {
+                    let name = jsx_name(gc, name);
+                    let key = if is_identifier_name(&name) {
+                        builders::ident(gc, &name)
+                    } else {
+                        builders::string(gc, &name)
+                    };
+                    let value = match value {
+                        None => builders::boolean(gc, true),
+                        Some(Node::JSXStringLiteral(JSXStringLiteral { value, .. })) => {
+                            let value = String::from_utf16_lossy(gc.str_u16(*value));
+                            builders::string(gc, &collapse_newlines(&value))
+                        }
+                        Some(Node::JSXExpressionContainer(JSXExpressionContainer {
+                            expression,
+                            ..
+                        })) => self.expr(gc, expression),
+                        Some(value) => self.expr(gc, value),
+                    };
+                    properties.push(builder::Property::build_template(
+                        gc,
+                        template::Property {
+                            metadata: metadata.into(),
+                            key,
+                            value,
+                            kind: PropertyKind::Init,
+                            computed: false,
+                            method: false,
+                            shorthand: false,
+                        },
+                    ));
+                }
+                Node::JSXSpreadAttribute(JSXSpreadAttribute { metadata, argument }) => {
+                    properties.push(builder::SpreadElement::build_template(
+                        gc,
+                        template::SpreadElement {
+                            metadata: metadata.into(),
+                            argument: self.expr(gc, argument),
+                        },
+                    ));
+                }
+                _ => {}
+            }
+        }
+        builder::ObjectExpression::build_template(
+            gc,
+            template::ObjectExpression {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(gc, properties),
+            },
+        )
+    }
+}
+
+/// The name `name` of a JSX tag or attribute as a string, like `a`, `a.b` or `a:b`.
+fn jsx_name(gc: &GCLock, name: &Node) -> String {
+    match name {
+        Node::JSXIdentifier(JSXIdentifier { name, .. }) => gc.str(*name).to_string(),
+        Node::JSXMemberExpression(JSXMemberExpression {
+            object, property, ..
+        }) => format!("{}.{}", jsx_name(gc, object), jsx_name(gc, property)),
+        Node::JSXNamespacedName(JSXNamespacedName {
+            namespace, name, ..
+        }) => format!("{}:{}", jsx_name(gc, namespace), jsx_name(gc, name)),
+        _ => unreachable!("not a JSX name"),
+    }
+}
+
+/// The type of the element whose tag is `name`: a string for intrinsic elements, otherwise
+/// the expression referring to the component.
+fn element_type<'gc>(gc: &'gc GCLock, name: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    match name {
+        Node::JSXIdentifier(JSXIdentifier { name, .. }) => {
+            let name = gc.str(*name);
+            if name.starts_with(|c: char| c.is_ascii_lowercase()) || name.contains('-') {
+                builders::string(gc, name)
+            } else {
+                dotted_path(gc, name)
+            }
+        }
+        Node::JSXMemberExpression(_) => dotted_path(gc, &jsx_name(gc, name)),
+        _ => builders::string(gc, &jsx_name(gc, name)),
+    }
+}
+
+/// The expression `path`, like `React.createElement`, where `this` may start the path.
+fn dotted_path<'gc>(gc: &'gc GCLock, path: &str) -> &'gc Node<'gc> {
+    let mut parts = path.split('.');
+    let first = parts.next().unwrap_or_default();
+    let object = if first == "this" {
+        builder::ThisExpression::build_template(
+            gc,
+            template::ThisExpression {
+                metadata: Default::default(),
+            },
+        )
+    } else {
+        builders::ident(gc, first)
+    };
+    parts.fold(object, |object, name| builders::member(gc, object, name))
+}
+
+/// Collapse the whitespace of the text `text` of a JSX child: tabs are spaces,
+/// every line is trimmed, except the start of the first and the end of the last,
+/// and the non-empty lines are joined with a space.
+fn clean_text(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    // `lines` ignores a final newline, which ends a last line made of whitespace.
+    let last = if text.ends_with('\n') {
+        lines.len()
+    } else {
+        lines.len().saturating_sub(1)
+    };
+    let last_non_empty = lines
+        .iter()
+        .rposition(|line| line.contains(|c| c != ' ' && c != '\t'));
+    let mut result = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let mut line = line.replace('\t', " ");
+        if i != 0 {
+            line = line.trim_start_matches(' ').to_string();
+        }
+        if i != last {
+            line = line.trim_end_matches(' ').to_string();
+        }
+        if !line.is_empty() {
+            result.push_str(&line);
+            if Some(i) != last_non_empty {
+                result.push(' ');
+            }
+        }
+    }
+    result
+}
+
+/// Replace every line break of an attribute string followed by whitespace, along with the
+/// whitespace, with a space.
+fn collapse_newlines(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' && chars.peek().iter().any(|c| c.is_whitespace()) {
+            while chars.peek().iter().any(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            result.push(' ');
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run_on;
+
+    fn jsx_ident<'gc>(gc: &'gc GCLock, name: &str) -> &'gc Node<'gc> {
+        builder::JSXIdentifier::build_template(
+            gc,
+            template::JSXIdentifier {
+                metadata: Default::default(),
+                name: gc.atom(name),
+            },
+        )
+    }
+
+    fn text<'gc>(gc: &'gc GCLock, value: &str) -> &'gc Node<'gc> {
+        builder::JSXText::build_template(
+            gc,
+            template::JSXText {
+                metadata: Default::default(),
+                value: gc.atom_u16(value.encode_utf16().collect::<Vec<_>>()),
+                raw: gc.atom(value),
+            },
+        )
+    }
+
+    fn container<'gc>(gc: &'gc GCLock, expression: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::JSXExpressionContainer::build_template(
+            gc,
+            template::JSXExpressionContainer {
+                metadata: Default::default(),
+                expression,
+            },
+        )
+    }
+
+    fn element<'gc>(
+        gc: &'gc GCLock,
+        name: &str,
+        attributes: Vec<&'gc Node<'gc>>,
+        children: Vec<&'gc Node<'gc>>,
+    ) -> &'gc Node<'gc> {
+        builder::JSXElement::build_template(
+            gc,
+            template::JSXElement {
+                metadata: Default::default(),
+                opening_element: builder::JSXOpeningElement::build_template(
+                    gc,
+                    template::JSXOpeningElement {
+                        metadata: Default::default(),
+                        name: jsx_ident(gc, name),
+                        attributes: NodeList::from_iter(gc, attributes),
+                        self_closing: children.is_empty(),
+                        type_arguments: None,
+                    },
+                ),
+                closing_element: None,
+                children: NodeList::from_iter(gc, children),
+            },
+        )
+    }
+
+    fn attribute<'gc>(
+        gc: &'gc GCLock,
+        name: &str,
+        value: Option<&'gc Node<'gc>>,
+    ) -> &'gc Node<'gc> {
+        builder::JSXAttribute::build_template(
+            gc,
+            template::JSXAttribute {
+                metadata: Default::default(),
+                name: jsx_ident(gc, name),
+                value,
+            },
+        )
+    }
+
+    #[test]
+    fn test_element() {
+        // <View style={s} data-id="a" hidden>
+        //   Hello {name}
+        //   <b>{}</b>
+        // </View>
+        let js = run_on(&mut Jsx::new(), |gc| {
+            let style = container(gc, builders::ident(gc, "s"));
+            let data_id = builder::JSXStringLiteral::build_template(
+                gc,
+                template::JSXStringLiteral {
+                    metadata: Default::default(),
+                    value: gc.atom_u16("a".encode_utf16().collect::<Vec<_>>()),
+                    raw: gc.atom("\"a\""),
+                },
+            );
+            let empty = container(
+                gc,
+                builder::JSXEmptyExpression::build_template(
+                    gc,
+                    template::JSXEmptyExpression {
+                        metadata: Default::default(),
+                    },
+                ),
+            );
+            let view = element(
+                gc,
+                "View",
+                vec![
+                    attribute(gc, "style", Some(style)),
+                    attribute(gc, "data-id", Some(data_id)),
+                    attribute(gc, "hidden", None),
+                ],
+                vec![
+                    text(gc, "\n  Hello "),
+                    container(gc, builders::ident(gc, "name")),
+                    text(gc, "\n  "),
+                    element(gc, "b", vec![], vec![empty]),
+                    text(gc, "\n"),
+                ],
+            );
+            builders::program(gc, [builders::expr_stmt(gc, view)])
+        });
+        assert_eq!(
+            js,
+            "React.createElement(View,{style:s,'data-id':'a',hidden:true},'Hello ',name,\
+            (React.createElement('b',null)));\n"
+        );
+    }
+
+    #[test]
+    fn test_pragma() {
+        // <>text</>
+        let js = run_on(&mut Jsx::with_pragma("h", "Fragment"), |gc| {
+            let fragment = builder::JSXFragment::build_template(
+                gc,
+                template::JSXFragment {
+                    metadata: Default::default(),
+                    opening_fragment: builder::JSXOpeningFragment::build_template(
+                        gc,
+                        template::JSXOpeningFragment {
+                            metadata: Default::default(),
+                        },
+                    ),
+                    children: NodeList::from_iter(gc, [text(gc, "text")]),
+                    closing_fragment: builder::JSXClosingFragment::build_template(
+                        gc,
+                        template::JSXClosingFragment {
+                            metadata: Default::default(),
+                        },
+                    ),
+                },
+            );
+            builders::program(gc, [builders::expr_stmt(gc, fragment)])
+        });
+        assert_eq!(js, "h(Fragment,null,'text');\n");
+    }
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(clean_text("  a  "), "  a  ");
+        assert_eq!(clean_text("\n  a\n  b  \n"), "a b");
+        assert_eq!(clean_text("a \n\n  b"), "a b");
+        assert_eq!(clean_text("\n   \n"), "");
+        assert_eq!(collapse_newlines("a\n    b\nc"), "a b\nc");
+    }
+}
@@ -0,0 +1,503 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass expanding macros: calls of configured functions which are rewritten at compile time,
+//! like Babel macros.
+//!
+//! A macro is a name, like `invariant` or `graphql`, or a dotted path, like `console.assert`.
+//! Calls of the name, like `invariant(x, 'message')`, and tagged templates with it as a tag,
+//! like ``graphql`query { viewer }` ``, are expanded by the handler of the macro, which is
+//! either:
+//! - a Rust callback, which receives the call as a [`MacroCall`] and returns a
+//!   [`MacroExpansion`];
+//! - a template, an expression in which the identifiers `$0`, `$1`... are replaced with the
+//!   arguments of the call, or the template literal of a tagged template, and by `void 0`
+//!   if the call has fewer arguments. For example, with the template `$0 || fail($1)`,
+//!   `invariant(x, 'message')` becomes `x || fail('message')`;
+//! - a removal. Calls which are expression statements are removed, and the others are
+//!   replaced with `void 0`, so `__DEV_ASSERT__(x);` disappears.
+//!
+//! Names are matched syntactically, regardless of the bindings in scope.
+//! Macros in the arguments of a call are expanded before the call, but the expansion of a
+//! call isn't expanded again, so macros can't loop.
+//!
+//! The pass holds the templates as [`NodeRc`], so it must be dropped before the
+//! [`Context`] it was created with.
+
+use std::collections::HashMap;
+
+use juno::ast::*;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::hparser::SnippetGoal;
+use thiserror::Error;
+
+use crate::codemod::Substitute;
+use crate::Pass;
+
+#[derive(Debug, Error)]
+pub enum MacroError {
+    #[error("invalid macro template: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("metavariable '{0}' in the template is not an argument index")]
+    InvalidMetavariable(String),
+}
+
+/// A call of a macro.
+pub struct MacroCall<'gc> {
+    /// The `CallExpression` or `TaggedTemplateExpression`.
+    pub node: &'gc Node<'gc>,
+
+    /// Name of the macro.
+    pub name: String,
+
+    /// The arguments of a call, or the template literal of a tagged template.
+    pub arguments: Vec<&'gc Node<'gc>>,
+}
+
+impl<'gc> MacroCall<'gc> {
+    /// Whether the macro is the tag of a template.
+    pub fn is_tagged(&self) -> bool {
+        matches!(self.node, Node::TaggedTemplateExpression(_))
+    }
+
+    /// The cooked strings of the template literal of a tagged template.
+    /// Strings with invalid escapes are empty.
+    pub fn quasis(&self, gc: &'gc GCLock) -> Vec<String> {
+        match self.node {
+            Node::TaggedTemplateExpression(TaggedTemplateExpression {
+                quasi: Node::TemplateLiteral(TemplateLiteral { quasis, .. }),
+                ..
+            }) => quasis
+                .iter()
+                .map(|quasi| match quasi {
+                    Node::TemplateElement(TemplateElement {
+                        cooked: Some(cooked),
+                        ..
+                    }) => String::from_utf16_lossy(gc.str_u16(*cooked)),
+                    _ => String::new(),
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// What a callback does with a call of its macro.
+pub enum MacroExpansion<'gc> {
+    /// Leave the call unchanged.
+    Keep,
+
+    /// Replace the call with an expression.
+    Replace(&'gc Node<'gc>),
+
+    /// Remove the call.
+    Remove,
+}
+
+/// A callback expanding calls of a macro.
+pub type MacroCallback =
+    Box<dyn for<'gc> FnMut(&'gc GCLock, &MacroCall<'gc>) -> MacroExpansion<'gc>>;
+
+enum Handler {
+    Callback(MacroCallback),
+    Template(NodeRc),
+    Remove,
+}
+
+#[derive(Default)]
+pub struct Macros {
+    handlers: HashMap<String, Handler>,
+
+    /// Number of calls expanded so far.
+    num_expansions: usize,
+
+    /// Set when the call which is the expression of the current statement is removed.
+    remove_statement: bool,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Expand the calls of `name` with `callback`.
+    pub fn add_callback<F>(&mut self, name: &str, callback: F)
+    where
+        F: for<'gc> FnMut(&'gc GCLock, &MacroCall<'gc>) -> MacroExpansion<'gc> + 'static,
+    {
+        self.handlers
+            .insert(name.to_string(), Handler::Callback(Box::new(callback)));
+    }
+
+    /// Replace the calls of `name` with `template`.
+    pub fn add_template(
+        &mut self,
+        ctx: &mut Context,
+        name: &str,
+        template: NodeRc,
+    ) -> Result<(), MacroError> {
+        {
+            let gc = GCLock::new(ctx);
+            for (node, _, _) in iter_preorder(&gc, template.node(&gc)) {
+                if let Node::Identifier(Identifier { name, .. }) = node {
+                    let name = gc.str(*name);
+                    if name.starts_with('$') && metavariable_index(name).is_none() {
+                        return Err(MacroError::InvalidMetavariable(name.to_string()));
+                    }
+                }
+            }
+        }
+        self.handlers
+            .insert(name.to_string(), Handler::Template(template));
+        Ok(())
+    }
+
+    /// Parse `template` as an expression and replace the calls of `name` with it.
+    /// If `template` is blank, the calls are removed.
+    pub fn add_template_from_source(
+        &mut self,
+        ctx: &mut Context,
+        name: &str,
+        template: &str,
+    ) -> Result<(), MacroError> {
+        if template.trim().is_empty() {
+            self.add_removal(name);
+            return Ok(());
+        }
+        let template =
+            hparser::parse_snippet(ctx, Default::default(), SnippetGoal::Expression, template)?;
+        self.add_template(ctx, name, template)
+    }
+
+    /// Remove the calls of `name`.
+    pub fn add_removal(&mut self, name: &str) {
+        self.handlers.insert(name.to_string(), Handler::Remove);
+    }
+
+    /// Number of calls expanded so far, over every run of the pass.
+    pub fn num_expansions(&self) -> usize {
+        self.num_expansions
+    }
+
+    /// Expand `node` if it is a call of a macro.
+    /// Returns `None` if it isn't, or if it is left unchanged.
+    fn expand<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+    ) -> Option<MacroExpansion<'gc>> {
+        let (callee, arguments) = match node {
+            Node::CallExpression(CallExpression {
+                callee, arguments, ..
+            }) => (*callee, arguments.iter().collect()),
+            Node::TaggedTemplateExpression(TaggedTemplateExpression { tag, quasi, .. }) => {
+                (*tag, vec![*quasi])
+            }
+            _ => return None,
+        };
+        let name = callee_name(gc, callee)?;
+        let handler = self.handlers.get_mut(&name)?;
+        let call = MacroCall {
+            node,
+            name,
+            arguments,
+        };
+        let expansion = match handler {
+            Handler::Callback(callback) => callback(gc, &call),
+            Handler::Template(template) => {
+                let template = template.node(gc);
+                let mut bindings = HashMap::new();
+                for (node, _, _) in iter_preorder(gc, template) {
+                    if let Node::Identifier(Identifier { name, .. }) = node {
+                        if let Some(index) = metavariable_index(gc.str(*name)) {
+                            let value = call.arguments.get(index).copied();
+                            bindings.insert(*name, value.unwrap_or_else(|| void_0(gc)));
+                        }
+                    }
+                }
+                let template = deep_clone(gc, template);
+                MacroExpansion::Replace(
+                    template
+                        .visit_mut(gc, &mut Substitute { bindings }, None)
+                        .unwrap(),
+                )
+            }
+            Handler::Remove => MacroExpansion::Remove,
+        };
+        match expansion {
+            MacroExpansion::Keep => None,
+            expansion => {
+                self.num_expansions += 1;
+                Some(expansion)
+            }
+        }
+    }
+}
+
+impl Pass for Macros {
+    fn name(&self) -> &'static str {
+        "Macros"
+    }
+    fn description(&self) -> &'static str {
+        "Expands the calls of macros with their handlers"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Macros {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        // Expand the arguments first, so handlers see their expansion.
+        let result = node.visit_children_mut(gc, self);
+        let current = match result {
+            TransformResult::Unchanged => node,
+            TransformResult::Changed(node) => node,
+            _ => return result,
+        };
+
+        if let Node::ExpressionStatement(_) = current {
+            if std::mem::take(&mut self.remove_statement) {
+                return match path {
+                    Some(path) if in_list(path) => TransformResult::Removed,
+                    _ => TransformResult::Changed(builder::EmptyStatement::build_template(
+                        gc,
+                        template::EmptyStatement {
+                            metadata: TemplateMetadata {
+                                range: *current.range(),
+                                ..Default::default()
+                            },
+                        },
+                    )),
+                };
+            }
+            return result;
+        }
+
+        match self.expand(gc, current) {
+            None | Some(MacroExpansion::Keep) => result,
+            Some(MacroExpansion::Replace(expansion)) => TransformResult::Changed(expansion),
+            Some(MacroExpansion::Remove) => {
+                if matches!(
+                    path,
+                    Some(Path {
+                        parent: Node::ExpressionStatement(_),
+                        field: NodeField::expression,
+                    })
+                ) {
+                    self.remove_statement = true;
+                }
+                TransformResult::Changed(void_0(gc))
+            }
+        }
+    }
+}
+
+/// The dotted path of `callee`, like `console.assert`, if it is an identifier or a chain
+/// of non-computed member expressions.
+fn callee_name(gc: &GCLock, callee: &Node) -> Option<String> {
+    match callee {
+        Node::Identifier(Identifier { name, .. }) => Some(gc.str(*name).to_string()),
+        Node::MemberExpression(MemberExpression {
+            object,
+            property: Node::Identifier(Identifier { name, .. }),
+            computed: false,
+            ..
+        }) => Some(format!("{}.{}", callee_name(gc, object)?, gc.str(*name))),
+        _ => None,
+    }
+}
+
+/// The index of the argument of the metavariable `name`, like `$1`.
+fn metavariable_index(name: &str) -> Option<usize> {
+    let digits = name.strip_prefix('$')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Whether the node at `path` is an element of a list.
+fn in_list(path: Path) -> bool {
+    matches!(path.parent.field(path.field), Some(FieldValue::NodeList(_)))
+}
+
+/// `void 0`
+fn void_0<'gc>(gc: &'gc GCLock) -> &'gc Node<'gc> {
+    builder::UnaryExpression::build_template(
+        gc,
+        template::UnaryExpression {
+            metadata: Default::default(),
+            operator: UnaryExpressionOperator::Void,
+            argument: builders::number(gc, 0.0),
+            prefix: true,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::gen_js;
+
+    use super::*;
+
+    /// Run `macros`, with the templates built by `templates`, on the statements built by
+    /// `body`, and return the generated JS.
+    fn run_macros<T, B>(mut macros: Macros, templates: T, body: B) -> String
+    where
+        T: for<'gc> FnOnce(&'gc GCLock) -> Vec<(&'static str, &'gc Node<'gc>)>,
+        B: for<'gc> FnOnce(&'gc GCLock) -> Vec<&'gc Node<'gc>>,
+    {
+        let mut ctx = Context::new();
+        let (templates, root) = {
+            let gc = GCLock::new(&mut ctx);
+            let templates = templates(&gc)
+                .into_iter()
+                .map(|(name, t)| (name, NodeRc::from_node(&gc, t)))
+                .collect::<Vec<_>>();
+            let root = builder::Program::build_template(
+                &gc,
+                template::Program {
+                    metadata: Default::default(),
+                    body: NodeList::from_iter(&gc, body(&gc)),
+                },
+            );
+            (templates, NodeRc::from_node(&gc, root))
+        };
+        for (name, template) in templates {
+            macros.add_template(&mut ctx, name, template).unwrap();
+        }
+        let root = crate::run_pass(&mut ctx, &mut macros, root);
+        // The templates must be dropped before the context.
+        drop(macros);
+        let mut out = vec![];
+        gen_js::generate(
+            &mut out,
+            &mut ctx,
+            &root,
+            gen_js::Opt {
+                pretty: gen_js::Pretty::No,
+                ..gen_js::Opt::new()
+            },
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn call<'gc>(gc: &'gc GCLock, callee: &str, args: Vec<&'gc Node<'gc>>) -> &'gc Node<'gc> {
+        builders::call(gc, builders::ident(gc, callee), args)
+    }
+
+    #[test]
+    fn test_templates() {
+        let mut macros = Macros::new();
+        macros.add_removal("__DEV_ASSERT__");
+        let js = run_macros(
+            macros,
+            // invariant: $0 || fail($1)
+            |gc| {
+                vec![(
+                    "invariant",
+                    builder::LogicalExpression::build_template(
+                        gc,
+                        template::LogicalExpression {
+                            metadata: Default::default(),
+                            left: builders::ident(gc, "$0"),
+                            right: call(gc, "fail", vec![builders::ident(gc, "$1")]),
+                            operator: LogicalExpressionOperator::Or,
+                        },
+                    ),
+                )]
+            },
+            |gc| {
+                let x = builders::ident(gc, "x");
+                let message = builders::string(gc, "message");
+                vec![
+                    builders::expr_stmt(gc, call(gc, "invariant", vec![x, message])),
+                    builders::expr_stmt(gc, call(gc, "invariant", vec![x])),
+                    builders::expr_stmt(gc, call(gc, "__DEV_ASSERT__", vec![x])),
+                    builders::expr_stmt(
+                        gc,
+                        call(gc, "f", vec![call(gc, "__DEV_ASSERT__", vec![x])]),
+                    ),
+                ]
+            },
+        );
+        assert_eq!(js, "x||fail('message');x||fail((void 0));f((void 0));\n");
+    }
+
+    #[test]
+    fn test_callback() {
+        let mut macros = Macros::new();
+        // Replace graphql`...` with the text of the query, and console.log with its argument.
+        macros.add_callback("graphql", |gc, call| {
+            assert!(call.is_tagged());
+            MacroExpansion::Replace(builders::string(gc, &call.quasis(gc).concat()))
+        });
+        macros.add_callback("console.log", |_, call| match call.arguments[..] {
+            [arg] => MacroExpansion::Replace(arg),
+            _ => MacroExpansion::Keep,
+        });
+        let js = run_macros(
+            macros,
+            |_| vec![],
+            |gc| {
+                let cooked = gc.atom_u16("query { viewer }".encode_utf16().collect::<Vec<_>>());
+                let quasi = builder::TemplateLiteral::build_template(
+                    gc,
+                    template::TemplateLiteral {
+                        metadata: Default::default(),
+                        quasis: NodeList::from_iter(
+                            gc,
+                            [builder::TemplateElement::build_template(
+                                gc,
+                                template::TemplateElement {
+                                    metadata: Default::default(),
+                                    tail: true,
+                                    cooked: Some(cooked),
+                                    raw: gc.atom("query { viewer }"),
+                                },
+                            )],
+                        ),
+                        expressions: NodeList::new(gc),
+                    },
+                );
+                let query = builder::TaggedTemplateExpression::build_template(
+                    gc,
+                    template::TaggedTemplateExpression {
+                        metadata: Default::default(),
+                        tag: builders::ident(gc, "graphql"),
+                        quasi,
+                    },
+                );
+                let log = |args| {
+                    builders::expr_stmt(
+                        gc,
+                        builders::call(
+                            gc,
+                            builders::member(gc, builders::ident(gc, "console"), "log"),
+                            args,
+                        ),
+                    )
+                };
+                vec![log(vec![query]), log(vec![])]
+            },
+        );
+        assert_eq!(js, "'query { viewer }';console.log();\n");
+    }
+}
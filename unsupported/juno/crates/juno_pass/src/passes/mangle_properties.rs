@@ -0,0 +1,470 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Opt-in pass for renaming properties whose names match a pattern.
+//!
+//! Properties such as `obj._privateLongName` are renamed to short names everywhere
+//! they are accessed or defined: in member expressions, object literals, object patterns
+//! and class bodies.
+//! ```js
+//! obj._privateLongName = { _other: 1 };
+//! ```
+//! becomes (with the pattern `^_`)
+//! ```js
+//! obj.a = { b: 1 };
+//! ```
+//! Quoted and computed names (`obj['_privateLongName']`) are never renamed,
+//! which gives the code a way to opt out.
+//!
+//! This is only correct if every access to the renamed properties is visible to the pass,
+//! so the same `MangleProperties` instance must be used on every module of the bundle:
+//! first call [`MangleProperties::collect`] on all of them, so that the new names don't
+//! collide with property names used anywhere, then run the pass on each module.
+//! The mapping from original to new names can be written out with
+//! [`MangleProperties::write_name_map`] to help debugging the output.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+
+use juno::ast::*;
+use juno_support::ident;
+use juno_support::json::JSONEmitter;
+use juno_support::json::Pretty;
+use juno_support::keywords::EsVersion;
+use regex::Regex;
+
+use crate::NameMap;
+use crate::Pass;
+
+/// Name of the scope of renamed properties in a [`NameMap`].
+const NAME_MAP_SCOPE: &str = "<properties>";
+
+/// Characters which can start a generated name.
+const FIRST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_";
+
+/// Characters which can appear after the first one in a generated name.
+const REST_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_0123456789";
+
+pub struct MangleProperties {
+    /// Properties whose names match are renamed.
+    pattern: Regex,
+
+    /// Names which must not be renamed, even if they match the pattern.
+    reserved: HashSet<String>,
+
+    /// Property names which are not renamed and can't be used as new names.
+    in_use: HashSet<String>,
+
+    /// Map from original to new names, sorted for deterministic output.
+    names: BTreeMap<String, String>,
+
+    /// Index of the next candidate name.
+    next_id: usize,
+}
+
+impl MangleProperties {
+    /// Create a pass renaming the properties whose names match the regular
+    /// expression `pattern`.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            reserved: ["constructor", "prototype", "__proto__"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            in_use: HashSet::new(),
+            names: BTreeMap::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Never rename the property `name`.
+    pub fn reserve(&mut self, name: &str) {
+        self.reserved.insert(name.to_string());
+        self.in_use.insert(name.to_string());
+    }
+
+    /// Record the property names used in the AST rooted at `node`, so they are not
+    /// chosen as new names.
+    /// Must be called on every module before running the pass on any of them.
+    pub fn collect<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>) {
+        node.visit(gc, &mut NameCollector { pass: self }, None);
+    }
+
+    /// Map from original to new property names for every property renamed so far.
+    pub fn name_map(&self) -> &BTreeMap<String, String> {
+        &self.names
+    }
+
+    /// Write the name map as a JSON object from original to new names.
+    pub fn write_name_map<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut emitter = JSONEmitter::new(out, Pretty::Yes);
+        emitter.open_dict();
+        for (original, renamed) in &self.names {
+            emitter.emit_key(original);
+            emitter.emit_string(renamed);
+        }
+        emitter.close_dict();
+        emitter.end()
+    }
+
+    /// Whether the property `name` is to be renamed.
+    fn should_rename(&self, name: &str) -> bool {
+        !self.reserved.contains(name) && self.pattern.is_match(name)
+    }
+
+    /// Return the new name of the property `name`, or `None` if it isn't renamed.
+    fn rename(&mut self, name: &str) -> Option<String> {
+        if let Some(renamed) = self.names.get(name) {
+            return Some(renamed.clone());
+        }
+        if !self.should_rename(name) {
+            return None;
+        }
+        let renamed = loop {
+            let candidate = generate_name(self.next_id);
+            self.next_id += 1;
+            // Property names can be reserved words since ES5, but avoiding them
+            // keeps the output valid for ES3 engines.
+            if !self.in_use.contains(&candidate)
+                && ident::is_valid_identifier(&candidate, EsVersion::ES3, false)
+            {
+                break candidate;
+            }
+        };
+        self.names.insert(name.to_string(), renamed.clone());
+        Some(renamed)
+    }
+
+    /// If `key` is an identifier which is renamed, return the renamed identifier.
+    fn rename_ident<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        key: &'gc Node<'gc>,
+    ) -> Option<&'gc Node<'gc>> {
+        match key {
+            Node::Identifier(Identifier {
+                metadata,
+                name,
+                type_annotation,
+                optional,
+            }) => {
+                let renamed = self.rename(gc.str(*name))?;
+                Some(builder::Identifier::build_template(
+                    gc,
+                    template::Identifier {
+                        metadata: metadata.into(),
+                        name: gc.atom(renamed),
+                        type_annotation: *type_annotation,
+                        optional: *optional,
+                    },
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Pass for MangleProperties {
+    fn name(&self) -> &'static str {
+        "Mangle properties"
+    }
+    fn description(&self) -> &'static str {
+        "Renames properties whose names match a pattern to short names"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        // Protect against a missing call to `collect` for this module.
+        self.collect(gc, node);
+        VisitorMut::call(self, gc, node, None)
+    }
+    fn record_names(&self, map: &mut NameMap) {
+        // Properties are renamed the same way everywhere.
+        for (original, renamed) in &self.names {
+            map.add(NAME_MAP_SCOPE, None, original, renamed);
+        }
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for MangleProperties {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            // `{_a}` must be expanded to `{b: _a}`, keeping the value.
+            Node::Property(Property {
+                metadata,
+                key,
+                value,
+                kind,
+                computed: false,
+                method,
+                shorthand: true,
+            }) => {
+                if let Some(key) = self.rename_ident(gc, key) {
+                    let value = match VisitorMut::call(
+                        self,
+                        gc,
+                        value,
+                        Some(Path::new(node, NodeField::value)),
+                    ) {
+                        TransformResult::Changed(new_value) => new_value,
+                        _ => value,
+                    };
+                    return TransformResult::Changed(builder::Property::build_template(
+                        gc,
+                        template::Property {
+                            metadata: metadata.into(),
+                            key,
+                            value,
+                            kind: *kind,
+                            computed: false,
+                            method: *method,
+                            shorthand: false,
+                        },
+                    ));
+                }
+            }
+            Node::Identifier(_) if is_property_name(path) => {
+                if let Some(renamed) = self.rename_ident(gc, node) {
+                    return TransformResult::Changed(renamed);
+                }
+            }
+            _ => {}
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Records the property names which must not be used as new names.
+struct NameCollector<'a> {
+    pass: &'a mut MangleProperties,
+}
+
+impl<'gc> Visitor<'gc> for NameCollector<'_> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        match node {
+            Node::Identifier(Identifier { name, .. }) if is_property_name(path) => {
+                let name = gc.str(*name);
+                if !self.pass.should_rename(name) {
+                    self.pass.in_use.insert(name.to_string());
+                }
+            }
+            // Quoted names are never renamed, so they are always in use.
+            Node::StringLiteral(StringLiteral { value, .. })
+                if matches!(
+                    path,
+                    Some(Path {
+                        field: NodeField::key | NodeField::property,
+                        ..
+                    })
+                ) =>
+            {
+                self.pass
+                    .in_use
+                    .insert(String::from_utf16_lossy(gc.str_u16(*value)));
+            }
+            _ => {}
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// Whether an identifier at `path` is the name of a property, rather than a variable.
+fn is_property_name(path: Option<Path>) -> bool {
+    let Path { parent, field } = match path {
+        Some(path) => path,
+        None => return false,
+    };
+    match parent {
+        Node::MemberExpression(MemberExpression { computed, .. })
+        | Node::OptionalMemberExpression(OptionalMemberExpression { computed, .. }) => {
+            !*computed && field == NodeField::property
+        }
+        Node::Property(Property { computed, .. })
+        | Node::MethodDefinition(MethodDefinition { computed, .. }) => {
+            !*computed && field == NodeField::key
+        }
+        Node::ClassProperty(prop) => !prop.computed && field == NodeField::key,
+        _ => false,
+    }
+}
+
+/// Return the `id`th shortest name: `a`, `b`, ..., `_`, `aa`, `ba`, ...
+fn generate_name(mut id: usize) -> String {
+    let mut name = String::new();
+    name.push(FIRST_CHARS[id % FIRST_CHARS.len()] as char);
+    id /= FIRST_CHARS.len();
+    while id > 0 {
+        id -= 1;
+        name.push(REST_CHARS[id % REST_CHARS.len()] as char);
+        id /= REST_CHARS.len();
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen;
+
+    fn assign<'gc>(gc: &'gc GCLock, left: &'gc Node<'gc>, right: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::AssignmentExpression::build_template(
+            gc,
+            template::AssignmentExpression {
+                metadata: Default::default(),
+                operator: AssignmentExpressionOperator::Assign,
+                left,
+                right,
+            },
+        )
+    }
+
+    fn object<'gc>(gc: &'gc GCLock, props: &[(&str, &'gc Node<'gc>)]) -> &'gc Node<'gc> {
+        let properties = props.iter().map(|(key, value)| {
+            let shorthand =
+                matches!(value, Node::Identifier(Identifier { name, .. }) if gc.str(*name) == *key);
+            builder::Property::build_template(
+                gc,
+                template::Property {
+                    metadata: Default::default(),
+                    key: builders::ident(gc, key),
+                    value,
+                    kind: PropertyKind::Init,
+                    computed: false,
+                    method: false,
+                    shorthand,
+                },
+            )
+        });
+        builder::ObjectExpression::build_template(
+            gc,
+            template::ObjectExpression {
+                metadata: Default::default(),
+                properties: NodeList::from_iter(gc, properties),
+            },
+        )
+    }
+
+    /// Run `pass` on each module built by `modules` after collecting names from all of them,
+    /// and return the generated JS of each.
+    fn run_pass<F>(pass: &mut MangleProperties, modules: F) -> Vec<String>
+    where
+        F: for<'gc> FnOnce(&'gc GCLock) -> Vec<Vec<&'gc Node<'gc>>>,
+    {
+        let mut ctx = Context::new();
+        let roots = {
+            let gc = GCLock::new(&mut ctx);
+            let modules = modules(&gc)
+                .into_iter()
+                .map(|body| builders::module(&gc, body))
+                .collect::<Vec<_>>();
+            for module in &modules {
+                pass.collect(&gc, module);
+            }
+            modules
+                .into_iter()
+                .map(|module| match pass.run(&gc, module) {
+                    TransformResult::Changed(new_node) => NodeRc::from_node(&gc, new_node),
+                    _ => NodeRc::from_node(&gc, module),
+                })
+                .collect::<Vec<_>>()
+        };
+        roots.iter().map(|root| gen(&mut ctx, root)).collect()
+    }
+
+    #[test]
+    fn test_generate_name() {
+        assert_eq!(generate_name(0), "a");
+        assert_eq!(generate_name(53), "_");
+        assert_eq!(generate_name(54), "aa");
+        assert_eq!(generate_name(55), "ba");
+        assert_eq!(generate_name(54 + 54 * 64), "aaa");
+    }
+
+    #[test]
+    fn test_skip_reserved() {
+        let mut pass = MangleProperties::new("^_").unwrap();
+        pass.next_id = 54 + 3 + 54 * 14;
+        assert_eq!(generate_name(pass.next_id), "do");
+        assert_eq!(pass.rename("_foo").as_deref(), Some("eo"));
+    }
+
+    #[test]
+    fn test_mangle() {
+        let mut pass = MangleProperties::new("^_").unwrap();
+        let js = run_pass(&mut pass, |gc| {
+            let o = builders::ident(gc, "o");
+            let first = vec![
+                // o._foo = {_bar: 1, a: 2};
+                builders::expr_stmt(
+                    gc,
+                    assign(
+                        gc,
+                        builders::member(gc, o, "_foo"),
+                        object(
+                            gc,
+                            &[
+                                ("_bar", builders::number(gc, 1.0)),
+                                ("a", builders::number(gc, 2.0)),
+                            ],
+                        ),
+                    ),
+                ),
+            ];
+            let second = vec![
+                // f({_foo}, o['_baz'], o._bar, _foo, o.constructor);
+                builders::expr_stmt(
+                    gc,
+                    builders::call(
+                        gc,
+                        builders::ident(gc, "f"),
+                        [
+                            object(gc, &[("_foo", builders::ident(gc, "_foo"))]),
+                            builder::MemberExpression::build_template(
+                                gc,
+                                template::MemberExpression {
+                                    metadata: Default::default(),
+                                    object: o,
+                                    property: builders::string(gc, "_baz"),
+                                    computed: true,
+                                },
+                            ),
+                            builders::member(gc, o, "_bar"),
+                            builders::ident(gc, "_foo"),
+                            builders::member(gc, o, "constructor"),
+                        ],
+                    ),
+                ),
+            ];
+            vec![first, second]
+        });
+        // `a` is used by a property which isn't renamed.
+        assert_eq!(
+            js,
+            [
+                "o.b={c:1,a:2};\n",
+                "f({b:_foo},(o['_baz']),(o.c),_foo,(o.constructor));\n"
+            ]
+        );
+
+        let mut map = vec![];
+        pass.write_name_map(&mut map).unwrap();
+        assert_eq!(
+            String::from_utf8(map).unwrap(),
+            "{\n  \"_bar\": \"c\",\n  \"_foo\": \"b\"\n}\n"
+        );
+    }
+}
@@ -6,5 +6,13 @@
  */
 
 pub mod add_negative;
+pub mod anonymize;
+pub mod codemod;
+pub mod constant_fold;
+pub mod destructuring;
+pub mod jsx;
+pub mod macros;
+pub mod mangle_properties;
+pub mod optimize_strings;
 pub mod reduce_conditional;
 pub mod strip_flow;
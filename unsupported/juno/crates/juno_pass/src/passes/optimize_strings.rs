@@ -0,0 +1,468 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Pass for shrinking string literals.
+//!
+//! * Merges concatenations of adjacent string literals:
+//!   `'a' + 'b'` becomes `'ab'` and `x + 'a' + 'b'` becomes `x + 'ab'`.
+//! * Converts template literals without substitutions into string literals:
+//!   `` `abc` `` becomes `'abc'`.
+//! * In ES modules, hoists long string literals which occur many times into
+//!   module-level constants, when that is estimated to make the output smaller:
+//! ```js
+//! f('some long string');
+//! g('some long string');
+//! ```
+//! becomes
+//! ```js
+//! const $s0 = 'some long string';
+//! f($s0);
+//! g($s0);
+//! ```
+//! Hoisting is only done in modules because in scripts the constant would be global.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use juno::ast::*;
+
+use crate::Pass;
+
+/// Minimum length of a string literal for it to be considered for hoisting.
+const MIN_HOIST_LENGTH: usize = 8;
+
+/// Prefix of the names of the hoisted constants.
+const HOIST_PREFIX: &str = "$s";
+
+#[derive(Default)]
+pub struct OptimizeStrings {
+    /// Estimated number of bytes saved in the generated JS by the last run.
+    bytes_saved: usize,
+}
+
+impl OptimizeStrings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Estimated number of bytes saved in the generated JS by the last run.
+    pub fn bytes_saved(&self) -> usize {
+        self.bytes_saved
+    }
+}
+
+impl Pass for OptimizeStrings {
+    fn name(&self) -> &'static str {
+        "Optimize strings"
+    }
+    fn description(&self) -> &'static str {
+        "Merges string concatenations and hoists repeated long string literals"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let mut folder = Folder { bytes_saved: 0 };
+        let folded = VisitorMut::call(&mut folder, gc, node, None);
+        self.bytes_saved = folder.bytes_saved;
+
+        let root = match folded {
+            TransformResult::Unchanged => node,
+            TransformResult::Changed(new_node) => new_node,
+            _ => return folded,
+        };
+        match hoist_literals(gc, root) {
+            Some((new_root, saved)) => {
+                self.bytes_saved += saved;
+                TransformResult::Changed(new_root)
+            }
+            None => folded,
+        }
+    }
+}
+
+/// Merges string concatenations and converts simple template literals.
+struct Folder {
+    bytes_saved: usize,
+}
+
+impl Folder {
+    /// Return the folded version of `node`, whose children have already been folded.
+    fn fold<'gc>(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> Option<&'gc Node<'gc>> {
+        match node {
+            Node::BinaryExpression(BinaryExpression {
+                metadata,
+                left,
+                right: Node::StringLiteral(StringLiteral { value: right, .. }),
+                operator: BinaryExpressionOperator::Plus,
+            }) => match left {
+                // 'a' + 'b'
+                Node::StringLiteral(StringLiteral { value: left, .. }) => {
+                    self.bytes_saved += 3;
+                    Some(concat(gc, metadata.range, *left, *right))
+                }
+                // x + 'a' + 'b'
+                // The left operand is always a string, so this is safe regardless of `x`.
+                Node::BinaryExpression(BinaryExpression {
+                    metadata: left_metadata,
+                    left: inner_left,
+                    right: Node::StringLiteral(StringLiteral { value: left, .. }),
+                    operator: BinaryExpressionOperator::Plus,
+                }) => {
+                    self.bytes_saved += 3;
+                    let right = concat(
+                        gc,
+                        SourceRange {
+                            end: metadata.range.end,
+                            ..left_metadata.range
+                        },
+                        *left,
+                        *right,
+                    );
+                    Some(builder::BinaryExpression::build_template(
+                        gc,
+                        template::BinaryExpression {
+                            metadata: metadata.into(),
+                            left: inner_left,
+                            right,
+                            operator: BinaryExpressionOperator::Plus,
+                        },
+                    ))
+                }
+                _ => None,
+            },
+            Node::TemplateLiteral(TemplateLiteral {
+                metadata,
+                quasis,
+                expressions,
+            }) if expressions.is_empty() => {
+                // Tagged templates receive the raw strings, so they must be left alone.
+                if let Some(Path {
+                    parent: Node::TaggedTemplateExpression(_),
+                    ..
+                }) = path
+                {
+                    return None;
+                }
+                match quasis.head() {
+                    Some(Node::TemplateElement(TemplateElement {
+                        cooked: Some(cooked),
+                        ..
+                    })) => Some(builder::StringLiteral::build_template(
+                        gc,
+                        template::StringLiteral {
+                            metadata: metadata.into(),
+                            value: *cooked,
+                        },
+                    )),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Folder {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        let children = node.visit_children_mut(gc, self);
+        let cur = match children {
+            TransformResult::Changed(new_node) => new_node,
+            _ => node,
+        };
+        match self.fold(gc, cur, path) {
+            Some(folded) => TransformResult::Changed(folded),
+            None => children,
+        }
+    }
+}
+
+/// Create a string literal containing `left` followed by `right`.
+fn concat<'gc>(
+    gc: &'gc GCLock,
+    range: SourceRange,
+    left: NodeString,
+    right: NodeString,
+) -> &'gc Node<'gc> {
+    let mut value = gc.str_u16(left).to_vec();
+    value.extend_from_slice(gc.str_u16(right));
+    builder::StringLiteral::build_template(
+        gc,
+        template::StringLiteral {
+            metadata: range.into(),
+            value: gc.atom_u16(value),
+        },
+    )
+}
+
+/// Whether a string literal at `path` is an expression which can be replaced with
+/// an identifier.
+/// Excludes directives, module specifiers, property keys, JSX and type annotations.
+fn is_replaceable(path: Option<Path>) -> bool {
+    let Path { parent, field } = match path {
+        Some(path) => path,
+        None => return false,
+    };
+    match parent {
+        Node::CallExpression(_) | Node::OptionalCallExpression(_) | Node::NewExpression(_) => {
+            field == NodeField::arguments
+        }
+        Node::AssignmentExpression(_) | Node::AssignmentPattern(_) => field == NodeField::right,
+        Node::VariableDeclarator(_) => field == NodeField::init,
+        Node::Property(_) => field == NodeField::value,
+        Node::TemplateLiteral(_) => field == NodeField::expressions,
+        Node::ArrowFunctionExpression(_) => field == NodeField::body,
+        Node::BinaryExpression(_)
+        | Node::LogicalExpression(_)
+        | Node::ConditionalExpression(_)
+        | Node::SequenceExpression(_)
+        | Node::ArrayExpression(_)
+        | Node::ReturnStatement(_)
+        | Node::SpreadElement(_)
+        | Node::UnaryExpression(_)
+        | Node::MemberExpression(_)
+        | Node::OptionalMemberExpression(_)
+        | Node::SwitchCase(_) => true,
+        _ => false,
+    }
+}
+
+/// Counts the replaceable string literals and collects every identifier name in use.
+#[derive(Default)]
+struct LiteralCounter {
+    counts: HashMap<NodeString, usize>,
+    names: HashSet<String>,
+}
+
+impl<'gc> Visitor<'gc> for LiteralCounter {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        match node {
+            Node::StringLiteral(StringLiteral { value, .. })
+                if gc.str_u16(*value).len() >= MIN_HOIST_LENGTH && is_replaceable(path) =>
+            {
+                *self.counts.entry(*value).or_insert(0) += 1;
+            }
+            Node::Identifier(Identifier { name, .. }) => {
+                self.names.insert(gc.str(*name).to_string());
+            }
+            _ => {}
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// Replaces the hoisted string literals with references to their constants.
+struct LiteralReplacer<'gc> {
+    constants: HashMap<NodeString, &'gc Node<'gc>>,
+}
+
+impl<'gc> VisitorMut<'gc> for LiteralReplacer<'gc> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::StringLiteral(StringLiteral { value, .. }) = node {
+            if is_replaceable(path) {
+                if let Some(id) = self.constants.get(value) {
+                    return TransformResult::Changed(id);
+                }
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Estimated number of bytes saved by hoisting a literal of length `len`
+/// which occurs `count` times into a constant called `name`.
+fn hoisting_savings(len: usize, count: usize, name: &str) -> isize {
+    // Each occurrence is the string and its quotes.
+    let before = count * (len + 2);
+    // `const name="...";` plus a reference per occurrence.
+    let after = "const =\"\";".len() + name.len() + len + count * name.len();
+    before as isize - after as isize
+}
+
+/// If `root` is a `Module`, hoist repeated long string literals into constants.
+/// Return the new root and the estimated number of bytes saved,
+/// or `None` if nothing was hoisted.
+fn hoist_literals<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>) -> Option<(&'gc Node<'gc>, usize)> {
+    if !matches!(root, Node::Module(_)) {
+        return None;
+    }
+
+    let mut counter = LiteralCounter::default();
+    root.visit(gc, &mut counter, None);
+
+    // Sort by the literal contents to make the output deterministic.
+    let LiteralCounter { counts, names } = counter;
+    let mut candidates = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(a, _), (b, _)| gc.str_u16(*a).cmp(gc.str_u16(*b)));
+
+    let mut next_id = 0usize;
+    let mut fresh_name = || loop {
+        let name = format!("{}{}", HOIST_PREFIX, next_id);
+        next_id += 1;
+        if !names.contains(&name) {
+            return name;
+        }
+    };
+
+    let mut saved = 0usize;
+    let mut constants = HashMap::new();
+    let mut decls = vec![];
+    for (value, count) in candidates {
+        let name = fresh_name();
+        let savings = hoisting_savings(gc.str_u16(value).len(), count, &name);
+        if savings <= 0 {
+            continue;
+        }
+        saved += savings as usize;
+        let init = builder::StringLiteral::build_template(
+            gc,
+            template::StringLiteral {
+                metadata: Default::default(),
+                value,
+            },
+        );
+        decls.push(builder::VariableDeclaration::build_template(
+            gc,
+            template::VariableDeclaration {
+                metadata: Default::default(),
+                kind: VariableDeclarationKind::Const,
+                declarations: NodeList::from_iter(
+                    gc,
+                    [builder::VariableDeclarator::build_template(
+                        gc,
+                        template::VariableDeclarator {
+                            metadata: Default::default(),
+                            init: Some(init),
+                            id: builders::ident(gc, &name),
+                        },
+                    )],
+                ),
+            },
+        ));
+        constants.insert(value, builders::ident(gc, &name));
+    }
+    if constants.is_empty() {
+        return None;
+    }
+
+    let mut replacer = LiteralReplacer { constants };
+    let new_root = match VisitorMut::call(&mut replacer, gc, root, None) {
+        TransformResult::Changed(new_root) => new_root,
+        _ => root,
+    };
+    Some((inject_prelude(gc, new_root, decls), saved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run_on;
+
+    fn plus<'gc>(gc: &'gc GCLock, left: &'gc Node<'gc>, right: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        builder::BinaryExpression::build_template(
+            gc,
+            template::BinaryExpression {
+                metadata: Default::default(),
+                left,
+                right,
+                operator: BinaryExpressionOperator::Plus,
+            },
+        )
+    }
+
+    fn template_literal<'gc>(gc: &'gc GCLock, s: &str) -> &'gc Node<'gc> {
+        let quasi = builder::TemplateElement::build_template(
+            gc,
+            template::TemplateElement {
+                metadata: Default::default(),
+                tail: true,
+                cooked: Some(gc.atom_u16(s.encode_utf16().collect::<Vec<u16>>())),
+                raw: gc.atom(s),
+            },
+        );
+        builder::TemplateLiteral::build_template(
+            gc,
+            template::TemplateLiteral {
+                metadata: Default::default(),
+                quasis: NodeList::from_iter(gc, [quasi]),
+                expressions: NodeList::new(gc),
+            },
+        )
+    }
+
+    /// Run the pass on a `Module` containing `body` and return the generated JS
+    /// and the estimated number of bytes saved.
+    fn run_pass<F>(body: F) -> (String, usize)
+    where
+        F: for<'gc> FnOnce(&'gc GCLock) -> Vec<&'gc Node<'gc>>,
+    {
+        let mut pass = OptimizeStrings::new();
+        let js = run_on(&mut pass, |gc| builders::module(gc, body(gc)));
+        (js, pass.bytes_saved())
+    }
+
+    #[test]
+    fn test_fold() {
+        let (js, saved) = run_pass(|gc| {
+            let x = builders::ident(gc, "x");
+            let ab = plus(gc, builders::string(gc, "a"), builders::string(gc, "b"));
+            let xcd = plus(
+                gc,
+                plus(gc, x, builders::string(gc, "c")),
+                builders::string(gc, "d"),
+            );
+            let args = [ab, xcd, template_literal(gc, "e")];
+            vec![builders::expr_stmt(
+                gc,
+                builders::call(gc, builders::ident(gc, "f"), args),
+            )]
+        });
+        assert_eq!(js, "f('ab',(x+'cd'),'e');\n");
+        assert_eq!(saved, 6);
+    }
+
+    #[test]
+    fn test_hoist() {
+        let (js, saved) = run_pass(|gc| {
+            let long = "some long string";
+            let calls = ["f", "g", "h"].map(|name| {
+                builders::expr_stmt(
+                    gc,
+                    builders::call(gc, builders::ident(gc, name), [builders::string(gc, long)]),
+                )
+            });
+            // `$s0` is already taken.
+            let mut body = vec![builders::expr_stmt(gc, builders::ident(gc, "$s0"))];
+            body.extend(calls);
+            body
+        });
+        assert_eq!(
+            js,
+            "const $s1='some long string';$s0;f($s1);g($s1);h($s1);\n"
+        );
+        assert_eq!(saved, hoisting_savings(16, 3, "$s1") as usize);
+    }
+}
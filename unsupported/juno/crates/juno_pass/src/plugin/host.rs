@@ -0,0 +1,571 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The host API through which native and WebAssembly plugins access the AST.
+//!
+//! The host API only uses `i32` values. Nodes are referred to by handles, which are
+//! non-negative, and `-1` stands for no node. Strings are UTF-8, passed as an offset in the
+//! memory of the plugin and a length. Functions returning strings or arrays write them to a
+//! buffer of the plugin given as an offset and a capacity, and return their full length, so
+//! the plugin can retry with a larger buffer if needed. Node handles are written as little
+//! endian `u32`s.
+//! - `root() -> node`: the root of the AST.
+//! - `kind(node, out, cap) -> len`: the kind of `node`, like `CallExpression`.
+//! - `parent(node) -> node`: the parent of `node`, or -1 for the root.
+//! - `field(node, name, name_len) -> node`: the node in the field `name` (in snake case),
+//!   or -1 if it is null.
+//! - `list(node, name, name_len, out, cap) -> count`: the nodes in the list field `name`.
+//! - `string(node, name, name_len, out, cap) -> len`: the value of the identifier, string
+//!   or enum field `name`.
+//! - `number(node, name, name_len, out) -> 0`: the value of the number or boolean field
+//!   `name`, written as a little endian `f64`.
+//! - `range(node, out) -> 0`: the start line, start column, end line and end column of
+//!   `node`, written as little endian `u32`s.
+//! - `query(kind, kind_len, out, cap) -> count`: the nodes of the kind `kind`, in preorder.
+//! - `replace(node, template, template_len, args, args_len) -> 0`: replace `node` with the
+//!   JS `template`, an expression, or a statement if `node` is one, in which the
+//!   identifiers `$0`, `$1`... are copies of the nodes of the array `args`.
+//! - `remove(node) -> 0`: remove `node` from the list which contains it.
+//! - `report(node, severity, message, message_len) -> 0`: report `message` at `node` as a
+//!   note (0), a warning (1) or an error (2).
+//!
+//! Invalid arguments, like unknown handles or fields of the wrong type, abort the plugin
+//! with an error. Changes take effect once the plugin returns, so the AST the plugin reads
+//! is never modified. A node can only be changed once, and changes to nodes in a replaced
+//! or removed node are ignored. Lint rules can't change the AST.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::convert::TryInto;
+
+use juno::ast::*;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::hparser::SnippetGoal;
+use thiserror::Error;
+
+use crate::codemod::Substitute;
+
+/// A function of the host API.
+#[derive(Debug, Copy, Clone)]
+pub struct HostFunction {
+    pub name: &'static str,
+
+    /// Number of `i32` parameters. Every function returns a single `i32`.
+    pub params: usize,
+}
+
+/// The functions of the host API.
+pub const HOST_FUNCTIONS: &[HostFunction] = &[
+    HostFunction {
+        name: "root",
+        params: 0,
+    },
+    HostFunction {
+        name: "kind",
+        params: 3,
+    },
+    HostFunction {
+        name: "parent",
+        params: 1,
+    },
+    HostFunction {
+        name: "field",
+        params: 3,
+    },
+    HostFunction {
+        name: "list",
+        params: 5,
+    },
+    HostFunction {
+        name: "string",
+        params: 5,
+    },
+    HostFunction {
+        name: "number",
+        params: 4,
+    },
+    HostFunction {
+        name: "range",
+        params: 2,
+    },
+    HostFunction {
+        name: "query",
+        params: 4,
+    },
+    HostFunction {
+        name: "replace",
+        params: 5,
+    },
+    HostFunction {
+        name: "remove",
+        params: 1,
+    },
+    HostFunction {
+        name: "report",
+        params: 4,
+    },
+];
+
+#[derive(Debug, Error)]
+pub enum HostError {
+    #[error("plugin failed with status {0}")]
+    Failed(i32),
+
+    #[error("unknown host function '{0}'")]
+    UnknownFunction(String),
+
+    #[error("invalid node handle {0}")]
+    InvalidHandle(i32),
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("access out of the bounds of the memory of the plugin")]
+    OutOfBounds,
+
+    #[error("invalid template: {0}")]
+    Template(ParseError),
+}
+
+/// The memory of a plugin, which the offsets passed to the host API point into.
+pub trait GuestMemory {
+    /// Read `len` bytes at `ptr`.
+    fn read(&self, ptr: u32, len: u32) -> Result<Vec<u8>, HostError>;
+
+    /// Write `data` at `ptr`.
+    fn write(&mut self, ptr: u32, data: &[u8]) -> Result<(), HostError>;
+}
+
+/// Implementation of the host API, called when the plugin calls one of its functions.
+pub trait HostFunctions {
+    /// Call the function `name` of [`HOST_FUNCTIONS`] with `args`, accessing `memory`.
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[i32],
+        memory: &mut dyn GuestMemory,
+    ) -> Result<i32, HostError>;
+}
+
+/// Run the pass of a plugin on `root`, consuming it in the process. `run` calls the plugin
+/// with the host API, and the changes it requested are applied once it returns.
+pub(super) fn run_pass<E: From<HostError>>(
+    ctx: &mut Context,
+    root: NodeRc,
+    run: impl FnOnce(&mut dyn HostFunctions) -> Result<(), E>,
+) -> Result<NodeRc, E> {
+    let edits = {
+        let gc = GCLock::new(ctx);
+        let mut host = Host::new(&gc, root.node(&gc), false);
+        run(&mut host)?;
+        host.edits
+    };
+    if edits.is_empty() {
+        return Ok(root);
+    }
+
+    // Templates are parsed once the plugin is done, as parsing needs the context.
+    let mut rewrites = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let action = match edit.action {
+            Action::Replace {
+                goal,
+                template,
+                args,
+            } => {
+                let template = hparser::parse_snippet(ctx, Default::default(), goal, &template)
+                    .map_err(HostError::Template)?;
+                Rewrite::Replace { template, args }
+            }
+            Action::Remove => Rewrite::Remove,
+        };
+        rewrites.push((edit.target, action));
+    }
+
+    let result = {
+        let gc = GCLock::new(ctx);
+        let mut apply = Apply {
+            rewrites: rewrites
+                .iter()
+                .map(|(target, rewrite)| (target.node(&gc) as *const Node as *const (), rewrite))
+                .collect(),
+        };
+        match VisitorMut::call(&mut apply, &gc, root.node(&gc), None) {
+            TransformResult::Changed(node) => NodeRc::from_node(&gc, node),
+            _ => root,
+        }
+    };
+    drop(rewrites);
+    ctx.collect_if_needed();
+    Ok(result)
+}
+
+/// Run the lint rule of a plugin on `root`. `lint` calls the plugin with the host API,
+/// which rejects any change to the AST.
+pub(super) fn run_lint<'gc, R>(
+    gc: &'gc GCLock,
+    root: &'gc Node<'gc>,
+    lint: impl FnOnce(&mut dyn HostFunctions) -> Result<R, HostError>,
+) -> Result<R, HostError> {
+    lint(&mut Host::new(gc, root, true))
+}
+
+/// Host functions for calls made before the AST is available.
+pub(super) struct NoHost;
+
+impl HostFunctions for NoHost {
+    fn call(
+        &mut self,
+        name: &str,
+        _args: &[i32],
+        _memory: &mut dyn GuestMemory,
+    ) -> Result<i32, HostError> {
+        Err(HostError::UnknownFunction(name.to_string()))
+    }
+}
+
+/// A change requested by the plugin.
+struct Edit {
+    target: NodeRc,
+    action: Action,
+}
+
+enum Action {
+    Replace {
+        goal: SnippetGoal,
+        template: String,
+        args: Vec<NodeRc>,
+    },
+    Remove,
+}
+
+/// An [`Action`] whose template is parsed.
+enum Rewrite {
+    Replace { template: NodeRc, args: Vec<NodeRc> },
+    Remove,
+}
+
+/// Implementation of the host API over the AST.
+struct Host<'gc, 'ast: 'gc, 'ctx> {
+    gc: &'gc GCLock<'ast, 'ctx>,
+
+    /// The nodes of the AST in preorder, indexed by their handle, with their parent.
+    nodes: Vec<(&'gc Node<'gc>, Option<usize>)>,
+
+    /// The handle of each node.
+    handles: HashMap<*const Node<'gc>, usize>,
+
+    /// Whether changes are rejected, for lint rules.
+    read_only: bool,
+
+    edits: Vec<Edit>,
+}
+
+impl<'gc, 'ast: 'gc, 'ctx> Host<'gc, 'ast, 'ctx> {
+    fn new(gc: &'gc GCLock<'ast, 'ctx>, root: &'gc Node<'gc>, read_only: bool) -> Self {
+        let mut nodes = vec![];
+        let mut handles = HashMap::new();
+        for (node, _, parent) in iter_preorder(gc, root) {
+            let parent = parent.map(|parent| handles[&(parent as *const Node)]);
+            handles.insert(node as *const Node, nodes.len());
+            nodes.push((node, parent));
+        }
+        Host {
+            gc,
+            nodes,
+            handles,
+            read_only,
+            edits: vec![],
+        }
+    }
+
+    fn node(&self, handle: i32) -> Result<&'gc Node<'gc>, HostError> {
+        usize::try_from(handle)
+            .ok()
+            .and_then(|i| self.nodes.get(i))
+            .map(|&(node, _)| node)
+            .ok_or(HostError::InvalidHandle(handle))
+    }
+
+    fn handle(&self, node: &'gc Node<'gc>) -> i32 {
+        self.handles[&(node as *const Node)] as i32
+    }
+
+    fn field(
+        &self,
+        memory: &dyn GuestMemory,
+        node: i32,
+        name: i32,
+        name_len: i32,
+    ) -> Result<FieldValue<'gc>, HostError> {
+        let name = read_str(memory, name, name_len)?;
+        let node = self.node(node)?;
+        node.field_by_name(&name).ok_or_else(|| {
+            HostError::InvalidArgument(format!("{} has no field '{}'", node.name(), name))
+        })
+    }
+
+    fn edit(&mut self, target: i32, action: Action) -> Result<i32, HostError> {
+        if self.read_only {
+            return Err(HostError::InvalidArgument(
+                "lint rules can't change the AST".into(),
+            ));
+        }
+        let target = self.node(target)?;
+        if self
+            .edits
+            .iter()
+            .any(|edit| edit.target.node(self.gc).ptr_eq(target))
+        {
+            return Err(HostError::InvalidArgument(format!(
+                "{} is already changed",
+                target.name()
+            )));
+        }
+        self.edits.push(Edit {
+            target: NodeRc::from_node(self.gc, target),
+            action,
+        });
+        Ok(0)
+    }
+}
+
+/// Convert the plugin value `value` to an offset or a length.
+fn to_u32(value: i32) -> Result<u32, HostError> {
+    u32::try_from(value).map_err(|_| HostError::OutOfBounds)
+}
+
+fn read_str(memory: &dyn GuestMemory, ptr: i32, len: i32) -> Result<String, HostError> {
+    String::from_utf8(memory.read(to_u32(ptr)?, to_u32(len)?)?)
+        .map_err(|_| HostError::InvalidArgument("string is not UTF-8".into()))
+}
+
+/// Write as much of `data` as fits in the buffer `out` of `cap` bytes, and return its length.
+fn write_bytes(
+    memory: &mut dyn GuestMemory,
+    out: i32,
+    cap: i32,
+    data: &[u8],
+) -> Result<i32, HostError> {
+    let len = data.len().min(to_u32(cap)? as usize);
+    memory.write(to_u32(out)?, &data[..len])?;
+    Ok(data.len() as i32)
+}
+
+/// Write as many of `handles` as fit in the buffer `out` of `cap` handles,
+/// and return their number.
+fn write_handles(
+    memory: &mut dyn GuestMemory,
+    out: i32,
+    cap: i32,
+    handles: &[i32],
+) -> Result<i32, HostError> {
+    let len = handles.len().min(to_u32(cap)? as usize);
+    let data = handles[..len]
+        .iter()
+        .flat_map(|handle| handle.to_le_bytes())
+        .collect::<Vec<_>>();
+    memory.write(to_u32(out)?, &data)?;
+    Ok(handles.len() as i32)
+}
+
+impl HostFunctions for Host<'_, '_, '_> {
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[i32],
+        memory: &mut dyn GuestMemory,
+    ) -> Result<i32, HostError> {
+        match HOST_FUNCTIONS.iter().find(|f| f.name == name) {
+            Some(f) if f.params == args.len() => {}
+            Some(f) => {
+                return Err(HostError::InvalidArgument(format!(
+                    "'{}' takes {} arguments",
+                    f.name, f.params
+                )))
+            }
+            None => return Err(HostError::UnknownFunction(name.to_string())),
+        }
+        match (name, args) {
+            ("root", []) => Ok(0),
+            ("kind", &[node, out, cap]) => {
+                let kind = self.node(node)?.name();
+                write_bytes(memory, out, cap, kind.as_bytes())
+            }
+            ("parent", &[node]) => {
+                self.node(node)?;
+                Ok(match self.nodes[node as usize].1 {
+                    Some(parent) => parent as i32,
+                    None => -1,
+                })
+            }
+            ("field", &[node, name, name_len]) => {
+                match self.field(memory, node, name, name_len)? {
+                    FieldValue::Node(child) => Ok(self.handle(child)),
+                    FieldValue::Null => Ok(-1),
+                    _ => Err(HostError::InvalidArgument("field is not a node".into())),
+                }
+            }
+            ("list", &[node, name, name_len, out, cap]) => {
+                let list = match self.field(memory, node, name, name_len)? {
+                    FieldValue::NodeList(list) => list,
+                    FieldValue::Null => NodeList::new(self.gc),
+                    _ => return Err(HostError::InvalidArgument("field is not a list".into())),
+                };
+                let handles = list
+                    .iter()
+                    .map(|child| self.handle(child))
+                    .collect::<Vec<_>>();
+                write_handles(memory, out, cap, &handles)
+            }
+            ("string", &[node, name, name_len, out, cap]) => {
+                let value = match self.field(memory, node, name, name_len)? {
+                    FieldValue::Label(label) => self.gc.str(label).to_string(),
+                    FieldValue::String(string) => String::from_utf16_lossy(self.gc.str_u16(string)),
+                    FieldValue::Enum(value) => value.to_string(),
+                    _ => return Err(HostError::InvalidArgument("field is not a string".into())),
+                };
+                write_bytes(memory, out, cap, value.as_bytes())
+            }
+            ("number", &[node, name, name_len, out]) => {
+                let value = match self.field(memory, node, name, name_len)? {
+                    FieldValue::Number(value) => value,
+                    FieldValue::Bool(value) => value as u8 as f64,
+                    _ => return Err(HostError::InvalidArgument("field is not a number".into())),
+                };
+                memory.write(to_u32(out)?, &value.to_le_bytes())?;
+                Ok(0)
+            }
+            ("range", &[node, out]) => {
+                let range = self.node(node)?.range();
+                let data = [
+                    range.start.line,
+                    range.start.col,
+                    range.end.line,
+                    range.end.col,
+                ]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+                memory.write(to_u32(out)?, &data)?;
+                Ok(0)
+            }
+            ("query", &[kind, kind_len, out, cap]) => {
+                let kind = read_str(memory, kind, kind_len)?;
+                let handles = self
+                    .nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (node, _))| node.name() == kind)
+                    .map(|(i, _)| i as i32)
+                    .collect::<Vec<_>>();
+                write_handles(memory, out, cap, &handles)
+            }
+            ("replace", &[node, template, template_len, args, args_len]) => {
+                let template = read_str(memory, template, template_len)?;
+                let args = memory
+                    .read(to_u32(args)?, to_u32(args_len)?.saturating_mul(4))?
+                    .chunks_exact(4)
+                    .map(|bytes| {
+                        let handle = i32::from_le_bytes(bytes.try_into().unwrap());
+                        Ok(NodeRc::from_node(self.gc, self.node(handle)?))
+                    })
+                    .collect::<Result<Vec<_>, HostError>>()?;
+                let goal = if self
+                    .node(node)?
+                    .variant()
+                    .is_subtype_of(NodeVariant::Statement)
+                {
+                    SnippetGoal::Statement
+                } else {
+                    SnippetGoal::Expression
+                };
+                self.edit(
+                    node,
+                    Action::Replace {
+                        goal,
+                        template,
+                        args,
+                    },
+                )
+            }
+            ("remove", &[node]) => {
+                let target = self.node(node)?;
+                let in_list = match self.nodes[node as usize].1 {
+                    Some(parent) => {
+                        let (parent, _) = self.nodes[parent];
+                        parent.fields().iter().any(|info| {
+                            matches!(
+                                parent.field(info.field),
+                                Some(FieldValue::NodeList(list))
+                                    if list.iter().any(|child| child.ptr_eq(target))
+                            )
+                        })
+                    }
+                    None => false,
+                };
+                if !in_list {
+                    return Err(HostError::InvalidArgument(
+                        "only nodes in lists can be removed".into(),
+                    ));
+                }
+                self.edit(node, Action::Remove)
+            }
+            ("report", &[node, severity, message, message_len]) => {
+                let range = *self.node(node)?.range();
+                let message = read_str(memory, message, message_len)?;
+                match severity {
+                    0 => self.gc.sm().note(range, message),
+                    1 => self.gc.sm().warning(range, message),
+                    2 => self.gc.sm().error(range, message),
+                    _ => {
+                        return Err(HostError::InvalidArgument(format!(
+                            "invalid severity {}",
+                            severity
+                        )))
+                    }
+                }
+                Ok(0)
+            }
+            _ => unreachable!("arity checked above"),
+        }
+    }
+}
+
+/// Applies the changes of a plugin.
+struct Apply<'a> {
+    /// The changes, by the address of their target.
+    rewrites: HashMap<*const (), &'a Rewrite>,
+}
+
+impl<'gc> VisitorMut<'gc> for Apply<'_> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match self.rewrites.get(&(node as *const Node as *const ())) {
+            Some(Rewrite::Replace { template, args }) => {
+                let bindings = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| (gc.atom(format!("${}", i)), arg.node(gc)))
+                    .collect();
+                let template = deep_clone(gc, template.node(gc));
+                TransformResult::Changed(
+                    template
+                        .visit_mut(gc, &mut Substitute { bindings }, None)
+                        .unwrap(),
+                )
+            }
+            Some(Rewrite::Remove) => TransformResult::Removed,
+            None => node.visit_children_mut(gc, self),
+        }
+    }
+}
@@ -0,0 +1,713 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Plugins providing passes and lint rules outside of Juno, loaded from dynamic libraries.
+//!
+//! Plugins access the AST through the [`host`] API, with C types only: Rust types like
+//! nodes, locks and trait objects are never shared with the host, since a library gets its
+//! own copy of the statics of the crates it links, and their layout depends on the
+//! compiler. A plugin can thus be built with any compiler, in any language.
+//!
+//! A plugin library exports a [`PluginDeclaration`] named `juno_plugin_declaration`, with
+//! the entry points of its pass and its lint rule, which a Rust plugin declares with
+//! [`declare_plugin!`]:
+//! ```ignore
+//! unsafe extern "C" fn run(host: *const NativeHost) -> i32 {
+//!     let host = &*host;
+//!     let root = host.call("root", &[]).unwrap();
+//!     // ...
+//!     0
+//! }
+//!
+//! juno_pass::declare_plugin!(name: "my-plugin", run: Some(run), lint: None);
+//! ```
+//!
+//! A plugin is only loaded if it was built for a compatible [`API_VERSION`]: the same major
+//! version, and a minor version no greater than the host's.
+//! Loaded libraries stay loaded until the process exits.
+//!
+//! Untrusted plugins can instead be compiled to WebAssembly and run in a sandbox, see [`wasm`].
+
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::path::PathBuf;
+use std::slice;
+
+use juno::ast::*;
+use thiserror::Error;
+
+use host::GuestMemory;
+use host::HostError;
+use host::HostFunctions;
+use wasm::WasmError;
+use wasm::WasmPlugin;
+
+pub mod host;
+pub mod wasm;
+#[cfg(feature = "wasmi")]
+pub mod wasmi_engine;
+
+/// Version of the interface between the host and its plugins.
+/// The minor version increases when the interface is extended compatibly, and the major
+/// version when it changes incompatibly.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ApiVersion {
+    /// Whether a plugin built for this version can be loaded by a `host` of another version.
+    pub fn is_compatible_with(&self, host: ApiVersion) -> bool {
+        self.major == host.major && self.minor <= host.minor
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Version of the interface implemented by this version of Juno.
+pub const API_VERSION: ApiVersion = ApiVersion { major: 2, minor: 0 };
+
+/// Size of the memory shared by the host and a native plugin, in bytes.
+const NATIVE_MEMORY_SIZE: usize = 1 << 20;
+
+/// An entry point of a plugin, called with the host API. The pass returns 0 on success,
+/// and the lint rule returns the number of problems it reported, or a negative value on
+/// failure.
+pub type EntryPoint = unsafe extern "C" fn(host: *const NativeHost) -> i32;
+
+/// The host API, as given to the entry points of a native plugin.
+/// It is only valid during the call to the entry point.
+#[repr(C)]
+pub struct NativeHost {
+    /// State of the host, passed back to `call_host`.
+    pub state: *mut c_void,
+
+    /// Memory shared by the host and the plugin, which the offsets passed to the host API
+    /// point into.
+    pub memory: *mut u8,
+    pub memory_len: usize,
+
+    /// Call the function `name` of [`host::HOST_FUNCTIONS`], with its `name_len` bytes,
+    /// and the `args_len` arguments `args`. Return 0 and store the result in `result` on
+    /// success, or return -1 if the call failed, in which case the entry point must return
+    /// without calling the host again: the host reports the error.
+    pub call_host: unsafe extern "C" fn(
+        state: *mut c_void,
+        name: *const u8,
+        name_len: usize,
+        args: *const i32,
+        args_len: usize,
+        result: *mut i32,
+    ) -> i32,
+}
+
+impl NativeHost {
+    /// Call the host function `name` with `args`, and return its result, or `None` if it
+    /// failed.
+    pub fn call(&self, name: &str, args: &[i32]) -> Option<i32> {
+        let mut result = 0;
+        // SAFETY: the host is only given to entry points, while it is valid.
+        let status = unsafe {
+            (self.call_host)(
+                self.state,
+                name.as_ptr(),
+                name.len(),
+                args.as_ptr(),
+                args.len(),
+                &mut result,
+            )
+        };
+        if status == 0 {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    /// Read `len` bytes of the shared memory at `offset`, or return `None` if they are out
+    /// of bounds.
+    pub fn read(&self, offset: usize, len: usize) -> Option<Vec<u8>> {
+        match offset.checked_add(len) {
+            // SAFETY: the memory is valid while the host is, and the range is in bounds.
+            Some(end) if end <= self.memory_len => {
+                Some(unsafe { slice::from_raw_parts(self.memory.add(offset), len) }.to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    /// Write `data` to the shared memory at `offset`, or return `false` if it is out of
+    /// bounds.
+    pub fn write(&self, offset: usize, data: &[u8]) -> bool {
+        match offset.checked_add(data.len()) {
+            // SAFETY: the memory is valid while the host is, and the range is in bounds.
+            Some(end) if end <= self.memory_len => {
+                unsafe { slice::from_raw_parts_mut(self.memory.add(offset), data.len()) }
+                    .copy_from_slice(data);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The entry point of a plugin library, exported by [`declare_plugin!`].
+/// The version comes first so it can be checked before anything else is used.
+#[repr(C)]
+pub struct PluginDeclaration {
+    pub api_version: ApiVersion,
+
+    /// Name of the plugin, for diagnostics, as a null-terminated UTF-8 string.
+    pub name: *const c_char,
+
+    /// The pass of the plugin, if any, which is run after the passes of Juno.
+    pub run: Option<EntryPoint>,
+
+    /// The lint rule of the plugin, if any, which is run with the lint rules of Juno.
+    pub lint: Option<EntryPoint>,
+}
+
+// SAFETY: the declaration is immutable, and its name is a static string.
+unsafe impl Sync for PluginDeclaration {}
+
+/// Name of the symbol of the [`PluginDeclaration`] of a plugin library.
+pub const DECLARATION_SYMBOL: &str = "juno_plugin_declaration";
+
+/// Export the [`PluginDeclaration`] of a plugin library, with the `name` of the plugin and
+/// the optional [`EntryPoint`]s `run` and `lint`.
+#[macro_export]
+macro_rules! declare_plugin {
+    (name: $name:literal, run: $run:expr, lint: $lint:expr $(,)?) => {
+        #[no_mangle]
+        pub static juno_plugin_declaration: $crate::plugin::PluginDeclaration =
+            $crate::plugin::PluginDeclaration {
+                api_version: $crate::plugin::API_VERSION,
+                name: concat!($name, "\0").as_ptr() as *const ::std::os::raw::c_char,
+                run: $run,
+                lint: $lint,
+            };
+    };
+}
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    /// The library couldn't be loaded, with the message of the dynamic loader, which names
+    /// the library.
+    #[error("{message}")]
+    Load { path: PathBuf, message: String },
+
+    #[error("{}: not a Juno plugin, '{}' is missing", path.display(), DECLARATION_SYMBOL)]
+    NotAPlugin { path: PathBuf },
+
+    #[error("plugin requires version {plugin} of the plugin API, but Juno provides {host}")]
+    Version {
+        plugin: ApiVersion,
+        host: ApiVersion,
+    },
+
+    #[error("plugin '{name}': {error}")]
+    Host { name: String, error: HostError },
+
+    #[error("plugin '{name}': {error}")]
+    Wasm { name: String, error: WasmError },
+
+    #[error("plugins can't be loaded on this platform")]
+    Unsupported,
+}
+
+/// A plugin loaded from a dynamic library.
+pub struct NativePlugin {
+    name: String,
+    run: Option<EntryPoint>,
+    lint: Option<EntryPoint>,
+}
+
+impl NativePlugin {
+    /// Create the plugin declared by `decl`, if it is compatible with the host.
+    ///
+    /// # Safety
+    /// `decl.name` must be a null-terminated string, and the entry points must follow the
+    /// host API.
+    pub unsafe fn new(decl: &PluginDeclaration) -> Result<Self, PluginError> {
+        if !decl.api_version.is_compatible_with(API_VERSION) {
+            return Err(PluginError::Version {
+                plugin: decl.api_version,
+                host: API_VERSION,
+            });
+        }
+        Ok(NativePlugin {
+            name: CStr::from_ptr(decl.name).to_string_lossy().into_owned(),
+            run: decl.run,
+            lint: decl.lint,
+        })
+    }
+
+    /// Name of the plugin, for diagnostics.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn error(&self, error: HostError) -> PluginError {
+        PluginError::Host {
+            name: self.name.clone(),
+            error,
+        }
+    }
+
+    /// Run the pass of the plugin on `root`, consuming it in the process.
+    pub fn run(&self, ctx: &mut Context, root: NodeRc) -> Result<NodeRc, PluginError> {
+        let run = match self.run {
+            Some(run) => run,
+            None => return Ok(root),
+        };
+        host::run_pass(ctx, root, |host| match call_native(run, host)? {
+            0 => Ok(()),
+            status => Err(HostError::Failed(status)),
+        })
+        .map_err(|error| self.error(error))
+    }
+
+    /// Run the lint rule of the plugin on `root` and return the number of problems.
+    pub fn lint<'gc>(&self, gc: &'gc GCLock, root: &'gc Node<'gc>) -> Result<usize, PluginError> {
+        let lint = match self.lint {
+            Some(lint) => lint,
+            None => return Ok(0),
+        };
+        host::run_lint(gc, root, |host| match call_native(lint, host)? {
+            count if count >= 0 => Ok(count as usize),
+            status => Err(HostError::Failed(status)),
+        })
+        .map_err(|error| self.error(error))
+    }
+}
+
+/// The state of the host during a call to a native plugin.
+struct NativeState<'a> {
+    host: &'a mut dyn HostFunctions,
+    memory: SharedMemory,
+
+    /// The first error of a call to the host.
+    error: Option<HostError>,
+}
+
+/// The memory shared with a native plugin, only accessed by one side at a time.
+struct SharedMemory {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl SharedMemory {
+    fn slice(&self, ptr: u32, len: usize) -> Result<*mut u8, HostError> {
+        match (ptr as usize).checked_add(len) {
+            Some(end) if end <= self.len => Ok(unsafe { self.ptr.add(ptr as usize) }),
+            _ => Err(HostError::OutOfBounds),
+        }
+    }
+}
+
+impl GuestMemory for SharedMemory {
+    fn read(&self, ptr: u32, len: u32) -> Result<Vec<u8>, HostError> {
+        let start = self.slice(ptr, len as usize)?;
+        Ok(unsafe { slice::from_raw_parts(start, len as usize) }.to_vec())
+    }
+
+    fn write(&mut self, ptr: u32, data: &[u8]) -> Result<(), HostError> {
+        let start = self.slice(ptr, data.len())?;
+        unsafe { slice::from_raw_parts_mut(start, data.len()) }.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Call the native `entry` point with the host API implemented by `host`.
+fn call_native(entry: EntryPoint, host: &mut dyn HostFunctions) -> Result<i32, HostError> {
+    let mut memory = vec![0u8; NATIVE_MEMORY_SIZE];
+    // The memory is only accessed through this pointer while the plugin runs.
+    let ptr = memory.as_mut_ptr();
+    let mut state = NativeState {
+        host,
+        memory: SharedMemory {
+            ptr,
+            len: memory.len(),
+        },
+        error: None,
+    };
+    let api = NativeHost {
+        state: &mut state as *mut NativeState as *mut c_void,
+        memory: ptr,
+        memory_len: memory.len(),
+        call_host: native_call,
+    };
+    // SAFETY: the plugin only uses `api` during the call, while `state` and `memory` live.
+    let status = unsafe { entry(&api) };
+    match state.error {
+        Some(error) => Err(error),
+        None => Ok(status),
+    }
+}
+
+/// The `call_host` function of [`NativeHost`].
+unsafe extern "C" fn native_call(
+    state: *mut c_void,
+    name: *const u8,
+    name_len: usize,
+    args: *const i32,
+    args_len: usize,
+    result: *mut i32,
+) -> i32 {
+    let state = &mut *(state as *mut NativeState);
+    if state.error.is_some() {
+        return -1;
+    }
+    // Empty slices may be passed as null pointers.
+    let name = if name_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(name, name_len)
+    };
+    let args = if args_len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(args, args_len)
+    };
+    let call = match std::str::from_utf8(name) {
+        Ok(name) => state.host.call(name, args, &mut state.memory),
+        Err(_) => Err(HostError::InvalidArgument(
+            "function name is not UTF-8".into(),
+        )),
+    };
+    match call {
+        Ok(value) => {
+            *result = value;
+            0
+        }
+        Err(error) => {
+            state.error = Some(error);
+            -1
+        }
+    }
+}
+
+/// The plugins of the host.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<NativePlugin>,
+    wasm_plugins: Vec<WasmPlugin>,
+}
+
+impl PluginRegistry {
+    /// Create with no plugins.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add `plugin`.
+    pub fn register(&mut self, plugin: NativePlugin) {
+        self.plugins.push(plugin);
+    }
+
+    /// Load the plugin library at `path` and add its plugin.
+    pub fn load(&mut self, path: &Path) -> Result<(), PluginError> {
+        let decl = dl::load_declaration(path)?;
+        // SAFETY: the library exports the declaration, so it was defined by
+        // `declare_plugin!` or follows the host API.
+        self.register(unsafe { NativePlugin::new(decl)? });
+        Ok(())
+    }
+
+    /// Add the WebAssembly `plugin`.
+    /// The passes of WebAssembly plugins run after the passes of native plugins.
+    pub fn register_wasm(&mut self, plugin: WasmPlugin) {
+        self.wasm_plugins.push(plugin);
+    }
+
+    /// Instantiate the WebAssembly plugin at `path` with the wasmi interpreter, its
+    /// resources limited by `limits`, and add it.
+    #[cfg(feature = "wasmi")]
+    pub fn load_wasm(&mut self, path: &Path, limits: &wasm::WasmLimits) -> Result<(), PluginError> {
+        let module = std::fs::read(path).map_err(|e| PluginError::Load {
+            path: path.to_path_buf(),
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let plugin = WasmPlugin::new(&wasmi_engine::WasmiEngine, &name, &module, limits).map_err(
+            |error| PluginError::Wasm {
+                name: name.to_string(),
+                error,
+            },
+        )?;
+        self.register_wasm(plugin);
+        Ok(())
+    }
+
+    /// The native plugins, in the order they were added.
+    pub fn plugins(&self) -> impl Iterator<Item = &NativePlugin> {
+        self.plugins.iter()
+    }
+
+    /// Whether no plugin was added.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty() && self.wasm_plugins.is_empty()
+    }
+
+    /// Run the passes of the plugins on `node`, consuming it in the process.
+    /// If the cancellation token of `ctx` is cancelled, the remaining passes are skipped.
+    pub fn run_passes(&mut self, ctx: &mut Context, node: NodeRc) -> Result<NodeRc, PluginError> {
+        let mut result = node;
+        for plugin in &self.plugins {
+            if ctx.is_cancelled() {
+                break;
+            }
+            result = plugin.run(ctx, result)?;
+        }
+        for plugin in &mut self.wasm_plugins {
+            if ctx.is_cancelled() {
+                break;
+            }
+            result = plugin.run(ctx, result).map_err(|error| PluginError::Wasm {
+                name: plugin.name().to_string(),
+                error,
+            })?;
+        }
+        Ok(result)
+    }
+
+    /// Run the lint rules of the native plugins on `root` and return the number of problems.
+    /// WebAssembly plugins are skipped, since their API has no lint rule.
+    pub fn lint<'gc>(&self, gc: &'gc GCLock, root: &'gc Node<'gc>) -> Result<usize, PluginError> {
+        let mut count = 0;
+        for plugin in &self.plugins {
+            count += plugin.lint(gc, root)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(unix)]
+mod dl {
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::os::raw::c_char;
+    use std::os::raw::c_int;
+    use std::os::raw::c_void;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::PluginDeclaration;
+    use super::PluginError;
+    use super::DECLARATION_SYMBOL;
+
+    const RTLD_NOW: c_int = 2;
+
+    #[cfg_attr(target_os = "linux", link(name = "dl"))]
+    extern "C" {
+        fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *mut c_char;
+    }
+
+    /// The message of the last error of the dynamic loader.
+    fn last_error() -> String {
+        // SAFETY: `dlerror` returns null or a null-terminated string.
+        match unsafe { dlerror().as_ref() } {
+            Some(message) => unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned(),
+            None => "unknown error".to_string(),
+        }
+    }
+
+    /// Load the library at `path` and return its declaration.
+    /// The library is never unloaded, so the declaration lives as long as the process.
+    pub(super) fn load_declaration(path: &Path) -> Result<&'static PluginDeclaration, PluginError> {
+        let load_error = |message| PluginError::Load {
+            path: path.to_path_buf(),
+            message,
+        };
+        let filename = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| load_error(format!("{}: path contains a null byte", path.display())))?;
+        let symbol = CString::new(DECLARATION_SYMBOL).unwrap();
+        // SAFETY: both strings are null-terminated, and the declaration is only read if the
+        // library exports it.
+        unsafe {
+            let handle = dlopen(filename.as_ptr(), RTLD_NOW);
+            if handle.is_null() {
+                return Err(load_error(last_error()));
+            }
+            let decl = dlsym(handle, symbol.as_ptr()) as *const PluginDeclaration;
+            decl.as_ref().ok_or_else(|| PluginError::NotAPlugin {
+                path: path.to_path_buf(),
+            })
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod dl {
+    use std::path::Path;
+
+    use super::PluginDeclaration;
+    use super::PluginError;
+
+    pub(super) fn load_declaration(
+        _path: &Path,
+    ) -> Result<&'static PluginDeclaration, PluginError> {
+        Err(PluginError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+    use crate::test_util::numbers;
+
+    /// Write `s` to the memory of `host` at `ptr` and return its pointer and length.
+    fn string(host: &NativeHost, ptr: usize, s: &str) -> [i32; 2] {
+        assert!(host.write(ptr, s.as_bytes()));
+        [ptr as i32, s.len() as i32]
+    }
+
+    /// The nodes of the kind `kind`.
+    fn query(host: &NativeHost, kind: &str) -> Option<Vec<i32>> {
+        let [kind, kind_len] = string(host, 0, kind);
+        let count = host.call("query", &[kind, kind_len, 64, 16])?;
+        Some(
+            host.read(64, 4 * count as usize)?
+                .chunks_exact(4)
+                .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect(),
+        )
+    }
+
+    /// Remove the expression statements.
+    unsafe extern "C" fn strip_run(host: *const NativeHost) -> i32 {
+        let host = &*host;
+        let statements = match query(host, "ExpressionStatement") {
+            Some(statements) => statements,
+            None => return 1,
+        };
+        for node in statements {
+            if host.call("remove", &[node]).is_none() {
+                return 1;
+            }
+        }
+        0
+    }
+
+    /// Count numeric literals as problems.
+    unsafe extern "C" fn count_lint(host: *const NativeHost) -> i32 {
+        match query(&*host, "NumericLiteral") {
+            Some(numbers) => numbers.len() as i32,
+            None => -1,
+        }
+    }
+
+    /// Try to remove the root, which fails.
+    unsafe extern "C" fn invalid_run(host: *const NativeHost) -> i32 {
+        let host = &*host;
+        let root = host.call("root", &[]).unwrap();
+        assert_eq!(host.call("remove", &[root]), None);
+        // Calls after a failure fail too.
+        assert_eq!(host.call("root", &[]), None);
+        0
+    }
+
+    declare_plugin!(name: "strip", run: Some(strip_run), lint: Some(count_lint));
+
+    #[test]
+    fn test_versions() {
+        let host = ApiVersion { major: 1, minor: 2 };
+        assert!(ApiVersion { major: 1, minor: 0 }.is_compatible_with(host));
+        assert!(ApiVersion { major: 1, minor: 2 }.is_compatible_with(host));
+        assert!(!ApiVersion { major: 1, minor: 3 }.is_compatible_with(host));
+        assert!(!ApiVersion { major: 0, minor: 2 }.is_compatible_with(host));
+        assert!(!ApiVersion { major: 2, minor: 0 }.is_compatible_with(host));
+
+        let result = unsafe {
+            NativePlugin::new(&PluginDeclaration {
+                api_version: ApiVersion {
+                    major: API_VERSION.major + 1,
+                    minor: 0,
+                },
+                ..juno_plugin_declaration
+            })
+        };
+        assert!(matches!(result, Err(PluginError::Version { .. })));
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = PluginRegistry::new();
+        registry.register(unsafe { NativePlugin::new(&juno_plugin_declaration) }.unwrap());
+        assert_eq!(
+            registry.plugins().map(|p| p.name()).collect::<Vec<_>>(),
+            ["strip"]
+        );
+
+        let mut ctx = Context::new();
+        let root = numbers(&mut ctx);
+        {
+            let gc = GCLock::new(&mut ctx);
+            assert_eq!(registry.lint(&gc, root.node(&gc)).unwrap(), 2);
+        }
+        let root = registry.run_passes(&mut ctx, root).unwrap();
+        let gc = GCLock::new(&mut ctx);
+        match root.node(&gc) {
+            Node::Program(Program { body, .. }) => assert!(body.is_empty()),
+            _ => panic!("expected a program"),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let plugin = unsafe {
+            NativePlugin::new(&PluginDeclaration {
+                run: Some(invalid_run),
+                ..juno_plugin_declaration
+            })
+        }
+        .unwrap();
+        let mut ctx = Context::new();
+        let root = numbers(&mut ctx);
+        let result = plugin.run(&mut ctx, root.clone());
+        assert!(matches!(
+            result,
+            Err(PluginError::Host {
+                error: HostError::InvalidArgument(_),
+                ..
+            })
+        ));
+
+        // Lint rules can't change the AST.
+        let plugin = unsafe {
+            NativePlugin::new(&PluginDeclaration {
+                lint: Some(strip_run),
+                ..juno_plugin_declaration
+            })
+        }
+        .unwrap();
+        let gc = GCLock::new(&mut ctx);
+        let result = plugin.lint(&gc, root.node(&gc));
+        assert!(matches!(
+            result,
+            Err(PluginError::Host {
+                error: HostError::InvalidArgument(_),
+                ..
+            })
+        ));
+    }
+}
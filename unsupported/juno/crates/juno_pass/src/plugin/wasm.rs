@@ -0,0 +1,363 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Plugins compiled to WebAssembly, which run in a sandbox and can only access the AST
+//! through the [`host`](super::host) API, so untrusted transforms can run inside build
+//! services.
+//!
+//! Plugins run in a WebAssembly runtime implementing [`WasmEngine`], which instantiates
+//! modules, and [`WasmInstance`], which calls their exports. With the `wasmi` feature, Juno
+//! provides the interpreter [`WasmiEngine`](super::wasmi_engine::WasmiEngine), and embedders
+//! can plug in another runtime. The engine must link the imports of the module `juno` listed in
+//! [`HOST_FUNCTIONS`](super::host::HOST_FUNCTIONS) to the [`HostFunctions`] given to
+//! [`WasmInstance::call`], reject modules with any other import, and enforce the
+//! [`WasmLimits`].
+//!
+//! A plugin module exports:
+//! - `memory`, which the offsets passed to the host API point into;
+//! - `juno_api_version() -> i32`, the [`ApiVersion`] it was built for, as
+//!   `major << 16 | minor`;
+//! - `juno_run() -> i32`, which transforms the AST with the host API, and returns 0 on
+//!   success.
+
+use juno::ast::*;
+use thiserror::Error;
+
+use super::host;
+use super::host::HostError;
+use super::host::HostFunctions;
+use super::host::NoHost;
+use super::ApiVersion;
+use super::API_VERSION;
+
+/// Limits of the resources a plugin can use, enforced by the engine.
+#[derive(Debug, Copy, Clone)]
+pub struct WasmLimits {
+    /// Maximum size of the memory of the plugin, in pages of 64 KiB.
+    pub max_memory_pages: u32,
+
+    /// Maximum amount of fuel consumed by a call, in units of the engine,
+    /// or `None` if calls can run indefinitely.
+    pub fuel: Option<u64>,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        WasmLimits {
+            max_memory_pages: 1024,
+            fuel: Some(1_000_000_000),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WasmError {
+    /// An error of the engine, like an invalid module or a trap.
+    #[error("{0}")]
+    Engine(String),
+
+    #[error("plugin requires version {plugin} of the plugin API, but Juno provides {host}")]
+    Version {
+        plugin: ApiVersion,
+        host: ApiVersion,
+    },
+
+    #[error(transparent)]
+    Host(#[from] HostError),
+}
+
+/// A WebAssembly runtime, provided by the embedder.
+pub trait WasmEngine {
+    /// Compile and instantiate `module`, which may only import [`HOST_FUNCTIONS`], and whose
+    /// resources are limited by `limits`.
+    fn instantiate(
+        &self,
+        module: &[u8],
+        limits: &WasmLimits,
+    ) -> Result<Box<dyn WasmInstance>, WasmError>;
+}
+
+/// An instance of a plugin module.
+pub trait WasmInstance {
+    /// Call the export `name` with `args` and return its results.
+    /// Calls to the host API are forwarded to `host`.
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[i32],
+        host: &mut dyn HostFunctions,
+    ) -> Result<Vec<i32>, WasmError>;
+}
+
+/// A plugin compiled to WebAssembly.
+pub struct WasmPlugin {
+    name: String,
+    instance: Box<dyn WasmInstance>,
+}
+
+impl WasmPlugin {
+    /// Instantiate the plugin `module` with `engine`, if it is compatible with the host.
+    pub fn new(
+        engine: &dyn WasmEngine,
+        name: &str,
+        module: &[u8],
+        limits: &WasmLimits,
+    ) -> Result<Self, WasmError> {
+        let mut instance = engine.instantiate(module, limits)?;
+        let version = match instance.call("juno_api_version", &[], &mut NoHost)?[..] {
+            [version] => ApiVersion {
+                major: (version as u32) >> 16,
+                minor: (version as u32) & 0xffff,
+            },
+            _ => {
+                return Err(WasmError::Engine(
+                    "juno_api_version must return a single value".into(),
+                ))
+            }
+        };
+        if !version.is_compatible_with(API_VERSION) {
+            return Err(WasmError::Version {
+                plugin: version,
+                host: API_VERSION,
+            });
+        }
+        Ok(WasmPlugin {
+            name: name.to_string(),
+            instance,
+        })
+    }
+
+    /// Name of the plugin, for diagnostics.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run the plugin on `root`, consuming it in the process.
+    pub fn run(&mut self, ctx: &mut Context, root: NodeRc) -> Result<NodeRc, WasmError> {
+        let instance = &mut self.instance;
+        host::run_pass(ctx, root, |host| {
+            match instance.call("juno_run", &[], host)?[..] {
+                [0] => Ok(()),
+                [status] => Err(HostError::Failed(status).into()),
+                _ => Err(WasmError::Engine(
+                    "juno_run must return a single value".into(),
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use juno_support::NullTerminatedBuf;
+
+    use super::super::host::GuestMemory;
+    use super::super::PluginRegistry;
+    use super::*;
+
+    struct FakeMemory(Vec<u8>);
+
+    impl GuestMemory for FakeMemory {
+        fn read(&self, ptr: u32, len: u32) -> Result<Vec<u8>, HostError> {
+            self.0
+                .get(ptr as usize..(ptr + len) as usize)
+                .map(|data| data.to_vec())
+                .ok_or(HostError::OutOfBounds)
+        }
+
+        fn write(&mut self, ptr: u32, data: &[u8]) -> Result<(), HostError> {
+            self.0
+                .get_mut(ptr as usize..ptr as usize + data.len())
+                .ok_or(HostError::OutOfBounds)?
+                .copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    type Guest = fn(&mut dyn HostFunctions, &mut FakeMemory) -> Result<i32, WasmError>;
+
+    /// An engine whose modules are implemented in Rust.
+    struct FakeEngine {
+        version: ApiVersion,
+        guest: Guest,
+    }
+
+    struct FakeInstance {
+        version: ApiVersion,
+        guest: Guest,
+        memory: FakeMemory,
+    }
+
+    impl WasmEngine for FakeEngine {
+        fn instantiate(
+            &self,
+            _module: &[u8],
+            _limits: &WasmLimits,
+        ) -> Result<Box<dyn WasmInstance>, WasmError> {
+            Ok(Box::new(FakeInstance {
+                version: self.version,
+                guest: self.guest,
+                memory: FakeMemory(vec![0; 1024]),
+            }))
+        }
+    }
+
+    impl WasmInstance for FakeInstance {
+        fn call(
+            &mut self,
+            name: &str,
+            _args: &[i32],
+            host: &mut dyn HostFunctions,
+        ) -> Result<Vec<i32>, WasmError> {
+            match name {
+                "juno_api_version" => {
+                    Ok(vec![(self.version.major << 16 | self.version.minor) as i32])
+                }
+                "juno_run" => Ok(vec![(self.guest)(host, &mut self.memory)?]),
+                _ => Err(WasmError::Engine(format!("no export '{}'", name))),
+            }
+        }
+    }
+
+    /// Write `s` at `ptr` and return its pointer and length.
+    fn string(memory: &mut FakeMemory, ptr: i32, s: &str) -> [i32; 2] {
+        memory.write(ptr as u32, s.as_bytes()).unwrap();
+        [ptr, s.len() as i32]
+    }
+
+    /// Report and remove every `debugger` statement.
+    fn remove_debugger(
+        host: &mut dyn HostFunctions,
+        memory: &mut FakeMemory,
+    ) -> Result<i32, WasmError> {
+        let root = host.call("root", &[], memory)?;
+        let len = host.call("kind", &[root, 0, 64], memory)?;
+        assert_eq!(memory.read(0, len as u32)?, b"Program");
+
+        let [kind, kind_len] = string(memory, 0, "DebuggerStatement");
+        let count = host.call("query", &[kind, kind_len, 64, 16], memory)?;
+        let [message, message_len] = string(memory, 128, "unexpected debugger statement");
+        for i in 0..count {
+            let bytes = memory.read(64 + 4 * i as u32, 4)?;
+            let node = i32::from_le_bytes(bytes.try_into().unwrap());
+            assert_eq!(host.call("parent", &[node], memory)?, root);
+            host.call("report", &[node, 1, message, message_len], memory)?;
+            host.call("remove", &[node], memory)?;
+        }
+        Ok(0)
+    }
+
+    /// `debugger; f(1); debugger;`
+    fn program(ctx: &mut Context) -> NodeRc {
+        let file = ctx.sm_mut().add_source(
+            "test.js",
+            NullTerminatedBuf::from_str_copy("debugger; f(1); debugger;"),
+        );
+        let gc = GCLock::new(ctx);
+        let debugger = |col| {
+            builder::DebuggerStatement::build_template(
+                &gc,
+                template::DebuggerStatement {
+                    metadata: TemplateMetadata {
+                        range: SourceRange {
+                            file,
+                            start: SourceLoc { line: 1, col },
+                            end: SourceLoc {
+                                line: 1,
+                                col: col + 8,
+                            },
+                        },
+                        ..Default::default()
+                    },
+                },
+            )
+        };
+        let call = builders::expr_stmt(
+            &gc,
+            builders::call(&gc, builders::ident(&gc, "f"), [builders::number(&gc, 1.0)]),
+        );
+        let program = builders::program(&gc, [debugger(1), call, debugger(17)]);
+        NodeRc::from_node(&gc, program)
+    }
+
+    #[test]
+    fn test_run() {
+        let engine = FakeEngine {
+            version: API_VERSION,
+            guest: remove_debugger,
+        };
+        let mut plugin = WasmPlugin::new(&engine, "debugger", &[], &Default::default()).unwrap();
+        let mut ctx = Context::new();
+        let root = program(&mut ctx);
+        let root = plugin.run(&mut ctx, root).unwrap();
+        assert_eq!(ctx.sm().num_warnings(), 2);
+        let gc = GCLock::new(&mut ctx);
+        match root.node(&gc) {
+            Node::Program(Program { body, .. }) => {
+                assert_eq!(
+                    body.iter().map(|n| n.name()).collect::<Vec<_>>(),
+                    ["ExpressionStatement"]
+                );
+            }
+            _ => panic!("expected a program"),
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        let engine = FakeEngine {
+            version: API_VERSION,
+            guest: remove_debugger,
+        };
+        let mut registry = PluginRegistry::new();
+        registry
+            .register_wasm(WasmPlugin::new(&engine, "debugger", &[], &Default::default()).unwrap());
+        let mut ctx = Context::new();
+        let root = program(&mut ctx);
+        // WebAssembly plugins have no lint rule, so the plugin is not called.
+        {
+            let gc = GCLock::new(&mut ctx);
+            assert_eq!(registry.lint(&gc, root.node(&gc)).unwrap(), 0);
+        }
+        assert_eq!(ctx.sm().num_warnings(), 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let engine = FakeEngine {
+            version: ApiVersion {
+                major: API_VERSION.major + 1,
+                minor: 0,
+            },
+            guest: remove_debugger,
+        };
+        let result = WasmPlugin::new(&engine, "debugger", &[], &Default::default());
+        assert!(matches!(result, Err(WasmError::Version { .. })));
+
+        let engine = FakeEngine {
+            version: API_VERSION,
+            guest: |host, memory| {
+                // The root can't be removed, and handles must exist.
+                assert!(matches!(
+                    host.call("remove", &[0], memory),
+                    Err(HostError::InvalidArgument(_))
+                ));
+                Ok(host.call("kind", &[1000, 0, 64], memory)?)
+            },
+        };
+        let mut plugin = WasmPlugin::new(&engine, "invalid", &[], &Default::default()).unwrap();
+        let mut ctx = Context::new();
+        let root = program(&mut ctx);
+        let result = plugin.run(&mut ctx, root);
+        assert!(matches!(
+            result,
+            Err(WasmError::Host(HostError::InvalidHandle(1000)))
+        ));
+    }
+}
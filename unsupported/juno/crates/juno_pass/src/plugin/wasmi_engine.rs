@@ -0,0 +1,389 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! A [`WasmEngine`] running plugins with the wasmi interpreter, enabled by the `wasmi`
+//! feature.
+//!
+//! The memory of an instance can't grow past [`WasmLimits::max_memory_pages`], and each
+//! call to an export can execute at most [`WasmLimits::fuel`] units of fuel, roughly one per
+//! instruction, after which it traps. The start function of the module is limited in the
+//! same way.
+
+use wasmi::core::Trap;
+use wasmi::core::ValueType;
+use wasmi::AsContext;
+use wasmi::AsContextMut;
+use wasmi::Caller;
+use wasmi::Config;
+use wasmi::Engine;
+use wasmi::Extern;
+use wasmi::FuncType;
+use wasmi::Instance;
+use wasmi::Linker;
+use wasmi::Memory;
+use wasmi::Module;
+use wasmi::Store;
+use wasmi::StoreLimits;
+use wasmi::StoreLimitsBuilder;
+use wasmi::Value;
+
+use super::host::GuestMemory;
+use super::host::HostError;
+use super::host::HostFunctions;
+use super::host::HOST_FUNCTIONS;
+use super::wasm::WasmEngine;
+use super::wasm::WasmError;
+use super::wasm::WasmInstance;
+use super::wasm::WasmLimits;
+
+/// Size of a page of WebAssembly memory, in bytes.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Runs plugins with the wasmi interpreter.
+#[derive(Debug, Default)]
+pub struct WasmiEngine;
+
+/// The state of an instance, owned by its store.
+struct State {
+    limits: StoreLimits,
+
+    /// The host functions of the current call to an export, which the imports forward to.
+    /// Only set during the call, so the lifetime of the pointer is erased.
+    host: Option<*mut (dyn HostFunctions + 'static)>,
+
+    /// The error of the host function which made the current call trap.
+    error: Option<HostError>,
+}
+
+/// A plugin instantiated by [`WasmiEngine`].
+struct WasmiInstance {
+    store: Store<State>,
+    instance: Instance,
+
+    /// Fuel given to each call, if limited.
+    fuel: Option<u64>,
+}
+
+fn engine_error(error: impl ToString) -> WasmError {
+    WasmError::Engine(error.to_string())
+}
+
+impl WasmEngine for WasmiEngine {
+    fn instantiate(
+        &self,
+        module: &[u8],
+        limits: &WasmLimits,
+    ) -> Result<Box<dyn WasmInstance>, WasmError> {
+        let mut config = Config::default();
+        config.consume_fuel(limits.fuel.is_some());
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, module).map_err(engine_error)?;
+
+        let mut store = Store::new(
+            &engine,
+            State {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(limits.max_memory_pages as usize * PAGE_SIZE)
+                    .instances(1)
+                    .build(),
+                host: None,
+                error: None,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+
+        // Only the host API is linked, so modules with any other import are rejected.
+        let mut linker = Linker::new(&engine);
+        for function in HOST_FUNCTIONS {
+            let name = function.name;
+            linker
+                .func_new(
+                    "juno",
+                    name,
+                    FuncType::new(vec![ValueType::I32; function.params], [ValueType::I32]),
+                    move |caller, params, results| call_host(caller, name, params, results),
+                )
+                .map_err(engine_error)?;
+        }
+
+        refuel(&mut store, limits.fuel)?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(engine_error)?;
+        Ok(Box::new(WasmiInstance {
+            store,
+            instance,
+            fuel: limits.fuel,
+        }))
+    }
+}
+
+/// Set the remaining fuel of `store` to `fuel`, if limited.
+fn refuel(store: &mut Store<State>, fuel: Option<u64>) -> Result<(), WasmError> {
+    if let Some(fuel) = fuel {
+        let remaining = store.consume_fuel(0).map_err(engine_error)?;
+        if remaining < fuel {
+            store.add_fuel(fuel - remaining).map_err(engine_error)?;
+        } else {
+            store.consume_fuel(remaining - fuel).map_err(engine_error)?;
+        }
+    }
+    Ok(())
+}
+
+impl WasmInstance for WasmiInstance {
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[i32],
+        host: &mut dyn HostFunctions,
+    ) -> Result<Vec<i32>, WasmError> {
+        let func = self
+            .instance
+            .get_func(&self.store, name)
+            .ok_or_else(|| WasmError::Engine(format!("no export '{}'", name)))?;
+        let inputs = args.iter().map(|&arg| Value::I32(arg)).collect::<Vec<_>>();
+        let mut outputs = func
+            .ty(&self.store)
+            .results()
+            .iter()
+            .map(|&ty| Value::default(ty))
+            .collect::<Vec<_>>();
+        refuel(&mut self.store, self.fuel)?;
+
+        let host: *mut (dyn HostFunctions + '_) = host;
+        // SAFETY: the pointer is only used by the imports during the call, and cleared
+        // right after it.
+        self.store.data_mut().host = Some(unsafe {
+            std::mem::transmute::<*mut (dyn HostFunctions + '_), *mut (dyn HostFunctions + 'static)>(
+                host,
+            )
+        });
+        let result = func.call(&mut self.store, &inputs, &mut outputs);
+        let state = self.store.data_mut();
+        state.host = None;
+        if let Some(error) = state.error.take() {
+            return Err(error.into());
+        }
+        result.map_err(engine_error)?;
+        outputs
+            .iter()
+            .map(|value| {
+                value
+                    .i32()
+                    .ok_or_else(|| WasmError::Engine(format!("'{}' must return i32 values", name)))
+            })
+            .collect()
+    }
+}
+
+/// The memory exported by the instance of `caller`.
+struct CallerMemory<'a, 'b> {
+    caller: &'a mut Caller<'b, State>,
+    memory: Memory,
+}
+
+impl GuestMemory for CallerMemory<'_, '_> {
+    fn read(&self, ptr: u32, len: u32) -> Result<Vec<u8>, HostError> {
+        let mut data = vec![0; len as usize];
+        self.memory
+            .read(self.caller.as_context(), ptr as usize, &mut data)
+            .map_err(|_| HostError::OutOfBounds)?;
+        Ok(data)
+    }
+
+    fn write(&mut self, ptr: u32, data: &[u8]) -> Result<(), HostError> {
+        self.memory
+            .write(self.caller.as_context_mut(), ptr as usize, data)
+            .map_err(|_| HostError::OutOfBounds)
+    }
+}
+
+/// Forward the call of the import `name` to the host functions of the current call.
+fn call_host(
+    mut caller: Caller<'_, State>,
+    name: &str,
+    params: &[Value],
+    results: &mut [Value],
+) -> Result<(), Trap> {
+    // The parameters are checked against the type of the import.
+    let args = params
+        .iter()
+        .map(|param| param.i32().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let result = match (
+        caller.data().host,
+        caller.get_export("memory").and_then(Extern::into_memory),
+    ) {
+        (Some(host), Some(memory)) => {
+            let mut memory = CallerMemory {
+                caller: &mut caller,
+                memory,
+            };
+            // SAFETY: `host` is only set during a call to an export, which this call is
+            // nested in.
+            unsafe { &mut *host }.call(name, &args, &mut memory)
+        }
+        (None, _) => Err(HostError::UnknownFunction(name.to_string())),
+        (_, None) => Err(HostError::InvalidArgument(
+            "the plugin must export its memory".into(),
+        )),
+    };
+    match result {
+        Ok(value) => {
+            results[0] = Value::I32(value);
+            Ok(())
+        }
+        Err(error) => {
+            let trap = Trap::new(error.to_string());
+            caller.data_mut().error = Some(error);
+            Err(trap)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::ast::*;
+
+    use super::super::wasm::WasmPlugin;
+    use super::super::PluginError;
+    use super::super::PluginRegistry;
+    use super::super::API_VERSION;
+    use super::*;
+    use crate::test_util::numbers;
+
+    /// Instantiate the plugin written in `wat`, exporting `juno_run`.
+    fn instantiate(wat: &str, limits: &WasmLimits) -> Result<WasmPlugin, WasmError> {
+        let module = wat::parse_str(format!(
+            r#"(module
+                 (import "juno" "root" (func $root (result i32)))
+                 (import "juno" "remove" (func $remove (param i32) (result i32)))
+                 (import "juno" "list" (func $list (param i32 i32 i32 i32 i32) (result i32)))
+                 (memory (export "memory") 1)
+                 (data (i32.const 0) "body")
+                 (func (export "juno_api_version") (result i32)
+                   i32.const {})
+                 {})"#,
+            API_VERSION.major << 16 | API_VERSION.minor,
+            wat
+        ))
+        .unwrap();
+        WasmPlugin::new(&WasmiEngine, "test", &module, limits)
+    }
+
+    fn body_len(ctx: &mut Context, root: &NodeRc) -> usize {
+        let gc = GCLock::new(ctx);
+        match root.node(&gc) {
+            Node::Program(Program { body, .. }) => body.len(),
+            _ => panic!("expected a program"),
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        // Remove the first statement of the program.
+        let mut plugin = instantiate(
+            r#"(func (export "juno_run") (result i32)
+                 (drop (call $list (call $root) (i32.const 0) (i32.const 4)
+                                   (i32.const 16) (i32.const 1)))
+                 (drop (call $remove (i32.load (i32.const 16))))
+                 i32.const 0)"#,
+            &Default::default(),
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        let root = numbers(&mut ctx);
+        let root = plugin.run(&mut ctx, root).unwrap();
+        assert_eq!(body_len(&mut ctx, &root), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut ctx = Context::new();
+
+        // Errors of the host API are reported.
+        let mut plugin = instantiate(
+            r#"(func (export "juno_run") (result i32)
+                 (call $remove (call $root)))"#,
+            &Default::default(),
+        )
+        .unwrap();
+        let root = numbers(&mut ctx);
+        assert!(matches!(
+            plugin.run(&mut ctx, root),
+            Err(WasmError::Host(HostError::InvalidArgument(_)))
+        ));
+
+        // Infinite loops run out of fuel.
+        let mut plugin = instantiate(
+            r#"(func (export "juno_run") (result i32)
+                 (loop br 0)
+                 i32.const 0)"#,
+            &WasmLimits {
+                fuel: Some(10_000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let root = numbers(&mut ctx);
+        assert!(matches!(
+            plugin.run(&mut ctx, root),
+            Err(WasmError::Engine(_))
+        ));
+
+        // The memory can't grow past the limit.
+        let mut plugin = instantiate(
+            r#"(func (export "juno_run") (result i32)
+                 (memory.grow (i32.const 1)))"#,
+            &WasmLimits {
+                max_memory_pages: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let root = numbers(&mut ctx);
+        assert!(matches!(
+            plugin.run(&mut ctx, root),
+            Err(WasmError::Host(HostError::Failed(-1)))
+        ));
+
+        // Only the host API can be imported.
+        let module = wat::parse_str(r#"(module (import "env" "f" (func)))"#).unwrap();
+        assert!(matches!(
+            WasmPlugin::new(&WasmiEngine, "test", &module, &Default::default()),
+            Err(WasmError::Engine(_))
+        ));
+    }
+
+    #[test]
+    fn test_registry() {
+        // Remove the first statement of the program.
+        let plugin = instantiate(
+            r#"(func (export "juno_run") (result i32)
+                 (drop (call $list (call $root) (i32.const 0) (i32.const 4)
+                                   (i32.const 16) (i32.const 1)))
+                 (drop (call $remove (i32.load (i32.const 16))))
+                 i32.const 0)"#,
+            &Default::default(),
+        )
+        .unwrap();
+        let mut plugins = PluginRegistry::new();
+        plugins.register_wasm(plugin);
+        assert!(!plugins.is_empty());
+        let mut ctx = Context::new();
+        let root = numbers(&mut ctx);
+        let root = plugins.run_passes(&mut ctx, root).unwrap();
+        assert_eq!(body_len(&mut ctx, &root), 1);
+
+        assert!(matches!(
+            plugins.load_wasm(std::path::Path::new("missing.wasm"), &Default::default()),
+            Err(PluginError::Load { .. })
+        ));
+    }
+}
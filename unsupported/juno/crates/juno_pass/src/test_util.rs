@@ -0,0 +1,55 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Helpers shared by the unit tests of the passes.
+
+use juno::ast::builders;
+use juno::ast::Context;
+use juno::ast::GCLock;
+use juno::ast::Node;
+use juno::ast::NodeRc;
+use juno::gen_js;
+
+use crate::run_pass;
+use crate::Pass;
+
+/// Generate the JS of `root` on a single line.
+pub(crate) fn gen(ctx: &mut Context, root: &NodeRc) -> String {
+    let mut out = vec![];
+    gen_js::generate(
+        &mut out,
+        ctx,
+        root,
+        gen_js::Opt {
+            pretty: gen_js::Pretty::No,
+            ..gen_js::Opt::new()
+        },
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The script `1; 2;`.
+pub(crate) fn numbers(ctx: &mut Context) -> NodeRc {
+    let gc = GCLock::new(ctx);
+    let body = [1.0, 2.0].map(|value| builders::expr_stmt(&gc, builders::number(&gc, value)));
+    NodeRc::from_node(&gc, builders::program(&gc, body))
+}
+
+/// Run `pass` on the `Program` or `Module` built by `root` and return the generated JS.
+pub(crate) fn run_on<F>(pass: &mut dyn Pass, root: F) -> String
+where
+    F: for<'gc> FnOnce(&'gc GCLock) -> &'gc Node<'gc>,
+{
+    let mut ctx = Context::new();
+    let root = {
+        let gc = GCLock::new(&mut ctx);
+        NodeRc::from_node(&gc, root(&gc))
+    };
+    let root = run_pass(&mut ctx, pass, root);
+    gen(&mut ctx, &root)
+}
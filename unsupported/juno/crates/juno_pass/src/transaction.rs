@@ -0,0 +1,189 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Transactions over an AST, for tools which preview changes before applying them.
+//!
+//! Nodes are immutable, so a transformed tree never modifies the original one: the
+//! transaction keeps the root from before each step, and rolling back just returns one of
+//! them. The nodes allocated by the discarded steps become garbage, and are freed by the
+//! next collection.
+
+use juno::ast::Context;
+use juno::ast::GCLock;
+use juno::ast::Node;
+use juno::ast::NodeRc;
+use juno::ast::TransformResult;
+
+use crate::Pass;
+
+/// A sequence of changes to a tree, which can be committed or rolled back as a whole,
+/// or undone one step at a time.
+///
+/// The transaction holds its roots as [`NodeRc`], so it must be dropped (usually by
+/// committing or rolling it back) before the [`Context`] of the tree.
+pub struct Transaction {
+    /// The root before each change, starting with the original one.
+    history: Vec<NodeRc>,
+    /// The current root.
+    current: NodeRc,
+}
+
+impl Transaction {
+    /// Start a transaction on the tree rooted at `root`.
+    pub fn begin(root: NodeRc) -> Self {
+        Self {
+            history: vec![],
+            current: root,
+        }
+    }
+
+    /// The root with every change applied so far, e.g. to preview the result.
+    pub fn current(&self) -> &NodeRc {
+        &self.current
+    }
+
+    /// The root from before the transaction started.
+    pub fn original(&self) -> &NodeRc {
+        self.history.first().unwrap_or(&self.current)
+    }
+
+    /// Number of changes which have been applied and not undone.
+    pub fn num_changes(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Replace the current root with the result of `f`.
+    /// Returns whether the tree changed; unchanged results aren't recorded as a change.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `f` removes or expands the root.
+    pub fn apply<F>(&mut self, ctx: &mut Context, f: F) -> bool
+    where
+        F: for<'gc> FnOnce(&'gc GCLock, &'gc Node<'gc>) -> TransformResult<&'gc Node<'gc>>,
+    {
+        let gc = GCLock::new(ctx);
+        let new_root = match f(&gc, self.current.node(&gc)) {
+            TransformResult::Unchanged => return false,
+            TransformResult::Changed(new_node) => NodeRc::from_node(&gc, new_node),
+            TransformResult::Removed => panic!("Root node removed in a transaction"),
+            TransformResult::Expanded(..) => panic!("Root node expanded in a transaction"),
+        };
+        self.history
+            .push(std::mem::replace(&mut self.current, new_root));
+        true
+    }
+
+    /// Run `pass` on the current root, as one change.
+    pub fn apply_pass(&mut self, ctx: &mut Context, pass: &mut dyn Pass) -> bool {
+        self.apply(ctx, |gc, node| pass.run(gc, node))
+    }
+
+    /// Revert the last change which hasn't been undone yet.
+    /// Returns `false` if there was no change to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(prev) => {
+                self.current = prev;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// End the transaction, keeping every change, and return the new root.
+    /// The intermediate trees become garbage; this is a safepoint, so they are freed if the
+    /// [`juno::ast::GcPolicy`] of `ctx` calls for a collection.
+    pub fn commit(self, ctx: &mut Context) -> NodeRc {
+        let Transaction { history, current } = self;
+        drop(history);
+        ctx.collect_if_needed();
+        current
+    }
+
+    /// End the transaction, discarding every change, and return the original root.
+    /// The nodes allocated by the changes become garbage, like in [`Transaction::commit`].
+    pub fn rollback(mut self, ctx: &mut Context) -> NodeRc {
+        let original = if self.history.is_empty() {
+            self.current
+        } else {
+            self.history.swap_remove(0)
+        };
+        drop(self.history);
+        ctx.collect_if_needed();
+        original
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juno::ast::*;
+
+    use super::*;
+    use crate::add_negative::AddNegative;
+    use crate::PassManager;
+
+    /// Parse `x + -y` in textual AST format.
+    fn parse(ctx: &mut Context) -> NodeRc {
+        let gc = GCLock::new(ctx);
+        let node = parse_text(
+            &gc,
+            r#"BinaryExpression
+  operator: +
+  left: Identifier
+    name: "x"
+  right: UnaryExpression
+    operator: -
+    argument: Identifier
+      name: "y"
+    prefix: true
+"#,
+        )
+        .unwrap();
+        NodeRc::from_node(&gc, node)
+    }
+
+    fn operator(ctx: &mut Context, root: &NodeRc) -> &'static str {
+        let gc = GCLock::new(ctx);
+        match root.node(&gc) {
+            Node::BinaryExpression(e) => e.operator.as_str(),
+            _ => panic!("expected a BinaryExpression"),
+        }
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut ctx = Context::new();
+        let root = parse(&mut ctx);
+
+        let mut tx = Transaction::begin(root.clone());
+        assert!(tx.apply_pass(&mut ctx, &mut AddNegative::new()));
+        assert!(!tx.apply_pass(&mut ctx, &mut AddNegative::new()));
+        assert_eq!(tx.num_changes(), 1);
+        assert_eq!(operator(&mut ctx, tx.current()), "-");
+        assert!(tx.original() == &root);
+        let root = tx.rollback(&mut ctx);
+        assert_eq!(operator(&mut ctx, &root), "+");
+
+        let mut tx = Transaction::begin(root);
+        assert!(tx.apply_pass(&mut ctx, &mut AddNegative::new()));
+        assert!(tx.undo());
+        assert!(!tx.undo());
+        assert_eq!(operator(&mut ctx, tx.current()), "+");
+        assert!(tx.apply_pass(&mut ctx, &mut AddNegative::new()));
+        let root = tx.commit(&mut ctx);
+        assert_eq!(operator(&mut ctx, &root), "-");
+
+        let mut tx = Transaction::begin(parse(&mut ctx));
+        let mut manager = PassManager::new();
+        manager.add_pass(Box::new(AddNegative::new()));
+        manager.run_in_transaction(&mut ctx, &mut tx);
+        assert_eq!(tx.num_changes(), 1);
+        drop(tx);
+        drop(root);
+    }
+}
@@ -0,0 +1,116 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast;
+use juno::ast::NodeRc;
+use juno::gen_js;
+use juno::hparser;
+use juno_pass::constant_fold::ConstantFold;
+use juno_pass::constant_fold::DefineError;
+use juno_pass::run_pass;
+use juno_pass::PassManager;
+
+/// Generate the JS of `root` on a single line.
+fn gen(ctx: &mut ast::Context, root: &NodeRc) -> String {
+    let mut out = vec![];
+    gen_js::generate(
+        &mut out,
+        ctx,
+        root,
+        gen_js::Opt {
+            pretty: gen_js::Pretty::No,
+            ..gen_js::Opt::new()
+        },
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Check that running the pass with `defines`, then the standard passes, on the script
+/// `input` generates the same JS as `expected`.
+fn assert_fold(defines: &[(&str, &str)], input: &str, expected: &str) {
+    let mut ctx = ast::Context::new();
+    let mut pass = ConstantFold::new();
+    for (name, value) in defines {
+        pass.define_from_source(&mut ctx, name, value).unwrap();
+    }
+    let root = hparser::parse(&mut ctx, input).unwrap();
+    let root = run_pass(&mut ctx, &mut pass, root);
+    let root = PassManager::standard().run(&mut ctx, root);
+    let folded = gen(&mut ctx, &root);
+
+    let mut ctx = ast::Context::new();
+    let root = hparser::parse(&mut ctx, expected).unwrap();
+    assert_eq!(folded, gen(&mut ctx, &root));
+}
+
+#[test]
+fn expressions() {
+    assert_fold(
+        &[],
+        "f(1 + 2 * 3, 'a' + 'b', `x${1}`, -(2), [1, 2].length);",
+        "f(7, 'ab', 'x1', -2, 2);",
+    );
+    assert_fold(
+        &[],
+        "f(x + 1, undefined, NaN);",
+        "f(x + 1, undefined, NaN);",
+    );
+    // Arrays and objects are not folded, only the primitives they contain.
+    assert_fold(&[], "f([1 + 1], {a: 2 * 2});", "f([2], {a: 4});");
+}
+
+#[test]
+fn defines() {
+    let defines = [
+        ("__DEV__", "false"),
+        ("process.env", "{NODE_ENV: 'production'}"),
+    ];
+    assert_fold(
+        &defines,
+        "if (process.env.NODE_ENV !== 'production') check();
+        f(__DEV__ ? a : b, process.env.OTHER);",
+        "f(b, process.env.OTHER);",
+    );
+    // Names which are not read are left alone.
+    let unchanged = "var __DEV__ = 1; __DEV__ = 2; o.__DEV__; ({__DEV__: 1});
+        var {__DEV__: x} = o; delete __DEV__;";
+    assert_fold(&defines, unchanged, unchanged);
+}
+
+#[test]
+fn shadowed() {
+    let defines = [("__DEV__", "false")];
+    assert_fold(
+        &defines,
+        "f(undefined == null, __DEV__);",
+        "f(true, false);",
+    );
+    // Names declared in the program may not refer to the globals, so they are left alone.
+    for unchanged in [
+        "function f(undefined) { return undefined == null; }",
+        "let NaN = 1; f(NaN + 1);",
+        "function g(__DEV__) { return __DEV__ ? a : b; }",
+        "with (o) f(Infinity - 1);",
+    ] {
+        assert_fold(&defines, unchanged, unchanged);
+    }
+}
+
+#[test]
+fn invalid_defines() {
+    let mut ctx = ast::Context::new();
+    let mut pass = ConstantFold::new();
+    assert!(matches!(
+        pass.define_from_source(&mut ctx, "X", "f()"),
+        Err(DefineError::NotConstant)
+    ));
+    assert!(matches!(
+        pass.define_from_source(&mut ctx, "X", "1 +"),
+        Err(DefineError::Parse(_))
+    ));
+}
@@ -5,5 +5,6 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+mod constant_fold;
 #[macro_use]
 mod strip_flow;
@@ -9,9 +9,12 @@ use std::cell::Cell;
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::fmt::Formatter;
+use std::ops::Range;
 use std::ptr::null;
+use std::rc::Rc;
 
 use crate::HeapSize;
+use crate::NullTerminatedBuf;
 
 /// Type used to hold a string index internally.
 type NumIndex = u32;
@@ -28,7 +31,7 @@ pub struct AtomTable(UnsafeCell<Inner>);
 #[derive(Default)]
 struct Inner {
     /// Strings are added here and never removed or mutated.
-    strings: Vec<String>,
+    strings: Vec<AtomStr>,
     /// Maps from a reference inside [`Inner::strings`] to the index in [`Inner::strings`].
     /// Since strings are never removed or modified, the lifetime of the key
     /// is effectively static.
@@ -40,6 +43,28 @@ struct Inner {
     /// Since strings are never removed or modified, the lifetime of the key
     /// is effectively static.
     map_u16: HashMap<&'static [u16], NumIndex>,
+
+    /// Buffers which atoms added by [`AtomTable::atom_in_buffer`] point into.
+    buffers: Vec<Rc<NullTerminatedBuf>>,
+}
+
+/// Contents of an atom.
+#[derive(Clone)]
+enum AtomStr {
+    /// Copied into the table.
+    Owned(String),
+    /// Pointing into one of [`Inner::buffers`], which are kept alive by the table.
+    Borrowed(&'static str),
+}
+
+impl AtomStr {
+    #[inline]
+    fn as_str(&self) -> &str {
+        match self {
+            AtomStr::Owned(s) => s.as_str(),
+            AtomStr::Borrowed(s) => s,
+        }
+    }
 }
 
 impl HeapSize for Inner {
@@ -104,6 +129,20 @@ impl std::fmt::Debug for AtomU16 {
     }
 }
 
+impl Atom {
+    /// Return the position of this atom in its table, in insertion order.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl AtomU16 {
+    /// Return the position of this atom in its table, in insertion order.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// A special value reserved for the invalid atom.
 pub const INVALID_ATOM: Atom = Atom(NumIndex::MAX);
 
@@ -119,16 +158,21 @@ impl Inner {
 
     /// Perform the actual addition of the owned string.
     fn add(&mut self, owned: String) -> Atom {
+        self.add_str(AtomStr::Owned(owned))
+    }
+
+    /// Perform the actual addition of the string.
+    fn add_str(&mut self, value: AtomStr) -> Atom {
         // Remember the index of the new element.
         let index = self.strings.len();
         assert!(index < INVALID_ATOM.0 as usize, "More than 4GB atoms?");
 
-        // Obtain a reference to the existing string on the heap. That reference
-        // is valid while `self` is valid.
-        let key: *const str = owned.as_str();
+        // Obtain a reference to the existing string on the heap or in a buffer.
+        // That reference is valid while `self` is valid.
+        let key: *const str = value.as_str();
 
         // Push the new string.
-        self.strings.push(owned);
+        self.strings.push(value);
 
         self.map.insert(unsafe { &*key }, index as NumIndex);
         Atom(index as NumIndex)
@@ -195,6 +239,12 @@ impl AtomTable {
         Default::default()
     }
 
+    /// Remove every atom from the table.
+    /// All previously created atoms become invalid.
+    pub fn clear(&mut self) {
+        *self.0.get_mut() = Default::default();
+    }
+
     /// Add a string to the table and return its atom index. The same
     /// string always returns the same index.
     pub fn atom<V: Into<String> + AsRef<str>>(&self, value: V) -> Atom {
@@ -229,6 +279,49 @@ impl AtomTable {
         unsafe { &*self.0.get() }.try_str_u16(ident)
     }
 
+    /// Add the string at `range` of `buf` to the table without copying it, keeping `buf`
+    /// alive instead, and return its atom index. Returns `None` if the string isn't valid
+    /// UTF-8.
+    /// The same string always returns the same index, so if it is already in the table,
+    /// the existing copy is used.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `range` is out of bounds.
+    pub fn atom_in_buffer(&self, buf: &Rc<NullTerminatedBuf>, range: Range<usize>) -> Option<Atom> {
+        let value = std::str::from_utf8(&buf.as_bytes()[range]).ok()?;
+        let inner = unsafe { &mut *self.0.get() };
+        if let Some(index) = inner.map.get(value) {
+            return Some(Atom(*index));
+        }
+        if !inner.buffers.iter().any(|b| Rc::ptr_eq(b, buf)) {
+            inner.buffers.push(Rc::clone(buf));
+        }
+        // The buffer is immutable and kept alive as long as the table.
+        let value: &'static str = unsafe { &*(value as *const str) };
+        Some(inner.add_str(AtomStr::Borrowed(value)))
+    }
+
+    /// Return the number of atoms in the table.
+    pub fn num_atoms(&self) -> usize {
+        unsafe { &*self.0.get() }.strings.len()
+    }
+
+    /// Return the number of UTF-16 atoms in the table.
+    pub fn num_atoms_u16(&self) -> usize {
+        unsafe { &*self.0.get() }.strings_u16.len()
+    }
+
+    /// Return the atom at position `index`, if there is one.
+    pub fn atom_at(&self, index: usize) -> Option<Atom> {
+        (index < self.num_atoms()).then_some(Atom(index as NumIndex))
+    }
+
+    /// Return the UTF-16 atom at position `index`, if there is one.
+    pub fn atom_u16_at(&self, index: usize) -> Option<AtomU16> {
+        (index < self.num_atoms_u16()).then_some(AtomU16(index as NumIndex))
+    }
+
     /// Execute the callback in a context where this table is used for debug
     /// printing of atoms.
     pub fn in_debug_context<R, F: FnOnce() -> R>(&self, f: F) -> R {
@@ -254,6 +347,24 @@ impl AtomTable {
     }
 }
 
+impl Clone for AtomTable {
+    /// Copy the table. Atoms keep their indices, so they are valid in both tables.
+    fn clone(&self) -> Self {
+        let inner = unsafe { &*self.0.get() };
+        let mut result = Inner {
+            buffers: inner.buffers.clone(),
+            ..Default::default()
+        };
+        for s in &inner.strings {
+            result.add_str(s.clone());
+        }
+        for s in &inner.strings_u16 {
+            result.add_u16(s.clone());
+        }
+        AtomTable(UnsafeCell::new(result))
+    }
+}
+
 impl HeapSize for AtomTable {
     fn heap_size(&self) -> usize {
         unsafe { &*self.0.get() }.heap_size()
@@ -299,5 +410,29 @@ mod tests {
         assert_eq!(idtab.str(id_bar), "bar");
 
         assert_eq!(idtab.str(id_foo) as *const str, p_foo);
+
+        assert_eq!(idtab.num_atoms(), 2);
+        assert_eq!(idtab.atom_at(id_bar.index()), Some(id_bar));
+        assert_eq!(idtab.atom_at(2), None);
+    }
+
+    #[test]
+    fn test_atom_in_buffer() {
+        let idtab = AtomTable::new();
+        let id_foo = idtab.atom("foo");
+        let buf = Rc::new(NullTerminatedBuf::from_slice_copy(b"foo bar \xff"));
+
+        assert_eq!(idtab.atom_in_buffer(&buf, 0..3), Some(id_foo));
+        let id_bar = idtab.atom_in_buffer(&buf, 4..7).unwrap();
+        assert_eq!(idtab.str(id_bar).as_ptr(), buf.as_bytes()[4..].as_ptr());
+        assert_eq!(idtab.atom("bar"), id_bar);
+        assert_eq!(Rc::strong_count(&buf), 2);
+        assert_eq!(idtab.atom_in_buffer(&buf, 8..9), None);
+
+        let copy = idtab.clone();
+        drop(idtab);
+        assert_eq!(copy.str(id_bar), "bar");
+        drop(copy);
+        assert_eq!(Rc::strong_count(&buf), 1);
     }
 }
@@ -0,0 +1,187 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Persistent cache of analysis and transform results, stored on disk.
+//!
+//! Entries are keyed by a [`CacheKey`], the hash of the contents of a source and of a string
+//! describing the configuration which produced the results, e.g. the enabled passes and
+//! their options. A changed source or configuration gives a different key, so entries never
+//! need to be invalidated, and repeated builds can skip the sources which didn't change.
+//!
+//! Each entry has several sections, e.g. `deps` for the dependency list or `js` for the
+//! generated code, which are stored as separate files and can be produced independently.
+//! Sections are opaque bytes: lists of strings, like dependency lists, can be encoded with
+//! [`encode_strings`]. Results which refer to AST nodes, like semantic analysis, can't be
+//! cached directly and must be recomputed or converted to such a representation.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Version of the cache format, included in every key so entries written by a version
+/// with a different format are never read.
+const FORMAT_VERSION: &str = "1";
+
+/// Key of a cache entry: a 128-bit FNV-1a hash of the source and the configuration.
+/// The hash is stable across platforms and runs, unlike the standard library hashers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(u128);
+
+impl CacheKey {
+    /// Compute the key of the results for the source `content`, produced with `config`.
+    pub fn new(content: &[u8], config: &str) -> CacheKey {
+        let mut hash = Fnv128::new();
+        // Lengths separate the parts, so moving bytes between them changes the hash.
+        for part in [FORMAT_VERSION.as_bytes(), config.as_bytes(), content] {
+            hash.write(&(part.len() as u64).to_le_bytes());
+            hash.write(part);
+        }
+        CacheKey(hash.0)
+    }
+
+    /// The key as 32 hexadecimal digits.
+    pub fn to_hex(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+}
+
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Fnv128 {
+        Fnv128(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u128;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+/// Cache stored in a directory.
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Open the cache in the directory `dir`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Cache> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Cache {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Return the contents of `section` of the entry `key`, or `None` if it isn't cached.
+    pub fn get(&self, key: &CacheKey, section: &str) -> Option<Vec<u8>> {
+        fs::read(self.path(key, section)).ok()
+    }
+
+    /// Store `data` as `section` of the entry `key`, replacing any previous contents.
+    /// The file is written under a temporary name and renamed, so concurrent readers
+    /// never see a partial entry.
+    pub fn put(&self, key: &CacheKey, section: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(key, section);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let tmp = path.with_extension(format!("{}.tmp{}", section, std::process::id()));
+        fs::write(&tmp, data)?;
+        if let Err(e) = fs::rename(&tmp, &path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> io::Result<()> {
+        fs::remove_dir_all(&self.dir)?;
+        fs::create_dir_all(&self.dir)
+    }
+
+    /// Entries are spread over 256 subdirectories, named after the first byte of the key.
+    fn path(&self, key: &CacheKey, section: &str) -> PathBuf {
+        let hex = key.to_hex();
+        self.dir
+            .join(&hex[..2])
+            .join(format!("{}.{}", &hex[2..], section))
+    }
+}
+
+/// Encode `strings` as a cache section: each string followed by a NUL byte.
+/// Strings must not contain NUL.
+pub fn encode_strings<S: AsRef<str>>(strings: &[S]) -> Vec<u8> {
+    let mut result = vec![];
+    for s in strings {
+        debug_assert!(!s.as_ref().contains('\0'), "NUL in cached string");
+        result.extend_from_slice(s.as_ref().as_bytes());
+        result.push(0);
+    }
+    result
+}
+
+/// Decode a section encoded by [`encode_strings`].
+/// Returns `None` if `data` isn't a valid encoding, e.g. if the file was corrupted.
+pub fn decode_strings(data: &[u8]) -> Option<Vec<String>> {
+    if data.is_empty() {
+        return Some(vec![]);
+    }
+    let data = data.strip_suffix(&[0])?;
+    data.split(|b| *b == 0)
+        .map(|s| String::from_utf8(s.to_vec()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        let key = CacheKey::new(b"x = 1;", "optimize");
+        assert_eq!(key, CacheKey::new(b"x = 1;", "optimize"));
+        assert_ne!(key, CacheKey::new(b"x = 2;", "optimize"));
+        assert_ne!(key, CacheKey::new(b"x = 1;", ""));
+        // Bytes can't move between the configuration and the content.
+        assert_ne!(CacheKey::new(b"ab", "c"), CacheKey::new(b"b", "ca"));
+        assert_eq!(key.to_hex().len(), 32);
+    }
+
+    #[test]
+    fn test_strings() {
+        let strings = ["./a", "", "b"];
+        assert_eq!(decode_strings(&encode_strings(&strings)).unwrap(), strings);
+        assert!(decode_strings(&encode_strings::<&str>(&[]))
+            .unwrap()
+            .is_empty());
+        assert_eq!(decode_strings(b"a\0b"), None);
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("juno-cache-test-{}", std::process::id()));
+        let cache = Cache::open(&dir).unwrap();
+        let key = CacheKey::new(b"x = 1;", "");
+        assert_eq!(cache.get(&key, "js"), None);
+        cache.put(&key, "js", b"x=1;").unwrap();
+        cache.put(&key, "deps", &encode_strings(&["./a"])).unwrap();
+        assert_eq!(cache.get(&key, "js").unwrap(), b"x=1;");
+        assert_eq!(
+            decode_strings(&cache.get(&key, "deps").unwrap()).unwrap(),
+            ["./a"]
+        );
+        cache.clear().unwrap();
+        assert_eq!(cache.get(&key, "js"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,24 +8,52 @@
 use crate::HeapSize;
 
 /// Append-only deque which ensures the elements pushed into it never move.
-/// Allocates chunks in doubling capacities.
+/// Allocates chunks in growing capacities, as set by its [`ChunkConfig`].
 #[derive(Debug)]
 pub struct Deque<T> {
     storage: Vec<Vec<T>>,
 
     /// Capacity at which to allocate the next chunk.
-    /// Doubles every chunk until reaching [`MAX_CHUNK_CAPACITY`].
+    /// Grows every chunk until reaching the maximum capacity of `config`.
     next_chunk_capacity: usize,
+
+    /// How the chunks are sized.
+    config: ChunkConfig,
 }
 
-/// Minimum chunk capacity in the deque.
-/// May be made configurable in the future.
+/// Default minimum chunk capacity in the deque.
 const MIN_CHUNK_CAPACITY: usize = 1 << 10;
 
-/// Maximum chunk capacity in the deque.
-/// May be made configurable in the future.
+/// Default maximum chunk capacity in the deque.
 const MAX_CHUNK_CAPACITY: usize = MIN_CHUNK_CAPACITY * (1 << 10);
 
+/// Sizes of the chunks allocated by a [`Deque`].
+/// Out of range values are clamped by [`Deque::with_config`]:
+/// capacities are at least 1, `max_capacity` is at least `min_capacity`
+/// and `growth_factor` is at least 1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChunkConfig {
+    /// Capacity of the first chunk.
+    pub min_capacity: usize,
+
+    /// Capacity past which chunks stop growing.
+    /// Chunks allocated by [`Deque::reserve`] may be larger.
+    pub max_capacity: usize,
+
+    /// Factor by which the capacity grows from one chunk to the next.
+    pub growth_factor: usize,
+}
+
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        ChunkConfig {
+            min_capacity: MIN_CHUNK_CAPACITY,
+            max_capacity: MAX_CHUNK_CAPACITY,
+            growth_factor: 2,
+        }
+    }
+}
+
 impl<T> Default for Deque<T> {
     fn default() -> Self {
         Self::new()
@@ -34,14 +62,41 @@ impl<T> Default for Deque<T> {
 
 impl<T> Deque<T> {
     pub fn new() -> Self {
+        Self::with_config(Default::default())
+    }
+
+    /// Create a deque whose chunks are sized according to `config`,
+    /// after clamping it to valid values.
+    pub fn with_config(config: ChunkConfig) -> Self {
+        let min_capacity = config.min_capacity.max(1);
+        let config = ChunkConfig {
+            min_capacity,
+            max_capacity: config.max_capacity.max(min_capacity),
+            growth_factor: config.growth_factor.max(1),
+        };
         let mut result = Self {
             storage: Default::default(),
-            next_chunk_capacity: MIN_CHUNK_CAPACITY,
+            next_chunk_capacity: config.min_capacity,
+            config,
         };
         result.new_chunk();
         result
     }
 
+    /// Ensure that `additional` more elements can be pushed without allocating a chunk,
+    /// by allocating a chunk large enough for all of them if needed.
+    /// This avoids growing through many chunks when the number of elements is known.
+    pub fn reserve(&mut self, additional: usize) {
+        let chunk = self.storage.last().unwrap();
+        if chunk.capacity() - chunk.len() >= additional {
+            return;
+        }
+        if chunk.is_empty() {
+            self.storage.pop();
+        }
+        self.storage.push(Vec::with_capacity(additional));
+    }
+
     /// Append an element to the deque and return a reference to it.
     /// The element will not move after it is allocated.
     pub fn push(&mut self, val: T) -> &T {
@@ -81,14 +136,29 @@ impl<T> Deque<T> {
         self.storage.iter_mut().flatten()
     }
 
+    /// Remove every element from the deque.
+    /// Only the largest chunk is kept for reuse, so refilling the deque doesn't have to grow
+    /// from the minimum capacity again.
+    pub fn clear(&mut self) {
+        let largest = (0..self.storage.len())
+            .max_by_key(|&i| self.storage[i].capacity())
+            .unwrap();
+        let mut chunk = self.storage.swap_remove(largest);
+        chunk.clear();
+        self.storage.clear();
+        self.storage.push(chunk);
+    }
+
     /// Allocate a new chunk in the node storage.
     fn new_chunk(&mut self) {
         let capacity = self.next_chunk_capacity;
         self.storage.push(Vec::with_capacity(capacity));
 
-        // Double the capacity if there's room.
-        if capacity < MAX_CHUNK_CAPACITY {
-            self.next_chunk_capacity = capacity * 2;
+        // Grow the capacity if there's room.
+        if capacity < self.config.max_capacity {
+            self.next_chunk_capacity = capacity
+                .saturating_mul(self.config.growth_factor)
+                .min(self.config.max_capacity);
         }
     }
 }
@@ -130,4 +200,77 @@ mod tests {
         // Make sure nothing in the first chunk moved around.
         assert_eq!(unsafe { *ptr }, 1000);
     }
+
+    #[test]
+    fn clear() {
+        let mut d = Deque::<usize>::new();
+        for i in 0..MIN_CHUNK_CAPACITY * 4 {
+            d.push(i);
+        }
+        d.clear();
+        assert_eq!(d.len(), 0);
+        assert_eq!(d.storage.len(), 1);
+        assert_eq!(d.storage[0].capacity(), MIN_CHUNK_CAPACITY * 4);
+        for i in 0..MIN_CHUNK_CAPACITY * 4 {
+            d.push(i);
+        }
+        assert_eq!(d.storage.len(), 1);
+    }
+
+    #[test]
+    fn config() {
+        let mut d = Deque::<usize>::with_config(ChunkConfig {
+            min_capacity: 4,
+            max_capacity: 48,
+            growth_factor: 3,
+        });
+        for i in 0..196 {
+            d.push(i);
+        }
+        let capacities = d.storage.iter().map(Vec::capacity).collect::<Vec<_>>();
+        assert_eq!(capacities, [4, 12, 36, 48, 48, 48]);
+
+        let mut d = Deque::<usize>::new();
+        d.reserve(MIN_CHUNK_CAPACITY * 3);
+        for i in 0..MIN_CHUNK_CAPACITY * 3 {
+            d.push(i);
+        }
+        assert_eq!(d.storage.len(), 1);
+        d.push(0);
+        assert_eq!(d.storage.len(), 2);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let mut d = Deque::<usize>::with_config(ChunkConfig {
+            min_capacity: 0,
+            max_capacity: 8,
+            growth_factor: 0,
+        });
+        assert_eq!(
+            d.config,
+            ChunkConfig {
+                min_capacity: 1,
+                max_capacity: 8,
+                growth_factor: 1,
+            }
+        );
+        for i in 0..3 {
+            d.push(i);
+        }
+        let capacities = d.storage.iter().map(Vec::capacity).collect::<Vec<_>>();
+        assert_eq!(capacities, [1, 1, 1]);
+
+        let mut d = Deque::<usize>::with_config(ChunkConfig {
+            min_capacity: 16,
+            max_capacity: 4,
+            growth_factor: 2,
+        });
+        assert_eq!(d.config.max_capacity, 16);
+        for i in 0..20 {
+            d.push(i);
+        }
+        let capacities = d.storage.iter().map(Vec::capacity).collect::<Vec<_>>();
+        assert_eq!(capacities, [16, 16]);
+    }
 }
@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Validation of identifier names, following the `IdentifierName` grammar of ECMAScript,
+//! which is based on the ID_Start and ID_Continue properties of UAX #31.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use unicode_xid::UnicodeXID;
+
+use crate::keywords;
+use crate::keywords::EsVersion;
+
+/// ZERO WIDTH NON-JOINER, allowed after the first character of an identifier.
+const ZWNJ: char = '\u{200c}';
+/// ZERO WIDTH JOINER, allowed after the first character of an identifier.
+const ZWJ: char = '\u{200d}';
+
+/// Whether `c` can start an identifier.
+pub fn is_id_start(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphabetic() || c == '$' || c == '_'
+    } else {
+        c.is_xid_start()
+    }
+}
+
+/// Whether `c` can appear in an identifier after the first character.
+pub fn is_id_continue(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '$' || c == '_'
+    } else {
+        c == ZWNJ || c == ZWJ || c.is_xid_continue()
+    }
+}
+
+/// Whether `name` is an `IdentifierName` without escapes.
+/// Reserved words are identifier names, use [`is_valid_identifier`] to exclude them.
+pub fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if is_id_start(c) => chars.all(is_id_continue),
+        _ => false,
+    }
+}
+
+/// Decode the source text of an identifier name, which may contain `\uXXXX` and
+/// `\u{X...}` escapes.
+/// Returns `None` if `src` isn't a valid `IdentifierName`, which includes escapes
+/// of characters which aren't allowed in their position.
+pub fn decode_identifier_name(src: &str) -> Option<Cow<'_, str>> {
+    if !src.contains('\\') {
+        return if is_identifier_name(src) {
+            Some(Cow::Borrowed(src))
+        } else {
+            None
+        };
+    }
+    let mut result = String::with_capacity(src.len());
+    let mut rest = src;
+    while let Some(c) = rest.chars().next() {
+        let c = if c == '\\' {
+            let (c, len) = decode_escape(&rest[1..])?;
+            rest = &rest[1 + len..];
+            c
+        } else {
+            rest = &rest[c.len_utf8()..];
+            c
+        };
+        let valid = if result.is_empty() {
+            is_id_start(c)
+        } else {
+            is_id_continue(c)
+        };
+        if !valid {
+            return None;
+        }
+        result.push(c);
+    }
+    Some(Cow::Owned(result))
+}
+
+/// Decode the escape `uXXXX` or `u{X...}` at the start of `s`, which follows a backslash.
+/// Returns the character and the length of the escape.
+fn decode_escape(s: &str) -> Option<(char, usize)> {
+    let s = s.strip_prefix('u')?;
+    let (digits, len) = if let Some(braced) = s.strip_prefix('{') {
+        let end = braced.find('}')?;
+        (&braced[..end], end + 3)
+    } else {
+        (s.get(..4)?, 5)
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let cp = u32::from_str_radix(digits, 16).ok()?;
+    // Surrogates are rejected here, they can't be escaped in identifiers.
+    Some((char::from_u32(cp)?, len))
+}
+
+/// Whether the source text `src` of an identifier, which may contain escapes,
+/// can be used as an identifier reference or binding in `version`, in strict mode
+/// code if `strict`.
+/// Escaped reserved words are rejected too.
+pub fn is_valid_identifier(src: &str, version: EsVersion, strict: bool) -> bool {
+    match decode_identifier_name(src) {
+        Some(name) => !keywords::is_reserved_word(&name, version, strict),
+        None => false,
+    }
+}
+
+/// Escape the non-ASCII characters of the identifier name `name`, so it can be
+/// emitted in ASCII-only output.
+/// Characters outside the BMP use `\u{X...}` escapes, which require ES2015.
+pub fn escape_identifier_name(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+    let mut result = String::with_capacity(name.len() * 2);
+    for c in name.chars() {
+        if c.is_ascii() {
+            result.push(c);
+        } else if c <= '\u{ffff}' {
+            write!(result, "\\u{:04x}", c as u32).unwrap();
+        } else {
+            write!(result, "\\u{{{:x}}}", c as u32).unwrap();
+        }
+    }
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_name() {
+        assert!(is_identifier_name("foo"));
+        assert!(is_identifier_name("$_1"));
+        assert!(is_identifier_name("café"));
+        assert!(is_identifier_name("a\u{200c}b"));
+        assert!(is_identifier_name("if"));
+        assert!(!is_identifier_name(""));
+        assert!(!is_identifier_name("1a"));
+        assert!(!is_identifier_name("a-b"));
+        assert!(!is_identifier_name("\u{200c}a"));
+        assert!(!is_identifier_name("a\u{2603}"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_identifier_name("a\\u0062c").unwrap(), "abc");
+        assert_eq!(decode_identifier_name("\\u{1d49c}").unwrap(), "\u{1d49c}");
+        assert_eq!(decode_identifier_name("foo").unwrap(), "foo");
+        // Escapes can't produce characters which aren't allowed in their position.
+        assert!(decode_identifier_name("\\u0031a").is_none());
+        assert!(decode_identifier_name("a\\u002d").is_none());
+        assert!(decode_identifier_name("\\ud800").is_none());
+        assert!(decode_identifier_name("a\\u00").is_none());
+        assert!(decode_identifier_name("a\\u{}").is_none());
+        assert!(decode_identifier_name("a\\x41").is_none());
+    }
+
+    #[test]
+    fn test_reserved() {
+        assert!(is_valid_identifier("foo", EsVersion::ES5, true));
+        assert!(!is_valid_identifier("if", EsVersion::ES5, false));
+        assert!(!is_valid_identifier("\\u0069f", EsVersion::ES5, false));
+        assert!(!is_valid_identifier("int", EsVersion::ES3, false));
+        assert!(is_valid_identifier("int", EsVersion::ES5, false));
+        assert!(is_valid_identifier("let", EsVersion::ES2015, false));
+        assert!(!is_valid_identifier("let", EsVersion::ES2015, true));
+        assert!(is_valid_identifier("arguments", EsVersion::ES5, true));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_identifier_name("foo"), "foo");
+        assert_eq!(escape_identifier_name("café"), "caf\\u00e9");
+        assert_eq!(escape_identifier_name("a\u{1d49c}"), "a\\u{1d49c}");
+        assert_eq!(
+            decode_identifier_name(&escape_identifier_name("café\u{1d49c}")).unwrap(),
+            "café\u{1d49c}"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Classification of the keywords and reserved words of each edition of ECMAScript.
+
+/// Edition of ECMAScript, which determines the reserved words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EsVersion {
+    ES3,
+    ES5,
+    ES2015,
+    ES2017,
+}
+
+/// Kind of a word which can't always be used as an identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordKind {
+    /// Reserved keyword, e.g. `if`.
+    Keyword,
+
+    /// `null`, `true` or `false`, which are reserved words but not keywords.
+    Literal,
+
+    /// Reserved for future use, e.g. `enum`.
+    FutureReserved,
+
+    /// Reserved only in strict mode code, e.g. `implements`.
+    /// `let` and `yield` are also contextual keywords in non-strict code.
+    StrictReserved,
+
+    /// Identifier with a special meaning in some contexts, e.g. `async`.
+    Contextual,
+}
+
+/// Classify `word` in `version`.
+/// Returns `None` if `word` is an ordinary identifier.
+pub fn classify(word: &str, version: EsVersion) -> Option<WordKind> {
+    use EsVersion::*;
+    use WordKind::*;
+    let kind = match word {
+        "break" | "case" | "catch" | "continue" | "default" | "delete" | "do" | "else"
+        | "finally" | "for" | "function" | "if" | "in" | "instanceof" | "new" | "return"
+        | "switch" | "this" | "throw" | "try" | "typeof" | "var" | "void" | "while" | "with" => {
+            Keyword
+        }
+        "null" | "true" | "false" => Literal,
+        "debugger" if version >= ES5 => Keyword,
+        "class" | "const" | "export" | "extends" | "import" | "super" if version >= ES2015 => {
+            Keyword
+        }
+        "class" | "const" | "debugger" | "enum" | "export" | "extends" | "import" | "super" => {
+            FutureReserved
+        }
+        "implements" | "interface" | "package" | "private" | "protected" | "public" | "static" => {
+            if version >= ES5 {
+                StrictReserved
+            } else {
+                FutureReserved
+            }
+        }
+        "abstract" | "boolean" | "byte" | "char" | "double" | "final" | "float" | "goto"
+        | "int" | "long" | "native" | "short" | "synchronized" | "throws" | "transient"
+        | "volatile"
+            if version == ES3 =>
+        {
+            FutureReserved
+        }
+        "let" | "yield" if version >= ES5 => StrictReserved,
+        // `await` is only reserved in modules.
+        "await" if version >= ES2015 => Contextual,
+        "async" if version >= ES2017 => Contextual,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Whether `word` is a reserved word in `version`, in strict mode code if `strict`.
+/// `arguments` and `eval` aren't reserved words, even though strict mode code
+/// can't bind them.
+pub fn is_reserved_word(word: &str, version: EsVersion, strict: bool) -> bool {
+    match classify(word, version) {
+        Some(WordKind::Keyword) | Some(WordKind::Literal) | Some(WordKind::FutureReserved) => true,
+        Some(WordKind::StrictReserved) => strict,
+        Some(WordKind::Contextual) | None => false,
+    }
+}
+
+/// Whether `word` is a reserved word in module code, which is always strict,
+/// in `version`.
+pub fn is_reserved_in_module(word: &str, version: EsVersion) -> bool {
+    is_reserved_word(word, version, true) || (word == "await" && version >= EsVersion::ES2015)
+}
+
+/// Whether `word` is a keyword in some contexts in `version` and an identifier in others,
+/// e.g. `yield` is a keyword in generators.
+pub fn is_contextual_keyword(word: &str, version: EsVersion) -> bool {
+    match word {
+        "let" | "yield" | "await" => version >= EsVersion::ES2015,
+        "async" => version >= EsVersion::ES2017,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        use EsVersion::*;
+        use WordKind::*;
+        assert_eq!(classify("if", ES3), Some(Keyword));
+        assert_eq!(classify("null", ES2017), Some(Literal));
+        assert_eq!(classify("foo", ES2017), None);
+        assert_eq!(classify("debugger", ES3), Some(FutureReserved));
+        assert_eq!(classify("debugger", ES5), Some(Keyword));
+        assert_eq!(classify("class", ES5), Some(FutureReserved));
+        assert_eq!(classify("class", ES2015), Some(Keyword));
+        assert_eq!(classify("enum", ES2017), Some(FutureReserved));
+        assert_eq!(classify("public", ES3), Some(FutureReserved));
+        assert_eq!(classify("public", ES5), Some(StrictReserved));
+        assert_eq!(classify("int", ES3), Some(FutureReserved));
+        assert_eq!(classify("int", ES5), None);
+        assert_eq!(classify("let", ES3), None);
+        assert_eq!(classify("let", ES2015), Some(StrictReserved));
+        assert_eq!(classify("await", ES5), None);
+        assert_eq!(classify("await", ES2015), Some(Contextual));
+        assert_eq!(classify("async", ES2015), None);
+        assert_eq!(classify("async", ES2017), Some(Contextual));
+    }
+
+    #[test]
+    fn test_reserved() {
+        use EsVersion::*;
+        assert!(is_reserved_word("true", ES5, false));
+        assert!(!is_reserved_word("yield", ES2015, false));
+        assert!(is_reserved_word("yield", ES2015, true));
+        assert!(!is_reserved_word("await", ES2017, true));
+        assert!(is_reserved_in_module("await", ES2017));
+        assert!(is_reserved_in_module("static", ES2015));
+        assert!(!is_reserved_in_module("async", ES2017));
+
+        assert!(is_contextual_keyword("let", ES2015));
+        assert!(!is_contextual_keyword("let", ES5));
+        assert!(is_contextual_keyword("async", ES2017));
+        assert!(!is_contextual_keyword("static", ES2017));
+    }
+}
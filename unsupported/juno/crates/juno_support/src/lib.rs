@@ -11,12 +11,18 @@ mod nullbuf;
 pub use nullbuf::NullTerminatedBuf;
 mod timer;
 pub use timer::Timer;
+mod cancel;
+pub use cancel::CancellationToken;
 mod scoped_hashmap;
 pub use scoped_hashmap::ScopedHashMap;
 mod deque;
+pub use deque::ChunkConfig;
 pub use deque::Deque;
 mod heap_size;
 pub use heap_size::HeapSize;
+mod progress;
+pub use progress::Progress;
+pub use progress::ProgressEvent;
 
 #[macro_use]
 #[allow(unused_macros)]
@@ -27,8 +33,11 @@ pub mod str_enum;
 pub mod opaque_id;
 
 pub mod atom_table;
+pub mod cache;
 pub mod case;
 pub mod convert;
 pub mod fetchurl;
+pub mod ident;
 pub mod json;
+pub mod keywords;
 pub mod source_manager;
@@ -0,0 +1,39 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+/// A step of a pipeline, reported to a [`Progress`] sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent<'a> {
+    /// The `done`th of `total` files has been parsed.
+    FileParsed {
+        name: &'a str,
+        done: usize,
+        total: usize,
+    },
+
+    /// The `done`th of `total` passes of a pipeline has completed.
+    PassCompleted {
+        name: &'static str,
+        done: usize,
+        total: usize,
+    },
+
+    /// `count` more nodes have been processed.
+    NodesProcessed { count: usize },
+}
+
+/// Receives the progress of a pipeline, e.g. to display a progress bar.
+/// Closures taking a [`ProgressEvent`] can be used as sinks.
+pub trait Progress {
+    fn report(&mut self, event: ProgressEvent);
+}
+
+impl<F: FnMut(ProgressEvent)> Progress for F {
+    fn report(&mut self, event: ProgressEvent) {
+        self(event)
+    }
+}
@@ -9,6 +9,8 @@ use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+use sourcemap::SourceMap;
+
 use crate::HeapSize;
 use crate::NullTerminatedBuf;
 
@@ -36,6 +38,12 @@ impl SourceRange {
             end: start,
         }
     }
+
+    /// Whether `loc` is within the range.
+    pub fn contains(&self, loc: SourceLoc) -> bool {
+        let key = |loc: SourceLoc| (loc.line, loc.col);
+        key(self.start) <= key(loc) && key(loc) <= key(self.end)
+    }
 }
 
 /// Line and column of a file.
@@ -71,7 +79,7 @@ impl SourceId {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct Inner {
     num_errors: usize,
     num_warnings: usize,
@@ -84,9 +92,24 @@ struct Inner {
 pub struct SourceManager {
     sources: Vec<(String, Rc<NullTerminatedBuf>)>,
     filenames: HashMap<String, SourceId>,
+    /// Source maps from sources to their originals, for sources which were
+    /// themselves generated.
+    input_source_maps: HashMap<SourceId, SourceMap>,
     inner: UnsafeCell<Inner>,
 }
 
+impl Clone for SourceManager {
+    /// Copy the sources and the error counts. The source buffers themselves are shared.
+    fn clone(&self) -> Self {
+        SourceManager {
+            sources: self.sources.clone(),
+            filenames: self.filenames.clone(),
+            input_source_maps: self.input_source_maps.clone(),
+            inner: UnsafeCell::new(unsafe { &*self.inner.get() }.clone()),
+        }
+    }
+}
+
 impl SourceManager {
     pub fn new() -> SourceManager {
         Default::default()
@@ -125,6 +148,22 @@ impl SourceManager {
         Rc::clone(&self.sources[source_id.as_usize()].1)
     }
 
+    /// Register `map` as the source map from the source `source_id` to its original sources.
+    /// Source maps generated from the source are then composed with it.
+    pub fn set_input_source_map(&mut self, source_id: SourceId, map: SourceMap) {
+        self.input_source_maps.insert(source_id, map);
+    }
+
+    /// Obtain the input source map registered for `source_id`, if any.
+    pub fn input_source_map(&self, source_id: SourceId) -> Option<&SourceMap> {
+        self.input_source_maps.get(&source_id)
+    }
+
+    /// Whether any input source maps have been registered.
+    pub fn has_input_source_maps(&self) -> bool {
+        !self.input_source_maps.is_empty()
+    }
+
     /// Get the `SourceId` for a given file `name`.
     pub fn lookup_name<S: AsRef<str>>(&self, name: S) -> Option<SourceId> {
         self.filenames.get(name.as_ref()).copied()
@@ -196,6 +235,26 @@ impl SourceManager {
     }
 }
 
+/// Byte offsets of the start of each line of `buf`, with the line terminators recognized
+/// by the parser: LF, CR, CRLF, LS and PS.
+pub fn line_starts(buf: &[u8]) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut i = 0;
+    while i < buf.len() {
+        i += match buf[i..] {
+            [b'\r', b'\n', ..] => 2,
+            [b'\n', ..] | [b'\r', ..] => 1,
+            [0xE2, 0x80, 0xA8 | 0xA9, ..] => 3,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        starts.push(i);
+    }
+    starts
+}
+
 impl HeapSize for SourceManager {
     fn heap_size(&self) -> usize {
         self.sources.heap_size() + self.filenames.heap_size()
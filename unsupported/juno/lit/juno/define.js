@@ -0,0 +1,17 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-js -O --define=__DEV__=false \
+// RUN: --define="process.env.NODE_ENV='production'" %s \
+// RUN: | %FileCheck %s --match-full-lines
+
+if (process.env.NODE_ENV !== 'production') {
+  check();
+}
+f(__DEV__ ? 'dev' : 'prod' + 1);
+
+// CHECK: f('prod1');
@@ -0,0 +1,32 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: echo -ne '1;' | %juno --gen-report - | %FileCheck %s --match-full-lines
+
+// CHECK-LABEL: {
+// CHECK-NEXT:   "sources": [
+// CHECK-NEXT:     {
+// CHECK-NEXT:       "id": 0,
+// CHECK-NEXT:       "name": "-",
+// CHECK-NEXT:       "text": "1;"
+// CHECK-NEXT:     }
+// CHECK-NEXT:   ],
+// CHECK-NEXT:   "diagnostics": [],
+// CHECK-NEXT:   "ast": {
+// CHECK-NEXT:     "type": "Program",
+// CHECK-NEXT:     "body": [
+// CHECK-NEXT:       {
+// CHECK-NEXT:         "type": "ExpressionStatement",
+// CHECK-NEXT:         "expression": {
+// CHECK-NEXT:           "type": "NumericLiteral",
+// CHECK-NEXT:           "value": 1
+// CHECK-NEXT:         },
+// CHECK-NEXT:         "directive": null
+// CHECK-NEXT:       }
+// CHECK-NEXT:     ]
+// CHECK-NEXT:   }
+// CHECK-NEXT: }
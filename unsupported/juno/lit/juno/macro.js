@@ -0,0 +1,16 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-js --macro='invariant=$0 || fail($1)' --macro=debug= %s \
+// RUN: | %FileCheck %s --match-full-lines
+
+invariant(x, 'x is set');
+debug(x);
+y();
+
+// CHECK: x || fail('x is set');
+// CHECK-NEXT: y();
@@ -0,0 +1,21 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: printf '%%s\n' \
+// RUN:   '{"id": 1, "file": "a.js", "source": "x = 1;", "ops": ["gen-js"]}' \
+// RUN:   '{"id": 2, "file": "b.js", "source": "x;\\nyyyyyyyyyyyyyyyyyy;"}' \
+// RUN:   '{"id": 3, "file": "c.js", "source": "x = 1;\\ny = 2;\\nz = 3;\\nw = 4;\\nv = 5;\\nu = 6;"}' \
+// RUN:   '{"id": 4, "file": "d.js", "source": "x = [[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]];"}' \
+// RUN:   '{"id": 5, "file": "/dev/zero", "ops": ["gen-js"]}' \
+// RUN: | %juno --pipe --request-max-line=16 --request-max-source=40 --request-max-memory=1024 \
+// RUN: | %FileCheck %s --match-full-lines
+
+// CHECK: {"id":1,"js":"x = 1;\n","ok":true}
+// CHECK-NEXT: {"errors":[{"column":17,"limit":true,"line":2,"message":"Line exceeds the limit of 16 bytes"}],"id":2,"ok":false}
+// CHECK-NEXT: {"errors":[{"limit":true,"message":"Source of 41 bytes exceeds the limit of 40 bytes"}],"id":3,"ok":false}
+// CHECK-NEXT: {"errors":[{"limit":true,"message":"AST memory limit of 1024 bytes exceeded"}],"id":4,"ok":false}
+// CHECK-NEXT: {"errors":[{"limit":true,"message":"Source exceeds the limit of 40 bytes"}],"id":5,"ok":false}
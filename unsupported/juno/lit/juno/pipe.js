@@ -0,0 +1,20 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: printf '%%s\n' \
+// RUN:   '{"id": 1, "file": "a.js", "source": "x = 1 + 2;", "ops": ["gen-js"]}' \
+// RUN:   '{"id": 2, "file": "b.js", "source": "var x: number = 1;", "ops": ["strip-flow", "gen-js"]}' \
+// RUN:   '{"id": 3, "file": "c.js", "source": "x = ;", "ops": ["gen-js"]}' \
+// RUN:   '{"id": 4, "file": "d.js", "ops": ["minify"]}' \
+// RUN:   '{"id": 5, "file": "e.js", "source": "1;"}' \
+// RUN: | %juno --pipe --dialect=flow | %FileCheck %s --match-full-lines
+
+// CHECK: {"id":1,"js":"x = 1 + 2;\n","ok":true}
+// CHECK-NEXT: {"id":2,"js":"var x = 1;\n","ok":true}
+// CHECK-NEXT: {"errors":[{"column":{{[0-9]+}},"line":1,"message":"{{.*}}"}],"id":3,"ok":false}
+// CHECK-NEXT: {"errors":[{"message":"Unknown operation: \"minify\""}],"id":4,"ok":false}
+// CHECK-NEXT: {"id":5,"ok":true}
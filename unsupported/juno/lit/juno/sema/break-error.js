@@ -0,0 +1,34 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: (%juno %s --gen-sema 2>&1 || true) | %FileCheck %s --match-full-lines
+
+x: {
+  break;
+}
+// CHECK: {{.*}}:11:3: error: 'break' not within a loop or switch
+
+while (1) {
+  (function () {
+    break;
+  });
+}
+// CHECK-NEXT: {{.*}}:17:5: error: 'break' not within a loop or switch
+
+a: while (1) {
+  (function () {
+    break a;
+  });
+}
+// CHECK-NEXT: {{.*}}:24:11: error: label 'a' is not defined
+
+while (1) {
+  () => {
+    continue;
+  };
+}
+// CHECK-NEXT: {{.*}}:31:5: error: 'continue' not within a loop or switch
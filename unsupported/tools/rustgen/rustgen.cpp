@@ -462,6 +462,11 @@ static void genConvert() {
                fld.name == "exportKind") ||
               (cls.name == "AssignmentExpression" && fld.name == "operator"))
             llvh::outs() << "cvt_enum(";
+          else if (
+              (cls.name == "Identifier" || cls.name == "JSXIdentifier") &&
+              fld.name == "name")
+            // Identifier names may point into the source buffer.
+            llvh::outs() << "cvt.cvt_source_label(gc, nr.source_range.start, ";
           else
             llvh::outs() << "cvt.cvt_label" << (fld.optional ? "_opt" : "")
                          << "(gc, ";